@@ -101,6 +101,18 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets whether the client should trust the certificate of a server the first time it
+    /// connects to an endpoint. The certificate is pinned to the endpoint URL in the
+    /// `/pinned` folder, and is only trusted for that endpoint. Afterwards, connecting to the
+    /// endpoint fails with `BadCertificateUntrusted` if the server presents any other
+    /// certificate, until the pinned certificate is removed.
+    ///
+    /// This is disabled by default, and is intended for development only.
+    pub fn trust_server_certs_on_first_use(mut self, trust_on_first_use: bool) -> Self {
+        self.config.trust_server_certs_on_first_use = trust_on_first_use;
+        self
+    }
+
     /// Sets whether the client should verify server certificates. Regardless of this setting,
     /// server certificates are always checked to see if they are trusted and have a valid key
     /// length. In addition (if `verify_server_certs` is unset or is set to `true`) it will
@@ -295,6 +307,19 @@ impl ClientBuilder {
         self
     }
 
+    /// Read the operation limits of the server each time the session is activated,
+    /// so that they are available from [`crate::Session::operation_limits`] immediately.
+    ///
+    /// Defaults to `true`. If `false`, the limits are only read the first time
+    /// a chunked service such as [`crate::Session::read_chunked`] is called.
+    pub fn read_operation_limits_on_connect(
+        mut self,
+        read_operation_limits_on_connect: bool,
+    ) -> Self {
+        self.config.read_operation_limits_on_connect = read_operation_limits_on_connect;
+        self
+    }
+
     /// Session name - the default name to use for a new session
     pub fn session_name(mut self, session_name: impl Into<String>) -> Self {
         self.config.session_name = session_name.into();
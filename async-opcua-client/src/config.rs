@@ -237,6 +237,10 @@ pub struct ClientConfig {
     /// Auto trusts server certificates. For testing/samples only unless you're sure what you're
     /// doing.
     pub(crate) trust_server_certs: bool,
+    /// Trusts the first certificate presented by each endpoint, and rejects any other
+    /// certificate from that endpoint afterwards. For development only.
+    #[serde(default)]
+    pub(crate) trust_server_certs_on_first_use: bool,
     /// Verify server certificates. For testing/samples only unless you're sure what you're
     /// doing.
     pub(crate) verify_server_certs: bool,
@@ -295,6 +299,10 @@ pub struct ClientConfig {
     /// `transfer_subscriptions`, then attempting to recreate subscriptions if that fails.
     #[serde(default = "defaults::recreate_subscriptions")]
     pub(crate) recreate_subscriptions: bool,
+    /// Read the operation limits of the server each time the session is activated,
+    /// instead of the first time a chunked service call needs them.
+    #[serde(default = "defaults::read_operation_limits_on_connect")]
+    pub(crate) read_operation_limits_on_connect: bool,
     /// Session name
     pub(crate) session_name: String,
     /// Requested session timeout in milliseconds
@@ -564,6 +572,10 @@ mod defaults {
         true
     }
 
+    pub(super) fn read_operation_limits_on_connect() -> bool {
+        true
+    }
+
     pub(super) fn session_timeout() -> u32 {
         60_000
     }
@@ -586,6 +598,7 @@ impl ClientConfig {
             certificate_path: None,
             private_key_path: None,
             trust_server_certs: false,
+            trust_server_certs_on_first_use: false,
             verify_server_certs: defaults::verify_server_certs(),
             pki_dir,
             preferred_locales: Vec::new(),
@@ -604,6 +617,7 @@ impl ClientConfig {
             min_publish_interval: defaults::min_publish_interval(),
             performance: Performance::default(),
             recreate_subscriptions: defaults::recreate_subscriptions(),
+            read_operation_limits_on_connect: defaults::read_operation_limits_on_connect(),
             session_name: "Rust OPC UA Client".into(),
             session_timeout: defaults::session_timeout(),
         }
@@ -614,10 +628,10 @@ impl ClientConfig {
 mod tests {
     use std::{self, collections::BTreeMap, path::PathBuf};
 
-    use crate::ClientBuilder;
+    use crate::{Client, ClientBuilder};
     use opcua_core::config::Config;
     use opcua_crypto::SecurityPolicy;
-    use opcua_types::MessageSecurityMode;
+    use opcua_types::{EndpointDescription, MessageSecurityMode};
 
     use super::{ClientConfig, ClientEndpoint, ClientUserToken, ANONYMOUS_USER_TOKEN_ID};
 
@@ -780,4 +794,36 @@ mod tests {
             "User tokens contains the reserved \"ANONYMOUS\" id, Token ANONYMOUS failed to validate: User token has an empty name."
         );
     }
+
+    #[test]
+    fn find_matching_endpoint_prefers_exact_url() {
+        let endpoint = |url: &str| EndpointDescription {
+            endpoint_url: url.into(),
+            security_mode: MessageSecurityMode::None,
+            security_policy_uri: SecurityPolicy::None.to_uri().into(),
+            ..Default::default()
+        };
+        let endpoints = vec![
+            endpoint("opc.tcp://other-host:4856/"),
+            endpoint("opc.tcp://my-host:4855/"),
+        ];
+        let found = Client::find_matching_endpoint(
+            &endpoints,
+            "opc.tcp://MY-HOST:4855",
+            SecurityPolicy::None,
+            MessageSecurityMode::None,
+        )
+        .unwrap();
+        assert_eq!(found.endpoint_url.as_ref(), "opc.tcp://MY-HOST:4855/");
+
+        // Without an exact match, the host and port are ignored.
+        let found = Client::find_matching_endpoint(
+            &endpoints[..1],
+            "opc.tcp://my-host:4855",
+            SecurityPolicy::None,
+            MessageSecurityMode::None,
+        )
+        .unwrap();
+        assert_eq!(found.endpoint_url.as_ref(), "opc.tcp://my-host:4856/");
+    }
 }
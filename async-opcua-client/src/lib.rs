@@ -124,10 +124,12 @@ pub use builder::ClientBuilder;
 pub use config::{ClientConfig, ClientEndpoint, ClientUserToken, ANONYMOUS_USER_TOKEN_ID};
 pub use retry::{ExponentialBackoff, SessionRetryPolicy};
 pub use session::{
-    Client, DataChangeCallback, DefaultRetryPolicy, EventCallback, HistoryReadAction,
-    HistoryUpdateAction, MonitoredItem, OnSubscriptionNotification, RequestRetryPolicy, Session,
-    SessionActivity, SessionBuilder, SessionConnectMode, SessionEventLoop, SessionPollResult,
-    Subscription, SubscriptionActivity, SubscriptionCallbacks, UARequest,
+    Client, DataChangeCallback, DataChangeItem, DefaultRetryPolicy, EventCallback, EventItem,
+    HistoryReadAction, HistoryUpdateAction, MonitoredItem, NamespaceMetadata, NotificationStream,
+    OnSubscriptionNotification, OperationLimits, RegisteredNodeHandle, RequestRetryPolicy,
+    ServerObjectClient, Session, SessionActivity, SessionBuilder, SessionConnectMode,
+    SessionEventLoop, SessionPollResult, StreamCallback, Subscription, SubscriptionActivity,
+    SubscriptionCallbacks, SubscriptionStreams, UARequest,
 };
 pub use transport::AsyncSecureChannel;
 
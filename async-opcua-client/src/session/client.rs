@@ -2,7 +2,7 @@ use std::{str::FromStr, sync::Arc};
 
 use chrono::Duration;
 use tokio::{pin, select};
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 use crate::{
     transport::{
@@ -14,7 +14,7 @@ use crate::{
 use opcua_core::{
     comms::url::{
         hostname_from_url, is_opc_ua_binary_url, is_valid_opc_ua_url, server_url_from_endpoint_url,
-        url_matches_except_host, url_with_replaced_hostname,
+        url_matches, url_matches_except_host, url_with_replaced_hostname,
     },
     config::Config,
     sync::RwLock,
@@ -75,6 +75,12 @@ impl Client {
         // Clients may choose to auto trust servers to save some messing around with rejected certs
         certificate_store.set_trust_unknown_certs(config.trust_server_certs);
 
+        // Or trust each server the first time they connect, and pin its certificate
+        if config.trust_server_certs_on_first_use {
+            warn!("Client trusts server certificates on first use. You do not want to do this in production code.");
+        }
+        certificate_store.set_trust_on_first_use(config.trust_server_certs_on_first_use);
+
         // The session retry policy dictates how many times to retry if connection to the server goes down
         // and on what interval
 
@@ -609,13 +615,20 @@ impl Client {
             panic!("Cannot match against unknown security policy");
         }
 
-        let mut matching_endpoint = endpoints
-            .iter()
-            .find(|e| {
-                // Endpoint matches if the security mode, policy and url match
-                security_mode == e.security_mode
-                    && security_policy == SecurityPolicy::from_uri(e.security_policy_uri.as_ref())
-                    && url_matches_except_host(endpoint_url, e.endpoint_url.as_ref())
+        // Endpoint matches if the security mode, policy and url match
+        let candidates = endpoints.iter().filter(|e| {
+            security_mode == e.security_mode
+                && security_policy == SecurityPolicy::from_uri(e.security_policy_uri.as_ref())
+        });
+        // Prefer an endpoint with the exact url, the server may advertise the same
+        // endpoint under several host names.
+        let mut matching_endpoint = candidates
+            .clone()
+            .find(|e| url_matches(endpoint_url, e.endpoint_url.as_ref()))
+            .or_else(|| {
+                candidates
+                    .clone()
+                    .find(|e| url_matches_except_host(endpoint_url, e.endpoint_url.as_ref()))
             })
             .cloned()?;
 
@@ -101,6 +101,13 @@ impl SessionConnector {
             }
         };
 
+        if self.inner.read_operation_limits_on_connect {
+            self.inner.read_operation_limits().await;
+        } else {
+            // The limits may have changed, read them again the next time they are needed.
+            self.inner.operation_limits.store(None);
+        }
+
         if self.inner.recreate_subscriptions {
             self.inner.transfer_subscriptions_from_old_session().await;
         }
@@ -0,0 +1,74 @@
+use opcua_types::{
+    AttributeId, DataTypeDefinition, NodeId, ReadValueId, StatusCode, TimestampsToReturn, Variant,
+};
+
+use crate::Session;
+
+impl Session {
+    /// Read the `DataTypeDefinition` attribute of a data type node from the server.
+    ///
+    /// This always reads from the server, see [`Session::data_type_definition`] for a cached
+    /// version of this method.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DataTypeDefinition)` - The definition of the data type, either a
+    ///   [`StructureDefinition`](opcua_types::StructureDefinition) or an
+    ///   [`EnumDefinition`](opcua_types::EnumDefinition).
+    /// * `Err(StatusCode)` - Request failed, the attribute could not be read, or the node
+    ///   does not have a structure or enum definition. [Status code](StatusCode) is the reason for failure.
+    ///
+    pub async fn read_data_type_definition(
+        &self,
+        data_type: &NodeId,
+    ) -> Result<DataTypeDefinition, StatusCode> {
+        let value = self
+            .read(
+                &[ReadValueId::new(
+                    data_type.clone(),
+                    AttributeId::DataTypeDefinition,
+                )],
+                TimestampsToReturn::Neither,
+                0.0,
+            )
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(StatusCode::BadUnexpectedError)?;
+
+        if value.status().is_bad() {
+            return Err(value.status());
+        }
+        match value.value {
+            Some(Variant::ExtensionObject(o)) => DataTypeDefinition::from_extension_object(o),
+            Some(Variant::Empty) | None => Err(StatusCode::BadNoValue),
+            Some(_) => Err(StatusCode::BadTypeMismatch),
+        }
+    }
+
+    /// Get the `DataTypeDefinition` attribute of a data type node, reading it from the server
+    /// the first time it is requested for a given data type.
+    ///
+    /// The cache is kept for the lifetime of the session, call
+    /// [`Session::clear_data_type_definition_cache`] if the types on the server may have changed.
+    /// Failed reads are not cached.
+    pub async fn data_type_definition(
+        &self,
+        data_type: &NodeId,
+    ) -> Result<DataTypeDefinition, StatusCode> {
+        if let Some(def) = self.data_type_definitions.read().get(data_type) {
+            return Ok(def.clone());
+        }
+
+        let def = self.read_data_type_definition(data_type).await?;
+        self.data_type_definitions
+            .write()
+            .insert(data_type.clone(), def.clone());
+        Ok(def)
+    }
+
+    /// Clear the cache of data type definitions used by [`Session::data_type_definition`].
+    pub fn clear_data_type_definition_cache(&self) {
+        self.data_type_definitions.write().clear();
+    }
+}
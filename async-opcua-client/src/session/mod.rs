@@ -1,9 +1,13 @@
 mod client;
 mod connect;
 mod connection;
+mod data_type_definitions;
 mod event_loop;
+mod namespace_metadata;
+mod operation_limits;
 mod request_builder;
 mod retry;
+mod server_object;
 mod services;
 
 /// Information about the server endpoint, security policy, security mode and user identity that the session will
@@ -38,19 +42,23 @@ impl From<(EndpointDescription, IdentityToken)> for EndpointInfo {
     }
 }
 
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use arc_swap::ArcSwap;
+use arc_swap::{ArcSwap, ArcSwapOption};
 pub use client::Client;
 pub use connect::SessionConnectMode;
 pub use connection::SessionBuilder;
 pub use event_loop::{SessionActivity, SessionEventLoop, SessionPollResult};
+pub use namespace_metadata::NamespaceMetadata;
 use opcua_core::handle::AtomicHandle;
 use opcua_core::sync::{Mutex, RwLock};
+pub use operation_limits::OperationLimits;
 pub use request_builder::UARequest;
 pub use retry::{DefaultRetryPolicy, RequestRetryPolicy};
+pub use server_object::ServerObjectClient;
 pub use services::attributes::{
     HistoryRead, HistoryReadAction, HistoryUpdate, HistoryUpdateAction, Read, Write,
 };
@@ -60,14 +68,14 @@ pub use services::session::{ActivateSession, Cancel, CloseSession, CreateSession
 use services::subscriptions::state::SubscriptionState;
 use services::subscriptions::PublishLimits;
 pub use services::subscriptions::{
-    CreateMonitoredItems, CreateSubscription, DataChangeCallback, DeleteMonitoredItems,
-    DeleteSubscriptions, EventCallback, ModifyMonitoredItems, ModifySubscription, MonitoredItem,
-    OnSubscriptionNotification, Publish, Republish, SetMonitoringMode, SetPublishingMode,
-    SetTriggering, Subscription, SubscriptionActivity, SubscriptionCallbacks,
-    TransferSubscriptions,
+    CreateMonitoredItems, CreateSubscription, DataChangeCallback, DataChangeItem,
+    DeleteMonitoredItems, DeleteSubscriptions, EventCallback, EventItem, ModifyMonitoredItems,
+    ModifySubscription, MonitoredItem, NotificationStream, OnSubscriptionNotification, Publish,
+    Republish, SetMonitoringMode, SetPublishingMode, SetTriggering, StreamCallback, Subscription,
+    SubscriptionActivity, SubscriptionCallbacks, SubscriptionStreams, TransferSubscriptions,
 };
 pub use services::view::{
-    Browse, BrowseNext, RegisterNodes, TranslateBrowsePaths, UnregisterNodes,
+    Browse, BrowseNext, RegisterNodes, RegisteredNodeHandle, TranslateBrowsePaths, UnregisterNodes,
 };
 use tracing::{error, info};
 
@@ -109,8 +117,8 @@ pub(crate) use session_trace;
 
 use opcua_core::ResponseMessage;
 use opcua_types::{
-    ApplicationDescription, ContextOwned, DecodingOptions, EndpointDescription, Error, IntegerId,
-    NamespaceMap, NodeId, ReadValueId, RequestHeader, ResponseHeader, StatusCode,
+    ApplicationDescription, ContextOwned, DataTypeDefinition, DecodingOptions, EndpointDescription,
+    Error, IntegerId, NamespaceMap, NodeId, ReadValueId, RequestHeader, ResponseHeader, StatusCode,
     TimestampsToReturn, TypeLoader, UAString, VariableId, Variant,
 };
 
@@ -176,6 +184,7 @@ pub struct Session {
     pub(super) publish_timeout: Duration,
     pub(super) recreate_monitored_items_chunk: usize,
     pub(super) recreate_subscriptions: bool,
+    pub(super) read_operation_limits_on_connect: bool,
     pub(super) should_reconnect: AtomicBool,
     pub(super) session_timeout: f64,
     /// Reference to the subscription cache for the client.
@@ -184,6 +193,8 @@ pub struct Session {
     pub(super) publish_limits_watch_tx: tokio::sync::watch::Sender<PublishLimits>,
     pub(super) monitored_item_handle: AtomicHandle,
     pub(super) trigger_publish_tx: tokio::sync::watch::Sender<Instant>,
+    pub(super) operation_limits: ArcSwapOption<OperationLimits>,
+    pub(super) data_type_definitions: RwLock<HashMap<NodeId, DataTypeDefinition>>,
     decoding_options: DecodingOptions,
 }
 
@@ -217,6 +228,7 @@ impl Session {
             publish_timeout: config.publish_timeout,
             recreate_monitored_items_chunk: config.performance.recreate_monitored_items_chunk,
             recreate_subscriptions: config.recreate_subscriptions,
+            read_operation_limits_on_connect: config.read_operation_limits_on_connect,
             should_reconnect: AtomicBool::new(true),
             subscription_state: Mutex::new(SubscriptionState::new(
                 config.min_publish_interval,
@@ -226,6 +238,8 @@ impl Session {
             publish_limits_watch_rx,
             publish_limits_watch_tx,
             trigger_publish_tx,
+            operation_limits: ArcSwapOption::empty(),
+            data_type_definitions: RwLock::new(HashMap::new()),
             decoding_options,
         });
 
@@ -0,0 +1,202 @@
+use opcua_types::{
+    AccessRestrictionType, BrowseDescription, BrowseDirection, BrowseResultMask, DateTime, IdType,
+    NodeClass, NodeId, NumericRange, ObjectId, ReadValueId, ReferenceDescription, ReferenceTypeId,
+    RolePermissionType, StatusCode, TimestampsToReturn, TryFromVariant, Variant,
+};
+
+use crate::Session;
+
+use super::session_debug;
+
+/// Metadata about a namespace on the server, read from the `NamespaceMetadataType`
+/// objects below the `Server.Namespaces` object.
+///
+/// Servers are not required to expose all of these properties, any property
+/// that is missing or could not be read is left as `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NamespaceMetadata {
+    /// Node ID of the namespace metadata object.
+    pub node_id: NodeId,
+    /// Namespace URI. If the `NamespaceUri` property is missing,
+    /// this is the browse name of the namespace metadata object.
+    pub namespace_uri: String,
+    /// Namespace version.
+    pub namespace_version: Option<String>,
+    /// Time this namespace was published.
+    pub namespace_publication_date: Option<DateTime>,
+    /// Whether this namespace is a subset of the full namespace.
+    pub is_namespace_subset: Option<bool>,
+    /// List of ID types used by static nodes in this namespace.
+    pub static_node_id_types: Option<Vec<IdType>>,
+    /// List of ranges for numeric node IDs on static nodes in this namespace.
+    pub static_numeric_node_id_range: Option<Vec<NumericRange>>,
+    /// Pattern that applies to string node IDs on static nodes in this namespace.
+    pub static_string_node_id_pattern: Option<String>,
+    /// Default access restrictions on this namespace.
+    pub default_access_restrictions: Option<AccessRestrictionType>,
+    /// Default role permissions on this namespace.
+    pub default_role_permissions: Option<Vec<RolePermissionType>>,
+    /// Default user role permissions on this namespace.
+    pub default_user_role_permissions: Option<Vec<RolePermissionType>>,
+}
+
+impl NamespaceMetadata {
+    /// Set the property with the given browse name from the value read from the server.
+    /// Values of the wrong type are ignored.
+    fn set_property(&mut self, name: &str, value: Variant) {
+        fn get<T: TryFromVariant>(value: Variant) -> Option<T> {
+            T::try_from_variant(value).ok()
+        }
+
+        match name {
+            "NamespaceUri" => {
+                if let Some(uri) = get(value) {
+                    self.namespace_uri = uri;
+                }
+            }
+            "NamespaceVersion" => self.namespace_version = get(value),
+            "NamespacePublicationDate" => self.namespace_publication_date = get(value),
+            "IsNamespaceSubset" => self.is_namespace_subset = get(value),
+            "StaticNodeIdTypes" => {
+                self.static_node_id_types = get::<Vec<i32>>(value).map(|v| {
+                    v.into_iter()
+                        .filter_map(|t| IdType::try_from(t).ok())
+                        .collect()
+                })
+            }
+            "StaticNumericNodeIdRange" => {
+                self.static_numeric_node_id_range = get::<Vec<String>>(value)
+                    .map(|v| v.into_iter().filter_map(|r| r.parse().ok()).collect())
+            }
+            "StaticStringNodeIdPattern" => self.static_string_node_id_pattern = get(value),
+            "DefaultAccessRestrictions" => {
+                self.default_access_restrictions =
+                    get::<i16>(value).map(AccessRestrictionType::from_bits_truncate)
+            }
+            "DefaultRolePermissions" => self.default_role_permissions = get(value),
+            "DefaultUserRolePermissions" => self.default_user_role_permissions = get(value),
+            _ => (),
+        }
+    }
+}
+
+impl Session {
+    /// Browse the given nodes, following continuation points until all
+    /// references have been returned. Nodes that fail to browse return no references.
+    async fn browse_all(
+        &self,
+        nodes_to_browse: &[BrowseDescription],
+    ) -> Result<Vec<Vec<ReferenceDescription>>, StatusCode> {
+        let results = self.browse_chunked(nodes_to_browse, 0, None).await?;
+        let mut references = Vec::with_capacity(results.len());
+        for mut result in results {
+            let mut refs = result.references.take().unwrap_or_default();
+            let mut continuation_point = result.continuation_point;
+            while !continuation_point.is_null() {
+                let Some(next) = self
+                    .browse_next(false, &[continuation_point])
+                    .await?
+                    .into_iter()
+                    .next()
+                else {
+                    break;
+                };
+                refs.extend(next.references.unwrap_or_default());
+                continuation_point = next.continuation_point;
+            }
+            references.push(refs);
+        }
+        Ok(references)
+    }
+
+    /// Read the metadata of each namespace on the server, by browsing the
+    /// `Server.Namespaces` object and reading the properties of each
+    /// `NamespaceMetadataType` object below it.
+    ///
+    /// Properties that the server does not expose are left empty.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<NamespaceMetadata>)` - Metadata for each namespace object found on the server.
+    /// * `Err(StatusCode)` - Request failed, [Status code](StatusCode) is the reason for failure.
+    ///
+    pub async fn namespace_metadata(&self) -> Result<Vec<NamespaceMetadata>, StatusCode> {
+        let namespaces = self
+            .browse_all(&[BrowseDescription {
+                node_id: ObjectId::Server_Namespaces.into(),
+                browse_direction: BrowseDirection::Forward,
+                reference_type_id: ReferenceTypeId::HasComponent.into(),
+                include_subtypes: true,
+                node_class_mask: NodeClass::Object as u32,
+                result_mask: BrowseResultMask::BrowseName as u32,
+            }])
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+
+        let mut metadata: Vec<_> = namespaces
+            .into_iter()
+            .filter(|r| r.node_id.server_index == 0 && r.node_id.namespace_uri.is_null())
+            .map(|r| NamespaceMetadata {
+                node_id: r.node_id.node_id,
+                namespace_uri: r.browse_name.name.as_ref().to_owned(),
+                ..Default::default()
+            })
+            .collect();
+        if metadata.is_empty() {
+            return Ok(metadata);
+        }
+
+        let properties = self
+            .browse_all(
+                &metadata
+                    .iter()
+                    .map(|m| BrowseDescription {
+                        node_id: m.node_id.clone(),
+                        browse_direction: BrowseDirection::Forward,
+                        reference_type_id: ReferenceTypeId::HasProperty.into(),
+                        include_subtypes: true,
+                        node_class_mask: NodeClass::Variable as u32,
+                        result_mask: BrowseResultMask::BrowseName as u32,
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .await?;
+
+        // Index into `metadata`, and browse name, of each property to read.
+        let mut to_read = Vec::new();
+        let mut nodes_to_read = Vec::new();
+        for (idx, refs) in properties.into_iter().enumerate() {
+            for r in refs {
+                if r.browse_name.namespace_index != 0 || r.node_id.server_index != 0 {
+                    continue;
+                }
+                to_read.push((idx, r.browse_name.name));
+                nodes_to_read.push(ReadValueId::new_value(r.node_id.node_id));
+            }
+        }
+        if nodes_to_read.is_empty() {
+            return Ok(metadata);
+        }
+
+        let values = self
+            .read_chunked(&nodes_to_read, TimestampsToReturn::Neither, 0.0)
+            .await?;
+        for ((idx, name), value) in to_read.into_iter().zip(values) {
+            if value.status().is_bad() {
+                session_debug!(
+                    self,
+                    "Failed to read namespace metadata property {name}: {}",
+                    value.status()
+                );
+                continue;
+            }
+            if let Some(value) = value.value {
+                metadata[idx].set_property(name.as_ref(), value);
+            }
+        }
+
+        Ok(metadata)
+    }
+}
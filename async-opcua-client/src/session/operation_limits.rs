@@ -0,0 +1,274 @@
+use std::{future::Future, sync::Arc};
+
+use opcua_types::{
+    BrowseDescription, BrowseResult, DataValue, ReadValueId, StatusCode, TimestampsToReturn,
+    VariableId, Variant, ViewDescription, WriteValue,
+};
+
+use crate::Session;
+
+use super::session_debug;
+
+/// Operation limits advertised by the server in its `ServerCapabilities` object.
+///
+/// These are read from the server at most once each time the session is activated.
+/// A value of `0` means that the server does not advertise a limit for the given
+/// service, either because there is no limit, or because the limit could not be read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OperationLimits {
+    /// Maximum number of nodes per Read call.
+    pub max_nodes_per_read: u32,
+    /// Maximum number of nodes per history read call for data values.
+    pub max_nodes_per_history_read_data: u32,
+    /// Maximum number of nodes per history read call for events.
+    pub max_nodes_per_history_read_events: u32,
+    /// Maximum number of nodes per Write call.
+    pub max_nodes_per_write: u32,
+    /// Maximum number of nodes per history update call for data values.
+    pub max_nodes_per_history_update_data: u32,
+    /// Maximum number of nodes per history update call for events.
+    pub max_nodes_per_history_update_events: u32,
+    /// Maximum number of nodes per Call service call.
+    pub max_nodes_per_method_call: u32,
+    /// Maximum number of nodes per Browse call.
+    pub max_nodes_per_browse: u32,
+    /// Maximum number of nodes per RegisterNodes call.
+    pub max_nodes_per_register_nodes: u32,
+    /// Maximum number of nodes per translate browse paths to node IDs call.
+    pub max_nodes_per_translate_browse_paths_to_node_ids: u32,
+    /// Maximum number of nodes per AddNodes, AddReferences, DeleteNodes
+    /// or DeleteReferences call.
+    pub max_nodes_per_node_management: u32,
+    /// Maximum number of items per create/modify/delete monitored items call.
+    pub max_monitored_items_per_call: u32,
+}
+
+const OPERATION_LIMIT_NODES: [VariableId; 12] = [
+    VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerRead,
+    VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerHistoryReadData,
+    VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerHistoryReadEvents,
+    VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerWrite,
+    VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerHistoryUpdateData,
+    VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerHistoryUpdateEvents,
+    VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerMethodCall,
+    VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerBrowse,
+    VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerRegisterNodes,
+    VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerTranslateBrowsePathsToNodeIds,
+    VariableId::Server_ServerCapabilities_OperationLimits_MaxNodesPerNodeManagement,
+    VariableId::Server_ServerCapabilities_OperationLimits_MaxMonitoredItemsPerCall,
+];
+
+impl OperationLimits {
+    /// Create operation limits from the result of reading [`OPERATION_LIMIT_NODES`].
+    fn from_values(values: &[DataValue]) -> Self {
+        let get = |idx: usize| match values.get(idx).and_then(|v| v.value.as_ref()) {
+            Some(Variant::UInt32(v)) => *v,
+            _ => 0,
+        };
+        Self {
+            max_nodes_per_read: get(0),
+            max_nodes_per_history_read_data: get(1),
+            max_nodes_per_history_read_events: get(2),
+            max_nodes_per_write: get(3),
+            max_nodes_per_history_update_data: get(4),
+            max_nodes_per_history_update_events: get(5),
+            max_nodes_per_method_call: get(6),
+            max_nodes_per_browse: get(7),
+            max_nodes_per_register_nodes: get(8),
+            max_nodes_per_translate_browse_paths_to_node_ids: get(9),
+            max_nodes_per_node_management: get(10),
+            max_monitored_items_per_call: get(11),
+        }
+    }
+}
+
+/// Get the chunk size for a service given the limit advertised by the server.
+fn chunk_size(limit: u32) -> usize {
+    if limit == 0 {
+        usize::MAX
+    } else {
+        limit as usize
+    }
+}
+
+/// Make sure that the server returned one result per operation.
+fn check_results<T>(results: &[T], expected: usize) -> Result<(), StatusCode> {
+    if results.len() != expected {
+        tracing::error!(
+            "Server returned {} results for a request with {} operations",
+            results.len(),
+            expected
+        );
+        Err(StatusCode::BadUnexpectedError)
+    } else {
+        Ok(())
+    }
+}
+
+/// Call `service` with chunks of at most `limit` operations from `items`,
+/// returning the results of all the calls in order.
+async fn call_in_chunks<'a, T, R, Fut>(
+    items: &'a [T],
+    limit: usize,
+    service: impl Fn(&'a [T]) -> Fut,
+) -> Result<Vec<R>, StatusCode>
+where
+    Fut: Future<Output = Result<Vec<R>, StatusCode>>,
+{
+    if items.len() <= limit {
+        return service(items).await;
+    }
+
+    let mut results = Vec::with_capacity(items.len());
+    for chunk in items.chunks(limit) {
+        let res = service(chunk).await?;
+        check_results(&res, chunk.len())?;
+        results.extend(res);
+    }
+    Ok(results)
+}
+
+impl Session {
+    /// Get the operation limits of the server.
+    ///
+    /// If the client is configured to read the operation limits when the session is activated,
+    /// these are available once the session has connected. Otherwise they are read the first
+    /// time a chunked service such as [`Session::read_chunked`] is called. No limits are
+    /// returned before the limits have been read.
+    pub fn operation_limits(&self) -> OperationLimits {
+        self.operation_limits
+            .load()
+            .as_deref()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Get the operation limits of the server, reading them if they have not been read
+    /// since the session was activated.
+    async fn cached_operation_limits(&self) -> OperationLimits {
+        if let Some(limits) = &*self.operation_limits.load() {
+            return **limits;
+        }
+        self.read_operation_limits().await
+    }
+
+    /// Read the operation limits from the server and cache them on the session.
+    ///
+    /// If the limits cannot be read, no limits are assumed, and nothing is cached,
+    /// so the limits are read again the next time they are needed.
+    pub(super) async fn read_operation_limits(&self) -> OperationLimits {
+        let nodes_to_read = OPERATION_LIMIT_NODES
+            .into_iter()
+            .map(|v| ReadValueId::new_value(v.into()))
+            .collect::<Vec<_>>();
+        let read = |chunk| self.read(chunk, TimestampsToReturn::Neither, 0.0);
+
+        let values = match read(&nodes_to_read).await {
+            // The server does not allow reading all the limits at once, so read
+            // MaxNodesPerRead first, then read the rest in chunks.
+            Err(StatusCode::BadTooManyOperations) => match read(&nodes_to_read[..1]).await {
+                Ok(first) => {
+                    let limit = OperationLimits::from_values(&first).max_nodes_per_read;
+                    call_in_chunks(&nodes_to_read, chunk_size(limit), read).await
+                }
+                Err(e) => Err(e),
+            },
+            r => r,
+        };
+
+        match values {
+            Ok(values) => {
+                let limits = OperationLimits::from_values(&values);
+                session_debug!(self, "Server operation limits: {limits:?}");
+                self.operation_limits.store(Some(Arc::new(limits)));
+                limits
+            }
+            Err(e) => {
+                session_debug!(self, "Failed to read server operation limits: {e}");
+                self.operation_limits.store(None);
+                OperationLimits::default()
+            }
+        }
+    }
+
+    /// Reads the value of nodes like [`Session::read`], but splits the request into
+    /// multiple calls if it contains more nodes than the server's `MaxNodesPerRead`.
+    ///
+    /// The limits are read from the server once, see [`Session::operation_limits`].
+    /// Results are returned in the same order as `nodes_to_read`.
+    ///
+    /// # Arguments
+    ///
+    /// * `nodes_to_read` - A list of [`ReadValueId`] to be read by the server.
+    /// * `timestamps_to_return` - The [`TimestampsToReturn`] for each node, Both, Server, Source or None
+    /// * `max_age` - The maximum age of value to read in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<DataValue>)` - A list of [`DataValue`] corresponding to each read operation.
+    /// * `Err(StatusCode)` - One of the requests failed, [Status code](StatusCode) is the reason for failure.
+    ///
+    pub async fn read_chunked(
+        &self,
+        nodes_to_read: &[ReadValueId],
+        timestamps_to_return: TimestampsToReturn,
+        max_age: f64,
+    ) -> Result<Vec<DataValue>, StatusCode> {
+        let limit = chunk_size(self.cached_operation_limits().await.max_nodes_per_read);
+        call_in_chunks(nodes_to_read, limit, |chunk| {
+            self.read(chunk, timestamps_to_return, max_age)
+        })
+        .await
+    }
+
+    /// Writes values to nodes like [`Session::write`], but splits the request into
+    /// multiple calls if it contains more nodes than the server's `MaxNodesPerWrite`.
+    ///
+    /// Note that if one of the requests fails, earlier chunks may already have been written.
+    ///
+    /// # Arguments
+    ///
+    /// * `nodes_to_write` - A list of [`WriteValue`] to be sent to the server.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<StatusCode>)` - A list of [`StatusCode`] results corresponding to each write operation.
+    /// * `Err(StatusCode)` - One of the requests failed, [Status code](StatusCode) is the reason for failure.
+    ///
+    pub async fn write_chunked(
+        &self,
+        nodes_to_write: &[WriteValue],
+    ) -> Result<Vec<StatusCode>, StatusCode> {
+        let limit = chunk_size(self.cached_operation_limits().await.max_nodes_per_write);
+        call_in_chunks(nodes_to_write, limit, |chunk| self.write(chunk)).await
+    }
+
+    /// Discover the references to the specified nodes like [`Session::browse`], but splits
+    /// the request into multiple calls if it contains more nodes than the server's `MaxNodesPerBrowse`.
+    ///
+    /// Note that continuation points from earlier chunks are kept while later chunks are
+    /// requested, so this may run into the server's limit on browse continuation points.
+    ///
+    /// # Arguments
+    ///
+    /// * `nodes_to_browse` - A list of [`BrowseDescription`] describing nodes to browse.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<BrowseResult>)` - A list [`BrowseResult`] corresponding to each node to browse. A browse result
+    ///   may contain a continuation point, for use with `browse_next()`.
+    /// * `Err(StatusCode)` - One of the requests failed, [Status code](StatusCode) is the reason for failure.
+    ///
+    pub async fn browse_chunked(
+        &self,
+        nodes_to_browse: &[BrowseDescription],
+        max_references_per_node: u32,
+        view: Option<ViewDescription>,
+    ) -> Result<Vec<BrowseResult>, StatusCode> {
+        let limit = chunk_size(self.cached_operation_limits().await.max_nodes_per_browse);
+        call_in_chunks(nodes_to_browse, limit, |chunk| {
+            self.browse(chunk, max_references_per_node, view.clone())
+        })
+        .await
+    }
+}
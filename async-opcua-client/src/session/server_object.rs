@@ -0,0 +1,103 @@
+use opcua_types::{
+    DateTime, Error, NamespaceMap, ReadValueId, ServerState, ServerStatusDataType, StatusCode,
+    TimestampsToReturn, TryFromVariant, VariableId,
+};
+
+use crate::Session;
+
+/// Typed wrapper over the standard `Server` object on the server,
+/// created with [`Session::server_object`].
+///
+/// Each method performs a single service call and decodes the result,
+/// returning `BadTypeMismatch` if the server returned a value of the wrong type.
+pub struct ServerObjectClient<'a> {
+    session: &'a Session,
+}
+
+impl<'a> ServerObjectClient<'a> {
+    /// Create a new wrapper over the server object using the given session.
+    pub fn new(session: &'a Session) -> Self {
+        Self { session }
+    }
+
+    /// Get the session this wrapper uses.
+    pub fn session(&self) -> &'a Session {
+        self.session
+    }
+
+    /// Read the value of a single variable and convert it to `T`.
+    async fn read_value<T: TryFromVariant>(&self, id: VariableId) -> Result<T, StatusCode> {
+        let mut res = self
+            .session
+            .read(
+                &[ReadValueId::new_value(id.into())],
+                TimestampsToReturn::Neither,
+                0.0,
+            )
+            .await?;
+        if res.is_empty() {
+            return Err(StatusCode::BadUnexpectedError);
+        }
+        let value = res.remove(0);
+        if value.status().is_bad() {
+            return Err(value.status());
+        }
+        let Some(value) = value.value else {
+            return Err(StatusCode::BadNoValue);
+        };
+        T::try_from_variant(value).map_err(|e| e.status())
+    }
+
+    /// Read the `ServerStatus` variable.
+    pub async fn server_status(&self) -> Result<ServerStatusDataType, StatusCode> {
+        self.read_value(VariableId::Server_ServerStatus).await
+    }
+
+    /// Read the `ServerStatus.CurrentTime` variable.
+    pub async fn current_time(&self) -> Result<DateTime, StatusCode> {
+        self.read_value(VariableId::Server_ServerStatus_CurrentTime)
+            .await
+    }
+
+    /// Read the `ServerStatus.State` variable.
+    pub async fn state(&self) -> Result<ServerState, StatusCode> {
+        let state: i32 = self
+            .read_value(VariableId::Server_ServerStatus_State)
+            .await?;
+        ServerState::try_from(state).map_err(|_| StatusCode::BadTypeMismatch)
+    }
+
+    /// Read the `ServiceLevel` variable.
+    pub async fn service_level(&self) -> Result<u8, StatusCode> {
+        self.read_value(VariableId::Server_ServiceLevel).await
+    }
+
+    /// Read the `ServerArray` variable.
+    pub async fn server_array(&self) -> Result<Vec<String>, StatusCode> {
+        self.read_value(VariableId::Server_ServerArray).await
+    }
+
+    /// Read the `NamespaceArray` variable. This also updates the namespace
+    /// map stored on the session, see [`Session::read_namespace_array`].
+    pub async fn namespace_array(&self) -> Result<NamespaceMap, Error> {
+        self.session.read_namespace_array().await
+    }
+
+    /// Call the `GetMonitoredItems` method, returning the server handles
+    /// and client handles of the monitored items in the given subscription.
+    /// See [`Session::call_get_monitored_items`].
+    pub async fn get_monitored_items(
+        &self,
+        subscription_id: u32,
+    ) -> Result<(Vec<u32>, Vec<u32>), StatusCode> {
+        self.session.call_get_monitored_items(subscription_id).await
+    }
+}
+
+impl Session {
+    /// Create a typed wrapper over the standard `Server` object, with
+    /// convenience methods for reading its common properties.
+    pub fn server_object(&self) -> ServerObjectClient<'_> {
+        ServerObjectClient::new(self)
+    }
+}
@@ -10,8 +10,8 @@ use crate::{
 };
 use opcua_core::ResponseMessage;
 use opcua_types::{
-    CallMethodRequest, CallMethodResult, CallRequest, CallResponse, IntegerId, MethodId, NodeId,
-    ObjectId, StatusCode, TryFromVariant, Variant,
+    CallMethodRequest, CallMethodResult, CallRequest, CallResponse, FromVariants, IntegerId,
+    MethodId, NodeId, ObjectId, StatusCode, TryFromVariant, Variant,
 };
 
 #[derive(Debug, Clone)]
@@ -158,6 +158,57 @@ impl Session {
             .unwrap())
     }
 
+    /// Calls a single method on an object on the server, converting the output arguments
+    /// to the type `O`, typically a tuple.
+    ///
+    /// See OPC UA Part 4 - Services 5.11.2 for complete description of the service and error responses.
+    ///
+    /// # Arguments
+    ///
+    /// * `object_id` - The object the method is called on.
+    /// * `method_id` - The method to call.
+    /// * `inputs` - The input arguments, anything that can be converted into a `Vec<Variant>`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(O)` - The output arguments of the method call.
+    /// * `Err(StatusCode)` - Request failed, the method call returned a bad status, or the output
+    ///   arguments could not be converted to `O`. [Status code](StatusCode) is the reason for failure.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use opcua_client::Session;
+    /// # use opcua_types::{NodeId, Variant, StatusCode};
+    /// # async fn example(session: &Session) -> Result<(), StatusCode> {
+    /// let (sum, message): (i32, String) = session
+    ///     .call_typed(
+    ///         NodeId::new(2, "Object"),
+    ///         NodeId::new(2, "Add"),
+    ///         vec![Variant::from(1), Variant::from(2)],
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn call_typed<I: Into<Vec<Variant>>, O: FromVariants>(
+        &self,
+        object_id: impl Into<NodeId>,
+        method_id: impl Into<NodeId>,
+        inputs: I,
+    ) -> Result<O, StatusCode> {
+        let request: CallMethodRequest =
+            (object_id.into(), method_id.into(), Some(inputs.into())).into();
+        let result = self.call_one(request).await?;
+        if result.status_code.is_bad() {
+            return Err(result.status_code);
+        }
+        O::from_variants(result.output_arguments.unwrap_or_default()).map_err(|e| {
+            session_error!(self, "Failed to convert method output arguments: {e}");
+            e.status()
+        })
+    }
+
     /// Calls GetMonitoredItems via call_method(), putting a sane interface on the input / output.
     ///
     /// # Arguments
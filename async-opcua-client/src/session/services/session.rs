@@ -189,8 +189,9 @@ impl UARequest for CreateSession<'_> {
                     let application_uri = self.endpoint.server.application_uri.as_ref();
 
                     let certificate_store = trace_write_lock!(self.certificate_store);
-                    certificate_store.validate_or_reject_application_instance_cert(
+                    certificate_store.validate_pinned_cert(
                         &server_certificate,
+                        self.endpoint.endpoint_url.as_ref(),
                         security_policy,
                         Some(&hostname),
                         Some(application_uri),
@@ -3,16 +3,22 @@ pub use event_loop::SubscriptionActivity;
 
 mod service;
 pub(crate) mod state;
+mod stream;
 
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap, VecDeque},
     time::Duration,
 };
 
+use futures::future::BoxFuture;
 use opcua_types::{
     match_extension_object_owned, DataChangeNotification, DataValue, EventNotificationList,
     ExtensionObject, MonitoringMode, NotificationMessage, ReadValueId, StatusChangeNotification,
-    Variant,
+    StatusCode, Variant,
+};
+
+pub use stream::{
+    DataChangeItem, EventItem, NotificationStream, StreamCallback, SubscriptionStreams,
 };
 
 pub use service::{
@@ -53,6 +59,18 @@ pub trait OnSubscriptionNotification: Send + Sync {
     /// Called for each received event.
     #[allow(unused)]
     fn on_event(&mut self, event_fields: Option<Vec<Variant>>, item: &MonitoredItem) {}
+
+    /// Called when a notification message was skipped, and could not be recovered
+    /// because it is no longer available on the server.
+    #[allow(unused)]
+    fn on_message_lost(&mut self, sequence_number: u32) {}
+
+    /// Called after each notification message. If this returns a future, the session
+    /// waits for it before acknowledging the message and before completing the
+    /// publish request, which applies backpressure to the server.
+    fn wait_for_capacity(&self) -> Option<BoxFuture<'static, ()>> {
+        None
+    }
 }
 
 type StatusChangeCallbackFun = dyn FnMut(StatusChangeNotification) + Send + Sync;
@@ -269,10 +287,36 @@ pub struct Subscription {
     monitored_items: HashMap<u32, MonitoredItem>,
     /// A map of client handle to monitored item id
     client_handles: HashMap<u32, u32>,
+    /// Sequence number of the next expected notification message, once any message has been received.
+    next_sequence_number: Option<u32>,
+    /// Notification messages from the first skipped message onwards, in sequence number order.
+    /// Received messages are held here until all messages before them have been delivered.
+    pending_messages: VecDeque<PendingMessage>,
 
     callback: Box<dyn OnSubscriptionNotification>,
 }
 
+/// A notification message that has not been delivered yet, because an earlier message was skipped.
+enum PendingMessage {
+    /// A skipped message, and whether it is being republished.
+    Missing(u32, bool),
+    /// A message that was received or republished.
+    Received(NotificationMessage),
+}
+
+/// Largest gap in sequence numbers that the client attempts to recover with republish.
+/// Larger gaps are treated as the server having reset its sequence numbers.
+const MAX_SEQUENCE_NUMBER_GAP: u32 = 1000;
+
+/// Get the sequence number following `sequence_number`. Sequence numbers roll over to 1, not 0.
+fn next_sequence_number(sequence_number: u32) -> u32 {
+    if sequence_number == u32::MAX {
+        1
+    } else {
+        sequence_number + 1
+    }
+}
+
 impl Subscription {
     /// Creates a new subscription using the supplied parameters and the supplied data change callback.
     #[allow(clippy::too_many_arguments)]
@@ -296,6 +340,8 @@ impl Subscription {
             priority,
             monitored_items: HashMap::new(),
             client_handles: HashMap::new(),
+            next_sequence_number: None,
+            pending_messages: VecDeque::new(),
             callback: status_change_callback,
         }
     }
@@ -427,6 +473,112 @@ impl Subscription {
         }
     }
 
+    /// Handle a notification message received in a publish response, noting any skipped
+    /// messages. Messages following a skipped message are held until the skipped message is
+    /// republished or lost, so that notifications are delivered in sequence number order.
+    pub(crate) fn on_published(&mut self, notification: NotificationMessage) {
+        let sequence_number = notification.sequence_number;
+        // Keep-alive messages carry the sequence number of the next notification message.
+        let is_keep_alive = notification.notification_data.is_none();
+        if !is_keep_alive {
+            if let Some(idx) = self.missing_position(sequence_number) {
+                self.pending_messages[idx] = PendingMessage::Received(notification);
+                self.deliver_pending();
+                return;
+            }
+        }
+        let next = if is_keep_alive {
+            sequence_number
+        } else {
+            next_sequence_number(sequence_number)
+        };
+
+        if let Some(expected) = self.next_sequence_number {
+            let gap = sequence_number.wrapping_sub(expected);
+            if gap > u32::MAX / 2 {
+                // The message is older than the last one we received, so it has already been handled.
+                return;
+            }
+            if gap > MAX_SEQUENCE_NUMBER_GAP {
+                tracing::warn!(
+                    "Sequence number on subscription {} jumped from {} to {}, not attempting to recover skipped messages",
+                    self.subscription_id,
+                    expected,
+                    sequence_number
+                );
+                self.pending_messages
+                    .retain(|m| matches!(m, PendingMessage::Received(_)));
+            } else {
+                let mut missing = expected;
+                while missing != sequence_number {
+                    self.pending_messages
+                        .push_back(PendingMessage::Missing(missing, false));
+                    missing = next_sequence_number(missing);
+                }
+            }
+        }
+        self.next_sequence_number = Some(next);
+        if !is_keep_alive {
+            self.pending_messages
+                .push_back(PendingMessage::Received(notification));
+        }
+        self.deliver_pending();
+    }
+
+    /// Get the sequence numbers of skipped messages that are not yet being republished,
+    /// and mark them as being republished.
+    pub(crate) fn take_missing_sequence_numbers(&mut self) -> Vec<u32> {
+        self.pending_messages
+            .iter_mut()
+            .filter_map(|m| match m {
+                PendingMessage::Missing(sequence_number, requested) if !*requested => {
+                    *requested = true;
+                    Some(*sequence_number)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Handle a skipped notification message that was recovered with republish.
+    pub(crate) fn on_republished(&mut self, notification: NotificationMessage) {
+        // If the message was received in the meantime, it has already been handled.
+        if let Some(idx) = self.missing_position(notification.sequence_number) {
+            self.pending_messages[idx] = PendingMessage::Received(notification);
+            self.deliver_pending();
+        }
+    }
+
+    /// Handle a failed republish of a skipped notification message.
+    pub(crate) fn on_republish_failed(&mut self, sequence_number: u32, status: StatusCode) {
+        if let Some(idx) = self.missing_position(sequence_number) {
+            self.pending_messages.remove(idx);
+            if status == StatusCode::BadMessageNotAvailable {
+                self.callback.on_message_lost(sequence_number);
+            }
+            self.deliver_pending();
+        }
+    }
+
+    fn missing_position(&self, sequence_number: u32) -> Option<usize> {
+        self.pending_messages
+            .iter()
+            .position(|m| matches!(m, PendingMessage::Missing(s, _) if *s == sequence_number))
+    }
+
+    /// Deliver held messages, up to the first message that is still missing.
+    fn deliver_pending(&mut self) {
+        while let Some(message) = self.pending_messages.pop_front() {
+            match message {
+                PendingMessage::Received(notification) => self.on_notification(notification),
+                missing => {
+                    self.pending_messages.push_front(missing);
+                    break;
+                }
+            }
+        }
+    }
+
     pub(crate) fn on_notification(&mut self, notification: NotificationMessage) {
         let Some(notifications) = notification.notification_data else {
             return;
@@ -514,3 +666,135 @@ impl PublishLimits {
             * (self.min_publish_requests);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use opcua_types::{
+        DateTime, ExtensionObject, NotificationMessage, StatusChangeNotification, StatusCode,
+    };
+
+    use super::{OnSubscriptionNotification, Subscription};
+
+    #[derive(Debug, PartialEq)]
+    enum Delivered {
+        Message(u32),
+        Lost(u32),
+    }
+
+    struct Recorder(Arc<Mutex<Vec<Delivered>>>);
+
+    impl OnSubscriptionNotification for Recorder {
+        fn on_subscription_status_change(&mut self, notification: StatusChangeNotification) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(Delivered::Message(notification.status.bits()));
+        }
+
+        fn on_message_lost(&mut self, sequence_number: u32) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(Delivered::Lost(sequence_number));
+        }
+    }
+
+    /// A notification message carrying its sequence number as a status change,
+    /// so that the order of delivery can be observed.
+    fn data_message(sequence_number: u32) -> NotificationMessage {
+        NotificationMessage {
+            sequence_number,
+            publish_time: DateTime::now(),
+            notification_data: Some(vec![ExtensionObject::from_message(
+                StatusChangeNotification {
+                    status: StatusCode::from(sequence_number),
+                    ..Default::default()
+                },
+            )]),
+        }
+    }
+
+    #[test]
+    fn track_sequence_numbers() {
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let mut sub = Subscription::new(
+            1,
+            Duration::from_millis(100),
+            100,
+            10,
+            0,
+            0,
+            true,
+            Box::new(Recorder(delivered.clone())),
+        );
+        let take = || std::mem::take(&mut *delivered.lock().unwrap());
+
+        sub.on_published(data_message(1));
+        sub.on_published(data_message(2));
+        assert_eq!(take(), vec![Delivered::Message(1), Delivered::Message(2)]);
+        assert!(sub.take_missing_sequence_numbers().is_empty());
+
+        // Skipping ahead marks the skipped messages as missing, once,
+        // and holds the new message until they are handled.
+        sub.on_published(data_message(5));
+        assert_eq!(sub.take_missing_sequence_numbers(), vec![3, 4]);
+        assert!(sub.take_missing_sequence_numbers().is_empty());
+        assert!(take().is_empty());
+
+        // A missing message that arrives late is delivered, but only once.
+        sub.on_published(data_message(3));
+        sub.on_published(data_message(3));
+        sub.on_published(data_message(5));
+        sub.on_republished(data_message(3));
+        assert_eq!(take(), vec![Delivered::Message(3)]);
+        sub.on_republish_failed(4, StatusCode::BadMessageNotAvailable);
+        assert_eq!(take(), vec![Delivered::Lost(4), Delivered::Message(5)]);
+        assert!(sub.pending_messages.is_empty());
+
+        // Republished messages are delivered in order, whatever order they arrive in.
+        sub.on_published(data_message(9));
+        assert_eq!(sub.take_missing_sequence_numbers(), vec![6, 7, 8]);
+        sub.on_republished(data_message(8));
+        sub.on_republished(data_message(6));
+        assert_eq!(take(), vec![Delivered::Message(6)]);
+        sub.on_republished(data_message(7));
+        assert_eq!(
+            take(),
+            vec![
+                Delivered::Message(7),
+                Delivered::Message(8),
+                Delivered::Message(9)
+            ]
+        );
+
+        // Keep-alive messages contain the next sequence number.
+        sub.on_published(NotificationMessage::keep_alive(10, DateTime::now()));
+        assert!(sub.take_missing_sequence_numbers().is_empty());
+        sub.on_published(NotificationMessage::keep_alive(12, DateTime::now()));
+        assert_eq!(sub.take_missing_sequence_numbers(), vec![10, 11]);
+        sub.pending_messages.clear();
+
+        // Sequence numbers roll over to 1.
+        sub.next_sequence_number = Some(u32::MAX - 1);
+        sub.on_published(data_message(1));
+        assert_eq!(
+            sub.take_missing_sequence_numbers(),
+            vec![u32::MAX - 1, u32::MAX]
+        );
+        assert_eq!(sub.next_sequence_number, Some(2));
+        assert!(take().is_empty());
+
+        // A large jump gives up on the skipped messages, and delivers held messages.
+        sub.on_published(data_message(5000));
+        assert_eq!(
+            take(),
+            vec![Delivered::Message(1), Delivered::Message(5000)]
+        );
+        assert!(sub.pending_messages.is_empty());
+    }
+}
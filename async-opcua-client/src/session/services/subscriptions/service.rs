@@ -2070,8 +2070,24 @@ impl Session {
             .await
         {
             Ok(r) => {
-                let mut subscription_state = trace_lock!(self.subscription_state);
-                subscription_state.handle_notification(r.subscription_id, r.notification_message);
+                let sequence_number = r.notification_message.sequence_number;
+                let (wait, missing) = {
+                    let mut subscription_state = trace_lock!(self.subscription_state);
+                    let wait = subscription_state
+                        .handle_notification(r.subscription_id, r.notification_message);
+                    (wait, subscription_state.take_missing_sequence_numbers())
+                };
+                for (subscription_id, sequence_number) in missing {
+                    self.recover_notification(subscription_id, sequence_number)
+                        .await;
+                }
+                // If the subscription is not ready for more notifications, hold on to
+                // the acknowledgement, and the publish request, until it is.
+                if let Some(wait) = wait {
+                    wait.await;
+                    let mut subscription_state = trace_lock!(self.subscription_state);
+                    subscription_state.add_acknowledgement(r.subscription_id, sequence_number);
+                }
                 Ok(r.more_notifications)
             }
             Err(e) => {
@@ -2084,6 +2100,33 @@ impl Session {
         }
     }
 
+    /// Republish a notification message that was skipped, and deliver it to the subscription.
+    async fn recover_notification(&self, subscription_id: u32, sequence_number: u32) {
+        session_debug!(
+            self,
+            "Republishing skipped notification {} on subscription {}",
+            sequence_number,
+            subscription_id
+        );
+        match self.republish(subscription_id, sequence_number).await {
+            Ok(notification) => {
+                let mut subscription_state = trace_lock!(self.subscription_state);
+                subscription_state.handle_republished(subscription_id, notification);
+            }
+            Err(e) => {
+                session_warn!(
+                    self,
+                    "Failed to republish skipped notification {} on subscription {}: {}",
+                    sequence_number,
+                    subscription_id,
+                    e
+                );
+                let mut subscription_state = trace_lock!(self.subscription_state);
+                subscription_state.handle_republish_failed(subscription_id, sequence_number, e);
+            }
+        }
+    }
+
     /// Send a request to re-publish an unacknowledged notification message from the server.
     ///
     /// If this succeeds, the session will automatically acknowledge the notification in the next publish request.
@@ -3,7 +3,8 @@ use std::{
     time::{Duration, Instant},
 };
 
-use opcua_types::{MonitoringMode, NotificationMessage, SubscriptionAcknowledgement};
+use futures::future::BoxFuture;
+use opcua_types::{MonitoringMode, NotificationMessage, StatusCode, SubscriptionAcknowledgement};
 
 use super::{CreateMonitoredItem, ModifyMonitoredItem, PublishLimits, Subscription};
 
@@ -208,20 +209,62 @@ impl SubscriptionState {
         }
     }
 
+    /// Handle a notification message. If this returns a future, the subscription is
+    /// not ready for more notifications, and the caller is responsible for acknowledging
+    /// the message once the future completes.
     pub(crate) fn handle_notification(
         &mut self,
         subscription_id: u32,
         notification: NotificationMessage,
-    ) {
-        self.add_acknowledgement(subscription_id, notification.sequence_number);
+    ) -> Option<BoxFuture<'static, ()>> {
+        let sequence_number = notification.sequence_number;
         if let Some(sub) = self.subscriptions.get_mut(&subscription_id) {
-            sub.on_notification(notification);
+            sub.on_published(notification);
+            if let Some(wait) = sub.callback.wait_for_capacity() {
+                return Some(wait);
+            }
         } else {
             tracing::warn!(
                 "Received notification for unknown subscription {}",
                 subscription_id
             );
         }
+        self.add_acknowledgement(subscription_id, sequence_number);
+        None
+    }
+
+    /// Get the subscription ID and sequence number of each skipped notification message
+    /// that should be republished.
+    pub(crate) fn take_missing_sequence_numbers(&mut self) -> Vec<(u32, u32)> {
+        self.subscriptions
+            .iter_mut()
+            .flat_map(|(id, sub)| {
+                sub.take_missing_sequence_numbers()
+                    .into_iter()
+                    .map(|seq| (*id, seq))
+            })
+            .collect()
+    }
+
+    pub(crate) fn handle_republished(
+        &mut self,
+        subscription_id: u32,
+        notification: NotificationMessage,
+    ) {
+        if let Some(sub) = self.subscriptions.get_mut(&subscription_id) {
+            sub.on_republished(notification);
+        }
+    }
+
+    pub(crate) fn handle_republish_failed(
+        &mut self,
+        subscription_id: u32,
+        sequence_number: u32,
+        status: StatusCode,
+    ) {
+        if let Some(sub) = self.subscriptions.get_mut(&subscription_id) {
+            sub.on_republish_failed(sequence_number, status);
+        }
     }
 
     fn set_keep_alive_timeout(&mut self) {
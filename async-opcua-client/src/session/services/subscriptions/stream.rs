@@ -0,0 +1,203 @@
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use futures::{future::BoxFuture, FutureExt, Stream};
+use opcua_types::{DataValue, Variant};
+use tokio::sync::{
+    mpsc::{UnboundedReceiver, UnboundedSender},
+    Notify,
+};
+
+use super::{MonitoredItem, OnSubscriptionNotification};
+
+/// A data change notification delivered through a [`NotificationStream`],
+/// the ID of the monitored item and its new value.
+pub type DataChangeItem = (u32, DataValue);
+
+/// An event notification delivered through a [`NotificationStream`],
+/// the ID of the monitored item and the selected event fields.
+pub type EventItem = (u32, Option<Vec<Variant>>);
+
+struct BufferState {
+    len: AtomicUsize,
+    capacity: usize,
+    space: Notify,
+}
+
+struct BufferSender<T> {
+    tx: UnboundedSender<T>,
+    state: Arc<BufferState>,
+}
+
+impl<T: Send + 'static> BufferSender<T> {
+    fn send(&self, value: T) {
+        self.state.len.fetch_add(1, Ordering::AcqRel);
+        if self.tx.send(value).is_err() {
+            self.state.len.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        !self.tx.is_closed() && self.state.len.load(Ordering::Acquire) >= self.state.capacity
+    }
+
+    fn wait_for_space(&self) -> BoxFuture<'static, ()> {
+        let tx = self.tx.clone();
+        let state = self.state.clone();
+        async move {
+            loop {
+                // Register for notifications before checking, so a message
+                // received in between is not missed.
+                let notified = state.space.notified();
+                if tx.is_closed() || state.len.load(Ordering::Acquire) < state.capacity {
+                    return;
+                }
+                notified.await;
+            }
+        }
+        .boxed()
+    }
+}
+
+fn buffer<T>(capacity: usize) -> (BufferSender<T>, NotificationStream<T>) {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let state = Arc::new(BufferState {
+        len: AtomicUsize::new(0),
+        capacity: capacity.max(1),
+        space: Notify::new(),
+    });
+    (
+        BufferSender {
+            tx,
+            state: state.clone(),
+        },
+        NotificationStream { rx, state },
+    )
+}
+
+/// A stream of notifications from a subscription, created by [`StreamCallback`].
+///
+/// The stream ends once the subscription is deleted.
+pub struct NotificationStream<T> {
+    rx: UnboundedReceiver<T>,
+    state: Arc<BufferState>,
+}
+
+impl<T> Stream for NotificationStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let res = self.rx.poll_recv(cx);
+        if let Poll::Ready(Some(_)) = &res {
+            self.state.len.fetch_sub(1, Ordering::AcqRel);
+            self.state.space.notify_waiters();
+        }
+        res
+    }
+}
+
+impl<T> Drop for NotificationStream<T> {
+    fn drop(&mut self) {
+        // Close the channel first, so that waiting senders see that the stream is gone.
+        self.rx.close();
+        self.state.space.notify_waiters();
+    }
+}
+
+/// The streams created together with a [`StreamCallback`].
+pub struct SubscriptionStreams {
+    data_changes: NotificationStream<DataChangeItem>,
+    events: NotificationStream<EventItem>,
+}
+
+impl SubscriptionStreams {
+    /// Get the stream of data change notifications, discarding the event stream.
+    pub fn data_changes(self) -> NotificationStream<DataChangeItem> {
+        self.data_changes
+    }
+
+    /// Get the stream of event notifications, discarding the data change stream.
+    pub fn events(self) -> NotificationStream<EventItem> {
+        self.events
+    }
+
+    /// Get both the data change stream and the event stream.
+    pub fn split(
+        self,
+    ) -> (
+        NotificationStream<DataChangeItem>,
+        NotificationStream<EventItem>,
+    ) {
+        (self.data_changes, self.events)
+    }
+}
+
+/// An implementation of [OnSubscriptionNotification] that delivers notifications
+/// through async streams, instead of calling a function.
+///
+/// Each stream buffers up to `buffer_size` notifications. Notifications are never
+/// dropped: once a buffer is full, the session stops acknowledging notification
+/// messages for the subscription and holds back further publish requests until
+/// the consumer catches up. A single publish response may push a buffer past
+/// `buffer_size`, since the whole notification message is always delivered.
+///
+/// A stream that is dropped no longer receives notifications and never blocks
+/// the subscription. Note that if the consumer stalls for longer than the
+/// lifetime of the subscription, the server may delete it.
+pub struct StreamCallback {
+    data_changes: BufferSender<DataChangeItem>,
+    events: BufferSender<EventItem>,
+}
+
+impl StreamCallback {
+    /// Create a new stream callback, and the streams it delivers notifications to.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer_size` - Number of notifications each stream buffers before applying backpressure.
+    pub fn new(buffer_size: usize) -> (Self, SubscriptionStreams) {
+        let (data_tx, data_rx) = buffer(buffer_size);
+        let (event_tx, event_rx) = buffer(buffer_size);
+        (
+            Self {
+                data_changes: data_tx,
+                events: event_tx,
+            },
+            SubscriptionStreams {
+                data_changes: data_rx,
+                events: event_rx,
+            },
+        )
+    }
+}
+
+impl OnSubscriptionNotification for StreamCallback {
+    fn on_data_value(&mut self, notification: DataValue, item: &MonitoredItem) {
+        self.data_changes.send((item.id(), notification));
+    }
+
+    fn on_event(&mut self, event_fields: Option<Vec<Variant>>, item: &MonitoredItem) {
+        self.events.send((item.id(), event_fields));
+    }
+
+    fn wait_for_capacity(&self) -> Option<BoxFuture<'static, ()>> {
+        if !self.data_changes.is_full() && !self.events.is_full() {
+            return None;
+        }
+        let data_changes = self.data_changes.wait_for_space();
+        let events = self.events.wait_for_space();
+        Some(
+            async move {
+                data_changes.await;
+                events.await;
+            }
+            .boxed(),
+        )
+    }
+}
@@ -12,6 +12,7 @@ use opcua_types::{
     ByteString, CloseSecureChannelRequest, ContextOwned, IntegerId, NodeId, RequestHeader,
     SecurityTokenRequestType, StatusCode,
 };
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error};
 
 use super::{
@@ -162,6 +163,31 @@ impl AsyncSecureChannel {
         &self,
         request: impl Into<RequestMessage>,
         timeout: Duration,
+    ) -> Result<ResponseMessage, StatusCode> {
+        self.send_inner(request, timeout, None).await
+    }
+
+    /// Send a message on the secure channel, and wait for a response, or for `token`
+    /// to be cancelled.
+    ///
+    /// If the token is cancelled while a large message is being sent, the remaining
+    /// chunks are replaced by an abort chunk, so the server discards the partial message.
+    /// In this case, or if the token is cancelled while waiting for a response, this returns
+    /// `BadRequestCancelledByClient`.
+    pub async fn send_cancellable(
+        &self,
+        request: impl Into<RequestMessage>,
+        timeout: Duration,
+        token: CancellationToken,
+    ) -> Result<ResponseMessage, StatusCode> {
+        self.send_inner(request, timeout, Some(token)).await
+    }
+
+    async fn send_inner(
+        &self,
+        request: impl Into<RequestMessage>,
+        timeout: Duration,
+        token: Option<CancellationToken>,
     ) -> Result<ResponseMessage, StatusCode> {
         let sender = self.request_send.load().as_deref().cloned();
         let Some(send) = sender else {
@@ -200,7 +226,10 @@ impl AsyncSecureChannel {
             drop(guard);
         }
 
-        Request::new(request, send, timeout).send().await
+        Request::new(request, send, timeout)
+            .with_cancel_token(token)
+            .send()
+            .await
     }
 
     /// Attempt to establish a connection using this channel, returning an event loop
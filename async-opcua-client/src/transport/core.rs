@@ -6,6 +6,7 @@ use futures::future::Either;
 use opcua_core::comms::sequence_number::SequenceNumberHandle;
 use opcua_core::{trace_read_lock, trace_write_lock, RequestMessage, ResponseMessage};
 use parking_lot::RwLock;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, trace, warn};
 
 use opcua_core::comms::buffer::SendBuffer;
@@ -65,6 +66,7 @@ pub struct OutgoingMessage {
     pub request: RequestMessage,
     pub callback: Option<tokio::sync::oneshot::Sender<Result<ResponseMessage, StatusCode>>>,
     pub deadline: Instant,
+    pub cancel: Option<CancellationToken>,
 }
 
 impl TransportState {
@@ -92,7 +94,7 @@ impl TransportState {
     pub(super) async fn wait_for_outgoing_message(
         &mut self,
         send_buffer: &mut SendBuffer,
-    ) -> Option<(RequestMessage, u32)> {
+    ) -> Option<(RequestMessage, u32, Option<CancellationToken>)> {
         loop {
             // Check for any messages that have timed out, and get the time until the next message
             // times out
@@ -107,6 +109,13 @@ impl TransportState {
                     }
                     outgoing = self.outgoing_recv.recv() => {
                         let outgoing = outgoing?;
+                        if outgoing.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+                            // Cancelled before we started sending it, just drop the message.
+                            if let Some(callback) = outgoing.callback {
+                                let _ = callback.send(Err(StatusCode::BadRequestCancelledByClient));
+                            }
+                            continue;
+                        }
                         let request_id = send_buffer.next_request_id();
                         if let Some(callback) = outgoing.callback {
                             self.message_states.insert(request_id, MessageState {
@@ -115,7 +124,7 @@ impl TransportState {
                                 deadline: outgoing.deadline,
                             });
                         }
-                        break Some((outgoing.request, request_id));
+                        break Some((outgoing.request, request_id, outgoing.cancel));
                     }
             }
         }
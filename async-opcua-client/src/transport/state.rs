@@ -3,7 +3,9 @@ use std::{
     time::{Duration, Instant},
 };
 
+use futures::future::Either;
 use tokio::sync::mpsc::error::SendTimeoutError;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, trace};
 
 use crate::{session::process_unexpected_response, transport::OutgoingMessage};
@@ -37,6 +39,7 @@ pub(super) struct Request {
     payload: RequestMessage,
     sender: RequestSend,
     timeout: std::time::Duration,
+    cancel: Option<CancellationToken>,
 }
 
 impl Request {
@@ -49,14 +52,23 @@ impl Request {
             payload: payload.into(),
             sender,
             timeout,
+            cancel: None,
         }
     }
 
+    /// Set a token used to cancel the request. If the request is cancelled while
+    /// it is being sent, the rest of the message is replaced by an abort chunk.
+    pub(super) fn with_cancel_token(mut self, token: Option<CancellationToken>) -> Self {
+        self.cancel = token;
+        self
+    }
+
     pub(super) async fn send_no_response(self) -> Result<(), StatusCode> {
         let message = OutgoingMessage {
             request: self.payload,
             callback: None,
             deadline: Instant::now() + self.timeout,
+            cancel: self.cancel,
         };
 
         match self.sender.send_timeout(message, self.timeout).await {
@@ -73,6 +85,7 @@ impl Request {
             request: self.payload,
             callback: Some(cb_send),
             deadline: Instant::now() + self.timeout,
+            cancel: self.cancel.clone(),
         };
 
         match self.sender.send_timeout(message, self.timeout).await {
@@ -81,10 +94,18 @@ impl Request {
             Err(SendTimeoutError::Timeout(_)) => return Err(StatusCode::BadTimeout),
         }
 
-        match cb_recv.await {
-            Ok(r) => r,
-            // Should not really happen, would mean something panicked.
-            Err(_) => Err(StatusCode::BadConnectionClosed),
+        let cancelled = match &self.cancel {
+            Some(token) => Either::Left(token.cancelled()),
+            None => Either::Right(futures::future::pending::<()>()),
+        };
+
+        tokio::select! {
+            r = cb_recv => match r {
+                Ok(r) => r,
+                // Should not really happen, would mean something panicked.
+                Err(_) => Err(StatusCode::BadConnectionClosed),
+            },
+            _ = cancelled => Err(StatusCode::BadRequestCancelledByClient),
         }
     }
 }
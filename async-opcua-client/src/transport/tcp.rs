@@ -21,7 +21,7 @@ use opcua_types::StatusCode;
 use parking_lot::RwLock;
 use tokio::io::{AsyncWriteExt, ReadHalf, WriteHalf};
 use tokio::net::TcpStream;
-use tokio_util::codec::FramedRead;
+use tokio_util::{codec::FramedRead, sync::CancellationToken};
 use tracing::{debug, error};
 
 #[derive(Debug, Clone, Copy)]
@@ -38,6 +38,8 @@ pub struct TcpTransport {
     send_buffer: SendBuffer,
     should_close: bool,
     closed: TransportCloseState,
+    /// Request ID and cancellation token of the message currently being sent.
+    sending: Option<(u32, CancellationToken)>,
 }
 
 #[derive(Debug, Clone)]
@@ -186,6 +188,7 @@ impl Connector for TcpConnector {
             send_buffer: buffer,
             should_close: false,
             closed: TransportCloseState::Open,
+            sending: None,
         })
     }
 }
@@ -213,7 +216,48 @@ impl TcpTransport {
         }
     }
 
+    /// If the message currently being sent has been cancelled, replace its
+    /// remaining chunks with an abort chunk.
+    fn abort_cancelled_message(&mut self) -> Option<TransportPollResult> {
+        let (request_id, token) = self.sending.as_ref()?;
+        if !token.is_cancelled() {
+            return None;
+        }
+        let request_id = *request_id;
+        self.sending = None;
+
+        let secure_channel = trace_read_lock!(self.state.secure_channel);
+        let aborted = match self.send_buffer.write_abort(
+            request_id,
+            StatusCode::BadRequestCancelledByClient,
+            "Request cancelled by client",
+            &secure_channel,
+        ) {
+            Ok(aborted) => aborted,
+            Err(e) => {
+                error!("Failed to abort message with request id {request_id}: {e}");
+                return Some(TransportPollResult::Closed(e.status()));
+            }
+        };
+        drop(secure_channel);
+
+        // If the message was already sent in full, we just wait for the response as normal.
+        if !aborted {
+            return None;
+        }
+        debug!("Aborted sending message with request id {request_id}");
+        self.state
+            .message_send_failed(request_id, StatusCode::BadRequestCancelledByClient);
+        Some(TransportPollResult::RecoverableError(
+            StatusCode::BadRequestCancelledByClient,
+        ))
+    }
+
     async fn poll_inner(&mut self) -> TransportPollResult {
+        if let Some(r) = self.abort_cancelled_message() {
+            return r;
+        }
+
         // Either we've got something in the send buffer, which we can send,
         // or we're waiting for more outgoing messages.
         // We won't wait for outgoing messages while sending, since that
@@ -222,6 +266,7 @@ impl TcpTransport {
         // If there's nothing in the send buffer, but there are chunks available,
         // write them to the send buffer before proceeding.
         if self.send_buffer.should_encode_chunks() {
+            self.send_buffer.wait_for_next_chunk().await;
             let secure_channel = trace_read_lock!(self.state.secure_channel);
             if let Err(e) = self.send_buffer.encode_next_chunk(&secure_channel) {
                 return TransportPollResult::Closed(e);
@@ -245,13 +290,14 @@ impl TcpTransport {
                 }
             }
         } else {
+            self.sending = None;
             if self.should_close {
                 debug!("Writer is setting the connection state to finished(good)");
                 return TransportPollResult::Closed(StatusCode::Good);
             }
             tokio::select! {
                 outgoing = self.state.wait_for_outgoing_message(&mut self.send_buffer) => {
-                    let Some((outgoing, request_id)) = outgoing else {
+                    let Some((outgoing, request_id, cancel)) = outgoing else {
                         return TransportPollResult::Closed(StatusCode::Good);
                     };
                     let close_connection =
@@ -271,6 +317,7 @@ impl TcpTransport {
                             TransportPollResult::Closed(e.status())
                         }
                     } else {
+                        self.sending = cancel.map(|c| (request_id, c));
                         TransportPollResult::OutgoingMessage
                     }
                 }
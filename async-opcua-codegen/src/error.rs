@@ -4,6 +4,7 @@ use std::{
     str::ParseBoolError,
 };
 
+use opcua_xml::XmlErrorInner;
 use thiserror::Error;
 
 #[derive(Error, Debug, Clone)]
@@ -34,17 +35,32 @@ pub struct CodeGenError {
     pub kind: Box<CodeGenErrorKind>,
     pub context: Option<String>,
     pub file: Option<String>,
+    pub node_id: Option<String>,
+    pub type_name: Option<String>,
+    pub line: Option<usize>,
 }
 
 impl Display for CodeGenError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Code generation failed: {}", self.kind)?;
+        match (&self.node_id, &self.type_name) {
+            (Some(node_id), Some(name)) => write!(
+                f,
+                "Failed generating node {node_id} '{name}': {}",
+                self.kind
+            )?,
+            (Some(node_id), None) => write!(f, "Failed generating node {node_id}: {}", self.kind)?,
+            (None, Some(name)) => write!(f, "Failed generating type '{name}': {}", self.kind)?,
+            (None, None) => write!(f, "Code generation failed: {}", self.kind)?,
+        }
         if let Some(context) = &self.context {
             write!(f, ", while {context}")?;
         }
         if let Some(file) = &self.file {
             write!(f, ", while loading file {file}")?;
         }
+        if let Some(line) = &self.line {
+            write!(f, " at line {line}")?;
+        }
         Ok(())
     }
 }
@@ -110,11 +126,57 @@ impl CodeGenError {
         self
     }
 
+    /// Set the node that was being generated when the error occurred.
+    /// If the error already refers to a node, the innermost node is kept.
+    pub fn with_node(mut self, node_id: impl Into<String>, name: impl Into<String>) -> Self {
+        if self.node_id.is_none() {
+            self.node_id = Some(node_id.into());
+            self.type_name = Some(name.into());
+        }
+        self
+    }
+
+    /// Set the name of the type that was being generated when the error occurred.
+    /// If the error already refers to a type, the innermost type is kept.
+    pub fn with_type(mut self, name: impl Into<String>) -> Self {
+        if self.type_name.is_none() {
+            self.type_name = Some(name.into());
+        }
+        self
+    }
+
+    pub fn at_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+
+    /// Set the line of an XML error from the source document `data`.
+    pub fn with_source(self, data: &str) -> Self {
+        let CodeGenErrorKind::Xml(e) = &*self.kind else {
+            return self;
+        };
+        let line = match &e.error {
+            XmlErrorInner::Xml(e) => e.pos().row as usize,
+            _ => {
+                let end = e.span.start.min(data.len());
+                data.as_bytes()[..end]
+                    .iter()
+                    .filter(|c| **c == b'\n')
+                    .count()
+                    + 1
+            }
+        };
+        self.at_line(line)
+    }
+
     pub fn new(kind: CodeGenErrorKind) -> Self {
         Self {
             kind: Box::new(kind),
             context: None,
             file: None,
+            node_id: None,
+            type_name: None,
+            line: None,
         }
     }
 }
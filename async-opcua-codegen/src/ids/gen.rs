@@ -8,7 +8,11 @@ use proc_macro2::Span;
 use quote::quote;
 use syn::{parse_quote, Ident, Item};
 
-use crate::{utils::safe_ident, CodeGenError};
+use crate::{
+    input::NodeSetInput,
+    utils::{safe_ident, split_qualified_name, NodeIdVariant, ParsedNodeId},
+    CodeGenError,
+};
 
 pub struct IdItem {
     pub name: String,
@@ -124,3 +128,37 @@ pub fn render(item: IdItem) -> Result<Vec<Item>, CodeGenError> {
 
     Ok(items)
 }
+
+/// Collect the browse names of all nodes with numeric IDs in the node set's own namespace.
+pub fn browse_names(nodeset: &NodeSetInput) -> Result<HashMap<u32, String>, CodeGenError> {
+    let mut res = HashMap::new();
+    for node in &nodeset.xml.nodes {
+        let base = node.base();
+        let with_node = |e: CodeGenError| e.with_node(&base.node_id.0, &base.browse_name.0);
+        let id = ParsedNodeId::parse(nodeset.resolve_alias(&base.node_id.0)).map_err(with_node)?;
+        let NodeIdVariant::Numeric(num) = id.value else {
+            continue;
+        };
+        if id.namespace != nodeset.own_namespace_index {
+            continue;
+        }
+        let (name, _) = split_qualified_name(&base.browse_name.0).map_err(with_node)?;
+        res.insert(num, name.to_owned());
+    }
+    Ok(res)
+}
+
+/// Render a `<Name>_BROWSE_NAME` constant for each ID in `item` with a known browse name.
+pub fn render_browse_names(item: &IdItem, browse_names: &HashMap<u32, String>) -> Vec<Item> {
+    item.variants
+        .iter()
+        .filter_map(|(val, key)| {
+            let name = browse_names.get(val)?;
+            let (idt, _) = safe_ident(&format!("{key}_BROWSE_NAME"));
+            Some(parse_quote! {
+                #[allow(non_upper_case_globals)]
+                pub const #idt: &str = #name;
+            })
+        })
+        .collect()
+}
@@ -1,7 +1,7 @@
 use std::fs::File;
 
-use crate::CodeGenError;
-use gen::{parse, render};
+use crate::{input::SchemaCache, CodeGenError};
+use gen::{browse_names, parse, render, render_browse_names};
 
 mod gen;
 
@@ -12,20 +12,35 @@ pub struct NodeIdCodeGenTarget {
     pub type_name: Option<String>,
     #[serde(default)]
     pub extra_header: String,
+    /// Node set to read browse names from. If this is set, a `<Name>_BROWSE_NAME`
+    /// constant is generated for each node ID that is defined in the node set.
+    #[serde(default)]
+    pub browse_names_from_nodeset: Option<String>,
 }
 
 pub fn generate_node_ids(
     target: &NodeIdCodeGenTarget,
     root_path: &str,
+    cache: &SchemaCache,
 ) -> Result<syn::File, CodeGenError> {
     let file = File::open(format!("{}/{}", root_path, target.file_path))
         .map_err(|e| CodeGenError::io("Failed to open node ID file", e))?;
     let data = parse(file, &target.file_path, target.type_name.as_deref())?;
     let mut pairs = data.into_iter().collect::<Vec<_>>();
     pairs.sort_by(|a, b| a.0.cmp(&b.0));
+    let browse_names = target
+        .browse_names_from_nodeset
+        .as_deref()
+        .map(|file| browse_names(cache.get_nodeset(file)?))
+        .transpose()?;
     let mut items = Vec::new();
     for (_, item) in pairs {
-        items.extend(render(item)?.into_iter());
+        let browse_name_items = browse_names
+            .as_ref()
+            .map(|names| render_browse_names(&item, names))
+            .unwrap_or_default();
+        items.extend(render(item)?);
+        items.extend(browse_name_items);
     }
     Ok(syn::File {
         shebang: None,
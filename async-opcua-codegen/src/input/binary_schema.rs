@@ -10,7 +10,7 @@ pub struct BinarySchemaInput {
 
 impl BinarySchemaInput {
     pub fn parse(data: &str, path: &str) -> Result<Self, CodeGenError> {
-        let xml = load_bsd_file(data)?;
+        let xml = load_bsd_file(data).map_err(|e| CodeGenError::from(e).with_source(data))?;
         Ok(Self {
             namespace: xml.target_namespace.clone(),
             xml,
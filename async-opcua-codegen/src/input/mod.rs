@@ -152,6 +152,33 @@ impl SchemaCache {
         Ok(())
     }
 
+    /// Load the node sets with the given keys and merge them into a single node set.
+    /// The first node set is the base, the rest are merged into it in order.
+    /// See [`NodeSetInput::merge`].
+    pub fn load_merged_nodeset(&self, keys: &[&str]) -> Result<NodeSetInput, CodeGenError> {
+        let mut merged: Option<NodeSetInput> = None;
+        for key in keys {
+            let cached = self.get_nodeset(key)?;
+            // Node sets in the cache are shared, so load a fresh copy to merge.
+            let mut nodeset = NodeSetInput::load(&self.root_path, &cached.path, None)?;
+            nodeset.documentation = cached.documentation.clone();
+            merged = Some(match merged {
+                Some(merged) => merged.merge(nodeset)?,
+                None => nodeset,
+            });
+        }
+        merged.ok_or_else(|| CodeGenError::other("No node sets to merge"))
+    }
+
+    /// Load and merge the node sets with the given keys, see [`SchemaCache::load_merged_nodeset`].
+    /// The merged node set replaces the node set for its model URI, so that node sets
+    /// depending on the model see the nodes from all the merged files.
+    pub fn merge_nodesets(&mut self, keys: &[&str]) -> Result<(), CodeGenError> {
+        let merged = self.load_merged_nodeset(keys)?;
+        self.nodesets.insert(merged.uri.clone(), merged);
+        Ok(())
+    }
+
     pub fn load_binary_schema(&mut self, file_path: &str) -> Result<(), CodeGenError> {
         let schema = BinarySchemaInput::load(&self.root_path, file_path)?;
         let idx = self.binary_schemas.insert(schema.namespace.clone(), schema);
@@ -6,8 +6,11 @@ use std::{
 use opcua_xml::{
     load_nodeset2_file,
     schema::{
-        opc_ua_types::Variant,
-        ua_node_set::{DataTypeDefinition, UANode, UANodeSet},
+        opc_ua_types::{self, Variant},
+        ua_node_set::{
+            DataTypeDefinition, NodeId, NodeIdAlias, UAInstance, UANode, UANodeBase, UANodeSet,
+            UriTable,
+        },
     },
     XmlElement,
 };
@@ -67,6 +70,161 @@ enum EncodingKind {
     Json,
 }
 
+/// Map from namespace indices in a node set being merged to the namespace
+/// indices in the node set it is merged into.
+struct NamespaceRemap(Vec<u16>);
+
+impl NamespaceRemap {
+    fn index(&self, idx: u16) -> u16 {
+        self.0.get(idx as usize).copied().unwrap_or(idx)
+    }
+
+    /// Remap a node ID on the form `ns=<index>;<identifier>`. Aliases and node IDs
+    /// in namespace 0 are left as they are.
+    fn node_id(&self, id: &mut String) {
+        let Some((idx, identifier)) = id.strip_prefix("ns=").and_then(|r| r.split_once(';')) else {
+            return;
+        };
+        let Ok(idx) = idx.parse() else {
+            return;
+        };
+        *id = format!("ns={};{identifier}", self.index(idx));
+    }
+
+    /// Remap a qualified name on the form `<index>:<name>`.
+    fn qualified_name(&self, name: &mut String) {
+        let Some((idx, rest)) = name.split_once(':') else {
+            return;
+        };
+        let Ok(idx) = idx.parse() else {
+            return;
+        };
+        *name = format!("{}:{rest}", self.index(idx));
+    }
+
+    fn node_base(&self, base: &mut UANodeBase) {
+        self.node_id(&mut base.node_id.0);
+        self.qualified_name(&mut base.browse_name.0);
+        for r in base
+            .references
+            .iter_mut()
+            .flat_map(|r| r.references.iter_mut())
+        {
+            self.node_id(&mut r.node_id.0);
+            self.node_id(&mut r.reference_type.0);
+        }
+        for p in base
+            .role_permissions
+            .iter_mut()
+            .flat_map(|r| r.role_permissions.iter_mut())
+        {
+            self.node_id(&mut p.node_id.0);
+        }
+    }
+
+    fn instance(&self, instance: &mut UAInstance) {
+        self.node_base(&mut instance.base);
+        if let Some(parent) = &mut instance.parent_node_id {
+            self.node_id(&mut parent.0);
+        }
+    }
+
+    fn node(&self, node: &mut UANode) {
+        match node {
+            UANode::Object(n) => self.instance(&mut n.base),
+            UANode::Variable(n) => {
+                self.instance(&mut n.base);
+                self.node_id(&mut n.data_type.0);
+                if let Some(value) = &mut n.value {
+                    self.variant(&mut value.0);
+                }
+            }
+            UANode::Method(n) => {
+                self.instance(&mut n.base);
+                if let Some(id) = &mut n.method_declaration_id {
+                    self.node_id(&mut id.0);
+                }
+            }
+            UANode::View(n) => self.instance(&mut n.base),
+            UANode::ObjectType(n) => self.node_base(&mut n.base.base),
+            UANode::VariableType(n) => {
+                self.node_base(&mut n.base.base);
+                self.node_id(&mut n.data_type.0);
+                if let Some(value) = &mut n.value {
+                    self.variant(&mut value.0);
+                }
+            }
+            UANode::DataType(n) => {
+                self.node_base(&mut n.base.base);
+                if let Some(definition) = &mut n.definition {
+                    self.qualified_name(&mut definition.name.0);
+                    for field in &mut definition.fields {
+                        self.node_id(&mut field.data_type.0);
+                    }
+                }
+            }
+            UANode::ReferenceType(n) => self.node_base(&mut n.base.base),
+        }
+    }
+
+    fn variant(&self, variant: &mut Variant) {
+        match variant {
+            Variant::QualifiedName(name) => self.xml_qualified_name(name),
+            Variant::ListOfQualifiedName(names) => {
+                names.iter_mut().for_each(|n| self.xml_qualified_name(n))
+            }
+            Variant::NodeId(id) | Variant::ExpandedNodeId(id) => self.xml_node_id(id),
+            Variant::ListOfNodeId(ids) | Variant::ListOfExpandedNodeId(ids) => {
+                ids.iter_mut().for_each(|id| self.xml_node_id(id))
+            }
+            Variant::ExtensionObject(obj) => self.extension_object(obj),
+            Variant::ListOfExtensionObject(objs) => {
+                objs.iter_mut().for_each(|o| self.extension_object(o))
+            }
+            Variant::Variant(variant) => self.variant(variant),
+            Variant::ListOfVariant(variants) => variants.iter_mut().for_each(|v| self.variant(v)),
+            _ => (),
+        }
+    }
+
+    fn xml_node_id(&self, id: &mut opc_ua_types::NodeId) {
+        if let Some(identifier) = &mut id.identifier {
+            self.node_id(identifier);
+        }
+    }
+
+    fn xml_qualified_name(&self, name: &mut opc_ua_types::QualifiedName) {
+        if let Some(idx) = &mut name.namespace_index {
+            *idx = self.index(*idx);
+        }
+    }
+
+    fn extension_object(&self, obj: &mut opc_ua_types::ExtensionObject) {
+        if let Some(type_id) = &mut obj.type_id {
+            self.xml_node_id(type_id);
+        }
+        if let Some(body) = obj.body.as_mut().and_then(|b| b.data.as_mut()) {
+            self.xml_element(body);
+        }
+    }
+
+    /// Remap node IDs and qualified name namespace indices in the body of an extension object.
+    fn xml_element(&self, element: &mut XmlElement) {
+        if let Some(text) = &mut element.text {
+            if element.tag == "NamespaceIndex" {
+                if let Ok(idx) = text.trim().parse() {
+                    *text = self.index(idx).to_string();
+                }
+            } else {
+                self.node_id(text);
+            }
+        }
+        for child in element.children.values_mut().flatten() {
+            self.xml_element(child);
+        }
+    }
+}
+
 impl NodeSetInput {
     fn find_referenced_xsd_schemas_rec(obj: &XmlElement, map: &mut HashSet<String>) {
         if let Some(attr) = obj.attributes.get("xmlns") {
@@ -126,7 +284,8 @@ impl NodeSetInput {
     }
 
     pub fn parse(data: &str, path: &str, docs: Option<&str>) -> Result<Self, CodeGenError> {
-        let nodeset = load_nodeset2_file(data)?;
+        let nodeset =
+            load_nodeset2_file(data).map_err(|e| CodeGenError::from(e).with_source(data))?;
 
         let Some(nodeset) = nodeset.node_set else {
             return Err(CodeGenError::missing_required_value("NodeSet"));
@@ -225,6 +384,120 @@ impl NodeSetInput {
         Self::parse(&data, file_path, docs.as_deref()).map_err(|e| e.in_file(file_path))
     }
 
+    /// Merge `other` into this node set, so that the two can be generated as a single module.
+    /// Both node sets must define the same model URI. Namespaces in `other` missing from this
+    /// node set are added to its namespace table, and namespace indices in `other` are remapped
+    /// to the indices in this node set. Aliases and required models are unified, it is an error
+    /// for the node sets to define the same alias with different targets, or to define the
+    /// same node.
+    pub fn merge(mut self, mut other: NodeSetInput) -> Result<Self, CodeGenError> {
+        if self.uri != other.uri {
+            return Err(CodeGenError::other(format!(
+                "Cannot merge node set with model URI {} into node set with model URI {}",
+                other.uri, self.uri
+            ))
+            .in_file(&other.path));
+        }
+
+        let mut indices = Vec::with_capacity(other.namespaces.len());
+        for namespace in &other.namespaces {
+            let idx = match self.namespaces.iter().position(|n| n == namespace) {
+                Some(idx) => idx,
+                None => {
+                    self.namespaces.push(namespace.clone());
+                    // Keep the namespace table in the XML in sync, it is used when generating events.
+                    self.xml
+                        .namespace_uris
+                        .get_or_insert_with(|| UriTable { uris: Vec::new() })
+                        .uris
+                        .push(namespace.clone());
+                    self.namespaces.len() - 1
+                }
+            };
+            indices.push(idx as u16);
+        }
+        let remap = NamespaceRemap(indices);
+        for id in other.aliases.values_mut() {
+            remap.node_id(id);
+        }
+        for node in &mut other.xml.nodes {
+            remap.node(node);
+        }
+        if self.own_namespace_index == 0 {
+            self.own_namespace_index = remap.index(other.own_namespace_index);
+        }
+
+        let mut new_aliases = Vec::new();
+        for (alias, id) in other.aliases {
+            match self.aliases.get(&alias) {
+                Some(existing) if existing == &id => (),
+                Some(existing) => {
+                    return Err(CodeGenError::other(format!(
+                        "Alias {alias} refers to {id}, but refers to {existing} in {}",
+                        self.path
+                    ))
+                    .in_file(&other.path))
+                }
+                None => {
+                    new_aliases.push(NodeIdAlias {
+                        id: NodeId(id.clone()),
+                        alias: alias.clone(),
+                    });
+                    self.aliases.insert(alias, id);
+                }
+            }
+        }
+        // Keep the alias table in the XML in sync, it is used when generating events.
+        self.xml
+            .aliases
+            .get_or_insert_with(Default::default)
+            .aliases
+            .extend(new_aliases);
+
+        let mut node_ids = HashSet::new();
+        for node in &self.xml.nodes {
+            node_ids.insert(ParsedNodeId::parse(
+                self.resolve_alias(&node.base().node_id.0),
+            )?);
+        }
+        for node in &other.xml.nodes {
+            let id = ParsedNodeId::parse(self.resolve_alias(&node.base().node_id.0))?;
+            if node_ids.contains(&id) {
+                return Err(CodeGenError::other(format!(
+                    "Node {} is also defined in {}",
+                    node.base().node_id.0,
+                    self.path
+                ))
+                .in_file(&other.path));
+            }
+        }
+
+        info!(
+            "Merged {} nodes from {} into nodeset {}",
+            other.xml.nodes.len(),
+            other.path,
+            self.uri
+        );
+        self.xml.nodes.extend(other.xml.nodes);
+
+        for uri in other.required_model_uris {
+            if !self.required_model_uris.contains(&uri) {
+                self.required_model_uris.push(uri);
+            }
+        }
+        match (&mut self.documentation, other.documentation) {
+            (Some(docs), Some(other_docs)) => docs.extend(other_docs),
+            (docs @ None, other_docs) => *docs = other_docs,
+            _ => (),
+        }
+        self.referenced_xsd_schemas
+            .extend(other.referenced_xsd_schemas);
+        self.parent_type_ids = OnceLock::new();
+        self.type_info = OnceLock::new();
+
+        Ok(self)
+    }
+
     pub fn validate(&self, cache: &SchemaCache) -> Result<(), CodeGenError> {
         for uri in &self.required_model_uris {
             cache.get_nodeset(uri)?;
@@ -386,3 +659,233 @@ impl NodeSetInput {
             .map_err(|e| e.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{NodeSetInput, SchemaCache};
+
+    fn nodeset(namespaces: &[&str], model: &str, aliases: &[(&str, &str)], ids: &[&str]) -> String {
+        let namespaces: String = namespaces
+            .iter()
+            .map(|n| format!("<Uri>{n}</Uri>"))
+            .collect();
+        let aliases: String = aliases
+            .iter()
+            .map(|(a, id)| format!(r#"<Alias Alias="{a}">{id}</Alias>"#))
+            .collect();
+        let nodes: String = ids
+            .iter()
+            .map(|id| {
+                format!(
+                    r#"<UAObject NodeId="{id}" BrowseName="1:Obj"><DisplayName>Obj</DisplayName></UAObject>"#
+                )
+            })
+            .collect();
+        format!(
+            r#"<UANodeSet xmlns="http://opcfoundation.org/UA/2011/03/UANodeSet.xsd">
+  <NamespaceUris>{namespaces}</NamespaceUris>
+  <Models>
+    <Model ModelUri="{model}"><RequiredModel ModelUri="http://opcfoundation.org/UA/" /></Model>
+  </Models>
+  <Aliases>{aliases}</Aliases>
+  {nodes}
+</UANodeSet>"#
+        )
+    }
+
+    fn parse(data: &str, path: &str) -> NodeSetInput {
+        NodeSetInput::parse(data, path, None).unwrap()
+    }
+
+    #[test]
+    fn merge_nodesets() {
+        let first = parse(
+            &nodeset(
+                &["http://test.com"],
+                "http://test.com",
+                &[("HasComponent", "i=47")],
+                &["ns=1;i=1", "ns=1;i=2"],
+            ),
+            "first.xml",
+        );
+        let second = parse(
+            &nodeset(
+                &["http://test.com", "http://other.com"],
+                "http://test.com",
+                &[("HasComponent", "i=47"), ("Other", "ns=2;i=1")],
+                &["ns=1;i=3"],
+            ),
+            "second.xml",
+        );
+        let merged = first.merge(second).unwrap();
+        assert_eq!(merged.uri, "http://test.com");
+        assert_eq!(merged.path, "first.xml");
+        assert_eq!(
+            merged.namespaces,
+            vec![
+                "http://opcfoundation.org/UA/",
+                "http://test.com",
+                "http://other.com"
+            ]
+        );
+        assert_eq!(merged.own_namespace_index, 1);
+        assert_eq!(merged.aliases.len(), 2);
+        assert_eq!(merged.resolve_alias("Other"), "ns=2;i=1");
+        assert_eq!(merged.xml.aliases.as_ref().unwrap().aliases.len(), 2);
+        assert_eq!(
+            merged.required_model_uris,
+            vec!["http://opcfoundation.org/UA/"]
+        );
+        let ids: Vec<_> = merged
+            .xml
+            .nodes
+            .iter()
+            .map(|n| n.base().node_id.0.as_str())
+            .collect();
+        assert_eq!(ids, vec!["ns=1;i=1", "ns=1;i=2", "ns=1;i=3"]);
+    }
+
+    #[test]
+    fn merge_nodesets_remaps_namespaces() {
+        let first = parse(
+            &nodeset(&["http://test.com"], "http://test.com", &[], &["ns=1;i=1"]),
+            "first.xml",
+        );
+        let second = parse(
+            &nodeset(
+                &["http://other.com", "http://test.com"],
+                "http://test.com",
+                &[("Other", "ns=1;i=5")],
+                &["ns=2;i=2"],
+            ),
+            "second.xml",
+        );
+        let merged = first.merge(second).unwrap();
+        assert_eq!(
+            merged.namespaces,
+            vec![
+                "http://opcfoundation.org/UA/",
+                "http://test.com",
+                "http://other.com"
+            ]
+        );
+        assert_eq!(
+            merged.xml.namespace_uris.as_ref().unwrap().uris,
+            vec!["http://test.com", "http://other.com"]
+        );
+        assert_eq!(merged.own_namespace_index, 1);
+        assert_eq!(merged.resolve_alias("Other"), "ns=2;i=5");
+        let node = &merged.xml.nodes[1];
+        assert_eq!(node.base().node_id.0, "ns=1;i=2");
+        // The browse name used namespace 1 in the second file, which is now namespace 2.
+        assert_eq!(node.base().browse_name.0, "2:Obj");
+
+        // Nodes are compared after remapping.
+        let err = parse(
+            &nodeset(&["http://test.com"], "http://test.com", &[], &["ns=1;i=1"]),
+            "first.xml",
+        )
+        .merge(parse(
+            &nodeset(
+                &["http://other.com", "http://test.com"],
+                "http://test.com",
+                &[],
+                &["ns=2;i=1"],
+            ),
+            "second.xml",
+        ))
+        .err()
+        .unwrap()
+        .to_string();
+        assert!(err.contains("Node ns=1;i=1 is also defined"), "{err}");
+    }
+
+    #[test]
+    fn schema_cache_merged_nodeset_replaces_model() {
+        let root = std::env::temp_dir().join(format!("opcua-codegen-merge-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(
+            root.join("first.xml"),
+            nodeset(&["http://test.com"], "http://test.com", &[], &["ns=1;i=1"]),
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("second.xml"),
+            nodeset(&["http://test.com"], "http://test.com", &[], &["ns=1;i=2"]),
+        )
+        .unwrap();
+
+        let mut cache = SchemaCache::new(root.to_str().unwrap());
+        cache.load_nodeset("first.xml", None).unwrap();
+        cache.load_nodeset("second.xml", None).unwrap();
+        assert_eq!(
+            cache
+                .get_nodeset("http://test.com")
+                .unwrap()
+                .xml
+                .nodes
+                .len(),
+            1
+        );
+        cache.merge_nodesets(&["first.xml", "second.xml"]).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        // Dependents looking up the model URI see the nodes from both files.
+        assert_eq!(
+            cache
+                .get_nodeset("http://test.com")
+                .unwrap()
+                .xml
+                .nodes
+                .len(),
+            2
+        );
+        assert_eq!(cache.get_nodeset("first.xml").unwrap().xml.nodes.len(), 1);
+        assert_eq!(cache.get_nodeset("second.xml").unwrap().xml.nodes.len(), 1);
+    }
+
+    #[test]
+    fn merge_nodesets_conflicts() {
+        let base = || {
+            parse(
+                &nodeset(
+                    &["http://test.com"],
+                    "http://test.com",
+                    &[("HasComponent", "i=47")],
+                    &["ns=1;i=1"],
+                ),
+                "first.xml",
+            )
+        };
+        let merge = |namespaces: &[&str], model: &str, aliases: &[(&str, &str)], ids: &[&str]| {
+            base()
+                .merge(parse(
+                    &nodeset(namespaces, model, aliases, ids),
+                    "second.xml",
+                ))
+                .err()
+                .unwrap()
+                .to_string()
+        };
+
+        let err = merge(
+            &["http://other.com"],
+            "http://other.com",
+            &[],
+            &["ns=1;i=2"],
+        );
+        assert!(err.contains("model URI http://other.com"), "{err}");
+
+        let err = merge(
+            &["http://test.com"],
+            "http://test.com",
+            &[("HasComponent", "i=46")],
+            &["ns=1;i=2"],
+        );
+        assert!(err.contains("Alias HasComponent"), "{err}");
+
+        let err = merge(&["http://test.com"], "http://test.com", &[], &["ns=1;i=1"]);
+        assert!(err.contains("Node ns=1;i=1 is also defined"), "{err}");
+        assert!(err.contains("second.xml"), "{err}");
+    }
+}
@@ -10,7 +10,7 @@ pub struct XmlSchemaInput {
 
 impl XmlSchemaInput {
     pub fn parse(data: &str, path: &str) -> Result<Self, CodeGenError> {
-        let xml = load_xsd_schema(data)?;
+        let xml = load_xsd_schema(data).map_err(|e| CodeGenError::from(e).with_source(data))?;
         Ok(Self {
             namespace: xml
                 .target_namespace
@@ -26,48 +26,77 @@ pub use types::{
 use types::{generate_types, generate_types_nodeset, type_loader_impl, EncodingIds, ExternalType};
 pub use utils::{create_module_file, GeneratedOutput};
 
+/// Write `content` to the file at `path`, unless the file already contains exactly
+/// `content`. Unchanged files are left untouched, so that their modification time
+/// is preserved. Returns `true` if the file was written.
+fn write_if_changed(path: &Path, content: &str) -> Result<bool, CodeGenError> {
+    if std::fs::read(path).is_ok_and(|existing| existing == content.as_bytes()) {
+        return Ok(false);
+    }
+    std::fs::write(path, content)
+        .map_err(|e| CodeGenError::io(&format!("Failed to write to file {}", path.display()), e))?;
+    Ok(true)
+}
+
+/// Write generated items to `dir`, one file per module. Files that would not change
+/// are not rewritten, and files from earlier runs that are no longer generated are removed.
+/// `mod.rs` is left in place, it is expected to be written by [`write_module_file`].
 pub fn write_to_directory<T: GeneratedOutput>(
     dir: &str,
     root_path: &str,
     header: &str,
     mut items: Vec<T>,
 ) -> Result<Vec<String>, CodeGenError> {
-    let mut modules = Vec::new();
-    let mut modules_seen = HashSet::new();
     let dir = format!("{}/{}", root_path, dir);
-    let _ = std::fs::remove_dir_all(&dir);
     std::fs::create_dir_all(&dir)
         .map_err(|e| CodeGenError::io(&format!("Failed to create dir {}", dir), e))?;
 
     items.sort_by_key(|a| a.name().to_lowercase());
 
+    // Do it this way so that we keep a stable ordering.
+    let mut modules: Vec<(String, String)> = Vec::new();
+    let mut module_index: HashMap<String, usize> = HashMap::new();
     for gen in items {
         let module = gen.module().to_owned();
-        let path = Path::new(&format!("{}/{}.rs", dir, module)).to_owned();
-        let is_new = !path.exists();
-        let mut file = std::fs::File::options()
-            .append(true)
-            .create(true)
-            .open(format!("{}/{}.rs", dir, module))
-            .map_err(|e| {
-                CodeGenError::io(&format!("Failed to open file {}/{}.rs", dir, module), e)
-            })?;
-        if is_new {
-            file.write_all(header.as_bytes()).map_err(|e| {
-                CodeGenError::io(&format!("Failed to write to file {}/{}.rs", dir, module), e)
-            })?;
+        let content = prettyplease::unparse(&gen.to_file());
+        match module_index.get(&module) {
+            Some(idx) => modules[*idx].1.push_str(&content),
+            None => {
+                module_index.insert(module.clone(), modules.len());
+                modules.push((module, format!("{header}{content}")));
+            }
         }
-        // Do it this way so that we keep a stable ordering.
-        if modules_seen.insert(module.clone()) {
-            modules.push(module.clone());
+    }
+
+    let mut files = HashSet::new();
+    let mut written = 0;
+    for (module, content) in &modules {
+        let file_name = format!("{}.rs", module);
+        if write_if_changed(&Path::new(&dir).join(&file_name), content)? {
+            written += 1;
         }
-        file.write_all(prettyplease::unparse(&gen.to_file()).as_bytes())
-            .map_err(|e| {
-                CodeGenError::io(&format!("Failed to write to file {}/{}.rs", dir, module), e)
+        files.insert(file_name);
+    }
+
+    let entries = std::fs::read_dir(&dir)
+        .map_err(|e| CodeGenError::io(&format!("Failed to read dir {}", dir), e))?;
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if entry.path().is_file() && file_name != "mod.rs" && !files.contains(&file_name) {
+            std::fs::remove_file(entry.path()).map_err(|e| {
+                CodeGenError::io(&format!("Failed to remove file {}/{}", dir, file_name), e)
             })?;
+        }
     }
 
-    Ok(modules)
+    info!(
+        "Wrote {} of {} files to {}, the rest were unchanged",
+        written,
+        modules.len(),
+        dir
+    );
+
+    Ok(modules.into_iter().map(|(m, _)| m).collect())
 }
 
 pub fn write_module_file(
@@ -76,17 +105,8 @@ pub fn write_module_file(
     header: &str,
     file: File,
 ) -> Result<(), CodeGenError> {
-    let mut mod_file = std::fs::File::options()
-        .append(true)
-        .create(true)
-        .open(format!("{}/{}/{}", root_path, dir, "mod.rs"))
-        .map_err(|e| CodeGenError::io(&format!("Failed to open file {}/mod.rs", dir), e))?;
-    mod_file
-        .write_all(header.as_bytes())
-        .map_err(|e| CodeGenError::io(&format!("Failed to write to file {}/mod.rs", dir), e))?;
-    mod_file
-        .write_all(prettyplease::unparse(&file).as_bytes())
-        .map_err(|e| CodeGenError::io(&format!("Failed to write to file {}/mod.rs", dir), e))?;
+    let content = format!("{header}{}", prettyplease::unparse(&file));
+    write_if_changed(&Path::new(root_path).join(dir).join("mod.rs"), &content)?;
 
     Ok(())
 }
@@ -114,7 +134,17 @@ fn make_header(path: &str, extra: &[&str]) -> String {
 }
 
 pub fn run_codegen(config: &CodeGenConfig, root_path: &str) -> Result<(), CodeGenError> {
-    let cache = load_schemas(root_path, &config.sources)?;
+    let mut cache = load_schemas(root_path, &config.sources)?;
+    // Merge node sets split over several files up front, so that targets depending
+    // on the model see the nodes from all of the files.
+    for target in &config.targets {
+        if let CodeGenTarget::Nodes(n) = target {
+            if !n.extra_files.is_empty() {
+                cache.merge_nodesets(&n.files())?;
+            }
+        }
+    }
+    let cache = cache;
 
     for target in &config.targets {
         match target {
@@ -161,6 +191,12 @@ pub fn run_codegen(config: &CodeGenConfig, root_path: &str) -> Result<(), CodeGe
             CodeGenTarget::Nodes(n) => {
                 info!("Running node set code generation for {}", n.file);
                 let node_set = cache.get_nodeset(&n.file)?;
+                let node_set = if n.extra_files.is_empty() {
+                    node_set
+                } else {
+                    // The merged node set is stored under the model URI.
+                    cache.get_nodeset(&node_set.uri)?
+                };
                 info!("Found {} nodes in node set", node_set.xml.nodes.len());
 
                 let chunks = generate_target(n, node_set, &config.preferred_locale, &cache)
@@ -207,7 +243,8 @@ pub fn run_codegen(config: &CodeGenConfig, root_path: &str) -> Result<(), CodeGe
             }
             CodeGenTarget::Ids(n) => {
                 info!("Running node ID code generation for {}", n.file_path);
-                let gen = generate_node_ids(n, root_path).map_err(|e| e.in_file(&n.file_path))?;
+                let gen =
+                    generate_node_ids(n, root_path, &cache).map_err(|e| e.in_file(&n.file_path))?;
                 let mut file = std::fs::File::options()
                     .create(true)
                     .truncate(true)
@@ -279,3 +316,102 @@ pub struct CodeGenConfig {
 }
 
 const BASE_NAMESPACE: &str = "http://opcfoundation.org/UA/";
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use syn::{parse_str, File};
+
+    use super::{write_to_directory, GeneratedOutput};
+
+    struct Item(&'static str, &'static str);
+
+    impl GeneratedOutput for Item {
+        fn to_file(self) -> File {
+            parse_str(&format!("pub struct {};", self.0)).unwrap()
+        }
+
+        fn module(&self) -> &str {
+            self.1
+        }
+
+        fn name(&self) -> &str {
+            self.0
+        }
+    }
+
+    #[test]
+    fn write_to_directory_only_changed() {
+        let root = std::env::temp_dir().join(format!("opcua-codegen-{}", std::process::id()));
+        let root_str = root.to_str().unwrap();
+        let dir = root.join("out");
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        let mtime = |name: &str| {
+            std::fs::metadata(dir.join(name))
+                .unwrap()
+                .modified()
+                .unwrap()
+        };
+        let set_old = |name: &str| {
+            std::fs::File::options()
+                .write(true)
+                .open(dir.join(name))
+                .unwrap()
+                .set_modified(old)
+                .unwrap()
+        };
+
+        let modules = write_to_directory(
+            "out",
+            root_str,
+            "",
+            vec![
+                Item("A", "nodeset_1"),
+                Item("B", "nodeset_2"),
+                Item("C", "nodeset_3"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(modules, vec!["nodeset_1", "nodeset_2", "nodeset_3"]);
+        for name in ["nodeset_1.rs", "nodeset_2.rs", "nodeset_3.rs"] {
+            set_old(name);
+        }
+        let original = std::fs::read_to_string(dir.join("nodeset_1.rs")).unwrap();
+
+        let modules = write_to_directory(
+            "out",
+            root_str,
+            "",
+            vec![Item("A", "nodeset_1"), Item("D", "nodeset_2")],
+        )
+        .unwrap();
+        assert_eq!(modules, vec!["nodeset_1", "nodeset_2"]);
+        assert_eq!(mtime("nodeset_1.rs"), old);
+        assert_ne!(mtime("nodeset_2.rs"), old);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("nodeset_1.rs")).unwrap(),
+            original
+        );
+        assert!(std::fs::read_to_string(dir.join("nodeset_2.rs"))
+            .unwrap()
+            .contains("pub struct D;"));
+        assert!(!dir.join("nodeset_3.rs").exists());
+
+        // Files that were edited after generation are overwritten.
+        std::fs::write(
+            dir.join("nodeset_1.rs"),
+            original.replace("pub struct A;", "pub struct A ;"),
+        )
+        .unwrap();
+        set_old("nodeset_1.rs");
+        write_to_directory("out", root_str, "", vec![Item("A", "nodeset_1")]).unwrap();
+        assert_ne!(mtime("nodeset_1.rs"), old);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("nodeset_1.rs")).unwrap(),
+            original
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
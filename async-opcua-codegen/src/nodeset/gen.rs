@@ -403,11 +403,10 @@ impl<'a> NodeSetCodeGenerator<'a> {
         let func_name: Ident = parse_str(&func_name_str)?;
         self.node_counter += 1;
 
-        let references = self.generate_references(node.base()).map_err(|e| {
-            e.with_context(format!(
-                "generating references for node {}",
-                node.base().node_id.0
-            ))
+        let base = node.base();
+        let references = self.generate_references(base).map_err(|e| {
+            e.with_context("generating references")
+                .with_node(&base.node_id.0, &base.browse_name.0)
         })?;
         let node = match &node {
             UANode::Object(n) => self.generate_object(n),
@@ -419,7 +418,7 @@ impl<'a> NodeSetCodeGenerator<'a> {
             UANode::DataType(n) => self.generate_data_type(n),
             UANode::ReferenceType(n) => self.generate_reference_type(n),
         }
-        .map_err(|e| e.with_context(format!("generating node {}", node.base().node_id.0)))?;
+        .map_err(|e| e.with_node(&base.node_id.0, &base.browse_name.0))?;
 
         let func: ItemFn = parse_quote! {
             #[allow(unused)]
@@ -439,3 +438,125 @@ impl<'a> NodeSetCodeGenerator<'a> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        input::{NodeSetInput, SchemaCache},
+        nodeset::{make_type_dict, NodeSetCodeGenTarget, NodeSetTypes},
+    };
+
+    use super::NodeSetCodeGenerator;
+
+    const TEST_NODESET: &str = r#"
+<UANodeSet xmlns="http://opcfoundation.org/UA/2011/03/UANodeSet.xsd">
+  <NamespaceUris>
+    <Uri>http://test.com</Uri>
+  </NamespaceUris>
+  <Models>
+    <Model ModelUri="http://test.com" Version="1.00" PublicationDate="2013-11-06T00:00:00Z" />
+  </Models>
+  <Aliases>
+    <Alias Alias="Int32">i=6</Alias>
+  </Aliases>
+  <UAVariable NodeId="ns=1;i=1" BrowseName="1:Scalar" DataType="Int32">
+    <DisplayName>Scalar</DisplayName>
+    <Value>
+      <Int32>5</Int32>
+    </Value>
+  </UAVariable>
+  <UAVariable NodeId="ns=1;i=2" BrowseName="1:Array" DataType="Int32" ValueRank="1" ArrayDimensions="3">
+    <DisplayName>Array</DisplayName>
+    <Value>
+      <ListOfInt32>
+        <Int32>1</Int32>
+        <Int32>2</Int32>
+        <Int32>3</Int32>
+      </ListOfInt32>
+    </Value>
+  </UAVariable>
+  <UAVariable NodeId="ns=1;i=3" BrowseName="1:Structure" DataType="i=887">
+    <DisplayName>Structure</DisplayName>
+    <Value>
+      <ExtensionObject>
+        <TypeId><Identifier>i=888</Identifier></TypeId>
+        <Body>
+          <EUInformation>
+            <NamespaceUri>http://unit-namespace.namespace</NamespaceUri>
+            <UnitId>15</UnitId>
+            <DisplayName>
+              <Locale>en</Locale>
+              <Text>Degrees Celsius</Text>
+            </DisplayName>
+          </EUInformation>
+        </Body>
+      </ExtensionObject>
+    </Value>
+  </UAVariable>
+  <UAVariable NodeId="ns=1;i=4" BrowseName="1:Variants" DataType="i=24" ValueRank="1">
+    <DisplayName>Variants</DisplayName>
+    <Value>
+      <ListOfVariant>
+        <Variant><Int32>1</Int32></Variant>
+        <Variant><String>two</String></Variant>
+      </ListOfVariant>
+    </Value>
+  </UAVariable>
+  <UAVariable NodeId="ns=1;i=5" BrowseName="1:Empty" DataType="Int32">
+    <DisplayName>Empty</DisplayName>
+  </UAVariable>
+</UANodeSet>"#;
+
+    /// Generate code for each node in the test node set, and return it as
+    /// a string without whitespace.
+    fn generate() -> Vec<String> {
+        let mut cache = SchemaCache::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../schemas/1.05"));
+        cache.load_xml_schema("Opc.Ua.Types.xsd").unwrap();
+        let target = NodeSetCodeGenTarget {
+            types: vec![NodeSetTypes {
+                file: "Opc.Ua.Types.xsd".to_owned(),
+                root_path: "opcua::types".to_owned(),
+            }],
+            ..Default::default()
+        };
+        let types = make_type_dict(&target, &cache).unwrap();
+        let input = NodeSetInput::parse(TEST_NODESET, "test.xml", None).unwrap();
+        let mut generator = NodeSetCodeGenerator::new("en", &input.aliases, types).unwrap();
+
+        input
+            .xml
+            .nodes
+            .iter()
+            .map(|node| {
+                let func = generator.generate_item(node).unwrap().func;
+                quote::quote!(#func)
+                    .to_string()
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn generate_variable_values() {
+        let generated = generate();
+        assert_eq!(generated.len(), 5);
+
+        assert!(generated[0].contains("DataValue::new_now(opcua::types::Variant::from(5i32))"));
+        assert!(generated[1]
+            .contains("DataValue::new_now(opcua::types::Variant::from(vec![1i32,2i32,3i32]))"));
+        assert!(generated[2].contains(
+            "DataValue::new_now(opcua::types::Variant::from(\
+             opcua::types::ExtensionObject::from_message(opcua::types::EUInformation{"
+        ));
+        assert!(generated[2].contains("unit_id:15i32"));
+        assert!(generated[2].contains("\"DegreesCelsius\""));
+        assert!(generated[3].contains(
+            "opcua::types::Variant::from((opcua::types::VariantScalarTypeId::Variant,vec![\
+             opcua::types::Variant::Variant(Box::new(opcua::types::Variant::from(1i32))),\
+             opcua::types::Variant::Variant(Box::new(opcua::types::Variant::from(\"two\"))),]))"
+        ));
+        assert!(generated[4].contains("opcua::types::DataValue::null()"));
+    }
+}
@@ -1,5 +1,6 @@
 mod events;
 mod gen;
+mod validate;
 mod value;
 
 use std::collections::HashMap;
@@ -12,6 +13,9 @@ use quote::quote;
 use serde::{Deserialize, Serialize};
 use syn::{parse_quote, parse_str, File, Ident, Item, ItemFn, Path};
 use tracing::info;
+pub use validate::{
+    find_dangling_references, validate_references, DanglingReference, ReferenceValidation,
+};
 
 use crate::{
     input::{NodeSetInput, SchemaCache},
@@ -32,6 +36,10 @@ pub struct NodeSetTypes {
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct NodeSetCodeGenTarget {
     pub file: String,
+    /// Additional node set files defining the same namespace as `file`.
+    /// These are merged with `file` and generated as a single module.
+    #[serde(default)]
+    pub extra_files: Vec<String>,
     pub output_dir: String,
     pub max_nodes_per_file: usize,
     pub types: Vec<NodeSetTypes>,
@@ -39,6 +47,18 @@ pub struct NodeSetCodeGenTarget {
     #[serde(default)]
     pub extra_header: String,
     pub events: Option<EventsTarget>,
+    #[serde(default)]
+    pub validate_references: ReferenceValidation,
+}
+
+impl NodeSetCodeGenTarget {
+    /// The keys of all the node set files of this target, `file` first.
+    pub fn files(&self) -> Vec<&str> {
+        std::iter::once(&self.file)
+            .chain(&self.extra_files)
+            .map(|f| f.as_str())
+            .collect()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -62,8 +82,14 @@ pub fn make_type_dict(
 ) -> Result<HashMap<String, XsdTypeWithPath>, CodeGenError> {
     let mut res = HashMap::new();
     for file in &target.types {
-        let xsd_file = cache.get_xml_schema(&file.file)?;
-        let path: Path = parse_str(&file.root_path)?;
+        let xsd_file = cache
+            .get_xml_schema(&file.file)
+            .map_err(|e| e.with_context("loading types for nodeset"))?;
+        let path: Path = parse_str(&file.root_path).map_err(|e| {
+            CodeGenError::from(e)
+                .with_context(format!("parsing root path {}", file.root_path))
+                .in_file(&file.file)
+        })?;
 
         for it in &xsd_file.xml.items {
             let (ty, name) = match it {
@@ -130,6 +156,9 @@ pub fn make_root_fun(chunk: &[NodeGenMethod]) -> ItemFn {
     // Also return the value as a boxed dyn iterator, by doing it this way we don't get an
     // enormous type signature on the final iterator,
     // and the runtime cost of a little indirection is so small it doesn't matter.
+    // Nodes are returned in the order of the generated functions, which is deterministic,
+    // but does not follow the node hierarchy. This is fine, since the import does not
+    // require referenced nodes to exist when a node is inserted.
     let first = names.next().unwrap();
     parse_quote! {
         pub(super) fn imported_nodes<'a>(ns_map: &'a opcua::nodes::NodeSetNamespaceMapper<'_>) -> Box<dyn Iterator<
@@ -149,6 +178,8 @@ pub fn generate_target(
     preferred_locale: &str,
     cache: &SchemaCache,
 ) -> Result<Vec<NodeSetChunk>, CodeGenError> {
+    validate_references(config.validate_references, input, cache)?;
+
     let types = make_type_dict(config, cache)?;
 
     let mut generator = NodeSetCodeGenerator::new(preferred_locale, &input.aliases, types)?;
@@ -0,0 +1,150 @@
+use std::{collections::HashSet, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    input::{NodeSetInput, SchemaCache},
+    utils::{NodeIdVariant, ParsedNodeId},
+    CodeGenError,
+};
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceValidation {
+    /// Do not check references.
+    #[default]
+    Off,
+    /// Log a warning for each dangling reference.
+    Warn,
+    /// Fail code generation if there are any dangling references.
+    Error,
+}
+
+/// A reference from a node in the node set to a node that is not
+/// defined in the node set or any of its required models.
+pub struct DanglingReference {
+    pub node_id: String,
+    pub browse_name: String,
+    pub target: String,
+    pub is_reference_type: bool,
+}
+
+impl Display for DanglingReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = if self.is_reference_type {
+            "reference type"
+        } else {
+            "node"
+        };
+        write!(
+            f,
+            "node {} '{}' references unknown {kind} {}",
+            self.node_id, self.browse_name, self.target
+        )
+    }
+}
+
+struct KnownNodes<'a> {
+    nodes: HashSet<(&'a str, NodeIdVariant)>,
+    visited: HashSet<&'a str>,
+}
+
+impl<'a> KnownNodes<'a> {
+    fn add(&mut self, input: &'a NodeSetInput, cache: &'a SchemaCache) -> Result<(), CodeGenError> {
+        if !self.visited.insert(&input.uri) {
+            return Ok(());
+        }
+        for node in &input.xml.nodes {
+            let id = &node.base().node_id.0;
+            if let Some(key) = resolve(input, id)? {
+                self.nodes.insert(key);
+            }
+        }
+        for uri in &input.required_model_uris {
+            self.add(cache.get_nodeset(uri)?, cache)?;
+        }
+        Ok(())
+    }
+}
+
+/// Resolve a node ID in `input` to its namespace URI and identifier.
+/// Returns `None` if the namespace index is not in the node set namespace table.
+fn resolve<'a>(
+    input: &'a NodeSetInput,
+    id: &str,
+) -> Result<Option<(&'a str, NodeIdVariant)>, CodeGenError> {
+    let parsed = ParsedNodeId::parse(input.resolve_alias(id))?;
+    Ok(input
+        .namespaces
+        .get(parsed.namespace as usize)
+        .map(|uri| (uri.as_str(), parsed.value)))
+}
+
+/// Find all references in `input` whose target or reference type does not resolve
+/// to a node in `input` or one of its required models.
+pub fn find_dangling_references(
+    input: &NodeSetInput,
+    cache: &SchemaCache,
+) -> Result<Vec<DanglingReference>, CodeGenError> {
+    let mut known = KnownNodes {
+        nodes: HashSet::new(),
+        visited: HashSet::new(),
+    };
+    known.add(input, cache)?;
+
+    let mut dangling = Vec::new();
+    for node in &input.xml.nodes {
+        let base = node.base();
+        for reference in base.references.iter().flat_map(|r| r.references.iter()) {
+            for (target, is_reference_type) in [
+                (&reference.node_id, false),
+                (&reference.reference_type, true),
+            ] {
+                let resolved = resolve(input, &target.0)
+                    .map_err(|e| e.with_node(&base.node_id.0, &base.browse_name.0))?;
+                if resolved.is_some_and(|key| known.nodes.contains(&key)) {
+                    continue;
+                }
+                dangling.push(DanglingReference {
+                    node_id: base.node_id.0.clone(),
+                    browse_name: base.browse_name.0.clone(),
+                    target: target.0.clone(),
+                    is_reference_type,
+                });
+            }
+        }
+    }
+
+    Ok(dangling)
+}
+
+/// Check `input` for dangling references, according to `mode`.
+pub fn validate_references(
+    mode: ReferenceValidation,
+    input: &NodeSetInput,
+    cache: &SchemaCache,
+) -> Result<(), CodeGenError> {
+    if mode == ReferenceValidation::Off {
+        return Ok(());
+    }
+    let dangling = find_dangling_references(input, cache)?;
+    if dangling.is_empty() {
+        return Ok(());
+    }
+    if mode == ReferenceValidation::Warn {
+        for reference in &dangling {
+            warn!("Dangling reference: {reference}");
+        }
+        return Ok(());
+    }
+    let list = dangling
+        .iter()
+        .map(|r| format!("  {r}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Err(CodeGenError::other(format!(
+        "Found {} dangling references:\n{list}",
+        dangling.len()
+    )))
+}
@@ -238,11 +238,12 @@ impl<'a> ValueBuilder<'a> {
                 for it in v {
                     let inner = self.render_variant(it)?;
                     items.extend(quote::quote! {
-                        opcua::types::Variant::Variant(Box::new(#inner))
+                        opcua::types::Variant::Variant(Box::new(#inner)),
                     });
                 }
+                // There is no `VariantType` for `Variant`, so build the array explicitly.
                 quote::quote! {
-                    vec![#items]
+                    (opcua::types::VariantScalarTypeId::Variant, vec![#items])
                 }
             }
             Variant::StatusCode(v) => {
@@ -228,10 +228,12 @@ impl CodeGenerator {
                 continue;
             }
 
-            match item {
-                LoadedType::Struct(v) => generated.push(self.generate_struct(v)?),
-                LoadedType::Enum(v) => generated.push(self.generate_enum(v)?),
-            }
+            let name = item.name().to_owned();
+            let res = match item {
+                LoadedType::Struct(v) => self.generate_struct(v),
+                LoadedType::Enum(v) => self.generate_enum(v),
+            };
+            generated.push(res.map_err(|e| e.with_type(name))?);
         }
 
         Ok(generated)
@@ -153,6 +153,17 @@ pub fn type_loader_impl(ids: &[(EncodingIds, String)], namespace: &str) -> Vec<I
         pub struct GeneratedTypeLoader;
     });
 
+    res.push(parse_quote! {
+        impl GeneratedTypeLoader {
+            /// Get the registry of all types handled by this type loader.
+            /// This can be used to enumerate the known types, and to decode them by
+            /// data type or encoding ID.
+            pub fn type_registry() -> &'static opcua::types::TypeLoaderInstance {
+                &TYPES
+            }
+        }
+    });
+
     res.push(parse_quote! {
         impl opcua::types::TypeLoader for GeneratedTypeLoader {
             #bin_body
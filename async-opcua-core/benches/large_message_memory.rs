@@ -0,0 +1,241 @@
+//! Measures the peak heap memory used while sending a Read response containing
+//! a large array, comparing encoding all chunks of the message up front with
+//! encoding chunks as they are sent.
+//!
+//! The measured value is the peak number of bytes allocated above the response itself,
+//! not time, so criterion reports it in bytes.
+//!
+//! Run with `cargo bench -p async-opcua-core --bench large_message_memory`.
+
+// `criterion_group!` generates an undocumented public function.
+#![allow(missing_docs)]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use criterion::{
+    criterion_group, criterion_main,
+    measurement::{Measurement, ValueFormatter},
+    Criterion, Throughput,
+};
+use opcua_core::{
+    comms::{
+        buffer::SendBuffer,
+        chunker::Chunker,
+        secure_channel::{Role, SecureChannel},
+        sequence_number::SequenceNumberHandle,
+    },
+    Message, ResponseMessage,
+};
+use opcua_crypto::CertificateStore;
+use opcua_types::{DataValue, ReadResponse, ResponseHeader, Variant};
+use parking_lot::RwLock;
+
+/// Allocator that keeps track of the number of bytes currently allocated,
+/// and the peak number of bytes allocated.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn add(size: usize) {
+    let current = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        add(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        add(new_size);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Criterion measurement of the peak number of bytes allocated.
+struct PeakAllocated;
+
+impl Measurement for PeakAllocated {
+    type Intermediate = usize;
+    type Value = usize;
+
+    /// Reset the peak to the current allocation, and return the current allocation.
+    fn start(&self) -> usize {
+        let current = ALLOCATED.load(Ordering::Relaxed);
+        PEAK.store(current, Ordering::Relaxed);
+        current
+    }
+
+    fn end(&self, start: usize) -> usize {
+        PEAK.load(Ordering::Relaxed).saturating_sub(start)
+    }
+
+    fn add(&self, v1: &usize, v2: &usize) -> usize {
+        v1 + v2
+    }
+
+    fn zero(&self) -> usize {
+        0
+    }
+
+    fn to_f64(&self, value: &usize) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &BytesFormatter
+    }
+}
+
+struct BytesFormatter;
+
+impl BytesFormatter {
+    fn scale(typical: f64, values: &mut [f64]) -> &'static str {
+        let (denominator, unit) = if typical < 1024.0 {
+            (1.0, "B")
+        } else if typical < 1024.0 * 1024.0 {
+            (1024.0, "KiB")
+        } else {
+            (1024.0 * 1024.0, "MiB")
+        };
+        for val in values {
+            *val /= denominator;
+        }
+        unit
+    }
+}
+
+impl ValueFormatter for BytesFormatter {
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        Self::scale(typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        // Report the peak relative to the encoded size of the message.
+        let size = match throughput {
+            Throughput::Elements(n) | Throughput::Bytes(n) | Throughput::BytesDecimal(n) => *n,
+        };
+        for val in values {
+            *val /= size as f64;
+        }
+        "B/B"
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "B"
+    }
+}
+
+const ARRAY_LENGTH: usize = 4_000_000;
+const CHUNK_SIZE: usize = 65535;
+
+fn large_response() -> ResponseMessage {
+    ReadResponse {
+        response_header: ResponseHeader::new_good(1),
+        results: Some(vec![DataValue::value_only(Variant::from(
+            (0..ARRAY_LENGTH).map(|i| i as f64).collect::<Vec<_>>(),
+        ))]),
+        diagnostic_infos: None,
+    }
+    .into()
+}
+
+/// Measure the peak bytes allocated by `send`, excluding the response it is given.
+/// Memory use is deterministic, so this sends the response once, and reports
+/// the same amount for each iteration.
+fn peak_allocated_by(iters: u64, send: impl FnOnce(ResponseMessage)) -> usize {
+    let response = large_response();
+    let start = PeakAllocated.start();
+    send(response);
+    PeakAllocated.end(start) * iters as usize
+}
+
+fn large_message_memory(c: &mut Criterion<PeakAllocated>) {
+    let channel = SecureChannel::new(
+        Arc::new(RwLock::new(CertificateStore::new(std::path::Path::new(
+            "./pki",
+        )))),
+        Role::Server,
+        Default::default(),
+    );
+    let runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+    let encoded_size = large_response().encoded_size(&channel.context().context());
+
+    let mut group = c.benchmark_group("large_message_memory");
+    group
+        .sample_size(10)
+        .warm_up_time(Duration::from_millis(100))
+        .measurement_time(Duration::from_secs(15))
+        .throughput(Throughput::Bytes(encoded_size as u64));
+
+    // Encode all chunks up front, then send them.
+    group.bench_function("encode_all_chunks", |b| {
+        b.iter_custom(|iters| {
+            peak_allocated_by(iters, |response| {
+                let chunks = Chunker::encode(
+                    SequenceNumberHandle::new(false),
+                    1,
+                    0,
+                    CHUNK_SIZE,
+                    &channel,
+                    &response,
+                )
+                .unwrap();
+                let mut buf = vec![0u8; CHUNK_SIZE + 1024];
+                for chunk in &chunks {
+                    channel.apply_security(chunk, &mut buf).unwrap();
+                }
+            })
+        })
+    });
+    // Encode chunks as they are sent.
+    group.bench_function("stream_chunks", |b| {
+        b.iter_custom(|iters| {
+            peak_allocated_by(iters, |response| {
+                runtime.block_on(async {
+                    let mut send_buffer = SendBuffer::new(CHUNK_SIZE, 0, 0, false);
+                    send_buffer.write(1, response, &channel).unwrap();
+                    let mut sink = tokio::io::sink();
+                    while send_buffer.should_encode_chunks() {
+                        send_buffer.wait_for_next_chunk().await;
+                        send_buffer.encode_next_chunk(&channel).unwrap();
+                        while send_buffer.can_read() {
+                            send_buffer.read_into_async(&mut sink).await.unwrap();
+                        }
+                    }
+                })
+            })
+        })
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().with_measurement(PeakAllocated);
+    targets = large_message_memory
+}
+criterion_main!(benches);
@@ -0,0 +1,253 @@
+//! Measures the number of heap allocations made while sending a sustained stream
+//! of publish responses through a send buffer, comparing a send buffer without
+//! a buffer pool to one using the default pool size.
+//!
+//! The `send_buffer_allocations` group counts allocations instead of measuring time,
+//! so criterion reports it as allocations per message. The `send_buffer_throughput`
+//! group measures the time taken to send the same messages.
+//!
+//! Run with `cargo bench -p async-opcua-core --bench send_buffer_allocations`.
+
+// `criterion_group!` generates an undocumented public function.
+#![allow(missing_docs)]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use criterion::{
+    criterion_group, criterion_main,
+    measurement::{Measurement, ValueFormatter},
+    BatchSize, Criterion, Throughput,
+};
+use opcua_core::{
+    comms::{
+        buffer::{SendBuffer, DEFAULT_BUFFER_POOL_SIZE},
+        secure_channel::{Role, SecureChannel},
+    },
+    ResponseMessage,
+};
+use opcua_crypto::CertificateStore;
+use opcua_types::{
+    DataChangeNotification, DataValue, DateTime, ExtensionObject, MonitoredItemNotification,
+    NotificationMessage, PublishResponse, ResponseHeader, Variant,
+};
+use parking_lot::RwLock;
+
+/// Allocator counting the number of allocations made.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Criterion measurement of the number of allocations made.
+struct Allocations;
+
+impl Measurement for Allocations {
+    type Intermediate = usize;
+    type Value = usize;
+
+    fn start(&self) -> usize {
+        ALLOCATIONS.load(Ordering::Relaxed)
+    }
+
+    fn end(&self, start: usize) -> usize {
+        ALLOCATIONS.load(Ordering::Relaxed) - start
+    }
+
+    fn add(&self, v1: &usize, v2: &usize) -> usize {
+        v1 + v2
+    }
+
+    fn zero(&self) -> usize {
+        0
+    }
+
+    fn to_f64(&self, value: &usize) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &AllocationsFormatter
+    }
+}
+
+struct AllocationsFormatter;
+
+impl ValueFormatter for AllocationsFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        // Report the number of allocations per message sent.
+        let messages = match throughput {
+            Throughput::Elements(n) | Throughput::Bytes(n) | Throughput::BytesDecimal(n) => *n,
+        };
+        for val in values {
+            *val /= messages as f64;
+        }
+        "allocs/msg"
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+}
+
+const MESSAGE_COUNT: usize = 2_000;
+const ITEMS_PER_MESSAGE: usize = 100;
+const CHUNK_SIZE: usize = 8192;
+const POOL_SIZES: [(&str, usize); 2] =
+    [("without_pool", 0), ("with_pool", DEFAULT_BUFFER_POOL_SIZE)];
+
+fn publish_responses() -> Vec<ResponseMessage> {
+    (0..MESSAGE_COUNT)
+        .map(|i| {
+            let notification = DataChangeNotification {
+                monitored_items: Some(
+                    (0..ITEMS_PER_MESSAGE)
+                        .map(|h| MonitoredItemNotification {
+                            client_handle: h as u32,
+                            value: DataValue::new_now(Variant::from(i as f64)),
+                        })
+                        .collect(),
+                ),
+                diagnostic_infos: None,
+            };
+            PublishResponse {
+                response_header: ResponseHeader::new_good(i as u32),
+                subscription_id: 1,
+                available_sequence_numbers: None,
+                more_notifications: false,
+                notification_message: NotificationMessage {
+                    sequence_number: i as u32,
+                    publish_time: DateTime::now(),
+                    notification_data: Some(vec![ExtensionObject::from_message(notification)]),
+                },
+                results: None,
+                diagnostic_infos: None,
+            }
+            .into()
+        })
+        .collect()
+}
+
+fn channel() -> SecureChannel {
+    SecureChannel::new(
+        Arc::new(RwLock::new(CertificateStore::new(std::path::Path::new(
+            "./pki",
+        )))),
+        Role::Server,
+        Default::default(),
+    )
+}
+
+/// Send all `responses` through a new send buffer with the given pool size.
+fn send_all(
+    runtime: &tokio::runtime::Runtime,
+    channel: &SecureChannel,
+    pool_size: usize,
+    responses: Vec<ResponseMessage>,
+) {
+    let mut send_buffer = SendBuffer::new(CHUNK_SIZE, 0, 0, false);
+    send_buffer.set_buffer_pool_size(pool_size);
+    runtime.block_on(async {
+        let mut sink = tokio::io::sink();
+        for (request_id, response) in responses.into_iter().enumerate() {
+            send_buffer
+                .write(request_id as u32 + 1, response, channel)
+                .unwrap();
+            while send_buffer.should_encode_chunks() {
+                send_buffer.encode_next_chunk(channel).unwrap();
+                while send_buffer.can_read() {
+                    send_buffer.read_into_async(&mut sink).await.unwrap();
+                }
+            }
+        }
+    });
+}
+
+fn send_buffer_allocations(c: &mut Criterion<Allocations>) {
+    let channel = channel();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("send_buffer_allocations");
+    group
+        .sample_size(10)
+        .warm_up_time(Duration::from_millis(100))
+        .throughput(Throughput::Elements(MESSAGE_COUNT as u64));
+    for (name, pool_size) in POOL_SIZES {
+        group.bench_function(name, |b| {
+            // The number of allocations is deterministic, so this sends the messages once,
+            // and reports the same number for each iteration.
+            b.iter_custom(|iters| {
+                let responses = publish_responses();
+                let start = Allocations.start();
+                send_all(&runtime, &channel, pool_size, responses);
+                Allocations.end(start) * iters as usize
+            })
+        });
+    }
+    group.finish();
+}
+
+fn send_buffer_throughput(c: &mut Criterion) {
+    let channel = channel();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("send_buffer_throughput");
+    group
+        .sample_size(10)
+        .throughput(Throughput::Elements(MESSAGE_COUNT as u64));
+    for (name, pool_size) in POOL_SIZES {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                publish_responses,
+                |responses| send_all(&runtime, &channel, pool_size, responses),
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = allocations;
+    config = Criterion::default().with_measurement(Allocations);
+    targets = send_buffer_allocations
+}
+criterion_group!(throughput, send_buffer_throughput);
+criterion_main!(allocations, throughput);
@@ -0,0 +1,128 @@
+//! Measures the time taken to encode and send a keep-alive publish response,
+//! comparing the general chunk encoding path with encoding small messages
+//! directly into a fixed size buffer.
+//!
+//! Run with `cargo bench -p async-opcua-core --bench small_message_latency`.
+
+// `criterion_group!` generates an undocumented public function.
+#![allow(missing_docs)]
+
+use std::{
+    hint::black_box,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use opcua_core::{
+    comms::{
+        buffer::SendBuffer,
+        buffer_pool::BufferPool,
+        chunker::Chunker,
+        secure_channel::{Role, SecureChannel},
+        sequence_number::SequenceNumberHandle,
+    },
+    ResponseMessage,
+};
+use opcua_crypto::CertificateStore;
+use opcua_types::{DateTime, NotificationMessage, PublishResponse, ResponseHeader};
+use parking_lot::RwLock;
+
+const CHUNK_SIZE: usize = 65535;
+
+fn keep_alive(sequence_number: u32) -> ResponseMessage {
+    PublishResponse {
+        response_header: ResponseHeader::new_good(sequence_number),
+        subscription_id: 1,
+        available_sequence_numbers: None,
+        more_notifications: false,
+        notification_message: NotificationMessage {
+            sequence_number,
+            publish_time: DateTime::now(),
+            notification_data: None,
+        },
+        results: None,
+        diagnostic_infos: None,
+    }
+    .into()
+}
+
+fn small_message_latency(c: &mut Criterion) {
+    let channel = SecureChannel::new(
+        Arc::new(RwLock::new(CertificateStore::new(std::path::Path::new(
+            "./pki",
+        )))),
+        Role::Server,
+        Default::default(),
+    );
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let message = keep_alive(1);
+    let sequence_number = SequenceNumberHandle::new(false);
+    let mut buf = vec![0u8; CHUNK_SIZE + 1024];
+
+    let mut group = c.benchmark_group("small_message_latency");
+    // General path, encoding each message to chunks using a buffer pool.
+    group.bench_function("chunker", |b| {
+        let mut pool = BufferPool::new(16);
+        b.iter(|| {
+            let chunks = Chunker::encode_in_pool(
+                sequence_number.clone(),
+                1,
+                0,
+                CHUNK_SIZE,
+                &channel,
+                &message,
+                &mut pool,
+            )
+            .unwrap();
+            for chunk in chunks {
+                black_box(channel.apply_security(&chunk, &mut buf).unwrap());
+                pool.put(chunk.data);
+            }
+        })
+    });
+    // Fast path, encoding each message directly into a fixed size buffer.
+    group.bench_function("small_message_fast_path", |b| {
+        b.iter(|| {
+            let mut small = [0u8; 1024];
+            let len = Chunker::encode_into(&sequence_number, 1, 0, &channel, &message, &mut small)
+                .unwrap();
+            buf[..len].copy_from_slice(&small[..len]);
+            black_box(&buf[..len]);
+        })
+    });
+    // Full send buffer, which uses the fast path for these messages.
+    group.bench_function("send_buffer", |b| {
+        let mut send_buffer = SendBuffer::new(CHUNK_SIZE, 0, 0, false);
+        b.iter_custom(|iters| {
+            // The send buffer takes ownership of each message, so create them up front.
+            let messages: Vec<_> = (0..iters as u32).map(keep_alive).collect();
+            let start = Instant::now();
+            runtime.block_on(async {
+                let mut sink = tokio::io::sink();
+                for (request_id, message) in messages.into_iter().enumerate() {
+                    send_buffer
+                        .write(request_id as u32, message, &channel)
+                        .unwrap();
+                    while send_buffer.should_encode_chunks() {
+                        send_buffer.encode_next_chunk(&channel).unwrap();
+                        while send_buffer.can_read() {
+                            send_buffer.read_into_async(&mut sink).await.unwrap();
+                        }
+                    }
+                }
+            });
+            start.elapsed()
+        })
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_secs(3));
+    targets = small_message_latency
+}
+criterion_main!(benches);
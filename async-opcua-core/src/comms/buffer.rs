@@ -10,11 +10,17 @@ use tokio::io::AsyncWriteExt;
 use tracing::trace;
 
 use crate::{
-    comms::{chunker::Chunker, message_chunk::MessageChunk, secure_channel::SecureChannel},
+    comms::{
+        buffer_pool::BufferPool,
+        chunker::{Chunker, StreamingMessage},
+        message_chunk::{MessageChunk, MessageIsFinalType},
+        message_chunk_info::ChunkInfo,
+        secure_channel::SecureChannel,
+    },
     Message,
 };
 
-use opcua_types::{Error, SimpleBinaryEncodable, StatusCode};
+use opcua_types::{EncodingResult, Error, SimpleBinaryEncodable, StatusCode, UAString};
 
 use super::{
     sequence_number::SequenceNumberHandle,
@@ -27,9 +33,52 @@ enum SendBufferState {
     Writing,
 }
 
+/// Default maximum number of unused buffers kept for reuse by a send buffer.
+pub const DEFAULT_BUFFER_POOL_SIZE: usize = 16;
+
+/// Messages with an encoded body of at most this many bytes are encoded into a
+/// fixed size buffer, instead of allocating chunks.
+pub const SMALL_MESSAGE_SIZE: usize = 512;
+
+/// Space for the chunk headers in the buffer used for small messages.
+const SMALL_CHUNK_HEADER_SIZE: usize = 64;
+
+/// Messages needing at least this many chunks are encoded incrementally as they are sent,
+/// instead of encoding all chunks up front.
+const MIN_STREAMING_CHUNKS: usize = 4;
+
+/// A small message encoded as a single chunk, without security applied.
+struct SmallChunk {
+    data: [u8; SMALL_MESSAGE_SIZE + SMALL_CHUNK_HEADER_SIZE],
+    len: usize,
+}
+
+impl SmallChunk {
+    /// Decode info about this chunk. This copies the chunk, it is only used
+    /// when aborting messages.
+    fn chunk_info(&self, secure_channel: &SecureChannel) -> EncodingResult<ChunkInfo> {
+        MessageChunk {
+            data: self.data[..self.len].to_vec(),
+        }
+        .chunk_info(secure_channel)
+    }
+}
+
+impl std::fmt::Debug for SmallChunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SmallChunk")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+// Small chunks are stored inline, so that queueing them does not allocate.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 enum PendingPayload {
     Chunk(MessageChunk),
+    Small(SmallChunk),
+    Stream(StreamingMessage),
     Ack(AcknowledgeMessage),
     Error(ErrorMessage),
 }
@@ -50,6 +99,8 @@ pub struct SendBuffer {
     pub max_chunk_count: usize,
     /// Maximum size of each individual chunk.
     pub send_buffer_size: usize,
+    /// Pool of buffers reused when encoding chunks.
+    pool: BufferPool,
 
     state: SendBufferState,
 }
@@ -75,6 +126,7 @@ impl SendBuffer {
             max_message_size,
             max_chunk_count,
             send_buffer_size: buffer_size,
+            pool: BufferPool::new(DEFAULT_BUFFER_POOL_SIZE),
             state: SendBufferState::Writing,
         }
     }
@@ -90,7 +142,48 @@ impl SendBuffer {
         };
 
         let size = match next_chunk {
-            PendingPayload::Chunk(c) => secure_channel.apply_security(&c, self.buffer.get_mut())?,
+            PendingPayload::Chunk(c) => {
+                let size = secure_channel.apply_security(&c, self.buffer.get_mut())?;
+                self.pool.put(c.data);
+                size
+            }
+            PendingPayload::Small(c) => {
+                let data = &c.data[..c.len];
+                if secure_channel.applies_security() {
+                    let mut buf = self.pool.take(c.len);
+                    buf.copy_from_slice(data);
+                    let chunk = MessageChunk { data: buf };
+                    let size = secure_channel.apply_security(&chunk, self.buffer.get_mut())?;
+                    self.pool.put(chunk.data);
+                    size
+                } else {
+                    let dst = self.buffer.get_mut();
+                    if c.len > dst.len() {
+                        return Err(StatusCode::BadEncodingLimitsExceeded);
+                    }
+                    dst[..c.len].copy_from_slice(data);
+                    c.len
+                }
+            }
+            PendingPayload::Stream(mut s) => {
+                let Some(chunk) = s.next_chunk(secure_channel) else {
+                    // The encoder has not produced the next chunk yet.
+                    self.chunks.push_front(PendingPayload::Stream(s));
+                    return Ok(());
+                };
+                // The sequence numbers of the remaining chunks are already reserved,
+                // so if encoding fails here the channel cannot recover.
+                let chunk = chunk.map_err(|e| {
+                    tracing::error!("Failed to encode streamed message: {e}");
+                    StatusCode::from(e)
+                })?;
+                if s.remaining_chunks() > 0 {
+                    self.chunks.push_front(PendingPayload::Stream(s));
+                }
+                let size = secure_channel.apply_security(&chunk, self.buffer.get_mut())?;
+                self.pool.put(chunk.data);
+                size
+            }
             PendingPayload::Ack(a) => {
                 a.encode(&mut self.buffer)?;
                 self.buffer.position() as usize
@@ -106,6 +199,23 @@ impl SendBuffer {
         Ok(())
     }
 
+    /// Wait until the next chunk in the queue can be encoded without blocking.
+    ///
+    /// Large messages are encoded on a blocking task as they are sent, so this should be
+    /// awaited before [SendBuffer::encode_next_chunk], which does nothing if the next chunk
+    /// of such a message is not ready yet. This is cancel safe.
+    pub async fn wait_for_next_chunk(&mut self) {
+        if let Some(PendingPayload::Stream(s)) = self.chunks.front_mut() {
+            s.ready().await;
+        }
+    }
+
+    /// Set the maximum number of unused buffers kept for reuse when encoding chunks.
+    /// Setting this to zero disables reuse of buffers.
+    pub fn set_buffer_pool_size(&mut self, size: usize) {
+        self.pool.set_max_buffers(size);
+    }
+
     /// Set whether we are using legacy sequence numbers or not.
     /// This depends on the active security policy.
     pub fn set_sequence_number_legacy(&mut self, is_legacy: bool) {
@@ -127,23 +237,40 @@ impl SendBuffer {
 
     /// Encode a message to chunks, then write them to the pending message queue.
     ///
-    /// The messages are encrypted as they are sent.
+    /// The messages are encrypted as they are sent. Small messages are encoded without
+    /// allocating, while large messages are encoded incrementally on a blocking task
+    /// as they are sent, to avoid keeping the entire encoded message in memory.
+    /// Large messages are only streamed when called from within a tokio runtime.
     pub fn write(
         &mut self,
         request_id: u32,
-        message: impl Message,
+        message: impl Message + Send + 'static,
         secure_channel: &SecureChannel,
     ) -> Result<u32, Error> {
         trace!("Writing request to buffer");
 
+        let size = message.byte_len(&secure_channel.context().context());
+        // Open secure channel messages may have large security headers, so they
+        // are never encoded as small messages.
+        if size <= SMALL_MESSAGE_SIZE && !message.message_type().is_open_secure_channel() {
+            return self.write_small(request_id, message, secure_channel);
+        }
+        if self.send_buffer_size > 0
+            && size >= self.send_buffer_size * MIN_STREAMING_CHUNKS
+            && tokio::runtime::Handle::try_current().is_ok()
+        {
+            return self.write_streaming(request_id, message, size, secure_channel);
+        }
+
         // Turn message to chunk(s)
-        let chunks = Chunker::encode(
+        let chunks = Chunker::encode_in_pool(
             self.sequence_numbers.clone(),
             request_id,
             self.max_message_size,
             self.send_buffer_size,
             secure_channel,
             &message,
+            &mut self.pool,
         )
         .map_err(|e| e.with_context(Some(request_id), Some(message.request_handle())))?;
 
@@ -168,6 +295,137 @@ impl SendBuffer {
         }
     }
 
+    fn write_small(
+        &mut self,
+        request_id: u32,
+        message: impl Message,
+        secure_channel: &SecureChannel,
+    ) -> Result<u32, Error> {
+        let mut chunk = SmallChunk {
+            data: [0u8; SMALL_MESSAGE_SIZE + SMALL_CHUNK_HEADER_SIZE],
+            len: 0,
+        };
+        chunk.len = Chunker::encode_into(
+            &self.sequence_numbers,
+            request_id,
+            self.max_message_size,
+            secure_channel,
+            &message,
+            &mut chunk.data,
+        )
+        .map_err(|e| e.with_context(Some(request_id), Some(message.request_handle())))?;
+
+        self.sequence_numbers.increment(1);
+        self.chunks.push_back(PendingPayload::Small(chunk));
+        Ok(request_id)
+    }
+
+    fn write_streaming(
+        &mut self,
+        request_id: u32,
+        message: impl Message + Send + 'static,
+        size: usize,
+        secure_channel: &SecureChannel,
+    ) -> Result<u32, Error> {
+        let request_handle = message.request_handle();
+        let stream = Chunker::encode_streaming(
+            self.sequence_numbers.clone(),
+            request_id,
+            self.max_message_size,
+            self.send_buffer_size,
+            secure_channel,
+            message,
+            size,
+        )
+        .map_err(|e| e.with_context(Some(request_id), Some(request_handle)))?;
+
+        let chunk_count = stream.remaining_chunks();
+        if self.max_chunk_count > 0 && chunk_count > self.max_chunk_count {
+            return Err(Error::new(
+                StatusCode::BadCommunicationError,
+                format!(
+                    "Cannot write message since {} chunks exceeds {} chunk limit",
+                    chunk_count, self.max_chunk_count
+                ),
+            )
+            .with_context(Some(request_id), Some(request_handle)));
+        }
+
+        self.sequence_numbers.increment(chunk_count as u32);
+        self.chunks.push_back(PendingPayload::Stream(stream));
+        Ok(request_id)
+    }
+
+    /// Abort a message that is in the process of being sent.
+    ///
+    /// Any chunks belonging to the message that have not yet been encoded are discarded,
+    /// and replaced by an abort chunk containing `error` and `reason`. The sequence numbers
+    /// of the discarded chunks are reused, so that subsequent messages are numbered correctly.
+    /// This requires that no other message has been written after the aborted one.
+    ///
+    /// Returns `false` if there are no pending chunks for the message, meaning
+    /// it has either been fully sent already, or was never written.
+    pub fn write_abort(
+        &mut self,
+        request_id: u32,
+        error: StatusCode,
+        reason: &str,
+        secure_channel: &SecureChannel,
+    ) -> Result<bool, Error> {
+        let mut aborted = None;
+        let mut remaining = VecDeque::with_capacity(self.chunks.len());
+        for payload in std::mem::take(&mut self.chunks) {
+            if let PendingPayload::Stream(stream) = &payload {
+                if stream.request_id() == request_id {
+                    if aborted.is_none() {
+                        aborted = Some((stream.sequence_number(), stream.message_type()));
+                    }
+                    continue;
+                }
+            }
+            // A small message is a single chunk, but it may still be waiting to be sent.
+            let chunk_info = match &payload {
+                PendingPayload::Chunk(chunk) => Some(chunk.chunk_info(secure_channel)?),
+                PendingPayload::Small(chunk) => Some(chunk.chunk_info(secure_channel)?),
+                _ => None,
+            };
+            if let Some(chunk_info) = chunk_info {
+                if chunk_info.sequence_header.request_id == request_id {
+                    if aborted.is_none() {
+                        aborted = Some((
+                            chunk_info.sequence_header.sequence_number,
+                            chunk_info.message_header.message_type,
+                        ));
+                    }
+                    continue;
+                }
+            }
+            remaining.push_back(payload);
+        }
+        self.chunks = remaining;
+
+        let Some((sequence_number, message_type)) = aborted else {
+            return Ok(false);
+        };
+
+        let mut body = Vec::with_capacity(error.byte_len() + 4 + reason.len());
+        error.encode(&mut body)?;
+        UAString::from(reason).encode(&mut body)?;
+        let chunk = MessageChunk::new(
+            sequence_number,
+            request_id,
+            message_type,
+            MessageIsFinalType::FinalError,
+            secure_channel,
+            &body,
+        )?;
+        self.sequence_numbers.set(sequence_number);
+        self.sequence_numbers.increment(1);
+        self.chunks.push_back(PendingPayload::Chunk(chunk));
+
+        Ok(true)
+    }
+
     /// Get the next request ID.
     pub fn next_request_id(&mut self) -> u32 {
         self.last_request_id += 1;
@@ -244,15 +502,18 @@ mod tests {
 
     use parking_lot::RwLock;
 
-    use super::SendBuffer;
+    use super::{PendingPayload, SendBuffer};
 
+    use crate::comms::chunker::Chunker;
+    use crate::comms::message_chunk::{MessageChunk, MessageIsFinalType};
     use crate::comms::secure_channel::{Role, SecureChannel};
+    use crate::comms::sequence_number::SequenceNumberHandle;
     use crate::RequestMessage;
     use opcua_crypto::CertificateStore;
-    use opcua_types::StatusCode;
     use opcua_types::{
         DateTime, NodeId, ReadRequest, ReadValueId, RequestHeader, TimestampsToReturn,
     };
+    use opcua_types::{DecodingOptions, SimpleBinaryDecodable, StatusCode, UAString};
 
     fn get_buffer_and_channel() -> (SendBuffer, SecureChannel) {
         let buffer = SendBuffer::new(8196, 81960, 5, true);
@@ -337,6 +598,83 @@ mod tests {
         assert!(cursor.get_ref().len() > 8196 * 2 && cursor.get_ref().len() < 8196 * 3);
     }
 
+    #[tokio::test]
+    async fn test_buffer_small_message() {
+        // Small messages are encoded directly, and produce the same output as
+        // messages encoded through the chunker.
+        let message = ReadRequest {
+            request_header: RequestHeader::new(&NodeId::null(), &DateTime::null(), 101),
+            max_age: 0.0,
+            timestamps_to_return: TimestampsToReturn::Both,
+            nodes_to_read: Some(vec![ReadValueId {
+                node_id: (1, 1).into(),
+                attribute_id: 1,
+                ..Default::default()
+            }]),
+        };
+        let (mut buffer, channel) = get_buffer_and_channel();
+        let m: RequestMessage = message.into();
+        let chunks = Chunker::encode(
+            SequenceNumberHandle::new(true),
+            1,
+            0,
+            buffer.send_buffer_size,
+            &channel,
+            &m,
+        )
+        .unwrap();
+
+        buffer.write(1, m, &channel).unwrap();
+        assert_eq!(buffer.chunks.len(), 1);
+        assert!(matches!(buffer.chunks[0], PendingPayload::Small(_)));
+        buffer.encode_next_chunk(&channel).unwrap();
+
+        let mut cursor = Cursor::new(Vec::new());
+        buffer.read_into_async(&mut cursor).await.unwrap();
+        assert_eq!(cursor.into_inner(), chunks[0].data);
+    }
+
+    #[tokio::test]
+    async fn test_buffer_pool_reuse() {
+        // Sending messages with and without a buffer pool should produce identical output.
+        let message = |size: u32| -> RequestMessage {
+            ReadRequest {
+                request_header: RequestHeader::new(&NodeId::null(), &DateTime::null(), 101),
+                max_age: 0.0,
+                timestamps_to_return: TimestampsToReturn::Both,
+                nodes_to_read: Some(
+                    (0..size)
+                        .map(|r| ReadValueId {
+                            node_id: (1, r).into(),
+                            attribute_id: 1,
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+            }
+            .into()
+        };
+
+        let mut outputs = Vec::new();
+        for pool_size in [0, 16] {
+            let (mut buffer, channel) = get_buffer_and_channel();
+            buffer.set_buffer_pool_size(pool_size);
+            let mut cursor = Cursor::new(Vec::new());
+            // A large message followed by a smaller one, so the smaller one reuses
+            // buffers previously filled with other data.
+            for (request_id, size) in [(1, 1000), (2, 100)] {
+                buffer.write(request_id, message(size), &channel).unwrap();
+                while buffer.should_encode_chunks() {
+                    buffer.encode_next_chunk(&channel).unwrap();
+                    buffer.read_into_async(&mut cursor).await.unwrap();
+                }
+            }
+            assert_eq!(buffer.pool.is_empty(), pool_size == 0);
+            outputs.push(cursor.into_inner());
+        }
+        assert_eq!(outputs[0], outputs[1]);
+    }
+
     #[test]
     fn test_buffer_too_large_message() {
         // Write a very large message exceeding the max message size.
@@ -387,6 +725,102 @@ mod tests {
         assert_eq!(err.status(), StatusCode::BadCommunicationError);
     }
 
+    #[tokio::test]
+    async fn test_buffer_abort() {
+        // Write a message split into chunks, then abort it after sending the first chunk.
+        let message = ReadRequest {
+            request_header: RequestHeader::new(&NodeId::null(), &DateTime::null(), 101),
+            max_age: 0.0,
+            timestamps_to_return: TimestampsToReturn::Both,
+            nodes_to_read: Some(
+                (0..1000)
+                    .map(|r| ReadValueId {
+                        node_id: (1, r).into(),
+                        attribute_id: 1,
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+        };
+
+        let (mut buffer, channel) = get_buffer_and_channel();
+
+        let m: RequestMessage = message.clone().into();
+        buffer.write(1, m, &channel).unwrap();
+        assert_eq!(buffer.chunks.len(), 3);
+
+        let mut cursor = Cursor::new(Vec::new());
+        buffer.encode_next_chunk(&channel).unwrap();
+        buffer.read_into_async(&mut cursor).await.unwrap();
+
+        assert!(!buffer
+            .write_abort(2, StatusCode::BadRequestCancelledByClient, "", &channel)
+            .unwrap());
+        assert!(buffer
+            .write_abort(
+                1,
+                StatusCode::BadRequestCancelledByClient,
+                "Cancelled",
+                &channel
+            )
+            .unwrap());
+        assert_eq!(buffer.chunks.len(), 1);
+        let PendingPayload::Chunk(abort) = &buffer.chunks[0] else {
+            panic!("Expected chunk");
+        };
+        let info = abort.chunk_info(&channel).unwrap();
+        assert_eq!(info.message_header.is_final, MessageIsFinalType::FinalError);
+        assert_eq!(info.sequence_header.request_id, 1);
+        assert_eq!(info.sequence_header.sequence_number, 2);
+        let mut body = Cursor::new(&abort.data[info.body_offset..]);
+        let options = DecodingOptions::default();
+        assert_eq!(
+            StatusCode::decode(&mut body, &options).unwrap(),
+            StatusCode::BadRequestCancelledByClient
+        );
+        assert_eq!(
+            UAString::decode(&mut body, &options).unwrap().as_ref(),
+            "Cancelled"
+        );
+
+        // The next message continues from the sequence number of the abort chunk.
+        buffer.chunks.clear();
+        let m: RequestMessage = message.into();
+        buffer.write(2, m, &channel).unwrap();
+        let PendingPayload::Chunk(chunk) = &buffer.chunks[0] else {
+            panic!("Expected chunk");
+        };
+        let info = chunk.chunk_info(&channel).unwrap();
+        assert_eq!(info.sequence_header.sequence_number, 3);
+    }
+
+    #[test]
+    fn test_buffer_abort_small() {
+        // A small message that has not been sent yet is replaced by the abort chunk.
+        let (mut buffer, channel) = get_buffer_and_channel();
+        let m: RequestMessage = ReadRequest {
+            request_header: RequestHeader::new(&NodeId::null(), &DateTime::null(), 101),
+            max_age: 0.0,
+            timestamps_to_return: TimestampsToReturn::Both,
+            nodes_to_read: None,
+        }
+        .into();
+        buffer.write(1, m, &channel).unwrap();
+        assert!(matches!(buffer.chunks[0], PendingPayload::Small(_)));
+
+        assert!(buffer
+            .write_abort(1, StatusCode::BadRequestCancelledByClient, "", &channel)
+            .unwrap());
+        assert_eq!(buffer.chunks.len(), 1);
+        let PendingPayload::Chunk(abort) = &buffer.chunks[0] else {
+            panic!("Expected chunk");
+        };
+        let info = abort.chunk_info(&channel).unwrap();
+        assert_eq!(info.message_header.is_final, MessageIsFinalType::FinalError);
+        assert_eq!(info.sequence_header.request_id, 1);
+        assert_eq!(info.sequence_header.sequence_number, 1);
+    }
+
     #[tokio::test]
     async fn test_buffer_read_partial() {
         // Write a large message to the buffer.
@@ -443,4 +877,121 @@ mod tests {
         assert!(!buffer.should_encode_chunks());
         assert!(!buffer.can_read());
     }
+
+    fn large_read_request() -> RequestMessage {
+        ReadRequest {
+            request_header: RequestHeader::new(&NodeId::null(), &DateTime::null(), 101),
+            max_age: 0.0,
+            timestamps_to_return: TimestampsToReturn::Both,
+            nodes_to_read: Some(
+                (0..3000)
+                    .map(|r| ReadValueId {
+                        node_id: (1, r).into(),
+                        attribute_id: 1,
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+        }
+        .into()
+    }
+
+    #[tokio::test]
+    async fn test_buffer_streaming() {
+        // Write a message large enough that it is encoded as it is sent.
+        let (_, channel) = get_buffer_and_channel();
+        let mut buffer = SendBuffer::new(8196, 0, 0, true);
+
+        let message = large_read_request();
+        buffer.write(1, message.clone(), &channel).unwrap();
+        assert_eq!(buffer.chunks.len(), 1);
+        assert!(matches!(buffer.chunks[0], PendingPayload::Stream(_)));
+
+        let mut cursor = Cursor::new(Vec::new());
+        let mut count = 0;
+        while buffer.should_encode_chunks() {
+            buffer.wait_for_next_chunk().await;
+            buffer.encode_next_chunk(&channel).unwrap();
+            while buffer.can_read() {
+                buffer.read_into_async(&mut cursor).await.unwrap();
+            }
+            count += 1;
+        }
+
+        // The result is identical to encoding all chunks up front.
+        let chunks = Chunker::encode(
+            SequenceNumberHandle::new(true),
+            1,
+            0,
+            8196,
+            &channel,
+            &message,
+        )
+        .unwrap();
+        assert_eq!(count, chunks.len());
+        assert!(count > 4);
+        let mut expected = Vec::new();
+        for chunk in &chunks {
+            let mut buf = vec![0u8; 8196 + 1024];
+            let size = channel.apply_security(chunk, &mut buf).unwrap();
+            expected.extend_from_slice(&buf[..size]);
+        }
+        assert_eq!(cursor.get_ref(), &expected);
+
+        // The next message continues after the sequence numbers of the streamed message.
+        let m: RequestMessage = ReadRequest {
+            request_header: RequestHeader::new(&NodeId::null(), &DateTime::null(), 102),
+            max_age: 0.0,
+            timestamps_to_return: TimestampsToReturn::Both,
+            nodes_to_read: None,
+        }
+        .into();
+        buffer.write(2, m, &channel).unwrap();
+        let PendingPayload::Small(chunk) = &buffer.chunks[0] else {
+            panic!("Expected small chunk");
+        };
+        let chunk = MessageChunk {
+            data: chunk.data[..chunk.len].to_vec(),
+        };
+        let info = chunk.chunk_info(&channel).unwrap();
+        assert_eq!(info.sequence_header.sequence_number, count as u32 + 1);
+    }
+
+    #[test]
+    fn test_buffer_streaming_without_runtime() {
+        // Outside a tokio runtime, large messages are encoded up front.
+        let (_, channel) = get_buffer_and_channel();
+        let mut buffer = SendBuffer::new(8196, 0, 0, true);
+
+        buffer.write(1, large_read_request(), &channel).unwrap();
+        assert!(buffer.chunks.len() > 4);
+        assert!(buffer
+            .chunks
+            .iter()
+            .all(|c| matches!(c, PendingPayload::Chunk(_))));
+    }
+
+    #[tokio::test]
+    async fn test_buffer_streaming_abort() {
+        let (_, channel) = get_buffer_and_channel();
+        let mut buffer = SendBuffer::new(8196, 0, 0, true);
+
+        buffer.write(1, large_read_request(), &channel).unwrap();
+        let mut cursor = Cursor::new(Vec::new());
+        buffer.wait_for_next_chunk().await;
+        buffer.encode_next_chunk(&channel).unwrap();
+        buffer.read_into_async(&mut cursor).await.unwrap();
+
+        // Aborting drops the rest of the streamed message.
+        assert!(buffer
+            .write_abort(1, StatusCode::BadRequestCancelledByClient, "", &channel)
+            .unwrap());
+        assert_eq!(buffer.chunks.len(), 1);
+        let PendingPayload::Chunk(abort) = &buffer.chunks[0] else {
+            panic!("Expected chunk");
+        };
+        let info = abort.chunk_info(&channel).unwrap();
+        assert_eq!(info.message_header.is_final, MessageIsFinalType::FinalError);
+        assert_eq!(info.sequence_header.sequence_number, 2);
+    }
 }
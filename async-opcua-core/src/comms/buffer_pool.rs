@@ -0,0 +1,101 @@
+//! Pool of byte buffers, reused when encoding outgoing messages to avoid
+//! allocating new buffers for each chunk.
+
+/// A pool of byte buffers.
+///
+/// Buffers are zeroed when taken from the pool, so no data from a previous
+/// message is ever exposed through a reused buffer.
+#[derive(Debug, Default)]
+pub struct BufferPool {
+    buffers: Vec<Vec<u8>>,
+    max_buffers: usize,
+}
+
+impl BufferPool {
+    /// Create a new buffer pool keeping at most `max_buffers` unused buffers.
+    /// If `max_buffers` is zero, buffers are never reused.
+    pub fn new(max_buffers: usize) -> Self {
+        Self {
+            buffers: Vec::with_capacity(max_buffers),
+            max_buffers,
+        }
+    }
+
+    /// Take a zeroed buffer with length `len` from the pool,
+    /// or allocate a new one if the pool is empty.
+    pub fn take(&mut self, len: usize) -> Vec<u8> {
+        match self.buffers.pop() {
+            Some(mut buf) => {
+                buf.clear();
+                buf.resize(len, 0);
+                buf
+            }
+            None => vec![0u8; len],
+        }
+    }
+
+    /// Return a buffer to the pool. The buffer is dropped if the pool is full.
+    pub fn put(&mut self, mut buf: Vec<u8>) {
+        if self.buffers.len() < self.max_buffers && buf.capacity() > 0 {
+            buf.clear();
+            self.buffers.push(buf);
+        }
+    }
+
+    /// Maximum number of unused buffers kept in the pool.
+    pub fn max_buffers(&self) -> usize {
+        self.max_buffers
+    }
+
+    /// Set the maximum number of unused buffers kept in the pool,
+    /// dropping any buffers exceeding the new limit.
+    pub fn set_max_buffers(&mut self, max_buffers: usize) {
+        self.max_buffers = max_buffers;
+        self.buffers.truncate(max_buffers);
+    }
+
+    /// Number of unused buffers currently in the pool.
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Return `true` if there are no unused buffers in the pool.
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BufferPool;
+
+    #[test]
+    fn reused_buffers_are_zeroed() {
+        let mut pool = BufferPool::new(1);
+        let mut buf = pool.take(16);
+        buf.fill(0xff);
+        let ptr = buf.as_ptr();
+        pool.put(buf);
+        assert_eq!(pool.len(), 1);
+
+        let buf = pool.take(8);
+        assert_eq!(buf.as_ptr(), ptr);
+        assert_eq!(buf, vec![0u8; 8]);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn pool_is_bounded() {
+        let mut pool = BufferPool::new(2);
+        for _ in 0..4 {
+            pool.put(vec![0u8; 4]);
+        }
+        assert_eq!(pool.len(), 2);
+        pool.set_max_buffers(1);
+        assert_eq!(pool.len(), 1);
+
+        let mut pool = BufferPool::new(0);
+        pool.put(vec![0u8; 4]);
+        assert!(pool.is_empty());
+    }
+}
@@ -4,12 +4,18 @@
 
 //! Contains code for turning messages into chunks and chunks into messages.
 
-use std::io::{Read, Write};
+use std::io::{Cursor, Read, Write};
+
+use tokio::sync::mpsc::{channel, error::TryRecvError, Receiver, Sender};
 
 use crate::{
     comms::{
-        message_chunk::{MessageChunk, MessageIsFinalType},
+        buffer_pool::BufferPool,
+        message_chunk::{
+            MessageChunk, MessageChunkHeader, MessageIsFinalType, MESSAGE_CHUNK_HEADER_SIZE,
+        },
         secure_channel::SecureChannel,
+        security_header::SequenceHeader,
         sequence_number::SequenceNumberHandle,
     },
     Message,
@@ -18,7 +24,7 @@ use crate::{
 use opcua_crypto::SecurityPolicy;
 use opcua_types::{
     encoding::BinaryEncodable, node_id::NodeId, status_code::StatusCode, BinaryDecodable,
-    EncodingResult, Error, ObjectId,
+    EncodingResult, Error, ObjectId, SimpleBinaryEncodable,
 };
 use tracing::{debug, error, trace};
 
@@ -104,6 +110,7 @@ impl<'a, T: Iterator<Item = &'a MessageChunk>> Read for ReceiveStream<'a, T> {
 
 struct ChunkingStream<'a> {
     secure_channel: &'a SecureChannel,
+    pool: &'a mut BufferPool,
     chunks: Vec<MessageChunk>,
     expected_chunk_count: usize,
     max_body_per_chunk: usize,
@@ -117,6 +124,7 @@ struct ChunkingStream<'a> {
 }
 
 impl<'a> ChunkingStream<'a> {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         message_type: MessageChunkType,
         secure_channel: &'a SecureChannel,
@@ -125,6 +133,7 @@ impl<'a> ChunkingStream<'a> {
         request_id: u32,
         request_handle: u32,
         sequence_number: SequenceNumberHandle,
+        pool: &'a mut BufferPool,
     ) -> Result<Self, Error> {
         if max_chunk_size > 0 {
             let max_body_per_chunk = MessageChunk::body_size_from_message_size(
@@ -158,10 +167,11 @@ impl<'a> ChunkingStream<'a> {
 
             Ok(Self {
                 secure_channel,
+                next_buf: pool.take(next_buf_size),
+                pool,
                 chunks: Vec::with_capacity(expected_chunk_count),
                 expected_chunk_count,
                 max_body_per_chunk,
-                next_buf: vec![0; next_buf_size],
                 buf_position: 0,
                 is_closed: false,
                 sequence_number,
@@ -175,10 +185,11 @@ impl<'a> ChunkingStream<'a> {
             let next_buf_size = message_size;
             Ok(Self {
                 secure_channel,
+                next_buf: pool.take(next_buf_size),
+                pool,
                 chunks: Vec::with_capacity(expected_chunk_count),
                 expected_chunk_count,
                 max_body_per_chunk,
-                next_buf: vec![0; next_buf_size],
                 buf_position: 0,
                 is_closed: false,
                 sequence_number,
@@ -202,14 +213,16 @@ impl<'a> ChunkingStream<'a> {
             MessageIsFinalType::Intermediate
         };
 
-        let chunk = MessageChunk::new(
+        let chunk = MessageChunk::new_in_pool(
             self.sequence_number.current(),
             self.request_id,
             self.message_type,
             is_final,
             self.secure_channel,
             &buf,
+            self.pool,
         )?;
+        self.pool.put(buf);
         self.sequence_number.increment(1);
         self.chunks.push(chunk);
 
@@ -219,7 +232,7 @@ impl<'a> ChunkingStream<'a> {
             } else {
                 self.max_body_per_chunk
             };
-            self.next_buf = vec![0; next_buf_size];
+            self.next_buf = self.pool.take(next_buf_size);
             self.buf_position = 0;
         }
 
@@ -259,6 +272,158 @@ impl Write for ChunkingStream<'_> {
     }
 }
 
+/// Number of chunk bodies an encoder may get ahead of the transport
+/// when streaming a message.
+const STREAMING_CHANNEL_CAPACITY: usize = 2;
+
+/// Write implementation producing chunk bodies of at most `max_body_per_chunk` bytes,
+/// sending each to the transport once it is full.
+struct ChunkBodyWriter {
+    sender: Sender<EncodingResult<Vec<u8>>>,
+    max_body_per_chunk: usize,
+    buf: Vec<u8>,
+    written: usize,
+}
+
+impl ChunkBodyWriter {
+    fn send(&mut self) -> std::io::Result<()> {
+        let buf = std::mem::replace(&mut self.buf, Vec::with_capacity(self.max_body_per_chunk));
+        self.sender.blocking_send(Ok(buf)).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "Streamed message was dropped",
+            )
+        })
+    }
+}
+
+impl Write for ChunkBodyWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let to_write = buf.len().min(self.max_body_per_chunk - self.buf.len());
+        self.buf.extend_from_slice(&buf[..to_write]);
+        self.written += to_write;
+        if self.buf.len() == self.max_body_per_chunk {
+            self.send()?;
+        }
+        Ok(to_write)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A message being encoded incrementally on a blocking task, producing
+/// the body of one chunk at a time.
+///
+/// The encoder is at most a couple of chunks ahead of the consumer, so the
+/// memory used for the encoded message is bounded by a small multiple of the chunk size,
+/// rather than the full message size. Dropping the message stops the encoder.
+pub struct StreamingMessage {
+    receiver: Receiver<EncodingResult<Vec<u8>>>,
+    next_body: Option<EncodingResult<Vec<u8>>>,
+    sequence_number: SequenceNumberHandle,
+    request_id: u32,
+    message_type: MessageChunkType,
+    remaining_chunks: usize,
+}
+
+impl std::fmt::Debug for StreamingMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamingMessage")
+            .field("request_id", &self.request_id)
+            .field("message_type", &self.message_type)
+            .field("remaining_chunks", &self.remaining_chunks)
+            .finish()
+    }
+}
+
+impl StreamingMessage {
+    /// Number of chunks remaining in the message.
+    pub fn remaining_chunks(&self) -> usize {
+        self.remaining_chunks
+    }
+
+    /// Request ID of the message.
+    pub fn request_id(&self) -> u32 {
+        self.request_id
+    }
+
+    /// Sequence number of the next chunk.
+    pub fn sequence_number(&self) -> u32 {
+        self.sequence_number.current()
+    }
+
+    /// Type of the message.
+    pub fn message_type(&self) -> MessageChunkType {
+        self.message_type
+    }
+
+    /// Wait for the encoder to produce the next chunk of the message.
+    ///
+    /// This is cancel safe.
+    pub async fn ready(&mut self) {
+        if self.next_body.is_none() && self.remaining_chunks > 0 {
+            self.next_body = Some(self.receiver.recv().await.unwrap_or_else(|| {
+                Err(Error::encoding(
+                    "Message encoder stopped before producing all chunks",
+                ))
+            }));
+        }
+    }
+
+    /// Return `true` if the next chunk of the message is ready, so that
+    /// [StreamingMessage::next_chunk] returns it without waiting for the encoder.
+    pub fn is_ready(&mut self) -> bool {
+        if self.next_body.is_none() && self.remaining_chunks > 0 {
+            self.next_body = match self.receiver.try_recv() {
+                Ok(body) => Some(body),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => Some(Err(Error::encoding(
+                    "Message encoder stopped before producing all chunks",
+                ))),
+            };
+        }
+        self.next_body.is_some()
+    }
+
+    /// Get the next chunk of the message, if the encoder has produced it.
+    /// Await [StreamingMessage::ready] to wait for the encoder.
+    ///
+    /// Returns `None` if the next chunk is not ready, or all chunks have been produced.
+    pub fn next_chunk(
+        &mut self,
+        secure_channel: &SecureChannel,
+    ) -> Option<EncodingResult<MessageChunk>> {
+        if !self.is_ready() {
+            return None;
+        }
+        let body = match self.next_body.take()? {
+            Ok(body) => body,
+            Err(e) => {
+                self.remaining_chunks = 0;
+                return Some(Err(e));
+            }
+        };
+        self.remaining_chunks -= 1;
+        let is_final = if self.remaining_chunks == 0 {
+            MessageIsFinalType::Final
+        } else {
+            MessageIsFinalType::Intermediate
+        };
+        let chunk = MessageChunk::new(
+            self.sequence_number.current(),
+            self.request_id,
+            self.message_type,
+            is_final,
+            secure_channel,
+            &body,
+        );
+        self.sequence_number.increment(1);
+        Some(chunk)
+    }
+}
+
 /// The Chunker is responsible for turning messages to chunks and chunks into messages.
 pub struct Chunker;
 
@@ -356,6 +521,28 @@ impl Chunker {
         max_chunk_size: usize,
         secure_channel: &SecureChannel,
         supported_message: &impl Message,
+    ) -> std::result::Result<Vec<MessageChunk>, Error> {
+        Self::encode_in_pool(
+            sequence_number,
+            request_id,
+            max_message_size,
+            max_chunk_size,
+            secure_channel,
+            supported_message,
+            &mut BufferPool::new(0),
+        )
+    }
+
+    /// Encodes a message like [Chunker::encode], taking the buffers used for
+    /// encoding from `pool`.
+    pub fn encode_in_pool(
+        sequence_number: SequenceNumberHandle,
+        request_id: u32,
+        max_message_size: usize,
+        max_chunk_size: usize,
+        secure_channel: &SecureChannel,
+        supported_message: &impl Message,
+        pool: &mut BufferPool,
     ) -> std::result::Result<Vec<MessageChunk>, Error> {
         let security_policy = secure_channel.security_policy();
         if security_policy == SecurityPolicy::Unknown {
@@ -366,30 +553,14 @@ impl Chunker {
         let handle = supported_message.request_handle();
         let ctx_handle = if handle > 0 { Some(handle) } else { None };
 
-        // Client / server stacks should validate the length of a message before sending it and
-        // here makes as good a place as any to do that.
         let ctx_r = secure_channel.context();
         let ctx = ctx_r.context();
-        let mut message_size = supported_message.byte_len(&ctx);
-        if max_message_size > 0 && message_size > max_message_size {
-            error!(
-                "Max message size is {} and message {} exceeds that",
-                max_message_size, message_size
-            );
-            // Client stack should report a BadRequestTooLarge, server BadResponseTooLarge
-            return Err(Error::new(
-                if secure_channel.is_client_role() {
-                    StatusCode::BadRequestTooLarge
-                } else {
-                    StatusCode::BadResponseTooLarge
-                },
-                format!(
-                    "Max message size is {} and message {} exceeds that",
-                    max_message_size, message_size
-                ),
-            )
-            .with_context(ctx_id, ctx_handle));
-        }
+        let mut message_size = Self::check_message_size(
+            max_message_size,
+            secure_channel,
+            supported_message.byte_len(&ctx),
+        )
+        .map_err(|e| e.with_context(ctx_id, ctx_handle))?;
 
         let node_id = supported_message.type_id();
         message_size += node_id.byte_len(&ctx);
@@ -404,6 +575,7 @@ impl Chunker {
             request_id,
             handle,
             sequence_number,
+            pool,
         )?;
 
         node_id.encode(&mut stream, &ctx)?;
@@ -416,6 +588,208 @@ impl Chunker {
         stream.finish()
     }
 
+    /// Encodes a message as a single final chunk directly into `dst`, returning the
+    /// length of the chunk. Like the chunks produced by [Chunker::encode], the chunk is
+    /// written without security applied.
+    ///
+    /// This does not allocate, which makes it suitable for small messages. If the chunk
+    /// does not fit in `dst`, this returns `BadEncodingLimitsExceeded`. The caller is
+    /// responsible for advancing `sequence_number` by one.
+    pub fn encode_into(
+        sequence_number: &SequenceNumberHandle,
+        request_id: u32,
+        max_message_size: usize,
+        secure_channel: &SecureChannel,
+        supported_message: &impl Message,
+        dst: &mut [u8],
+    ) -> std::result::Result<usize, Error> {
+        let ctx_id = Some(request_id);
+        let handle = supported_message.request_handle();
+        let ctx_handle = if handle > 0 { Some(handle) } else { None };
+
+        let ctx_r = secure_channel.context();
+        let ctx = ctx_r.context();
+        let node_id = supported_message.type_id();
+        let body_size = Self::check_message_size(
+            max_message_size,
+            secure_channel,
+            supported_message.byte_len(&ctx),
+        )
+        .map_err(|e| e.with_context(ctx_id, ctx_handle))?
+            + node_id.byte_len(&ctx);
+
+        let message_type = supported_message.message_type();
+        let security_header = secure_channel.make_security_header(message_type);
+        let sequence_header = SequenceHeader {
+            sequence_number: sequence_number.current(),
+            request_id,
+        };
+        let chunk_size = MESSAGE_CHUNK_HEADER_SIZE
+            + SimpleBinaryEncodable::byte_len(&security_header)
+            + SimpleBinaryEncodable::byte_len(&sequence_header)
+            + body_size;
+        if chunk_size > dst.len() {
+            return Err(Error::new(
+                StatusCode::BadEncodingLimitsExceeded,
+                format!(
+                    "Chunk of {} bytes does not fit in buffer of {} bytes",
+                    chunk_size,
+                    dst.len()
+                ),
+            )
+            .with_context(ctx_id, ctx_handle));
+        }
+
+        let chunk_header = MessageChunkHeader {
+            message_type,
+            is_final: MessageIsFinalType::Final,
+            message_size: chunk_size as u32,
+            secure_channel_id: secure_channel.secure_channel_id(),
+        };
+        let mut stream = Cursor::new(&mut dst[..chunk_size]);
+        SimpleBinaryEncodable::encode(&chunk_header, &mut stream)?;
+        SimpleBinaryEncodable::encode(&security_header, &mut stream)?;
+        SimpleBinaryEncodable::encode(&sequence_header, &mut stream)?;
+        node_id.encode(&mut stream, &ctx)?;
+        supported_message
+            .encode(&mut stream, &ctx)
+            .map_err(|e| e.with_context(ctx_id, ctx_handle))?;
+
+        Ok(chunk_size)
+    }
+
+    /// Encodes a message incrementally on a blocking tokio task, returning a [StreamingMessage]
+    /// which produces the chunks of the message one at a time.
+    ///
+    /// `message_size` is the encoded size of the message as returned by `byte_len`,
+    /// passed in so that it is not computed twice for large messages.
+    ///
+    /// Unlike [Chunker::encode], this does not keep all chunks of the message in memory
+    /// at once, which is useful for very large messages. The caller is responsible for
+    /// advancing `sequence_number` by the number of chunks in the message.
+    /// This must be called from within a tokio runtime.
+    pub fn encode_streaming(
+        sequence_number: SequenceNumberHandle,
+        request_id: u32,
+        max_message_size: usize,
+        max_chunk_size: usize,
+        secure_channel: &SecureChannel,
+        supported_message: impl Message + Send + 'static,
+        message_size: usize,
+    ) -> std::result::Result<StreamingMessage, Error> {
+        let ctx_id = Some(request_id);
+        let handle = supported_message.request_handle();
+        let ctx_handle = if handle > 0 { Some(handle) } else { None };
+
+        let runtime = tokio::runtime::Handle::try_current().map_err(|e| {
+            Error::new(
+                StatusCode::BadInternalError,
+                format!("Streaming a message requires a tokio runtime: {e}"),
+            )
+            .with_context(ctx_id, ctx_handle)
+        })?;
+
+        // Encode using a copy of the context, so that the encoder does not hold a lock
+        // on the context of the channel while waiting for the transport.
+        let context = secure_channel.context().clone();
+        let message_size = Self::check_message_size(max_message_size, secure_channel, message_size)
+            .map_err(|e| e.with_context(ctx_id, ctx_handle))?
+            + supported_message.type_id().byte_len(&context.context());
+
+        let message_type = supported_message.message_type();
+        let max_body_per_chunk = if max_chunk_size > 0 {
+            MessageChunk::body_size_from_message_size(message_type, secure_channel, max_chunk_size)
+                .map_err(|_| {
+                    Error::new(
+                        StatusCode::BadTcpInternalError,
+                        format!(
+                            "body_size_from_message_size error for max_chunk_size = {}",
+                            max_chunk_size
+                        ),
+                    )
+                    .with_context(ctx_id, ctx_handle)
+                })?
+        } else {
+            // No chunk size limit, so the message is sent as a single chunk.
+            message_size + 1
+        };
+        // Same as `ChunkingStream`, a message whose size is a multiple of the
+        // chunk body size ends with an empty chunk.
+        let chunk_count = message_size / max_body_per_chunk + 1;
+
+        let (sender, receiver) = channel(STREAMING_CHANNEL_CAPACITY);
+        // The encoder blocks while the transport catches up, so it runs on the
+        // blocking thread pool rather than on a runtime worker.
+        runtime.spawn_blocking(move || {
+            let ctx = context.context();
+            let mut writer = ChunkBodyWriter {
+                sender,
+                max_body_per_chunk,
+                buf: Vec::with_capacity(max_body_per_chunk),
+                written: 0,
+            };
+            let res = supported_message
+                .type_id()
+                .encode(&mut writer, &ctx)
+                .and_then(|_| supported_message.encode(&mut writer, &ctx));
+            // Release the message before sending the last chunk, so that its memory
+            // is freed by the time the message has been sent.
+            drop(supported_message);
+            let res = res.and_then(|_| {
+                if writer.written != message_size {
+                    return Err(Error::encoding(
+                        "Message did not encode to the expected size",
+                    ));
+                }
+                writer.send().map_err(Error::encoding)
+            });
+            if let Err(e) = res {
+                let _ = writer
+                    .sender
+                    .blocking_send(Err(e.with_context(ctx_id, ctx_handle)));
+            }
+        });
+
+        Ok(StreamingMessage {
+            receiver,
+            next_body: None,
+            sequence_number,
+            request_id,
+            message_type,
+            remaining_chunks: chunk_count,
+        })
+    }
+
+    /// Check that the encoded size of a message body does not exceed `max_message_size`,
+    /// returning the size.
+    fn check_message_size(
+        max_message_size: usize,
+        secure_channel: &SecureChannel,
+        message_size: usize,
+    ) -> std::result::Result<usize, Error> {
+        // Client / server stacks should validate the length of a message before sending it and
+        // here makes as good a place as any to do that.
+        if max_message_size > 0 && message_size > max_message_size {
+            error!(
+                "Max message size is {} and message {} exceeds that",
+                max_message_size, message_size
+            );
+            // Client stack should report a BadRequestTooLarge, server BadResponseTooLarge
+            return Err(Error::new(
+                if secure_channel.is_client_role() {
+                    StatusCode::BadRequestTooLarge
+                } else {
+                    StatusCode::BadResponseTooLarge
+                },
+                format!(
+                    "Max message size is {} and message {} exceeds that",
+                    max_message_size, message_size
+                ),
+            ));
+        }
+        Ok(message_size)
+    }
+
     /// Decodes a series of chunks to create a message. The message must be of a `SupportedMessage`
     /// type otherwise an error will occur.
     pub fn decode<T: Message>(
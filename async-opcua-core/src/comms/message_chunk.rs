@@ -15,6 +15,7 @@ use opcua_types::{
 use tracing::{error, trace};
 
 use super::{
+    buffer_pool::BufferPool,
     message_chunk_info::ChunkInfo,
     secure_channel::SecureChannel,
     security_header::{SecurityHeader, SequenceHeader},
@@ -218,6 +219,27 @@ impl MessageChunk {
         is_final: MessageIsFinalType,
         secure_channel: &SecureChannel,
         data: &[u8],
+    ) -> EncodingResult<MessageChunk> {
+        Self::new_in_pool(
+            sequence_number,
+            request_id,
+            message_type,
+            is_final,
+            secure_channel,
+            data,
+            &mut BufferPool::new(0),
+        )
+    }
+
+    /// Create a new message chunk, taking the buffer for the chunk from `pool`.
+    pub fn new_in_pool(
+        sequence_number: u32,
+        request_id: u32,
+        message_type: MessageChunkType,
+        is_final: MessageIsFinalType,
+        secure_channel: &SecureChannel,
+        data: &[u8],
+        pool: &mut BufferPool,
     ) -> EncodingResult<MessageChunk> {
         // security header depends on message type
         let security_header = secure_channel.make_security_header(message_type);
@@ -244,7 +266,7 @@ impl MessageChunk {
             secure_channel_id,
         };
 
-        let mut buf = vec![0u8; message_size];
+        let mut buf = pool.take(message_size);
         let buf_ref = &mut buf as &mut [u8];
         let mut stream = Cursor::new(buf_ref);
         // write chunk header
@@ -6,6 +6,7 @@
 //! and turning those messages into and out of chunks.
 
 pub mod buffer;
+pub mod buffer_pool;
 pub mod chunker;
 pub mod message_chunk;
 pub mod message_chunk_info;
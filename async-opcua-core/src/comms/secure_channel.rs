@@ -643,16 +643,21 @@ impl SecureChannel {
         crate::debug::log_buffer(message, data);
     }
 
+    /// Return `true` if outgoing chunks are signed or encrypted on this channel,
+    /// i.e. if [SecureChannel::apply_security] does more than copy the chunk.
+    pub fn applies_security(&self) -> bool {
+        self.security_policy != SecurityPolicy::None
+            && (self.security_mode == MessageSecurityMode::Sign
+                || self.security_mode == MessageSecurityMode::SignAndEncrypt)
+    }
+
     /// Applies security to a message chunk and yields a encrypted/signed block to be streamed
     pub fn apply_security(
         &self,
         message_chunk: &MessageChunk,
         dst: &mut [u8],
     ) -> Result<usize, StatusCode> {
-        let size = if self.security_policy != SecurityPolicy::None
-            && (self.security_mode == MessageSecurityMode::Sign
-                || self.security_mode == MessageSecurityMode::SignAndEncrypt)
-        {
+        let size = if self.applies_security() {
             let encrypted_data_offset =
                 message_chunk.encrypted_data_offset(&self.decoding_options())?;
 
@@ -4,6 +4,7 @@
 
 //! Provides functions for parsing Urls from strings.
 
+use percent_encoding::percent_decode_str;
 use tracing::error;
 use url::Url;
 
@@ -34,22 +35,58 @@ pub fn url_with_replaced_hostname(url: &str, hostname: &str) -> Result<String, u
     Ok(url.into())
 }
 
+/// Components of a URL normalized for comparison.
+#[derive(PartialEq, Eq)]
+struct NormalizedUrl {
+    scheme: String,
+    host: Option<String>,
+    port: Option<u16>,
+    path: String,
+    query: Option<String>,
+    fragment: Option<String>,
+}
+
+impl NormalizedUrl {
+    /// Parse and normalize a URL. The host is lowercased, the port defaults to 4840,
+    /// percent-encoding is decoded, and trailing slashes are stripped from the path.
+    /// Invalid URLs are logged by `opc_url_from_str`.
+    fn parse(s: &str) -> Option<Self> {
+        let url = opc_url_from_str(s).ok()?;
+        let decode = |v: &str| percent_decode_str(v).decode_utf8_lossy().into_owned();
+        Some(Self {
+            scheme: url.scheme().to_ascii_lowercase(),
+            host: url.host_str().map(|h| decode(h).to_ascii_lowercase()),
+            port: url.port(),
+            path: decode(url.path()).trim_end_matches('/').to_owned(),
+            query: url.query().map(decode),
+            fragment: url.fragment().map(decode),
+        })
+    }
+}
+
+/// Test if the two urls are functionally identical. Differences in host casing,
+/// trailing slashes, the default port and percent-encoding are ignored.
+pub fn url_matches(url1: &str, url2: &str) -> bool {
+    match (NormalizedUrl::parse(url1), NormalizedUrl::parse(url2)) {
+        (Some(url1), Some(url2)) => url1 == url2,
+        _ => false,
+    }
+}
+
 /// Test if the two urls match except for the hostname. Can be used by a server whose endpoint doesn't
 /// exactly match the incoming connection, e.g. 127.0.0.1 vs localhost.
+///
+/// Like [url_matches], differences in trailing slashes and percent-encoding are ignored.
 pub fn url_matches_except_host(url1: &str, url2: &str) -> bool {
-    if let Ok(url1) = opc_url_from_str(url1) {
-        if let Ok(url2) = opc_url_from_str(url2) {
-            return url1.scheme() == url2.scheme() // Scheme must match
-                && url1.path().trim_end_matches("/") == url2.path().trim_end_matches("/") // Path must match, except for trailing /
-                && url1.query() == url2.query() // Scheme and query must match, most OPC-UA endpoints won't have these.
-                && url1.fragment() == url2.fragment();
-        } else {
-            error!("Cannot parse url \"{}\"", url2);
+    match (NormalizedUrl::parse(url1), NormalizedUrl::parse(url2)) {
+        (Some(url1), Some(url2)) => {
+            url1.scheme == url2.scheme // Scheme must match
+                && url1.path == url2.path // Path must match, except for trailing /
+                && url1.query == url2.query // Scheme and query must match, most OPC-UA endpoints won't have these.
+                && url1.fragment == url2.fragment
         }
-    } else {
-        error!("Cannot parse url \"{}\"", url1);
+        _ => false,
     }
-    false
 }
 
 /// Takes an endpoint url and strips off the path and args to leave just the protocol, host & port.
@@ -146,6 +183,24 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn url_matches_normalized() {
+        assert!(url_matches("opc.tcp://Host:4840/", "opc.tcp://host:4840"));
+        assert!(url_matches("opc.tcp://Host/", "opc.tcp://host:4840"));
+        assert!(url_matches(
+            "opc.tcp://host:4840/my%20server/",
+            "opc.tcp://HOST:4840/my server"
+        ));
+        assert!(url_matches("opc.tcp://host/%41bc", "opc.tcp://host/Abc"));
+        assert!(!url_matches("opc.tcp://host:4841", "opc.tcp://host:4840"));
+        assert!(!url_matches("opc.tcp://host/abc", "opc.tcp://host/Abc"));
+        assert!(!url_matches("opc.tcp://host1", "opc.tcp://host2"));
+        assert!(url_matches_except_host(
+            "opc.tcp://localhost/%78yz/",
+            "opc.tcp://127.0.0.1/xyz"
+        ));
+    }
+
     #[test]
     fn server_url_from_endpoint_url_test() {
         assert_eq!(
@@ -34,4 +34,10 @@ pub trait Message: BinaryEncodable + MessageType {
 
     /// Get the type ID of the message.
     fn type_id(&self) -> NodeId;
+
+    /// Get the full encoded size of the message in bytes, including the
+    /// encoded type ID preceding the message body.
+    fn encoded_size(&self, ctx: &opcua_types::Context<'_>) -> usize {
+        self.type_id().byte_len(ctx) + self.byte_len(ctx)
+    }
 }
@@ -143,6 +143,56 @@ fn chunk_multi_encode_decode() {
     assert_eq!(response, new_response);
 }
 
+/// Encode a small message directly into a buffer, and ensure it produces the same
+/// chunk as the general encoding path.
+#[test]
+fn chunk_encode_into() {
+    let _ = Test::setup();
+
+    let (secure_channel, _) = make_secure_channels(
+        MessageSecurityMode::SignAndEncrypt,
+        SecurityPolicy::Basic256Sha256,
+    );
+    for secure_channel in [SecureChannel::new_no_certificate_store(), secure_channel] {
+        let message = make_sample_message();
+        let sequence_number = SequenceNumberHandle::new_at(true, 1000);
+        let chunks = Chunker::encode(
+            sequence_number.clone(),
+            100,
+            0,
+            MIN_CHUNK_SIZE,
+            &secure_channel,
+            &message,
+        )
+        .unwrap();
+        assert_eq!(chunks.len(), 1);
+
+        let mut buf = [0u8; 1024];
+        let len = Chunker::encode_into(
+            &sequence_number,
+            100,
+            0,
+            &secure_channel,
+            &message,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(&buf[..len], &chunks[0].data[..]);
+
+        // The chunk does not fit in a buffer that is too small.
+        let err = Chunker::encode_into(
+            &sequence_number,
+            100,
+            0,
+            &secure_channel,
+            &message,
+            &mut buf[..len - 1],
+        )
+        .unwrap_err();
+        assert_eq!(err.status(), StatusCode::BadEncodingLimitsExceeded);
+    }
+}
+
 /// Encode a large message with multiple chunks. Ensure all but the last chunk is marked intermediate
 /// and the last is marked final.
 #[test]
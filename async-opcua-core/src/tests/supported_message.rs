@@ -1,4 +1,10 @@
-use crate::{RequestMessage, ResponseMessage};
+use opcua_types::{
+    BinaryEncodable, BrowseDescription, BrowseRequest, ContextOwned, DataValue, DateTime, NodeId,
+    ReadRequest, ReadResponse, ReadValueId, RequestHeader, ResponseHeader, ServiceFault,
+    StatusCode, TimestampsToReturn, Variant, ViewDescription,
+};
+
+use crate::{Message, RequestMessage, ResponseMessage};
 
 #[test]
 fn size() {
@@ -13,3 +19,61 @@ fn size() {
     println!("ResponseMessage size = {}", size);
     assert!(size <= 16);
 }
+
+fn assert_encoded_size(message: &impl Message) {
+    let ctx_r = ContextOwned::default();
+    let ctx = ctx_r.context();
+    let mut stream = Vec::new();
+    message.type_id().encode(&mut stream, &ctx).unwrap();
+    message.encode(&mut stream, &ctx).unwrap();
+    assert_eq!(message.encoded_size(&ctx), stream.len());
+}
+
+#[test]
+fn encoded_size() {
+    let read_request: RequestMessage = ReadRequest {
+        request_header: RequestHeader::dummy(),
+        max_age: 0.0,
+        timestamps_to_return: TimestampsToReturn::Both,
+        nodes_to_read: Some(
+            (0..10)
+                .map(|i| ReadValueId::from(NodeId::new(2, format!("node_{i}"))))
+                .collect(),
+        ),
+    }
+    .into();
+    assert_encoded_size(&read_request);
+
+    let browse_request: RequestMessage = BrowseRequest {
+        request_header: RequestHeader::dummy(),
+        view: ViewDescription::default(),
+        requested_max_references_per_node: 100,
+        nodes_to_browse: Some(vec![BrowseDescription::default()]),
+    }
+    .into();
+    assert_encoded_size(&browse_request);
+
+    let read_response: ResponseMessage = ReadResponse {
+        response_header: ResponseHeader::new_good(1),
+        results: Some(
+            (0..1000)
+                .map(|i| DataValue {
+                    value: Some(Variant::from(format!("value {i}"))),
+                    status: Some(StatusCode::Good),
+                    source_timestamp: Some(DateTime::now()),
+                    server_timestamp: Some(DateTime::now()),
+                    ..Default::default()
+                })
+                .collect(),
+        ),
+        diagnostic_infos: None,
+    }
+    .into();
+    assert_encoded_size(&read_response);
+
+    let fault: ResponseMessage = ServiceFault {
+        response_header: ResponseHeader::new_service_result(1, StatusCode::BadTimeout),
+    }
+    .into();
+    assert_encoded_size(&fault);
+}
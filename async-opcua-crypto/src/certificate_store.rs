@@ -30,6 +30,8 @@ const OWN_PRIVATE_KEY_PATH: &str = "private/private.pem";
 const TRUSTED_CERTS_DIR: &str = "trusted";
 /// The directory holding rejected certificates
 const REJECTED_CERTS_DIR: &str = "rejected";
+/// The directory holding certificates pinned on first use
+const PINNED_CERTS_DIR: &str = "pinned";
 
 /// The certificate store manages the storage of a server/client's own certificate & private key
 /// and the trust / rejection of certificates from the other end.
@@ -51,6 +53,9 @@ pub struct CertificateStore {
     /// into the trusted folder if this flag is set. Certs in the trusted folder must still pass
     /// validity checks.
     trust_unknown_certs: bool,
+    /// The first certificate presented by an endpoint can be trusted automatically and pinned,
+    /// so that any other certificate from that endpoint is rejected afterwards.
+    trust_on_first_use: bool,
 }
 
 impl CertificateStore {
@@ -65,6 +70,7 @@ impl CertificateStore {
             check_time: true,
             skip_verify_certs: false,
             trust_unknown_certs: false,
+            trust_on_first_use: false,
         }
     }
 
@@ -130,6 +136,13 @@ impl CertificateStore {
         self.trust_unknown_certs = trust_unknown_certs;
     }
 
+    /// Set `trust_on_first_use` to automatically trust the first certificate
+    /// presented by an endpoint, and reject any other certificate from it afterwards.
+    /// See [`CertificateStore::validate_pinned_cert`].
+    pub fn set_trust_on_first_use(&mut self, trust_on_first_use: bool) {
+        self.trust_on_first_use = trust_on_first_use;
+    }
+
     /// Check expiration time of incoming certificates.
     pub fn set_check_time(&mut self, check_time: bool) {
         self.check_time = check_time;
@@ -338,55 +351,158 @@ impl CertificateStore {
                 error!("Certificate in memory does not match the one on disk {} so cert will automatically be treated as untrusted", cert_path.display());
                 return Err(StatusCode::BadUnexpectedError);
             }
+        }
+
+        self.verify_cert(
+            cert,
+            &cert_file_name,
+            security_policy,
+            hostname,
+            application_uri,
+        )
+    }
 
-            // Check that the certificate is the right length for the security policy
-            match cert.key_length() {
-                Err(_) => {
-                    error!("Cannot read key length from certificate {}", cert_file_name);
+    /// Checks the key length, and unless `skip_verify_certs` is set, the validity time,
+    /// hostname and application uri of a cert that is already known to be trusted.
+    fn verify_cert(
+        &self,
+        cert: &X509,
+        cert_file_name: &str,
+        security_policy: SecurityPolicy,
+        hostname: Option<&str>,
+        application_uri: Option<&str>,
+    ) -> Result<(), StatusCode> {
+        // Check that the certificate is the right length for the security policy
+        match cert.key_length() {
+            Err(_) => {
+                error!("Cannot read key length from certificate {}", cert_file_name);
+                return Err(StatusCode::BadSecurityChecksFailed);
+            }
+            Ok(key_length) => {
+                if !security_policy.is_valid_keylength(key_length) {
+                    warn!(
+                        "Certificate {} has an invalid key length {} for the policy {}",
+                        cert_file_name, key_length, security_policy
+                    );
                     return Err(StatusCode::BadSecurityChecksFailed);
                 }
-                Ok(key_length) => {
-                    if !security_policy.is_valid_keylength(key_length) {
-                        warn!(
-                            "Certificate {} has an invalid key length {} for the policy {}",
-                            cert_file_name, key_length, security_policy
-                        );
-                        return Err(StatusCode::BadSecurityChecksFailed);
-                    }
-                }
             }
+        }
 
-            if self.skip_verify_certs {
-                debug!(
-                    "Skipping additional verifications for certificate {}",
-                    cert_file_name
+        if self.skip_verify_certs {
+            debug!(
+                "Skipping additional verifications for certificate {}",
+                cert_file_name
+            );
+            return Ok(());
+        }
+
+        // Now inspect the cert not before / after values to ensure its validity
+        if self.check_time {
+            use chrono::Utc;
+            let now = Utc::now();
+            cert.is_time_valid(&now)?;
+        }
+
+        // Compare the hostname of the cert against the cert supplied
+        if let Some(hostname) = hostname {
+            cert.is_hostname_valid(hostname)?;
+        }
+
+        // Compare the application / product uri to the supplied application description
+        if let Some(application_uri) = application_uri {
+            cert.is_application_uri_valid(application_uri)?;
+        }
+
+        // Other tests that we might do with trust lists
+        // ... issuer
+        // ... trust (self-signed, ca etc.)
+        // ... revocation
+        Ok(())
+    }
+
+    /// Validates the cert presented by the server at `endpoint_url`.
+    ///
+    /// If `trust_on_first_use` is set, the cert is trusted only if it is the cert pinned for
+    /// the endpoint, and the first cert presented by an endpoint is pinned. The trusted and
+    /// rejected folders are not used for these certs. Otherwise this is the same as
+    /// `validate_or_reject_application_instance_cert`.
+    ///
+    /// # Errors
+    ///
+    /// `BadCertificateUntrusted` if a different cert is pinned for the endpoint, or any
+    /// other non `Good` status code if the cert fails validation.
+    ///
+    pub fn validate_pinned_cert(
+        &self,
+        cert: &X509,
+        endpoint_url: &str,
+        security_policy: SecurityPolicy,
+        hostname: Option<&str>,
+        application_uri: Option<&str>,
+    ) -> Result<(), StatusCode> {
+        if !self.trust_on_first_use {
+            return self.validate_or_reject_application_instance_cert(
+                cert,
+                security_policy,
+                hostname,
+                application_uri,
+            );
+        }
+
+        let cert_file_name = CertificateStore::cert_file_name(cert);
+        let pinned_path = self.pinned_cert_path(endpoint_url);
+        if pinned_path.exists() {
+            let pinned = CertificateStore::read_cert(&pinned_path).map_err(|e| {
+                error!("Cannot read pinned certificate for endpoint {endpoint_url}: {e}");
+                StatusCode::BadCertificateUntrusted
+            })?;
+            if pinned.thumbprint() != cert.thumbprint() {
+                error!(
+                    "Certificate {} does not match the certificate {} pinned for endpoint {}. Remove {} to trust the new certificate",
+                    cert_file_name,
+                    CertificateStore::cert_file_name(&pinned),
+                    endpoint_url,
+                    pinned_path.display()
                 );
-                return Ok(());
+                return Err(StatusCode::BadCertificateUntrusted);
             }
-
-            // Now inspect the cert not before / after values to ensure its validity
-            if self.check_time {
-                use chrono::Utc;
-                let now = Utc::now();
-                cert.is_time_valid(&now)?;
+        } else {
+            warn!(
+                "Trusting certificate {} for endpoint {} on first use, it will be pinned in {}",
+                cert_file_name,
+                endpoint_url,
+                pinned_path.display()
+            );
+            if let Err(e) = CertificateStore::store_cert(cert, &pinned_path, false) {
+                error!("Failed to pin certificate {cert_file_name}: {e}");
+                return Err(StatusCode::BadUnexpectedError);
             }
+        }
 
-            // Compare the hostname of the cert against the cert supplied
-            if let Some(hostname) = hostname {
-                cert.is_hostname_valid(hostname)?;
-            }
+        self.verify_cert(
+            cert,
+            &cert_file_name,
+            security_policy,
+            hostname,
+            application_uri,
+        )
+    }
 
-            // Compare the application / product uri to the supplied application description
-            if let Some(application_uri) = application_uri {
-                cert.is_application_uri_valid(application_uri)?;
+    /// Get the path to the cert pinned for `endpoint_url`. Characters other than ASCII
+    /// letters and digits are percent encoded, so each endpoint URL gets its own file.
+    fn pinned_cert_path(&self, endpoint_url: &str) -> PathBuf {
+        let mut file_name = String::with_capacity(endpoint_url.len());
+        for b in endpoint_url.bytes() {
+            if b.is_ascii_alphanumeric() {
+                file_name.push(b as char);
+            } else {
+                file_name.push_str(&format!("%{b:02X}"));
             }
-
-            // Other tests that we might do with trust lists
-            // ... issuer
-            // ... trust (self-signed, ca etc.)
-            // ... revocation
         }
-        Ok(())
+        let mut path = self.pinned_certs_dir();
+        path.push(format!("{file_name}.der"));
+        path
     }
 
     /// Returns a certificate file name from the cert's issuer and thumbprint fields.
@@ -471,6 +587,13 @@ impl CertificateStore {
         path
     }
 
+    /// Get the path to the dir of certs pinned on first use
+    pub fn pinned_certs_dir(&self) -> PathBuf {
+        let mut path = PathBuf::from(&self.pki_path);
+        path.push(PINNED_CERTS_DIR);
+        path
+    }
+
     /// Write a cert to the rejected directory. If the write succeeds, the function
     /// returns a path to the written file.
     ///
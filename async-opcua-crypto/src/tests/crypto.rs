@@ -196,6 +196,65 @@ fn test_and_reject_thumbprint_mismatch() {
     drop(tmp_dir);
 }
 
+#[test]
+fn trust_on_first_use() {
+    let (tmp_dir, mut cert_store) = make_certificate_store();
+    cert_store.set_trust_on_first_use(true);
+
+    let (cert, _) = make_test_cert_1024();
+    let (cert2, _) = make_test_cert_1024();
+    let endpoint_url = "opc.tcp://testhost:4855/";
+    let validate = |cert_store: &CertificateStore, cert: &X509, endpoint_url: &str| {
+        cert_store.validate_pinned_cert(
+            cert,
+            endpoint_url,
+            SecurityPolicy::Basic128Rsa15,
+            None,
+            None,
+        )
+    };
+
+    // The first cert is trusted and pinned, but only for this endpoint
+    assert!(validate(&cert_store, &cert, endpoint_url).is_ok());
+    assert!(validate(&cert_store, &cert, endpoint_url).is_ok());
+    assert!(!cert_store
+        .trusted_certs_dir()
+        .join(CertificateStore::cert_file_name(&cert))
+        .exists());
+    assert_eq!(
+        cert_store.validate_or_reject_application_instance_cert(
+            &cert,
+            SecurityPolicy::Basic128Rsa15,
+            None,
+            None,
+        ),
+        Err(StatusCode::BadCertificateUntrusted)
+    );
+
+    // Any other cert from the same endpoint is rejected
+    assert_eq!(
+        validate(&cert_store, &cert2, endpoint_url),
+        Err(StatusCode::BadCertificateUntrusted)
+    );
+
+    // Other endpoints pin their own cert, even if the URLs only differ in punctuation
+    assert!(validate(&cert_store, &cert2, "opc.tcp://otherhost:4855/").is_ok());
+    assert!(validate(&cert_store, &cert2, "opc.tcp://testhost:4855_").is_ok());
+    assert_eq!(
+        validate(&cert_store, &cert, "opc.tcp://testhost:4855_"),
+        Err(StatusCode::BadCertificateUntrusted)
+    );
+
+    // Without trust on first use, certs are validated against the trusted folder
+    cert_store.set_trust_on_first_use(false);
+    assert_eq!(
+        validate(&cert_store, &cert2, endpoint_url),
+        Err(StatusCode::BadCertificateUntrusted)
+    );
+
+    drop(tmp_dir);
+}
+
 fn test_asymmetric_encrypt_and_decrypt(
     cert: &X509,
     key: &PrivateKey,
@@ -24,16 +24,19 @@ impl ParsedEventFilter {
             return None;
         }
 
-        let fields: Vec<_> = self
-            .select_clauses
-            .iter()
-            .map(|c| get_field(event, c))
-            .collect();
         Some(EventFieldList {
             client_handle,
-            event_fields: Some(fields),
+            event_fields: Some(Self::select_fields(&self.select_clauses, event)),
         })
     }
+
+    /// Get the values of the given select clauses from the event.
+    pub fn select_fields(
+        select_clauses: &[ParsedSimpleAttributeOperand],
+        event: &dyn Event,
+    ) -> Vec<Variant> {
+        select_clauses.iter().map(|c| get_field(event, c)).collect()
+    }
 }
 
 macro_rules! cmp_op {
@@ -11,7 +11,7 @@ use opcua_types::{
 
 use crate::TypeTree;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// Parsed version of the raw [opcua_types::AttributeOperand]
 pub struct ParsedAttributeOperand {
     /// Node ID of the node to get attribute from.
@@ -26,7 +26,7 @@ pub struct ParsedAttributeOperand {
     pub index_range: NumericRange,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// Parsed version of the raw [SimpleAttributeOperand].
 pub struct ParsedSimpleAttributeOperand {
     /// Node ID of the type definition to get values from.
@@ -39,7 +39,7 @@ pub struct ParsedSimpleAttributeOperand {
     pub index_range: NumericRange,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// Parsed and validated [Operand].
 pub enum ParsedOperand {
     /// Another element in the filter.
@@ -89,7 +89,7 @@ impl ParsedOperand {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// Parsed version of the raw [EventFilter].
 pub struct ParsedEventFilter {
     pub(super) content_filter: ParsedContentFilter,
@@ -106,9 +106,19 @@ impl ParsedEventFilter {
     ) -> (EventFilterResult, Result<Self, StatusCode>) {
         validate(raw, type_tree)
     }
+
+    /// Get the where clause of this event filter.
+    pub fn content_filter(&self) -> &ParsedContentFilter {
+        &self.content_filter
+    }
+
+    /// Get the select clauses of this event filter.
+    pub fn select_clauses(&self) -> &[ParsedSimpleAttributeOperand] {
+        &self.select_clauses
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// Parsed version of the raw [ContentFilter].
 pub struct ParsedContentFilter {
     pub(super) elements: Vec<ParsedContentFilterElement>,
@@ -144,7 +154,7 @@ impl ParsedContentFilter {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// Element of a parsed content filter.
 pub struct ParsedContentFilterElement {
     pub(super) operator: FilterOperator,
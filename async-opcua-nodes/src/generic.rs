@@ -111,12 +111,15 @@ pub fn new_node_from_attributes(
             } else {
                 DataValue::default()
             },
+            value_shared: false,
+            shared_value: None,
             access_level: masked_or_default!(AttributeId::AccessLevel, a, access_level),
             user_access_level: masked_or_default!(
                 AttributeId::UserAccessLevel,
                 a,
                 user_access_level
             ),
+            access_level_ex: 0,
             array_dimensions: masked_or_default!(AttributeId::ArrayDimensions, a, array_dimensions),
             minimum_sampling_interval: masked_or_default_opt!(
                 AttributeId::MinimumSamplingInterval,
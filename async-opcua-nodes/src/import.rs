@@ -39,6 +39,9 @@ pub trait NodeSetImport {
 
     /// Create an iterator over items imported from the nodeset.
     /// This will usually be lazy.
+    ///
+    /// Items may be returned in any order. References to nodes that have not been
+    /// imported yet are allowed, so parents do not need to be loaded before children.
     fn load<'a>(
         &'a self,
         namespaces: &'a NodeSetNamespaceMapper,
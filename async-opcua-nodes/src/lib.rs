@@ -8,6 +8,7 @@ use bitflags::bitflags;
 mod events;
 mod generic;
 mod import;
+mod node_key;
 mod references;
 mod type_tree;
 #[cfg(feature = "xml")]
@@ -22,6 +23,7 @@ pub use generic::new_node_from_attributes;
 pub use import::{ImportedItem, ImportedReference, NodeSetImport, NodeSetNamespaceMapper};
 pub use method::{Method, MethodBuilder};
 pub use node::{HasNodeId, Node, NodeBase, NodeType};
+pub use node_key::NodeKey;
 pub use object::{Object, ObjectBuilder};
 pub use object_type::{ObjectType, ObjectTypeBuilder};
 pub use opcua_types::NamespaceMap;
@@ -31,7 +33,7 @@ pub use references::{Reference, ReferenceRef, References};
 pub use type_tree::{
     DefaultTypeTree, TypeProperty, TypePropertyInverseRef, TypeTree, TypeTreeNode,
 };
-pub use variable::{Variable, VariableBuilder};
+pub use variable::{SharedDataValue, Variable, VariableBuildError, VariableBuilder};
 pub use variable_type::{VariableType, VariableTypeBuilder};
 pub use view::{View, ViewBuilder};
 
@@ -66,10 +68,85 @@ pub trait NodeInsertTarget {
     ) -> bool;
 }
 
+/// A child node created by a node builder, such as the `InputArguments` property
+/// of a method, returned by the `build_with_children` method of the builder.
+#[derive(Debug)]
+pub struct ChildNode {
+    /// The child node.
+    pub node: NodeType,
+    /// References from the child node, as the target node, the reference type,
+    /// and the direction of the reference.
+    pub references: Vec<(NodeId, NodeId, ReferenceDirection)>,
+}
+
+impl ChildNode {
+    /// Insert the child node and its references into the address space.
+    pub fn insert(self, address_space: &mut impl NodeInsertTarget) -> bool {
+        let references = self
+            .references
+            .iter()
+            .map(|v| (&v.0, &v.1, v.2))
+            .collect::<Vec<_>>();
+        address_space.insert(self.node, Some(references.as_slice()))
+    }
+}
+
+/// Collects the child nodes created by a node builder, instead of inserting them.
+#[derive(Default)]
+pub(crate) struct ChildNodes(pub(crate) Vec<ChildNode>);
+
+impl NodeInsertTarget for ChildNodes {
+    fn insert<'a>(
+        &mut self,
+        node: impl Into<NodeType>,
+        references: Option<&'a [(&'a NodeId, &NodeId, ReferenceDirection)]>,
+    ) -> bool {
+        self.0.push(ChildNode {
+            node: node.into(),
+            references: references
+                .unwrap_or_default()
+                .iter()
+                .map(|(target, ref_type, dir)| ((*target).clone(), (*ref_type).clone(), *dir))
+                .collect(),
+        });
+        true
+    }
+}
+
+/// Extra state kept by a node builder, inserted into the address space
+/// after the node itself. Most builders have no extra state.
+pub(crate) trait NodeBuilderExtra: Default {
+    /// Insert any nodes described by this state, after the node with ID `node_id`
+    /// has been inserted.
+    fn insert(self, _node_id: &NodeId, _address_space: &mut impl NodeInsertTarget) {}
+
+    /// Return `true` if this state describes no extra nodes.
+    fn is_empty(&self) -> bool {
+        true
+    }
+}
+
+impl NodeBuilderExtra for () {}
+
+/// Get the node ID of a child node created by a builder for the node with ID `parent`.
+/// This is a string node ID in the same namespace as the parent, for example
+/// `ns=2;s=MyMethod_InputArguments` for a child `InputArguments` of `ns=2;s=MyMethod`.
+pub(crate) fn child_node_id(parent: &NodeId, name: &str) -> NodeId {
+    let base = match &parent.identifier {
+        opcua_types::Identifier::String(s) => s.as_ref().to_owned(),
+        opcua_types::Identifier::Numeric(n) => n.to_string(),
+        r => r.to_string(),
+    };
+    NodeId::new(parent.namespace, format!("{base}_{name}"))
+}
+
 // A macro for creating builders. Builders can be used for more conveniently creating objects,
 // variables etc.
 macro_rules! node_builder_impl {
     ( $node_builder_ty:ident, $node_ty:ident ) => {
+        node_builder_impl!($node_builder_ty, $node_ty, ());
+    };
+    ( $node_builder_ty:ident, $node_ty:ident, $extra_ty:ty ) => {
         use opcua_types::{LocalizedText, NodeId, QualifiedName, ReferenceTypeId};
         use tracing::trace;
         use $crate::ReferenceDirection;
@@ -80,6 +157,7 @@ macro_rules! node_builder_impl {
         pub struct $node_builder_ty {
             node: $node_ty,
             references: Vec<(NodeId, NodeId, ReferenceDirection)>,
+            extra: $extra_ty,
         }
 
         impl $node_builder_ty {
@@ -93,6 +171,7 @@ macro_rules! node_builder_impl {
                 Self {
                     node: $node_ty::default(),
                     references: Vec::with_capacity(10),
+                    extra: Default::default(),
                 }
                 .node_id(node_id.clone())
                 .browse_name(browse_name)
@@ -184,37 +263,61 @@ macro_rules! node_builder_impl {
             /// Yields a built node. This function will panic if the node is invalid. Note that
             /// calling this function discards any references for the node, so there is no purpose
             /// in adding references if you intend to call this method.
+            ///
+            /// Any child nodes the builder would create, such as method arguments or the
+            /// properties of an analog item, are discarded as well.
+            /// Use [`Self::build_with_children`] to keep them.
             pub fn build(self) -> $node_ty {
-                if self.is_valid() {
-                    self.node
-                } else {
+                if !$crate::NodeBuilderExtra::is_empty(&self.extra) {
+                    tracing::warn!(
+                        "Discarding child nodes of built node, node id = {:?}",
+                        self.node.base.node_id()
+                    );
+                }
+                self.build_with_children().0
+            }
+
+            /// Yields a built node, and the child nodes the builder would create on insert,
+            /// such as method arguments or the properties of an analog item. Each child node
+            /// has a reference to the built node. This function will panic if the node is invalid.
+            ///
+            /// Like [`Self::build`], this discards any references for the node itself.
+            pub fn build_with_children(self) -> ($node_ty, Vec<$crate::ChildNode>) {
+                if !self.is_valid() {
                     panic!(
                         "The node is not valid, node id = {:?}",
                         self.node.base.node_id()
                     );
                 }
+                let mut children = $crate::ChildNodes::default();
+                $crate::NodeBuilderExtra::insert(self.extra, self.node.node_id(), &mut children);
+                (self.node, children.0)
             }
 
             /// Inserts the node into the address space, including references. This function
             /// will panic if the node is in an invalid state.
             pub fn insert(self, address_space: &mut impl crate::NodeInsertTarget) -> bool {
-                if self.is_valid() {
-                    if !self.references.is_empty() {
-                        let references = self
-                            .references
-                            .iter()
-                            .map(|v| (&v.0, &v.1, v.2))
-                            .collect::<Vec<_>>();
-                        address_space.insert(self.node, Some(references.as_slice()))
-                    } else {
-                        address_space.insert(self.node, None)
-                    }
-                } else {
+                if !self.is_valid() {
                     panic!(
                         "The node is not valid, node id = {:?}",
                         self.node.base.node_id()
                     );
                 }
+                let node_id = self.node.node_id().clone();
+                let inserted = if !self.references.is_empty() {
+                    let references = self
+                        .references
+                        .iter()
+                        .map(|v| (&v.0, &v.1, v.2))
+                        .collect::<Vec<_>>();
+                    address_space.insert(self.node, Some(references.as_slice()))
+                } else {
+                    address_space.insert(self.node, None)
+                };
+                if inserted {
+                    $crate::NodeBuilderExtra::insert(self.extra, &node_id, address_space);
+                }
+                inserted
             }
         }
     };
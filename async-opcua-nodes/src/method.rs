@@ -6,12 +6,12 @@
 
 use opcua_types::{
     Argument, AttributeId, AttributesMask, DataEncoding, DataTypeId, DataValue, ExtensionObject,
-    MethodAttributes, NumericRange, StatusCode, TimestampsToReturn, VariableTypeId, Variant,
-    VariantScalarTypeId,
+    MethodAttributes, NumericRange, StatusCode, TimestampsToReturn, UAString, VariableTypeId,
+    Variant, VariantScalarTypeId,
 };
 use tracing::error;
 
-use crate::{FromAttributesError, NodeInsertTarget};
+use crate::{child_node_id, FromAttributesError, NodeBuilderExtra, NodeInsertTarget};
 
 use super::{
     base::Base,
@@ -19,11 +19,109 @@ use super::{
     variable::VariableBuilder,
 };
 
-node_builder_impl!(MethodBuilder, Method);
+node_builder_impl!(MethodBuilder, Method, MethodArguments);
 node_builder_impl_component_of!(MethodBuilder);
 node_builder_impl_generates_event!(MethodBuilder);
 
+/// Arguments added to a method builder with `input_argument` and `output_argument`,
+/// inserted as properties once the method has been inserted.
+#[derive(Default)]
+struct MethodArguments {
+    input: Vec<Argument>,
+    output: Vec<Argument>,
+}
+
+impl MethodArguments {
+    /// Get the node ID of an argument property of the method with ID `method_id`.
+    /// This is a string node ID in the same namespace as the method, for example
+    /// `ns=2;s=MyMethod_InputArguments` for a method with ID `ns=2;s=MyMethod`.
+    fn property_id(method_id: &NodeId, args_name: &str) -> NodeId {
+        child_node_id(method_id, args_name)
+    }
+}
+
+impl NodeBuilderExtra for MethodArguments {
+    fn is_empty(&self) -> bool {
+        self.input.is_empty() && self.output.is_empty()
+    }
+
+    fn insert(self, node_id: &NodeId, address_space: &mut impl NodeInsertTarget) {
+        for (args_name, arguments) in [
+            ("InputArguments", self.input),
+            ("OutputArguments", self.output),
+        ] {
+            if !arguments.is_empty() {
+                MethodBuilder::args_builder(
+                    &Self::property_id(node_id, args_name),
+                    node_id,
+                    args_name,
+                    &arguments,
+                )
+                .insert(address_space);
+            }
+        }
+    }
+}
+
 impl MethodBuilder {
+    /// Add an input argument to the method. When the method is inserted, an `InputArguments`
+    /// property is created containing all arguments added this way, with a node ID derived
+    /// from the node ID of the method, e.g. `ns=2;s=MyMethod_InputArguments`.
+    ///
+    /// Servers use the `InputArguments` property to check the number of arguments
+    /// passed by clients when the method is called.
+    ///
+    /// The property is only created by [`MethodBuilder::insert`] and
+    /// [`MethodBuilder::build_with_children`].
+    /// Do not combine this with [`MethodBuilder::input_args`].
+    pub fn input_argument(
+        mut self,
+        name: impl Into<UAString>,
+        data_type: impl Into<NodeId>,
+        value_rank: i32,
+        description: impl Into<LocalizedText>,
+    ) -> Self {
+        self.extra
+            .input
+            .push(Self::argument(name, data_type, value_rank, description));
+        self
+    }
+
+    /// Add an output argument to the method. When the method is inserted, an `OutputArguments`
+    /// property is created containing all arguments added this way, with a node ID derived
+    /// from the node ID of the method, e.g. `ns=2;s=MyMethod_OutputArguments`.
+    ///
+    /// The property is only created by [`MethodBuilder::insert`] and
+    /// [`MethodBuilder::build_with_children`].
+    /// Do not combine this with [`MethodBuilder::output_args`].
+    pub fn output_argument(
+        mut self,
+        name: impl Into<UAString>,
+        data_type: impl Into<NodeId>,
+        value_rank: i32,
+        description: impl Into<LocalizedText>,
+    ) -> Self {
+        self.extra
+            .output
+            .push(Self::argument(name, data_type, value_rank, description));
+        self
+    }
+
+    fn argument(
+        name: impl Into<UAString>,
+        data_type: impl Into<NodeId>,
+        value_rank: i32,
+        description: impl Into<LocalizedText>,
+    ) -> Argument {
+        Argument {
+            name: name.into(),
+            data_type: data_type.into(),
+            value_rank,
+            array_dimensions: None,
+            description: description.into(),
+        }
+    }
+
     /// Specify output arguments from the method. This will create an OutputArguments
     /// variable child of the method which describes the out parameters.
     pub fn output_args(
@@ -84,16 +182,24 @@ impl MethodBuilder {
         address_space: &mut impl NodeInsertTarget,
         arguments: &[Argument],
     ) {
-        let fn_node_id = self.node.node_id();
+        Self::args_builder(node_id, self.node.node_id(), args_name, arguments)
+            .insert(address_space);
+    }
+
+    fn args_builder(
+        node_id: &NodeId,
+        fn_node_id: &NodeId,
+        args_name: &str,
+        arguments: &[Argument],
+    ) -> VariableBuilder {
         let args_value = Self::args_to_variant(arguments);
         VariableBuilder::new(node_id, args_name, args_name)
-            .property_of(fn_node_id)
+            .property_of(fn_node_id.clone())
             .has_type_definition(VariableTypeId::PropertyType)
             .data_type(DataTypeId::Argument)
             .value_rank(1)
             .array_dimensions(&[arguments.len() as u32])
             .value(args_value)
-            .insert(address_space);
     }
 }
 
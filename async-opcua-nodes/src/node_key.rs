@@ -0,0 +1,115 @@
+//! Contains `NodeKey`, a compact key type for node IDs.
+
+use std::hash::{Hash, Hasher};
+
+use hashbrown::Equivalent;
+use opcua_types::{Identifier, NodeId};
+
+/// Compact representation of a [`NodeId`], used as the key in large maps and sets,
+/// such as the node map and reference index of an address space.
+///
+/// A `NodeId` has room for string, guid and opaque identifiers, which makes it 40 bytes.
+/// Most nodes have numeric node IDs, which only need 6 bytes, so `NodeKey` stores those
+/// inline and boxes any other node ID, making it 16 bytes.
+///
+/// A `NodeKey` hashes and compares like the node ID it was created from, so hashbrown
+/// maps keyed by `NodeKey` can be queried with a `&NodeId`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeKey(Repr);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Repr {
+    Numeric(u16, u32),
+    Other(Box<NodeId>),
+}
+
+impl NodeKey {
+    /// Get the node ID this key was created from.
+    pub fn to_node_id(&self) -> NodeId {
+        match &self.0 {
+            Repr::Numeric(namespace, id) => NodeId::new(*namespace, *id),
+            Repr::Other(node_id) => (**node_id).clone(),
+        }
+    }
+}
+
+impl From<NodeId> for NodeKey {
+    fn from(value: NodeId) -> Self {
+        match value.identifier {
+            Identifier::Numeric(id) => Self(Repr::Numeric(value.namespace, id)),
+            _ => Self(Repr::Other(Box::new(value))),
+        }
+    }
+}
+
+impl From<&NodeId> for NodeKey {
+    fn from(value: &NodeId) -> Self {
+        match value.identifier {
+            Identifier::Numeric(id) => Self(Repr::Numeric(value.namespace, id)),
+            _ => Self(Repr::Other(Box::new(value.clone()))),
+        }
+    }
+}
+
+impl Hash for NodeKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Must hash the same as the equivalent node ID.
+        match &self.0 {
+            Repr::Numeric(namespace, id) => NodeId {
+                namespace: *namespace,
+                identifier: Identifier::Numeric(*id),
+            }
+            .hash(state),
+            Repr::Other(node_id) => node_id.hash(state),
+        }
+    }
+}
+
+impl Equivalent<NodeKey> for NodeId {
+    fn equivalent(&self, key: &NodeKey) -> bool {
+        match (&key.0, &self.identifier) {
+            (Repr::Numeric(namespace, id), Identifier::Numeric(other)) => {
+                *namespace == self.namespace && id == other
+            }
+            (Repr::Other(node_id), _) => **node_id == *self,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use hashbrown::HashMap;
+    use opcua_types::{ByteString, Guid, NodeId};
+
+    use super::NodeKey;
+
+    #[test]
+    fn node_key() {
+        assert_eq!(std::mem::size_of::<NodeKey>(), 16);
+
+        let ids = [
+            NodeId::new(0, 85),
+            NodeId::new(3, 85),
+            NodeId::new(3, "85"),
+            NodeId::new(
+                3,
+                Guid::from_str("72962b91-fa75-4ae6-8d28-b404dc7daf63").unwrap(),
+            ),
+            NodeId::new(3, ByteString::from(vec![1u8, 2, 3])),
+        ];
+        let map: HashMap<NodeKey, usize> = ids
+            .iter()
+            .enumerate()
+            .map(|(idx, id)| (NodeKey::from(id), idx))
+            .collect();
+        for (idx, id) in ids.iter().enumerate() {
+            assert_eq!(map.get(id), Some(&idx));
+            assert_eq!(NodeKey::from(id).to_node_id(), *id);
+        }
+        assert!(map.get(&NodeId::new(1, 85)).is_none());
+        assert!(map.get(&NodeId::new(0, "85")).is_none());
+    }
+}
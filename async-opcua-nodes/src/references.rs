@@ -1,7 +1,7 @@
 use hashbrown::{Equivalent, HashMap, HashSet};
 use opcua_types::{BrowseDirection, NodeId};
 
-use crate::{ImportedReference, ReferenceDirection, TypeTree};
+use crate::{ImportedReference, NodeKey, ReferenceDirection, TypeTree};
 
 #[derive(PartialEq, Eq, Clone, Debug, Hash)]
 /// Owned OPC-UA reference.
@@ -52,9 +52,9 @@ pub struct ReferenceRef<'a> {
 /// Structure for storing and accessing OPC-UA references.
 pub struct References {
     /// References by source node ID.
-    by_source: HashMap<NodeId, HashSet<Reference>>,
+    by_source: HashMap<NodeKey, HashSet<Reference>>,
     /// References by target node ID.
-    by_target: HashMap<NodeId, HashSet<Reference>>,
+    by_target: HashMap<NodeKey, HashSet<Reference>>,
 }
 
 impl References {
@@ -99,7 +99,7 @@ impl References {
 
         let forward_refs = match self.by_source.get_mut(source_node) {
             Some(r) => r,
-            None => self.by_source.entry(source_node.clone()).or_default(),
+            None => self.by_source.entry(source_node.into()).or_default(),
         };
 
         let reference_type = reference_type.into();
@@ -114,7 +114,7 @@ impl References {
 
         let inverse_refs = match self.by_target.get_mut(target_node) {
             Some(r) => r,
-            None => self.by_target.entry(target_node.clone()).or_default(),
+            None => self.by_target.entry(target_node.into()).or_default(),
         };
 
         inverse_refs.insert(Reference {
@@ -150,7 +150,7 @@ impl References {
 
         let forward_refs = match self.by_source.get_mut(&source) {
             Some(r) => r,
-            None => self.by_source.entry(source.clone()).or_default(),
+            None => self.by_source.entry((&source).into()).or_default(),
         };
 
         if !forward_refs.insert(Reference {
@@ -163,7 +163,7 @@ impl References {
 
         let inverse_refs = match self.by_target.get_mut(&target) {
             Some(r) => r,
-            None => self.by_target.entry(target).or_default(),
+            None => self.by_target.entry((&target).into()).or_default(),
         };
 
         inverse_refs.insert(Reference {
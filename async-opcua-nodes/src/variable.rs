@@ -4,32 +4,251 @@
 
 //! Contains the implementation of `Variable` and `VariableBuilder`.
 
-use std::convert::Into;
+use std::{convert::Into, sync::Arc};
 
 use opcua_types::{
-    AttributeId, AttributesMask, DataEncoding, DataTypeId, DataValue, DateTime, NumericRange,
-    StatusCode, TimestampsToReturn, TryFromVariant, VariableAttributes, Variant,
+    AccessLevelExType, AttributeId, AttributesMask, DataEncoding, DataTypeId, DataValue, DateTime,
+    EUInformation, ExtensionObject, NumericRange, Range, StatusCode, TimestampsToReturn,
+    TryFromVariant, VariableAttributes, VariableTypeId, Variant,
 };
 use tracing::error;
 
-use crate::FromAttributesError;
+use crate::{child_node_id, FromAttributesError, NodeBuilderExtra, NodeInsertTarget};
 
 use super::base::Base;
 use super::{AccessLevel, Node, NodeBase};
 
 // This is a builder object for constructing variable nodes programmatically.
 
-node_builder_impl!(VariableBuilder, Variable);
+node_builder_impl!(VariableBuilder, Variable, TypedProperties);
 node_builder_impl_component_of!(VariableBuilder);
 node_builder_impl_property_of!(VariableBuilder);
 
+/// Properties required by variable types such as `AnalogItemType` or
+/// `TwoStateDiscreteType`, added to a variable builder and inserted once
+/// the variable has been inserted.
+#[derive(Default)]
+struct TypedProperties {
+    eu_range: Option<Range>,
+    engineering_units: Option<EUInformation>,
+    true_state: Option<LocalizedText>,
+    false_state: Option<LocalizedText>,
+    enum_strings: Option<Vec<LocalizedText>>,
+}
+
+impl TypedProperties {
+    fn insert_property(
+        node_id: &NodeId,
+        name: &str,
+        data_type: DataTypeId,
+        value: impl Into<Variant>,
+        address_space: &mut impl NodeInsertTarget,
+    ) {
+        let value = value.into();
+        let builder = VariableBuilder::new(&child_node_id(node_id, name), name, name)
+            .property_of(node_id.clone())
+            .has_type_definition(VariableTypeId::PropertyType)
+            .data_type(data_type);
+        let builder = if let Variant::Array(a) = &value {
+            builder
+                .value_rank(1)
+                .array_dimensions(&[a.values.len() as u32])
+        } else {
+            builder
+        };
+        builder.value(value).insert(address_space);
+    }
+}
+
+impl NodeBuilderExtra for TypedProperties {
+    fn is_empty(&self) -> bool {
+        self.eu_range.is_none()
+            && self.engineering_units.is_none()
+            && self.true_state.is_none()
+            && self.false_state.is_none()
+            && self.enum_strings.is_none()
+    }
+
+    fn insert(self, node_id: &NodeId, address_space: &mut impl NodeInsertTarget) {
+        if let Some(eu_range) = self.eu_range {
+            Self::insert_property(
+                node_id,
+                "EURange",
+                DataTypeId::Range,
+                ExtensionObject::from_message(eu_range),
+                address_space,
+            );
+        }
+        if let Some(engineering_units) = self.engineering_units {
+            Self::insert_property(
+                node_id,
+                "EngineeringUnits",
+                DataTypeId::EUInformation,
+                ExtensionObject::from_message(engineering_units),
+                address_space,
+            );
+        }
+        if let Some(true_state) = self.true_state {
+            Self::insert_property(
+                node_id,
+                "TrueState",
+                DataTypeId::LocalizedText,
+                true_state,
+                address_space,
+            );
+        }
+        if let Some(false_state) = self.false_state {
+            Self::insert_property(
+                node_id,
+                "FalseState",
+                DataTypeId::LocalizedText,
+                false_state,
+                address_space,
+            );
+        }
+        if let Some(enum_strings) = self.enum_strings {
+            Self::insert_property(
+                node_id,
+                "EnumStrings",
+                DataTypeId::LocalizedText,
+                enum_strings,
+                address_space,
+            );
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+/// Error returned when building a variable with [VariableBuilder::try_build]
+/// or [VariableBuilder::try_insert].
+pub enum VariableBuildError {
+    /// The node is missing mandatory attributes, such as a data type.
+    #[error("The node is not valid, node id = {0}")]
+    InvalidNode(NodeId),
+    /// The value rank is not a valid value rank.
+    #[error("Invalid value rank {0}")]
+    InvalidValueRank(i32),
+    /// The number of array dimensions, either set explicitly or given by
+    /// the value, is not allowed by the value rank.
+    #[error("Value rank {value_rank} does not allow {dimensions} array dimensions")]
+    InconsistentArrayDimensions {
+        /// Value rank of the variable.
+        value_rank: i32,
+        /// Number of array dimensions.
+        dimensions: usize,
+    },
+    /// The value is a scalar, but the value rank requires an array.
+    #[error("Value rank {0} requires an array, but the value is a scalar")]
+    ScalarValue(i32),
+    /// The builder has properties that can only be created by inserting the node,
+    /// such as an `EURange`, so the node cannot be built on its own.
+    #[error("The node has properties that can only be created by insert, node id = {0}")]
+    HasProperties(NodeId),
+}
+
+/// Check whether `dimensions` array dimensions are allowed by `value_rank`.
+fn value_rank_allows(value_rank: i32, dimensions: usize) -> bool {
+    match value_rank {
+        // Any
+        -2 => true,
+        // ScalarOrOneDimension
+        -3 => dimensions <= 1,
+        // Scalar
+        -1 => dimensions == 0,
+        // OneOrMoreDimensions
+        0 => dimensions >= 1,
+        n => dimensions == n as usize,
+    }
+}
+
 impl VariableBuilder {
+    /// Check that the value rank, array dimensions and value of the variable
+    /// are consistent with each other.
+    fn check_value_rank(&self) -> Result<(), VariableBuildError> {
+        let node = &self.node;
+        let value_rank = node.value_rank;
+        if value_rank < -3 {
+            return Err(VariableBuildError::InvalidValueRank(value_rank));
+        }
+        // Array dimensions are only meaningful for arrays, an empty list on a
+        // scalar variable is treated as no array dimensions.
+        let dimensions = node.array_dimensions.as_ref().map(|d| d.len());
+        if let Some(dimensions) = dimensions.filter(|d| *d > 0) {
+            if !value_rank_allows(value_rank, dimensions) {
+                return Err(VariableBuildError::InconsistentArrayDimensions {
+                    value_rank,
+                    dimensions,
+                });
+            }
+        }
+        match node.variant() {
+            None | Some(Variant::Empty) => (),
+            Some(Variant::Array(array)) => {
+                let dimensions = array.dimensions.as_ref().map(|d| d.len()).unwrap_or(1);
+                if !value_rank_allows(value_rank, dimensions) {
+                    return Err(VariableBuildError::InconsistentArrayDimensions {
+                        value_rank,
+                        dimensions,
+                    });
+                }
+            }
+            Some(_) => {
+                if value_rank >= 0 {
+                    return Err(VariableBuildError::ScalarValue(value_rank));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Yields a built node, or an error if the node is invalid, or if its value rank,
+    /// array dimensions and value are inconsistent. For example, a variable with value rank
+    /// `1` can not have two array dimensions, and a variable with a scalar value cannot have
+    /// a positive value rank. Variables with properties added by methods such as
+    /// [VariableBuilder::eu_range] are also an error, since the properties would be
+    /// discarded, use [VariableBuilder::build_with_children] or insert the variable instead.
+    ///
+    /// Like [VariableBuilder::build], this discards any references for the node.
+    pub fn try_build(self) -> Result<Variable, VariableBuildError> {
+        if !self.is_valid() {
+            return Err(VariableBuildError::InvalidNode(self.node.node_id().clone()));
+        }
+        self.check_value_rank()?;
+        if !NodeBuilderExtra::is_empty(&self.extra) {
+            return Err(VariableBuildError::HasProperties(
+                self.node.node_id().clone(),
+            ));
+        }
+        Ok(self.node)
+    }
+
+    /// Inserts the node into the address space, including references, or returns
+    /// an error if the node is invalid. See [VariableBuilder::try_build] for the
+    /// checks that are made.
+    pub fn try_insert(
+        self,
+        address_space: &mut impl NodeInsertTarget,
+    ) -> Result<bool, VariableBuildError> {
+        if !self.is_valid() {
+            return Err(VariableBuildError::InvalidNode(self.node.node_id().clone()));
+        }
+        self.check_value_rank()?;
+        Ok(self.insert(address_space))
+    }
+
     /// Sets the value of the variable.
     pub fn value(mut self, value: impl Into<Variant>) -> Self {
         let _ = self.node.set_value(&NumericRange::None, value);
         self
     }
 
+    /// Store the value of the variable behind an `Arc`, so that it can be read
+    /// without cloning it. See [Variable::set_value_shared].
+    pub fn shared_value(mut self) -> Self {
+        self.node.set_value_shared(true);
+        self
+    }
+
     /// Sets the data type of the variable.
     pub fn data_type(mut self, data_type: impl Into<NodeId>) -> Self {
         self.node.set_data_type(data_type);
@@ -48,6 +267,13 @@ impl VariableBuilder {
         self
     }
 
+    /// Sets the extended access level for the variable. The lower 8 bits are the
+    /// same as the access level, so this also sets the access level.
+    pub fn access_level_ex(mut self, access_level_ex: AccessLevelExType) -> Self {
+        self.node.set_access_level_ex(access_level_ex);
+        self
+    }
+
     /// Sets the user access level for the variable.
     pub fn user_access_level(mut self, user_access_level: AccessLevel) -> Self {
         self.node.set_user_access_level(user_access_level);
@@ -118,6 +344,76 @@ impl VariableBuilder {
         )
     }
 
+    /// Set the `EURange` of the variable, which is the range of values it is expected
+    /// to have under normal operation. When the variable is inserted, an `EURange` property
+    /// is created with a node ID derived from the node ID of the variable,
+    /// e.g. `ns=2;s=MyVariable_EURange`.
+    ///
+    /// This also sets the type definition of the variable to `AnalogItemType`,
+    /// replacing any type definition set earlier. The property is only created by
+    /// [VariableBuilder::insert] and [VariableBuilder::build_with_children].
+    pub fn eu_range(mut self, low: f64, high: f64) -> Self {
+        self.extra.eu_range = Some(Range { low, high });
+        self.analog_item_type()
+    }
+
+    /// Set the `EngineeringUnits` of the variable. When the variable is inserted, an
+    /// `EngineeringUnits` property is created with a node ID derived from the node ID
+    /// of the variable, e.g. `ns=2;s=MyVariable_EngineeringUnits`.
+    ///
+    /// This also sets the type definition of the variable to `AnalogItemType`,
+    /// replacing any type definition set earlier. Note that `AnalogItemType`
+    /// requires an `EURange`, see [`VariableBuilder::eu_range`]. The property is only
+    /// created by [VariableBuilder::insert] and [VariableBuilder::build_with_children].
+    pub fn engineering_units(mut self, engineering_units: EUInformation) -> Self {
+        self.extra.engineering_units = Some(engineering_units);
+        self.analog_item_type()
+    }
+
+    fn analog_item_type(self) -> Self {
+        self.replace_type_definition(VariableTypeId::AnalogItemType)
+    }
+
+    /// Make the variable a `TwoStateDiscreteType` with data type `Boolean`. When the variable
+    /// is inserted, `TrueState` and `FalseState` properties are created with node IDs derived
+    /// from the node ID of the variable, e.g. `ns=2;s=MyVariable_TrueState`.
+    ///
+    /// This replaces any type definition set earlier. The properties are only created by
+    /// [VariableBuilder::insert] and [VariableBuilder::build_with_children].
+    pub fn two_state_discrete(
+        mut self,
+        true_text: impl Into<LocalizedText>,
+        false_text: impl Into<LocalizedText>,
+    ) -> Self {
+        self.extra.true_state = Some(true_text.into());
+        self.extra.false_state = Some(false_text.into());
+        self.data_type(DataTypeId::Boolean)
+            .replace_type_definition(VariableTypeId::TwoStateDiscreteType)
+    }
+
+    /// Make the variable a `MultiStateDiscreteType`, where the value of the variable is an index
+    /// into `states`. When the variable is inserted, an `EnumStrings` property is created with
+    /// a node ID derived from the node ID of the variable, e.g. `ns=2;s=MyVariable_EnumStrings`.
+    ///
+    /// The data type of the variable must be an unsigned integer type, and is set to `UInt32`
+    /// unless another data type has already been set. This replaces any type definition set earlier.
+    /// The property is only created by [VariableBuilder::insert] and [VariableBuilder::build_with_children].
+    pub fn multi_state_discrete(mut self, states: &[&str]) -> Self {
+        self.extra.enum_strings = Some(states.iter().map(|s| LocalizedText::from(*s)).collect());
+        if self.node.data_type().is_null() {
+            self.node.set_data_type(DataTypeId::UInt32);
+        }
+        self.replace_type_definition(VariableTypeId::MultiStateDiscreteType)
+    }
+
+    fn replace_type_definition(mut self, type_id: VariableTypeId) -> Self {
+        let type_definition_id: NodeId = ReferenceTypeId::HasTypeDefinition.into();
+        self.references.retain(|(_, ref_type, direction)| {
+            *ref_type != type_definition_id || *direction != ReferenceDirection::Forward
+        });
+        self.has_type_definition(type_id)
+    }
+
     /// Add a reference to the variable indicating it has a modelling rule of another node.
     pub fn has_modelling_rule<T>(self, type_id: T) -> Self
     where
@@ -131,6 +427,73 @@ impl VariableBuilder {
     }
 }
 
+/// A data value read from a variable, where the value may be shared with the variable.
+/// Returned by [Variable::shared_value].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SharedDataValue {
+    /// The value.
+    pub value: Option<Arc<Variant>>,
+    /// The status associated with the value.
+    pub status: Option<StatusCode>,
+    /// The source timestamp associated with the value.
+    pub source_timestamp: Option<DateTime>,
+    /// The number of 10 picosecond intervals for the source timestamp.
+    pub source_picoseconds: Option<u16>,
+    /// The server timestamp associated with the value.
+    pub server_timestamp: Option<DateTime>,
+    /// The number of 10 picosecond intervals for the server timestamp.
+    pub server_picoseconds: Option<u16>,
+}
+
+impl SharedDataValue {
+    fn with_timestamps(mut self, timestamps_to_return: TimestampsToReturn) -> Self {
+        match timestamps_to_return {
+            TimestampsToReturn::Source => {
+                self.server_timestamp = None;
+                self.server_picoseconds = None;
+            }
+            TimestampsToReturn::Server => {
+                self.source_timestamp = None;
+                self.source_picoseconds = None;
+            }
+            TimestampsToReturn::Neither => {
+                self.server_timestamp = None;
+                self.source_timestamp = None;
+                self.server_picoseconds = None;
+                self.source_picoseconds = None;
+            }
+            _ => (),
+        }
+        self
+    }
+
+    /// Convert this into an owned [DataValue]. The value is only cloned
+    /// if it is still shared with the variable, or with other readers.
+    pub fn into_data_value(self) -> DataValue {
+        DataValue {
+            value: self.value.map(Arc::unwrap_or_clone),
+            status: self.status,
+            source_timestamp: self.source_timestamp,
+            source_picoseconds: self.source_picoseconds,
+            server_timestamp: self.server_timestamp,
+            server_picoseconds: self.server_picoseconds,
+        }
+    }
+}
+
+impl From<DataValue> for SharedDataValue {
+    fn from(value: DataValue) -> Self {
+        Self {
+            value: value.value.map(Arc::new),
+            status: value.status,
+            source_timestamp: value.source_timestamp,
+            source_picoseconds: value.source_picoseconds,
+            server_timestamp: value.server_timestamp,
+            server_picoseconds: value.server_picoseconds,
+        }
+    }
+}
+
 // Note we use derivative builder macro so we can skip over the value getter / setter
 
 /// A `Variable` is a type of node within the `AddressSpace`.
@@ -141,8 +504,13 @@ pub struct Variable {
     pub(super) historizing: bool,
     pub(super) value_rank: i32,
     pub(super) value: DataValue,
+    /// If this is `true`, the value is stored in `shared_value`, and `value.value`
+    /// is always `None`.
+    pub(super) value_shared: bool,
+    pub(super) shared_value: Option<Arc<Variant>>,
     pub(super) access_level: u8,
     pub(super) user_access_level: u8,
+    pub(super) access_level_ex: u32,
     pub(super) array_dimensions: Option<Vec<u32>>,
     pub(super) minimum_sampling_interval: Option<f64>,
 }
@@ -155,8 +523,11 @@ impl Default for Variable {
             historizing: false,
             value_rank: -1,
             value: Variant::Empty.into(),
+            value_shared: false,
+            shared_value: None,
             access_level: AccessLevel::CURRENT_READ.bits(),
             user_access_level: AccessLevel::CURRENT_READ.bits(),
+            access_level_ex: 0,
             array_dimensions: None,
             minimum_sampling_interval: None,
         }
@@ -188,6 +559,7 @@ impl Node for Variable {
             AttributeId::AccessLevel => Some(self.access_level().bits().into()),
             AttributeId::UserAccessLevel => Some(self.user_access_level().bits().into()),
             // Optional attributes
+            AttributeId::AccessLevelEx => Some((self.access_level_ex().bits() as u32).into()),
             AttributeId::ArrayDimensions => {
                 self.array_dimensions().map(|v| Variant::from(v).into())
             }
@@ -254,6 +626,14 @@ impl Node for Variable {
                     Err(StatusCode::BadTypeMismatch)
                 }
             }
+            AttributeId::AccessLevelEx => {
+                if let Variant::UInt32(v) = value {
+                    self.set_access_level_ex(AccessLevelExType::from_bits_truncate(v as i32));
+                    Ok(())
+                } else {
+                    Err(StatusCode::BadTypeMismatch)
+                }
+            }
             AttributeId::ArrayDimensions => {
                 let array_dimensions = <Vec<u32>>::try_from_variant(value);
                 if let Ok(array_dimensions) = array_dimensions {
@@ -328,8 +708,11 @@ impl Variable {
             historizing,
             value_rank,
             value,
+            value_shared: false,
+            shared_value: None,
             access_level,
             user_access_level,
+            access_level_ex: 0,
             array_dimensions,
             minimum_sampling_interval,
         }
@@ -472,7 +855,7 @@ impl Variable {
         };
 
         // Get the value
-        if let Some(ref value) = data_value.value {
+        if let Some(value) = self.variant() {
             match value.range_of(index_range) {
                 Ok(value) => {
                     result.value = Some(value);
@@ -548,15 +931,15 @@ impl Variable {
         source_timestamp: &DateTime,
     ) -> Result<(), StatusCode> {
         if matches!(index_range, NumericRange::None) {
-            self.value.value = Some(value);
+            self.replace_variant(Some(value));
             self.value.status = Some(status_code);
             self.value.server_timestamp = Some(*server_timestamp);
             self.value.source_timestamp = Some(*source_timestamp);
             return Ok(());
         }
 
-        match self.value.value {
-            Some(ref mut full_value) => {
+        match self.variant_mut() {
+            Some(full_value) => {
                 // Overwrite a partial section of the value
                 full_value.set_range_of(index_range, &value)?;
                 self.value.status = Some(status_code);
@@ -579,7 +962,7 @@ impl Variable {
     where
         V: Into<Variant>,
     {
-        self.value.value = Some(value.into());
+        self.replace_variant(Some(value.into()));
         self.value.status = Some(status_code);
         self.value.server_timestamp = Some(*server_timestamp);
         self.value.source_timestamp = Some(*source_timestamp);
@@ -587,8 +970,85 @@ impl Variable {
     }
 
     /// Sets the variable type's `DataValue`
-    pub fn set_data_value(&mut self, value: DataValue) {
+    pub fn set_data_value(&mut self, mut value: DataValue) {
+        let variant = value.value.take();
         self.value = value;
+        self.replace_variant(variant);
+    }
+
+    /// Set whether the value of this variable is stored behind an `Arc`.
+    ///
+    /// Reading a shared value with [Variable::shared_value] only clones the `Arc`, which
+    /// is much cheaper than cloning a large value, and lets callers materialize an owned copy
+    /// after releasing any locks on the address space. Writing to part of a shared value
+    /// clones it if it is currently being read.
+    pub fn set_value_shared(&mut self, shared: bool) {
+        if shared == self.value_shared {
+            return;
+        }
+        let variant = if shared {
+            self.value.value.take()
+        } else {
+            self.shared_value.take().map(Arc::unwrap_or_clone)
+        };
+        self.value_shared = shared;
+        self.replace_variant(variant);
+    }
+
+    /// Get whether the value of this variable is stored behind an `Arc`.
+    pub fn is_value_shared(&self) -> bool {
+        self.value_shared
+    }
+
+    /// Read the value of the variable as a [SharedDataValue]. If the value is shared and
+    /// no index range is given, this only clones the `Arc` holding the value, otherwise the
+    /// value or the requested range of the value is copied.
+    pub fn shared_value(
+        &self,
+        timestamps_to_return: TimestampsToReturn,
+        index_range: &NumericRange,
+    ) -> SharedDataValue {
+        let value = match (&self.shared_value, index_range) {
+            (Some(value), NumericRange::None) => SharedDataValue {
+                value: Some(value.clone()),
+                status: self.value.status,
+                source_timestamp: self.value.source_timestamp,
+                source_picoseconds: self.value.source_picoseconds,
+                server_timestamp: self.value.server_timestamp,
+                server_picoseconds: self.value.server_picoseconds,
+            },
+            _ => SharedDataValue::from(self.value(
+                TimestampsToReturn::Both,
+                index_range,
+                &DataEncoding::Binary,
+                0.0,
+            )),
+        };
+        value.with_timestamps(timestamps_to_return)
+    }
+
+    fn variant(&self) -> Option<&Variant> {
+        if self.value_shared {
+            self.shared_value.as_deref()
+        } else {
+            self.value.value.as_ref()
+        }
+    }
+
+    fn variant_mut(&mut self) -> Option<&mut Variant> {
+        if self.value_shared {
+            self.shared_value.as_mut().map(Arc::make_mut)
+        } else {
+            self.value.value.as_mut()
+        }
+    }
+
+    fn replace_variant(&mut self, variant: Option<Variant>) {
+        if self.value_shared {
+            self.shared_value = variant.map(Arc::new);
+        } else {
+            self.value.value = variant;
+        }
     }
 
     /// Gets the minimum sampling interval, if the attribute was set
@@ -638,6 +1098,23 @@ impl Variable {
         self.access_level = access_level.bits();
     }
 
+    /// Get the extended access level of the variable. The lower 8 bits are always
+    /// equal to the access level, the remaining bits are only set through
+    /// [Variable::set_access_level_ex].
+    pub fn access_level_ex(&self) -> AccessLevelExType {
+        AccessLevelExType::from_bits_truncate(
+            ((self.access_level_ex & !0xFF) | self.access_level as u32) as i32,
+        )
+    }
+
+    /// Set the extended access level of the variable. This also sets the
+    /// access level to the lower 8 bits of `access_level_ex`.
+    pub fn set_access_level_ex(&mut self, access_level_ex: AccessLevelExType) {
+        let bits = access_level_ex.bits() as u32;
+        self.access_level = AccessLevel::from_bits_truncate(bits as u8).bits();
+        self.access_level_ex = bits;
+    }
+
     /// Test if the variable is user readable.
     pub fn is_user_readable(&self) -> bool {
         self.user_access_level().contains(AccessLevel::CURRENT_READ)
@@ -532,7 +532,7 @@ impl NodeSetImport for NodeSet2Import {
 mod tests {
     use opcua_types::{
         DataTypeId, EUInformation, ExtensionObject, LocalizedText, NamespaceMap,
-        NodeSetNamespaceMapper, QualifiedName, Variant,
+        NodeSetNamespaceMapper, QualifiedName, Variant, VariantScalarTypeId,
     };
 
     use crate::{NodeBase, NodeSetImport, NodeType};
@@ -585,6 +585,15 @@ mod tests {
       </ExtensionObject>
     </Value>
   </UAVariable>
+  <UAVariable NodeId="ns=1;i=3" BrowseName="1:My Variants" DataType="i=24" ValueRank="1">
+    <DisplayName>My Variants</DisplayName>
+    <Value>
+      <ListOfVariant>
+        <Variant><Int32>1</Int32></Variant>
+        <Variant><String>two</String></Variant>
+      </ListOfVariant>
+    </Value>
+  </UAVariable>
 </UANodeSet>"#;
 
     #[test]
@@ -598,7 +607,7 @@ mod tests {
         let mut map = NodeSetNamespaceMapper::new(&mut ns);
         import.register_namespaces(&mut map);
         let nodes: Vec<_> = import.load(&map).collect();
-        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes.len(), 3);
         let node = &nodes[0];
         let NodeType::Object(o) = &node.node else {
             panic!("Unexpected node type");
@@ -625,5 +634,20 @@ mod tests {
                 }
             )))
         );
+
+        let node = &nodes[2];
+        let NodeType::Variable(v) = &node.node else {
+            panic!("Unexpected node type");
+        };
+        assert_eq!(
+            v.value.value,
+            Some(Variant::from((
+                VariantScalarTypeId::Variant,
+                vec![
+                    Variant::Variant(Box::new(Variant::Int32(1))),
+                    Variant::Variant(Box::new(Variant::from("two"))),
+                ]
+            )))
+        );
     }
 }
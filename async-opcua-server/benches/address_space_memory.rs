@@ -0,0 +1,205 @@
+//! Measures the heap memory used by maps keyed by a large number of numeric node IDs,
+//! comparing the compact `NodeKey` used by the address space against plain `NodeId` keys,
+//! and the memory used by a whole address space with that many nodes.
+//!
+//! The measured value is bytes allocated, not time, so criterion reports it in bytes.
+//!
+//! Run with `cargo bench -p async-opcua-server --bench address_space_memory`.
+
+// `criterion_group!` generates an undocumented public function.
+#![allow(missing_docs)]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use criterion::{
+    criterion_group, criterion_main,
+    measurement::{Measurement, ValueFormatter},
+    Criterion, Throughput,
+};
+use hashbrown::HashMap;
+use opcua_nodes::NodeKey;
+use opcua_server::address_space::{AddressSpace, ObjectBuilder, ReferenceDirection};
+use opcua_types::{NodeId, ReferenceTypeId};
+
+/// Allocator that keeps track of the number of bytes currently allocated.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Criterion measurement of the number of bytes allocated.
+struct Allocated;
+
+impl Measurement for Allocated {
+    type Intermediate = usize;
+    type Value = usize;
+
+    fn start(&self) -> usize {
+        ALLOCATED.load(Ordering::Relaxed)
+    }
+
+    fn end(&self, start: usize) -> usize {
+        ALLOCATED.load(Ordering::Relaxed).saturating_sub(start)
+    }
+
+    fn add(&self, v1: &usize, v2: &usize) -> usize {
+        v1 + v2
+    }
+
+    fn zero(&self) -> usize {
+        0
+    }
+
+    fn to_f64(&self, value: &usize) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &BytesFormatter
+    }
+}
+
+struct BytesFormatter;
+
+impl BytesFormatter {
+    fn scale(typical: f64, values: &mut [f64]) -> &'static str {
+        let (denominator, unit) = if typical < 1024.0 {
+            (1.0, "B")
+        } else if typical < 1024.0 * 1024.0 {
+            (1024.0, "KiB")
+        } else {
+            (1024.0 * 1024.0, "MiB")
+        };
+        for val in values {
+            *val /= denominator;
+        }
+        unit
+    }
+}
+
+impl ValueFormatter for BytesFormatter {
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        Self::scale(typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        // Report bytes per node.
+        let count = match throughput {
+            Throughput::Elements(n) | Throughput::Bytes(n) | Throughput::BytesDecimal(n) => *n,
+        };
+        for val in values {
+            *val /= count as f64;
+        }
+        "B/node"
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "B"
+    }
+}
+
+const NODE_COUNT: u32 = 1_000_000;
+
+/// Measure the bytes allocated by `build` while the value it returns is alive.
+/// Memory use is deterministic, so this builds the value once, and reports
+/// the same amount for each iteration.
+fn allocated_by<T>(iters: u64, build: impl Fn() -> T) -> usize {
+    let start = Allocated.start();
+    let value = build();
+    let used = Allocated.end(start);
+    drop(black_box(value));
+    used * iters as usize
+}
+
+fn node_ids() -> impl Iterator<Item = NodeId> {
+    (0..NODE_COUNT).map(|i| NodeId::new(1, i))
+}
+
+fn address_space_memory(c: &mut Criterion<Allocated>) {
+    let mut group = c.benchmark_group("address_space_memory");
+    group
+        .sample_size(10)
+        .warm_up_time(Duration::from_millis(100))
+        .measurement_time(Duration::from_secs(30))
+        .throughput(Throughput::Elements(NODE_COUNT.into()));
+
+    // The key type used before `NodeKey`, as a baseline.
+    group.bench_function("node_id_keys", |b| {
+        b.iter_custom(|iters| {
+            allocated_by(iters, || {
+                node_ids()
+                    .enumerate()
+                    .map(|(i, id)| (id, i))
+                    .collect::<HashMap<NodeId, usize>>()
+            })
+        })
+    });
+    group.bench_function("node_key_keys", |b| {
+        b.iter_custom(|iters| {
+            allocated_by(iters, || {
+                node_ids()
+                    .enumerate()
+                    .map(|(i, id)| (NodeKey::from(id), i))
+                    .collect::<HashMap<NodeKey, usize>>()
+            })
+        })
+    });
+    group.bench_function("address_space", |b| {
+        b.iter_custom(|iters| {
+            allocated_by(iters, || {
+                let mut address_space = AddressSpace::new();
+                address_space.add_namespace("urn:bench", 1);
+                let root = NodeId::new(1, "Root");
+                ObjectBuilder::new(&root, "Root", "Root").insert(&mut address_space);
+                for id in node_ids() {
+                    ObjectBuilder::new(&id, "Node", "Node")
+                        .reference(
+                            root.clone(),
+                            ReferenceTypeId::Organizes,
+                            ReferenceDirection::Inverse,
+                        )
+                        .insert(&mut address_space);
+                }
+                address_space
+            })
+        })
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().with_measurement(Allocated);
+    targets = address_space_memory
+}
+criterion_main!(benches);
@@ -0,0 +1,106 @@
+//! Measures the time taken by several threads concurrently reading a large
+//! array variable, with and without a shared value, and how much time
+//! is spent acquiring and holding the address space lock.
+//!
+//! `read` measures the wall time until all threads are done, while `lock_held`
+//! measures the time each thread spends acquiring and holding the lock.
+//!
+//! Run with `cargo bench -p async-opcua-server --bench concurrent_reads`.
+
+// `criterion_group!` generates an undocumented public function.
+#![allow(missing_docs)]
+
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use opcua_core::sync::RwLock;
+use opcua_server::address_space::{AddressSpace, NodeType, VariableBuilder};
+use opcua_types::{DataEncoding, DataTypeId, NodeId, NumericRange, TimestampsToReturn};
+
+const ARRAY_LENGTH: usize = 1_000_000;
+const THREADS: usize = 8;
+
+fn make_address_space(shared: bool) -> AddressSpace {
+    let mut address_space = AddressSpace::new();
+    address_space.add_namespace("urn:bench", 1);
+    let mut builder = VariableBuilder::new(&NodeId::new(1, "Array"), "Array", "Array")
+        .data_type(DataTypeId::Double)
+        .value_rank(1)
+        .value(vec![1.0f64; ARRAY_LENGTH]);
+    if shared {
+        builder = builder.shared_value();
+    }
+    builder.insert(&mut address_space);
+    address_space
+}
+
+/// Read the variable `reads` times on each of [`THREADS`] threads. Returns the time until
+/// all threads are done, and the average time each thread spent acquiring and holding the lock.
+fn run(
+    address_space: &Arc<RwLock<AddressSpace>>,
+    shared: bool,
+    reads: u64,
+) -> (Duration, Duration) {
+    let node_id = NodeId::new(1, "Array");
+    let start = Instant::now();
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let address_space = address_space.clone();
+            let node_id = node_id.clone();
+            thread::spawn(move || {
+                let mut locked = Duration::ZERO;
+                for _ in 0..reads {
+                    let lock_start = Instant::now();
+                    let value = {
+                        let address_space = address_space.read();
+                        let Some(NodeType::Variable(v)) = address_space.find(&node_id) else {
+                            panic!("Variable is missing");
+                        };
+                        if shared {
+                            v.shared_value(TimestampsToReturn::Both, &NumericRange::None)
+                        } else {
+                            v.value(
+                                TimestampsToReturn::Both,
+                                &NumericRange::None,
+                                &DataEncoding::Binary,
+                                0.0,
+                            )
+                            .into()
+                        }
+                    };
+                    locked += lock_start.elapsed();
+                    std::hint::black_box(value.into_data_value());
+                }
+                locked
+            })
+        })
+        .collect();
+    let locked: Duration = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    (start.elapsed(), locked / THREADS as u32)
+}
+
+fn concurrent_reads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("concurrent_reads");
+    group
+        .sample_size(10)
+        .throughput(Throughput::Elements(THREADS as u64));
+    for (name, shared) in [("owned", false), ("shared", true)] {
+        let address_space = Arc::new(RwLock::new(make_address_space(shared)));
+        group.bench_with_input(BenchmarkId::new("read", name), &shared, |b, &shared| {
+            b.iter_custom(|iters| run(&address_space, shared, iters).0)
+        });
+        group.bench_with_input(
+            BenchmarkId::new("lock_held", name),
+            &shared,
+            |b, &shared| b.iter_custom(|iters| run(&address_space, shared, iters).1),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, concurrent_reads);
+criterion_main!(benches);
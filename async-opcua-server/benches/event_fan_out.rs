@@ -0,0 +1,111 @@
+//! Compares evaluating the event filters of 100 event monitored items on the
+//! same source, as the server does when notifying a batch of events, with and
+//! without sharing the evaluation of identical filters through `EventFilterCache`.
+//!
+//! Run with `cargo bench -p async-opcua-server --bench event_fan_out`.
+
+// `criterion_group!` generates an undocumented public function.
+#![allow(missing_docs)]
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use opcua_nodes::{BaseEventType, DefaultTypeTree, Event, ParsedEventFilter};
+use opcua_server::EventFilterCache;
+use opcua_types::{
+    AttributeId, ByteString, ContentFilter, ContentFilterElement, EventFilter, FilterOperator,
+    NumericRange, ObjectTypeId, Operand, SimpleAttributeOperand,
+};
+
+const ITEM_COUNT: usize = 100;
+
+fn make_filter(type_tree: &DefaultTypeTree) -> ParsedEventFilter {
+    let select_clauses = ["EventId", "Message", "Severity", "Time"]
+        .into_iter()
+        .map(|field| {
+            SimpleAttributeOperand::new(
+                ObjectTypeId::BaseEventType,
+                field,
+                AttributeId::Value,
+                NumericRange::None,
+            )
+        })
+        .collect();
+    let where_clause = ContentFilter {
+        elements: Some(vec![ContentFilterElement {
+            filter_operator: FilterOperator::GreaterThanOrEqual,
+            filter_operands: Some(vec![
+                (&Operand::simple_attribute(
+                    ObjectTypeId::BaseEventType,
+                    "Severity",
+                    AttributeId::Value,
+                    NumericRange::None,
+                ))
+                    .into(),
+                (&Operand::literal(500u16)).into(),
+            ]),
+        }]),
+    };
+    let (_, filter) = ParsedEventFilter::new(
+        EventFilter {
+            select_clauses: Some(select_clauses),
+            where_clause,
+        },
+        type_tree,
+    );
+    filter.unwrap()
+}
+
+fn make_events(count: usize) -> Vec<BaseEventType> {
+    (0..count)
+        .map(|i| {
+            let mut event =
+                BaseEventType::new_now(ObjectTypeId::BaseEventType, ByteString::null(), "message");
+            // Half of the events pass the where clause.
+            event.severity = if i % 2 == 0 { 800 } else { 100 };
+            event
+        })
+        .collect()
+}
+
+fn event_fan_out(c: &mut Criterion) {
+    let type_tree = DefaultTypeTree::new();
+    let filters: Vec<_> = (0..ITEM_COUNT).map(|_| make_filter(&type_tree)).collect();
+
+    let mut group = c.benchmark_group("event_fan_out");
+    for count in [1usize, 100] {
+        let events = make_events(count);
+        group.throughput(Throughput::Elements((count * ITEM_COUNT) as u64));
+        // Each monitored item evaluates its own filter.
+        group.bench_with_input(BenchmarkId::new("uncached", count), &events, |b, events| {
+            b.iter(|| {
+                let mut notifications = 0;
+                for event in events {
+                    for (handle, filter) in filters.iter().enumerate() {
+                        if filter.evaluate(event, handle as u32).is_some() {
+                            notifications += 1;
+                        }
+                    }
+                }
+                notifications
+            })
+        });
+        // Identical filters are evaluated once per event, for all monitored items.
+        group.bench_with_input(BenchmarkId::new("cached", count), &events, |b, events| {
+            b.iter(|| {
+                let mut cache = EventFilterCache::default();
+                let mut notifications = 0;
+                for (idx, event) in events.iter().enumerate() {
+                    for filter in &filters {
+                        if cache.evaluate(filter, idx, event as &dyn Event).is_some() {
+                            notifications += 1;
+                        }
+                    }
+                }
+                notifications
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, event_fan_out);
+criterion_main!(benches);
@@ -0,0 +1,68 @@
+//! Compares reading many variables from the address space while acquiring the
+//! read lock once for the whole batch, as the in-memory node manager does when
+//! the server groups a `Read` request by node manager, against acquiring the
+//! lock once per node.
+//!
+//! Run with `cargo bench -p async-opcua-server --bench read_batching`.
+
+// `criterion_group!` generates an undocumented public function.
+#![allow(missing_docs)]
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use opcua_core::sync::RwLock;
+use opcua_server::address_space::{AddressSpace, NodeType, VariableBuilder};
+use opcua_types::{DataEncoding, DataTypeId, DataValue, NodeId, NumericRange, TimestampsToReturn};
+
+fn make_address_space(count: u32) -> (RwLock<AddressSpace>, Vec<NodeId>) {
+    let mut address_space = AddressSpace::new();
+    address_space.add_namespace("urn:bench", 1);
+    let ids: Vec<_> = (0..count).map(|i| NodeId::new(1, i)).collect();
+    for (i, id) in ids.iter().enumerate() {
+        VariableBuilder::new(id, "Var", "Var")
+            .data_type(DataTypeId::Int32)
+            .value(i as i32)
+            .insert(&mut address_space);
+    }
+    (RwLock::new(address_space), ids)
+}
+
+fn read_value(address_space: &AddressSpace, id: &NodeId) -> DataValue {
+    let Some(NodeType::Variable(v)) = address_space.find(id) else {
+        panic!("Variable is missing");
+    };
+    v.value(
+        TimestampsToReturn::Both,
+        &NumericRange::None,
+        &DataEncoding::Binary,
+        0.0,
+    )
+}
+
+fn read_batching(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_batching");
+    for count in [100u32, 10_000] {
+        let (address_space, ids) = make_address_space(count);
+        group.throughput(Throughput::Elements(count as u64));
+        // One lock acquisition for the whole batch.
+        group.bench_with_input(BenchmarkId::new("lock_per_batch", count), &ids, |b, ids| {
+            b.iter(|| {
+                let lck = address_space.read();
+                ids.iter()
+                    .map(|id| read_value(&lck, id))
+                    .collect::<Vec<_>>()
+            })
+        });
+        // One lock acquisition per node, as if each node was dispatched separately.
+        group.bench_with_input(BenchmarkId::new("lock_per_node", count), &ids, |b, ids| {
+            b.iter(|| {
+                ids.iter()
+                    .map(|id| read_value(&address_space.read(), id))
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, read_batching);
+criterion_main!(benches);
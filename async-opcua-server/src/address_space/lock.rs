@@ -0,0 +1,129 @@
+use opcua_core::{sync::RwLock, trace_read_lock, trace_write_lock};
+
+use super::AddressSpace;
+
+/// Extension trait for locking an address space for the duration of a closure.
+///
+/// Node managers typically share their address space as `RwLock<AddressSpace>`,
+/// and the lock is synchronous. Holding the guard across an `.await` blocks
+/// every other request that needs the address space until the future resumes,
+/// and may deadlock if the awaited future needs the lock itself. Since the
+/// closures passed to these methods are synchronous, the lock can never be
+/// held across an await point. Guards obtained by locking the `RwLock` directly
+/// are not tracked, clippy's `await_holding_lock` lint catches those instead.
+///
+/// In debug builds, these methods also panic if the same address space
+/// is locked again on the same thread while the closure is running, which
+/// would deadlock if another thread is waiting for a write lock.
+///
+/// # Example
+///
+/// ```ignore
+/// let value = address_space.with_read(|sp| sp.find(&node_id).map(|n| n.node_class()));
+/// some_async_call().await;
+/// ```
+pub trait AddressSpaceLock {
+    /// Call `f` with a read lock on the address space.
+    fn with_read<R>(&self, f: impl FnOnce(&AddressSpace) -> R) -> R;
+
+    /// Call `f` with a write lock on the address space.
+    fn with_write<R>(&self, f: impl FnOnce(&mut AddressSpace) -> R) -> R;
+}
+
+impl AddressSpaceLock for RwLock<AddressSpace> {
+    fn with_read<R>(&self, f: impl FnOnce(&AddressSpace) -> R) -> R {
+        let _held = held::HeldLock::new(self);
+        let address_space = trace_read_lock!(self);
+        f(&address_space)
+    }
+
+    fn with_write<R>(&self, f: impl FnOnce(&mut AddressSpace) -> R) -> R {
+        let _held = held::HeldLock::new(self);
+        let mut address_space = trace_write_lock!(self);
+        f(&mut address_space)
+    }
+}
+
+#[cfg(debug_assertions)]
+mod held {
+    use std::cell::RefCell;
+
+    use opcua_core::sync::RwLock;
+
+    use crate::address_space::AddressSpace;
+
+    thread_local! {
+        static HELD_LOCKS: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Marker for an address space lock held by the current thread.
+    pub(super) struct HeldLock(usize);
+
+    impl HeldLock {
+        pub(super) fn new(lock: &RwLock<AddressSpace>) -> Self {
+            let addr = lock as *const _ as usize;
+            HELD_LOCKS.with_borrow_mut(|held| {
+                assert!(
+                    !held.contains(&addr),
+                    "Address space locked recursively on the same thread"
+                );
+                held.push(addr);
+            });
+            Self(addr)
+        }
+    }
+
+    impl Drop for HeldLock {
+        fn drop(&mut self) {
+            HELD_LOCKS.with_borrow_mut(|held| held.retain(|h| *h != self.0));
+        }
+    }
+}
+
+#[cfg(not(debug_assertions))]
+mod held {
+    use opcua_core::sync::RwLock;
+
+    use crate::address_space::AddressSpace;
+
+    pub(super) struct HeldLock;
+
+    impl HeldLock {
+        #[inline(always)]
+        pub(super) fn new(_lock: &RwLock<AddressSpace>) -> Self {
+            Self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opcua_core::sync::RwLock;
+    use opcua_types::NodeId;
+
+    use super::AddressSpaceLock;
+    use crate::address_space::{AddressSpace, ObjectBuilder};
+
+    #[test]
+    fn with_read_and_write() {
+        let lock = RwLock::new(AddressSpace::new());
+        let id = NodeId::new(1, "obj");
+        lock.with_write(|sp| {
+            sp.add_namespace("urn:test", 1);
+            ObjectBuilder::new(&id, "Obj", "Obj").insert(sp)
+        });
+        assert!(lock.with_read(|sp| sp.find(&id).is_some()));
+
+        // Locking a different address space while one is held is fine.
+        let other = RwLock::new(AddressSpace::new());
+        lock.with_read(|_| other.with_read(|sp| assert!(sp.find(&id).is_none())));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Address space locked recursively")]
+    fn recursive_lock_panics() {
+        let lock = RwLock::new(AddressSpace::new());
+        lock.with_read(|_| lock.with_read(|_| ()));
+    }
+}
@@ -1,7 +1,9 @@
 //! Implementation of [AddressSpace], and in-memory OPC-UA address space.
 
+mod lock;
 mod utils;
 
+pub use lock::AddressSpaceLock;
 pub use opcua_nodes::*;
 pub use utils::*;
 
@@ -22,16 +24,28 @@ use opcua_types::{
 /// Represents an in-memory address space.
 #[derive(Default)]
 pub struct AddressSpace {
-    node_map: HashMap<NodeId, NodeType>,
+    node_map: HashMap<NodeKey, NodeType>,
+    /// Index of node IDs by node class, see [`class_index`].
+    nodes_by_class: [HashSet<NodeKey>; 8],
     namespaces: HashMap<u16, String>,
     references: References,
 }
 
+/// Get the index of a node class in `AddressSpace::nodes_by_class`.
+/// Node classes are single bits, so this is just the position of that bit.
+fn class_index(class: NodeClass) -> Option<usize> {
+    match class {
+        NodeClass::Unspecified => None,
+        c => Some((c as i32).trailing_zeros() as usize),
+    }
+}
+
 impl AddressSpace {
     /// Create a new empty address space.
     pub fn new() -> Self {
         Self {
             node_map: HashMap::new(),
+            nodes_by_class: Default::default(),
             namespaces: HashMap::new(),
             references: References::new(),
         }
@@ -39,6 +53,10 @@ impl AddressSpace {
 
     /// Import a node set into this address space.
     /// This will register namespaces from the node set import.
+    ///
+    /// The nodes of the import may be loaded in any order. References are stored
+    /// without checking that their targets exist, so a node may reference nodes
+    /// that are imported after it, or by a later node set import.
     pub fn import_node_set<T: NodeSetImport + ?Sized>(
         &mut self,
         import: &T,
@@ -182,7 +200,7 @@ impl AddressSpace {
             if let Some(references) = references {
                 self.references.insert::<S>(&node_id, references);
             }
-            self.node_map.insert(node_id, node_type);
+            self.insert_node(node_id, node_type);
 
             true
         }
@@ -197,7 +215,7 @@ impl AddressSpace {
             error!("This node {} already exists", node_id);
             false
         } else {
-            self.node_map.insert(node_id.clone(), node.node);
+            self.insert_node(node_id.clone(), node.node);
             for r in node.references {
                 self.references.import_reference(node_id.clone(), r);
             }
@@ -214,6 +232,40 @@ impl AddressSpace {
             .map(|(i, _)| *i)
     }
 
+    /// Insert a node into the node map and the node class index.
+    /// The caller must check that the node does not already exist.
+    fn insert_node(&mut self, node_id: NodeId, node: NodeType) {
+        if let Some(idx) = class_index(node.node_class()) {
+            self.nodes_by_class[idx].insert((&node_id).into());
+        }
+        self.node_map.insert(node_id.into(), node);
+    }
+
+    /// Iterate over all nodes in the address space, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &NodeType> + '_ {
+        self.node_map.values()
+    }
+
+    /// Iterate over all nodes with the given node class.
+    ///
+    /// This uses an index maintained when nodes are inserted and deleted, so it
+    /// does not need to visit every node in the address space. The nodes are
+    /// returned in no particular order.
+    pub fn iter_by_class(&self, class: NodeClass) -> impl Iterator<Item = &NodeType> + '_ {
+        class_index(class)
+            .into_iter()
+            .flat_map(move |idx| self.nodes_by_class[idx].iter())
+            .filter_map(|id| self.node_map.get(id))
+            .filter(move |n| n.node_class() == class)
+    }
+
+    /// Get the number of nodes with the given node class.
+    pub fn count_by_class(&self, class: NodeClass) -> usize {
+        class_index(class)
+            .map(|idx| self.nodes_by_class[idx].len())
+            .unwrap_or_default()
+    }
+
     fn assert_namespace(&self, node_id: &NodeId) {
         if !self.namespaces.contains_key(&node_id.namespace) {
             panic!("Namespace index {} not in address space", node_id.namespace);
@@ -291,6 +343,32 @@ impl AddressSpace {
             .find_references(source_node, filter, type_tree, direction)
     }
 
+    /// Get the IDs of the nodes referenced from `source_node` by references of type
+    /// `reference_type` in the given direction. If `include_subtypes` is true,
+    /// references with a subtype of `reference_type` are included as well, using `type_tree`
+    /// to resolve the subtypes.
+    ///
+    /// Each target is only returned once, even if it is referenced more than once.
+    pub fn targets(
+        &self,
+        source_node: &NodeId,
+        reference_type: impl Into<NodeId>,
+        include_subtypes: bool,
+        type_tree: &dyn TypeTree,
+        direction: BrowseDirection,
+    ) -> Vec<NodeId> {
+        let mut seen = HashSet::new();
+        self.find_references(
+            source_node,
+            Some((reference_type, include_subtypes)),
+            type_tree,
+            direction,
+        )
+        .filter(|r| seen.insert(r.target_node))
+        .map(|r| r.target_node.clone())
+        .collect()
+    }
+
     /// Find a child of `source_node` matching the given `filter` with
     /// browse name equal to `browse_name`.
     pub fn find_node_by_browse_name<'a: 'b, 'b>(
@@ -439,6 +517,9 @@ impl AddressSpace {
     /// Remove a node from the address space.
     pub fn delete(&mut self, node_id: &NodeId, delete_target_references: bool) -> Option<NodeType> {
         let n = self.node_map.remove(node_id);
+        if let Some(idx) = n.as_ref().and_then(|n| class_index(n.node_class())) {
+            self.nodes_by_class[idx].remove(node_id);
+        }
         self.references
             .delete_node_references(node_id, delete_target_references);
 
@@ -501,7 +582,7 @@ impl NodeInsertTarget for AddressSpace {
             if let Some(references) = references {
                 self.references.insert(&node_id, references);
             }
-            self.node_map.insert(node_id, node_type);
+            self.insert_node(node_id, node_type);
 
             true
         }
@@ -510,15 +591,22 @@ impl NodeInsertTarget for AddressSpace {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use crate::address_space::{
-        CoreNamespace, EventNotifier, MethodBuilder, NodeBase, NodeType, Object, ObjectBuilder,
-        ObjectTypeBuilder, Variable, VariableBuilder,
+        AccessLevel, CoreNamespace, EventNotifier, HasNodeId, MethodBuilder, Node, NodeBase,
+        NodeType, Object, ObjectBuilder, ObjectTypeBuilder, ReferenceDirection, Variable,
+        VariableBuildError, VariableBuilder,
+    };
+    use opcua_nodes::{
+        DefaultTypeTree, ImportedItem, NamespaceMap, NodeSetImport, NodeSetNamespaceMapper,
+        TypeTree,
     };
-    use opcua_nodes::{DefaultTypeTree, NamespaceMap, TypeTree};
     use opcua_types::{
-        argument::Argument, Array, BrowseDirection, DataTypeId, LocalizedText, NodeClass, NodeId,
-        NumericRange, ObjectId, ObjectTypeId, QualifiedName, ReferenceTypeId, TimestampsToReturn,
-        UAString, Variant, VariantScalarTypeId,
+        argument::Argument, AccessLevelExType, Array, AttributeId, BrowseDirection, DataTypeId,
+        DataValue, EUInformation, LocalizedText, NodeClass, NodeId, NumericRange, ObjectId,
+        ObjectTypeId, QualifiedName, Range, ReferenceTypeId, StatusCode, TimestampsToReturn,
+        UAString, VariableTypeId, Variant, VariantScalarTypeId,
     };
 
     use super::AddressSpace;
@@ -647,6 +735,70 @@ mod tests {
         }
     }
 
+    /// Node set import that yields the nodes of an inner import in reverse order.
+    struct ReversedImport<'a>(&'a dyn NodeSetImport);
+
+    impl NodeSetImport for ReversedImport<'_> {
+        fn register_namespaces(&self, namespaces: &mut NodeSetNamespaceMapper) {
+            self.0.register_namespaces(namespaces)
+        }
+
+        fn get_own_namespaces(&self) -> Vec<String> {
+            self.0.get_own_namespaces()
+        }
+
+        fn load<'a>(
+            &'a self,
+            namespaces: &'a NodeSetNamespaceMapper,
+        ) -> Box<dyn Iterator<Item = ImportedItem> + 'a> {
+            let mut items: Vec<_> = self.0.load(namespaces).collect();
+            items.reverse();
+            Box::new(items.into_iter())
+        }
+    }
+
+    #[test]
+    fn import_order_independent() {
+        let mut forward = AddressSpace::new();
+        forward.import_node_set(&CoreNamespace, &mut NamespaceMap::new());
+        let mut reversed = AddressSpace::new();
+        reversed.import_node_set(&ReversedImport(&CoreNamespace), &mut NamespaceMap::new());
+
+        assert_eq!(forward.node_map.len(), reversed.node_map.len());
+        let type_tree = DefaultTypeTree::new();
+        for node in forward.node_map.values() {
+            let node_id = node.node_id();
+            assert!(reversed.node_exists(node_id));
+            let refs = |space: &AddressSpace| {
+                let mut refs: Vec<_> = space
+                    .find_references(
+                        node_id,
+                        None::<(NodeId, bool)>,
+                        &type_tree,
+                        BrowseDirection::Both,
+                    )
+                    .map(|r| (r.reference_type.clone(), r.target_node.clone(), r.direction))
+                    .collect();
+                refs.sort_by_key(|r| format!("{r:?}"));
+                refs
+            };
+            assert_eq!(refs(&forward), refs(&reversed), "references of {node_id}");
+        }
+
+        let mut forward_tree = DefaultTypeTree::new();
+        forward.load_into_type_tree(&mut forward_tree);
+        let mut reversed_tree = DefaultTypeTree::new();
+        reversed.load_into_type_tree(&mut reversed_tree);
+        for node in forward.node_map.values() {
+            let node_id = node.node_id();
+            assert_eq!(forward_tree.get(node_id), reversed_tree.get(node_id));
+            assert_eq!(
+                forward_tree.get_supertype(node_id),
+                reversed_tree.get_supertype(node_id)
+            );
+        }
+    }
+
     #[test]
     fn object_attributes() {
         let on = NodeId::new(1, "o1");
@@ -1079,6 +1231,433 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn variable_builder_try_build() {
+        let builder = |value_rank: i32| {
+            VariableBuilder::new(&NodeId::new(1, "Var"), "Var", "Var")
+                .data_type(DataTypeId::Int32)
+                .value_rank(value_rank)
+        };
+
+        assert_eq!(
+            VariableBuilder::new(&NodeId::null(), "", "")
+                .try_build()
+                .unwrap_err(),
+            VariableBuildError::InvalidNode(NodeId::null())
+        );
+        assert_eq!(
+            builder(-4).try_build().unwrap_err(),
+            VariableBuildError::InvalidValueRank(-4)
+        );
+        assert_eq!(
+            builder(1)
+                .array_dimensions(&[2, 3])
+                .try_build()
+                .unwrap_err(),
+            VariableBuildError::InconsistentArrayDimensions {
+                value_rank: 1,
+                dimensions: 2
+            }
+        );
+        assert_eq!(
+            builder(-1).array_dimensions(&[2]).try_build().unwrap_err(),
+            VariableBuildError::InconsistentArrayDimensions {
+                value_rank: -1,
+                dimensions: 1
+            }
+        );
+        assert_eq!(
+            builder(1).value(5i32).try_build().unwrap_err(),
+            VariableBuildError::ScalarValue(1)
+        );
+        assert_eq!(
+            builder(2)
+                .array_dimensions(&[3, 1])
+                .value(vec![1i32, 2, 3])
+                .try_build()
+                .unwrap_err(),
+            VariableBuildError::InconsistentArrayDimensions {
+                value_rank: 2,
+                dimensions: 1
+            }
+        );
+
+        // Consistent variables build.
+        builder(-1).value(5i32).try_build().unwrap();
+        builder(-2).value(5i32).try_build().unwrap();
+        builder(1)
+            .array_dimensions(&[3])
+            .value(vec![1i32, 2, 3])
+            .try_build()
+            .unwrap();
+        builder(0)
+            .array_dimensions(&[3])
+            .value(vec![1i32, 2, 3])
+            .try_build()
+            .unwrap();
+        builder(2)
+            .array_dimensions(&[2, 2])
+            .value(
+                Array::new_multi(
+                    VariantScalarTypeId::Int32,
+                    vec![1i32.into(), 2i32.into(), 3i32.into(), 4i32.into()],
+                    vec![2, 2],
+                )
+                .unwrap(),
+            )
+            .try_build()
+            .unwrap();
+        // An empty value is allowed for any value rank.
+        builder(3).try_build().unwrap();
+
+        let mut address_space = make_sample_address_space();
+        assert!(builder(1)
+            .value(5i32)
+            .try_insert(&mut address_space)
+            .is_err());
+        assert!(address_space.find(NodeId::new(1, "Var")).is_none());
+        assert!(builder(1)
+            .value(vec![1i32])
+            .try_insert(&mut address_space)
+            .unwrap());
+        assert!(address_space.find(NodeId::new(1, "Var")).is_some());
+    }
+
+    #[test]
+    fn builders_with_properties() {
+        let id = NodeId::new(1, "Temperature");
+        let builder = || {
+            VariableBuilder::new(&id, "Temperature", "Temperature")
+                .data_type(DataTypeId::Double)
+                .eu_range(-40.0, 120.0)
+        };
+        assert_eq!(
+            builder().try_build().unwrap_err(),
+            VariableBuildError::HasProperties(id.clone())
+        );
+        // Building discards the properties.
+        assert_eq!(builder().build().node_id(), &id);
+
+        let mut address_space = make_sample_address_space();
+        address_space.add_namespace("urn:test", 1);
+        assert!(builder().try_insert(&mut address_space).unwrap());
+        assert!(address_space
+            .find(NodeId::new(1, "Temperature_EURange"))
+            .is_some());
+
+        // The properties can be built alongside the node, and inserted separately.
+        let method_id = NodeId::new(1, "Method");
+        let (method, children) = MethodBuilder::new(&method_id, "Method", "Method")
+            .input_argument("Input", DataTypeId::Int32, -1, "")
+            .output_argument("Output", DataTypeId::Int32, -1, "")
+            .build_with_children();
+        assert_eq!(children.len(), 2);
+        let input_id = NodeId::new(1, "Method_InputArguments");
+        assert_eq!(children[0].node.node_id(), &input_id);
+        assert!(children[0].references.contains(&(
+            method_id.clone(),
+            ReferenceTypeId::HasProperty.into(),
+            ReferenceDirection::Inverse
+        )));
+        assert!(address_space.insert(method, None::<&[(&NodeId, &NodeId, ReferenceDirection)]>));
+        for child in children {
+            assert!(child.insert(&mut address_space));
+        }
+        assert!(address_space.find(&input_id).is_some());
+        assert!(address_space
+            .find_references(
+                &method_id,
+                Some((ReferenceTypeId::HasProperty, false)),
+                &DefaultTypeTree::new(),
+                BrowseDirection::Forward,
+            )
+            .any(|r| r.target_node == &input_id));
+    }
+
+    #[test]
+    fn variable_access_level_ex() {
+        let read_ex = |v: &Variable| {
+            v.get_attribute(
+                TimestampsToReturn::Neither,
+                AttributeId::AccessLevelEx,
+                &NumericRange::None,
+                &opcua_types::DataEncoding::Binary,
+            )
+            .unwrap()
+            .value
+            .unwrap()
+        };
+
+        // By default, the extended access level is derived from the access level.
+        let mut v = VariableBuilder::new(&NodeId::new(1, "Var"), "Var", "Var")
+            .data_type(DataTypeId::Int32)
+            .writable()
+            .build();
+        assert_eq!(
+            v.access_level_ex(),
+            AccessLevelExType::CurrentRead | AccessLevelExType::CurrentWrite
+        );
+        assert_eq!(read_ex(&v), Variant::UInt32(3));
+
+        v.set_attribute(
+            AttributeId::AccessLevelEx,
+            Variant::UInt32(
+                (AccessLevelExType::CurrentRead | AccessLevelExType::NonatomicWrite).bits() as u32,
+            ),
+        )
+        .unwrap();
+        assert_eq!(v.access_level().bits(), AccessLevel::CURRENT_READ.bits());
+        assert_eq!(read_ex(&v), Variant::UInt32(0x201));
+        assert_eq!(
+            v.set_attribute(AttributeId::AccessLevelEx, Variant::Byte(1)),
+            Err(StatusCode::BadTypeMismatch)
+        );
+
+        // Changing the access level keeps the extended bits.
+        v.set_access_level(AccessLevel::CURRENT_READ | AccessLevel::HISTORY_READ);
+        assert_eq!(
+            v.access_level_ex(),
+            AccessLevelExType::CurrentRead
+                | AccessLevelExType::HistoryRead
+                | AccessLevelExType::NonatomicWrite
+        );
+
+        let v = VariableBuilder::new(&NodeId::new(1, "Var"), "Var", "Var")
+            .data_type(DataTypeId::Int32)
+            .access_level_ex(AccessLevelExType::CurrentRead | AccessLevelExType::WriteFullArrayOnly)
+            .build();
+        assert_eq!(v.access_level().bits(), AccessLevel::CURRENT_READ.bits());
+        assert_eq!(read_ex(&v), Variant::UInt32(0x401));
+    }
+
+    #[test]
+    fn variable_shared_value() {
+        let mut v = VariableBuilder::new(&NodeId::new(1, "Var"), "Var", "Var")
+            .data_type(DataTypeId::Int32)
+            .value_rank(1)
+            .value(vec![1i32, 2, 3])
+            .shared_value()
+            .build();
+        assert!(v.is_value_shared());
+        let read = |v: &Variable, range: &NumericRange| {
+            v.value(
+                TimestampsToReturn::Both,
+                range,
+                &opcua_types::DataEncoding::Binary,
+                0.0,
+            )
+        };
+        assert_eq!(
+            read(&v, &NumericRange::None).value,
+            Some(Variant::from(vec![1i32, 2, 3]))
+        );
+
+        // Reading without a range shares the value.
+        let r1 = v.shared_value(TimestampsToReturn::Both, &NumericRange::None);
+        let r2 = v.shared_value(TimestampsToReturn::Neither, &NumericRange::None);
+        assert!(Arc::ptr_eq(
+            r1.value.as_ref().unwrap(),
+            r2.value.as_ref().unwrap()
+        ));
+        assert!(r1.source_timestamp.is_some());
+        assert!(r2.source_timestamp.is_none());
+        assert_eq!(r1.clone().into_data_value(), read(&v, &NumericRange::None));
+
+        // Reading a range copies the range.
+        let range = NumericRange::Index(1);
+        let r3 = v.shared_value(TimestampsToReturn::Both, &range);
+        assert_eq!(r3.value.as_deref(), Some(&Variant::from(vec![2i32])));
+
+        // Writing part of the value does not affect values already read.
+        v.set_value(&range, vec![5i32]).unwrap();
+        assert_eq!(
+            r1.into_data_value().value,
+            Some(Variant::from(vec![1i32, 2, 3]))
+        );
+        assert_eq!(
+            read(&v, &NumericRange::None).value,
+            Some(Variant::from(vec![1i32, 5, 3]))
+        );
+
+        // The value is kept when switching back to an owned value.
+        v.set_value_shared(false);
+        assert!(!v.is_value_shared());
+        assert_eq!(
+            v.shared_value(TimestampsToReturn::Both, &NumericRange::None)
+                .into_data_value()
+                .value,
+            Some(Variant::from(vec![1i32, 5, 3]))
+        );
+        v.set_data_value(DataValue::value_only(7i32));
+        v.set_value_shared(true);
+        assert_eq!(
+            read(&v, &NumericRange::None).value,
+            Some(Variant::from(7i32))
+        );
+    }
+
+    #[test]
+    fn analog_item_builder() {
+        let mut address_space = make_sample_address_space();
+        address_space.add_namespace("urn:test", 1);
+
+        let node_id = NodeId::new(1, "Temperature");
+        let units = EUInformation {
+            namespace_uri: "http://www.opcfoundation.org/UA/units/un/cefact".into(),
+            unit_id: 4408652,
+            display_name: "°C".into(),
+            description: "degree Celsius".into(),
+        };
+        let inserted = VariableBuilder::new(&node_id, "Temperature", "Temperature")
+            .data_type(DataTypeId::Double)
+            .value(20.0)
+            .has_type_definition(VariableTypeId::BaseDataVariableType)
+            .eu_range(-40.0, 120.0)
+            .engineering_units(units.clone())
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut address_space);
+        assert!(inserted);
+
+        let type_tree = DefaultTypeTree::new();
+        assert_eq!(
+            address_space.targets(
+                &node_id,
+                ReferenceTypeId::HasTypeDefinition,
+                false,
+                &type_tree,
+                BrowseDirection::Forward
+            ),
+            vec![NodeId::from(VariableTypeId::AnalogItemType)]
+        );
+
+        let read_property = |name: &str| {
+            let Some(NodeType::Variable(v)) = address_space.find_node_by_browse_name(
+                &node_id,
+                Some((ReferenceTypeId::HasProperty, false)),
+                &type_tree,
+                BrowseDirection::Forward,
+                name,
+            ) else {
+                panic!("Missing property {name}");
+            };
+            assert_eq!(v.node_id(), &NodeId::new(1, format!("Temperature_{name}")));
+            let Some(Variant::ExtensionObject(value)) = v
+                .value(
+                    TimestampsToReturn::Neither,
+                    &NumericRange::None,
+                    &opcua_types::DataEncoding::Binary,
+                    0.0,
+                )
+                .value
+            else {
+                panic!("Property {name} has wrong type");
+            };
+            value
+        };
+        assert_eq!(
+            read_property("EURange").inner_as::<Range>(),
+            Some(&Range {
+                low: -40.0,
+                high: 120.0
+            })
+        );
+        assert_eq!(
+            read_property("EngineeringUnits").inner_as::<EUInformation>(),
+            Some(&units)
+        );
+    }
+
+    #[test]
+    fn discrete_builders() {
+        let mut address_space = make_sample_address_space();
+        address_space.add_namespace("urn:test", 1);
+        let type_tree = DefaultTypeTree::new();
+
+        let read_property = |address_space: &AddressSpace, node_id: &NodeId, name: &str| {
+            let Some(NodeType::Variable(v)) = address_space.find_node_by_browse_name(
+                node_id,
+                Some((ReferenceTypeId::HasProperty, false)),
+                &type_tree,
+                BrowseDirection::Forward,
+                name,
+            ) else {
+                panic!("Missing property {name}");
+            };
+            (
+                v.value_rank(),
+                v.value(
+                    TimestampsToReturn::Neither,
+                    &NumericRange::None,
+                    &opcua_types::DataEncoding::Binary,
+                    0.0,
+                )
+                .value
+                .unwrap(),
+            )
+        };
+
+        let two_state = NodeId::new(1, "Valve");
+        assert!(VariableBuilder::new(&two_state, "Valve", "Valve")
+            .value(false)
+            .two_state_discrete("Open", "Closed")
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut address_space));
+        let Some(NodeType::Variable(v)) = address_space.find_node(&two_state) else {
+            panic!("Missing variable");
+        };
+        assert_eq!(v.data_type(), DataTypeId::Boolean);
+        assert_eq!(
+            address_space.targets(
+                &two_state,
+                ReferenceTypeId::HasTypeDefinition,
+                false,
+                &type_tree,
+                BrowseDirection::Forward
+            ),
+            vec![NodeId::from(VariableTypeId::TwoStateDiscreteType)]
+        );
+        assert_eq!(
+            read_property(&address_space, &two_state, "TrueState"),
+            (-1, Variant::from(LocalizedText::from("Open")))
+        );
+        assert_eq!(
+            read_property(&address_space, &two_state, "FalseState"),
+            (-1, Variant::from(LocalizedText::from("Closed")))
+        );
+
+        let multi_state = NodeId::new(1, "Mode");
+        assert!(VariableBuilder::new(&multi_state, "Mode", "Mode")
+            .data_type(DataTypeId::Byte)
+            .value(0u8)
+            .multi_state_discrete(&["Off", "Manual", "Auto"])
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut address_space));
+        let Some(NodeType::Variable(v)) = address_space.find_node(&multi_state) else {
+            panic!("Missing variable");
+        };
+        assert_eq!(v.data_type(), DataTypeId::Byte);
+        assert_eq!(
+            address_space.targets(
+                &multi_state,
+                ReferenceTypeId::HasTypeDefinition,
+                false,
+                &type_tree,
+                BrowseDirection::Forward
+            ),
+            vec![NodeId::from(VariableTypeId::MultiStateDiscreteType)]
+        );
+        let (value_rank, value) = read_property(&address_space, &multi_state, "EnumStrings");
+        assert_eq!(value_rank, 1);
+        assert_eq!(
+            value,
+            Variant::from(vec![
+                LocalizedText::from("Off"),
+                LocalizedText::from("Manual"),
+                LocalizedText::from("Auto")
+            ])
+        );
+    }
+
     #[test]
     fn method_builder() {
         let mut address_space = make_sample_address_space();
@@ -1256,4 +1835,121 @@ mod tests {
             }
         });
     }
+
+    #[test]
+    fn iter_by_class() {
+        let mut address_space = make_sample_address_space();
+
+        for class in [
+            NodeClass::Object,
+            NodeClass::Variable,
+            NodeClass::Method,
+            NodeClass::ObjectType,
+            NodeClass::VariableType,
+            NodeClass::ReferenceType,
+            NodeClass::DataType,
+            NodeClass::View,
+        ] {
+            let expected = address_space
+                .node_map
+                .values()
+                .filter(|n| n.node_class() == class)
+                .count();
+            assert_eq!(address_space.count_by_class(class), expected);
+            assert_eq!(address_space.iter_by_class(class).count(), expected);
+            assert!(address_space
+                .iter_by_class(class)
+                .all(|n| n.node_class() == class));
+        }
+        assert_eq!(address_space.count_by_class(NodeClass::Unspecified), 0);
+
+        let count = address_space.count_by_class(NodeClass::Variable);
+        address_space.delete(&NodeId::new(1, "v1"), true);
+        assert_eq!(address_space.count_by_class(NodeClass::Variable), count - 1);
+        assert!(address_space
+            .iter_by_class(NodeClass::Variable)
+            .all(|n| n.node_id() != &NodeId::new(1, "v1")));
+    }
+
+    #[test]
+    fn targets() {
+        let address_space = make_sample_address_space();
+        let mut type_tree = DefaultTypeTree::new();
+        address_space.load_into_type_tree(&mut type_tree);
+
+        let sample_folder = address_space
+            .find_node_by_browse_name(
+                &NodeId::objects_folder_id(),
+                Some((ReferenceTypeId::Organizes, false)),
+                &type_tree,
+                BrowseDirection::Forward,
+                "Sample",
+            )
+            .unwrap()
+            .node_id()
+            .clone();
+
+        let mut targets = address_space.targets(
+            &sample_folder,
+            ReferenceTypeId::HasComponent,
+            false,
+            &type_tree,
+            BrowseDirection::Forward,
+        );
+        targets.sort_by_key(|t| t.to_string());
+        assert_eq!(
+            targets,
+            vec![
+                NodeId::new(1, 300),
+                NodeId::new(1, "v1"),
+                NodeId::new(1, "v3"),
+                NodeId::new(1, "v4"),
+            ]
+        );
+
+        // HasComponent is not a subtype of Organizes
+        assert!(address_space
+            .targets(
+                &sample_folder,
+                ReferenceTypeId::Organizes,
+                true,
+                &type_tree,
+                BrowseDirection::Forward,
+            )
+            .is_empty());
+
+        // But it is a subtype of HierarchicalReferences, which is not matched without subtypes.
+        assert_eq!(
+            address_space
+                .targets(
+                    &sample_folder,
+                    ReferenceTypeId::HierarchicalReferences,
+                    true,
+                    &type_tree,
+                    BrowseDirection::Forward,
+                )
+                .len(),
+            4
+        );
+        assert!(address_space
+            .targets(
+                &sample_folder,
+                ReferenceTypeId::HierarchicalReferences,
+                false,
+                &type_tree,
+                BrowseDirection::Forward,
+            )
+            .is_empty());
+
+        assert_eq!(
+            address_space.targets(
+                &NodeId::new(1, "v1"),
+                ReferenceTypeId::HasComponent,
+                false,
+                &type_tree,
+                BrowseDirection::Inverse,
+            ),
+            vec![sample_folder]
+        );
+    }
 }
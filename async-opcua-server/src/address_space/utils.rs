@@ -1,8 +1,9 @@
 use crate::node_manager::{ParsedReadValueId, ParsedWriteValue, RequestContext, ServerContext};
-use opcua_nodes::TypeTree;
+use opcua_nodes::{NodeBase, TypeTree};
 use opcua_types::{
-    AttributeId, DataEncoding, DataTypeId, DataValue, DateTime, NumericRange, StatusCode,
-    TimestampsToReturn, Variant, WriteMask,
+    AttributeId, BrowseDirection, DataEncoding, DataTypeId, DataValue, DateTime, NodeClass, NodeId,
+    NumericRange, QualifiedName, Range, ReferenceTypeId, StatusCode, TimestampsToReturn, Variant,
+    WriteMask,
 };
 use tracing::debug;
 
@@ -71,17 +72,9 @@ pub fn is_writable(
 }
 
 /// Get the effective user access level for `node`.
+/// See [`RequestContext::effective_access_level`].
 pub fn user_access_level(context: &RequestContext, node: &NodeType) -> AccessLevel {
-    let user_access_level = if let NodeType::Variable(ref node) = node {
-        node.user_access_level()
-    } else {
-        AccessLevel::CURRENT_READ
-    };
-    context.authenticator.effective_user_access_level(
-        &context.token,
-        user_access_level,
-        node.node_id(),
-    )
+    context.effective_access_level(node.as_node())
 }
 
 /// Validate that the user given by `context` is allowed to read
@@ -217,15 +210,9 @@ pub fn read_node_value(
 
     let value = if node_to_read.attribute_id == AttributeId::UserAccessLevel {
         match attribute.value {
-            Some(Variant::Byte(val)) => {
-                let access_level = AccessLevel::from_bits_truncate(val);
-                let access_level = context.authenticator.effective_user_access_level(
-                    &context.token,
-                    access_level,
-                    node.node_id(),
-                );
-                Some(Variant::from(access_level.bits()))
-            }
+            Some(Variant::Byte(_)) => Some(Variant::from(
+                context.effective_access_level(node.as_node()).bits(),
+            )),
             Some(v) => Some(v),
             _ => None,
         }
@@ -295,19 +282,106 @@ pub fn write_node_value(
     )
 }
 
+/// If `node_id` is a property that defines the semantics of a variable, such as
+/// `EURange`, `EngineeringUnits` or `EnumStrings`, return the node ID of that variable.
+///
+/// Node managers can use this after writing a property to find the variable to pass to
+/// [`SubscriptionCache::notify_semantics_changed`](crate::SubscriptionCache::notify_semantics_changed).
+pub fn semantics_changed_variable(
+    address_space: &AddressSpace,
+    node_id: &NodeId,
+    type_tree: &dyn TypeTree,
+) -> Option<NodeId> {
+    let node = address_space.find(node_id)?;
+    let browse_name = node.as_node().browse_name();
+    if browse_name.namespace_index != 0
+        || !matches!(
+            browse_name.name.as_ref(),
+            "EURange"
+                | "EngineeringUnits"
+                | "InstrumentRange"
+                | "EnumStrings"
+                | "EnumValues"
+                | "TrueState"
+                | "FalseState"
+                | "ValuePrecision"
+        )
+    {
+        return None;
+    }
+
+    address_space
+        .find_references(
+            node_id,
+            Some((ReferenceTypeId::HasProperty, false)),
+            type_tree,
+            BrowseDirection::Inverse,
+        )
+        .map(|r| r.target_node)
+        .find(|id| {
+            address_space
+                .find(*id)
+                .is_some_and(|n| n.node_class() == NodeClass::Variable)
+        })
+        .cloned()
+}
+
+/// Get the `EURange` property of the variable with ID `node_id`, if it has one.
+///
+/// Pass this to [`SubscriptionCache::notify_semantics_changed`](crate::SubscriptionCache::notify_semantics_changed)
+/// so that percent deadband filters use the current range.
+pub fn eu_range(
+    address_space: &AddressSpace,
+    node_id: &NodeId,
+    type_tree: &dyn TypeTree,
+) -> Option<(f64, f64)> {
+    address_space
+        .find_references(
+            node_id,
+            Some((ReferenceTypeId::HasProperty, false)),
+            type_tree,
+            BrowseDirection::Forward,
+        )
+        .filter_map(|r| match address_space.find(r.target_node) {
+            Some(NodeType::Variable(v)) if v.browse_name() == &QualifiedName::from("EURange") => {
+                Some(v)
+            }
+            _ => None,
+        })
+        .find_map(|v| {
+            let value = v.value(
+                TimestampsToReturn::Neither,
+                &NumericRange::None,
+                &DataEncoding::Binary,
+                0.0,
+            );
+            let Some(Variant::ExtensionObject(obj)) = value.value else {
+                return None;
+            };
+            obj.inner_as::<Range>().map(|r| (r.low, r.high))
+        })
+}
+
 /// Add the given list of namespaces to the type tree in `context` and
 /// `address_space`.
+///
+/// If the server is already running, clients subscribed to the `NamespaceArray`
+/// variable are notified, so namespaces reported by node managers should be
+/// visible before this is called.
 pub fn add_namespaces(
     context: &ServerContext,
     address_space: &mut AddressSpace,
     namespaces: &[&str],
 ) -> Vec<u16> {
-    let mut type_tree = context.type_tree.write();
     let mut res = Vec::new();
-    for ns in namespaces {
-        let idx = type_tree.namespaces_mut().add_namespace(ns);
-        address_space.add_namespace(ns, idx);
-        res.push(idx);
+    {
+        let mut type_tree = context.type_tree.write();
+        for ns in namespaces {
+            let idx = type_tree.namespaces_mut().add_namespace(ns);
+            address_space.add_namespace(ns, idx);
+            res.push(idx);
+        }
     }
+    context.notify_namespaces_changed();
     res
 }
@@ -70,10 +70,33 @@ impl UserToken {
 }
 
 /// Permissions for the core and diagnostics node managers.
+///
+/// More permissions may be added in the future, so this cannot be constructed
+/// with a struct literal outside this crate. Use [`CoreServerPermissions::default`]
+/// with the builder methods instead.
 #[derive(Default, Debug, Clone)]
+#[non_exhaustive]
 pub struct CoreServerPermissions {
     /// Whether the user can read the server diagnostics.
     pub read_diagnostics: bool,
+    /// Whether the user has administrative access to the server, which allows inspecting
+    /// the subscriptions of every session, not just the user's own, and fetching the keys
+    /// of PubSub security groups.
+    pub admin: bool,
+}
+
+impl CoreServerPermissions {
+    /// Set whether the user can read the server diagnostics.
+    pub fn read_diagnostics(mut self, read_diagnostics: bool) -> Self {
+        self.read_diagnostics = read_diagnostics;
+        self
+    }
+
+    /// Set whether the user has administrative access to the server.
+    pub fn admin(mut self, admin: bool) -> Self {
+        self.admin = admin;
+        self
+    }
 }
 
 #[allow(unused)]
@@ -342,8 +365,10 @@ impl AuthManager for DefaultAuthenticator {
     fn core_permissions(&self, token: &UserToken) -> CoreServerPermissions {
         self.users
             .get(token.0.as_str())
-            .map(|r| CoreServerPermissions {
-                read_diagnostics: r.read_diagnostics,
+            .map(|r| {
+                CoreServerPermissions::default()
+                    .read_diagnostics(r.read_diagnostics)
+                    .admin(r.admin)
             })
             .unwrap_or_default()
     }
@@ -1,16 +1,22 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{net::IpAddr, path::PathBuf, sync::Arc};
 
+use tokio::runtime::Handle;
 use tokio_util::sync::CancellationToken;
 use tracing::warn;
 
-use crate::{constants, node_manager::TypeTreeForUser};
+use crate::{
+    constants,
+    node_manager::{NodeVisibilityFilter, TypeTreeForUser},
+    ContinuationPointStoreFactory,
+};
 use opcua_core::config::Config;
 use opcua_crypto::SecurityPolicy;
-use opcua_types::{BuildInfo, MessageSecurityMode, TypeLoader, TypeLoaderCollection};
+use opcua_types::{BuildInfo, MessageSecurityMode, StatusCode, TypeLoader, TypeLoaderCollection};
 
 use super::{
-    authenticator::AuthManager, node_manager::NodeManagerBuilder, Limits, Server, ServerConfig,
-    ServerEndpoint, ServerHandle, ServerUserToken, ANONYMOUS_USER_TOKEN_ID,
+    authenticator::AuthManager, node_manager::NodeManagerBuilder, CertificateExpiryConfig,
+    DiagnosticsConfig, EndpointHostSubstitution, Limits, Server, ServerCapabilities, ServerConfig,
+    ServerEndpoint, ServerHandle, ServerUserToken, ServiceFaultDiagnostic, ANONYMOUS_USER_TOKEN_ID,
 };
 
 /// Server builder, used to configure the server programatically,
@@ -21,9 +27,15 @@ pub struct ServerBuilder {
     pub(crate) node_managers: Vec<Box<dyn NodeManagerBuilder>>,
     pub(crate) authenticator: Option<Arc<dyn AuthManager>>,
     pub(crate) type_tree_getter: Option<Arc<dyn TypeTreeForUser>>,
+    pub(crate) node_visibility_filter: Option<Arc<dyn NodeVisibilityFilter>>,
+    pub(crate) continuation_point_store: Option<Arc<dyn ContinuationPointStoreFactory>>,
     pub(crate) type_loaders: TypeLoaderCollection,
     pub(crate) token: CancellationToken,
     pub(crate) build_info: BuildInfo,
+    pub(crate) capabilities: ServerCapabilities,
+    pub(crate) sampler_runtime: Option<Handle>,
+    #[cfg(feature = "pubsub")]
+    pub(crate) security_key_service: Option<Arc<crate::pubsub::SecurityKeyService>>,
 }
 
 impl Default for ServerBuilder {
@@ -34,8 +46,14 @@ impl Default for ServerBuilder {
             authenticator: None,
             token: CancellationToken::new(),
             type_tree_getter: None,
+            node_visibility_filter: None,
+            continuation_point_store: None,
             build_info: BuildInfo::default(),
+            capabilities: ServerCapabilities::default(),
             type_loaders: TypeLoaderCollection::new(),
+            sampler_runtime: None,
+            #[cfg(feature = "pubsub")]
+            security_key_service: None,
         };
         #[cfg(feature = "generated-address-space")]
         {
@@ -287,6 +305,48 @@ impl ServerBuilder {
         self
     }
 
+    /// Set a filter deciding which nodes are visible to each user.
+    ///
+    /// Nodes hidden from a user appear not to exist: Read returns `BadNodeIdUnknown`
+    /// for them, and Browse leaves them out of the returned references. This avoids
+    /// revealing that a node exists, which `BadUserAccessDenied` would.
+    ///
+    /// This is not an access control mechanism on its own. Other services, such as
+    /// Write, Call or CreateMonitoredItems, do not consult the filter, so node
+    /// managers must still deny access to nodes the user may not use.
+    pub fn with_node_visibility_filter(
+        mut self,
+        node_visibility_filter: Arc<dyn NodeVisibilityFilter>,
+    ) -> Self {
+        self.node_visibility_filter = Some(node_visibility_filter);
+        self
+    }
+
+    /// Set a custom continuation point store factory. This is called for each new session
+    /// to create storage for Browse, HistoryRead and Query continuation points.
+    ///
+    /// The default stores continuation points in memory, limited by
+    /// `max_browse_continuation_points`, `max_history_continuation_points` and
+    /// `max_query_continuation_points` in the server limits.
+    pub fn with_continuation_point_store(
+        mut self,
+        continuation_point_store: Arc<dyn ContinuationPointStoreFactory>,
+    ) -> Self {
+        self.continuation_point_store = Some(continuation_point_store);
+        self
+    }
+
+    /// Set the security key service, implementing the `GetSecurityKeys` and
+    /// `GetSecurityGroup` methods on the `PublishSubscribe` object.
+    #[cfg(feature = "pubsub")]
+    pub fn with_security_key_service(
+        mut self,
+        security_key_service: Arc<crate::pubsub::SecurityKeyService>,
+    ) -> Self {
+        self.security_key_service = Some(security_key_service);
+        self
+    }
+
     /// Set information about the application exposed to the user in the
     /// `ServerStatus/BuildInfo` variable on the server.
     pub fn build_info(mut self, build_info: BuildInfo) -> Self {
@@ -294,6 +354,15 @@ impl ServerBuilder {
         self
     }
 
+    /// Set the capabilities of the server, exposed in the `ServerCapabilities` object.
+    /// This includes the supported server profiles and historical access capabilities.
+    ///
+    /// Note that this does not enable any features, it only declares them to clients.
+    pub fn capabilities(mut self, capabilities: ServerCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
     /// Server application name.
     pub fn application_name(mut self, application_name: impl Into<String>) -> Self {
         self.config.application_name = application_name.into();
@@ -313,11 +382,22 @@ impl ServerBuilder {
     }
 
     /// Autocreates public / private keypair if they do not exist.
+    ///
+    /// The keypair is self-signed and stored in the PKI directory, so it is only created
+    /// on the first start. This is disabled by default, and should not be used in production.
     pub fn create_sample_keypair(mut self, create_sample_keypair: bool) -> Self {
         self.config.create_sample_keypair = create_sample_keypair;
         self
     }
 
+    /// Additional DNS names or IP addresses to include in an automatically created
+    /// application instance certificate, in addition to the configured host and
+    /// the host names of this machine. See [`ServerBuilder::create_sample_keypair`].
+    pub fn certificate_host_names(mut self, host_names: Vec<String>) -> Self {
+        self.config.certificate_host_names = host_names;
+        self
+    }
+
     /// Path to a custom certificate, to be used instead of the default .der certificate
     pub fn certificate_path(mut self, certificate_path: impl Into<PathBuf>) -> Self {
         self.config.certificate_path = Some(certificate_path.into());
@@ -343,6 +423,16 @@ impl ServerBuilder {
         self
     }
 
+    /// Only log a warning when the application URI of a client does not match the
+    /// URI in its certificate, instead of rejecting the session. Typically only used
+    /// for interoperability with clients that do not set the URI correctly.
+    pub fn warn_on_application_uri_mismatch(mut self, warn_only: bool) -> Self {
+        self.config
+            .certificate_validation
+            .warn_on_application_uri_mismatch = warn_only;
+        self
+    }
+
     /// PKI folder, either absolute or relative to executable.
     pub fn pki_dir(mut self, pki_dir: impl Into<PathBuf>) -> Self {
         self.config.pki_dir = pki_dir.into();
@@ -364,12 +454,48 @@ impl ServerBuilder {
         self
     }
 
+    /// Maximum number of concurrently open secure channels from a single remote
+    /// IP address. Connections beyond this limit are refused. 0 for no limit.
+    pub fn max_secure_channels_per_ip(mut self, max_secure_channels_per_ip: usize) -> Self {
+        self.config.tcp_config.max_secure_channels_per_ip = max_secure_channels_per_ip;
+        self
+    }
+
+    /// Set a list of remote IP addresses exempt from the limit set by
+    /// `max_secure_channels_per_ip`.
+    pub fn secure_channel_limit_allowlist(mut self, allowlist: Vec<IpAddr>) -> Self {
+        self.config.tcp_config.secure_channel_limit_allowlist = allowlist;
+        self
+    }
+
+    /// Minimum interval in milliseconds between log messages for repeated transport errors.
+    /// Errors from the same client address within the interval are counted and reported
+    /// with the next message, which keeps the log readable when a client connection is
+    /// flapping. 0 to log every error.
+    pub fn transport_error_log_interval_ms(mut self, interval_ms: u64) -> Self {
+        self.config.tcp_config.transport_error_log_interval_ms = interval_ms;
+        self
+    }
+
     /// Hostname to listen to incoming TCP connections on.
     pub fn host(mut self, host: impl Into<String>) -> Self {
         self.config.tcp_config.host = host.into();
         self
     }
 
+    /// Set how the host part of endpoint URLs returned from `GetEndpoints`
+    /// and `FindServers` is chosen. By default the configured host is used.
+    pub fn endpoint_host_substitution(mut self, substitution: EndpointHostSubstitution) -> Self {
+        self.config.endpoint_host_substitution = substitution;
+        self
+    }
+
+    /// Configure the periodic check of the expiry date of the application instance certificate.
+    pub fn certificate_expiry(mut self, certificate_expiry: CertificateExpiryConfig) -> Self {
+        self.config.certificate_expiry = certificate_expiry;
+        self
+    }
+
     /// Port number used to listen for incoming TCP connections.
     pub fn port(mut self, port: u16) -> Self {
         self.config.tcp_config.port = port;
@@ -492,6 +618,13 @@ impl ServerBuilder {
         self
     }
 
+    /// Maximum number of unused send buffers kept per connection for reuse.
+    /// Set to zero to allocate new buffers for every message.
+    pub fn send_buffer_pool_size(mut self, send_buffer_pool_size: usize) -> Self {
+        self.config.limits.send_buffer_pool_size = send_buffer_pool_size;
+        self
+    }
+
     /// Maximum receive buffer size, can be negotiated lower with clients.
     pub fn receive_buffer_size(mut self, receive_buffer_size: usize) -> Self {
         self.config.limits.receive_buffer_size = receive_buffer_size;
@@ -532,6 +665,13 @@ impl ServerBuilder {
         self
     }
 
+    /// Maximum time in milliseconds a secure channel can stay open without a session
+    /// before it is closed. 0 for no limit, which is the default.
+    pub fn secure_channel_idle_timeout_ms(mut self, secure_channel_idle_timeout_ms: u64) -> Self {
+        self.config.secure_channel_idle_timeout_ms = secure_channel_idle_timeout_ms;
+        self
+    }
+
     /// Set the cancellation token used by the server. You only need to
     /// set the token if you need to use a token from somewhere else to cancel,
     /// otherwise you can get the token after building the server with
@@ -541,6 +681,17 @@ impl ServerBuilder {
         self
     }
 
+    /// Set a tokio runtime used to run samplers in the built-in node managers.
+    /// Sampling can be CPU-heavy, running it on a dedicated runtime keeps it
+    /// from starving request processing. Custom node managers can get the
+    /// runtime from `ServerInfo::sampler_runtime`.
+    ///
+    /// By default samplers are spawned on the runtime the server is built on.
+    pub fn sampler_runtime(mut self, handle: Handle) -> Self {
+        self.sampler_runtime = Some(handle);
+        self
+    }
+
     /// Register a type loader. Any deserialization will
     /// use this type loader to handle types coming from the user.
     ///
@@ -552,8 +703,42 @@ impl ServerBuilder {
 
     /// Set whether to enable diagnostics on the server or not.
     /// Only users with the right permissions can read the diagnostics
+    ///
+    /// This enables or disables all groups of diagnostics, use
+    /// [`ServerBuilder::diagnostics`] for finer control.
     pub fn diagnostics_enabled(mut self, enabled: bool) -> Self {
         self.config.diagnostics = enabled;
         self
     }
+
+    /// Set which groups of diagnostics are collected by the server, enabling
+    /// diagnostics if any group is enabled.
+    /// Only users with the right permissions can read the diagnostics
+    pub fn diagnostics(mut self, diagnostics: DiagnosticsConfig) -> Self {
+        self.config.diagnostics = diagnostics.enabled();
+        self.config.diagnostics_groups = diagnostics;
+        self
+    }
+
+    /// Enable or disable diagnostic info on service faults, returned to clients
+    /// that request service level diagnostics.
+    pub fn service_fault_diagnostics(mut self, enabled: bool) -> Self {
+        self.config.service_fault_diagnostics.enabled = enabled;
+        self
+    }
+
+    /// Set the diagnostic info returned with service faults with the given status code.
+    /// This does not enable service fault diagnostics on its own,
+    /// see [`ServerBuilder::service_fault_diagnostics`].
+    pub fn service_fault_diagnostic(
+        mut self,
+        status: StatusCode,
+        diagnostic: ServiceFaultDiagnostic,
+    ) -> Self {
+        self.config
+            .service_fault_diagnostics
+            .status_codes
+            .insert(status.sub_code().name().to_owned(), diagnostic);
+        self
+    }
 }
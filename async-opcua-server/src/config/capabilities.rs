@@ -1,3 +1,5 @@
+use std::fmt;
+
 use opcua_types::NodeId;
 
 #[derive(Debug, Clone, Default)]
@@ -46,5 +48,114 @@ pub struct ServerCapabilities {
     /// Historical server capabilities.
     pub history: HistoryServerCapabilities,
     /// Supported server profiles.
-    pub profiles: Vec<String>,
+    pub profiles: Vec<Profile>,
+}
+
+impl ServerCapabilities {
+    /// Add a supported server profile, returning the modified capabilities.
+    pub fn with_profile(mut self, profile: impl Into<Profile>) -> Self {
+        self.add_profile(profile);
+        self
+    }
+
+    /// Add a supported server profile. Profiles that are already present are ignored.
+    pub fn add_profile(&mut self, profile: impl Into<Profile>) {
+        let profile = profile.into();
+        if !self.profiles.contains(&profile) {
+            self.profiles.push(profile);
+        }
+    }
+
+    /// Get the URIs of the supported server profiles, as exposed in
+    /// `ServerCapabilities/ServerProfileArray`.
+    pub fn profile_uris(&self) -> Vec<String> {
+        self.profiles.iter().map(|p| p.uri().to_owned()).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A server profile or facet, identified by its URI.
+///
+/// See OPC-UA Part 7 for the meaning of each profile. Conformance tooling
+/// requires the URIs to match exactly, so prefer the named variants over `Custom`.
+pub enum Profile {
+    /// Nano Embedded Device 2017 Server Profile.
+    NanoEmbeddedDevice2017,
+    /// Micro Embedded Device 2017 Server Profile.
+    MicroEmbeddedDevice2017,
+    /// Embedded 2017 UA Server Profile.
+    EmbeddedUA2017,
+    /// Standard 2017 UA Server Profile.
+    StandardUA2017,
+    /// Core 2017 Server Facet.
+    Core2017Facet,
+    /// Data Access Server Facet.
+    DataAccess,
+    /// Standard Event Subscription Server Facet.
+    StandardEventSubscription,
+    /// Method Server Facet.
+    Methods,
+    /// Historical Raw Data Server Facet.
+    HistoricalRawData,
+    /// Any other profile, given by its URI.
+    Custom(String),
+}
+
+impl Profile {
+    const KNOWN: [Profile; 9] = [
+        Profile::NanoEmbeddedDevice2017,
+        Profile::MicroEmbeddedDevice2017,
+        Profile::EmbeddedUA2017,
+        Profile::StandardUA2017,
+        Profile::Core2017Facet,
+        Profile::DataAccess,
+        Profile::StandardEventSubscription,
+        Profile::Methods,
+        Profile::HistoricalRawData,
+    ];
+
+    /// Get the canonical URI of this profile.
+    pub fn uri(&self) -> &str {
+        match self {
+            Profile::NanoEmbeddedDevice2017 => {
+                "http://opcfoundation.org/UA-Profile/Server/NanoEmbeddedDevice2017"
+            }
+            Profile::MicroEmbeddedDevice2017 => {
+                "http://opcfoundation.org/UA-Profile/Server/MicroEmbeddedDevice2017"
+            }
+            Profile::EmbeddedUA2017 => "http://opcfoundation.org/UA-Profile/Server/EmbeddedUA2017",
+            Profile::StandardUA2017 => "http://opcfoundation.org/UA-Profile/Server/StandardUA2017",
+            Profile::Core2017Facet => "http://opcfoundation.org/UA-Profile/Server/Core2017Facet",
+            Profile::DataAccess => "http://opcfoundation.org/UA-Profile/Server/DataAccess",
+            Profile::StandardEventSubscription => {
+                "http://opcfoundation.org/UA-Profile/Server/StandardEventSubscription"
+            }
+            Profile::Methods => "http://opcfoundation.org/UA-Profile/Server/Methods",
+            Profile::HistoricalRawData => {
+                "http://opcfoundation.org/UA-Profile/Server/HistoricalRawData"
+            }
+            Profile::Custom(uri) => uri,
+        }
+    }
+}
+
+impl From<&str> for Profile {
+    fn from(uri: &str) -> Self {
+        Self::KNOWN
+            .into_iter()
+            .find(|p| p.uri() == uri)
+            .unwrap_or_else(|| Profile::Custom(uri.to_owned()))
+    }
+}
+
+impl From<String> for Profile {
+    fn from(uri: String) -> Self {
+        Self::from(uri.as_str())
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.uri())
+    }
 }
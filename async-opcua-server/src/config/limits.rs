@@ -21,6 +21,10 @@ pub struct Limits {
     /// Send buffer size in bytes
     #[serde(default = "defaults::send_buffer_size")]
     pub send_buffer_size: usize,
+    /// Maximum number of unused send buffers kept per connection for reuse
+    /// when encoding outgoing messages. Zero disables reuse.
+    #[serde(default = "defaults::send_buffer_pool_size")]
+    pub send_buffer_pool_size: usize,
     /// Receive buffer size in bytes
     #[serde(default = "defaults::receive_buffer_size")]
     pub receive_buffer_size: usize,
@@ -53,6 +57,7 @@ impl Default for Limits {
             max_message_size: defaults::max_message_size(),
             max_chunk_count: defaults::max_chunk_count(),
             send_buffer_size: defaults::send_buffer_size(),
+            send_buffer_pool_size: defaults::send_buffer_pool_size(),
             receive_buffer_size: defaults::receive_buffer_size(),
             subscriptions: Default::default(),
             max_browse_continuation_points: defaults::max_browse_continuation_points(),
@@ -83,6 +88,9 @@ pub struct SubscriptionLimits {
     /// Specifies the minimum publishing interval for this server in milliseconds.
     #[serde(default = "defaults::min_publishing_interval_ms")]
     pub min_publishing_interval_ms: f64,
+    /// Specifies the maximum publishing interval for this server in milliseconds.
+    #[serde(default = "defaults::max_publishing_interval_ms")]
+    pub max_publishing_interval_ms: f64,
     /// Maximum value of `KeepAliveCount`
     #[serde(default = "defaults::max_keep_alive_count")]
     pub max_keep_alive_count: u32,
@@ -104,6 +112,11 @@ pub struct SubscriptionLimits {
     /// Maximum number of queued notifications per subscription. 0 for unlimited.
     #[serde(default = "defaults::max_queued_notifications")]
     pub max_queued_notifications: usize,
+    /// Maximum number of sent notification messages kept per subscription
+    /// for `Republish` until they are acknowledged. Beyond this the oldest
+    /// messages are dropped. 0 disables republishing.
+    #[serde(default = "defaults::max_retained_notifications_per_subscription")]
+    pub max_retained_notifications_per_subscription: usize,
 }
 
 impl Default for SubscriptionLimits {
@@ -115,6 +128,7 @@ impl Default for SubscriptionLimits {
             ),
             min_sampling_interval_ms: defaults::min_sampling_interval_ms(),
             min_publishing_interval_ms: defaults::min_publishing_interval_ms(),
+            max_publishing_interval_ms: defaults::max_publishing_interval_ms(),
             max_keep_alive_count: defaults::max_keep_alive_count(),
             default_keep_alive_count: defaults::default_keep_alive_count(),
             max_monitored_items_per_sub: defaults::max_monitored_items_per_sub(),
@@ -122,6 +136,8 @@ impl Default for SubscriptionLimits {
             max_lifetime_count: defaults::max_lifetime_count(),
             max_notifications_per_publish: defaults::max_notifications_per_publish(),
             max_queued_notifications: defaults::max_queued_notifications(),
+            max_retained_notifications_per_subscription:
+                defaults::max_retained_notifications_per_subscription(),
         }
     }
 }
@@ -228,6 +244,9 @@ mod defaults {
     pub(super) fn send_buffer_size() -> usize {
         constants::SEND_BUFFER_SIZE
     }
+    pub(super) fn send_buffer_pool_size() -> usize {
+        constants::SEND_BUFFER_POOL_SIZE
+    }
     pub(super) fn receive_buffer_size() -> usize {
         constants::RECEIVE_BUFFER_SIZE
     }
@@ -259,6 +278,9 @@ mod defaults {
     pub(super) fn min_publishing_interval_ms() -> f64 {
         constants::MIN_PUBLISHING_INTERVAL_MS
     }
+    pub(super) fn max_publishing_interval_ms() -> f64 {
+        constants::MAX_PUBLISHING_INTERVAL_MS
+    }
     pub(super) fn max_keep_alive_count() -> u32 {
         constants::MAX_KEEP_ALIVE_COUNT
     }
@@ -280,6 +302,9 @@ mod defaults {
     pub(super) fn max_queued_notifications() -> usize {
         constants::MAX_QUEUED_NOTIFICATIONS
     }
+    pub(super) fn max_retained_notifications_per_subscription() -> usize {
+        constants::MAX_RETAINED_NOTIFICATIONS_PER_SUBSCRIPTION
+    }
 
     pub(super) fn max_nodes_per_translate_browse_paths_to_node_ids() -> usize {
         constants::MAX_NODES_PER_TRANSLATE_BROWSE_PATHS_TO_NODE_IDS
@@ -6,6 +6,7 @@
 
 use std::{
     collections::BTreeMap,
+    net::IpAddr,
     path::{Path, PathBuf},
 };
 
@@ -16,8 +17,8 @@ use crate::constants;
 use opcua_core::{comms::url::url_matches_except_host, config::Config};
 use opcua_crypto::{CertificateStore, SecurityPolicy, Thumbprint};
 use opcua_types::{
-    ApplicationDescription, ApplicationType, DecodingOptions, LocalizedText, MessageSecurityMode,
-    UAString,
+    status_code::SubStatusCode, ApplicationDescription, ApplicationType, DecodingOptions,
+    LocalizedText, MessageSecurityMode, UAString,
 };
 
 use super::{endpoint::ServerEndpoint, limits::Limits};
@@ -34,6 +35,124 @@ pub struct TcpConfig {
     pub host: String,
     /// The port number of the service
     pub port: u16,
+    /// Maximum number of concurrently open secure channels from a single
+    /// remote IP address. 0 for no limit.
+    #[serde(default)]
+    pub max_secure_channels_per_ip: usize,
+    /// Remote IP addresses exempt from `max_secure_channels_per_ip`.
+    #[serde(default)]
+    pub secure_channel_limit_allowlist: Vec<IpAddr>,
+    /// Minimum interval in milliseconds between log messages for repeated transport errors.
+    /// Errors occurring within this interval of the last logged error from the same
+    /// client address are counted, and the count is included in the next message.
+    /// 0 to log every error.
+    #[serde(default = "defaults::transport_error_log_interval_ms")]
+    pub transport_error_log_interval_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+/// How the host part of endpoint URLs returned from `GetEndpoints` and `FindServers`
+/// is chosen. This is useful when the server runs behind NAT or in a container, where
+/// the configured host is not the host clients use to reach the server.
+pub enum EndpointHostSubstitution {
+    /// Use the configured host as is.
+    #[default]
+    None,
+    /// Replace the host with a fixed value.
+    Static(String),
+    /// Replace the host with the host the client used to connect, taken from
+    /// the endpoint URL in the client's `HELLO` message.
+    EchoClientHost,
+    /// Return each endpoint URL once for each host in the list.
+    List(Vec<String>),
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+/// Configuration for which groups of server diagnostics are collected when
+/// [`ServerConfig::diagnostics`] is enabled. Collecting diagnostics has some overhead,
+/// so each group can be enabled separately. By default all groups are enabled.
+pub struct DiagnosticsConfig {
+    /// Collect session diagnostics, such as the current and cumulated session count,
+    /// and the number of sessions that were rejected, timed out, or aborted.
+    pub sessions: bool,
+    /// Collect subscription diagnostics, such as the current and cumulated subscription
+    /// count, and the number of publishing intervals.
+    pub subscriptions: bool,
+    /// Collect request diagnostics, such as the number of rejected requests.
+    pub requests: bool,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl DiagnosticsConfig {
+    /// Create a new diagnostics config with all groups either enabled or disabled.
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            sessions: enabled,
+            subscriptions: enabled,
+            requests: enabled,
+        }
+    }
+
+    /// Enable or disable collection of session diagnostics.
+    pub fn sessions(mut self, enabled: bool) -> Self {
+        self.sessions = enabled;
+        self
+    }
+
+    /// Enable or disable collection of subscription diagnostics.
+    pub fn subscriptions(mut self, enabled: bool) -> Self {
+        self.subscriptions = enabled;
+        self
+    }
+
+    /// Enable or disable collection of request diagnostics.
+    pub fn requests(mut self, enabled: bool) -> Self {
+        self.requests = enabled;
+        self
+    }
+
+    /// Return `true` if any diagnostics are enabled.
+    pub fn enabled(&self) -> bool {
+        self.sessions || self.subscriptions || self.requests
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+/// Configuration of the diagnostic info attached to service faults, for clients
+/// that request service level diagnostics through `ReturnDiagnostics`.
+pub struct ServiceFaultDiagnosticsConfig {
+    /// Attach diagnostic info to service faults. Unless overridden in `status_codes`,
+    /// the symbolic ID and text are the name and description of the status code.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Diagnostic info for specific status codes, keyed by the name of the
+    /// status code, for example `BadNodeIdUnknown`.
+    #[serde(default)]
+    pub status_codes: BTreeMap<String, ServiceFaultDiagnostic>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+/// Diagnostic info returned with service faults for a specific status code.
+pub struct ServiceFaultDiagnostic {
+    /// Symbolic ID, replacing the name of the status code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbolic_id: Option<String>,
+    /// Human readable text, replacing the description of the status code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Locale of `text`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Additional application specific information.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub additional_info: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
@@ -53,6 +172,9 @@ pub struct ServerUserToken {
     #[serde(default)]
     /// Access to read diagnostics on the server.
     pub read_diagnostics: bool,
+    #[serde(default)]
+    /// Administrative access to the server.
+    pub admin: bool,
 }
 
 impl ServerUserToken {
@@ -67,6 +189,7 @@ impl ServerUserToken {
             x509: None,
             thumbprint: None,
             read_diagnostics: false,
+            admin: false,
         }
     }
 
@@ -81,6 +204,7 @@ impl ServerUserToken {
             x509: Some(cert_path.to_string_lossy().to_string()),
             thumbprint: None,
             read_diagnostics: false,
+            admin: false,
         }
     }
 
@@ -144,6 +268,40 @@ impl ServerUserToken {
         self.read_diagnostics = read;
         self
     }
+
+    /// Set whether the user has administrative access to the server.
+    pub fn admin(mut self, admin: bool) -> Self {
+        self.admin = admin;
+        self
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+/// Configuration of the periodic check of the expiry date of the application
+/// instance certificate.
+pub struct CertificateExpiryConfig {
+    /// Log a warning when the certificate expires within this many days.
+    /// Set to 0 to only warn once the certificate has expired.
+    #[serde(default = "defaults::certificate_expiry_warning_days")]
+    pub warning_days: u32,
+    /// Interval in seconds between each check of the certificate. Set to 0 to disable the check.
+    #[serde(default = "defaults::certificate_expiry_check_interval_secs")]
+    pub check_interval_secs: u64,
+    /// Emit an `AuditCertificateEventType` event from the `Server` object when the certificate
+    /// is about to expire, and an `AuditCertificateExpiredEventType` event once it has expired.
+    /// This requires the `generated-address-space` feature.
+    #[serde(default)]
+    pub emit_events: bool,
+}
+
+impl Default for CertificateExpiryConfig {
+    fn default() -> Self {
+        Self {
+            warning_days: defaults::certificate_expiry_warning_days(),
+            check_interval_secs: defaults::certificate_expiry_check_interval_secs(),
+            emit_events: false,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
@@ -154,6 +312,11 @@ pub struct CertificateValidation {
     pub trust_client_certs: bool,
     /// Check the valid from/to fields of a certificate
     pub check_time: bool,
+    /// Only log a warning when the application URI of a client does not match the URI in
+    /// its certificate, instead of rejecting session activation with `BadCertificateUriInvalid`.
+    /// For interoperability with clients that do not set the URI correctly.
+    #[serde(default)]
+    pub warn_on_application_uri_mismatch: bool,
 }
 
 impl Default for CertificateValidation {
@@ -161,6 +324,7 @@ impl Default for CertificateValidation {
         Self {
             trust_client_certs: false,
             check_time: true,
+            warn_on_application_uri_mismatch: false,
         }
     }
 }
@@ -176,8 +340,16 @@ pub struct ServerConfig {
     pub product_uri: String,
     /// Autocreates public / private keypair if they don't exist. For testing/samples only
     /// since you do not have control of the values
+    ///
+    /// The generated certificate is self-signed, persisted in the PKI directory, and
+    /// contains the application URI, the configured host, `certificate_host_names`,
+    /// and the host names and addresses of this machine.
     #[serde(default)]
     pub create_sample_keypair: bool,
+    /// Additional DNS names or IP addresses to include in an automatically created
+    /// application instance certificate, see `create_sample_keypair`.
+    #[serde(default)]
+    pub certificate_host_names: Vec<String>,
     /// Path to a custom certificate, to be used instead of the default .der certificate
     #[serde(default)]
     pub certificate_path: Option<PathBuf>,
@@ -236,14 +408,38 @@ pub struct ServerConfig {
     /// we will instantly time out.
     #[serde(default = "defaults::max_session_timeout_ms")]
     pub max_session_timeout_ms: u64,
+    /// Time in milliseconds a secure channel may stay open without a session
+    /// before it is closed. 0 for no limit, which is the default.
+    #[serde(default = "defaults::secure_channel_idle_timeout_ms")]
+    pub secure_channel_idle_timeout_ms: u64,
     /// Enable server diagnostics.
     #[serde(default)]
     pub diagnostics: bool,
+    /// Which groups of server diagnostics are collected, if `diagnostics` is enabled.
+    #[serde(default)]
+    pub diagnostics_groups: DiagnosticsConfig,
+    /// Configuration of the diagnostic info returned with service faults.
+    #[serde(default)]
+    pub service_fault_diagnostics: ServiceFaultDiagnosticsConfig,
+    /// How to substitute the host of endpoint URLs returned to clients.
+    #[serde(default)]
+    pub endpoint_host_substitution: EndpointHostSubstitution,
+    /// Configuration of the check of the expiry date of the application instance certificate.
+    #[serde(default)]
+    pub certificate_expiry: CertificateExpiryConfig,
 }
 
 mod defaults {
     use crate::constants;
 
+    pub(super) fn certificate_expiry_warning_days() -> u32 {
+        30
+    }
+
+    pub(super) fn certificate_expiry_check_interval_secs() -> u64 {
+        24 * 60 * 60
+    }
+
     pub(super) fn subscription_poll_interval_ms() -> u64 {
         constants::SUBSCRIPTION_TIMER_RATE_MS
     }
@@ -263,6 +459,14 @@ mod defaults {
     pub(super) fn max_session_timeout_ms() -> u64 {
         constants::MAX_SESSION_TIMEOUT
     }
+
+    pub(super) fn secure_channel_idle_timeout_ms() -> u64 {
+        constants::DEFAULT_SECURE_CHANNEL_IDLE_TIMEOUT_MS
+    }
+
+    pub(super) fn transport_error_log_interval_ms() -> u64 {
+        constants::DEFAULT_TRANSPORT_ERROR_LOG_INTERVAL_MS
+    }
 }
 
 impl Config for ServerConfig {
@@ -314,9 +518,23 @@ impl Config for ServerConfig {
                 "Server configuration is invalid. Max byte string length is invalid".to_owned(),
             );
         }
+        let subscriptions = &self.limits.subscriptions;
+        if subscriptions.min_publishing_interval_ms > subscriptions.max_publishing_interval_ms {
+            errors.push(
+                "Server configuration is invalid. Min publishing interval is greater than max publishing interval"
+                    .to_owned(),
+            );
+        }
         if self.discovery_urls.is_empty() {
             errors.push("Server configuration is invalid. Discovery urls not set".to_owned());
         }
+        for name in self.service_fault_diagnostics.status_codes.keys() {
+            if name.parse::<SubStatusCode>().is_err() {
+                errors.push(format!(
+                    "Server configuration is invalid. Unknown status code {name} in service fault diagnostics"
+                ));
+            }
+        }
 
         if errors.is_empty() {
             Ok(())
@@ -370,6 +588,7 @@ impl Default for ServerConfig {
             application_uri: String::new(),
             product_uri: String::new(),
             create_sample_keypair: false,
+            certificate_host_names: Vec::new(),
             certificate_path: None,
             private_key_path: None,
             pki_dir,
@@ -379,6 +598,9 @@ impl Default for ServerConfig {
                 host: "127.0.0.1".to_string(),
                 port: constants::DEFAULT_RUST_OPC_UA_SERVER_PORT,
                 hello_timeout: constants::DEFAULT_HELLO_TIMEOUT_SECONDS,
+                max_secure_channels_per_ip: 0,
+                secure_channel_limit_allowlist: Vec::new(),
+                transport_error_log_interval_ms: constants::DEFAULT_TRANSPORT_ERROR_LOG_INTERVAL_MS,
             },
             limits: Limits::default(),
             user_tokens: BTreeMap::new(),
@@ -391,7 +613,12 @@ impl Default for ServerConfig {
             max_timeout_ms: defaults::max_timeout_ms(),
             max_secure_channel_token_lifetime_ms: defaults::max_secure_channel_token_lifetime_ms(),
             max_session_timeout_ms: defaults::max_session_timeout_ms(),
+            secure_channel_idle_timeout_ms: defaults::secure_channel_idle_timeout_ms(),
             diagnostics: false,
+            diagnostics_groups: DiagnosticsConfig::default(),
+            service_fault_diagnostics: ServiceFaultDiagnosticsConfig::default(),
+            endpoint_host_substitution: EndpointHostSubstitution::None,
+            certificate_expiry: CertificateExpiryConfig::default(),
         }
     }
 }
@@ -430,6 +657,7 @@ impl ServerConfig {
             certificate_validation: CertificateValidation {
                 trust_client_certs: false,
                 check_time: true,
+                warn_on_application_uri_mismatch: false,
             },
             pki_dir,
             discovery_server_url,
@@ -437,6 +665,9 @@ impl ServerConfig {
                 host,
                 port,
                 hello_timeout: constants::DEFAULT_HELLO_TIMEOUT_SECONDS,
+                max_secure_channels_per_ip: 0,
+                secure_channel_limit_allowlist: Vec::new(),
+                transport_error_log_interval_ms: constants::DEFAULT_TRANSPORT_ERROR_LOG_INTERVAL_MS,
             },
             locale_ids,
             user_tokens,
@@ -446,6 +677,23 @@ impl ServerConfig {
         }
     }
 
+    /// Host names and addresses to include in an automatically created application
+    /// instance certificate, in addition to those of this machine.
+    pub(crate) fn sample_keypair_host_names(&self) -> Vec<String> {
+        let mut host_names = Vec::new();
+        let host = self.tcp_config.host.as_str();
+        // Unspecified addresses used to listen on all interfaces are not valid host names.
+        if !host.is_empty() && host != "0.0.0.0" && host != "::" {
+            host_names.push(host.to_owned());
+        }
+        for host_name in &self.certificate_host_names {
+            if !host_names.contains(host_name) {
+                host_names.push(host_name.clone());
+            }
+        }
+        host_names
+    }
+
     /// Decoding options given by this config.
     pub fn decoding_options(&self) -> DecodingOptions {
         DecodingOptions {
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use opcua_types::{
+    status_code::SubStatusCode, DiagnosticBits, DiagnosticInfo, ResponseHeader, ServiceFault,
+    StatusCode, UAString,
+};
+
+use crate::config::{ServiceFaultDiagnostic, ServiceFaultDiagnosticsConfig};
+
+/// Registry of diagnostic info attached to service faults, for clients that
+/// request service level diagnostics.
+pub struct ServiceFaultDiagnostics {
+    enabled: bool,
+    status_codes: HashMap<StatusCode, ServiceFaultDiagnostic>,
+}
+
+impl ServiceFaultDiagnostics {
+    /// Create a new registry from server configuration. Entries with unknown
+    /// status code names are ignored.
+    pub fn new(config: &ServiceFaultDiagnosticsConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            status_codes: config
+                .status_codes
+                .iter()
+                .filter_map(|(name, diag)| {
+                    let code = name.parse::<SubStatusCode>().ok()?;
+                    Some((StatusCode::from_category(code), diag.clone()))
+                })
+                .collect(),
+        }
+    }
+
+    /// Create a service fault for `status`, with diagnostic info if requested
+    /// in `return_diagnostics`.
+    pub fn service_fault(
+        &self,
+        request_handle: u32,
+        return_diagnostics: DiagnosticBits,
+        status: StatusCode,
+    ) -> ServiceFault {
+        let mut response_header = ResponseHeader::new_service_result(request_handle, status);
+        if self.enabled {
+            self.populate(&mut response_header, return_diagnostics);
+        }
+        ServiceFault { response_header }
+    }
+
+    fn populate(&self, header: &mut ResponseHeader, return_diagnostics: DiagnosticBits) {
+        let sub_code = header.service_result.sub_code();
+        let diag = self.status_codes.get(&StatusCode::from_category(sub_code));
+        let mut strings = Vec::new();
+        let mut push = |s: &str| {
+            strings.push(UAString::from(s));
+            Some(strings.len() as i32 - 1)
+        };
+        let mut info = DiagnosticInfo::default();
+
+        if return_diagnostics.contains(DiagnosticBits::SERVICE_LEVEL_SYMBOLIC_ID) {
+            info.symbolic_id = match diag.and_then(|d| d.symbolic_id.as_deref()) {
+                Some(id) => push(id),
+                None => push(sub_code.name()),
+            };
+        }
+        if return_diagnostics.contains(DiagnosticBits::SERVICE_LEVEL_LOCALIZED_TEXT) {
+            info.localized_text = match diag.and_then(|d| d.text.as_deref()) {
+                Some(text) => push(text),
+                None => push(sub_code.description().trim()),
+            };
+            if let Some(locale) = diag.and_then(|d| d.locale.as_deref()) {
+                info.locale = push(locale);
+            }
+        }
+        if return_diagnostics.contains(DiagnosticBits::SERVICE_LEVEL_ADDITIONAL_INFO) {
+            info.additional_info = diag
+                .and_then(|d| d.additional_info.as_deref())
+                .map(UAString::from);
+        }
+
+        header.service_diagnostics = info;
+        if !strings.is_empty() {
+            header.string_table = Some(strings);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opcua_types::{DiagnosticBits, DiagnosticInfo, StatusCode, UAString};
+
+    use super::ServiceFaultDiagnostics;
+    use crate::config::{ServiceFaultDiagnostic, ServiceFaultDiagnosticsConfig};
+
+    fn registry(enabled: bool) -> ServiceFaultDiagnostics {
+        let mut config = ServiceFaultDiagnosticsConfig {
+            enabled,
+            ..Default::default()
+        };
+        config.status_codes.insert(
+            "BadNodeIdUnknown".to_owned(),
+            ServiceFaultDiagnostic {
+                symbolic_id: Some("UnknownNode".to_owned()),
+                text: Some("Ukjent node".to_owned()),
+                locale: Some("nb".to_owned()),
+                additional_info: Some("Check the namespace index".to_owned()),
+            },
+        );
+        ServiceFaultDiagnostics::new(&config)
+    }
+
+    #[test]
+    fn service_fault_registered() {
+        let fault = registry(true).service_fault(
+            5,
+            DiagnosticBits::SERVICE_LEVEL_SYMBOLIC_ID
+                | DiagnosticBits::SERVICE_LEVEL_LOCALIZED_TEXT
+                | DiagnosticBits::SERVICE_LEVEL_ADDITIONAL_INFO,
+            StatusCode::BadNodeIdUnknown,
+        );
+        let header = fault.response_header;
+        assert_eq!(header.request_handle, 5);
+        assert_eq!(header.service_result, StatusCode::BadNodeIdUnknown);
+        let strings = header.string_table.unwrap();
+        let info = header.service_diagnostics;
+        assert_eq!(
+            strings[info.symbolic_id.unwrap() as usize].as_ref(),
+            "UnknownNode"
+        );
+        assert_eq!(
+            strings[info.localized_text.unwrap() as usize].as_ref(),
+            "Ukjent node"
+        );
+        assert_eq!(strings[info.locale.unwrap() as usize].as_ref(), "nb");
+        assert_eq!(
+            info.additional_info,
+            Some(UAString::from("Check the namespace index"))
+        );
+    }
+
+    #[test]
+    fn service_fault_defaults() {
+        // Unregistered status codes use the name and description of the status code.
+        let fault = registry(true).service_fault(
+            1,
+            DiagnosticBits::SERVICE_LEVEL_SYMBOLIC_ID
+                | DiagnosticBits::SERVICE_LEVEL_LOCALIZED_TEXT,
+            StatusCode::BadTimeout,
+        );
+        let header = fault.response_header;
+        let strings = header.string_table.unwrap();
+        let info = header.service_diagnostics;
+        assert_eq!(
+            strings[info.symbolic_id.unwrap() as usize].as_ref(),
+            "BadTimeout"
+        );
+        assert_eq!(
+            strings[info.localized_text.unwrap() as usize].as_ref(),
+            "The operation timed out."
+        );
+        assert_eq!(info.locale, None);
+        assert_eq!(info.additional_info, None);
+
+        // Only requested fields are returned.
+        let fault = registry(true).service_fault(
+            1,
+            DiagnosticBits::OPERATIONAL_LEVEL_SYMBOLIC_ID,
+            StatusCode::BadNodeIdUnknown,
+        );
+        assert_eq!(
+            fault.response_header.service_diagnostics,
+            DiagnosticInfo::default()
+        );
+        assert_eq!(fault.response_header.string_table, None);
+
+        // Nothing is returned if service fault diagnostics are disabled.
+        let fault =
+            registry(false).service_fault(1, DiagnosticBits::all(), StatusCode::BadNodeIdUnknown);
+        assert_eq!(
+            fault.response_header.service_diagnostics,
+            DiagnosticInfo::default()
+        );
+        assert_eq!(fault.response_header.string_table, None);
+    }
+}
@@ -1,7 +1,9 @@
 //! This module contains the diagnostics node manager, and related types.
 
+mod fault;
 mod node_manager;
 mod server;
+pub use fault::ServiceFaultDiagnostics;
 pub use node_manager::{DiagnosticsNodeManager, DiagnosticsNodeManagerBuilder, NamespaceMetadata};
 use opcua_core::sync::Mutex;
 use opcua_types::{DataValue, DateTime, IntoVariant};
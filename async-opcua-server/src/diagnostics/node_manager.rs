@@ -5,24 +5,26 @@ use std::{
 };
 
 use async_trait::async_trait;
-use opcua_core::trace_read_lock;
+use opcua_core::{trace_lock, trace_read_lock};
 use opcua_nodes::DefaultTypeTree;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     address_space::AccessLevel,
+    load_method_args,
     node_manager::{
         as_opaque_node_id, from_opaque_node_id, impl_translate_browse_paths_using_browse,
         AddReferenceResult, BrowseNode, BrowsePathItem, DynNodeManager, ExternalReferenceRequest,
-        NodeManager, NodeManagerBuilder, NodeManagersRef, NodeMetadata, ReadNode, RequestContext,
-        ServerContext, SyncSampler,
+        MethodCall, NodeManager, NodeManagerBuilder, NodeManagersRef, NodeMetadata, ReadNode,
+        RequestContext, ServerContext, SyncSampler,
     },
 };
 use opcua_types::{
-    AccessLevelExType, AccessRestrictionType, AttributeId, BrowseDirection, DataTypeId, DataValue,
-    DateTime, ExpandedNodeId, ExtensionObject, IdType, LocalizedText, NodeClass, NodeId,
-    NumericRange, ObjectId, ObjectTypeId, QualifiedName, ReferenceDescription, ReferenceTypeId,
-    RolePermissionType, StatusCode, TimestampsToReturn, VariableTypeId, Variant,
+    argument::Argument, AccessLevelExType, AccessRestrictionType, AttributeId, BrowseDirection,
+    DataTypeId, DataValue, DateTime, ExpandedNodeId, ExtensionObject, IdType, LocalizedText,
+    NodeClass, NodeId, NumericRange, ObjectId, ObjectTypeId, QualifiedName, ReferenceDescription,
+    ReferenceTypeId, RolePermissionType, StatusCode, TimestampsToReturn, VariableTypeId, Variant,
+    VariantScalarTypeId, VariantTypeId,
 };
 
 /// Node manager handling nodes in the server hierarchy that are not part of the
@@ -89,6 +91,109 @@ struct NamespaceNode {
 #[derive(Serialize, Deserialize, Debug)]
 enum DiagnosticsNode {
     Namespace(NamespaceNode),
+    /// Vendor specific variant of `GetMonitoredItems` on the `Server` object,
+    /// which can look up subscriptions owned by any session. Requires admin permissions.
+    GetAllMonitoredItems,
+    /// Vendor specific variant of `ResendData` on the `Server` object, which only
+    /// resends the latest value of the monitored items with a given client handle.
+    /// Its input arguments are the subscription ID and the client handle, both `UInt32`.
+    ResendMonitoredItemData,
+    /// `InputArguments` or `OutputArguments` property of one of the vendor specific methods.
+    Arguments(ArgumentsNode),
+}
+
+/// One of the vendor specific methods on the `Server` object.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+enum VendorMethod {
+    GetAllMonitoredItems,
+    ResendMonitoredItemData,
+}
+
+impl VendorMethod {
+    fn node(self) -> DiagnosticsNode {
+        match self {
+            VendorMethod::GetAllMonitoredItems => DiagnosticsNode::GetAllMonitoredItems,
+            VendorMethod::ResendMonitoredItemData => DiagnosticsNode::ResendMonitoredItemData,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            VendorMethod::GetAllMonitoredItems => "GetAllMonitoredItems",
+            VendorMethod::ResendMonitoredItemData => "ResendMonitoredItemData",
+        }
+    }
+
+    /// Input and output arguments of the method.
+    fn arguments(self) -> (Vec<Argument>, Vec<Argument>) {
+        let arg = |name: &str, value_rank: i32, description: &str| Argument {
+            name: name.into(),
+            data_type: DataTypeId::UInt32.into(),
+            value_rank,
+            array_dimensions: (value_rank == 1).then(|| vec![0]),
+            description: LocalizedText::new("", description),
+        };
+        match self {
+            VendorMethod::GetAllMonitoredItems => (
+                vec![arg("SubscriptionId", -1, "ID of the subscription")],
+                vec![
+                    arg("ServerHandles", 1, "Server handles of the monitored items"),
+                    arg("ClientHandles", 1, "Client handles of the monitored items"),
+                ],
+            ),
+            VendorMethod::ResendMonitoredItemData => (
+                vec![
+                    arg("SubscriptionId", -1, "ID of the subscription"),
+                    arg(
+                        "ClientHandle",
+                        -1,
+                        "Client handle of the monitored items to resend",
+                    ),
+                ],
+                Vec::new(),
+            ),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ArgumentsNode {
+    method: VendorMethod,
+    output: bool,
+}
+
+impl ArgumentsNode {
+    fn name(&self) -> &'static str {
+        if self.output {
+            "OutputArguments"
+        } else {
+            "InputArguments"
+        }
+    }
+
+    /// Get the arguments described by this node, or `None` if the method
+    /// has no such arguments.
+    fn arguments(&self) -> Option<Vec<Argument>> {
+        let (input, output) = self.method.arguments();
+        let args = if self.output { output } else { input };
+        (!args.is_empty()).then_some(args)
+    }
+}
+
+impl DiagnosticsNode {
+    fn vendor_method(&self) -> Option<VendorMethod> {
+        match self {
+            DiagnosticsNode::Namespace(_) | DiagnosticsNode::Arguments(_) => None,
+            DiagnosticsNode::GetAllMonitoredItems => Some(VendorMethod::GetAllMonitoredItems),
+            DiagnosticsNode::ResendMonitoredItemData => Some(VendorMethod::ResendMonitoredItemData),
+        }
+    }
+
+    /// Browse name of the node, if it is one of the vendor specific methods
+    /// on the `Server` object.
+    fn method_name(&self) -> Option<&'static str> {
+        self.vendor_method().map(VendorMethod::name)
+    }
 }
 
 /// Builder for the diagnostics node manager.
@@ -546,6 +651,296 @@ impl DiagnosticsNodeManager {
             self.read_namespace_metadata_node(start_time, node_to_read, namespace);
         }
     }
+
+    fn method_metadata(&self, method: &DiagnosticsNode, name: &str) -> NodeMetadata {
+        NodeMetadata {
+            node_id: ExpandedNodeId::new(as_opaque_node_id(method, self.namespace_index).unwrap()),
+            type_definition: ExpandedNodeId::null(),
+            browse_name: QualifiedName::new(self.namespace_index, name),
+            display_name: LocalizedText::new("", name),
+            node_class: NodeClass::Method,
+        }
+    }
+
+    fn browse_server(&self, node_to_browse: &mut BrowseNode, type_tree: &DefaultTypeTree) {
+        if !matches!(
+            node_to_browse.browse_direction(),
+            BrowseDirection::Forward | BrowseDirection::Both
+        ) {
+            return;
+        }
+
+        let mut cp = BrowseContinuationPoint::default();
+        for method in [
+            DiagnosticsNode::GetAllMonitoredItems,
+            DiagnosticsNode::ResendMonitoredItemData,
+        ] {
+            let Some(name) = method.method_name() else {
+                continue;
+            };
+            let ref_desc = self
+                .method_metadata(&method, name)
+                .into_ref_desc(true, ReferenceTypeId::HasComponent);
+            if let AddReferenceResult::Full(c) = node_to_browse.add(type_tree, ref_desc) {
+                cp.nodes.push_back(c);
+            }
+        }
+
+        if !cp.nodes.is_empty() {
+            node_to_browse.set_next_continuation_point(Box::new(cp));
+        }
+    }
+
+    fn arguments_metadata(&self, arguments: &ArgumentsNode) -> NodeMetadata {
+        let name = arguments.name();
+        NodeMetadata {
+            node_id: ExpandedNodeId::new(
+                as_opaque_node_id(
+                    &DiagnosticsNode::Arguments(arguments.clone()),
+                    self.namespace_index,
+                )
+                .unwrap(),
+            ),
+            type_definition: VariableTypeId::PropertyType.into(),
+            browse_name: QualifiedName::new(0, name),
+            display_name: LocalizedText::new("", name),
+            node_class: NodeClass::Variable,
+        }
+    }
+
+    fn browse_method(
+        &self,
+        node_to_browse: &mut BrowseNode,
+        type_tree: &DefaultTypeTree,
+        method: VendorMethod,
+    ) {
+        let mut cp = BrowseContinuationPoint::default();
+
+        if matches!(
+            node_to_browse.browse_direction(),
+            BrowseDirection::Forward | BrowseDirection::Both
+        ) {
+            for output in [false, true] {
+                let arguments = ArgumentsNode { method, output };
+                if arguments.arguments().is_none() {
+                    continue;
+                }
+                let ref_desc = self
+                    .arguments_metadata(&arguments)
+                    .into_ref_desc(true, ReferenceTypeId::HasProperty);
+                if let AddReferenceResult::Full(c) = node_to_browse.add(type_tree, ref_desc) {
+                    cp.nodes.push_back(c);
+                }
+            }
+        }
+
+        if matches!(
+            node_to_browse.browse_direction(),
+            BrowseDirection::Inverse | BrowseDirection::Both
+        ) {
+            let ref_desc = ReferenceDescription {
+                reference_type_id: ReferenceTypeId::HasComponent.into(),
+                is_forward: false,
+                node_id: ObjectId::Server.into(),
+                browse_name: QualifiedName::new(0, "Server"),
+                display_name: LocalizedText::new("", "Server"),
+                node_class: NodeClass::Object,
+                type_definition: ObjectTypeId::ServerType.into(),
+            };
+            if let AddReferenceResult::Full(c) = node_to_browse.add(type_tree, ref_desc) {
+                cp.nodes.push_back(c);
+            }
+        }
+
+        if !cp.nodes.is_empty() {
+            node_to_browse.set_next_continuation_point(Box::new(cp));
+        }
+    }
+
+    fn browse_arguments(
+        &self,
+        node_to_browse: &mut BrowseNode,
+        type_tree: &DefaultTypeTree,
+        arguments: ArgumentsNode,
+    ) {
+        if arguments.arguments().is_none() {
+            node_to_browse.set_status(StatusCode::BadNodeIdUnknown);
+            return;
+        }
+
+        let mut cp = BrowseContinuationPoint::default();
+
+        if matches!(
+            node_to_browse.browse_direction(),
+            BrowseDirection::Forward | BrowseDirection::Both
+        ) {
+            let ref_desc = ReferenceDescription {
+                reference_type_id: ReferenceTypeId::HasTypeDefinition.into(),
+                is_forward: true,
+                node_id: VariableTypeId::PropertyType.into(),
+                browse_name: QualifiedName::new(0, "PropertyType"),
+                display_name: LocalizedText::new("", "PropertyType"),
+                node_class: NodeClass::VariableType,
+                type_definition: ExpandedNodeId::null(),
+            };
+            if let AddReferenceResult::Full(c) = node_to_browse.add(type_tree, ref_desc) {
+                cp.nodes.push_back(c);
+            }
+        }
+
+        if matches!(
+            node_to_browse.browse_direction(),
+            BrowseDirection::Inverse | BrowseDirection::Both
+        ) {
+            let ref_desc = self
+                .method_metadata(&arguments.method.node(), arguments.method.name())
+                .into_ref_desc(false, ReferenceTypeId::HasProperty);
+            if let AddReferenceResult::Full(c) = node_to_browse.add(type_tree, ref_desc) {
+                cp.nodes.push_back(c);
+            }
+        }
+
+        if !cp.nodes.is_empty() {
+            node_to_browse.set_next_continuation_point(Box::new(cp));
+        }
+    }
+
+    fn read_method(
+        &self,
+        context: &RequestContext,
+        start_time: DateTime,
+        node_to_read: &mut ReadNode,
+        method: &DiagnosticsNode,
+        name: &str,
+    ) {
+        let v: Variant = match node_to_read.node().attribute_id {
+            AttributeId::NodeId => as_opaque_node_id(method, self.namespace_index)
+                .unwrap()
+                .into(),
+            AttributeId::NodeClass => (NodeClass::Method as i32).into(),
+            AttributeId::BrowseName => QualifiedName::new(self.namespace_index, name).into(),
+            AttributeId::DisplayName => LocalizedText::new("", name).into(),
+            AttributeId::Executable => true.into(),
+            AttributeId::UserExecutable => match method {
+                DiagnosticsNode::GetAllMonitoredItems => context
+                    .authenticator
+                    .core_permissions(&context.token)
+                    .admin
+                    .into(),
+                _ => true.into(),
+            },
+            AttributeId::WriteMask | AttributeId::UserWriteMask => 0u32.into(),
+            _ => {
+                node_to_read.set_error(StatusCode::BadAttributeIdInvalid);
+                return;
+            }
+        };
+
+        node_to_read.set_result(DataValue {
+            value: Some(v),
+            status: Some(StatusCode::Good),
+            source_timestamp: Some(start_time),
+            source_picoseconds: None,
+            server_timestamp: Some(start_time),
+            server_picoseconds: None,
+        });
+    }
+
+    fn read_arguments(
+        &self,
+        start_time: DateTime,
+        node_to_read: &mut ReadNode,
+        arguments: &ArgumentsNode,
+    ) {
+        let Some(args) = arguments.arguments() else {
+            node_to_read.set_error(StatusCode::BadNodeIdUnknown);
+            return;
+        };
+        let name = arguments.name();
+
+        let v: Variant = match node_to_read.node().attribute_id {
+            AttributeId::NodeId => as_opaque_node_id(
+                &DiagnosticsNode::Arguments(arguments.clone()),
+                self.namespace_index,
+            )
+            .unwrap()
+            .into(),
+            AttributeId::NodeClass => (NodeClass::Variable as i32).into(),
+            AttributeId::BrowseName => QualifiedName::new(0, name).into(),
+            AttributeId::DisplayName => LocalizedText::new("", name).into(),
+            AttributeId::Value => args
+                .into_iter()
+                .map(ExtensionObject::from_message)
+                .collect::<Vec<_>>()
+                .into(),
+            AttributeId::DataType => Variant::NodeId(Box::new(DataTypeId::Argument.into())),
+            AttributeId::ValueRank => 1.into(),
+            AttributeId::ArrayDimensions => vec![0u32].into(),
+            AttributeId::AccessLevel | AttributeId::UserAccessLevel => {
+                AccessLevel::CURRENT_READ.bits().into()
+            }
+            AttributeId::AccessLevelEx => (AccessLevelExType::CurrentRead.bits() as u32).into(),
+            AttributeId::MinimumSamplingInterval => 0.0.into(),
+            AttributeId::Historizing => false.into(),
+            AttributeId::WriteMask | AttributeId::UserWriteMask => 0u32.into(),
+            _ => {
+                node_to_read.set_error(StatusCode::BadAttributeIdInvalid);
+                return;
+            }
+        };
+
+        node_to_read.set_result(DataValue {
+            value: Some(v),
+            status: Some(StatusCode::Good),
+            source_timestamp: Some(start_time),
+            source_picoseconds: None,
+            server_timestamp: Some(start_time),
+            server_picoseconds: None,
+        });
+    }
+
+    fn call_get_all_monitored_items(
+        &self,
+        context: &RequestContext,
+        call: &mut MethodCall,
+    ) -> Result<(), StatusCode> {
+        if call.object_id() != &ObjectId::Server {
+            return Err(StatusCode::BadMethodInvalid);
+        }
+        if !context.authenticator.core_permissions(&context.token).admin {
+            return Err(StatusCode::BadUserAccessDenied);
+        }
+        let id = load_method_args!(call, UInt32)?;
+        // Same as the standard `GetMonitoredItems`, but not restricted to the calling session.
+        let (ids, handles) = context.subscriptions.get_monitored_items(None, id)?;
+        call.set_outputs(vec![ids.into(), handles.into()]);
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
+
+    fn call_resend_monitored_item_data(
+        &self,
+        context: &RequestContext,
+        call: &mut MethodCall,
+    ) -> Result<(), StatusCode> {
+        if call.object_id() != &ObjectId::Server {
+            return Err(StatusCode::BadMethodInvalid);
+        }
+        let (id, client_handle) = load_method_args!(call, UInt32, UInt32)?;
+        let subs = context
+            .subscriptions
+            .get_session_subscriptions(context.session_id)
+            .ok_or(StatusCode::BadSessionIdInvalid)?;
+        let mut subs = trace_lock!(subs);
+        let sub = subs
+            .get_mut(id)
+            .ok_or(StatusCode::BadSubscriptionIdInvalid)?;
+        if !sub.set_resend_data_for_client_handle(client_handle) {
+            return Err(StatusCode::BadMonitoredItemIdInvalid);
+        }
+        call.set_status(StatusCode::Good);
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -577,9 +972,10 @@ impl NodeManager for DiagnosticsNodeManager {
             .min_sampling_interval_ms
             .floor() as u64;
         let sampler_interval = if interval > 0 { interval } else { 100 };
-        self.sampler.run(
+        self.sampler.run_on(
             Duration::from_millis(sampler_interval),
             context.subscriptions.clone(),
+            &context.info.sampler_runtime(),
         );
     }
 
@@ -611,6 +1007,18 @@ impl NodeManager for DiagnosticsNodeManager {
                         self.namespace_node_metadata(ns_node)
                     }
                 }
+                DiagnosticsNode::Arguments(arguments) => {
+                    if arguments.arguments().is_none() {
+                        continue;
+                    }
+                    self.arguments_metadata(&arguments)
+                }
+                method => {
+                    let Some(name) = method.method_name() else {
+                        continue;
+                    };
+                    self.method_metadata(&method, name)
+                }
             };
             req.set(meta);
         }
@@ -646,6 +1054,9 @@ impl NodeManager for DiagnosticsNodeManager {
                     ObjectId::Server_Namespaces => {
                         self.browse_namespaces(node, &type_tree, namespaces);
                     }
+                    ObjectId::Server => {
+                        self.browse_server(node, &type_tree);
+                    }
                     _ => continue,
                 }
             } else if node.node_id().namespace == self.namespace_index {
@@ -659,6 +1070,14 @@ impl NodeManager for DiagnosticsNodeManager {
                             lazy_namespaces.get_or_insert_with(|| self.namespaces(context));
                         self.browse_namespace_node(node, &type_tree, namespaces, &ns);
                     }
+                    DiagnosticsNode::Arguments(arguments) => {
+                        self.browse_arguments(node, &type_tree, arguments);
+                    }
+                    method => {
+                        if let Some(method) = method.vendor_method() {
+                            self.browse_method(node, &type_tree, method);
+                        }
+                    }
                 }
             }
         }
@@ -688,6 +1107,37 @@ impl NodeManager for DiagnosticsNodeManager {
                         lazy_namespaces.get_or_insert_with(|| self.namespaces(context));
                     self.read_namespace_node(start_time, node, namespaces, &ns);
                 }
+                DiagnosticsNode::Arguments(arguments) => {
+                    self.read_arguments(start_time, node, &arguments);
+                }
+                method => {
+                    let Some(name) = method.method_name() else {
+                        continue;
+                    };
+                    self.read_method(context, start_time, node, &method, name);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn call(
+        &self,
+        context: &RequestContext,
+        methods_to_call: &mut [&mut MethodCall],
+    ) -> Result<(), StatusCode> {
+        for method in methods_to_call {
+            let res = match from_opaque_node_id::<DiagnosticsNode>(method.method_id()) {
+                Some(DiagnosticsNode::GetAllMonitoredItems) => {
+                    self.call_get_all_monitored_items(context, method)
+                }
+                Some(DiagnosticsNode::ResendMonitoredItemData) => {
+                    self.call_resend_monitored_item_data(context, method)
+                }
+                _ => Err(StatusCode::BadMethodInvalid),
+            };
+            if let Err(e) = res {
+                method.set_status(e);
             }
         }
         Ok(())
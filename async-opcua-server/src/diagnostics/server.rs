@@ -1,5 +1,10 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use opcua_core::sync::Mutex;
 use opcua_types::{DataValue, ServerDiagnosticsSummaryDataType, VariableId};
 
+use crate::DiagnosticsConfig;
+
 use super::LocalValue;
 
 /// The server diagnostics struct, containing shared
@@ -11,12 +16,75 @@ pub struct ServerDiagnostics {
     /// Whether diagnostics are enabled or not.
     /// Set on server startup.
     pub enabled: bool,
+    /// Which groups of diagnostics are collected, if diagnostics are enabled.
+    /// Set on server startup.
+    pub config: DiagnosticsConfig,
+    /// Number of requests whose timeout expired before they were dispatched.
+    /// This is not part of the standard diagnostics summary.
+    pre_dispatch_timeout_count: AtomicU64,
+    /// Number of notifications dropped by subscriptions because a queue was full.
+    /// This is not part of the standard diagnostics summary.
+    dropped_notification_count: AtomicU64,
+    /// Number of unacknowledged notification messages dropped from retransmission queues.
+    /// This is not part of the standard diagnostics summary.
+    dropped_retained_message_count: AtomicU64,
+    /// Number of days until the application instance certificate expires.
+    /// This is not part of the standard diagnostics summary.
+    certificate_days_until_expiry: Mutex<Option<i64>>,
 }
 
 impl ServerDiagnostics {
+    /// Create a new server diagnostics object, collecting the given groups
+    /// if diagnostics are enabled.
+    pub fn new(enabled: bool, config: DiagnosticsConfig) -> Self {
+        Self {
+            enabled,
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Return `true` if session diagnostics are collected.
+    pub fn sessions_enabled(&self) -> bool {
+        self.enabled && self.config.sessions
+    }
+
+    /// Return `true` if subscription diagnostics are collected.
+    pub fn subscriptions_enabled(&self) -> bool {
+        self.enabled && self.config.subscriptions
+    }
+
+    /// Return `true` if request diagnostics are collected.
+    pub fn requests_enabled(&self) -> bool {
+        self.enabled && self.config.requests
+    }
+
     /// Check if the given variable ID is managed by this object.
     pub fn is_mapped(&self, variable_id: VariableId) -> bool {
-        self.enabled && self.summary.is_mapped(variable_id)
+        self.enabled && self.summary.is_mapped(variable_id) && self.is_populated(variable_id)
+    }
+
+    fn is_populated(&self, variable_id: VariableId) -> bool {
+        match variable_id {
+            VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_CurrentSessionCount
+            | VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_CumulatedSessionCount
+            | VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_SecurityRejectedSessionCount
+            | VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_RejectedSessionCount
+            | VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_SessionTimeoutCount
+            | VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_SessionAbortCount => {
+                self.config.sessions
+            }
+            VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_CurrentSubscriptionCount
+            | VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_CumulatedSubscriptionCount
+            | VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_PublishingIntervalCount => {
+                self.config.subscriptions
+            }
+            VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_SecurityRejectedRequestsCount
+            | VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_RejectedRequestsCount => {
+                self.config.requests
+            }
+            _ => true,
+        }
     }
 
     /// Get the value of a diagnostics element by its ID.
@@ -26,50 +94,123 @@ impl ServerDiagnostics {
 
     /// Set the current session count.
     pub fn set_current_session_count(&self, count: u32) {
-        if self.enabled {
+        if self.sessions_enabled() {
             self.summary.current_session_count.set(count);
         }
     }
 
     /// Set the current subscription count.
     pub fn set_current_subscription_count(&self, count: u32) {
-        if self.enabled {
+        if self.subscriptions_enabled() {
             self.summary.current_subscription_count.set(count);
         }
     }
 
     /// Increment the cumulated session count.
     pub fn inc_session_count(&self) {
-        if self.enabled {
-            self.summary.cumulated_session_count.increment();
+        if self.sessions_enabled() {
+            self.summary
+                .increment(&self.summary.cumulated_session_count, |d| {
+                    d.cumulated_session_count += 1
+                });
         }
     }
 
     /// Increment the cumulated subscription count.
     pub fn inc_subscription_count(&self) {
-        if self.enabled {
-            self.summary.cumulated_subscription_count.increment();
+        if self.subscriptions_enabled() {
+            self.summary
+                .increment(&self.summary.cumulated_subscription_count, |d| {
+                    d.cumulated_subscription_count += 1
+                });
         }
     }
 
     /// Increment the rejected requests count.
     pub fn inc_rejected_requests(&self) {
-        if self.enabled {
-            self.summary.rejected_requests_count.increment();
+        if self.requests_enabled() {
+            self.summary
+                .increment(&self.summary.rejected_requests_count, |d| {
+                    d.rejected_requests_count += 1
+                });
         }
     }
 
     /// Increment the security rejected requests count.
     pub fn inc_security_rejected_requests(&self) {
-        if self.enabled {
-            self.summary.security_rejected_requests_count.increment();
+        if self.requests_enabled() {
+            self.summary
+                .increment(&self.summary.security_rejected_requests_count, |d| {
+                    d.security_rejected_requests_count += 1
+                });
         }
     }
 
+    /// Increment the count of requests that timed out before being dispatched.
+    pub fn inc_pre_dispatch_timeout_count(&self) {
+        if self.requests_enabled() {
+            self.pre_dispatch_timeout_count
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Get the number of requests that timed out before being dispatched.
+    pub fn pre_dispatch_timeout_count(&self) -> u64 {
+        self.pre_dispatch_timeout_count.load(Ordering::Relaxed)
+    }
+
+    /// Add `count` to the number of notifications dropped by subscriptions.
+    pub fn inc_dropped_notification_count(&self, count: u64) {
+        if self.subscriptions_enabled() && count > 0 {
+            self.dropped_notification_count
+                .fetch_add(count, Ordering::Relaxed);
+        }
+    }
+
+    /// Get the number of notifications dropped by subscriptions, either because
+    /// the queue of a monitored item overflowed or because too many notification
+    /// messages were queued.
+    pub fn dropped_notification_count(&self) -> u64 {
+        self.dropped_notification_count.load(Ordering::Relaxed)
+    }
+
+    /// Add `count` to the number of notification messages dropped from retransmission queues.
+    pub fn inc_dropped_retained_message_count(&self, count: u64) {
+        if self.subscriptions_enabled() && count > 0 {
+            self.dropped_retained_message_count
+                .fetch_add(count, Ordering::Relaxed);
+        }
+    }
+
+    /// Get the number of unacknowledged notification messages dropped from the
+    /// retransmission queue because it was full. These messages
+    /// are no longer available for `Republish`.
+    pub fn dropped_retained_message_count(&self) -> u64 {
+        self.dropped_retained_message_count.load(Ordering::Relaxed)
+    }
+
+    /// Set the number of days until the application instance certificate expires.
+    pub fn set_certificate_days_until_expiry(&self, days: i64) {
+        *self.certificate_days_until_expiry.lock() = Some(days);
+    }
+
+    /// Get the number of whole days until the application instance certificate expires,
+    /// negative if it has already expired. This is `None` if the server has no certificate,
+    /// or the certificate has not been checked yet, see
+    /// [`CertificateExpiryConfig`](crate::CertificateExpiryConfig).
+    ///
+    /// Unlike the other diagnostics, this is always collected.
+    pub fn certificate_days_until_expiry(&self) -> Option<i64> {
+        *self.certificate_days_until_expiry.lock()
+    }
+
     /// Increment the security rejected session count.
     pub fn inc_security_rejected_session_count(&self) {
-        if self.enabled {
-            self.summary.security_rejected_session_count.increment();
+        if self.sessions_enabled() {
+            self.summary
+                .increment(&self.summary.security_rejected_session_count, |d| {
+                    d.security_rejected_session_count += 1
+                });
         }
     }
 
@@ -82,21 +223,27 @@ impl ServerDiagnostics {
 
     /// Increment the session abort count.
     pub fn inc_session_abort_count(&self) {
-        if self.enabled {
-            self.summary.session_abort_count.increment();
+        if self.sessions_enabled() {
+            self.summary
+                .increment(&self.summary.session_abort_count, |d| {
+                    d.session_abort_count += 1
+                });
         }
     }
 
     /// Increment the session timeout count.
     pub fn inc_session_timeout_count(&self) {
-        if self.enabled {
-            self.summary.session_timeout_count.increment();
+        if self.sessions_enabled() {
+            self.summary
+                .increment(&self.summary.session_timeout_count, |d| {
+                    d.session_timeout_count += 1
+                });
         }
     }
 
     /// Set the number of publishing intervals supported by the server.
     pub fn set_publishing_interval_count(&self, count: u32) {
-        if self.enabled {
+        if self.subscriptions_enabled() {
             self.summary.publishing_interval_count.set(count);
         }
     }
@@ -130,9 +277,42 @@ pub struct ServerDiagnosticsSummary {
     session_abort_count: LocalValue<u32>,
     /// The number of sessions that timed out since the server started.
     session_timeout_count: LocalValue<u32>,
+    /// Counters accumulated since the last call to `snapshot_and_reset`.
+    interval: Mutex<ServerDiagnosticsSummaryDataType>,
 }
 
 impl ServerDiagnosticsSummary {
+    fn increment(
+        &self,
+        value: &LocalValue<u32>,
+        delta: impl FnOnce(&mut ServerDiagnosticsSummaryDataType),
+    ) {
+        // Hold the interval lock while updating the cumulative counter,
+        // so that snapshots are consistent with the cumulative values.
+        let mut interval = self.interval.lock();
+        value.increment();
+        delta(&mut interval);
+    }
+
+    /// Get a snapshot of the counters accumulated since the last call to this method,
+    /// and reset them.
+    ///
+    /// Counter fields, such as `rejected_requests_count` and `cumulated_session_count`,
+    /// contain only the increments during the interval, while the current values,
+    /// `current_session_count`, `current_subscription_count`, `server_view_count`, and
+    /// `publishing_interval_count`, contain their present value.
+    ///
+    /// This does not affect the cumulative counters exposed through the address space.
+    pub fn snapshot_and_reset(&self) -> ServerDiagnosticsSummaryDataType {
+        let mut interval = self.interval.lock();
+        let mut snapshot = std::mem::take(&mut *interval);
+        snapshot.current_session_count = self.current_session_count.get();
+        snapshot.current_subscription_count = self.current_subscription_count.get();
+        snapshot.server_view_count = self.server_view_count.get();
+        snapshot.publishing_interval_count = self.publishing_interval_count.get();
+        snapshot
+    }
+
     /// Check if the given variable ID is managed by this object.
     pub fn is_mapped(&self, variable_id: VariableId) -> bool {
         matches!(variable_id,
@@ -0,0 +1,64 @@
+use opcua_core_namespace::events::{AuditCertificateEventType, AuditCertificateExpiredEventType};
+use opcua_crypto::X509;
+use opcua_nodes::{Event, NamespaceMap};
+use opcua_types::{ByteString, DateTime, Guid, ObjectId, ObjectTypeId, StatusCode, UAString};
+
+/// Create the event emitted from the `Server` object when the application instance
+/// certificate `cert` is about to expire, or has expired.
+///
+/// This is an `AuditCertificateEventType` while the certificate is still valid, and an
+/// `AuditCertificateExpiredEventType` once it has expired.
+pub(crate) fn certificate_expiry_event(
+    cert: &X509,
+    server_id: &UAString,
+    expired: bool,
+    message: String,
+) -> Box<dyn Event> {
+    let event_id = ByteString::from(Guid::new().as_bytes().to_vec());
+    let namespaces = NamespaceMap::new();
+    if expired {
+        let mut event = AuditCertificateExpiredEventType::new_event_now(
+            ObjectTypeId::AuditCertificateExpiredEventType.into(),
+            event_id,
+            message,
+            &namespaces,
+        );
+        set_fields(
+            &mut event.base,
+            cert,
+            server_id,
+            StatusCode::BadCertificateTimeInvalid,
+            900,
+        );
+        Box::new(event)
+    } else {
+        let mut event = AuditCertificateEventType::new_event_now(
+            ObjectTypeId::AuditCertificateEventType.into(),
+            event_id,
+            message,
+            &namespaces,
+        );
+        set_fields(&mut event, cert, server_id, StatusCode::Good, 500);
+        Box::new(event)
+    }
+}
+
+fn set_fields(
+    event: &mut AuditCertificateEventType,
+    cert: &X509,
+    server_id: &UAString,
+    status: StatusCode,
+    severity: u16,
+) {
+    event.certificate = cert.as_byte_string();
+    let security = &mut event.base;
+    security.status_code_id = status;
+    let audit = &mut security.base;
+    audit.action_time_stamp = DateTime::now();
+    audit.server_id = server_id.clone();
+    audit.status = status.is_good();
+    let base = &mut audit.base;
+    base.source_node = ObjectId::Server.into();
+    base.source_name = "Server".into();
+    base.severity = severity;
+}
@@ -0,0 +1,425 @@
+use opcua_core_namespace::events::{AlarmConditionType, StateVariableType, TwoStateVariableType};
+use opcua_nodes::{
+    Event, EventField, MethodBuilder, NodeInsertTarget, ObjectBuilder, ReferenceDirection,
+};
+use opcua_types::{
+    AttributeId, ByteString, DataTypeId, DateTime, Guid, Identifier, LocalizedText, MethodId,
+    NamespaceMap, NodeId, NumericRange, ObjectTypeId, QualifiedName, ReferenceTypeId, StatusCode,
+    UAString, Variant,
+};
+
+/// State of a single condition, following the `AlarmConditionType` state machine.
+///
+/// This tracks the `EnabledState`, `ActiveState`, `AckedState` and `ConfirmedState` of
+/// a condition without branches. The condition is retained while it is enabled and
+/// either active, unacknowledged or unconfirmed.
+///
+/// The condition does not emit events on its own. Each state change returns a
+/// [ConditionEvent], which should be emitted from the source node of the condition using
+/// [`InMemoryNodeManager::emit_condition_event`](crate::node_manager::memory::InMemoryNodeManager::emit_condition_event),
+/// so that the state of retained conditions is sent to clients calling `ConditionRefresh`.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    node_id: NodeId,
+    source_node: NodeId,
+    source_name: UAString,
+    condition_name: UAString,
+    severity: u16,
+    message: LocalizedText,
+    comment: (LocalizedText, DateTime),
+    last_event_id: ByteString,
+    enabled: (bool, DateTime),
+    active: (bool, DateTime),
+    acked: (bool, DateTime),
+    confirmed: (bool, DateTime),
+}
+
+impl Condition {
+    /// Create a new condition with ID `node_id` for the event source `source_node`.
+    ///
+    /// The condition starts out enabled, inactive, acknowledged and confirmed.
+    pub fn new(
+        node_id: impl Into<NodeId>,
+        source_node: impl Into<NodeId>,
+        source_name: impl Into<UAString>,
+        condition_name: impl Into<UAString>,
+    ) -> Self {
+        let now = DateTime::now();
+        Self {
+            node_id: node_id.into(),
+            source_node: source_node.into(),
+            source_name: source_name.into(),
+            condition_name: condition_name.into(),
+            severity: 1,
+            message: LocalizedText::null(),
+            comment: (LocalizedText::null(), DateTime::null()),
+            last_event_id: ByteString::null(),
+            enabled: (true, now),
+            active: (false, now),
+            acked: (true, now),
+            confirmed: (true, now),
+        }
+    }
+
+    /// Get the ID of the condition node.
+    pub fn node_id(&self) -> &NodeId {
+        &self.node_id
+    }
+
+    /// Get the ID of the source node of the condition.
+    pub fn source_node(&self) -> &NodeId {
+        &self.source_node
+    }
+
+    /// Get whether the condition is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.0
+    }
+
+    /// Get whether the condition is active.
+    pub fn is_active(&self) -> bool {
+        self.active.0
+    }
+
+    /// Get whether the condition has been acknowledged.
+    pub fn is_acked(&self) -> bool {
+        self.acked.0
+    }
+
+    /// Get whether the condition has been confirmed.
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed.0
+    }
+
+    /// Get whether the condition is retained, meaning that it is enabled, and is
+    /// either active or waiting to be acknowledged or confirmed.
+    pub fn is_retained(&self) -> bool {
+        self.enabled.0 && (self.active.0 || !self.acked.0 || !self.confirmed.0)
+    }
+
+    /// Get the last comment added when acknowledging or confirming the condition.
+    pub fn comment(&self) -> &LocalizedText {
+        &self.comment.0
+    }
+
+    /// Get the ID of the last event emitted for this condition. Clients pass this
+    /// to `Acknowledge` and `Confirm`.
+    pub fn last_event_id(&self) -> &ByteString {
+        &self.last_event_id
+    }
+
+    /// Get the ID of the `Acknowledge` method of the condition node.
+    pub fn acknowledge_method_id(&self) -> NodeId {
+        self.method_id("Acknowledge")
+    }
+
+    /// Get the ID of the `Confirm` method of the condition node.
+    pub fn confirm_method_id(&self) -> NodeId {
+        self.method_id("Confirm")
+    }
+
+    /// Methods of the condition node are string node IDs in the same namespace as the
+    /// condition, for example `ns=2;s=MyAlarm_Acknowledge` for the condition `ns=2;s=MyAlarm`.
+    fn method_id(&self, name: &str) -> NodeId {
+        let base = match &self.node_id.identifier {
+            Identifier::String(s) => s.as_ref().to_owned(),
+            Identifier::Numeric(n) => n.to_string(),
+            r => r.to_string(),
+        };
+        NodeId::new(self.node_id.namespace, format!("{base}_{name}"))
+    }
+
+    /// Insert the condition node into the address space, as an `AlarmConditionType` object
+    /// referenced from the source node with a `HasCondition` reference, with its own
+    /// `Acknowledge` and `Confirm` methods.
+    ///
+    /// Calls to these methods, and to the methods of `AcknowledgeableConditionType` on the
+    /// condition node, should be passed to [Condition::call].
+    pub fn insert(&self, address_space: &mut impl NodeInsertTarget) {
+        let name = self.condition_name.as_ref();
+        ObjectBuilder::new(&self.node_id, name, name)
+            .has_type_definition(ObjectTypeId::AlarmConditionType)
+            .reference(
+                self.source_node.clone(),
+                ReferenceTypeId::HasCondition,
+                ReferenceDirection::Inverse,
+            )
+            .insert(address_space);
+
+        for (method_id, name, event_id_description) in [
+            (
+                self.acknowledge_method_id(),
+                "Acknowledge",
+                "The identifier of the event to acknowledge",
+            ),
+            (
+                self.confirm_method_id(),
+                "Confirm",
+                "The identifier of the event to confirm",
+            ),
+        ] {
+            MethodBuilder::new(&method_id, name, name)
+                .executable(true)
+                .user_executable(true)
+                .component_of(self.node_id.clone())
+                .input_argument("EventId", DataTypeId::ByteString, -1, event_id_description)
+                .input_argument(
+                    "Comment",
+                    DataTypeId::LocalizedText,
+                    -1,
+                    "The comment to add to the condition",
+                )
+                .insert(address_space);
+        }
+    }
+
+    /// Enable or disable the condition.
+    pub fn set_enabled(&mut self, enabled: bool) -> Result<ConditionEvent, StatusCode> {
+        if self.enabled.0 == enabled {
+            return Err(if enabled {
+                StatusCode::BadConditionAlreadyEnabled
+            } else {
+                StatusCode::BadConditionAlreadyDisabled
+            });
+        }
+        self.enabled = (enabled, DateTime::now());
+        Ok(self.event())
+    }
+
+    /// Set the condition to active or inactive, with a new severity and message.
+    ///
+    /// Activating the condition means it needs to be acknowledged and confirmed again.
+    pub fn set_active(
+        &mut self,
+        active: bool,
+        severity: u16,
+        message: impl Into<LocalizedText>,
+    ) -> Result<ConditionEvent, StatusCode> {
+        if !self.enabled.0 {
+            return Err(StatusCode::BadConditionDisabled);
+        }
+        let now = DateTime::now();
+        if active && !self.active.0 {
+            self.acked = (false, now);
+            self.confirmed = (false, now);
+        }
+        if active != self.active.0 {
+            self.active = (active, now);
+        }
+        self.severity = severity;
+        self.message = message.into();
+        Ok(self.event())
+    }
+
+    /// Acknowledge the condition. `event_id` must be the ID of the last event
+    /// emitted for the condition.
+    pub fn acknowledge(
+        &mut self,
+        event_id: &ByteString,
+        comment: impl Into<LocalizedText>,
+    ) -> Result<ConditionEvent, StatusCode> {
+        self.validate_event_id(event_id)?;
+        if self.acked.0 {
+            return Err(StatusCode::BadConditionBranchAlreadyAcked);
+        }
+        self.acked = (true, DateTime::now());
+        self.set_comment(comment);
+        Ok(self.event())
+    }
+
+    /// Confirm the condition. `event_id` must be the ID of the last event
+    /// emitted for the condition, and the condition must be acknowledged first.
+    pub fn confirm(
+        &mut self,
+        event_id: &ByteString,
+        comment: impl Into<LocalizedText>,
+    ) -> Result<ConditionEvent, StatusCode> {
+        self.validate_event_id(event_id)?;
+        if self.confirmed.0 {
+            return Err(StatusCode::BadConditionBranchAlreadyConfirmed);
+        }
+        if !self.acked.0 {
+            return Err(StatusCode::BadInvalidState);
+        }
+        self.confirmed = (true, DateTime::now());
+        self.set_comment(comment);
+        Ok(self.event())
+    }
+
+    /// Handle a call to the `Acknowledge` or `Confirm` method, with the `EventId` and
+    /// `Comment` arguments. `method_id` is either the method of the condition node, or
+    /// the method of `AcknowledgeableConditionType`, and `object_id` must be the condition node.
+    pub fn call(
+        &mut self,
+        object_id: &NodeId,
+        method_id: &NodeId,
+        arguments: &[Variant],
+    ) -> Result<ConditionEvent, StatusCode> {
+        if object_id != &self.node_id {
+            return Err(StatusCode::BadMethodInvalid);
+        }
+        let (event_id, comment) = match arguments {
+            [Variant::ByteString(event_id), Variant::LocalizedText(comment)] => {
+                (event_id, (**comment).clone())
+            }
+            [_, _] => return Err(StatusCode::BadInvalidArgument),
+            [] | [_] => return Err(StatusCode::BadArgumentsMissing),
+            _ => return Err(StatusCode::BadTooManyArguments),
+        };
+        let type_method = method_id.as_method_id().ok();
+        if type_method == Some(MethodId::AcknowledgeableConditionType_Acknowledge)
+            || method_id == &self.acknowledge_method_id()
+        {
+            self.acknowledge(event_id, comment)
+        } else if type_method == Some(MethodId::AcknowledgeableConditionType_Confirm)
+            || method_id == &self.confirm_method_id()
+        {
+            self.confirm(event_id, comment)
+        } else {
+            Err(StatusCode::BadMethodInvalid)
+        }
+    }
+
+    fn validate_event_id(&self, event_id: &ByteString) -> Result<(), StatusCode> {
+        if !self.enabled.0 {
+            return Err(StatusCode::BadConditionDisabled);
+        }
+        if event_id.is_null_or_empty() || event_id != &self.last_event_id {
+            return Err(StatusCode::BadEventIdUnknown);
+        }
+        Ok(())
+    }
+
+    fn set_comment(&mut self, comment: impl Into<LocalizedText>) {
+        let comment = comment.into();
+        if !comment.text.is_null() {
+            self.comment = (comment, DateTime::now());
+        }
+    }
+
+    fn two_state(
+        (value, time): (bool, DateTime),
+        true_state: &str,
+        false_state: &str,
+    ) -> TwoStateVariableType {
+        TwoStateVariableType {
+            base: StateVariableType {
+                value: if value { true_state } else { false_state }.into(),
+                ..Default::default()
+            },
+            id: value,
+            transition_time: time,
+            true_state: true_state.into(),
+            false_state: false_state.into(),
+            ..Default::default()
+        }
+    }
+
+    fn event(&mut self) -> ConditionEvent {
+        self.last_event_id = ByteString::from(Guid::new().as_bytes().to_vec());
+        let mut event = AlarmConditionType::new_event_now(
+            ObjectTypeId::AlarmConditionType.into(),
+            self.last_event_id.clone(),
+            self.message.clone(),
+            &NamespaceMap::new(),
+        );
+
+        let enabled_state = || Self::two_state(self.enabled, "Enabled", "Disabled");
+        event.active_state = Self::two_state(self.active, "Active", "Inactive");
+        event.enabled_state = enabled_state();
+
+        let acknowledgeable = &mut event.base;
+        acknowledgeable.acked_state = Self::two_state(self.acked, "Acknowledged", "Unacknowledged");
+        acknowledgeable.confirmed_state =
+            Self::two_state(self.confirmed, "Confirmed", "Unconfirmed");
+        acknowledgeable.enabled_state = enabled_state();
+
+        let condition = &mut acknowledgeable.base;
+        condition.condition_name = self.condition_name.clone();
+        condition.condition_class_id = ObjectTypeId::BaseConditionClassType.into();
+        condition.condition_class_name = "BaseConditionClassType".into();
+        condition.enabled_state = enabled_state();
+        condition.retain = self.is_retained();
+        condition.comment.source_timestamp = self.comment.1;
+
+        condition.base.source_node = self.source_node.clone();
+        condition.base.source_name = self.source_name.clone();
+        condition.base.severity = self.severity;
+
+        ConditionEvent {
+            condition_id: self.node_id.clone(),
+            comment: self.comment.0.clone(),
+            event,
+        }
+    }
+}
+
+/// Event emitted when the state of a [Condition] changes.
+#[derive(Debug)]
+pub struct ConditionEvent {
+    condition_id: NodeId,
+    comment: LocalizedText,
+    event: AlarmConditionType,
+}
+
+impl ConditionEvent {
+    /// Get the event ID of this event.
+    pub fn event_id(&self) -> &ByteString {
+        &self.event.base.base.base.event_id
+    }
+
+    /// Get the ID of the condition that emitted this event.
+    pub fn condition_id(&self) -> &NodeId {
+        &self.condition_id
+    }
+
+    /// Get the ID of the source node of the condition.
+    pub fn source_node(&self) -> &NodeId {
+        &self.event.base.base.base.source_node
+    }
+
+    /// Get whether the condition was retained when this event was emitted.
+    pub fn retain(&self) -> bool {
+        self.event.base.base.retain
+    }
+}
+
+impl Event for ConditionEvent {
+    fn get_field(
+        &self,
+        type_definition_id: &NodeId,
+        attribute_id: AttributeId,
+        index_range: &NumericRange,
+        browse_path: &[QualifiedName],
+    ) -> Variant {
+        // The `ConditionId` is selected as the node ID of the condition type itself.
+        if browse_path.is_empty() && attribute_id == AttributeId::NodeId {
+            return self.condition_id.get_value(attribute_id, index_range, &[]);
+        }
+        // The value of `Comment` is a localized text, which the generated event type
+        // cannot hold, its `SourceTimestamp` is set on the event.
+        if let [name] = browse_path {
+            if name.namespace_index == 0 && name.name.as_ref() == "Comment" {
+                return self.comment.get_value(attribute_id, index_range, &[]);
+            }
+        }
+        self.event
+            .get_field(type_definition_id, attribute_id, index_range, browse_path)
+    }
+
+    fn time(&self) -> &DateTime {
+        self.event.time()
+    }
+}
+
+impl EventField for ConditionEvent {
+    fn get_value(
+        &self,
+        attribute_id: AttributeId,
+        index_range: &NumericRange,
+        remaining_path: &[QualifiedName],
+    ) -> Variant {
+        self.event
+            .get_value(attribute_id, index_range, remaining_path)
+    }
+}
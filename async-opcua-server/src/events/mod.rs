@@ -0,0 +1,14 @@
+//! Server side support for events with state, such as conditions and alarms,
+//! and for model change events.
+//!
+//! Plain events are emitted using
+//! [`InMemoryNodeManager::emit_event`](crate::node_manager::memory::InMemoryNodeManager::emit_event)
+//! or [`SubscriptionCache::notify_events`](crate::SubscriptionCache::notify_events).
+
+mod certificate;
+mod condition;
+mod model_change;
+
+pub(crate) use certificate::certificate_expiry_event;
+pub use condition::{Condition, ConditionEvent};
+pub use model_change::GeneralModelChangeEvent;
@@ -0,0 +1,106 @@
+use opcua_nodes::{BaseEventType, Event, EventField};
+use opcua_types::{
+    AttributeId, ByteString, DateTime, Guid, ModelChangeStructureDataType,
+    ModelChangeStructureVerbMask, NodeId, NumericRange, ObjectId, ObjectTypeId, QualifiedName,
+    Variant,
+};
+
+/// A `GeneralModelChangeEvent`, emitted from the `Server` object when nodes or
+/// references are added to or removed from the address space.
+///
+/// Changes to the same node are merged into a single entry, with the verbs combined.
+#[derive(Debug)]
+pub struct GeneralModelChangeEvent {
+    base: BaseEventType,
+    changes: Vec<ModelChangeStructureDataType>,
+}
+
+impl GeneralModelChangeEvent {
+    /// Create a new empty model change event, with the current time.
+    pub fn new_now() -> Self {
+        Self {
+            base: BaseEventType::new_now(
+                ObjectTypeId::GeneralModelChangeEventType,
+                ByteString::from(Guid::new().as_bytes().to_vec()),
+                "Address space changed",
+            )
+            .set_source_node(ObjectId::Server.into())
+            .set_source_name("Server".into()),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Record a change to the node `affected`, with type definition `affected_type`.
+    /// `affected_type` should be null if the node is not an object or variable.
+    pub fn add_change(
+        &mut self,
+        affected: &NodeId,
+        affected_type: &NodeId,
+        verb: ModelChangeStructureVerbMask,
+    ) {
+        if affected.is_null() {
+            return;
+        }
+        if let Some(change) = self.changes.iter_mut().find(|c| &c.affected == affected) {
+            change.verb |= verb as u8;
+            if change.affected_type.is_null() {
+                change.affected_type = affected_type.clone();
+            }
+            return;
+        }
+        self.changes.push(ModelChangeStructureDataType {
+            affected: affected.clone(),
+            affected_type: affected_type.clone(),
+            verb: verb as u8,
+        });
+    }
+
+    /// Get the changes recorded in this event.
+    pub fn changes(&self) -> &[ModelChangeStructureDataType] {
+        &self.changes
+    }
+
+    /// Return `true` if no changes have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl Event for GeneralModelChangeEvent {
+    fn get_field(
+        &self,
+        type_definition_id: &NodeId,
+        attribute_id: AttributeId,
+        index_range: &NumericRange,
+        browse_path: &[QualifiedName],
+    ) -> Variant {
+        if type_definition_id == &ObjectTypeId::GeneralModelChangeEventType {
+            self.get_value(attribute_id, index_range, browse_path)
+        } else {
+            self.base
+                .get_field(type_definition_id, attribute_id, index_range, browse_path)
+        }
+    }
+
+    fn time(&self) -> &DateTime {
+        self.base.time()
+    }
+}
+
+impl EventField for GeneralModelChangeEvent {
+    fn get_value(
+        &self,
+        attribute_id: AttributeId,
+        index_range: &NumericRange,
+        remaining_path: &[QualifiedName],
+    ) -> Variant {
+        match remaining_path {
+            [field] if field.namespace_index == 0 && field.name.as_ref() == "Changes" => {
+                self.changes.get_value(attribute_id, index_range, &[])
+            }
+            _ => self
+                .base
+                .get_value(attribute_id, index_range, remaining_path),
+        }
+    }
+}
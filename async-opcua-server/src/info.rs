@@ -9,12 +9,17 @@ use std::sync::Arc;
 
 use arc_swap::ArcSwap;
 use opcua_nodes::DefaultTypeTree;
+use tokio::runtime::Handle;
 use tracing::{debug, error, warn};
 
 use crate::authenticator::{user_pass_security_policy_id, Password};
-use crate::diagnostics::{ServerDiagnostics, ServerDiagnosticsSummary};
-use crate::node_manager::TypeTreeForUser;
-use opcua_core::comms::url::{hostname_from_url, url_matches_except_host};
+use crate::diagnostics::{ServerDiagnostics, ServerDiagnosticsSummary, ServiceFaultDiagnostics};
+use crate::node_manager::{NodeVisibilityFilter, TypeTreeForUser};
+use crate::session::continuation_points::ContinuationPointStoreFactory;
+use crate::transport::{LogThrottle, SecureChannelLimiter};
+use opcua_core::comms::url::{
+    hostname_from_url, url_matches_except_host, url_with_replaced_hostname,
+};
 use opcua_core::handle::AtomicHandle;
 use opcua_core::sync::RwLock;
 use opcua_crypto::{user_identity, PrivateKey, SecurityPolicy, X509};
@@ -30,7 +35,7 @@ use opcua_types::{
     TypeLoaderCollection, UAString,
 };
 
-use crate::config::{ServerConfig, ServerEndpoint};
+use crate::config::{EndpointHostSubstitution, ServerConfig, ServerEndpoint};
 
 use super::authenticator::{AuthManager, UserToken};
 use super::identity_token::{IdentityToken, POLICY_ID_ANONYMOUS, POLICY_ID_X509};
@@ -73,6 +78,10 @@ pub struct ServerInfo {
     pub type_tree: Arc<RwLock<DefaultTypeTree>>,
     /// Wrapper to get a type tree for a specific user.
     pub type_tree_getter: Arc<dyn TypeTreeForUser>,
+    /// Filter hiding nodes from individual users, if any.
+    pub node_visibility_filter: Option<Arc<dyn NodeVisibilityFilter>>,
+    /// Factory for the continuation point store of each session.
+    pub continuation_point_store: Arc<dyn ContinuationPointStoreFactory>,
     /// Generator for subscription IDs.
     pub subscription_id_handle: AtomicHandle,
     /// Generator for monitored item IDs.
@@ -89,9 +98,28 @@ pub struct ServerInfo {
     pub type_loaders: RwLock<TypeLoaderCollection>,
     /// Current server diagnostics.
     pub diagnostics: ServerDiagnostics,
+    /// Diagnostic info returned with service faults.
+    pub service_fault_diagnostics: ServiceFaultDiagnostics,
+    /// Runtime samplers should be spawned on, if set.
+    pub(crate) sampler_runtime: Option<Handle>,
+    /// Limit on open secure channels per remote IP address.
+    pub(crate) secure_channel_limiter: SecureChannelLimiter,
+    /// Throttle for logging recoverable transport errors, per client address.
+    pub(crate) recoverable_transport_error_log: LogThrottle,
+    /// Throttle for logging fatal transport errors, per client address.
+    pub(crate) fatal_transport_error_log: LogThrottle,
+    /// Security key service implementing `GetSecurityKeys` and `GetSecurityGroup`, if set.
+    #[cfg(feature = "pubsub")]
+    pub security_key_service: Option<Arc<crate::pubsub::SecurityKeyService>>,
 }
 
 impl ServerInfo {
+    /// Get the tokio runtime samplers should be spawned on. This is the runtime
+    /// set using `ServerBuilder::sampler_runtime`, or the current runtime.
+    pub fn sampler_runtime(&self) -> Handle {
+        self.sampler_runtime.clone().unwrap_or_else(Handle::current)
+    }
+
     /// Get the list of endpoints that match the provided filters.
     pub fn endpoints(
         &self,
@@ -124,12 +152,33 @@ impl ServerInfo {
             if !hostname.eq_ignore_ascii_case(&self.config.tcp_config.host) {
                 debug!("Endpoint url \"{}\" hostname supplied by caller does not match server's hostname \"{}\"", endpoint_url, &self.config.tcp_config.host);
             }
-            let endpoints = self
+            // Return the endpoints matching the requested URL. The host is ignored, since
+            // the server may be reachable under several different names.
+            // If no endpoints match, return all of them and let the client decide.
+            let base_endpoint_url = self.base_endpoint();
+            let matching: Vec<_> = self
                 .config
                 .endpoints
                 .values()
-                .map(|e| self.new_endpoint_description(e, true))
+                .filter(|e| {
+                    url_matches_except_host(
+                        &e.endpoint_url(&base_endpoint_url),
+                        endpoint_url.as_ref(),
+                    )
+                })
                 .collect();
+            let endpoints = if matching.is_empty() {
+                self.config
+                    .endpoints
+                    .values()
+                    .map(|e| self.new_endpoint_description(e, true))
+                    .collect()
+            } else {
+                matching
+                    .into_iter()
+                    .map(|e| self.new_endpoint_description(e, true))
+                    .collect()
+            };
             Some(endpoints)
         } else {
             warn!(
@@ -241,6 +290,97 @@ impl ServerInfo {
         }
     }
 
+    /// Get the hosts that should replace the host in URLs returned to a client
+    /// that connected using `client_url`, or `None` if URLs should be returned as is.
+    fn substituted_hosts(&self, client_url: &str) -> Option<Vec<String>> {
+        match &self.config.endpoint_host_substitution {
+            EndpointHostSubstitution::None => None,
+            EndpointHostSubstitution::Static(host) => Some(vec![host.clone()]),
+            EndpointHostSubstitution::EchoClientHost => match hostname_from_url(client_url) {
+                Ok(host) => Some(vec![host]),
+                Err(_) => {
+                    warn!(
+                        "Unable to get host from client endpoint url \"{}\", not substituting",
+                        client_url
+                    );
+                    None
+                }
+            },
+            EndpointHostSubstitution::List(hosts) if hosts.is_empty() => None,
+            EndpointHostSubstitution::List(hosts) => Some(hosts.clone()),
+        }
+    }
+
+    fn urls_with_hosts(url: &str, hosts: &[String]) -> Vec<UAString> {
+        let urls: Vec<_> = hosts
+            .iter()
+            .filter_map(|host| url_with_replaced_hostname(url, host).ok())
+            .map(UAString::from)
+            .collect();
+        if urls.is_empty() {
+            vec![UAString::from(url)]
+        } else {
+            urls
+        }
+    }
+
+    fn substitute_url_list(urls: Option<Vec<UAString>>, hosts: &[String]) -> Option<Vec<UAString>> {
+        urls.map(|urls| {
+            urls.iter()
+                .flat_map(|u| Self::urls_with_hosts(u.as_ref(), hosts))
+                .collect()
+        })
+    }
+
+    /// Substitute the host of the given endpoint descriptions according to the configured
+    /// [EndpointHostSubstitution]. `client_url` is the endpoint URL the client used to connect.
+    ///
+    /// With [EndpointHostSubstitution::List] each endpoint is returned once for each host.
+    pub fn substitute_endpoint_hosts(
+        &self,
+        endpoints: Vec<EndpointDescription>,
+        client_url: &str,
+    ) -> Vec<EndpointDescription> {
+        let Some(hosts) = self.substituted_hosts(client_url) else {
+            return endpoints;
+        };
+        endpoints
+            .into_iter()
+            .flat_map(|e| {
+                let server = ApplicationDescription {
+                    discovery_urls: Self::substitute_url_list(
+                        e.server.discovery_urls.clone(),
+                        &hosts,
+                    ),
+                    ..e.server.clone()
+                };
+                Self::urls_with_hosts(e.endpoint_url.as_ref(), &hosts)
+                    .into_iter()
+                    .map(move |endpoint_url| EndpointDescription {
+                        endpoint_url,
+                        server: server.clone(),
+                        ..e.clone()
+                    })
+            })
+            .collect()
+    }
+
+    /// Substitute the host of the discovery URLs in the given application description
+    /// according to the configured [EndpointHostSubstitution].
+    pub fn substitute_application_hosts(
+        &self,
+        description: ApplicationDescription,
+        client_url: &str,
+    ) -> ApplicationDescription {
+        let Some(hosts) = self.substituted_hosts(client_url) else {
+            return description;
+        };
+        ApplicationDescription {
+            discovery_urls: Self::substitute_url_list(description.discovery_urls, &hosts),
+            ..description
+        }
+    }
+
     /// Get the list of discovery URLs on the server.
     pub fn discovery_urls(&self) -> Option<Vec<UAString>> {
         if self.config.discovery_urls.is_empty() {
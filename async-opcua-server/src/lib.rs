@@ -17,9 +17,13 @@ mod config;
 pub mod diagnostics;
 #[cfg(feature = "discovery-server-registration")]
 mod discovery;
+#[cfg(feature = "generated-address-space")]
+pub mod events;
 mod identity_token;
 mod info;
 pub mod node_manager;
+#[cfg(feature = "pubsub")]
+pub mod pubsub;
 mod server;
 mod server_handle;
 mod server_status;
@@ -35,10 +39,13 @@ pub use opcua_types::event_field::EventField;
 pub use server::Server;
 pub use server_handle::ServerHandle;
 pub use server_status::ServerStatusWrapper;
-pub use session::continuation_points::ContinuationPoint;
+pub use session::continuation_points::{
+    ContinuationPoint, ContinuationPointKind, ContinuationPointStore,
+    ContinuationPointStoreFactory, InMemoryContinuationPointStore,
+};
 pub use subscriptions::{
-    CreateMonitoredItem, MonitoredItem, MonitoredItemHandle, SessionSubscriptions, Subscription,
-    SubscriptionCache, SubscriptionState,
+    CreateMonitoredItem, EventFilterCache, MonitoredItem, MonitoredItemHandle,
+    SessionSubscriptions, Subscription, SubscriptionCache, SubscriptionState,
 };
 
 /// Contains constaints for default configuration values.
@@ -46,6 +53,11 @@ pub use subscriptions::{
 pub mod constants {
     /// The default hello timeout period in seconds
     pub const DEFAULT_HELLO_TIMEOUT_SECONDS: u32 = 5;
+    /// The default time in milliseconds a secure channel may stay open without a session,
+    /// 0 meaning no limit
+    pub const DEFAULT_SECURE_CHANNEL_IDLE_TIMEOUT_MS: u64 = 0;
+    /// The default minimum interval in milliseconds between log messages for repeated transport errors
+    pub const DEFAULT_TRANSPORT_ERROR_LOG_INTERVAL_MS: u64 = 10_000;
     /// Default OPC UA server port for this implementation
     pub const DEFAULT_RUST_OPC_UA_SERVER_PORT: u16 = 4855;
     /// Default maximum number of monitored items per subscription
@@ -61,6 +73,8 @@ pub mod constants {
     pub const SUBSCRIPTION_TIMER_RATE_MS: u64 = 100;
     /// Minimum publishing interval for subscriptions
     pub const MIN_PUBLISHING_INTERVAL_MS: f64 = SUBSCRIPTION_TIMER_RATE_MS as f64;
+    /// Maximum publishing interval for subscriptions
+    pub const MAX_PUBLISHING_INTERVAL_MS: f64 = 3_600_000.0;
     /// Minimum sampling interval on monitored items
     pub const MIN_SAMPLING_INTERVAL_MS: f64 = SUBSCRIPTION_TIMER_RATE_MS as f64;
     /// Maximum data change queue allowed by clients on monitored items
@@ -131,9 +145,13 @@ pub mod constants {
     pub const MAX_NOTIFICATIONS_PER_PUBLISH: u64 = 0;
     /// Maximum number of queued notifications. Any notifications beyond this are dropped.
     pub const MAX_QUEUED_NOTIFICATIONS: usize = 20;
+    /// Maximum number of unacknowledged notification messages kept per subscription for republishing.
+    pub const MAX_RETAINED_NOTIFICATIONS_PER_SUBSCRIPTION: usize = 20;
 
     /// Receive buffer size default.
     pub const RECEIVE_BUFFER_SIZE: usize = u16::MAX as usize;
     /// Send buffer size default.
     pub const SEND_BUFFER_SIZE: usize = u16::MAX as usize;
+    /// Send buffer pool size default.
+    pub const SEND_BUFFER_POOL_SIZE: usize = opcua_core::comms::buffer::DEFAULT_BUFFER_POOL_SIZE;
 }
@@ -242,6 +242,39 @@ impl WriteNode {
     }
 }
 
+impl WriteNode {
+    /// Create a list of `WriteNode`s from a write transaction, used when falling back
+    /// to independent writes for node managers that do not implement transactions.
+    pub(crate) fn from_transaction(
+        node_id: &NodeId,
+        writes: &[(AttributeId, DataValue)],
+    ) -> Vec<WriteNode> {
+        writes
+            .iter()
+            .map(|(attribute_id, value)| {
+                WriteNode::new(
+                    WriteValue {
+                        node_id: node_id.clone(),
+                        attribute_id: *attribute_id as u32,
+                        index_range: NumericRange::None,
+                        value: value.clone(),
+                    },
+                    DiagnosticBits::empty(),
+                )
+            })
+            .collect()
+    }
+
+    /// Get the result of a list of `WriteNode`s created with `from_transaction`,
+    /// this is the first error, if any.
+    pub(crate) fn transaction_result(nodes: &[WriteNode]) -> Result<(), StatusCode> {
+        match nodes.iter().map(|n| n.status()).find(|s| !s.is_good()) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
 impl IntoResult for WriteNode {
     type Result = StatusCode;
 
@@ -1,14 +1,18 @@
 use std::sync::Arc;
 
 use crate::{
+    address_space::AccessLevel,
     authenticator::{AuthManager, UserToken},
     info::ServerInfo,
     session::instance::Session,
     SubscriptionCache,
 };
 use opcua_core::{sync::RwLock, trace_read_lock};
-use opcua_nodes::TypeTree;
-use opcua_types::{BrowseDescriptionResultMask, NodeId};
+use opcua_nodes::{Node, TypeTree};
+use opcua_types::{
+    AttributeId, BrowseDescriptionResultMask, DataEncoding, ExpandedNodeId, NodeId, NumericRange,
+    TimestampsToReturn, Variant,
+};
 use parking_lot::lock_api::{RawRwLock, RwLockReadGuard};
 use tracing::debug_span;
 use tracing_futures::Instrument;
@@ -44,6 +48,30 @@ impl TypeTreeForUser for DefaultTypeTreeGetter {
     }
 }
 
+/// Trait for hiding individual nodes from some users, for servers that present
+/// a different address space to each user.
+///
+/// Nodes that are not visible are reported as `BadNodeIdUnknown` by Read, and
+/// are left out of Browse results, as if they did not exist. Note that hiding
+/// a node does not prevent other services from accessing it, so node managers
+/// must still deny access to hidden nodes.
+pub trait NodeVisibilityFilter: Send + Sync {
+    /// Return `true` if the node with ID `node_id` is visible to the user
+    /// given by `context`.
+    fn is_visible(&self, context: &RequestContext, node_id: &NodeId) -> bool;
+
+    /// Return `true` if references of type `reference_type_id` are visible to the
+    /// user given by `context`. Hidden references are left out of Browse results.
+    #[allow(unused_variables)]
+    fn is_reference_type_visible(
+        &self,
+        context: &RequestContext,
+        reference_type_id: &NodeId,
+    ) -> bool {
+        true
+    }
+}
+
 /// Type returned from [`TypeTreeForUser`], a trait for something that dereferences
 /// to a `dyn TypeTree`.
 pub trait TypeTreeReadContext {
@@ -87,6 +115,67 @@ impl RequestContext {
     pub fn get_type_tree_for_user<'a>(&'a self) -> Box<dyn TypeTreeReadContext + 'a> {
         self.type_tree_getter.get_type_tree_for_user(self)
     }
+
+    /// Return `true` if the node with ID `node_id` is visible to the current user.
+    /// See [`NodeVisibilityFilter`].
+    pub fn is_node_visible(&self, node_id: &NodeId) -> bool {
+        self.info
+            .node_visibility_filter
+            .as_ref()
+            .is_none_or(|f| f.is_visible(self, node_id))
+    }
+
+    /// Return `true` if a reference of type `reference_type_id` to `target_id` is
+    /// visible to the current user. See [`NodeVisibilityFilter`].
+    pub fn is_reference_visible(
+        &self,
+        reference_type_id: &NodeId,
+        target_id: &ExpandedNodeId,
+    ) -> bool {
+        let Some(filter) = &self.info.node_visibility_filter else {
+            return true;
+        };
+        // Nodes on other servers are not covered by the filter.
+        (reference_type_id.is_null() || filter.is_reference_type_visible(self, reference_type_id))
+            && (target_id.server_index != 0 || filter.is_visible(self, &target_id.node_id))
+    }
+
+    /// Get the effective access level of `node` for the current user.
+    ///
+    /// This is the intersection of the `AccessLevel` and `UserAccessLevel` attributes
+    /// of the node, further restricted by
+    /// [`AuthManager::effective_user_access_level`]. Nodes without an access level,
+    /// i.e. anything but variables, are only readable.
+    ///
+    /// The server does not track the roles of a session, so the `RolePermissions` and
+    /// `UserRolePermissions` attributes of the node are _not_ evaluated here. Servers
+    /// using roles should apply them in [`AuthManager::effective_user_access_level`].
+    pub fn effective_access_level(&self, node: &dyn Node) -> AccessLevel {
+        let read_level = |attribute_id| match node
+            .get_attribute(
+                TimestampsToReturn::Neither,
+                attribute_id,
+                &NumericRange::None,
+                &DataEncoding::Binary,
+            )
+            .and_then(|v| v.value)
+        {
+            Some(Variant::Byte(v)) => Some(AccessLevel::from_bits_truncate(v)),
+            _ => None,
+        };
+        let access_level = match (
+            read_level(AttributeId::AccessLevel),
+            read_level(AttributeId::UserAccessLevel),
+        ) {
+            (None, None) => AccessLevel::CURRENT_READ,
+            (access_level, user_access_level) => {
+                access_level.unwrap_or(AccessLevel::all())
+                    & user_access_level.unwrap_or(AccessLevel::all())
+            }
+        };
+        self.authenticator
+            .effective_user_access_level(&self.token, access_level, node.node_id())
+    }
 }
 
 /// Resolve a list of references.
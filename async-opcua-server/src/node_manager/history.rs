@@ -170,8 +170,8 @@ impl HistoryNode {
         let cp = match self.next_continuation_point {
             Some(p) => {
                 let id = random::byte_string(6);
-                if session.add_history_continuation_point(&id, p).is_err() {
-                    self.status = StatusCode::BadNoContinuationPoints;
+                if let Err(e) = session.add_history_continuation_point(&id, p) {
+                    self.status = e;
                     ByteString::null()
                 } else {
                     id
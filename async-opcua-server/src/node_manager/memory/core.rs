@@ -3,24 +3,28 @@ use std::{sync::Arc, time::Duration};
 use async_trait::async_trait;
 use chrono::Offset;
 use hashbrown::HashMap;
-use opcua_nodes::NodeType;
+use opcua_nodes::{BaseEventType, NodeType};
 
 use crate::{
-    address_space::{read_node_value, AddressSpace, CoreNamespace},
+    address_space::{read_node_value, AddressSpace, AddressSpaceLock, CoreNamespace},
     diagnostics::NamespaceMetadata,
     load_method_args,
     node_manager::{
         MethodCall, MonitoredItemRef, MonitoredItemUpdateRef, NodeManagersRef, ParsedReadValueId,
         RequestContext, ServerContext, SyncSampler,
     },
-    subscriptions::CreateMonitoredItem,
-    ServerCapabilities, ServerStatusWrapper,
+    subscriptions::{CreateMonitoredItem, MonitoredItemHandle},
+    ServerCapabilities, ServerStatusWrapper, SubscriptionCache,
+};
+use opcua_core::{
+    sync::{Mutex, RwLock},
+    trace_lock,
 };
-use opcua_core::{sync::RwLock, trace_lock};
 use opcua_types::{
-    DataValue, DateTime, ExtensionObject, IdType, Identifier, MethodId, MonitoringMode, NodeId,
-    NumericRange, ObjectId, ReferenceTypeId, StatusCode, TimeZoneDataType, TimestampsToReturn,
-    VariableId, Variant, VariantScalarTypeId, VariantTypeId,
+    AttributeId, ByteString, DataValue, DateTime, ExtensionObject, Guid, IdType, Identifier,
+    MethodId, MonitoringMode, NodeId, NumericRange, ObjectId, ObjectTypeId, ReferenceTypeId,
+    StatusCode, TimeZoneDataType, TimestampsToReturn, VariableId, Variant, VariantScalarTypeId,
+    VariantTypeId,
 };
 
 use super::{InMemoryNodeManager, InMemoryNodeManagerImpl, InMemoryNodeManagerImplBuilder};
@@ -30,6 +34,9 @@ pub struct CoreNodeManagerImpl {
     sampler: SyncSampler,
     node_managers: NodeManagersRef,
     status: Arc<ServerStatusWrapper>,
+    /// Monitored items on the namespace array, with the context of the session that created them,
+    /// since the namespace array may be different for each user.
+    namespace_array_items: Mutex<HashMap<MonitoredItemHandle, RequestContext>>,
 }
 
 /// Node manager for the core namespace.
@@ -72,13 +79,20 @@ impl InMemoryNodeManagerImpl for CoreNodeManagerImpl {
             .min_sampling_interval_ms
             .floor() as u64;
         let sampler_interval = if interval > 0 { interval } else { 100 };
-        self.sampler.run(
+        self.sampler.run_on(
             Duration::from_millis(sampler_interval),
             context.subscriptions.clone(),
+            &context.info.sampler_runtime(),
         );
         // Some core methods should be generally executable
         Self::set_method_executable(address_space, MethodId::Server_GetMonitoredItems);
         Self::set_method_executable(address_space, MethodId::Server_ResendData);
+        Self::set_method_executable(address_space, MethodId::ConditionType_ConditionRefresh);
+        #[cfg(feature = "pubsub")]
+        if context.info.security_key_service.is_some() {
+            Self::set_method_executable(address_space, MethodId::PublishSubscribe_GetSecurityKeys);
+            Self::set_method_executable(address_space, MethodId::PublishSubscribe_GetSecurityGroup);
+        }
     }
 
     fn namespaces(&self) -> Vec<NamespaceMetadata> {
@@ -108,14 +122,14 @@ impl InMemoryNodeManagerImpl for CoreNodeManagerImpl {
         max_age: f64,
         timestamps_to_return: TimestampsToReturn,
     ) -> Vec<DataValue> {
-        let address_space = address_space.read();
-
-        nodes
-            .iter()
-            .map(|n| {
-                self.read_node_value(context, &address_space, n, max_age, timestamps_to_return)
-            })
-            .collect()
+        address_space.with_read(|address_space| {
+            nodes
+                .iter()
+                .map(|n| {
+                    self.read_node_value(context, address_space, n, max_age, timestamps_to_return)
+                })
+                .collect()
+        })
     }
 
     async fn call(
@@ -138,37 +152,46 @@ impl InMemoryNodeManagerImpl for CoreNodeManagerImpl {
         address_space: &RwLock<AddressSpace>,
         items: &mut [&mut &mut CreateMonitoredItem],
     ) {
-        let address_space = address_space.read();
-        for node in items {
-            let value = self.read_node_value(
-                context,
-                &address_space,
-                node.item_to_monitor(),
-                0.0,
-                node.timestamps_to_return(),
-            );
-            if value.status() == StatusCode::BadUserAccessDenied {
-                node.set_status(StatusCode::BadUserAccessDenied);
-                continue;
-            }
-            if value.status() != StatusCode::BadAttributeIdInvalid {
-                node.set_initial_value(value);
-            }
-            node.set_status(StatusCode::Good);
-
-            if let Some(var_id) = self.status.get_managed_id(&node.item_to_monitor().node_id) {
-                self.status.subscribe_to_component(
-                    var_id,
-                    node.monitoring_mode(),
-                    node.handle(),
-                    Duration::from_millis(node.sampling_interval() as u64),
+        address_space.with_read(|address_space| {
+            for node in items {
+                let value = self.read_node_value(
+                    context,
+                    address_space,
+                    node.item_to_monitor(),
+                    0.0,
+                    node.timestamps_to_return(),
                 );
-            } else if self.is_internal_sampled(&node.item_to_monitor().node_id, context) {
-                if let Err(e) = self.add_internal_sampler(node, context) {
-                    node.set_status(e);
+                if value.status() == StatusCode::BadUserAccessDenied {
+                    node.set_status(StatusCode::BadUserAccessDenied);
+                    continue;
+                }
+                if value.status() != StatusCode::BadAttributeIdInvalid {
+                    node.set_initial_value(value);
+                }
+                node.set_status(StatusCode::Good);
+
+                if node.item_to_monitor().node_id == VariableId::Server_NamespaceArray
+                    && node.item_to_monitor().attribute_id == AttributeId::Value
+                {
+                    self.namespace_array_items
+                        .lock()
+                        .insert(node.handle(), context.clone());
+                }
+
+                if let Some(var_id) = self.status.get_managed_id(&node.item_to_monitor().node_id) {
+                    self.status.subscribe_to_component(
+                        var_id,
+                        node.monitoring_mode(),
+                        node.handle(),
+                        Duration::from_millis(node.sampling_interval() as u64),
+                    );
+                } else if self.is_internal_sampled(&node.item_to_monitor().node_id, context) {
+                    if let Err(e) = self.add_internal_sampler(node, context) {
+                        node.set_status(e);
+                    }
                 }
             }
-        }
+        })
     }
 
     async fn set_monitoring_mode(
@@ -207,6 +230,12 @@ impl InMemoryNodeManagerImpl for CoreNodeManagerImpl {
     }
 
     async fn delete_monitored_items(&self, _context: &RequestContext, items: &[&MonitoredItemRef]) {
+        {
+            let mut namespace_array_items = self.namespace_array_items.lock();
+            for item in items {
+                namespace_array_items.remove(&item.handle());
+            }
+        }
         for item in items {
             if self.status.get_managed_id(item.node_id()).is_some() {
                 self.status.sampler().remove_sampler(
@@ -225,6 +254,7 @@ impl CoreNodeManagerImpl {
             sampler: SyncSampler::new(),
             status,
             node_managers,
+            namespace_array_items: Default::default(),
         }
     }
 
@@ -301,6 +331,50 @@ impl CoreNodeManagerImpl {
         }
     }
 
+    /// Get the value of the namespace array for the user in `context`.
+    fn namespace_array(&self, context: &RequestContext) -> Option<Variant> {
+        // This actually calls into other node managers to obtain the value, in fact
+        // it calls into _this_ node manager as well.
+        // Be careful to avoid holding exclusive locks in a way that causes a deadlock
+        // when doing this. Here we hold a read lock on the address space,
+        // but in this case it doesn't matter.
+        let nss: HashMap<_, _> = self
+            .node_managers
+            .iter()
+            .flat_map(|n| n.namespaces_for_user(context))
+            .map(|ns| (ns.namespace_index, ns.namespace_uri))
+            .collect();
+        // Make sure that holes are filled with empty strings, so that the
+        // namespace array actually has correct indices.
+        let &max = nss.keys().max()?;
+        let namespaces: Vec<_> = (0..(max + 1))
+            .map(|idx| nss.get(&idx).cloned().unwrap_or_default())
+            .collect();
+        Some(namespaces.into())
+    }
+
+    /// Notify any monitored items on the `NamespaceArray` variable of the current
+    /// namespace array. Use [`ServerContext::notify_namespaces_changed`] or
+    /// [`ServerHandle::notify_namespaces_changed`](crate::ServerHandle::notify_namespaces_changed)
+    /// rather than calling this directly.
+    pub fn notify_namespaces_changed(&self, subscriptions: &SubscriptionCache) {
+        // Compute the values first, since reading the namespace array calls into node managers.
+        let items: Vec<_> = self
+            .namespace_array_items
+            .lock()
+            .iter()
+            .map(|(handle, context)| (*handle, context.clone()))
+            .collect();
+        let values: Vec<_> = items
+            .into_iter()
+            .filter_map(|(handle, context)| {
+                let value = self.namespace_array(&context)?;
+                Some((handle, DataValue::new_now(value)))
+            })
+            .collect();
+        subscriptions.notify_data_change_for_items(values.into_iter());
+    }
+
     fn read_server_value(
         &self,
         context: &RequestContext,
@@ -370,7 +444,7 @@ impl CoreNodeManagerImpl {
                 (limits.operational.max_nodes_per_write as u32).into()
             }
             VariableId::Server_ServerCapabilities_ServerProfileArray => {
-                context.info.capabilities.profiles.clone().into()
+                context.info.capabilities.profile_uris().into()
             }
 
             // History capabilities
@@ -478,18 +552,22 @@ impl CoreNodeManagerImpl {
                 (self.status.state() as i32).into()
             }
 
-            VariableId::Server_NamespaceArray => {
-                // This actually calls into other node managers to obtain the value, in fact
-                // it calls into _this_ node manager as well.
-                // Be careful to avoid holding exclusive locks in a way that causes a deadlock
-                // when doing this. Here we hold a read lock on the address space,
-                // but in this case it doesn't matter.
-                let nss: HashMap<_, _> = self.node_managers.iter().flat_map(|n| n.namespaces_for_user(context)).map(|ns| (ns.namespace_index, ns.namespace_uri)).collect();
-                // Make sure that holes are filled with empty strings, so that the
-                // namespace array actually has correct indices.
-                let &max = nss.keys().max()?;
-                let namespaces: Vec<_> = (0..(max + 1)).map(|idx| nss.get(&idx).cloned().unwrap_or_default()).collect();
-                namespaces.into()
+            VariableId::Server_NamespaceArray => self.namespace_array(context)?,
+
+            VariableId::Server_ServerDiagnostics_SubscriptionDiagnosticsArray
+                if context.info.diagnostics.subscriptions_enabled() =>
+            {
+                let perms = context.info.authenticator.core_permissions(&context.token);
+                if !perms.read_diagnostics {
+                    return Some(DataValue::new_now_status(Variant::Empty, StatusCode::BadUserAccessDenied));
+                }
+                context
+                    .subscriptions
+                    .subscription_diagnostics()
+                    .into_iter()
+                    .map(ExtensionObject::from_message)
+                    .collect::<Vec<_>>()
+                    .into()
             }
 
             r if context.info.diagnostics.is_mapped(r) => {
@@ -539,6 +617,16 @@ impl CoreNodeManagerImpl {
         }
     }
 
+    fn refresh_event(type_id: ObjectTypeId, message: &str) -> BaseEventType {
+        BaseEventType::new_now(
+            type_id,
+            ByteString::from(Guid::new().as_bytes().to_vec()),
+            message,
+        )
+        .set_source_node(ObjectId::Server.into())
+        .set_source_name("Server".into())
+    }
+
     fn set_method_executable(address_space: &mut AddressSpace, method: MethodId) {
         let Some(NodeType::Method(m)) = address_space.find_mut(method) else {
             return;
@@ -559,14 +647,9 @@ impl CoreNodeManagerImpl {
         match id {
             MethodId::Server_GetMonitoredItems => {
                 let id = load_method_args!(call, UInt32)?;
-                let subs = context
+                let (ids, handles) = context
                     .subscriptions
-                    .get_session_subscriptions(context.session_id)
-                    .ok_or(StatusCode::BadSessionIdInvalid)?;
-                let subs = trace_lock!(subs);
-                let sub = subs.get(id).ok_or(StatusCode::BadSubscriptionIdInvalid)?;
-                let (ids, handles): (Vec<_>, Vec<_>) =
-                    sub.items().map(|i| (i.id(), i.client_handle())).unzip();
+                    .get_monitored_items(Some(context.session_id), id)?;
                 call.set_outputs(vec![ids.into(), handles.into()]);
                 call.set_status(StatusCode::Good);
             }
@@ -583,6 +666,55 @@ impl CoreNodeManagerImpl {
                 sub.set_resend_data();
                 call.set_status(StatusCode::Good);
             }
+            MethodId::ConditionType_ConditionRefresh => {
+                let id = load_method_args!(call, UInt32)?;
+                let start =
+                    Self::refresh_event(ObjectTypeId::RefreshStartEventType, "Refresh start");
+                let end = Self::refresh_event(ObjectTypeId::RefreshEndEventType, "Refresh end");
+                context
+                    .subscriptions
+                    .condition_refresh(context.session_id, id, &start, &end)?;
+                call.set_status(StatusCode::Good);
+            }
+            #[cfg(feature = "pubsub")]
+            MethodId::PublishSubscribe_GetSecurityKeys => {
+                let sks = context
+                    .info
+                    .security_key_service
+                    .as_ref()
+                    .ok_or(StatusCode::BadNotSupported)?;
+                // Keys may only be sent over an encrypted channel.
+                if context.session.read().message_security_mode()
+                    != opcua_types::MessageSecurityMode::SignAndEncrypt
+                {
+                    return Err(StatusCode::BadSecurityModeInsufficient);
+                }
+                // Keys give access to all messages of the group, so only administrators may fetch them.
+                if !context.authenticator.core_permissions(&context.token).admin {
+                    return Err(StatusCode::BadUserAccessDenied);
+                }
+                let (group_id, starting_token_id, requested_key_count) =
+                    load_method_args!(call, String, UInt32, UInt32)?;
+                let keys = sks.get_security_keys(
+                    group_id.as_ref(),
+                    starting_token_id,
+                    requested_key_count,
+                )?;
+                call.set_outputs(keys.into_outputs());
+                call.set_status(StatusCode::Good);
+            }
+            #[cfg(feature = "pubsub")]
+            MethodId::PublishSubscribe_GetSecurityGroup => {
+                let sks = context
+                    .info
+                    .security_key_service
+                    .as_ref()
+                    .ok_or(StatusCode::BadNotSupported)?;
+                let group_id = load_method_args!(call, String)?;
+                let node_id = sks.get_security_group(group_id.as_ref())?;
+                call.set_outputs(vec![node_id.into()]);
+                call.set_status(StatusCode::Good);
+            }
             _ => return Err(StatusCode::BadNotSupported),
         }
         Ok(())
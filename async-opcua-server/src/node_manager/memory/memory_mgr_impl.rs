@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 
 use crate::{
-    address_space::AddressSpace,
+    address_space::{AddressSpace, AddressSpaceLock},
     diagnostics::NamespaceMetadata,
     node_manager::{
         AddNodeItem, AddReferenceItem, DeleteNodeItem, DeleteReferenceItem, HistoryNode,
@@ -12,7 +12,7 @@ use crate::{
 };
 use opcua_core::sync::RwLock;
 use opcua_types::{
-    DataValue, ExpandedNodeId, MonitoringMode, NodeId, ReadAnnotationDataDetails,
+    AttributeId, DataValue, ExpandedNodeId, MonitoringMode, NodeId, ReadAnnotationDataDetails,
     ReadAtTimeDetails, ReadEventDetails, ReadProcessedDetails, ReadRawModifiedDetails, StatusCode,
     TimestampsToReturn,
 };
@@ -95,11 +95,12 @@ pub trait InMemoryNodeManagerImpl: Send + Sync + 'static {
         max_age: f64,
         timestamps_to_return: TimestampsToReturn,
     ) -> Vec<DataValue> {
-        let address_space = address_space.read();
-        nodes
-            .iter()
-            .map(|n| address_space.read(context, n, max_age, timestamps_to_return))
-            .collect()
+        address_space.with_read(|address_space| {
+            nodes
+                .iter()
+                .map(|n| address_space.read(context, n, max_age, timestamps_to_return))
+                .collect()
+        })
     }
 
     /// Create monitored items for the Value attribute, as needed.
@@ -278,6 +279,31 @@ pub trait InMemoryNodeManagerImpl: Send + Sync + 'static {
         Err(StatusCode::BadServiceUnsupported)
     }
 
+    /// Return whether this node manager supports write transactions.
+    /// See [NodeManager::supports_write_transactions](crate::node_manager::NodeManager::supports_write_transactions).
+    fn supports_write_transactions(&self) -> bool {
+        false
+    }
+
+    /// Write a group of attributes on a single node atomically. Either all writes
+    /// should be applied, or none of them.
+    ///
+    /// The default implementation falls back to independent writes through `write`.
+    /// See [NodeManager::write_transaction](crate::node_manager::NodeManager::write_transaction)
+    /// for details.
+    async fn write_transaction(
+        &self,
+        context: &RequestContext,
+        address_space: &RwLock<AddressSpace>,
+        node_id: &NodeId,
+        writes: &[(AttributeId, DataValue)],
+    ) -> Result<(), StatusCode> {
+        let mut nodes = WriteNode::from_transaction(node_id, writes);
+        let mut refs: Vec<_> = nodes.iter_mut().collect();
+        self.write(context, address_space, &mut refs).await?;
+        WriteNode::transaction_result(&nodes)
+    }
+
     /// Call a list of methods.
     ///
     /// The methods have already had their arguments verified to have valid length
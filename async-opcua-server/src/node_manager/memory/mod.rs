@@ -3,6 +3,7 @@
 //! details to a type implementing [InMemoryNodeManagerImpl].
 
 mod memory_mgr_impl;
+mod signature;
 mod simple;
 
 #[cfg(feature = "generated-address-space")]
@@ -13,33 +14,43 @@ pub use core::{CoreNodeManager, CoreNodeManagerBuilder, CoreNodeManagerImpl};
 
 pub use memory_mgr_impl::*;
 use opcua_core::{trace_read_lock, trace_write_lock};
+use signature::MethodSignatureCache;
+pub use signature::{read_method_arguments, MethodSignature};
 pub use simple::*;
 use tracing::warn;
 
 use std::{
     collections::{HashSet, VecDeque},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
 
 use async_trait::async_trait;
 use hashbrown::HashMap;
 
+#[cfg(feature = "generated-address-space")]
+use crate::events::GeneralModelChangeEvent;
 use crate::{
     address_space::{
-        read_node_value, user_access_level, AccessLevel, EventNotifier, NodeType,
-        ReferenceDirection,
+        read_node_value, user_access_level, AccessLevel, AddressSpaceLock, BaseEventType, Event,
+        EventNotifier, HasNodeId, NodeType, ReferenceDirection,
     },
     diagnostics::NamespaceMetadata,
     subscriptions::CreateMonitoredItem,
     SubscriptionCache,
 };
 use opcua_core::sync::RwLock;
+use opcua_nodes::{TypeTree, TypeTreeNode};
+#[cfg(feature = "generated-address-space")]
+use opcua_types::ModelChangeStructureVerbMask as Verb;
 use opcua_types::{
-    argument::Argument, AttributeId, BrowseDescriptionResultMask, BrowseDirection, DataEncoding,
-    DataValue, DateTime, ExpandedNodeId, MonitoringMode, NodeClass, NodeId, NumericRange,
-    ReadAnnotationDataDetails, ReadAtTimeDetails, ReadEventDetails, ReadProcessedDetails,
-    ReadRawModifiedDetails, ReferenceDescription, ReferenceTypeId, StatusCode, TimestampsToReturn,
-    Variant,
+    AttributeId, BrowseDescriptionResultMask, BrowseDirection, ByteString, DataTypeId, DataValue,
+    DateTime, ExpandedNodeId, Guid, MonitoringMode, NodeClass, NodeId, NumericRange, ObjectId,
+    ObjectTypeId, ReadAnnotationDataDetails, ReadAtTimeDetails, ReadEventDetails,
+    ReadProcessedDetails, ReadRawModifiedDetails, ReferenceDescription, ReferenceTypeId,
+    StatusCode, TimestampsToReturn, Variant,
 };
 
 use super::{
@@ -53,9 +64,46 @@ use super::{
 
 use crate::address_space::AddressSpace;
 
+/// Attributes that data change monitored items may be created for. `EventNotifier` is
+/// left out, since monitoring it means subscribing to events.
+const DATA_CHANGE_ATTRIBUTES: [AttributeId; 26] = [
+    AttributeId::NodeId,
+    AttributeId::NodeClass,
+    AttributeId::BrowseName,
+    AttributeId::DisplayName,
+    AttributeId::Description,
+    AttributeId::WriteMask,
+    AttributeId::UserWriteMask,
+    AttributeId::IsAbstract,
+    AttributeId::Symmetric,
+    AttributeId::InverseName,
+    AttributeId::ContainsNoLoops,
+    AttributeId::Value,
+    AttributeId::DataType,
+    AttributeId::ValueRank,
+    AttributeId::ArrayDimensions,
+    AttributeId::AccessLevel,
+    AttributeId::UserAccessLevel,
+    AttributeId::MinimumSamplingInterval,
+    AttributeId::Historizing,
+    AttributeId::Executable,
+    AttributeId::UserExecutable,
+    AttributeId::DataTypeDefinition,
+    AttributeId::RolePermissions,
+    AttributeId::UserRolePermissions,
+    AttributeId::AccessRestrictions,
+    AttributeId::AccessLevelEx,
+];
+
+/// Continuation point for browse. This is a snapshot of the remaining references
+/// taken when the node was first browsed, so that BrowseNext returns them in the
+/// original order, even if the address space is modified in the meantime.
+///
+/// Continuation points are invalidated if the whole address space is replaced.
 #[derive(Default)]
 struct BrowseContinuationPoint {
     nodes: VecDeque<ReferenceDescription>,
+    generation: u64,
 }
 
 /// A node manager that stores its nodes in an in-memory [AddressSpace]. This
@@ -66,6 +114,9 @@ struct BrowseContinuationPoint {
 pub struct InMemoryNodeManager<TImpl> {
     address_space: Arc<RwLock<AddressSpace>>,
     namespaces: HashMap<u16, String>,
+    method_signatures: MethodSignatureCache,
+    /// Incremented each time the address space is replaced.
+    generation: AtomicU64,
     inner: TImpl,
 }
 
@@ -95,6 +146,8 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
         Self {
             namespaces: address_space.namespaces().clone(),
             address_space: Arc::new(RwLock::new(address_space)),
+            method_signatures: MethodSignatureCache::default(),
+            generation: AtomicU64::new(0),
             inner,
         }
     }
@@ -115,6 +168,104 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
         &self.namespaces
     }
 
+    /// Get the signature of the method with ID `method_id`, read from its
+    /// `InputArguments` and `OutputArguments` properties.
+    ///
+    /// Signatures are cached per method. The cache is cleared when nodes are written
+    /// or modified through the node management services, but if you modify the argument
+    /// properties of a method directly in the address space, call
+    /// [InMemoryNodeManager::invalidate_method_signature] afterwards.
+    pub fn method_signature(
+        &self,
+        type_tree: &dyn TypeTree,
+        method_id: &NodeId,
+    ) -> Arc<MethodSignature> {
+        self.address_space.with_read(|address_space| {
+            self.method_signatures
+                .get_or_read(address_space, type_tree, method_id)
+        })
+    }
+
+    /// Remove the cached signature of the method with ID `method_id`, so that
+    /// it is read from the address space again the next time it is needed.
+    pub fn invalidate_method_signature(&self, method_id: &NodeId) {
+        self.method_signatures.invalidate(method_id);
+    }
+
+    /// Replace the entire address space of this node manager with `address_space`,
+    /// for example to reload configuration. The new address space can be built
+    /// without holding any locks, and is swapped in at once, so clients never see
+    /// a partially loaded address space.
+    ///
+    /// The new address space must contain the same namespaces as the current one,
+    /// otherwise this returns `BadInvalidArgument`. Types are updated in the server
+    /// type tree, browse continuation points into this node manager are invalidated,
+    /// and monitored items on nodes that no longer exist report `BadNodeIdUnknown`.
+    /// Finally, a `BaseModelChangeEvent` is emitted from the `Server` object.
+    pub fn replace_address_space(
+        &self,
+        subscriptions: &SubscriptionCache,
+        type_tree: &RwLock<DefaultTypeTree>,
+        address_space: AddressSpace,
+    ) -> Result<(), StatusCode> {
+        if address_space.namespaces() != &self.namespaces {
+            return Err(StatusCode::BadInvalidArgument);
+        }
+
+        let old = self.address_space.with_write(|current| {
+            let old = std::mem::replace(&mut *current, address_space);
+            self.generation.fetch_add(1, Ordering::AcqRel);
+            self.method_signatures.clear();
+
+            let mut type_tree = trace_write_lock!(type_tree);
+            for node in old.iter() {
+                if current.find(node.node_id()).is_none() {
+                    type_tree.remove(node.node_id());
+                }
+            }
+            current.load_into_type_tree(&mut type_tree);
+            old
+        });
+
+        // Notify subscriptions once the address space and type tree are unlocked.
+        // Only nodes in the old address space can have monitored items.
+        self.address_space.with_read(|current| {
+            subscriptions.maybe_notify(
+                old.iter().flat_map(|n| {
+                    DATA_CHANGE_ATTRIBUTES
+                        .iter()
+                        .map(move |a| (n.node_id(), *a))
+                }),
+                |node_id, attribute_id, index_range, data_encoding| {
+                    let Some(node) = current.find(node_id) else {
+                        return Some(DataValue {
+                            status: Some(StatusCode::BadNodeIdUnknown),
+                            server_timestamp: Some(DateTime::now()),
+                            ..Default::default()
+                        });
+                    };
+                    node.as_node().get_attribute(
+                        TimestampsToReturn::Both,
+                        attribute_id,
+                        index_range,
+                        data_encoding,
+                    )
+                },
+            );
+        });
+
+        let event = BaseEventType::new_now(
+            ObjectTypeId::BaseModelChangeEventType,
+            ByteString::from(Guid::new().as_bytes().to_vec()),
+            "Address space replaced",
+        )
+        .set_source_node(ObjectId::Server.into())
+        .set_source_name("Server".into());
+        subscriptions.notify_events([(&event as &dyn Event, &ObjectId::Server.into())].into_iter());
+
+        Ok(())
+    }
+
     /// Set the attributes given in `values` and notify any subscriptions
     /// about the changes.
     ///
@@ -124,39 +275,40 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
         subscriptions: &SubscriptionCache,
         values: impl Iterator<Item = (&'a NodeId, AttributeId, Variant)>,
     ) -> Result<(), StatusCode> {
-        let mut address_space = trace_write_lock!(self.address_space);
-        let mut output = Vec::new();
+        self.address_space.with_write(|address_space| {
+            let mut output = Vec::new();
 
-        for (id, attribute_id, value) in values {
-            let Some(node) = address_space.find_mut(id) else {
-                return Err(StatusCode::BadNodeIdUnknown);
-            };
+            for (id, attribute_id, value) in values {
+                let Some(node) = address_space.find_mut(id) else {
+                    return Err(StatusCode::BadNodeIdUnknown);
+                };
 
-            let node_mut = node.as_mut_node();
-            node_mut.set_attribute(attribute_id, value)?;
-            // Don't notify on changes to event notifier, subscribing to that
-            // specific attribute means subscribing to events.
-            if attribute_id != AttributeId::EventNotifier {
-                output.push((id, attribute_id));
+                let node_mut = node.as_mut_node();
+                node_mut.set_attribute(attribute_id, value)?;
+                // Don't notify on changes to event notifier, subscribing to that
+                // specific attribute means subscribing to events.
+                if attribute_id != AttributeId::EventNotifier {
+                    output.push((id, attribute_id));
+                }
             }
-        }
 
-        subscriptions.maybe_notify(
-            output.into_iter(),
-            |node_id, attribute_id, index_range, data_encoding| {
-                let node = address_space.find(node_id)?;
-                let node_ref = node.as_node();
-
-                node_ref.get_attribute(
-                    TimestampsToReturn::Both,
-                    attribute_id,
-                    index_range,
-                    data_encoding,
-                )
-            },
-        );
+            subscriptions.maybe_notify(
+                output.into_iter(),
+                |node_id, attribute_id, index_range, data_encoding| {
+                    let node = address_space.find(node_id)?;
+                    let node_ref = node.as_node();
+
+                    node_ref.get_attribute(
+                        TimestampsToReturn::Both,
+                        attribute_id,
+                        index_range,
+                        data_encoding,
+                    )
+                },
+            );
 
-        Ok(())
+            Ok(())
+        })
     }
 
     /// Set the attribute given by `attribute_id` on the node with ID `id` to
@@ -178,55 +330,56 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
         subscriptions: &SubscriptionCache,
         values: impl Iterator<Item = (&'a NodeId, Option<&'a NumericRange>, DataValue)>,
     ) -> Result<(), StatusCode> {
-        let mut address_space = trace_write_lock!(self.address_space);
-        let now = DateTime::now();
-        let mut output = Vec::new();
+        self.address_space.with_write(|address_space| {
+            let now = DateTime::now();
+            let mut output = Vec::new();
 
-        for (id, index_range, value) in values {
-            let Some(node) = address_space.find_mut(id) else {
-                return Err(StatusCode::BadNodeIdUnknown);
-            };
+            for (id, index_range, value) in values {
+                let Some(node) = address_space.find_mut(id) else {
+                    return Err(StatusCode::BadNodeIdUnknown);
+                };
 
-            match node {
-                NodeType::Variable(v) => {
-                    if let Some(range) = index_range {
-                        let status = value.status();
-                        let source_timestamp = value.source_timestamp.unwrap_or(now);
-                        let server_timestamp = value.server_timestamp.unwrap_or(now);
-                        v.set_value_range(
-                            value.value.unwrap_or_default(),
-                            range,
-                            status,
-                            &server_timestamp,
-                            &source_timestamp,
-                        )?
-                    } else {
-                        v.set_data_value(value)
+                match node {
+                    NodeType::Variable(v) => {
+                        if let Some(range) = index_range {
+                            let status = value.status();
+                            let source_timestamp = value.source_timestamp.unwrap_or(now);
+                            let server_timestamp = value.server_timestamp.unwrap_or(now);
+                            v.set_value_range(
+                                value.value.unwrap_or_default(),
+                                range,
+                                status,
+                                &server_timestamp,
+                                &source_timestamp,
+                            )?
+                        } else {
+                            v.set_data_value(value)
+                        }
                     }
+                    NodeType::VariableType(v) => v.set_value(value.value.unwrap_or_default()),
+                    _ => return Err(StatusCode::BadAttributeIdInvalid),
                 }
-                NodeType::VariableType(v) => v.set_value(value.value.unwrap_or_default()),
-                _ => return Err(StatusCode::BadAttributeIdInvalid),
-            }
 
-            output.push((id, AttributeId::Value));
-        }
+                output.push((id, AttributeId::Value));
+            }
 
-        subscriptions.maybe_notify(
-            output.into_iter(),
-            |node_id, attribute_id, index_range, data_encoding| {
-                let node = address_space.find(node_id)?;
-                let node_ref = node.as_node();
-
-                node_ref.get_attribute(
-                    TimestampsToReturn::Both,
-                    attribute_id,
-                    index_range,
-                    data_encoding,
-                )
-            },
-        );
+            subscriptions.maybe_notify(
+                output.into_iter(),
+                |node_id, attribute_id, index_range, data_encoding| {
+                    let node = address_space.find(node_id)?;
+                    let node_ref = node.as_node();
+
+                    node_ref.get_attribute(
+                        TimestampsToReturn::Both,
+                        attribute_id,
+                        index_range,
+                        data_encoding,
+                    )
+                },
+            );
 
-        Ok(())
+            Ok(())
+        })
     }
 
     /// Set the variable value to `value`, using `index_range`, on the
@@ -241,6 +394,73 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
         self.set_values(subscriptions, [(id, index_range, value)].into_iter())
     }
 
+    /// Enable events on the object or view with ID `node_id`.
+    ///
+    /// This sets the `SubscribeToEvents` bit of the `EventNotifier` attribute, so that
+    /// clients can create event monitored items on the node, and registers the node as
+    /// an event source with the subscription cache. Use [InMemoryNodeManager::emit_event]
+    /// to emit events from the node.
+    pub fn enable_events(
+        &self,
+        subscriptions: &SubscriptionCache,
+        node_id: &NodeId,
+    ) -> Result<(), StatusCode> {
+        self.address_space
+            .with_write(|address_space| match address_space.find_mut(node_id) {
+                Some(NodeType::Object(o)) => {
+                    o.set_event_notifier(o.event_notifier() | EventNotifier::SUBSCRIBE_TO_EVENTS);
+                    Ok(())
+                }
+                Some(NodeType::View(v)) => {
+                    v.set_event_notifier(v.event_notifier() | EventNotifier::SUBSCRIBE_TO_EVENTS);
+                    Ok(())
+                }
+                Some(_) => Err(StatusCode::BadNodeClassInvalid),
+                None => Err(StatusCode::BadNodeIdUnknown),
+            })?;
+        subscriptions.register_event_source(node_id);
+        Ok(())
+    }
+
+    /// Emit `event` from the event source `source`, notifying any event monitored items
+    /// on `source`, and on the `Server` object, which receives all events.
+    ///
+    /// `source` must be the `Server` object or a node passed to
+    /// [InMemoryNodeManager::enable_events], otherwise this returns `BadNotSupported`.
+    pub fn emit_event(
+        &self,
+        subscriptions: &SubscriptionCache,
+        source: &NodeId,
+        event: &dyn Event,
+    ) -> Result<(), StatusCode> {
+        if !subscriptions.is_event_source(source) {
+            return Err(StatusCode::BadNotSupported);
+        }
+        subscriptions.notify_events([(event, source)].into_iter());
+        Ok(())
+    }
+
+    /// Emit an event from a [Condition](crate::events::Condition) from the source node
+    /// of the condition. If the condition is retained, the event is kept as its current state,
+    /// and sent again to clients calling `ConditionRefresh`.
+    ///
+    /// The source node must be an event source, as with [InMemoryNodeManager::emit_event].
+    #[cfg(feature = "generated-address-space")]
+    pub fn emit_condition_event(
+        &self,
+        subscriptions: &SubscriptionCache,
+        event: crate::events::ConditionEvent,
+    ) -> Result<(), StatusCode> {
+        let source = event.source_node().clone();
+        if !subscriptions.is_event_source(&source) {
+            return Err(StatusCode::BadNotSupported);
+        }
+        let condition_id = event.condition_id().clone();
+        let retain = event.retain();
+        subscriptions.notify_condition_event(&condition_id, &source, Box::new(event), retain);
+        Ok(())
+    }
+
     fn get_reference(
         address_space: &AddressSpace,
         type_tree: &DefaultTypeTree,
@@ -291,6 +511,7 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
         type_tree: &DefaultTypeTree,
         node: &mut BrowseNode,
         namespaces: &hashbrown::HashMap<u16, String>,
+        generation: u64,
     ) {
         let reference_type_id = if node.reference_type_id().is_null() {
             None
@@ -300,7 +521,10 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
             None
         };
 
-        let mut cont_point = BrowseContinuationPoint::default();
+        let mut cont_point = BrowseContinuationPoint {
+            generation,
+            ..Default::default()
+        };
 
         let source_node_id = node.node_id().clone();
 
@@ -441,46 +665,47 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
         nodes: &'b mut [&'a mut HistoryNode],
         is_for_events: bool,
     ) -> Vec<&'b mut &'a mut HistoryNode> {
-        let address_space = trace_read_lock!(self.address_space);
-        let mut valid = Vec::with_capacity(nodes.len());
-
-        for history_node in nodes {
-            let Some(node) = address_space.find(history_node.node_id()) else {
-                history_node.set_status(StatusCode::BadNodeIdUnknown);
-                continue;
-            };
+        self.address_space.with_read(|address_space| {
+            let mut valid = Vec::with_capacity(nodes.len());
 
-            if is_for_events {
-                let NodeType::Object(object) = node else {
-                    history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
+            for history_node in nodes {
+                let Some(node) = address_space.find(history_node.node_id()) else {
+                    history_node.set_status(StatusCode::BadNodeIdUnknown);
                     continue;
                 };
 
-                if !object
-                    .event_notifier()
-                    .contains(EventNotifier::HISTORY_READ)
-                {
-                    history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
-                    continue;
-                }
-            } else {
-                let NodeType::Variable(_) = node else {
-                    history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
-                    continue;
-                };
+                if is_for_events {
+                    let NodeType::Object(object) = node else {
+                        history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
+                        continue;
+                    };
 
-                let user_access_level = user_access_level(context, node);
+                    if !object
+                        .event_notifier()
+                        .contains(EventNotifier::HISTORY_READ)
+                    {
+                        history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
+                        continue;
+                    }
+                } else {
+                    let NodeType::Variable(_) = node else {
+                        history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
+                        continue;
+                    };
 
-                if !user_access_level.contains(AccessLevel::HISTORY_READ) {
-                    history_node.set_status(StatusCode::BadUserAccessDenied);
-                    continue;
+                    let user_access_level = user_access_level(context, node);
+
+                    if !user_access_level.contains(AccessLevel::HISTORY_READ) {
+                        history_node.set_status(StatusCode::BadUserAccessDenied);
+                        continue;
+                    }
                 }
-            }
 
-            valid.push(history_node);
-        }
+                valid.push(history_node);
+            }
 
-        valid
+            valid
+        })
     }
 
     fn validate_history_write_nodes<'a, 'b>(
@@ -488,51 +713,79 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
         context: &RequestContext,
         nodes: &'b mut [&'a mut HistoryUpdateNode],
     ) -> Vec<&'b mut &'a mut HistoryUpdateNode> {
-        let address_space = trace_read_lock!(self.address_space);
-        let mut valid = Vec::with_capacity(nodes.len());
+        self.address_space.with_read(|address_space| {
+            let mut valid = Vec::with_capacity(nodes.len());
 
-        for history_node in nodes {
-            let Some(node) = address_space.find(history_node.details().node_id()) else {
-                history_node.set_status(StatusCode::BadNodeIdUnknown);
-                continue;
-            };
-
-            let is_for_events = matches!(
-                history_node.details(),
-                HistoryUpdateDetails::DeleteEvent(_) | HistoryUpdateDetails::UpdateEvent(_)
-            );
-
-            if is_for_events {
-                let NodeType::Object(object) = node else {
-                    history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
+            for history_node in nodes {
+                let Some(node) = address_space.find(history_node.details().node_id()) else {
+                    history_node.set_status(StatusCode::BadNodeIdUnknown);
                     continue;
                 };
 
-                if !object
-                    .event_notifier()
-                    .contains(EventNotifier::HISTORY_WRITE)
-                {
-                    history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
-                    continue;
-                }
-            } else {
-                let NodeType::Variable(_) = node else {
-                    history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
-                    continue;
-                };
+                let is_for_events = matches!(
+                    history_node.details(),
+                    HistoryUpdateDetails::DeleteEvent(_) | HistoryUpdateDetails::UpdateEvent(_)
+                );
 
-                let user_access_level = user_access_level(context, node);
+                if is_for_events {
+                    let NodeType::Object(object) = node else {
+                        history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
+                        continue;
+                    };
 
-                if !user_access_level.contains(AccessLevel::HISTORY_WRITE) {
-                    history_node.set_status(StatusCode::BadUserAccessDenied);
-                    continue;
+                    if !object
+                        .event_notifier()
+                        .contains(EventNotifier::HISTORY_WRITE)
+                    {
+                        history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
+                        continue;
+                    }
+                } else {
+                    let NodeType::Variable(_) = node else {
+                        history_node.set_status(StatusCode::BadHistoryOperationUnsupported);
+                        continue;
+                    };
+
+                    let user_access_level = user_access_level(context, node);
+
+                    if !user_access_level.contains(AccessLevel::HISTORY_WRITE) {
+                        history_node.set_status(StatusCode::BadUserAccessDenied);
+                        continue;
+                    }
                 }
+
+                valid.push(history_node);
             }
 
-            valid.push(history_node);
-        }
+            valid
+        })
+    }
 
-        valid
+    /// Check whether `method_id` is a method of the type definition of `object_id`,
+    /// or one of its supertypes, and may be called on the object.
+    fn is_type_method(
+        address_space: &AddressSpace,
+        type_tree: &DefaultTypeTree,
+        object_id: &NodeId,
+        method_id: &NodeId,
+    ) -> bool {
+        let Some(TypeTreeNode::Property(prop)) = type_tree.get_node(method_id) else {
+            return false;
+        };
+        let is_method = type_tree
+            .find_type_prop_by_browse_path(&prop.type_id, &prop.path)
+            .is_some_and(|p| p.node_class == NodeClass::Method);
+        if !is_method || prop.path.len() != 1 {
+            return false;
+        }
+        address_space
+            .find_references(
+                object_id,
+                Some((ReferenceTypeId::HasTypeDefinition, false)),
+                type_tree,
+                BrowseDirection::Forward,
+            )
+            .any(|r| type_tree.is_subtype_of(r.target_node, &prop.type_id))
     }
 
     fn validate_method_calls<'a, 'b>(
@@ -540,112 +793,165 @@ impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
         context: &RequestContext,
         methods: &'b mut [&'a mut MethodCall],
     ) -> Vec<&'b mut &'a mut MethodCall> {
-        let address_space = trace_read_lock!(self.address_space);
-        let type_tree = trace_read_lock!(context.type_tree);
-        let mut valid = Vec::with_capacity(methods.len());
-
-        for method in methods {
-            let Some(method_ref) = address_space
-                .find_references(
-                    method.object_id(),
-                    Some((ReferenceTypeId::HasComponent, false)),
-                    &*type_tree,
-                    BrowseDirection::Forward,
-                )
-                .find(|r| r.target_node == method.method_id())
-            else {
-                method.set_status(StatusCode::BadMethodInvalid);
-                continue;
-            };
+        self.address_space.with_read(|address_space| {
+            let type_tree = trace_read_lock!(context.type_tree);
+            let mut valid = Vec::with_capacity(methods.len());
+
+            for method in methods {
+                let Some(method_ref) = address_space
+                    .find_references(
+                        method.object_id(),
+                        Some((ReferenceTypeId::HasComponent, false)),
+                        &*type_tree,
+                        BrowseDirection::Forward,
+                    )
+                    .find(|r| r.target_node == method.method_id())
+                else {
+                    if Self::is_type_method(
+                        address_space,
+                        &type_tree,
+                        method.object_id(),
+                        method.method_id(),
+                    ) {
+                        if !context
+                            .authenticator
+                            .is_user_executable(&context.token, method.method_id())
+                        {
+                            method.set_status(StatusCode::BadUserAccessDenied);
+                        } else {
+                            // The method node is owned by a different node manager, so leave
+                            // validating the arguments to the implementation.
+                            valid.push(method);
+                        }
+                        continue;
+                    }
+                    method.set_status(StatusCode::BadMethodInvalid);
+                    continue;
+                };
 
-            let Some(NodeType::Method(method_node)) = address_space.find(method_ref.target_node)
-            else {
-                method.set_status(StatusCode::BadMethodInvalid);
-                continue;
-            };
+                let Some(NodeType::Method(method_node)) =
+                    address_space.find(method_ref.target_node)
+                else {
+                    method.set_status(StatusCode::BadMethodInvalid);
+                    continue;
+                };
 
-            if !method_node.user_executable()
-                || !context
-                    .authenticator
-                    .is_user_executable(&context.token, method.method_id())
-            {
-                method.set_status(StatusCode::BadUserAccessDenied);
-                continue;
-            }
+                if !method_node.user_executable()
+                    || !context
+                        .authenticator
+                        .is_user_executable(&context.token, method.method_id())
+                {
+                    method.set_status(StatusCode::BadUserAccessDenied);
+                    continue;
+                }
 
-            let input_arguments = address_space.find_node_by_browse_name(
-                method.method_id(),
-                Some((ReferenceTypeId::HasProperty, false)),
-                &*type_tree,
-                BrowseDirection::Forward,
-                "InputArguments",
-            );
+                let signature = self.method_signatures.get_or_read(
+                    address_space,
+                    &*type_tree,
+                    method.method_id(),
+                );
 
-            // If there are no input arguments, it means the method takes no inputs.
-            let Some(input_arguments) = input_arguments else {
-                if method.arguments().is_empty() {
+                // If the input arguments property is invalid, we pass the call along anyway and leave it
+                // up to the implementation to validate. If there is no input arguments property,
+                // the method takes no inputs.
+                let Some(arguments) = &signature.input_arguments else {
                     valid.push(method);
-                } else {
+                    continue;
+                };
+
+                if arguments.len() < method.arguments().len() {
                     method.set_status(StatusCode::BadTooManyArguments);
+                    continue;
                 }
-                continue;
-            };
 
-            // If the input arguments object is invalid, we pass it along anyway and leave it up to
-            // the implementation to validate.
-            let NodeType::Variable(arg_var) = input_arguments else {
-                warn!(
-                    "InputArguments for method with ID {} has incorrect node class",
-                    method.method_id()
-                );
                 valid.push(method);
-                continue;
-            };
+            }
 
-            let Some(Variant::Array(input_arguments_value)) = arg_var
-                .value(
-                    TimestampsToReturn::Neither,
-                    &NumericRange::None,
-                    &DataEncoding::Binary,
-                    0.0,
-                )
-                .value
-            else {
-                warn!(
-                    "InputArguments for method with ID {} has incorrect type",
-                    method.method_id()
-                );
-                valid.push(method);
-                continue;
-            };
+            valid
+        })
+    }
+}
 
-            let num_args = input_arguments_value.values.len();
-            let arguments: Vec<_> = input_arguments_value
-                .values
-                .into_iter()
-                .filter_map(|v| match v {
-                    Variant::ExtensionObject(o) => o.into_inner_as::<Argument>(),
-                    _ => None,
+#[cfg(feature = "generated-address-space")]
+impl<TImpl: InMemoryNodeManagerImpl> InMemoryNodeManager<TImpl> {
+    /// Return `true` if any client is subscribed to events on the `Server` object,
+    /// which receives model change events.
+    fn is_watching_model_changes(context: &RequestContext) -> bool {
+        context
+            .subscriptions
+            .has_event_monitored_items(&ObjectId::Server.into())
+    }
+
+    /// Get the type definitions of the nodes in `node_ids` that have one.
+    fn type_definitions<'a>(
+        &self,
+        context: &RequestContext,
+        node_ids: impl Iterator<Item = &'a NodeId>,
+    ) -> HashMap<NodeId, NodeId> {
+        self.address_space.with_read(|address_space| {
+            let type_tree = trace_read_lock!(context.type_tree);
+            node_ids
+                .filter_map(|id| {
+                    let type_def = address_space
+                        .find_references(
+                            id,
+                            Some((ReferenceTypeId::HasTypeDefinition, false)),
+                            &*type_tree,
+                            BrowseDirection::Forward,
+                        )
+                        .next()?;
+                    Some((id.clone(), type_def.target_node.clone()))
                 })
-                .collect();
-            if arguments.len() != num_args {
-                warn!(
-                    "InputArguments for method with ID {} has invalid arguments",
-                    method.method_id()
-                );
-                valid.push(method);
-                continue;
-            };
+                .collect()
+        })
+    }
 
-            if arguments.len() < method.arguments().len() {
-                method.set_status(StatusCode::BadTooManyArguments);
-                continue;
+    /// Get the model changes for references added or deleted in this node manager,
+    /// given the source and target node IDs of each reference, and the status of
+    /// each end of the reference.
+    fn reference_changes<'a>(
+        &self,
+        references: impl Iterator<Item = ((&'a NodeId, StatusCode), (&'a NodeId, StatusCode))>,
+        verb: Verb,
+    ) -> Vec<(NodeId, Verb)> {
+        let mut changes = Vec::new();
+        for ((source, source_status), (target, target_status)) in references {
+            for (id, status) in [(source, source_status), (target, target_status)] {
+                if status.is_good() && self.owns_node(id) {
+                    changes.push((id.clone(), verb));
+                }
             }
+        }
+        changes
+    }
 
-            valid.push(method);
+    /// Emit a `GeneralModelChangeEvent` from the `Server` object with the given changes.
+    /// `types` contains known type definitions of affected nodes, other type definitions
+    /// are looked up in the address space.
+    fn notify_model_changes(
+        &self,
+        context: &RequestContext,
+        changes: Vec<(NodeId, Verb)>,
+        mut types: HashMap<NodeId, NodeId>,
+    ) {
+        if changes.is_empty() {
+            return;
         }
+        let missing: Vec<_> = changes
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| !types.contains_key(*id))
+            .collect();
+        types.extend(self.type_definitions(context, missing.into_iter()));
 
-        valid
+        let mut event = GeneralModelChangeEvent::new_now();
+        for (id, verb) in &changes {
+            let affected_type = types.get(id).cloned().unwrap_or_default();
+            event.add_change(id, &affected_type, *verb);
+        }
+        context
+            .subscriptions
+            .notify_events([(&event as &dyn Event, &ObjectId::Server.into())].into_iter());
     }
 }
 
@@ -682,23 +988,24 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         context: &RequestContext,
         items: &mut [&mut ExternalReferenceRequest],
     ) {
-        let address_space = trace_read_lock!(self.address_space);
-        let type_tree = trace_read_lock!(context.type_tree);
+        self.address_space.with_read(|address_space| {
+            let type_tree = trace_read_lock!(context.type_tree);
 
-        for item in items {
-            let target_node = address_space.find_node(item.node_id());
+            for item in items {
+                let target_node = address_space.find_node(item.node_id());
 
-            let Some(target_node) = target_node else {
-                continue;
-            };
+                let Some(target_node) = target_node else {
+                    continue;
+                };
 
-            item.set(Self::get_reference(
-                &address_space,
-                &type_tree,
-                target_node,
-                item.result_mask(),
-            ));
-        }
+                item.set(Self::get_reference(
+                    address_space,
+                    &type_tree,
+                    target_node,
+                    item.result_mask(),
+                ));
+            }
+        })
     }
 
     async fn browse(
@@ -706,36 +1013,49 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         context: &RequestContext,
         nodes_to_browse: &mut [BrowseNode],
     ) -> Result<(), StatusCode> {
-        let address_space = trace_read_lock!(self.address_space);
-        let type_tree = trace_read_lock!(context.type_tree);
+        self.address_space.with_read(|address_space| {
+            let type_tree = trace_read_lock!(context.type_tree);
+            let generation = self.generation.load(Ordering::Acquire);
 
-        for node in nodes_to_browse.iter_mut() {
-            if node.node_id().is_null() {
-                continue;
-            }
+            for node in nodes_to_browse.iter_mut() {
+                if node.node_id().is_null() {
+                    continue;
+                }
 
-            node.set_status(StatusCode::Good);
+                node.set_status(StatusCode::Good);
 
-            if let Some(mut point) = node.take_continuation_point::<BrowseContinuationPoint>() {
-                loop {
-                    if node.remaining() == 0 {
-                        break;
+                if let Some(mut point) = node.take_continuation_point::<BrowseContinuationPoint>() {
+                    if point.generation != generation {
+                        // The address space was replaced since the continuation point was created.
+                        node.set_status(StatusCode::BadContinuationPointInvalid);
+                        continue;
                     }
-                    let Some(ref_desc) = point.nodes.pop_back() else {
-                        break;
-                    };
-                    // Node is already filtered.
-                    node.add_unchecked(ref_desc);
-                }
-                if !point.nodes.is_empty() {
-                    node.set_next_continuation_point(point);
+                    loop {
+                        if node.remaining() == 0 {
+                            break;
+                        }
+                        let Some(ref_desc) = point.nodes.pop_front() else {
+                            break;
+                        };
+                        // Node is already filtered.
+                        node.add_unchecked(ref_desc);
+                    }
+                    if !point.nodes.is_empty() {
+                        node.set_next_continuation_point(point);
+                    }
+                } else {
+                    Self::browse_node(
+                        address_space,
+                        &type_tree,
+                        node,
+                        &self.namespaces,
+                        generation,
+                    );
                 }
-            } else {
-                Self::browse_node(&address_space, &type_tree, node, &self.namespaces);
             }
-        }
 
-        Ok(())
+            Ok(())
+        })
     }
 
     async fn read(
@@ -746,8 +1066,7 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         nodes_to_read: &mut [&mut ReadNode],
     ) -> Result<(), StatusCode> {
         let mut read_values = Vec::new();
-        {
-            let address_space = trace_read_lock!(self.address_space);
+        self.address_space.with_read(|address_space| {
             for node in nodes_to_read {
                 if node.node().attribute_id == AttributeId::Value {
                     read_values.push(node);
@@ -761,7 +1080,7 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
                     timestamps_to_return,
                 ));
             }
-        }
+        });
 
         if !read_values.is_empty() {
             let ids: Vec<_> = read_values.iter().map(|r| r.node()).collect();
@@ -788,20 +1107,21 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         context: &RequestContext,
         nodes: &mut [&mut BrowsePathItem],
     ) -> Result<(), StatusCode> {
-        let address_space = trace_read_lock!(self.address_space);
-        let type_tree = trace_read_lock!(context.type_tree);
-
-        for node in nodes {
-            Self::translate_browse_paths(
-                &address_space,
-                &type_tree,
-                context,
-                &self.namespaces,
-                node,
-            );
-        }
+        self.address_space.with_read(|address_space| {
+            let type_tree = trace_read_lock!(context.type_tree);
 
-        Ok(())
+            for node in nodes {
+                Self::translate_browse_paths(
+                    address_space,
+                    &type_tree,
+                    context,
+                    &self.namespaces,
+                    node,
+                );
+            }
+
+            Ok(())
+        })
     }
 
     async fn register_nodes(
@@ -832,10 +1152,26 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         let mut value_items = Vec::new();
         let mut event_items = Vec::new();
 
-        {
-            let address_space = trace_read_lock!(self.address_space);
+        self.address_space.with_read(|address_space| {
+            let type_tree = trace_read_lock!(context.type_tree);
             for node in items {
                 if node.item_to_monitor().attribute_id == AttributeId::Value {
+                    if let Some(NodeType::Variable(v)) =
+                        address_space.find(&node.item_to_monitor().node_id)
+                    {
+                        // Deadbands cannot be applied to non-numeric values.
+                        let numeric =
+                            type_tree.is_subtype_of(&v.data_type(), &DataTypeId::Number.into());
+                        if node.filter().has_deadband() && !numeric {
+                            node.set_status(StatusCode::BadFilterNotAllowed);
+                            continue;
+                        }
+                        node.set_deadband_allowed(numeric);
+                        // Variables may not be sampled faster than their minimum sampling interval.
+                        if let Some(minimum) = v.minimum_sampling_interval() {
+                            node.clamp_sampling_interval(minimum);
+                        }
+                    }
                     value_items.push(node);
                     continue;
                 }
@@ -883,7 +1219,7 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
 
                 node.set_status(StatusCode::Good);
             }
-        }
+        });
 
         if !value_items.is_empty() {
             self.inner
@@ -1021,9 +1357,30 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         context: &RequestContext,
         nodes_to_write: &mut [&mut WriteNode],
     ) -> Result<(), StatusCode> {
-        self.inner
+        let res = self
+            .inner
             .write(context, &self.address_space, nodes_to_write)
-            .await
+            .await;
+        self.method_signatures.clear();
+        res
+    }
+
+    fn supports_write_transactions(&self) -> bool {
+        self.inner.supports_write_transactions()
+    }
+
+    async fn write_transaction(
+        &self,
+        context: &RequestContext,
+        node_id: &NodeId,
+        writes: &[(AttributeId, DataValue)],
+    ) -> Result<(), StatusCode> {
+        let res = self
+            .inner
+            .write_transaction(context, &self.address_space, node_id, writes)
+            .await;
+        self.method_signatures.clear();
+        res
     }
 
     async fn history_update(
@@ -1055,9 +1412,24 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         context: &RequestContext,
         nodes_to_add: &mut [&mut AddNodeItem],
     ) -> Result<(), StatusCode> {
-        self.inner
+        let res = self
+            .inner
             .add_nodes(context, &self.address_space, nodes_to_add)
-            .await
+            .await;
+        self.method_signatures.clear();
+        #[cfg(feature = "generated-address-space")]
+        if Self::is_watching_model_changes(context) {
+            let mut changes = Vec::new();
+            for node in nodes_to_add.iter().filter(|n| n.status().is_good()) {
+                changes.push((node.result_node_id().clone(), Verb::NodeAdded));
+                let parent_id = &node.parent_node_id().node_id;
+                if self.owns_node(parent_id) {
+                    changes.push((parent_id.clone(), Verb::ReferenceAdded));
+                }
+            }
+            self.notify_model_changes(context, changes, HashMap::new());
+        }
+        res
     }
 
     async fn add_references(
@@ -1065,9 +1437,25 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         context: &RequestContext,
         references_to_add: &mut [&mut AddReferenceItem],
     ) -> Result<(), StatusCode> {
-        self.inner
+        let res = self
+            .inner
             .add_references(context, &self.address_space, references_to_add)
-            .await
+            .await;
+        self.method_signatures.clear();
+        #[cfg(feature = "generated-address-space")]
+        if Self::is_watching_model_changes(context) {
+            let changes = self.reference_changes(
+                references_to_add.iter().map(|r| {
+                    (
+                        (r.source_node_id(), r.source_status()),
+                        (&r.target_node_id().node_id, r.target_status()),
+                    )
+                }),
+                Verb::ReferenceAdded,
+            );
+            self.notify_model_changes(context, changes, HashMap::new());
+        }
+        res
     }
 
     async fn delete_nodes(
@@ -1075,9 +1463,25 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         context: &RequestContext,
         nodes_to_delete: &mut [&mut DeleteNodeItem],
     ) -> Result<(), StatusCode> {
-        self.inner
+        // Type definitions must be looked up before the nodes are deleted.
+        #[cfg(feature = "generated-address-space")]
+        let types = Self::is_watching_model_changes(context)
+            .then(|| self.type_definitions(context, nodes_to_delete.iter().map(|n| n.node_id())));
+        let res = self
+            .inner
             .delete_nodes(context, &self.address_space, nodes_to_delete)
-            .await
+            .await;
+        self.method_signatures.clear();
+        #[cfg(feature = "generated-address-space")]
+        if let Some(types) = types {
+            let changes = nodes_to_delete
+                .iter()
+                .filter(|n| n.status().is_good())
+                .map(|n| (n.node_id().clone(), Verb::NodeDeleted))
+                .collect();
+            self.notify_model_changes(context, changes, types);
+        }
+        res
     }
 
     async fn delete_node_references(
@@ -1095,8 +1499,24 @@ impl<TImpl: InMemoryNodeManagerImpl> NodeManager for InMemoryNodeManager<TImpl>
         context: &RequestContext,
         references_to_delete: &mut [&mut DeleteReferenceItem],
     ) -> Result<(), StatusCode> {
-        self.inner
+        let res = self
+            .inner
             .delete_references(context, &self.address_space, references_to_delete)
-            .await
+            .await;
+        self.method_signatures.clear();
+        #[cfg(feature = "generated-address-space")]
+        if Self::is_watching_model_changes(context) {
+            let changes = self.reference_changes(
+                references_to_delete.iter().map(|r| {
+                    (
+                        (r.source_node_id(), r.source_status()),
+                        (&r.target_node_id().node_id, r.target_status()),
+                    )
+                }),
+                Verb::ReferenceDeleted,
+            );
+            self.notify_model_changes(context, changes, HashMap::new());
+        }
+        res
     }
 }
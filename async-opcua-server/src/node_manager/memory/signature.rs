@@ -0,0 +1,225 @@
+use std::sync::Arc;
+
+use hashbrown::HashMap;
+use opcua_core::{sync::RwLock, trace_read_lock, trace_write_lock};
+use opcua_nodes::TypeTree;
+use opcua_types::{
+    argument::Argument, BrowseDirection, DataEncoding, NodeId, NumericRange, ReferenceTypeId,
+    TimestampsToReturn, Variant,
+};
+use tracing::warn;
+
+use crate::address_space::{AddressSpace, NodeType};
+
+/// The declared arguments of a method, read from its `InputArguments`
+/// and `OutputArguments` properties.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MethodSignature {
+    /// Input arguments of the method. This is empty if the method has no
+    /// `InputArguments` property, and `None` if the property exists but
+    /// does not contain a list of `Argument` structures.
+    pub input_arguments: Option<Vec<Argument>>,
+    /// Output arguments of the method. This is empty if the method has no
+    /// `OutputArguments` property, and `None` if the property exists but
+    /// does not contain a list of `Argument` structures.
+    pub output_arguments: Option<Vec<Argument>>,
+}
+
+impl MethodSignature {
+    /// Read the signature of the method with ID `method_id` from the address space.
+    pub fn read(
+        address_space: &AddressSpace,
+        type_tree: &dyn TypeTree,
+        method_id: &NodeId,
+    ) -> Self {
+        Self {
+            input_arguments: read_method_arguments(
+                address_space,
+                type_tree,
+                method_id,
+                "InputArguments",
+            ),
+            output_arguments: read_method_arguments(
+                address_space,
+                type_tree,
+                method_id,
+                "OutputArguments",
+            ),
+        }
+    }
+}
+
+/// Read the list of arguments from the property with browse name `property`,
+/// typically `InputArguments` or `OutputArguments`, on the method with ID `method_id`.
+///
+/// Returns an empty list if the method has no such property, and `None` if the
+/// property is not a variable containing an array of `Argument` structures.
+pub fn read_method_arguments(
+    address_space: &AddressSpace,
+    type_tree: &dyn TypeTree,
+    method_id: &NodeId,
+    property: &str,
+) -> Option<Vec<Argument>> {
+    let Some(node) = address_space.find_node_by_browse_name(
+        method_id,
+        Some((ReferenceTypeId::HasProperty, false)),
+        type_tree,
+        BrowseDirection::Forward,
+        property,
+    ) else {
+        return Some(Vec::new());
+    };
+
+    let NodeType::Variable(var) = node else {
+        warn!("{property} for method with ID {method_id} has incorrect node class");
+        return None;
+    };
+
+    let Some(Variant::Array(value)) = var
+        .value(
+            TimestampsToReturn::Neither,
+            &NumericRange::None,
+            &DataEncoding::Binary,
+            0.0,
+        )
+        .value
+    else {
+        warn!("{property} for method with ID {method_id} has incorrect type");
+        return None;
+    };
+
+    let num_args = value.values.len();
+    let arguments: Vec<_> = value
+        .values
+        .into_iter()
+        .filter_map(|v| match v {
+            Variant::ExtensionObject(o) => o.into_inner_as::<Argument>().map(|a| *a),
+            _ => None,
+        })
+        .collect();
+    if arguments.len() != num_args {
+        warn!("{property} for method with ID {method_id} has invalid arguments");
+        return None;
+    }
+
+    Some(arguments)
+}
+
+/// Cache of parsed method signatures, by method node ID.
+#[derive(Default)]
+pub(super) struct MethodSignatureCache {
+    signatures: RwLock<HashMap<NodeId, Arc<MethodSignature>>>,
+}
+
+impl MethodSignatureCache {
+    pub(super) fn get_or_read(
+        &self,
+        address_space: &AddressSpace,
+        type_tree: &dyn TypeTree,
+        method_id: &NodeId,
+    ) -> Arc<MethodSignature> {
+        if let Some(signature) = trace_read_lock!(self.signatures).get(method_id) {
+            return signature.clone();
+        }
+        let signature = Arc::new(MethodSignature::read(address_space, type_tree, method_id));
+        trace_write_lock!(self.signatures).insert(method_id.clone(), signature.clone());
+        signature
+    }
+
+    pub(super) fn invalidate(&self, method_id: &NodeId) {
+        trace_write_lock!(self.signatures).remove(method_id);
+    }
+
+    pub(super) fn clear(&self) {
+        trace_write_lock!(self.signatures).clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opcua_nodes::{DefaultTypeTree, MethodBuilder, VariableBuilder};
+    use opcua_types::{DataTypeId, NodeId, ObjectId, ReferenceTypeId};
+
+    use super::{MethodSignature, MethodSignatureCache};
+    use crate::address_space::AddressSpace;
+
+    #[test]
+    fn read_method_signature() {
+        let mut address_space = AddressSpace::new();
+        address_space.add_namespace("urn:test", 1);
+        let type_tree = DefaultTypeTree::new();
+        let object_id: NodeId = ObjectId::ObjectsFolder.into();
+
+        let method_id = NodeId::new(1, "Method");
+        MethodBuilder::new(&method_id, "Method", "Method")
+            .component_of(object_id.clone())
+            .input_args(
+                &mut address_space,
+                &NodeId::new(1, "MethodIn"),
+                &[
+                    ("First", DataTypeId::Int32).into(),
+                    ("Second", DataTypeId::String).into(),
+                ],
+            )
+            .output_args(
+                &mut address_space,
+                &NodeId::new(1, "MethodOut"),
+                &[("Result", DataTypeId::Boolean).into()],
+            )
+            .insert(&mut address_space);
+
+        let signature = MethodSignature::read(&address_space, &type_tree, &method_id);
+        let inputs = signature.input_arguments.unwrap();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0].name.as_ref(), "First");
+        assert_eq!(inputs[0].data_type, DataTypeId::Int32);
+        assert_eq!(inputs[1].data_type, DataTypeId::String);
+        assert_eq!(inputs[1].value_rank, -1);
+        let outputs = signature.output_arguments.unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].data_type, DataTypeId::Boolean);
+
+        // A method without argument properties takes no arguments.
+        let no_args_id = NodeId::new(1, "NoArgs");
+        MethodBuilder::new(&no_args_id, "NoArgs", "NoArgs")
+            .component_of(object_id.clone())
+            .insert(&mut address_space);
+        let signature = MethodSignature::read(&address_space, &type_tree, &no_args_id);
+        assert_eq!(signature.input_arguments, Some(Vec::new()));
+        assert_eq!(signature.output_arguments, Some(Vec::new()));
+
+        // An argument property with the wrong value is invalid.
+        let invalid_id = NodeId::new(1, "Invalid");
+        MethodBuilder::new(&invalid_id, "Invalid", "Invalid")
+            .component_of(object_id)
+            .insert(&mut address_space);
+        VariableBuilder::new(
+            &NodeId::new(1, "InvalidIn"),
+            "InputArguments",
+            "InputArguments",
+        )
+        .value(5i32)
+        .data_type(DataTypeId::Argument)
+        .property_of(invalid_id.clone())
+        .insert(&mut address_space);
+        let signature = MethodSignature::read(&address_space, &type_tree, &invalid_id);
+        assert_eq!(signature.input_arguments, None);
+        assert_eq!(signature.output_arguments, Some(Vec::new()));
+
+        // Signatures are cached until invalidated.
+        let cache = MethodSignatureCache::default();
+        let cached = cache.get_or_read(&address_space, &type_tree, &method_id);
+        address_space.delete_reference(
+            &method_id,
+            &NodeId::new(1, "MethodIn"),
+            ReferenceTypeId::HasProperty,
+        );
+        assert_eq!(
+            cache.get_or_read(&address_space, &type_tree, &method_id),
+            cached
+        );
+        cache.invalidate(&method_id);
+        let signature = cache.get_or_read(&address_space, &type_tree, &method_id);
+        assert_eq!(signature.input_arguments, Some(Vec::new()));
+    }
+}
@@ -2,19 +2,23 @@ use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use opcua_core::{trace_read_lock, trace_write_lock};
-use opcua_nodes::{HasNodeId, NodeSetImport};
+use opcua_nodes::{HasNodeId, NodeSetImport, NodeType, SharedDataValue};
 
 use crate::{
-    address_space::{read_node_value, write_node_value, AddressSpace},
+    address_space::{
+        eu_range, read_node_value, semantics_changed_variable, write_node_value, AddressSpace,
+        AddressSpaceLock,
+    },
     node_manager::{
-        DefaultTypeTree, MethodCall, MonitoredItemRef, MonitoredItemUpdateRef, NodeManagerBuilder,
-        NodeManagersRef, ParsedReadValueId, RequestContext, ServerContext, SyncSampler, WriteNode,
+        DebouncedWriter, DefaultTypeTree, MethodCall, MonitoredItemRef, MonitoredItemUpdateRef,
+        NodeManagerBuilder, NodeManagersRef, ParsedReadValueId, RequestContext, ServerContext,
+        SyncSampler, WriteNode,
     },
     CreateMonitoredItem,
 };
 use opcua_core::sync::RwLock;
 use opcua_types::{
-    AttributeId, DataValue, MonitoringMode, NodeClass, NodeId, NumericRange, StatusCode,
+    AttributeId, DataValue, DateTime, MonitoringMode, NodeClass, NodeId, NumericRange, StatusCode,
     TimestampsToReturn, Variant,
 };
 
@@ -34,6 +38,7 @@ type ReadCB = Arc<
         + Sync
         + 'static,
 >;
+type ComputedCB = Arc<dyn Fn(&RequestContext) -> DataValue + Send + Sync + 'static>;
 type MethodCB = Arc<dyn Fn(&[Variant]) -> Result<Vec<Variant>, StatusCode> + Send + Sync + 'static>;
 
 /// Builder for the [SimpleNodeManager].
@@ -118,7 +123,9 @@ pub fn simple_node_manager_imports(
 ///  - and you don't need to be able to write attributes other than `Value`.
 pub struct SimpleNodeManagerImpl {
     write_cbs: RwLock<HashMap<NodeId, WriteCB>>,
+    debounced_writers: RwLock<HashMap<NodeId, Arc<DebouncedWriter>>>,
     read_cbs: RwLock<HashMap<NodeId, ReadCB>>,
+    computed_cbs: RwLock<HashMap<NodeId, ComputedCB>>,
     method_cbs: RwLock<HashMap<NodeId, MethodCB>>,
     namespaces: Vec<NamespaceMetadata>,
     #[allow(unused)]
@@ -130,7 +137,7 @@ pub struct SimpleNodeManagerImpl {
 #[async_trait]
 impl InMemoryNodeManagerImpl for SimpleNodeManagerImpl {
     async fn init(&self, _address_space: &mut AddressSpace, context: ServerContext) {
-        self.samplers.run(
+        self.samplers.run_on(
             Duration::from_millis(
                 context
                     .info
@@ -140,6 +147,7 @@ impl InMemoryNodeManagerImpl for SimpleNodeManagerImpl {
                     .min_sampling_interval_ms as u64,
             ),
             context.subscriptions.clone(),
+            &context.info.sampler_runtime(),
         );
     }
 
@@ -159,21 +167,32 @@ impl InMemoryNodeManagerImpl for SimpleNodeManagerImpl {
         max_age: f64,
         timestamps_to_return: TimestampsToReturn,
     ) -> Vec<DataValue> {
-        let address_space = address_space.read();
-        let cbs = trace_read_lock!(self.read_cbs);
-
-        nodes
-            .iter()
-            .map(|n| {
-                self.read_node_value(
-                    &cbs,
-                    context,
-                    &address_space,
-                    n,
-                    max_age,
-                    timestamps_to_return,
-                )
+        let values: Vec<_> = {
+            address_space.with_read(|address_space| {
+                let cbs = trace_read_lock!(self.read_cbs);
+                let computed = trace_read_lock!(self.computed_cbs);
+
+                nodes
+                    .iter()
+                    .map(|n| {
+                        self.read_node_value(
+                            &cbs,
+                            &computed,
+                            context,
+                            address_space,
+                            n,
+                            max_age,
+                            timestamps_to_return,
+                        )
+                    })
+                    .collect()
             })
+        };
+
+        // Shared values are only cloned once the address space lock is released.
+        values
+            .into_iter()
+            .map(SharedDataValue::into_data_value)
             .collect()
     }
 
@@ -195,6 +214,7 @@ impl InMemoryNodeManagerImpl for SimpleNodeManagerImpl {
             .await;
 
         let cbs = trace_read_lock!(self.read_cbs);
+        let computed = trace_read_lock!(self.computed_cbs);
 
         for (value, node) in values.into_iter().zip(items.iter_mut()) {
             if value.status() != StatusCode::BadAttributeIdInvalid {
@@ -203,7 +223,25 @@ impl InMemoryNodeManagerImpl for SimpleNodeManagerImpl {
             node.set_status(StatusCode::Good);
             let rf = &node.item_to_monitor().node_id;
 
-            if let Some(cb) = cbs.get(rf).cloned() {
+            if let Some(cb) = computed
+                .get(rf)
+                .filter(|_| node.item_to_monitor().attribute_id == AttributeId::Value)
+                .cloned()
+            {
+                // Computed values have no stored value to notify on, so they must be sampled.
+                let tss = node.timestamps_to_return();
+                let index_range = node.item_to_monitor().index_range.clone();
+                let context = context.clone();
+
+                self.samplers.add_sampler(
+                    node.item_to_monitor().node_id.clone(),
+                    AttributeId::Value,
+                    move || Some(computed_value(&cb, &context, &index_range, tss)),
+                    node.monitoring_mode(),
+                    node.handle(),
+                    Duration::from_millis(node.sampling_interval() as u64),
+                )
+            } else if let Some(cb) = cbs.get(rf).cloned() {
                 let tss = node.timestamps_to_return();
                 let index_range = node.item_to_monitor().index_range.clone();
 
@@ -267,12 +305,44 @@ impl InMemoryNodeManagerImpl for SimpleNodeManagerImpl {
         address_space: &RwLock<AddressSpace>,
         nodes_to_write: &mut [&mut WriteNode],
     ) -> Result<(), StatusCode> {
-        let mut address_space = trace_write_lock!(address_space);
-        let type_tree = trace_read_lock!(context.type_tree);
-        let cbs = trace_read_lock!(self.write_cbs);
-
-        for write in nodes_to_write {
-            self.write_node_value(&cbs, context, &mut address_space, &type_tree, write);
+        let mut debounced = Vec::new();
+        address_space.with_write(|address_space| {
+            let type_tree = trace_read_lock!(context.type_tree);
+            let cbs = trace_read_lock!(self.write_cbs);
+            let computed = trace_read_lock!(self.computed_cbs);
+            let writers = trace_read_lock!(self.debounced_writers);
+
+            for write in nodes_to_write.iter_mut() {
+                if write.value().attribute_id == AttributeId::Value {
+                    if computed.contains_key(&write.value().node_id) {
+                        write.set_status(StatusCode::BadNotWritable);
+                        continue;
+                    }
+                    if let Some(writer) = writers.get(&write.value().node_id) {
+                        match address_space.validate_node_write(context, write.value(), &*type_tree)
+                        {
+                            Ok(node) if node.node_class() == NodeClass::Variable => {
+                                debounced.push((writer.clone(), write));
+                            }
+                            Ok(_) => write.set_status(StatusCode::BadNotWritable),
+                            Err(e) => write.set_status(e),
+                        }
+                        continue;
+                    }
+                }
+                self.write_node_value(&cbs, context, address_space, &type_tree, write);
+            }
+        });
+
+        // Debounced writes may wait for the value to be flushed, so they are
+        // queued after the address space lock is released.
+        let results = futures::future::join_all(debounced.iter().map(|(writer, write)| {
+            let value = write.value();
+            writer.write(&value.node_id, value.value.clone(), &value.index_range)
+        }))
+        .await;
+        for ((_, write), status) in debounced.into_iter().zip(results) {
+            write.set_status(status);
         }
 
         Ok(())
@@ -305,7 +375,9 @@ impl SimpleNodeManagerImpl {
     fn new(namespaces: Vec<NamespaceMetadata>, name: &str, node_managers: NodeManagersRef) -> Self {
         Self {
             write_cbs: Default::default(),
+            debounced_writers: Default::default(),
             read_cbs: Default::default(),
+            computed_cbs: Default::default(),
             method_cbs: Default::default(),
             namespaces,
             name: name.to_owned(),
@@ -314,16 +386,18 @@ impl SimpleNodeManagerImpl {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn read_node_value(
         &self,
         cbs: &HashMap<NodeId, ReadCB>,
+        computed: &HashMap<NodeId, ComputedCB>,
         context: &RequestContext,
         address_space: &AddressSpace,
         node_to_read: &ParsedReadValueId,
         max_age: f64,
         timestamps_to_return: TimestampsToReturn,
-    ) -> DataValue {
-        let mut result_value = DataValue::null();
+    ) -> SharedDataValue {
+        let mut result_value = SharedDataValue::default();
         // Check that the read is permitted.
         let node = match address_space.validate_node_read(context, node_to_read) {
             Ok(n) => n,
@@ -333,18 +407,38 @@ impl SimpleNodeManagerImpl {
             }
         };
 
+        if node_to_read.attribute_id == AttributeId::Value {
+            if let Some(cb) = computed.get(&node_to_read.node_id) {
+                return computed_value(
+                    cb,
+                    context,
+                    &node_to_read.index_range,
+                    timestamps_to_return,
+                )
+                .into();
+            }
+        }
+
         // If there is a callback registered, call that, otherwise read it from the node hierarchy.
         if let Some(cb) = cbs.get(&node_to_read.node_id) {
             match cb(&node_to_read.index_range, timestamps_to_return, max_age) {
-                Err(e) => DataValue {
+                Err(e) => SharedDataValue {
                     status: Some(e),
                     ..Default::default()
                 },
-                Ok(v) => v,
+                Ok(v) => v.into(),
             }
         } else {
-            // If it can't be found, read it from the node hierarchy.
-            read_node_value(node, context, node_to_read, max_age, timestamps_to_return)
+            match node {
+                NodeType::Variable(v)
+                    if v.is_value_shared() && node_to_read.attribute_id == AttributeId::Value =>
+                {
+                    v.shared_value(timestamps_to_return, &node_to_read.index_range)
+                }
+                // If it can't be found, read it from the node hierarchy.
+                _ => read_node_value(node, context, node_to_read, max_age, timestamps_to_return)
+                    .into(),
+            }
         }
     }
 
@@ -395,6 +489,14 @@ impl SimpleNodeManagerImpl {
                     [(val, node.node_id(), write.value().attribute_id)].into_iter(),
                 );
             }
+            if let Some(variable) =
+                semantics_changed_variable(address_space, &write.value().node_id, type_tree)
+            {
+                context.subscriptions.notify_semantics_changed(
+                    &variable,
+                    eu_range(address_space, &variable, type_tree),
+                );
+            }
         }
     }
 
@@ -408,6 +510,16 @@ impl SimpleNodeManagerImpl {
         cbs.insert(id, Arc::new(cb));
     }
 
+    /// Debounce `Write` on the node given by `id` through `writer`, which passes
+    /// the values on to its flush callback. The written value is not stored in the
+    /// address space, and this takes precedence over any write callback for the node.
+    ///
+    /// The same writer may be used for several nodes.
+    pub fn add_debounced_writer(&self, id: NodeId, writer: Arc<DebouncedWriter>) {
+        let mut writers = trace_write_lock!(self.debounced_writers);
+        writers.insert(id, writer);
+    }
+
     /// Add a callback for `Read` on the node given by `id`.
     pub fn add_read_callback(
         &self,
@@ -421,6 +533,27 @@ impl SimpleNodeManagerImpl {
         cbs.insert(id, Arc::new(cb));
     }
 
+    /// Make the variable given by `id` a computed variable, whose value is produced
+    /// by `cb` each time it is read, instead of being stored in the address space.
+    ///
+    /// The index range and timestamps to return of the read are applied to the
+    /// computed value. Missing timestamps are set to the current time. Writes to
+    /// the value of a computed variable are rejected with `BadNotWritable`.
+    pub fn add_computed_variable(
+        &self,
+        id: NodeId,
+        cb: impl Fn(&RequestContext) -> DataValue + Send + Sync + 'static,
+    ) {
+        let mut cbs = trace_write_lock!(self.computed_cbs);
+        cbs.insert(id, Arc::new(cb));
+    }
+
+    /// Remove the computed value callback for the variable given by `id`, if it exists.
+    pub fn remove_computed_variable(&self, id: &NodeId) -> bool {
+        let mut cbs = trace_write_lock!(self.computed_cbs);
+        cbs.remove(id).is_some()
+    }
+
     /// Add a callback for `Call` on the method given by `id`.
     pub fn add_method_callback(
         &self,
@@ -431,3 +564,29 @@ impl SimpleNodeManagerImpl {
         cbs.insert(id, Arc::new(cb));
     }
 }
+
+/// Compute the value of a computed variable, applying the index range and
+/// timestamps to return.
+fn computed_value(
+    cb: &ComputedCB,
+    context: &RequestContext,
+    index_range: &NumericRange,
+    timestamps_to_return: TimestampsToReturn,
+) -> DataValue {
+    let mut value = cb(context);
+    if let Some(v) = value.value.take() {
+        match v.range_of_owned(index_range) {
+            Ok(v) => value.value = Some(v),
+            Err(e) => {
+                return DataValue {
+                    status: Some(e),
+                    ..Default::default()
+                }
+            }
+        }
+    }
+    let now = DateTime::now();
+    let source_timestamp = value.source_timestamp.unwrap_or(now);
+    value.set_timestamps(timestamps_to_return, source_timestamp, now);
+    value
+}
@@ -13,8 +13,9 @@ use async_trait::async_trait;
 use opcua_core::sync::RwLock;
 use opcua_nodes::DefaultTypeTree;
 use opcua_types::{
-    ExpandedNodeId, MonitoringMode, NodeId, ReadAnnotationDataDetails, ReadAtTimeDetails,
-    ReadEventDetails, ReadProcessedDetails, ReadRawModifiedDetails, StatusCode, TimestampsToReturn,
+    AttributeId, DataValue, ExpandedNodeId, MonitoringMode, NodeId, ReadAnnotationDataDetails,
+    ReadAtTimeDetails, ReadEventDetails, ReadProcessedDetails, ReadRawModifiedDetails, StatusCode,
+    TimestampsToReturn,
 };
 use tokio::sync::OnceCell;
 
@@ -27,6 +28,8 @@ mod method;
 mod monitored_items;
 mod node_management;
 mod query;
+#[cfg(feature = "remote-node-manager")]
+pub mod remote;
 mod utils;
 mod view;
 
@@ -40,7 +43,7 @@ use super::{
 pub use {
     attributes::{ParsedReadValueId, ParsedWriteValue, ReadNode, WriteNode},
     build::NodeManagerBuilder,
-    context::{RequestContext, TypeTreeForUser, TypeTreeReadContext},
+    context::{NodeVisibilityFilter, RequestContext, TypeTreeForUser, TypeTreeReadContext},
     history::{HistoryNode, HistoryResult, HistoryUpdateDetails, HistoryUpdateNode},
     method::MethodCall,
     monitored_items::{MonitoredItemRef, MonitoredItemUpdateRef},
@@ -137,6 +140,20 @@ impl NodeManagers {
         None
     }
 
+    /// Notify clients subscribed to the `NamespaceArray` variable of the current
+    /// namespace array.
+    ///
+    /// The `NamespaceArray` is owned by the core node manager, so this does nothing
+    /// without the `generated-address-space` feature.
+    pub(crate) fn notify_namespaces_changed(&self, subscriptions: &SubscriptionCache) {
+        #[cfg(feature = "generated-address-space")]
+        if let Some(core) = self.get_of_type::<memory::CoreNodeManager>() {
+            core.inner().notify_namespaces_changed(subscriptions);
+        }
+        #[cfg(not(feature = "generated-address-space"))]
+        let _ = subscriptions;
+    }
+
     /// Create a weak reference to the node managers.
     /// A node manager should avoid holding a copy of the `NodeManagers` object since that
     /// results in a circular reference which will leak memory once dropped.
@@ -250,6 +267,21 @@ pub struct ServerContext {
     pub status: Arc<ServerStatusWrapper>,
 }
 
+impl ServerContext {
+    /// Notify clients subscribed to the `NamespaceArray` variable that the set of
+    /// namespaces on the server has changed.
+    ///
+    /// The namespace array is computed from the namespaces of each node manager when
+    /// it is read. [`add_namespaces`](crate::address_space::add_namespaces) calls this
+    /// automatically, node managers that change their namespaces some other way after
+    /// the server has started should call this once the namespaces are visible.
+    pub fn notify_namespaces_changed(&self) {
+        if let Some(node_managers) = self.node_managers.upgrade() {
+            node_managers.notify_namespaces_changed(&self.subscriptions);
+        }
+    }
+}
+
 /// This trait is a workaround for the lack of
 /// dyn upcasting coercion.
 pub trait IntoAnyArc {
@@ -406,6 +438,38 @@ pub trait NodeManager: IntoAnyArc + Any {
         Err(StatusCode::BadServiceUnsupported)
     }
 
+    /// Return whether this node manager supports write transactions, see `write_transaction`.
+    /// If this returns `true`, the server will call `write_transaction` instead of `write`
+    /// when a single `Write` request contains multiple writes to the same node.
+    fn supports_write_transactions(&self) -> bool {
+        false
+    }
+
+    /// Write a group of attributes on a single node as a transaction.
+    ///
+    /// This is only called if `supports_write_transactions` returns `true`, for
+    /// nodes with more than one write in a single `Write` request. Writes with an
+    /// index range are never part of a transaction, they are passed to `write` as usual.
+    ///
+    /// Implementations must treat the writes atomically: either every write is applied
+    /// and this returns `Ok`, or none of them are and this returns the error that
+    /// caused the transaction to fail. The returned status is reported for every
+    /// write in the transaction.
+    ///
+    /// The default implementation falls back to independent writes through `write`, and
+    /// returns the first error. This does _not_ roll back writes that succeeded.
+    async fn write_transaction(
+        &self,
+        context: &RequestContext,
+        node_id: &NodeId,
+        writes: &[(AttributeId, DataValue)],
+    ) -> Result<(), StatusCode> {
+        let mut nodes = WriteNode::from_transaction(node_id, writes);
+        let mut refs: Vec<_> = nodes.iter_mut().collect();
+        self.write(context, &mut refs).await?;
+        WriteNode::transaction_result(&nodes)
+    }
+
     /// Perform the HistoryUpdate service. This should write result
     /// status codes to the `nodes` list as appropriate.
     async fn history_update(
@@ -96,6 +96,12 @@ impl AddNodeItem {
         self.status = status;
     }
 
+    /// The node ID of the created node, null if no node was created.
+    #[cfg(feature = "generated-address-space")]
+    pub(crate) fn result_node_id(&self) -> &NodeId {
+        &self.result_node_id
+    }
+
     /// The requested parent node ID.
     pub fn parent_node_id(&self) -> &ExpandedNodeId {
         &self.parent_node_id
@@ -240,8 +240,8 @@ impl QueryRequest {
         // If we're out of continuation points, the correct response is to not store it, and
         // set the status code to BadNoContinuationPoints.
         if let Some(c) = continuation_point {
-            if session.add_query_continuation_point(&cp_id, c).is_err() {
-                status = StatusCode::BadNoContinuationPoints;
+            if let Err(e) = session.add_query_continuation_point(&cp_id, c) {
+                status = e;
                 cp_id = ByteString::null();
             }
         }
@@ -0,0 +1,1039 @@
+//! A node manager that exposes nodes from an upstream OPC UA server,
+//! by forwarding requests through a client [`Session`].
+//!
+//! This is useful for building aggregating gateways. Nodes in the proxied
+//! namespaces are owned by the [`RemoteNodeManager`], and namespace indices are
+//! translated between the namespace table of the upstream server and the namespace
+//! table of this server.
+//!
+//! The upstream server only sees the user of the client session, so access
+//! checks for the local user are made by the [`AuthManager`](crate::authenticator::AuthManager)
+//! before forwarding reads, writes, calls and monitored items.
+
+mod namespaces;
+
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use arc_swap::ArcSwapOption;
+use async_trait::async_trait;
+use hashbrown::HashMap;
+use opcua_client::{DataChangeCallback, Session};
+use opcua_core::{sync::RwLock, trace_read_lock, trace_write_lock};
+use opcua_nodes::DefaultTypeTree;
+use opcua_types::{
+    AttributeId, BrowseDescription, BrowseDescriptionResultMask, BrowseDirection, BrowseResult,
+    ByteString, CallMethodRequest, DataValue, ExtensionObject, LocalizedText,
+    MonitoredItemCreateRequest, MonitoringParameters, NamespaceMap, NodeClass, NodeId,
+    NumericRange, ReadValueId, ReferenceDescription, ReferenceTypeId, StatusCode,
+    TimestampsToReturn, VariableId, Variant, WriteValue,
+};
+use tracing::warn;
+
+use crate::{address_space::AccessLevel, diagnostics::NamespaceMetadata, SubscriptionCache};
+
+use super::{
+    impl_translate_browse_paths_using_browse, BrowseNode, BrowsePathItem, DynNodeManager,
+    ExternalReferenceRequest, MethodCall, MonitoredItemRef, NodeManager, NodeManagerBuilder,
+    NodeMetadata, ReadNode, RequestContext, ServerContext, WriteNode,
+};
+use crate::{subscriptions::CreateMonitoredItem, MonitoredItemHandle};
+
+use namespaces::RemoteNamespaces;
+
+/// Builder for the [`RemoteNodeManager`].
+pub struct RemoteNodeManagerBuilder {
+    session: Arc<Session>,
+    namespaces: Vec<String>,
+    name: String,
+    publishing_interval: Duration,
+}
+
+impl RemoteNodeManagerBuilder {
+    /// Create a new remote node manager builder, proxying the namespaces
+    /// with the given URIs from the server `session` is connected to.
+    ///
+    /// The session is not managed by the node manager, you are responsible for
+    /// running its event loop.
+    pub fn new(
+        session: Arc<Session>,
+        namespaces: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            session,
+            namespaces: namespaces.into_iter().map(|n| n.into()).collect(),
+            name: "remote".to_owned(),
+            publishing_interval: Duration::from_millis(100),
+        }
+    }
+
+    /// Set the name of the node manager, defaults to `remote`.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Set the publishing interval of the subscription on the upstream server,
+    /// used for monitored items. Defaults to 100 milliseconds.
+    pub fn publishing_interval(mut self, publishing_interval: Duration) -> Self {
+        self.publishing_interval = publishing_interval;
+        self
+    }
+}
+
+impl NodeManagerBuilder for RemoteNodeManagerBuilder {
+    fn build(self: Box<Self>, context: ServerContext) -> Arc<DynNodeManager> {
+        Arc::new(RemoteNodeManager::new(context, *self))
+    }
+}
+
+/// Key for a monitored item on the upstream server. Local monitored items on the same
+/// node and attribute, with the same sampling interval and queue size, share a single
+/// monitored item on the upstream server.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct MonitoredItemKey {
+    node_id: NodeId,
+    attribute_id: AttributeId,
+    /// Bits of the `f64` sampling interval, so that the key can be hashed.
+    sampling_interval: u64,
+    queue_size: usize,
+}
+
+struct RemoteMonitoredItem {
+    monitored_item_id: u32,
+    client_handle: u32,
+}
+
+#[derive(Default)]
+struct RemoteSubscriptionState {
+    subscription_id: Option<u32>,
+    next_client_handle: u32,
+    items: HashMap<MonitoredItemKey, RemoteMonitoredItem>,
+    handles: HashMap<MonitoredItemHandle, MonitoredItemKey>,
+}
+
+/// Local monitored items notified of data changes on each monitored item
+/// on the upstream server, by client handle.
+type ClientHandles = RwLock<HashMap<u32, Vec<MonitoredItemHandle>>>;
+
+/// Namespace mapping, read while connected to a specific session on the upstream server.
+struct CachedNamespaces {
+    server_session_id: NodeId,
+    namespaces: Arc<RemoteNamespaces>,
+}
+
+/// Continuation point for browsing nodes on the upstream server. This contains
+/// any references returned by the upstream server that did not fit in the
+/// response, and the continuation point on the upstream server, if any.
+struct RemoteBrowseContinuationPoint {
+    session: Arc<Session>,
+    references: VecDeque<ReferenceDescription>,
+    remote: Option<ByteString>,
+}
+
+impl Drop for RemoteBrowseContinuationPoint {
+    fn drop(&mut self) {
+        // If the continuation point is released before we are done,
+        // release it on the upstream server as well.
+        let Some(remote) = self.remote.take() else {
+            return;
+        };
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let session = self.session.clone();
+        handle.spawn(async move {
+            if let Err(e) = session.browse_next(true, &[remote]).await {
+                warn!("Failed to release continuation point on upstream server: {e}");
+            }
+        });
+    }
+}
+
+/// Node manager forwarding requests for a set of namespaces to an upstream
+/// server through a client [`Session`].
+///
+/// This supports the Read, Write, Browse, BrowseNext, TranslateBrowsePathsToNodeIds and
+/// Call services, as well as monitored items for data changes. Monitored items are
+/// forwarded to a single subscription on the upstream server, and notifications
+/// are distributed to local subscriptions.
+///
+/// Browsing nodes in namespace 0 on this server, such as the `Objects` folder, also browses
+/// the upstream server, returning any references to nodes in the proxied namespaces. This means
+/// that nodes organized under the `Objects` folder on the upstream server appear under the
+/// `Objects` folder on this server.
+///
+/// The namespace table of the upstream server is read the first time it is needed, and
+/// read again once the client has created a new session, for example because the upstream
+/// server was restarted.
+pub struct RemoteNodeManager {
+    session: Arc<Session>,
+    name: String,
+    namespaces: Vec<(String, u16)>,
+    remote_namespaces: Arc<ArcSwapOption<CachedNamespaces>>,
+    subscriptions: Arc<SubscriptionCache>,
+    subscription_state: tokio::sync::Mutex<RemoteSubscriptionState>,
+    client_handles: Arc<ClientHandles>,
+    publishing_interval: Duration,
+}
+
+impl RemoteNodeManager {
+    /// Create a new remote node manager, registering the proxied namespaces
+    /// on the server.
+    pub fn new(context: ServerContext, builder: RemoteNodeManagerBuilder) -> Self {
+        let namespaces = {
+            let mut type_tree = trace_write_lock!(context.type_tree);
+            builder
+                .namespaces
+                .into_iter()
+                .map(|uri| {
+                    let idx = type_tree.namespaces_mut().add_namespace(&uri);
+                    (uri, idx)
+                })
+                .collect()
+        };
+
+        Self {
+            session: builder.session,
+            name: builder.name,
+            namespaces,
+            remote_namespaces: Default::default(),
+            subscriptions: context.subscriptions,
+            subscription_state: Default::default(),
+            client_handles: Default::default(),
+            publishing_interval: builder.publishing_interval,
+        }
+    }
+
+    /// Get the client session used to communicate with the upstream server.
+    pub fn session(&self) -> &Arc<Session> {
+        &self.session
+    }
+
+    async fn remote_namespaces(&self) -> Result<Arc<RemoteNamespaces>, StatusCode> {
+        // The namespace table may have changed if the client had to create a new session.
+        let server_session_id = self.session.server_session_id();
+        if let Some(cached) = self.remote_namespaces.load_full() {
+            if cached.server_session_id == server_session_id {
+                return Ok(cached.namespaces.clone());
+            }
+        }
+
+        let result = self
+            .session
+            .read(
+                &[ReadValueId::from(NodeId::from(
+                    VariableId::Server_NamespaceArray,
+                ))],
+                TimestampsToReturn::Neither,
+                0.0,
+            )
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(StatusCode::BadUnexpectedError)?;
+        let Some(Variant::Array(arr)) = result.value else {
+            warn!(
+                "Failed to read namespace array from upstream server: {}",
+                result.status()
+            );
+            return Err(StatusCode::BadCommunicationError);
+        };
+        let remote = NamespaceMap::new_from_variant_array(&arr.values).map_err(|e| {
+            warn!("Invalid namespace array on upstream server: {e}");
+            StatusCode::BadCommunicationError
+        })?;
+
+        let ns = Arc::new(RemoteNamespaces::new(&self.namespaces, &remote));
+        self.remote_namespaces
+            .store(Some(Arc::new(CachedNamespaces {
+                server_session_id,
+                namespaces: ns.clone(),
+            })));
+        Ok(ns)
+    }
+
+    fn to_local_reference(
+        &self,
+        ns: &RemoteNamespaces,
+        reference: ReferenceDescription,
+    ) -> Option<ReferenceDescription> {
+        Some(ReferenceDescription {
+            reference_type_id: ns.to_local_node_id(&reference.reference_type_id)?,
+            is_forward: reference.is_forward,
+            node_id: ns.to_local_expanded_node_id(&reference.node_id)?,
+            browse_name: ns.to_local_qualified_name(&reference.browse_name)?,
+            display_name: reference.display_name,
+            node_class: reference.node_class,
+            type_definition: ns.to_local_expanded_node_id(&reference.type_definition)?,
+        })
+    }
+
+    /// Get the access level of `node_id` for the current user, as restricted by the
+    /// local [`AuthManager`](crate::authenticator::AuthManager). The upstream server
+    /// only checks the access of the client session.
+    fn local_access_level(context: &RequestContext, node_id: &NodeId) -> AccessLevel {
+        context.authenticator.effective_user_access_level(
+            &context.token,
+            AccessLevel::all(),
+            node_id,
+        )
+    }
+
+    /// Restrict the user specific attributes read from the upstream server to
+    /// the access of the current user.
+    fn restrict_user_attribute(
+        context: &RequestContext,
+        node_id: &NodeId,
+        attribute_id: AttributeId,
+        value: &mut DataValue,
+    ) {
+        match (attribute_id, &mut value.value) {
+            (AttributeId::UserAccessLevel, Some(Variant::Byte(v))) => {
+                *v = context
+                    .authenticator
+                    .effective_user_access_level(
+                        &context.token,
+                        AccessLevel::from_bits_truncate(*v),
+                        node_id,
+                    )
+                    .bits();
+            }
+            (AttributeId::UserExecutable, Some(Variant::Boolean(v))) => {
+                *v = *v
+                    && context
+                        .authenticator
+                        .is_user_executable(&context.token, node_id);
+            }
+            _ => (),
+        }
+    }
+
+    /// Add the references in a browse result from the upstream server to `node`,
+    /// storing a continuation point if needed.
+    fn add_browse_result(
+        &self,
+        ns: &RemoteNamespaces,
+        node: &mut BrowseNode,
+        mut references: VecDeque<ReferenceDescription>,
+        result: BrowseResult,
+    ) {
+        let owned = self.owns_node(node.node_id());
+        if owned {
+            node.set_status(result.status_code);
+        }
+
+        for rf in result.references.into_iter().flatten() {
+            let Some(rf) = self.to_local_reference(ns, rf) else {
+                continue;
+            };
+            // References to nodes outside of the proxied namespaces
+            // are only returned when browsing our own nodes, other node managers
+            // are responsible for their own references.
+            if !owned
+                && (!rf.node_id.namespace_uri.is_null() || !self.owns_node(&rf.node_id.node_id))
+            {
+                continue;
+            }
+            references.push_back(rf);
+        }
+
+        self.continue_browse(node, references, result.continuation_point);
+    }
+
+    fn continue_browse(
+        &self,
+        node: &mut BrowseNode,
+        mut references: VecDeque<ReferenceDescription>,
+        remote: ByteString,
+    ) {
+        while node.remaining() > 0 {
+            let Some(rf) = references.pop_front() else {
+                break;
+            };
+            node.add_unchecked(rf);
+        }
+
+        if !references.is_empty() || !remote.is_null() {
+            node.set_next_continuation_point(Box::new(RemoteBrowseContinuationPoint {
+                session: self.session.clone(),
+                references,
+                remote: (!remote.is_null()).then_some(remote),
+            }));
+        }
+    }
+
+    fn on_data_change(
+        subscriptions: &SubscriptionCache,
+        client_handles: &ClientHandles,
+        namespaces: &ArcSwapOption<CachedNamespaces>,
+        client_handle: u32,
+        value: DataValue,
+    ) {
+        let Some(handles) = trace_read_lock!(client_handles)
+            .get(&client_handle)
+            .cloned()
+        else {
+            return;
+        };
+        let Some(cached) = namespaces.load_full() else {
+            return;
+        };
+        let value = cached.namespaces.to_local_data_value(value);
+        subscriptions.notify_data_change_for_items(handles.into_iter().map(|h| (h, value.clone())));
+    }
+
+    async fn get_or_create_subscription(
+        &self,
+        state: &mut RemoteSubscriptionState,
+    ) -> Result<u32, StatusCode> {
+        if let Some(id) = state.subscription_id {
+            return Ok(id);
+        }
+
+        let subscriptions = self.subscriptions.clone();
+        let client_handles = self.client_handles.clone();
+        let namespaces = self.remote_namespaces.clone();
+        let id = self
+            .session
+            .create_subscription(
+                self.publishing_interval,
+                60,
+                20,
+                0,
+                0,
+                true,
+                DataChangeCallback::new(move |value, item| {
+                    Self::on_data_change(
+                        &subscriptions,
+                        &client_handles,
+                        &namespaces,
+                        item.client_handle(),
+                        value,
+                    );
+                }),
+            )
+            .await?;
+        state.subscription_id = Some(id);
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl NodeManager for RemoteNodeManager {
+    fn owns_node(&self, id: &NodeId) -> bool {
+        self.namespaces.iter().any(|(_, idx)| *idx == id.namespace)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn namespaces_for_user(&self, _context: &RequestContext) -> Vec<NamespaceMetadata> {
+        self.namespaces
+            .iter()
+            .map(|(uri, idx)| NamespaceMetadata {
+                namespace_uri: uri.clone(),
+                namespace_index: *idx,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    async fn init(&self, _type_tree: &mut DefaultTypeTree, _context: ServerContext) {}
+
+    async fn resolve_external_references(
+        &self,
+        _context: &RequestContext,
+        items: &mut [&mut ExternalReferenceRequest],
+    ) {
+        if !items.iter().any(|i| self.owns_node(i.node_id())) {
+            return;
+        }
+        let Ok(ns) = self.remote_namespaces().await else {
+            return;
+        };
+
+        let (mut items, remote_ids): (Vec<_>, Vec<_>) = items
+            .iter_mut()
+            .filter(|i| self.owns_node(i.node_id()))
+            .filter_map(|i| {
+                let id = ns.to_remote_node_id(i.node_id())?;
+                Some((i, id))
+            })
+            .unzip();
+        if items.is_empty() {
+            return;
+        }
+        let to_read: Vec<_> = remote_ids
+            .iter()
+            .flat_map(|id| {
+                [
+                    AttributeId::NodeClass,
+                    AttributeId::BrowseName,
+                    AttributeId::DisplayName,
+                ]
+                .map(|attribute_id| ReadValueId {
+                    node_id: id.clone(),
+                    attribute_id: attribute_id as u32,
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let to_browse: Vec<_> = remote_ids
+            .iter()
+            .map(|id| BrowseDescription {
+                node_id: id.clone(),
+                browse_direction: BrowseDirection::Forward,
+                reference_type_id: ReferenceTypeId::HasTypeDefinition.into(),
+                include_subtypes: false,
+                node_class_mask: 0,
+                result_mask: BrowseDescriptionResultMask::empty().bits(),
+            })
+            .collect();
+
+        let (values, types) = match futures::future::try_join(
+            self.session
+                .read(&to_read, TimestampsToReturn::Neither, 0.0),
+            self.session.browse(&to_browse, 1, None),
+        )
+        .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("Failed to resolve references on upstream server: {e}");
+                return;
+            }
+        };
+
+        for ((item, values), type_def) in items.iter_mut().zip(values.chunks(3)).zip(types) {
+            let [node_class, browse_name, display_name] = values else {
+                continue;
+            };
+            let Some(Variant::Int32(node_class)) = node_class.value else {
+                continue;
+            };
+            let Some(Variant::QualifiedName(browse_name)) = &browse_name.value else {
+                continue;
+            };
+            let display_name = match &display_name.value {
+                Some(Variant::LocalizedText(t)) => (**t).clone(),
+                _ => LocalizedText::null(),
+            };
+            let type_definition = type_def
+                .references
+                .into_iter()
+                .flatten()
+                .next()
+                .and_then(|r| ns.to_local_expanded_node_id(&r.node_id))
+                .unwrap_or_default();
+            let Some(browse_name) = ns.to_local_qualified_name(browse_name) else {
+                continue;
+            };
+
+            item.set(NodeMetadata {
+                node_id: item.node_id().clone().into(),
+                type_definition,
+                browse_name,
+                display_name,
+                node_class: NodeClass::try_from(node_class).unwrap_or(NodeClass::Unspecified),
+            });
+        }
+    }
+
+    async fn read(
+        &self,
+        context: &RequestContext,
+        max_age: f64,
+        timestamps_to_return: TimestampsToReturn,
+        nodes_to_read: &mut [&mut ReadNode],
+    ) -> Result<(), StatusCode> {
+        let ns = self.remote_namespaces().await?;
+
+        let mut to_read = Vec::with_capacity(nodes_to_read.len());
+        let mut targets = Vec::with_capacity(nodes_to_read.len());
+        for node in nodes_to_read.iter_mut() {
+            let item = node.node();
+            let Some(node_id) = ns.to_remote_node_id(&item.node_id) else {
+                node.set_error(StatusCode::BadNodeIdUnknown);
+                continue;
+            };
+            let Some(data_encoding) = ns.to_remote_data_encoding(&item.data_encoding) else {
+                node.set_error(StatusCode::BadDataEncodingInvalid);
+                continue;
+            };
+            if item.attribute_id == AttributeId::Value
+                && !Self::local_access_level(context, &item.node_id)
+                    .contains(AccessLevel::CURRENT_READ)
+            {
+                node.set_error(StatusCode::BadUserAccessDenied);
+                continue;
+            }
+            to_read.push(ReadValueId {
+                node_id,
+                attribute_id: item.attribute_id as u32,
+                index_range: item.index_range.clone(),
+                data_encoding,
+            });
+            targets.push(node);
+        }
+        if to_read.is_empty() {
+            return Ok(());
+        }
+
+        let results = self
+            .session
+            .read(&to_read, timestamps_to_return, max_age)
+            .await?;
+        for (node, value) in targets.into_iter().zip(results) {
+            let mut value = ns.to_local_data_value(value);
+            let item = node.node();
+            Self::restrict_user_attribute(context, &item.node_id, item.attribute_id, &mut value);
+            node.set_result(value);
+        }
+
+        Ok(())
+    }
+
+    async fn write(
+        &self,
+        context: &RequestContext,
+        nodes_to_write: &mut [&mut WriteNode],
+    ) -> Result<(), StatusCode> {
+        let ns = self.remote_namespaces().await?;
+
+        let mut to_write = Vec::with_capacity(nodes_to_write.len());
+        let mut targets = Vec::with_capacity(nodes_to_write.len());
+        for node in nodes_to_write.iter_mut() {
+            let item = node.value();
+            let Some(node_id) = ns.to_remote_node_id(&item.node_id) else {
+                node.set_status(StatusCode::BadNodeIdUnknown);
+                continue;
+            };
+            let Some(value) = ns.to_remote_data_value(item.value.clone()) else {
+                node.set_status(StatusCode::BadNodeIdUnknown);
+                continue;
+            };
+            // Writes to attributes other than the value are governed by the
+            // `UserWriteMask` of the upstream server.
+            if item.attribute_id == AttributeId::Value
+                && !Self::local_access_level(context, &item.node_id)
+                    .contains(AccessLevel::CURRENT_WRITE)
+            {
+                node.set_status(StatusCode::BadUserAccessDenied);
+                continue;
+            }
+            to_write.push(WriteValue {
+                node_id,
+                attribute_id: item.attribute_id as u32,
+                index_range: item.index_range.clone(),
+                value,
+            });
+            targets.push(node);
+        }
+        if to_write.is_empty() {
+            return Ok(());
+        }
+
+        let results = self.session.write(&to_write).await?;
+        for (node, status) in targets.into_iter().zip(results) {
+            node.set_status(status);
+        }
+
+        Ok(())
+    }
+
+    async fn browse(
+        &self,
+        _context: &RequestContext,
+        nodes_to_browse: &mut [BrowseNode],
+    ) -> Result<(), StatusCode> {
+        let relevant = |node: &BrowseNode| {
+            !node.node_id().is_null()
+                && (node.node_id().namespace == 0 || self.owns_node(node.node_id()))
+        };
+        if !nodes_to_browse.iter().any(relevant) {
+            return Ok(());
+        }
+        let ns = self.remote_namespaces().await?;
+
+        let mut to_browse = Vec::new();
+        let mut to_browse_next = Vec::new();
+        for (idx, node) in nodes_to_browse.iter_mut().enumerate() {
+            if !relevant(node) {
+                continue;
+            }
+
+            if let Some(mut point) = node.take_continuation_point::<RemoteBrowseContinuationPoint>()
+            {
+                let references = std::mem::take(&mut point.references);
+                let remote = point.remote.take();
+                if self.owns_node(node.node_id()) {
+                    node.set_status(StatusCode::Good);
+                }
+                // Only continue on the upstream server once we have
+                // returned all the references we already have.
+                match remote {
+                    Some(remote) if references.is_empty() && node.remaining() > 0 => {
+                        to_browse_next.push((idx, remote));
+                    }
+                    Some(remote) => self.continue_browse(node, references, remote),
+                    None => self.continue_browse(node, references, ByteString::null()),
+                }
+                continue;
+            }
+
+            let Some(node_id) = ns.to_remote_node_id(node.node_id()) else {
+                continue;
+            };
+            let Some(reference_type_id) = ns.to_remote_node_id(node.reference_type_id()) else {
+                continue;
+            };
+            to_browse.push((
+                idx,
+                BrowseDescription {
+                    node_id,
+                    browse_direction: node.browse_direction(),
+                    reference_type_id,
+                    include_subtypes: node.include_subtypes(),
+                    node_class_mask: node.node_class_mask().bits(),
+                    result_mask: node.result_mask().bits(),
+                },
+            ));
+        }
+
+        if !to_browse.is_empty() {
+            let max_references = to_browse
+                .iter()
+                .map(|(idx, _)| nodes_to_browse[*idx].remaining())
+                .max()
+                .unwrap_or_default();
+            let descriptions: Vec<_> = to_browse.iter().map(|(_, d)| d.clone()).collect();
+            match self
+                .session
+                .browse(&descriptions, max_references as u32, None)
+                .await
+            {
+                Ok(results) => {
+                    for ((idx, _), result) in to_browse.iter().zip(results) {
+                        self.add_browse_result(
+                            &ns,
+                            &mut nodes_to_browse[*idx],
+                            VecDeque::new(),
+                            result,
+                        );
+                    }
+                }
+                Err(e) => {
+                    for (idx, _) in &to_browse {
+                        let node = &mut nodes_to_browse[*idx];
+                        if self.owns_node(node.node_id()) {
+                            node.set_status(e);
+                        }
+                    }
+                }
+            }
+        }
+
+        if !to_browse_next.is_empty() {
+            let points: Vec<_> = to_browse_next.iter().map(|(_, p)| p.clone()).collect();
+            match self.session.browse_next(false, &points).await {
+                Ok(results) => {
+                    for ((idx, _), result) in to_browse_next.iter().zip(results) {
+                        self.add_browse_result(
+                            &ns,
+                            &mut nodes_to_browse[*idx],
+                            VecDeque::new(),
+                            result,
+                        );
+                    }
+                }
+                Err(e) => {
+                    for (idx, _) in &to_browse_next {
+                        let node = &mut nodes_to_browse[*idx];
+                        if self.owns_node(node.node_id()) {
+                            node.set_status(e);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn translate_browse_paths_to_node_ids(
+        &self,
+        context: &RequestContext,
+        nodes: &mut [&mut BrowsePathItem],
+    ) -> Result<(), StatusCode> {
+        impl_translate_browse_paths_using_browse(self, context, nodes).await
+    }
+
+    async fn call(
+        &self,
+        context: &RequestContext,
+        methods_to_call: &mut [&mut MethodCall],
+    ) -> Result<(), StatusCode> {
+        let ns = self.remote_namespaces().await?;
+
+        let mut to_call = Vec::with_capacity(methods_to_call.len());
+        let mut targets = Vec::with_capacity(methods_to_call.len());
+        for method in methods_to_call.iter_mut() {
+            let (Some(object_id), Some(method_id)) = (
+                ns.to_remote_node_id(method.object_id()),
+                ns.to_remote_node_id(method.method_id()),
+            ) else {
+                method.set_status(StatusCode::BadNodeIdUnknown);
+                continue;
+            };
+            if !context
+                .authenticator
+                .is_user_executable(&context.token, method.method_id())
+            {
+                method.set_status(StatusCode::BadUserAccessDenied);
+                continue;
+            }
+            let Some(input_arguments) = method
+                .arguments()
+                .iter()
+                .map(|a| ns.to_remote_variant(a.clone()))
+                .collect::<Option<Vec<_>>>()
+            else {
+                method.set_status(StatusCode::BadInvalidArgument);
+                continue;
+            };
+            to_call.push(CallMethodRequest {
+                object_id,
+                method_id,
+                input_arguments: Some(input_arguments),
+            });
+            targets.push(method);
+        }
+        if to_call.is_empty() {
+            return Ok(());
+        }
+
+        let results = self.session.call(to_call).await?;
+        for (method, result) in targets.into_iter().zip(results) {
+            if let Some(arg_results) = result.input_argument_results {
+                if arg_results.iter().any(|r| r.is_bad()) {
+                    method.set_argument_error(arg_results);
+                }
+            }
+            method.set_status(result.status_code);
+            if let Some(outputs) = result.output_arguments {
+                match outputs
+                    .into_iter()
+                    .map(|v| ns.to_local_variant(v))
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(outputs) => method.set_outputs(outputs),
+                    None => method.set_status(StatusCode::BadNodeIdUnknown),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn create_monitored_items(
+        &self,
+        context: &RequestContext,
+        items: &mut [&mut CreateMonitoredItem],
+    ) -> Result<(), StatusCode> {
+        let ns = self.remote_namespaces().await?;
+        let mut state = self.subscription_state.lock().await;
+
+        // Read initial values, since any values published by the upstream server before
+        // the local monitored items are created will be lost.
+        let mut to_read = Vec::with_capacity(items.len());
+        let mut targets = Vec::with_capacity(items.len());
+        for item in items.iter_mut() {
+            let target = item.item_to_monitor();
+            if target.attribute_id == AttributeId::EventNotifier {
+                item.set_status(StatusCode::BadMonitoredItemFilterUnsupported);
+                continue;
+            }
+            let Some(node_id) = ns.to_remote_node_id(&target.node_id) else {
+                item.set_status(StatusCode::BadNodeIdUnknown);
+                continue;
+            };
+            let Some(data_encoding) = ns.to_remote_data_encoding(&target.data_encoding) else {
+                item.set_status(StatusCode::BadDataEncodingInvalid);
+                continue;
+            };
+            if target.attribute_id == AttributeId::Value
+                && !Self::local_access_level(context, &target.node_id)
+                    .contains(AccessLevel::CURRENT_READ)
+            {
+                item.set_status(StatusCode::BadUserAccessDenied);
+                continue;
+            }
+            to_read.push(ReadValueId {
+                node_id,
+                attribute_id: target.attribute_id as u32,
+                index_range: NumericRange::None,
+                data_encoding,
+            });
+            targets.push(item);
+        }
+        if targets.is_empty() {
+            return Ok(());
+        }
+        let subscription_id = self.get_or_create_subscription(&mut state).await?;
+        let values = self
+            .session
+            .read(&to_read, TimestampsToReturn::Both, 0.0)
+            .await?;
+
+        // Group the items by node, attribute and monitoring parameters, so that we only
+        // create a single monitored item on the upstream server for each.
+        let mut to_create: HashMap<MonitoredItemKey, (MonitoredItemCreateRequest, Vec<usize>)> =
+            HashMap::new();
+        for (idx, (item, (read, value))) in targets
+            .iter_mut()
+            .zip(to_read.into_iter().zip(values))
+            .enumerate()
+        {
+            let key = MonitoredItemKey {
+                node_id: item.item_to_monitor().node_id.clone(),
+                attribute_id: item.item_to_monitor().attribute_id,
+                sampling_interval: item.sampling_interval().to_bits(),
+                queue_size: item.queue_size(),
+            };
+            if let Some(existing) = state.items.get(&key) {
+                if let Some(handles) =
+                    trace_write_lock!(self.client_handles).get_mut(&existing.client_handle)
+                {
+                    handles.push(item.handle());
+                }
+                state.handles.insert(item.handle(), key);
+                item.set_initial_value(ns.to_local_data_value(value));
+                item.set_status(StatusCode::Good);
+                continue;
+            }
+            item.set_initial_value(ns.to_local_data_value(value));
+
+            let entry = to_create.entry(key).or_insert_with(|| {
+                state.next_client_handle += 1;
+                (
+                    MonitoredItemCreateRequest {
+                        item_to_monitor: read,
+                        monitoring_mode: opcua_types::MonitoringMode::Reporting,
+                        requested_parameters: MonitoringParameters {
+                            client_handle: state.next_client_handle,
+                            sampling_interval: item.sampling_interval(),
+                            filter: ExtensionObject::null(),
+                            queue_size: item.queue_size() as u32,
+                            discard_oldest: true,
+                        },
+                    },
+                    Vec::new(),
+                )
+            });
+            entry.1.push(idx);
+        }
+        if to_create.is_empty() {
+            return Ok(());
+        }
+
+        let (keys, (requests, indices)): (Vec<_>, (Vec<_>, Vec<_>)) = to_create.into_iter().unzip();
+        let client_handles: Vec<_> = requests
+            .iter()
+            .map(|r| r.requested_parameters.client_handle)
+            .collect();
+        // Register the client handles before creating the items, so that
+        // we don't miss any notifications.
+        {
+            let mut handles = trace_write_lock!(self.client_handles);
+            for (indices, handle) in indices.iter().zip(&client_handles) {
+                handles.insert(
+                    *handle,
+                    indices.iter().map(|idx| targets[*idx].handle()).collect(),
+                );
+            }
+        }
+        let results = match self
+            .session
+            .create_monitored_items(subscription_id, TimestampsToReturn::Both, requests)
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                let mut handles = trace_write_lock!(self.client_handles);
+                for handle in &client_handles {
+                    handles.remove(handle);
+                }
+                return Err(e);
+            }
+        };
+
+        for (((key, indices), client_handle), result) in keys
+            .into_iter()
+            .zip(indices)
+            .zip(client_handles)
+            .zip(results)
+        {
+            let status = result.result.status_code;
+            if status.is_bad() {
+                trace_write_lock!(self.client_handles).remove(&client_handle);
+            } else {
+                state.items.insert(
+                    key.clone(),
+                    RemoteMonitoredItem {
+                        monitored_item_id: result.result.monitored_item_id,
+                        client_handle,
+                    },
+                );
+            }
+            for idx in indices {
+                let item = &mut targets[idx];
+                item.set_status(status);
+                if status.is_good() {
+                    item.revise_sampling_interval(result.result.revised_sampling_interval);
+                    state.handles.insert(item.handle(), key.clone());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn delete_monitored_items(&self, _context: &RequestContext, items: &[&MonitoredItemRef]) {
+        let mut state = self.subscription_state.lock().await;
+        let Some(subscription_id) = state.subscription_id else {
+            return;
+        };
+
+        let mut to_delete = Vec::new();
+        for item in items {
+            let Some(key) = state.handles.remove(&item.handle()) else {
+                continue;
+            };
+            let Some(client_handle) = state.items.get(&key).map(|r| r.client_handle) else {
+                continue;
+            };
+            let mut client_handles = trace_write_lock!(self.client_handles);
+            if let Some(handles) = client_handles.get_mut(&client_handle) {
+                handles.retain(|h| *h != item.handle());
+                if !handles.is_empty() {
+                    continue;
+                }
+            }
+            client_handles.remove(&client_handle);
+            if let Some(remote) = state.items.remove(&key) {
+                to_delete.push(remote.monitored_item_id);
+            }
+        }
+
+        if !to_delete.is_empty() {
+            if let Err(e) = self
+                .session
+                .delete_monitored_items(subscription_id, &to_delete)
+                .await
+            {
+                warn!("Failed to delete monitored items on upstream server: {e}");
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use hashbrown::HashMap;
+use opcua_types::{
+    DataEncoding, DataValue, ExpandedNodeId, NamespaceMap, NamespaceRemapper, NodeId,
+    QualifiedName, StatusCode, Variant,
+};
+
+/// Mapping between namespace indices on the upstream server and namespace
+/// indices on this server. Namespace 0 is always mapped to itself.
+pub(super) struct RemoteNamespaces {
+    to_remote: NamespaceRemapper,
+    to_local: NamespaceRemapper,
+}
+
+impl RemoteNamespaces {
+    /// Create a new mapping from the proxied namespaces, given as pairs of
+    /// namespace URI and local namespace index, and the namespace map of the
+    /// upstream server.
+    pub(super) fn new(local: &[(String, u16)], remote: &NamespaceMap) -> Self {
+        let mut map: HashMap<_, _> = local.iter().cloned().collect();
+        map.insert("http://opcfoundation.org/UA/".to_owned(), 0);
+        let to_remote = NamespaceMap::new_full(map).remapper(remote);
+        let to_local = to_remote.inverse();
+
+        Self {
+            to_remote,
+            to_local,
+        }
+    }
+
+    /// Convert a local node ID to a node ID on the upstream server.
+    pub(super) fn to_remote_node_id(&self, id: &NodeId) -> Option<NodeId> {
+        self.to_remote.remap_node_id(id).ok()
+    }
+
+    /// Convert a node ID on the upstream server to a local node ID.
+    pub(super) fn to_local_node_id(&self, id: &NodeId) -> Option<NodeId> {
+        self.to_local.remap_node_id(id).ok()
+    }
+
+    /// Convert an expanded node ID on the upstream server to a local expanded node ID.
+    /// Node IDs in namespaces that are not proxied are returned with an absolute
+    /// namespace URI instead. Returns `None` if the namespace is unknown.
+    pub(super) fn to_local_expanded_node_id(&self, id: &ExpandedNodeId) -> Option<ExpandedNodeId> {
+        Self::remap_expanded_node_id(&self.to_local, id)
+    }
+
+    fn to_remote_expanded_node_id(&self, id: &ExpandedNodeId) -> Option<ExpandedNodeId> {
+        Self::remap_expanded_node_id(&self.to_remote, id)
+    }
+
+    fn remap_expanded_node_id(
+        remapper: &NamespaceRemapper,
+        id: &ExpandedNodeId,
+    ) -> Option<ExpandedNodeId> {
+        if let Ok(id) = remapper.remap_expanded_node_id(id) {
+            return Some(id);
+        }
+        // Namespaces that are not mapped must not keep their index, since it
+        // refers to an unrelated namespace on the other side.
+        let id = remapper.remap_expanded_node_id_or_absolute(id);
+        (!id.namespace_uri.is_null()).then_some(id)
+    }
+
+    /// Convert a qualified name on the upstream server to a local qualified name.
+    /// Returns `None` for names in namespaces that are not proxied.
+    pub(super) fn to_local_qualified_name(&self, name: &QualifiedName) -> Option<QualifiedName> {
+        self.to_local.remap_qualified_name(name).ok()
+    }
+
+    /// Convert a local qualified name to a qualified name on the upstream server.
+    /// Returns `None` for names in namespaces that are not proxied.
+    pub(super) fn to_remote_qualified_name(&self, name: &QualifiedName) -> Option<QualifiedName> {
+        self.to_remote.remap_qualified_name(name).ok()
+    }
+
+    /// Get the browse name of a data encoding on the upstream server.
+    pub(super) fn to_remote_data_encoding(&self, encoding: &DataEncoding) -> Option<QualifiedName> {
+        match encoding {
+            DataEncoding::Binary => Some(QualifiedName::null()),
+            DataEncoding::XML => Some(QualifiedName::new(0, "Default XML")),
+            DataEncoding::JSON => Some(QualifiedName::new(0, "Default JSON")),
+            DataEncoding::Other(name) => self.to_remote_qualified_name(name),
+        }
+    }
+
+    /// Convert node IDs and qualified names in a variant from the upstream server.
+    /// Returns `None` if the variant contains node IDs or qualified names in namespaces
+    /// that are not proxied.
+    pub(super) fn to_local_variant(&self, value: Variant) -> Option<Variant> {
+        Some(match value {
+            Variant::NodeId(id) => Variant::NodeId(Box::new(self.to_local_node_id(&id)?)),
+            Variant::ExpandedNodeId(id) => {
+                Variant::ExpandedNodeId(Box::new(self.to_local_expanded_node_id(&id)?))
+            }
+            Variant::QualifiedName(name) => {
+                Variant::QualifiedName(Box::new(self.to_local_qualified_name(&name)?))
+            }
+            Variant::Array(mut arr) => {
+                arr.values = std::mem::take(&mut arr.values)
+                    .into_iter()
+                    .map(|v| self.to_local_variant(v))
+                    .collect::<Option<_>>()?;
+                Variant::Array(arr)
+            }
+            r => r,
+        })
+    }
+
+    /// Convert node IDs and qualified names in a variant to the upstream server.
+    /// Returns `None` if the variant contains node IDs or qualified names in namespaces
+    /// that are not proxied.
+    pub(super) fn to_remote_variant(&self, value: Variant) -> Option<Variant> {
+        Some(match value {
+            Variant::NodeId(id) => Variant::NodeId(Box::new(self.to_remote_node_id(&id)?)),
+            Variant::ExpandedNodeId(id) => {
+                Variant::ExpandedNodeId(Box::new(self.to_remote_expanded_node_id(&id)?))
+            }
+            Variant::QualifiedName(name) => {
+                Variant::QualifiedName(Box::new(self.to_remote_qualified_name(&name)?))
+            }
+            Variant::Array(mut arr) => {
+                arr.values = std::mem::take(&mut arr.values)
+                    .into_iter()
+                    .map(|v| self.to_remote_variant(v))
+                    .collect::<Option<_>>()?;
+                Variant::Array(arr)
+            }
+            r => r,
+        })
+    }
+
+    /// Convert the value of a data value from the upstream server.
+    /// Values that cannot be converted are replaced by a `BadNodeIdUnknown` status.
+    pub(super) fn to_local_data_value(&self, mut value: DataValue) -> DataValue {
+        if let Some(v) = value.value.take() {
+            match self.to_local_variant(v) {
+                Some(v) => value.value = Some(v),
+                None => value.status = Some(StatusCode::BadNodeIdUnknown),
+            }
+        }
+        value
+    }
+
+    /// Convert the value of a data value to the upstream server.
+    pub(super) fn to_remote_data_value(&self, mut value: DataValue) -> Option<DataValue> {
+        value.value = match value.value {
+            Some(v) => Some(self.to_remote_variant(v)?),
+            None => None,
+        };
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opcua_types::{
+        DataValue, ExpandedNodeId, NamespaceMap, NodeId, QualifiedName, StatusCode, Variant,
+    };
+
+    use super::RemoteNamespaces;
+
+    #[test]
+    fn remap_namespaces() {
+        let mut remote = NamespaceMap::new();
+        remote.add_namespace("urn:other");
+        remote.add_namespace("urn:proxied");
+        let ns = RemoteNamespaces::new(&[("urn:proxied".to_owned(), 5)], &remote);
+
+        assert_eq!(
+            ns.to_remote_node_id(&NodeId::new(5, "foo")),
+            Some(NodeId::new(2, "foo"))
+        );
+        assert_eq!(
+            ns.to_local_node_id(&NodeId::new(2, "foo")),
+            Some(NodeId::new(5, "foo"))
+        );
+        assert_eq!(
+            ns.to_local_node_id(&NodeId::new(0, 85)),
+            Some(NodeId::new(0, 85))
+        );
+        assert_eq!(ns.to_local_node_id(&NodeId::new(1, "foo")), None);
+        assert_eq!(ns.to_remote_node_id(&NodeId::new(3, "foo")), None);
+
+        let id = ns
+            .to_local_expanded_node_id(&NodeId::new(1, "foo").into())
+            .unwrap();
+        assert_eq!(id.namespace_uri.as_ref(), "urn:other");
+        assert_eq!(id.node_id, NodeId::new(0, "foo"));
+
+        let Some(Variant::Array(arr)) = ns.to_local_variant(Variant::from(vec![
+            QualifiedName::new(2, "a"),
+            QualifiedName::new(0, "b"),
+        ])) else {
+            panic!("Expected array");
+        };
+        assert_eq!(
+            arr.values,
+            vec![
+                Variant::from(QualifiedName::new(5, "a")),
+                Variant::from(QualifiedName::new(0, "b"))
+            ]
+        );
+
+        // Namespaces that are not proxied do not keep their index, since it
+        // refers to an unrelated namespace on the other server.
+        assert_eq!(
+            ns.to_local_qualified_name(&QualifiedName::new(1, "a")),
+            None
+        );
+        assert_eq!(
+            ns.to_remote_qualified_name(&QualifiedName::new(1, "a")),
+            None
+        );
+        assert_eq!(
+            ns.to_local_variant(Variant::from(vec![
+                QualifiedName::new(2, "a"),
+                QualifiedName::new(1, "b"),
+            ])),
+            None
+        );
+        assert_eq!(
+            ns.to_remote_variant(Variant::from(NodeId::new(3, "foo"))),
+            None
+        );
+        assert_eq!(
+            ns.to_remote_variant(Variant::from(ExpandedNodeId::from(NodeId::new(3, "foo")))),
+            None
+        );
+        let value = ns.to_local_data_value(DataValue::value_only(NodeId::new(1, "foo")));
+        assert_eq!(value.value, None);
+        assert_eq!(value.status, Some(StatusCode::BadNodeIdUnknown));
+    }
+}
@@ -0,0 +1,403 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use tokio::{runtime::RuntimeFlavor, sync::oneshot};
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::node_manager::WriteNode;
+use opcua_core::sync::Mutex;
+use opcua_types::{DataValue, NodeId, NumericRange, StatusCode};
+
+type FlushCB = Box<dyn Fn(&NodeId, DataValue, &NumericRange) -> StatusCode + Send + Sync>;
+
+/// When a [DebouncedWriter] reports the result of a write back to the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebounceAck {
+    /// Report `Good` as soon as the write is queued. Errors from the backend
+    /// are logged, but never reach the client.
+    #[default]
+    Optimistic,
+    /// Wait until the write is flushed, and report the status returned by the
+    /// backend. If a write is superseded by a later write to the same node and index
+    /// range before it is flushed, it gets the status of the write that replaced it.
+    OnFlush,
+}
+
+struct PendingWrite {
+    value: DataValue,
+    index_range: NumericRange,
+    waiters: Vec<oneshot::Sender<StatusCode>>,
+}
+
+struct DebouncedWriterInner {
+    /// Pending writes per node, in the order they should be applied.
+    pending: Mutex<HashMap<NodeId, Vec<PendingWrite>>>,
+    /// Held for the duration of a flush, so that flushes from the timer, explicit
+    /// calls to `flush`, and drop are applied one batch at a time, in order.
+    flushing: Mutex<()>,
+    flush: FlushCB,
+}
+
+impl DebouncedWriterInner {
+    fn flush(&self) {
+        let _flushing = self.flushing.lock();
+        let pending = std::mem::take(&mut *self.pending.lock());
+        for (node_id, writes) in pending {
+            for write in writes {
+                let status = (self.flush)(&node_id, write.value, &write.index_range);
+                if !status.is_good() {
+                    tracing::warn!("Debounced write to {node_id} failed with status {status}");
+                }
+                for waiter in write.waiters {
+                    let _ = waiter.send(status);
+                }
+            }
+        }
+    }
+
+    /// Flush pending writes on a blocking thread, since the backend may be slow.
+    async fn flush_blocking(self: &Arc<Self>) {
+        let inner = self.clone();
+        if let Err(e) = tokio::task::spawn_blocking(move || inner.flush()).await {
+            tracing::error!("Debounced writer flush failed: {e}");
+        }
+    }
+}
+
+/// Utility for coalescing writes to slow backing storage, such as a PLC.
+///
+/// Writes are queued per node and index range, and only the latest value for each
+/// is kept. Queued writes are flushed at most once per `interval`, by calling the flush
+/// callback once for each pending write, in the order the writes were made.
+///
+/// The flush callback is called on a blocking thread, so it may block on IO.
+/// Flushes never overlap, so the callback sees writes in the order they were made.
+///
+/// You should call `run` to start the writer once you have access to the server context,
+/// typically in `init`. Any pending writes are flushed before the writer is dropped,
+/// blocking the dropping thread until the flush is complete.
+///
+/// To debounce writes to nodes in a [`SimpleNodeManager`](crate::node_manager::memory::SimpleNodeManager),
+/// register the writer with [`SimpleNodeManagerImpl::add_debounced_writer`](crate::node_manager::memory::SimpleNodeManagerImpl::add_debounced_writer).
+pub struct DebouncedWriter {
+    inner: Arc<DebouncedWriterInner>,
+    interval: Duration,
+    ack: DebounceAck,
+    _guard: DropGuard,
+    token: CancellationToken,
+}
+
+impl DebouncedWriter {
+    /// Create a new debounced writer, flushing at most once per `interval`.
+    /// `flush` is called once per node and index range with the latest value
+    /// written to it since the last flush.
+    pub fn new(
+        interval: Duration,
+        ack: DebounceAck,
+        flush: impl Fn(&NodeId, DataValue, &NumericRange) -> StatusCode + Send + Sync + 'static,
+    ) -> Self {
+        let token = CancellationToken::new();
+        Self {
+            inner: Arc::new(DebouncedWriterInner {
+                pending: Default::default(),
+                flushing: Default::default(),
+                flush: Box::new(flush),
+            }),
+            interval,
+            ack,
+            _guard: token.clone().drop_guard(),
+            token,
+        }
+    }
+
+    /// Start the writer. You should avoid calling this multiple times.
+    /// The writer will automatically shut down once it is dropped.
+    pub fn run(&self) {
+        let token = self.token.clone();
+        let inner = self.inner.clone();
+        let interval = self.interval;
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(interval);
+            tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = tick.tick() => inner.flush_blocking().await,
+                    _ = token.cancelled() => break,
+                }
+            }
+        });
+    }
+
+    /// Queue a write to `node_id`, replacing any write to the same node and
+    /// index range that has not yet been flushed. A write without an index range
+    /// replaces all pending writes to the node.
+    ///
+    /// Depending on the configured [DebounceAck], this either returns `Good`
+    /// immediately, or waits for the value to be flushed.
+    pub async fn write(
+        &self,
+        node_id: &NodeId,
+        value: DataValue,
+        index_range: &NumericRange,
+    ) -> StatusCode {
+        match self.ack {
+            DebounceAck::Optimistic => {
+                self.enqueue(node_id, value, index_range, None);
+                StatusCode::Good
+            }
+            DebounceAck::OnFlush => {
+                let (tx, rx) = oneshot::channel();
+                self.enqueue(node_id, value, index_range, Some(tx));
+                rx.await.unwrap_or(StatusCode::BadShutdown)
+            }
+        }
+    }
+
+    /// Queue a list of writes from the `Write` service, setting the status
+    /// of each according to the configured [DebounceAck].
+    ///
+    /// This does not validate the writes, the caller should check that each
+    /// node exists and is writable before calling this.
+    pub async fn write_nodes(&self, nodes_to_write: &mut [&mut WriteNode]) {
+        let results = futures::future::join_all(nodes_to_write.iter().map(|n| {
+            let value = n.value();
+            self.write(&value.node_id, value.value.clone(), &value.index_range)
+        }))
+        .await;
+
+        for (node, status) in nodes_to_write.iter_mut().zip(results) {
+            node.set_status(status);
+        }
+    }
+
+    /// Immediately flush all pending writes.
+    pub async fn flush(&self) {
+        self.inner.flush_blocking().await;
+    }
+
+    /// Get the number of nodes with a pending write.
+    pub fn pending_count(&self) -> usize {
+        self.inner.pending.lock().len()
+    }
+
+    fn enqueue(
+        &self,
+        node_id: &NodeId,
+        value: DataValue,
+        index_range: &NumericRange,
+        waiter: Option<oneshot::Sender<StatusCode>>,
+    ) {
+        let mut pending = self.inner.pending.lock();
+        let writes = pending.entry(node_id.clone()).or_default();
+        // Replaced writes are removed, and the new write is applied last,
+        // so that overlapping writes to other ranges keep their order.
+        let mut waiters = Vec::new();
+        writes.retain_mut(|w| {
+            if matches!(index_range, NumericRange::None) || w.index_range == *index_range {
+                waiters.append(&mut w.waiters);
+                false
+            } else {
+                true
+            }
+        });
+        waiters.extend(waiter);
+        writes.push(PendingWrite {
+            value,
+            index_range: index_range.clone(),
+            waiters,
+        });
+    }
+}
+
+impl Drop for DebouncedWriter {
+    fn drop(&mut self) {
+        // Flush synchronously, a spawned flush may never run if the runtime is shutting down.
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(|| self.inner.flush())
+            }
+            _ => self.inner.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use opcua_core::sync::Mutex;
+    use opcua_types::{DataValue, NodeId, NumericRange, StatusCode, Variant};
+
+    use super::{DebounceAck, DebouncedWriter};
+
+    type Log = Arc<Mutex<Vec<(NodeId, Variant, NumericRange)>>>;
+
+    fn writer(ack: DebounceAck, interval: Duration) -> (DebouncedWriter, Log) {
+        let log: Log = Default::default();
+        let log_ref = log.clone();
+        let writer = DebouncedWriter::new(interval, ack, move |id, value, range| {
+            log_ref
+                .lock()
+                .push((id.clone(), value.value.unwrap_or_default(), range.clone()));
+            StatusCode::Good
+        });
+        (writer, log)
+    }
+
+    #[tokio::test]
+    async fn coalesces_writes() {
+        let (writer, log) = writer(DebounceAck::Optimistic, Duration::from_secs(3600));
+        let id = NodeId::new(1, 1);
+        for i in 0..5 {
+            let status = writer
+                .write(&id, DataValue::new_now(i), &NumericRange::None)
+                .await;
+            assert_eq!(status, StatusCode::Good);
+        }
+        writer
+            .write(
+                &NodeId::new(1, 2),
+                DataValue::new_now(7),
+                &NumericRange::None,
+            )
+            .await;
+        assert_eq!(writer.pending_count(), 2);
+        assert!(log.lock().is_empty());
+
+        writer.flush().await;
+        assert_eq!(writer.pending_count(), 0);
+        let mut log = log.lock().clone();
+        log.sort_by_key(|(id, _, _)| id.to_string());
+        assert_eq!(
+            log,
+            vec![
+                (id, Variant::Int32(4), NumericRange::None),
+                (NodeId::new(1, 2), Variant::Int32(7), NumericRange::None)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_writes_to_different_ranges() {
+        let (writer, log) = writer(DebounceAck::Optimistic, Duration::from_secs(3600));
+        let id = NodeId::new(1, 1);
+        writer
+            .write(&id, DataValue::new_now(1), &NumericRange::Index(0))
+            .await;
+        writer
+            .write(&id, DataValue::new_now(2), &NumericRange::Index(1))
+            .await;
+        // Replaces the first write, and is applied after the second.
+        writer
+            .write(&id, DataValue::new_now(3), &NumericRange::Index(0))
+            .await;
+        writer.flush().await;
+        assert_eq!(
+            *log.lock(),
+            vec![
+                (id.clone(), Variant::Int32(2), NumericRange::Index(1)),
+                (id.clone(), Variant::Int32(3), NumericRange::Index(0)),
+            ]
+        );
+
+        // A write to the whole value replaces writes to any range.
+        log.lock().clear();
+        writer
+            .write(&id, DataValue::new_now(4), &NumericRange::Index(0))
+            .await;
+        writer
+            .write(&id, DataValue::new_now(5), &NumericRange::None)
+            .await;
+        writer.flush().await;
+        assert_eq!(
+            *log.lock(),
+            vec![(id, Variant::Int32(5), NumericRange::None)]
+        );
+    }
+
+    #[test]
+    fn flush_on_drop() {
+        let (writer, log) = writer(DebounceAck::Optimistic, Duration::from_secs(3600));
+        futures::executor::block_on(writer.write(
+            &NodeId::new(1, 1),
+            DataValue::new_now(1),
+            &NumericRange::None,
+        ));
+        drop(writer);
+        assert_eq!(log.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn flush_on_drop_current_thread() {
+        let (writer, log) = writer(DebounceAck::Optimistic, Duration::from_secs(3600));
+        writer.run();
+        writer
+            .write(
+                &NodeId::new(1, 1),
+                DataValue::new_now(1),
+                &NumericRange::None,
+            )
+            .await;
+        drop(writer);
+        assert_eq!(log.lock().len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn flush_on_drop_multi_thread() {
+        let (writer, log) = writer(DebounceAck::Optimistic, Duration::from_secs(3600));
+        writer.run();
+        writer
+            .write(
+                &NodeId::new(1, 1),
+                DataValue::new_now(1),
+                &NumericRange::None,
+            )
+            .await;
+        drop(writer);
+        assert_eq!(log.lock().len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn flushes_do_not_overlap() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let active = Arc::new(AtomicBool::new(false));
+        let overlapped = Arc::new(AtomicBool::new(false));
+        let log: Log = Default::default();
+        let (active_ref, overlapped_ref, log_ref) =
+            (active.clone(), overlapped.clone(), log.clone());
+        let writer = Arc::new(DebouncedWriter::new(
+            Duration::from_secs(3600),
+            DebounceAck::Optimistic,
+            move |id, value, range| {
+                if active_ref.swap(true, Ordering::SeqCst) {
+                    overlapped_ref.store(true, Ordering::SeqCst);
+                }
+                std::thread::sleep(Duration::from_millis(20));
+                log_ref
+                    .lock()
+                    .push((id.clone(), value.value.unwrap_or_default(), range.clone()));
+                active_ref.store(false, Ordering::SeqCst);
+                StatusCode::Good
+            },
+        ));
+        let id = NodeId::new(1, 1);
+        let mut flushes = Vec::new();
+        for i in 0..3 {
+            writer
+                .write(&id, DataValue::new_now(i), &NumericRange::None)
+                .await;
+            let writer = writer.clone();
+            flushes.push(tokio::spawn(async move { writer.flush().await }));
+        }
+        for flush in flushes {
+            flush.await.unwrap();
+        }
+        assert!(!overlapped.load(Ordering::SeqCst));
+        // Batches are applied in the order the writes were made.
+        let values: Vec<_> = log.lock().iter().map(|(_, v, _)| v.clone()).collect();
+        assert_eq!(values.last(), Some(&Variant::Int32(2)));
+        assert!(values
+            .windows(2)
+            .all(|w| matches!((&w[0], &w[1]), (Variant::Int32(a), Variant::Int32(b)) if a < b)));
+    }
+}
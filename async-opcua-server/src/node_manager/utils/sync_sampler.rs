@@ -4,6 +4,7 @@ use std::{
     time::{Duration, Instant},
 };
 
+use tokio::runtime::Handle;
 use tokio_util::sync::{CancellationToken, DropGuard};
 
 use crate::{MonitoredItemHandle, SubscriptionCache};
@@ -73,9 +74,20 @@ impl SyncSampler {
     /// this is called in `build_nodes` or `init`. The sampler will automatically shut down
     /// once it is dropped.
     pub fn run(&self, interval: Duration, subscriptions: Arc<SubscriptionCache>) {
+        self.run_on(interval, subscriptions, &Handle::current());
+    }
+
+    /// Start the sampler on the given tokio runtime, typically the one returned by
+    /// `ServerInfo::sampler_runtime`. Otherwise this is the same as `run`.
+    pub fn run_on(
+        &self,
+        interval: Duration,
+        subscriptions: Arc<SubscriptionCache>,
+        runtime: &Handle,
+    ) {
         let token = self.token.clone();
         let samplers = self.samplers.clone();
-        tokio::spawn(async move {
+        runtime.spawn(async move {
             tokio::select! {
                 _ = Self::run_internal(samplers, interval, subscriptions) => {},
                 _ = token.cancelled() => {}
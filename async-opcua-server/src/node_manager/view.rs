@@ -10,9 +10,10 @@ use crate::{
 use opcua_crypto::random;
 use opcua_nodes::TypeTree;
 use opcua_types::{
-    BrowseDescription, BrowseDescriptionResultMask, BrowseDirection, BrowsePath, BrowseResult,
-    BrowseResultMask, ByteString, ExpandedNodeId, LocalizedText, NodeClass, NodeClassMask, NodeId,
-    QualifiedName, ReferenceDescription, RelativePathElement, StatusCode,
+    BinaryDecodable, BinaryEncodable, BrowseDescription, BrowseDescriptionResultMask,
+    BrowseDirection, BrowsePath, BrowseResult, BrowseResultMask, ByteString, Context, ContextOwned,
+    EncodingResult, ExpandedNodeId, LocalizedText, NodeClass, NodeClassMask, NodeId, QualifiedName,
+    ReferenceDescription, RelativePathElement, StatusCode,
 };
 use tracing::warn;
 
@@ -166,6 +167,10 @@ pub struct BrowseNode {
     /// reference. These are resolved after the initial browse, and any excess is stored
     /// in a continuation point.
     external_references: Vec<ExternalReference>,
+
+    /// Context used to hide references the user is not allowed to see, if the
+    /// server has a `NodeVisibilityFilter`.
+    visibility_context: Option<RequestContext>,
 }
 
 pub(crate) struct BrowseContinuationPoint {
@@ -184,6 +189,123 @@ pub(crate) struct BrowseContinuationPoint {
     external_references: Vec<ExternalReference>,
 }
 
+impl BrowseContinuationPoint {
+    /// Encode the continuation point, so that it can be serialized by the
+    /// continuation point store. Returns `None` if the continuation point of the
+    /// node manager is not encoded.
+    pub(crate) fn encode(&self) -> Option<ByteString> {
+        let inner = match self.continuation_point.as_bytes() {
+            Some(b) => Some(b),
+            None if self
+                .continuation_point
+                .get::<EmptyContinuationPoint>()
+                .is_some() =>
+            {
+                None
+            }
+            None => return None,
+        };
+        let ctx_owned = ContextOwned::default();
+        let mut stream = Vec::new();
+        match self.encode_inner(&mut stream, inner, &ctx_owned.context()) {
+            Ok(()) => Some(ByteString::from(stream)),
+            Err(e) => {
+                warn!("Failed to encode browse continuation point: {e}");
+                None
+            }
+        }
+    }
+
+    fn encode_inner(
+        &self,
+        stream: &mut Vec<u8>,
+        inner: Option<&ByteString>,
+        ctx: &Context<'_>,
+    ) -> EncodingResult<()> {
+        (self.node_manager_index as u32).encode(stream, ctx)?;
+        self.id.encode(stream, ctx)?;
+        self.node_id.encode(stream, ctx)?;
+        self.browse_direction.encode(stream, ctx)?;
+        self.reference_type_id.encode(stream, ctx)?;
+        self.include_subtypes.encode(stream, ctx)?;
+        self.node_class_mask.bits().encode(stream, ctx)?;
+        self.result_mask.bits().encode(stream, ctx)?;
+        (self.max_references_per_node as u32).encode(stream, ctx)?;
+        (self.external_references.len() as u32).encode(stream, ctx)?;
+        for r in &self.external_references {
+            r.target_id.encode(stream, ctx)?;
+            r.reference_type_id.encode(stream, ctx)?;
+            matches!(r.direction, ReferenceDirection::Forward).encode(stream, ctx)?;
+        }
+        match inner {
+            Some(b) => {
+                true.encode(stream, ctx)?;
+                b.encode(stream, ctx)
+            }
+            None => false.encode(stream, ctx),
+        }
+    }
+
+    /// Decode a continuation point encoded with [`BrowseContinuationPoint::encode`].
+    pub(crate) fn decode(bytes: &ByteString) -> Option<Self> {
+        let ctx_owned = ContextOwned::default();
+        match Self::decode_inner(&mut bytes.as_ref(), &ctx_owned.context()) {
+            Ok(p) => Some(p),
+            Err(e) => {
+                warn!("Failed to decode browse continuation point: {e}");
+                None
+            }
+        }
+    }
+
+    fn decode_inner(stream: &mut &[u8], ctx: &Context<'_>) -> EncodingResult<Self> {
+        let node_manager_index = u32::decode(stream, ctx)? as usize;
+        let id = ByteString::decode(stream, ctx)?;
+        let node_id = NodeId::decode(stream, ctx)?;
+        let browse_direction = BrowseDirection::decode(stream, ctx)?;
+        let reference_type_id = NodeId::decode(stream, ctx)?;
+        let include_subtypes = bool::decode(stream, ctx)?;
+        let node_class_mask = NodeClassMask::from_bits_truncate(u32::decode(stream, ctx)?);
+        let result_mask =
+            BrowseDescriptionResultMask::from_bits_truncate(u32::decode(stream, ctx)?);
+        let max_references_per_node = u32::decode(stream, ctx)? as usize;
+        let count = u32::decode(stream, ctx)?;
+        let mut external_references = Vec::new();
+        for _ in 0..count {
+            let target_id = ExpandedNodeId::decode(stream, ctx)?;
+            let reference_type_id = NodeId::decode(stream, ctx)?;
+            let direction = if bool::decode(stream, ctx)? {
+                ReferenceDirection::Forward
+            } else {
+                ReferenceDirection::Inverse
+            };
+            external_references.push(ExternalReference::new(
+                target_id,
+                reference_type_id,
+                direction,
+            ));
+        }
+        let continuation_point = if bool::decode(stream, ctx)? {
+            ContinuationPoint::from_bytes(ByteString::decode(stream, ctx)?)
+        } else {
+            ContinuationPoint::new(Box::new(EmptyContinuationPoint))
+        };
+        Ok(Self {
+            node_manager_index,
+            continuation_point,
+            id,
+            node_id,
+            browse_direction,
+            reference_type_id,
+            include_subtypes,
+            node_class_mask,
+            result_mask,
+            max_references_per_node,
+            external_references,
+        })
+    }
+}
+
 impl BrowseNode {
     /// Create a new empty browse node
     pub(crate) fn new(
@@ -206,6 +328,7 @@ impl BrowseNode {
             input_index,
             start_node_manager: 0,
             external_references: Vec::new(),
+            visibility_context: None,
         }
     }
 
@@ -228,6 +351,14 @@ impl BrowseNode {
             input_index,
             start_node_manager: point.node_manager_index,
             external_references: point.external_references,
+            visibility_context: None,
+        }
+    }
+
+    /// Hide references that are not visible to the user given by `context`.
+    pub(crate) fn set_visibility_context(&mut self, context: &RequestContext) {
+        if context.info.node_visibility_filter.is_some() {
+            self.visibility_context = Some(context.clone());
         }
     }
 
@@ -366,7 +497,14 @@ impl BrowseNode {
         }
 
         // Check the reference type filter.
-        self.allows_reference_type(&reference.reference_type_id, type_tree)
+        if !self.allows_reference_type(&reference.reference_type_id, type_tree) {
+            return false;
+        }
+
+        // Finally, hide references the user may not see.
+        self.visibility_context.as_ref().is_none_or(|c| {
+            c.is_reference_visible(&reference.reference_type_id, &reference.node_id)
+        })
     }
 
     /// Add a reference, validating that it matches the filters, and returning `Added` if it was added.
@@ -508,8 +646,8 @@ impl BrowseNode {
         // If we're out of continuation points, the correct response is to not store it, and
         // set the status code to BadNoContinuationPoints.
         if let Some(c) = continuation_point {
-            if session.add_browse_continuation_point(c).is_err() {
-                result.status_code = StatusCode::BadNoContinuationPoints;
+            if let Err(e) = session.add_browse_continuation_point(c) {
+                result.status_code = e;
                 result.continuation_point = ByteString::null();
             }
         }
@@ -882,3 +1020,88 @@ pub async fn impl_translate_browse_paths_using_browse(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use opcua_types::{
+        BrowseDescriptionResultMask, BrowseDirection, ByteString, ExpandedNodeId, NodeClassMask,
+        NodeId, ReferenceTypeId,
+    };
+
+    use super::{BrowseContinuationPoint, ExternalReference};
+    use crate::{
+        address_space::ReferenceDirection,
+        session::continuation_points::{ContinuationPoint, EmptyContinuationPoint},
+    };
+
+    fn continuation_point(inner: ContinuationPoint) -> BrowseContinuationPoint {
+        BrowseContinuationPoint {
+            node_manager_index: 2,
+            continuation_point: inner,
+            id: ByteString::from(vec![1, 2, 3]),
+            node_id: NodeId::new(1, "Root"),
+            browse_direction: BrowseDirection::Inverse,
+            reference_type_id: ReferenceTypeId::HierarchicalReferences.into(),
+            include_subtypes: true,
+            node_class_mask: NodeClassMask::OBJECT | NodeClassMask::VARIABLE,
+            result_mask: BrowseDescriptionResultMask::RESULT_MASK_BROWSE_NAME,
+            max_references_per_node: 10,
+            external_references: vec![ExternalReference::new(
+                ExpandedNodeId::new(NodeId::new(2, 5)),
+                ReferenceTypeId::Organizes.into(),
+                ReferenceDirection::Inverse,
+            )],
+        }
+    }
+
+    #[test]
+    fn encode_browse_continuation_point() {
+        let bytes = continuation_point(ContinuationPoint::from_bytes(ByteString::from(vec![4])))
+            .encode()
+            .unwrap();
+        let decoded = BrowseContinuationPoint::decode(&bytes).unwrap();
+        assert_eq!(decoded.node_manager_index, 2);
+        assert_eq!(decoded.id, ByteString::from(vec![1, 2, 3]));
+        assert_eq!(decoded.node_id, NodeId::new(1, "Root"));
+        assert_eq!(decoded.browse_direction, BrowseDirection::Inverse);
+        assert_eq!(
+            decoded.reference_type_id,
+            ReferenceTypeId::HierarchicalReferences
+        );
+        assert!(decoded.include_subtypes);
+        assert_eq!(
+            decoded.node_class_mask.bits(),
+            (NodeClassMask::OBJECT | NodeClassMask::VARIABLE).bits()
+        );
+        assert_eq!(
+            decoded.result_mask.bits(),
+            BrowseDescriptionResultMask::RESULT_MASK_BROWSE_NAME.bits()
+        );
+        assert_eq!(decoded.max_references_per_node, 10);
+        assert_eq!(decoded.external_references.len(), 1);
+        let r = &decoded.external_references[0];
+        assert_eq!(r.target_id, ExpandedNodeId::new(NodeId::new(2, 5)));
+        assert_eq!(r.reference_type_id, ReferenceTypeId::Organizes);
+        assert!(matches!(r.direction, ReferenceDirection::Inverse));
+        assert_eq!(
+            decoded.continuation_point.as_bytes(),
+            Some(&ByteString::from(vec![4]))
+        );
+
+        // Continuing in the next node manager.
+        let bytes = continuation_point(ContinuationPoint::new(Box::new(EmptyContinuationPoint)))
+            .encode()
+            .unwrap();
+        let decoded = BrowseContinuationPoint::decode(&bytes).unwrap();
+        assert!(decoded
+            .continuation_point
+            .get::<EmptyContinuationPoint>()
+            .is_some());
+
+        // Node manager continuation points that are not encoded cannot be encoded.
+        assert!(continuation_point(ContinuationPoint::new(Box::new(5u32)))
+            .encode()
+            .is_none());
+        assert!(BrowseContinuationPoint::decode(&ByteString::from(vec![1])).is_none());
+    }
+}
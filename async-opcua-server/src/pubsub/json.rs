@@ -0,0 +1,264 @@
+use std::io::{Cursor, Read, Write};
+
+use opcua_types::{
+    json::{
+        consume_raw_value, JsonDecodable, JsonEncodable, JsonReader, JsonStreamReader,
+        JsonStreamWriter, JsonWriter, ValueType,
+    },
+    Context, DataSetFieldContentMask, DataValue, DateTime, EncodingResult, Error, Guid, UAString,
+    Variant,
+};
+
+use super::{
+    message::{masked_value, publisher_id_string},
+    DataSetMessage, DataSetMetaDataMessage, NetworkMessage,
+};
+
+impl NetworkMessage {
+    /// Encode the network message using the PubSub JSON message mapping.
+    pub fn encode_json(&self, ctx: &Context<'_>) -> EncodingResult<Vec<u8>> {
+        let mut res = Vec::new();
+        let mut cursor = Cursor::new(&mut res);
+        let mut stream = JsonStreamWriter::new(&mut cursor as &mut dyn Write);
+        stream.begin_object()?;
+        stream.name("MessageId")?;
+        stream.string_value(&self.message_id)?;
+        stream.name("MessageType")?;
+        stream.string_value("ua-data")?;
+        stream.name("PublisherId")?;
+        stream.string_value(&self.publisher_id)?;
+        if !self.writer_group_name.is_empty() {
+            stream.name("WriterGroupName")?;
+            stream.string_value(&self.writer_group_name)?;
+        }
+        stream.name("Messages")?;
+        stream.begin_array()?;
+        for message in &self.messages {
+            message.encode_json(&mut stream, ctx)?;
+        }
+        stream.end_array()?;
+        stream.end_object()?;
+        stream.finish_document()?;
+        Ok(res)
+    }
+}
+
+impl DataSetMetaDataMessage {
+    /// Encode the metadata message using the PubSub JSON message mapping.
+    pub fn encode_json(&self, ctx: &Context<'_>) -> EncodingResult<Vec<u8>> {
+        let mut res = Vec::new();
+        let mut cursor = Cursor::new(&mut res);
+        let mut stream = JsonStreamWriter::new(&mut cursor as &mut dyn Write);
+        stream.begin_object()?;
+        stream.name("MessageId")?;
+        stream.string_value(&Guid::new().to_string())?;
+        stream.name("MessageType")?;
+        stream.string_value("ua-metadata")?;
+        stream.name("PublisherId")?;
+        stream.string_value(&publisher_id_string(&self.publisher_id))?;
+        stream.name("DataSetWriterId")?;
+        stream.number_value(self.data_set_writer_id)?;
+        stream.name("MetaData")?;
+        self.meta_data.encode(&mut stream, ctx)?;
+        stream.end_object()?;
+        stream.finish_document()?;
+        Ok(res)
+    }
+}
+
+impl DataSetMessage {
+    fn encode_json(
+        &self,
+        stream: &mut JsonStreamWriter<&mut dyn Write>,
+        ctx: &Context<'_>,
+    ) -> EncodingResult<()> {
+        stream.begin_object()?;
+        stream.name("DataSetWriterId")?;
+        stream.number_value(self.data_set_writer_id)?;
+        stream.name("SequenceNumber")?;
+        stream.number_value(self.sequence_number)?;
+        stream.name("Timestamp")?;
+        self.timestamp.encode(stream, ctx)?;
+        stream.name("MessageType")?;
+        stream.string_value("ua-keyframe")?;
+        stream.name("Payload")?;
+        stream.begin_object()?;
+        for (name, value) in &self.fields {
+            stream.name(name)?;
+            self.encode_field(value, stream, ctx)?;
+        }
+        stream.end_object()?;
+        stream.end_object()?;
+        Ok(())
+    }
+
+    fn encode_field(
+        &self,
+        value: &DataValue,
+        stream: &mut JsonStreamWriter<&mut dyn Write>,
+        ctx: &Context<'_>,
+    ) -> EncodingResult<()> {
+        let mask = self.field_content_mask;
+        if mask.is_empty() || mask.contains(DataSetFieldContentMask::RawData) {
+            return match &value.value {
+                Some(v) => v.encode(stream, ctx),
+                None => Ok(stream.null_value()?),
+            };
+        }
+
+        masked_value(value, mask).encode(stream, ctx)
+    }
+}
+
+/// A data set message where the fields have not been decoded yet, since the
+/// encoding of the fields depends on the configuration of the reader.
+pub(super) struct RawDataSetMessage {
+    pub(super) data_set_writer_id: u16,
+    pub(super) sequence_number: u32,
+    pub(super) timestamp: DateTime,
+    pub(super) fields: Vec<(String, Vec<u8>)>,
+}
+
+/// A decoded JSON network message, containing raw data set messages.
+pub(super) struct RawNetworkMessage {
+    pub(super) publisher_id: String,
+    pub(super) messages: Vec<RawDataSetMessage>,
+}
+
+fn decode_string(
+    stream: &mut JsonStreamReader<&mut dyn Read>,
+    ctx: &Context<'_>,
+) -> EncodingResult<String> {
+    match stream.peek()? {
+        ValueType::Number => Ok(stream.next_number_as_string()?),
+        _ => Ok(UAString::decode(stream, ctx)?.as_ref().to_owned()),
+    }
+}
+
+impl RawNetworkMessage {
+    /// Decode a network message using the PubSub JSON message mapping. This also accepts
+    /// a single data set message without a network message header.
+    ///
+    /// Messages with a `MessageType` other than `ua-data` produce no data set messages.
+    pub(super) fn decode_json(data: &[u8], ctx: &Context<'_>) -> EncodingResult<Self> {
+        let mut cursor = Cursor::new(data);
+        let mut stream = JsonStreamReader::new(&mut cursor as &mut dyn Read);
+
+        let mut publisher_id = String::new();
+        let mut message_type = None;
+        let mut messages = None;
+        stream.begin_object()?;
+        // Fields of a single data set message without a network message header.
+        let single = RawDataSetMessage::decode_fields(&mut stream, ctx, |name, stream| {
+            match name {
+                "PublisherId" => publisher_id = decode_string(stream, ctx)?,
+                "MessageType" => message_type = Some(decode_string(stream, ctx)?),
+                "Messages" => {
+                    let mut res = Vec::new();
+                    if stream.peek()? == ValueType::Array {
+                        stream.begin_array()?;
+                        while stream.has_next()? {
+                            res.push(RawDataSetMessage::decode_json(stream, ctx)?);
+                        }
+                        stream.end_array()?;
+                    } else {
+                        res.push(RawDataSetMessage::decode_json(stream, ctx)?);
+                    }
+                    messages = Some(res);
+                }
+                _ => stream.skip_value()?,
+            }
+            Ok(())
+        })?;
+        stream.end_object()?;
+
+        let messages = match (message_type.as_deref(), messages) {
+            (Some("ua-data") | None, Some(messages)) => messages,
+            (Some("ua-keyframe" | "ua-deltaframe") | None, None) => vec![single],
+            _ => Vec::new(),
+        };
+
+        Ok(Self {
+            publisher_id,
+            messages,
+        })
+    }
+}
+
+impl RawDataSetMessage {
+    fn decode_json(
+        stream: &mut JsonStreamReader<&mut dyn Read>,
+        ctx: &Context<'_>,
+    ) -> EncodingResult<Self> {
+        stream.begin_object()?;
+        let res = Self::decode_fields(stream, ctx, |_, stream| Ok(stream.skip_value()?))?;
+        stream.end_object()?;
+        Ok(res)
+    }
+
+    /// Decode the fields of a data set message from an object, calling `other` for any
+    /// fields that are not part of the data set message.
+    fn decode_fields(
+        stream: &mut JsonStreamReader<&mut dyn Read>,
+        ctx: &Context<'_>,
+        mut other: impl FnMut(&str, &mut JsonStreamReader<&mut dyn Read>) -> EncodingResult<()>,
+    ) -> EncodingResult<Self> {
+        let mut res = Self {
+            data_set_writer_id: 0,
+            sequence_number: 0,
+            timestamp: DateTime::null(),
+            fields: Vec::new(),
+        };
+        while stream.has_next()? {
+            let name = stream.next_name_owned()?;
+            match name.as_str() {
+                "DataSetWriterId" => res.data_set_writer_id = stream.next_number()??,
+                "SequenceNumber" => res.sequence_number = stream.next_number()??,
+                "Timestamp" => res.timestamp = DateTime::decode(stream, ctx)?,
+                "Payload" => {
+                    stream.begin_object()?;
+                    while stream.has_next()? {
+                        let name = stream.next_name_owned()?;
+                        res.fields.push((name, consume_raw_value(stream)?));
+                    }
+                    stream.end_object()?;
+                }
+                _ => other(&name, stream)?,
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// Decode a single field of a data set message, given the field content mask of the
+/// reader and the built in type of the field from the data set metadata.
+pub(super) fn decode_field(
+    raw: &[u8],
+    field_content_mask: DataSetFieldContentMask,
+    built_in_type: u8,
+    ctx: &Context<'_>,
+) -> EncodingResult<DataValue> {
+    if field_content_mask.contains(DataSetFieldContentMask::RawData) {
+        // Raw fields have no type information, so wrap them in a variant
+        // using the type from the metadata.
+        if built_in_type == 0 {
+            return Err(Error::decoding(
+                "Cannot decode raw field without a built in type in the data set metadata",
+            ));
+        }
+        let mut wrapped = format!("{{\"Type\":{built_in_type},\"Body\":").into_bytes();
+        wrapped.extend_from_slice(raw);
+        wrapped.push(b'}');
+        let mut cursor = Cursor::new(wrapped);
+        let mut stream = JsonStreamReader::new(&mut cursor as &mut dyn Read);
+        return Ok(DataValue::value_only(Variant::decode(&mut stream, ctx)?));
+    }
+
+    let mut cursor = Cursor::new(raw);
+    let mut stream = JsonStreamReader::new(&mut cursor as &mut dyn Read);
+    if field_content_mask.is_empty() {
+        Ok(DataValue::value_only(Variant::decode(&mut stream, ctx)?))
+    } else {
+        DataValue::decode(&mut stream, ctx)
+    }
+}
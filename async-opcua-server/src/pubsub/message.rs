@@ -0,0 +1,61 @@
+use opcua_types::{DataSetFieldContentMask, DataValue, DateTime, Variant};
+
+/// A single data set message, containing the fields of a published data set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataSetMessage {
+    /// ID of the data set writer that produced the message.
+    pub data_set_writer_id: u16,
+    /// Sequence number of the message, incremented for each message sent by the writer.
+    pub sequence_number: u32,
+    /// Time the data set was sampled.
+    pub timestamp: DateTime,
+    /// Which parts of each field value are included in the message. If this is empty,
+    /// fields are encoded as plain variants, otherwise as data values.
+    pub field_content_mask: DataSetFieldContentMask,
+    /// Fields of the data set, with their names.
+    pub fields: Vec<(String, DataValue)>,
+}
+
+/// A network message, containing data set messages from the writers in a single writer group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkMessage {
+    /// Unique ID of the message.
+    pub message_id: String,
+    /// ID of the publisher.
+    pub publisher_id: String,
+    /// Name of the writer group that produced the message.
+    pub writer_group_name: String,
+    /// Data set messages in this network message.
+    pub messages: Vec<DataSetMessage>,
+}
+
+/// Get the parts of `value` included by the field content mask `mask`.
+pub(super) fn masked_value(value: &DataValue, mask: DataSetFieldContentMask) -> DataValue {
+    DataValue {
+        value: value.value.clone(),
+        status: value
+            .status
+            .filter(|_| mask.contains(DataSetFieldContentMask::StatusCode)),
+        source_timestamp: value
+            .source_timestamp
+            .filter(|_| mask.contains(DataSetFieldContentMask::SourceTimestamp)),
+        source_picoseconds: value
+            .source_picoseconds
+            .filter(|_| mask.contains(DataSetFieldContentMask::SourcePicoSeconds)),
+        server_timestamp: value
+            .server_timestamp
+            .filter(|_| mask.contains(DataSetFieldContentMask::ServerTimestamp)),
+        server_picoseconds: value
+            .server_picoseconds
+            .filter(|_| mask.contains(DataSetFieldContentMask::ServerPicoSeconds)),
+    }
+}
+
+/// Get a publisher ID as a string, as used in the JSON message mapping.
+pub(super) fn publisher_id_string(id: &Variant) -> String {
+    match id {
+        Variant::String(s) => s.as_ref().to_owned(),
+        Variant::Empty => String::new(),
+        v => v.to_string(),
+    }
+}
@@ -0,0 +1,63 @@
+use opcua_types::{
+    ConfigurationVersionDataType, DataSetFieldFlags, DataSetMetaDataType, DateTime, FieldMetaData,
+    Guid, NodeId, Variant, VariantScalarTypeId, VersionTime,
+};
+
+/// Build the metadata of a data set named `name`, with one field for each
+/// `(name, data_type, value_rank)` in `fields`.
+///
+/// The built in type of each field is derived from its data type. Fields with a data type
+/// that is not a built in type, such as a structure or enumeration, get built in type 0,
+/// and should be updated if subscribers need to decode them using the raw data encoding.
+///
+/// Each field gets a new data set field ID, and the configuration version is set to
+/// the current time.
+pub fn data_set_meta_data<'a>(
+    name: &str,
+    fields: impl IntoIterator<Item = (&'a str, NodeId, i32)>,
+) -> DataSetMetaDataType {
+    let version = version_time_now();
+    DataSetMetaDataType {
+        name: name.into(),
+        fields: Some(
+            fields
+                .into_iter()
+                .map(|(name, data_type, value_rank)| FieldMetaData {
+                    name: name.into(),
+                    field_flags: DataSetFieldFlags::empty(),
+                    built_in_type: VariantScalarTypeId::try_from(&data_type)
+                        .map(|t| t as u8)
+                        .unwrap_or_default(),
+                    data_type,
+                    value_rank,
+                    data_set_field_id: Guid::new(),
+                    ..Default::default()
+                })
+                .collect(),
+        ),
+        configuration_version: ConfigurationVersionDataType {
+            major_version: version,
+            minor_version: version,
+        },
+        ..Default::default()
+    }
+}
+
+/// Get the current time as a `VersionTime`, the number of seconds since the start of 2000.
+fn version_time_now() -> VersionTime {
+    let elapsed = DateTime::now().as_chrono() - DateTime::ymd(2000, 1, 1).as_chrono();
+    elapsed.num_seconds().clamp(0, u32::MAX as i64) as VersionTime
+}
+
+/// A message describing the layout of the data sets published by a data set writer,
+/// so that subscribers can decode its data set messages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataSetMetaDataMessage {
+    /// ID of the publisher. For UADP messages this must be `Byte`, `UInt16`, `UInt32`,
+    /// `UInt64` or `String`, or `Empty` to omit the publisher ID.
+    pub publisher_id: Variant,
+    /// ID of the data set writer publishing the data set.
+    pub data_set_writer_id: u16,
+    /// Metadata of the data set.
+    pub meta_data: DataSetMetaDataType,
+}
@@ -0,0 +1,45 @@
+//! Support for OPC UA PubSub.
+//!
+//! This currently contains a publisher for network messages using the JSON or UADP
+//! message mapping, which periodically samples variables and sends the resulting
+//! data set messages through a pluggable [PubSubSink], such as the built in [UdpSink],
+//! and a subscriber, which receives network messages from a [PubSubSource] and decodes
+//! the data set messages in them. With the `pubsub-mqtt` feature, `MqttSink` and
+//! `MqttSource` publish to and receive from an MQTT broker.
+//!
+//! Published values are sampled from a [DataSetSource]. The implementation for
+//! `RwLock<AddressSpace>` reads the values stored in the address space directly,
+//! so values that a node manager produces when read, like read callbacks on the
+//! `SimpleNodeManager`, need a custom [DataSetSource].
+//!
+//! UADP messages can also be encoded and decoded directly using [UadpNetworkMessage].
+//! Signed and encrypted UADP messages are not supported.
+//!
+//! [SecurityKeyService] is a minimal Security Key Service, providing keys for secured
+//! PubSub through the `GetSecurityKeys` method.
+//!
+//! Both are configured using the standard `PubSubConnectionDataType` structure,
+//! and the publisher uses `PublishedDataSetDataType` to define the published data sets.
+
+mod json;
+mod message;
+mod metadata;
+#[cfg(feature = "pubsub-mqtt")]
+mod mqtt;
+mod publisher;
+mod sks;
+mod subscriber;
+mod transport;
+mod uadp;
+
+pub use message::{DataSetMessage, NetworkMessage};
+pub use metadata::{data_set_meta_data, DataSetMetaDataMessage};
+#[cfg(feature = "pubsub-mqtt")]
+pub use mqtt::{MqttSink, MqttSource};
+pub use publisher::PubSubPublisher;
+#[cfg(feature = "pubsub-mqtt")]
+pub use rumqttc::{MqttOptions, QoS};
+pub use sks::{SecurityKeyService, SecurityKeys, PUBSUB_AES128_CTR, PUBSUB_AES256_CTR};
+pub use subscriber::PubSubSubscriber;
+pub use transport::{DataSetSource, PubSubSink, PubSubSource, UdpSink, UdpSource};
+pub use uadp::{UadpNetworkMessage, UadpSecurityHeader};
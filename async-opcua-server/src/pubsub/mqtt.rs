@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use opcua_types::{
+    BrokerDataSetReaderTransportDataType, NetworkAddressUrlDataType, PubSubConnectionDataType,
+    StatusCode,
+};
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS};
+use tokio::{
+    sync::{mpsc, Mutex},
+    task::JoinHandle,
+};
+use tracing::{error, warn};
+use url::{Host, Url};
+
+use super::{PubSubSink, PubSubSource};
+
+const DEFAULT_MQTT_PORT: u16 = 1883;
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Get the broker options for the `mqtt://host[:port]` address configured on the
+/// given connection, using the connection name as client ID.
+fn connection_options(connection: &PubSubConnectionDataType) -> Result<MqttOptions, StatusCode> {
+    let Some(address) = connection.address.inner_as::<NetworkAddressUrlDataType>() else {
+        return Err(StatusCode::BadConfigurationError);
+    };
+    let url = match Url::parse(address.url.as_ref()) {
+        Ok(url) if url.scheme() == "mqtt" => url,
+        _ => {
+            error!("Unsupported PubSub connection address {}", address.url);
+            return Err(StatusCode::BadConfigurationError);
+        }
+    };
+    // IPv6 hosts are bracketed in the URL, but not in the broker address.
+    let host = match url.host() {
+        Some(Host::Domain(host)) => host.to_owned(),
+        Some(Host::Ipv4(addr)) => addr.to_string(),
+        Some(Host::Ipv6(addr)) => addr.to_string(),
+        None => {
+            error!("Missing host in PubSub connection address {}", address.url);
+            return Err(StatusCode::BadConfigurationError);
+        }
+    };
+    let port = url.port().unwrap_or(DEFAULT_MQTT_PORT);
+    Ok(MqttOptions::new(connection.name.as_ref(), host, port))
+}
+
+/// Poll the MQTT event loop, which connects to the broker and reconnects after errors,
+/// passing the payload of received messages to `messages`.
+///
+/// Topics in `subscribe` are subscribed to each time the client connects, since
+/// the broker may not keep subscriptions between connections.
+async fn run_event_loop(
+    mut event_loop: EventLoop,
+    client: AsyncClient,
+    subscribe: Vec<String>,
+    messages: Option<mpsc::Sender<Vec<u8>>>,
+) {
+    let mut delay = MIN_RECONNECT_DELAY;
+    loop {
+        match event_loop.poll().await {
+            Ok(event) => {
+                delay = MIN_RECONNECT_DELAY;
+                match event {
+                    Event::Incoming(Packet::ConnAck(_)) => {
+                        for topic in &subscribe {
+                            if let Err(e) = client.try_subscribe(topic, QoS::AtLeastOnce) {
+                                warn!("Failed to subscribe to MQTT topic {topic}: {e}");
+                            }
+                        }
+                    }
+                    Event::Incoming(Packet::Publish(publish)) => {
+                        let Some(messages) = &messages else {
+                            continue;
+                        };
+                        if messages.send(publish.payload.to_vec()).await.is_err() {
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            Err(e) => {
+                warn!(
+                    "MQTT connection error: {e}, reconnecting in {}ms",
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        }
+    }
+}
+
+/// PubSub sink publishing each network message to an MQTT broker, on the topic
+/// given by the queue name of the writer group or data set writer.
+pub struct MqttSink {
+    client: AsyncClient,
+    qos: QoS,
+    event_loop: JoinHandle<()>,
+}
+
+impl MqttSink {
+    /// Create a new MQTT sink connecting to the broker with the given options.
+    ///
+    /// This spawns a task driving the connection, which stops when the sink is dropped.
+    pub fn new(options: MqttOptions) -> Self {
+        let (client, event_loop) = AsyncClient::new(options, 10);
+        let event_loop = tokio::spawn(run_event_loop(event_loop, client.clone(), Vec::new(), None));
+        Self {
+            client,
+            qos: QoS::AtLeastOnce,
+            event_loop,
+        }
+    }
+
+    /// Create a new MQTT sink connecting to the `mqtt://host[:port]` address
+    /// configured on the given connection.
+    pub fn from_connection(connection: &PubSubConnectionDataType) -> Result<Self, StatusCode> {
+        Ok(Self::new(connection_options(connection)?))
+    }
+
+    /// Set the quality of service used when publishing messages.
+    /// The default is `AtLeastOnce`.
+    pub fn with_qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+}
+
+impl Drop for MqttSink {
+    fn drop(&mut self) {
+        self.event_loop.abort();
+    }
+}
+
+#[async_trait]
+impl PubSubSink for MqttSink {
+    async fn send(&self, queue_name: Option<&str>, payload: Vec<u8>) -> Result<(), StatusCode> {
+        let Some(topic) = queue_name else {
+            error!("Cannot publish to an MQTT broker without a queue name");
+            return Err(StatusCode::BadConfigurationError);
+        };
+        self.client
+            .publish(topic, self.qos, false, payload)
+            .await
+            .map_err(|_| StatusCode::BadCommunicationError)
+    }
+}
+
+/// PubSub source receiving network messages from an MQTT broker.
+pub struct MqttSource {
+    messages: Mutex<mpsc::Receiver<Vec<u8>>>,
+    event_loop: JoinHandle<()>,
+}
+
+impl MqttSource {
+    /// Create a new MQTT source connecting to the broker with the given options,
+    /// and subscribing to `topics`.
+    ///
+    /// This spawns a task driving the connection, which stops when the source is dropped.
+    pub fn new(options: MqttOptions, topics: Vec<String>) -> Self {
+        let (client, event_loop) = AsyncClient::new(options, 10);
+        let (send, recv) = mpsc::channel(100);
+        let event_loop = tokio::spawn(run_event_loop(event_loop, client, topics, Some(send)));
+        Self {
+            messages: Mutex::new(recv),
+            event_loop,
+        }
+    }
+
+    /// Create a new MQTT source connecting to the `mqtt://host[:port]` address
+    /// configured on the given connection, subscribing to the queue names
+    /// of the enabled data set readers with broker transport settings.
+    pub fn from_connection(connection: &PubSubConnectionDataType) -> Result<Self, StatusCode> {
+        let mut topics: Vec<String> = connection
+            .reader_groups
+            .iter()
+            .flatten()
+            .filter(|g| g.enabled)
+            .flat_map(|g| g.data_set_readers.iter().flatten())
+            .filter(|r| r.enabled)
+            .filter_map(|r| {
+                r.transport_settings
+                    .inner_as::<BrokerDataSetReaderTransportDataType>()
+                    .filter(|t| !t.queue_name.is_empty())
+                    .map(|t| t.queue_name.as_ref().to_owned())
+            })
+            .collect();
+        topics.sort();
+        topics.dedup();
+        if topics.is_empty() {
+            error!(
+                "PubSub connection {} has no data set readers with a queue name",
+                connection.name
+            );
+            return Err(StatusCode::BadConfigurationError);
+        }
+        Ok(Self::new(connection_options(connection)?, topics))
+    }
+}
+
+impl Drop for MqttSource {
+    fn drop(&mut self) {
+        self.event_loop.abort();
+    }
+}
+
+#[async_trait]
+impl PubSubSource for MqttSource {
+    async fn recv(&self) -> Result<Vec<u8>, StatusCode> {
+        self.messages
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(StatusCode::BadCommunicationError)
+    }
+}
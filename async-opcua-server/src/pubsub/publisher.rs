@@ -0,0 +1,347 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::future::join_all;
+use opcua_types::{
+    BrokerDataSetWriterTransportDataType, BrokerWriterGroupTransportDataType, Context,
+    ContextOwned, DataSetFieldContentMask, DataSetMetaDataType, DateTime, EncodingResult, Guid,
+    PubSubConnectionDataType, PublishedDataItemsDataType, PublishedDataSetDataType,
+    PublishedVariableDataType, StatusCode, UadpWriterGroupMessageDataType, Variant,
+    WriterGroupDataType,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, warn};
+
+use super::{
+    message::publisher_id_string, DataSetMessage, DataSetMetaDataMessage, DataSetSource,
+    NetworkMessage, PubSubSink, UadpNetworkMessage,
+};
+
+struct DataSetWriter {
+    id: u16,
+    field_content_mask: DataSetFieldContentMask,
+    field_names: Vec<String>,
+    variables: Vec<PublishedVariableDataType>,
+    sequence_number: u32,
+    meta_data: DataSetMetaDataType,
+    meta_data_queue: Option<String>,
+    meta_data_interval: Option<Duration>,
+    meta_data_sent: Option<Instant>,
+}
+
+struct WriterGroup {
+    name: String,
+    writer_group_id: u16,
+    uadp: bool,
+    sequence_number: u16,
+    discovery_sequence_number: u16,
+    queue_name: Option<String>,
+    publishing_interval: Duration,
+    writers: Vec<DataSetWriter>,
+}
+
+/// Publisher for PubSub messages using the JSON or UADP message mapping.
+///
+/// Each enabled writer group in the connection publishes a network message at its
+/// publishing interval, containing one data set message for each of its enabled writers.
+/// Writer groups with `UadpWriterGroupMessageDataType` message settings use the UADP
+/// message mapping, all others use the JSON message mapping.
+///
+/// Writers with `BrokerDataSetWriterTransportDataType` transport settings and a
+/// `meta_data_queue_name` also publish the metadata of their data set to that queue,
+/// when the publisher starts and then every `meta_data_update_time` milliseconds,
+/// if it is positive.
+pub struct PubSubPublisher {
+    publisher_id: Variant,
+    groups: Vec<WriterGroup>,
+    source: Arc<dyn DataSetSource>,
+    sink: Arc<dyn PubSubSink>,
+    context: ContextOwned,
+}
+
+impl PubSubPublisher {
+    /// Create a new publisher for `connection`, publishing the data sets in `data_sets`,
+    /// referenced by name from the data set writers in the connection.
+    ///
+    /// Data sets must have a `PublishedDataItemsDataType` source, and the name of each
+    /// field is taken from the data set metadata.
+    pub fn new(
+        connection: &PubSubConnectionDataType,
+        data_sets: &[PublishedDataSetDataType],
+        source: Arc<dyn DataSetSource>,
+        sink: Arc<dyn PubSubSink>,
+    ) -> Result<Self, StatusCode> {
+        let groups = connection
+            .writer_groups
+            .iter()
+            .flatten()
+            .filter(|g| g.enabled)
+            .map(|g| Self::writer_group(g, data_sets))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            publisher_id: connection.publisher_id.clone(),
+            groups,
+            source,
+            sink,
+            context: ContextOwned::default(),
+        })
+    }
+
+    /// Set the encoding context used when encoding messages. This should contain
+    /// the namespaces of the server, if any published values contain node IDs.
+    pub fn with_encoding_context(mut self, context: ContextOwned) -> Self {
+        self.context = context;
+        self
+    }
+
+    fn writer_group(
+        group: &WriterGroupDataType,
+        data_sets: &[PublishedDataSetDataType],
+    ) -> Result<WriterGroup, StatusCode> {
+        if group.publishing_interval <= 0.0 {
+            error!(
+                "Writer group {} has invalid publishing interval {}",
+                group.name, group.publishing_interval
+            );
+            return Err(StatusCode::BadConfigurationError);
+        }
+
+        let writers = group
+            .data_set_writers
+            .iter()
+            .flatten()
+            .filter(|w| w.enabled)
+            .map(|w| {
+                let Some(data_set) = data_sets.iter().find(|d| d.name == w.data_set_name) else {
+                    error!(
+                        "Data set writer {} references unknown data set {}",
+                        w.name, w.data_set_name
+                    );
+                    return Err(StatusCode::BadConfigurationError);
+                };
+                let Some(items) = data_set
+                    .data_set_source
+                    .inner_as::<PublishedDataItemsDataType>()
+                else {
+                    error!(
+                        "Data set {} does not have a published data items source",
+                        data_set.name
+                    );
+                    return Err(StatusCode::BadConfigurationError);
+                };
+                let variables = items.published_data.clone().unwrap_or_default();
+                let fields = data_set.data_set_meta_data.fields.as_deref().unwrap_or(&[]);
+                if fields.len() != variables.len() {
+                    error!(
+                        "Data set {} has {} fields in its metadata, but {} published variables",
+                        data_set.name,
+                        fields.len(),
+                        variables.len()
+                    );
+                    return Err(StatusCode::BadConfigurationError);
+                }
+
+                let transport = w
+                    .transport_settings
+                    .inner_as::<BrokerDataSetWriterTransportDataType>();
+
+                Ok(DataSetWriter {
+                    id: w.data_set_writer_id,
+                    field_content_mask: w.data_set_field_content_mask,
+                    field_names: fields.iter().map(|f| f.name.as_ref().to_owned()).collect(),
+                    variables,
+                    sequence_number: 0,
+                    meta_data: data_set.data_set_meta_data.clone(),
+                    meta_data_queue: transport
+                        .filter(|t| !t.meta_data_queue_name.is_null())
+                        .map(|t| t.meta_data_queue_name.as_ref().to_owned()),
+                    meta_data_interval: transport
+                        .filter(|t| t.meta_data_update_time > 0.0)
+                        .map(|t| Duration::from_secs_f64(t.meta_data_update_time / 1000.0)),
+                    meta_data_sent: None,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let queue_name = group
+            .transport_settings
+            .inner_as::<BrokerWriterGroupTransportDataType>()
+            .filter(|t| !t.queue_name.is_null())
+            .map(|t| t.queue_name.as_ref().to_owned());
+
+        Ok(WriterGroup {
+            name: group.name.as_ref().to_owned(),
+            writer_group_id: group.writer_group_id,
+            uadp: group
+                .message_settings
+                .inner_as::<UadpWriterGroupMessageDataType>()
+                .is_some(),
+            sequence_number: 0,
+            discovery_sequence_number: 0,
+            queue_name,
+            publishing_interval: Duration::from_secs_f64(group.publishing_interval / 1000.0),
+            writers,
+        })
+    }
+
+    /// Run the publisher until `token` is cancelled.
+    pub async fn run(self, token: CancellationToken) {
+        let Self {
+            publisher_id,
+            groups,
+            source,
+            sink,
+            context,
+        } = self;
+
+        join_all(groups.into_iter().map(|group| {
+            group.run(
+                &publisher_id,
+                source.as_ref(),
+                sink.as_ref(),
+                &context,
+                &token,
+            )
+        }))
+        .await;
+    }
+}
+
+impl WriterGroup {
+    async fn run(
+        mut self,
+        publisher_id: &Variant,
+        source: &dyn DataSetSource,
+        sink: &dyn PubSubSink,
+        context: &ContextOwned,
+        token: &CancellationToken,
+    ) {
+        let mut interval = tokio::time::interval(self.publishing_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = token.cancelled() => break,
+            }
+
+            self.send_meta_data(publisher_id, sink, &context.context())
+                .await;
+
+            let payload = match self.encode(publisher_id, source, &context.context()) {
+                Ok(p) => p,
+                Err(e) => {
+                    error!(
+                        "Failed to encode network message for writer group {}: {e}",
+                        self.name
+                    );
+                    continue;
+                }
+            };
+            if let Err(e) = sink.send(self.queue_name.as_deref(), payload).await {
+                warn!(
+                    "Failed to send network message for writer group {}: {e}",
+                    self.name
+                );
+            }
+        }
+    }
+
+    async fn send_meta_data(
+        &mut self,
+        publisher_id: &Variant,
+        sink: &dyn PubSubSink,
+        ctx: &Context<'_>,
+    ) {
+        let now = Instant::now();
+        for writer in &mut self.writers {
+            let Some(queue) = writer.meta_data_queue.as_deref() else {
+                continue;
+            };
+            let due = match (writer.meta_data_sent, writer.meta_data_interval) {
+                (None, _) => true,
+                (Some(sent), Some(interval)) => now.duration_since(sent) >= interval,
+                (Some(_), None) => false,
+            };
+            if !due {
+                continue;
+            }
+            writer.meta_data_sent = Some(now);
+
+            let message = DataSetMetaDataMessage {
+                publisher_id: publisher_id.clone(),
+                data_set_writer_id: writer.id,
+                meta_data: writer.meta_data.clone(),
+            };
+            let payload = if self.uadp {
+                self.discovery_sequence_number = self.discovery_sequence_number.wrapping_add(1);
+                message.encode_uadp(self.discovery_sequence_number, ctx)
+            } else {
+                message.encode_json(ctx)
+            };
+            let payload = match payload {
+                Ok(p) => p,
+                Err(e) => {
+                    error!(
+                        "Failed to encode metadata for data set writer {}: {e}",
+                        writer.id
+                    );
+                    continue;
+                }
+            };
+            if let Err(e) = sink.send(Some(queue), payload).await {
+                warn!(
+                    "Failed to send metadata for data set writer {}: {e}",
+                    writer.id
+                );
+            }
+        }
+    }
+
+    fn encode(
+        &mut self,
+        publisher_id: &Variant,
+        source: &dyn DataSetSource,
+        ctx: &Context<'_>,
+    ) -> EncodingResult<Vec<u8>> {
+        let messages = self.sample(source);
+        if self.uadp {
+            self.sequence_number = self.sequence_number.wrapping_add(1);
+            UadpNetworkMessage {
+                publisher_id: publisher_id.clone(),
+                writer_group_id: Some(self.writer_group_id),
+                sequence_number: Some(self.sequence_number),
+                messages,
+                ..Default::default()
+            }
+            .encode(ctx)
+        } else {
+            NetworkMessage {
+                message_id: Guid::new().to_string(),
+                publisher_id: publisher_id_string(publisher_id),
+                writer_group_name: self.name.clone(),
+                messages,
+            }
+            .encode_json(ctx)
+        }
+    }
+
+    fn sample(&mut self, source: &dyn DataSetSource) -> Vec<DataSetMessage> {
+        self.writers
+            .iter_mut()
+            .map(|w| {
+                w.sequence_number = w.sequence_number.wrapping_add(1);
+                let values = source.sample(&w.variables);
+                DataSetMessage {
+                    data_set_writer_id: w.id,
+                    sequence_number: w.sequence_number,
+                    timestamp: DateTime::now(),
+                    field_content_mask: w.field_content_mask,
+                    fields: w.field_names.iter().cloned().zip(values).collect(),
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use hashbrown::HashMap;
+use opcua_core::sync::Mutex;
+use opcua_crypto::random;
+use opcua_types::{ByteString, NodeId, SecurityGroupDataType, StatusCode, Variant};
+use tracing::error;
+
+/// URI of the `PubSub-Aes128-CTR` security policy.
+pub const PUBSUB_AES128_CTR: &str = "http://opcfoundation.org/UA/SecurityPolicy#PubSub-Aes128-CTR";
+/// URI of the `PubSub-Aes256-CTR` security policy.
+pub const PUBSUB_AES256_CTR: &str = "http://opcfoundation.org/UA/SecurityPolicy#PubSub-Aes256-CTR";
+
+/// Length of the key data for a PubSub security policy, consisting of
+/// the signing key, the encrypting key, and the key nonce.
+fn key_data_length(security_policy_uri: &str) -> Option<usize> {
+    match security_policy_uri {
+        PUBSUB_AES128_CTR => Some(32 + 16 + 4),
+        PUBSUB_AES256_CTR => Some(32 + 32 + 4),
+        _ => None,
+    }
+}
+
+/// Result of a call to `GetSecurityKeys`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityKeys {
+    /// URI of the security policy the keys are used with.
+    pub security_policy_uri: String,
+    /// Token ID of the first key in `keys`.
+    pub first_token_id: u32,
+    /// Keys, starting with the key for `first_token_id`, with
+    /// each following key having the next token ID.
+    pub keys: Vec<ByteString>,
+    /// Time until the current key expires and the next key becomes current.
+    pub time_to_next_key: Duration,
+    /// Lifetime of each key.
+    pub key_lifetime: Duration,
+}
+
+impl SecurityKeys {
+    /// Get the output arguments of the `GetSecurityKeys` method.
+    pub fn into_outputs(self) -> Vec<Variant> {
+        vec![
+            self.security_policy_uri.into(),
+            self.first_token_id.into(),
+            self.keys.into(),
+            (self.time_to_next_key.as_secs_f64() * 1000.0).into(),
+            (self.key_lifetime.as_secs_f64() * 1000.0).into(),
+        ]
+    }
+}
+
+struct SecurityKey {
+    token_id: u32,
+    key: ByteString,
+}
+
+struct SecurityGroup {
+    node_id: NodeId,
+    config: SecurityGroupDataType,
+    key_lifetime: Duration,
+    key_length: usize,
+    /// Past keys, the current key, and future keys, in token ID order.
+    keys: VecDeque<SecurityKey>,
+    /// Index of the current key in `keys`.
+    current: usize,
+    /// Time the current key became current.
+    current_start: Instant,
+    next_token_id: u32,
+}
+
+impl SecurityGroup {
+    fn new(node_id: NodeId, config: SecurityGroupDataType) -> Result<Self, StatusCode> {
+        let Some(key_length) = key_data_length(config.security_policy_uri.as_ref()) else {
+            error!(
+                "Security group {} has unsupported security policy {}",
+                config.security_group_id, config.security_policy_uri
+            );
+            return Err(StatusCode::BadSecurityPolicyRejected);
+        };
+        // Rejects NaN, infinite, negative, and too small lifetimes.
+        let Some(key_lifetime) = Duration::try_from_secs_f64(config.key_lifetime / 1000.0)
+            .ok()
+            .filter(|d| !d.is_zero())
+        else {
+            error!(
+                "Security group {} has invalid key lifetime {}",
+                config.security_group_id, config.key_lifetime
+            );
+            return Err(StatusCode::BadConfigurationError);
+        };
+
+        let mut group = Self {
+            node_id,
+            key_lifetime,
+            config,
+            key_length,
+            keys: VecDeque::new(),
+            current: 0,
+            current_start: Instant::now(),
+            next_token_id: 1,
+        };
+        group.push_key();
+        group.fill_future_keys();
+        Ok(group)
+    }
+
+    fn push_key(&mut self) {
+        let token_id = self.next_token_id;
+        // Token ID 0 is reserved, so skip it when wrapping around.
+        self.next_token_id = self.next_token_id.checked_add(1).unwrap_or(1);
+        self.keys.push_back(SecurityKey {
+            token_id,
+            key: random::byte_string(self.key_length),
+        });
+    }
+
+    fn fill_future_keys(&mut self) {
+        while self.keys.len() - self.current - 1 < self.config.max_future_key_count as usize {
+            self.push_key();
+        }
+    }
+
+    /// Skip `count` token IDs without creating keys for them.
+    fn skip_token_ids(&mut self, count: u128) {
+        // Token IDs run from 1 to u32::MAX, since 0 is reserved.
+        let space = u32::MAX as u128;
+        let next = (self.next_token_id as u128 - 1 + count % space) % space + 1;
+        self.next_token_id = next as u32;
+    }
+
+    /// Make the next key current for each key lifetime that has passed since the
+    /// current key became current, creating new future keys and discarding old past keys.
+    ///
+    /// Keys that would have expired before ever being returned are skipped, so at most
+    /// `max_past_key_count + 1 + max_future_key_count` keys are created, no matter
+    /// how much time has passed.
+    fn rotate(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.current_start);
+        let lifetime = self.key_lifetime.as_nanos();
+        let periods = elapsed.as_nanos() / lifetime;
+        if periods == 0 {
+            return;
+        }
+        // The remainder is less than the elapsed time, so it fits in a u64.
+        self.current_start = now - Duration::from_nanos((elapsed.as_nanos() % lifetime) as u64);
+
+        let max_past = self.config.max_past_key_count as usize;
+        let target = self.current as u128 + periods;
+        let len = self.keys.len() as u128;
+        if target < len {
+            self.current = target as usize;
+        } else {
+            // Keys from the end of `keys` up to and including the new current key
+            // are missing. Only the last of these are kept as past keys.
+            let missing = target - len + 1;
+            let created = missing.min(max_past as u128 + 1);
+            self.skip_token_ids(missing - created);
+            for _ in 0..created {
+                self.push_key();
+            }
+            self.current = self.keys.len() - 1;
+        }
+        while self.current > max_past {
+            self.keys.pop_front();
+            self.current -= 1;
+        }
+        self.fill_future_keys();
+    }
+
+    fn get_keys(&mut self, starting_token_id: u32, requested_key_count: u32) -> SecurityKeys {
+        let now = Instant::now();
+        self.rotate(now);
+
+        // If the starting token is zero or unknown, start with the current key.
+        let start = self
+            .keys
+            .iter()
+            .position(|k| k.token_id == starting_token_id && starting_token_id != 0)
+            .unwrap_or(self.current);
+        let available = self.keys.len() - start;
+        let count = if requested_key_count == 0 {
+            available
+        } else {
+            available.min(requested_key_count as usize)
+        };
+
+        SecurityKeys {
+            security_policy_uri: self.config.security_policy_uri.as_ref().to_owned(),
+            first_token_id: self.keys[start].token_id,
+            keys: self
+                .keys
+                .iter()
+                .skip(start)
+                .take(count)
+                .map(|k| k.key.clone())
+                .collect(),
+            time_to_next_key: self
+                .key_lifetime
+                .saturating_sub(now.duration_since(self.current_start)),
+            key_lifetime: self.key_lifetime,
+        }
+    }
+}
+
+/// A minimal Security Key Service, managing the keys of a set of security groups
+/// and implementing the `GetSecurityKeys` and `GetSecurityGroup` methods on the
+/// `PublishSubscribe` object.
+///
+/// Keys are rotated lazily when requested, according to the `key_lifetime` of the group.
+/// Each group keeps `max_future_key_count` keys after the current key, and up to
+/// `max_past_key_count` keys before it.
+///
+/// `GetSecurityKeys` is only allowed over encrypted channels, for users with
+/// [admin](crate::authenticator::CoreServerPermissions::admin) permissions.
+///
+/// Register the service with the server using
+/// [ServerBuilder::with_security_key_service](crate::ServerBuilder::with_security_key_service).
+/// The service does not create nodes for the security groups, so if the groups should be
+/// visible in the address space they must be added separately.
+#[derive(Default)]
+pub struct SecurityKeyService {
+    groups: Mutex<HashMap<String, SecurityGroup>>,
+}
+
+impl SecurityKeyService {
+    /// Create a new security key service with no security groups.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a security group, replacing any existing group with the same `security_group_id`.
+    /// `node_id` is the node ID of the `SecurityGroupType` object representing the
+    /// group, returned from `GetSecurityGroup`.
+    ///
+    /// Returns `BadSecurityPolicyRejected` if the security policy of the group
+    /// is not a supported PubSub security policy, and `BadConfigurationError`
+    /// if the key lifetime is not a positive, finite number of milliseconds.
+    pub fn add_security_group(
+        &self,
+        node_id: NodeId,
+        group: SecurityGroupDataType,
+    ) -> Result<(), StatusCode> {
+        let id = group.security_group_id.as_ref().to_owned();
+        let group = SecurityGroup::new(node_id, group)?;
+        self.groups.lock().insert(id, group);
+        Ok(())
+    }
+
+    /// Remove the security group with ID `security_group_id`, returning whether it existed.
+    pub fn remove_security_group(&self, security_group_id: &str) -> bool {
+        self.groups.lock().remove(security_group_id).is_some()
+    }
+
+    /// Get the keys for a security group, starting with `starting_token_id`,
+    /// or the current key if the token ID is zero or no longer available.
+    /// At most `requested_key_count` keys are returned, or all available keys
+    /// if it is zero.
+    pub fn get_security_keys(
+        &self,
+        security_group_id: &str,
+        starting_token_id: u32,
+        requested_key_count: u32,
+    ) -> Result<SecurityKeys, StatusCode> {
+        let mut groups = self.groups.lock();
+        let group = groups
+            .get_mut(security_group_id)
+            .ok_or(StatusCode::BadNotFound)?;
+        Ok(group.get_keys(starting_token_id, requested_key_count))
+    }
+
+    /// Get the node ID of the security group with ID `security_group_id`.
+    pub fn get_security_group(&self, security_group_id: &str) -> Result<NodeId, StatusCode> {
+        self.groups
+            .lock()
+            .get(security_group_id)
+            .map(|g| g.node_id.clone())
+            .ok_or(StatusCode::BadNotFound)
+    }
+}
@@ -0,0 +1,214 @@
+use std::sync::Arc;
+
+use opcua_types::{
+    ContextOwned, DataSetFieldContentMask, DataValue, FieldMetaData, PubSubConnectionDataType,
+    StatusCode, Variant,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+use super::{
+    json::{decode_field, RawDataSetMessage, RawNetworkMessage},
+    message::publisher_id_string,
+    DataSetMessage, PubSubSource, UadpNetworkMessage,
+};
+
+struct DataSetReader {
+    name: String,
+    publisher_id: Option<String>,
+    data_set_writer_id: u16,
+    field_content_mask: DataSetFieldContentMask,
+    fields: Vec<FieldMetaData>,
+}
+
+impl DataSetReader {
+    fn matches(&self, publisher_id: &str, data_set_writer_id: u16) -> bool {
+        self.publisher_id.as_ref().is_none_or(|p| p == publisher_id)
+            && (self.data_set_writer_id == 0 || self.data_set_writer_id == data_set_writer_id)
+    }
+
+    fn decode(&self, message: &RawDataSetMessage, context: &ContextOwned) -> DataSetMessage {
+        let decode = |raw: &[u8], built_in_type: u8| {
+            decode_field(
+                raw,
+                self.field_content_mask,
+                built_in_type,
+                &context.context(),
+            )
+            .unwrap_or_else(|e| {
+                warn!(
+                    "Failed to decode field for data set reader {}: {e}",
+                    self.name
+                );
+                DataValue {
+                    status: Some(StatusCode::BadDecodingError),
+                    ..Default::default()
+                }
+            })
+        };
+
+        let fields = if self.fields.is_empty() {
+            message
+                .fields
+                .iter()
+                .map(|(name, raw)| (name.clone(), decode(raw, 0)))
+                .collect()
+        } else {
+            // Use the order and types of the fields in the metadata, ignoring unknown fields.
+            self.fields
+                .iter()
+                .filter_map(|f| {
+                    let (name, raw) = message.fields.iter().find(|(n, _)| n == f.name.as_ref())?;
+                    Some((name.clone(), decode(raw, f.built_in_type)))
+                })
+                .collect()
+        };
+
+        DataSetMessage {
+            data_set_writer_id: message.data_set_writer_id,
+            sequence_number: message.sequence_number,
+            timestamp: message.timestamp,
+            field_content_mask: self.field_content_mask,
+            fields,
+        }
+    }
+}
+
+/// Subscriber for PubSub messages using the JSON or UADP message mapping.
+///
+/// Received data set messages are matched against the enabled data set readers
+/// in the connection, by publisher ID and data set writer ID, and decoded using
+/// the field content mask and metadata of the reader. For UADP messages the field
+/// encoding is given by the message itself, and the metadata is only used to name
+/// the fields and decode raw fields.
+pub struct PubSubSubscriber {
+    readers: Vec<DataSetReader>,
+    source: Arc<dyn PubSubSource>,
+    context: ContextOwned,
+}
+
+impl PubSubSubscriber {
+    /// Create a new subscriber for the data set readers in `connection`,
+    /// receiving network messages from `source`.
+    pub fn new(connection: &PubSubConnectionDataType, source: Arc<dyn PubSubSource>) -> Self {
+        let readers = connection
+            .reader_groups
+            .iter()
+            .flatten()
+            .filter(|g| g.enabled)
+            .flat_map(|g| g.data_set_readers.iter().flatten())
+            .filter(|r| r.enabled)
+            .map(|r| DataSetReader {
+                name: r.name.as_ref().to_owned(),
+                publisher_id: match &r.publisher_id {
+                    Variant::Empty => None,
+                    v => Some(publisher_id_string(v)),
+                },
+                data_set_writer_id: r.data_set_writer_id,
+                field_content_mask: r.data_set_field_content_mask,
+                fields: r.data_set_meta_data.fields.clone().unwrap_or_default(),
+            })
+            .collect();
+
+        Self {
+            readers,
+            source,
+            context: ContextOwned::default(),
+        }
+    }
+
+    /// Set the encoding context used when decoding messages. This should contain
+    /// any namespaces and custom types used by the published values.
+    pub fn with_encoding_context(mut self, context: ContextOwned) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Decode a single encoded network message, returning each data set message matched
+    /// by a data set reader, together with the name of the reader.
+    ///
+    /// Messages starting with `{` are decoded using the JSON message mapping,
+    /// all others using the UADP message mapping.
+    pub fn decode(&self, payload: &[u8]) -> Result<Vec<(String, DataSetMessage)>, StatusCode> {
+        if payload.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+            self.decode_json(payload)
+        } else {
+            self.decode_uadp(payload)
+        }
+    }
+
+    fn decode_json(&self, payload: &[u8]) -> Result<Vec<(String, DataSetMessage)>, StatusCode> {
+        let message =
+            RawNetworkMessage::decode_json(payload, &self.context.context()).map_err(|e| {
+                warn!("Failed to decode PubSub network message: {e}");
+                StatusCode::BadDecodingError
+            })?;
+
+        let mut res = Vec::new();
+        for data_set in &message.messages {
+            for reader in &self.readers {
+                if reader.matches(&message.publisher_id, data_set.data_set_writer_id) {
+                    res.push((reader.name.clone(), reader.decode(data_set, &self.context)));
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    fn decode_uadp(&self, payload: &[u8]) -> Result<Vec<(String, DataSetMessage)>, StatusCode> {
+        let message = UadpNetworkMessage::decode(
+            payload,
+            &self.context.context(),
+            |publisher_id, data_set_writer_id| {
+                let publisher_id = publisher_id_string(publisher_id);
+                self.readers
+                    .iter()
+                    .find(|r| r.matches(&publisher_id, data_set_writer_id) && !r.fields.is_empty())
+                    .map(|r| r.fields.as_slice())
+            },
+        )
+        .map_err(|e| {
+            warn!("Failed to decode UADP network message: {e}");
+            StatusCode::BadDecodingError
+        })?;
+
+        let publisher_id = publisher_id_string(&message.publisher_id);
+        let mut res = Vec::new();
+        for data_set in message.messages {
+            for reader in &self.readers {
+                if reader.matches(&publisher_id, data_set.data_set_writer_id) {
+                    res.push((reader.name.clone(), data_set.clone()));
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    /// Receive network messages until `token` is cancelled, calling `callback` with the
+    /// name of the data set reader and the decoded message for each matched data set message.
+    pub async fn run(
+        self,
+        token: CancellationToken,
+        mut callback: impl FnMut(&str, DataSetMessage) + Send,
+    ) {
+        loop {
+            let payload = tokio::select! {
+                r = self.source.recv() => r,
+                _ = token.cancelled() => break,
+            };
+            let payload = match payload {
+                Ok(p) => p,
+                Err(e) => {
+                    warn!("Failed to receive PubSub network message: {e}");
+                    continue;
+                }
+            };
+            let Ok(messages) = self.decode(&payload) else {
+                continue;
+            };
+            for (reader, message) in messages {
+                callback(&reader, message);
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::net::SocketAddr;
+
+use async_trait::async_trait;
+use opcua_core::{sync::RwLock, trace_read_lock};
+use opcua_types::{
+    AttributeId, DataEncoding, DataValue, DateTime, NetworkAddressUrlDataType,
+    PubSubConnectionDataType, PublishedVariableDataType, StatusCode, TimestampsToReturn,
+};
+use tokio::net::UdpSocket;
+use tracing::error;
+
+use crate::address_space::AddressSpace;
+
+/// Source of the values of published data sets.
+///
+/// This is sampled outside of any service call, so it does not go through
+/// the node managers of the server.
+pub trait DataSetSource: Send + Sync {
+    /// Sample the current values of `variables`, returning one value for each variable.
+    fn sample(&self, variables: &[PublishedVariableDataType]) -> Vec<DataValue>;
+}
+
+/// Samples the values stored in the address space, as they are kept up to date by an
+/// in-memory node manager. Node managers that produce values on read, for example using
+/// read callbacks on the `SimpleNodeManager`, are not called, so such values must be
+/// published using a custom [DataSetSource].
+impl DataSetSource for RwLock<AddressSpace> {
+    fn sample(&self, variables: &[PublishedVariableDataType]) -> Vec<DataValue> {
+        let address_space = trace_read_lock!(self);
+        variables
+            .iter()
+            .map(|v| {
+                let attribute_id = match v.attribute_id {
+                    0 => AttributeId::Value,
+                    id => match AttributeId::from_u32(id) {
+                        Ok(id) => id,
+                        Err(_) => return bad_value(StatusCode::BadAttributeIdInvalid),
+                    },
+                };
+                let value = address_space
+                    .find(&v.published_variable)
+                    .and_then(|n| {
+                        n.as_node().get_attribute(
+                            TimestampsToReturn::Both,
+                            attribute_id,
+                            &v.index_range,
+                            &DataEncoding::Binary,
+                        )
+                    })
+                    .unwrap_or_else(|| bad_value(StatusCode::BadNodeIdUnknown));
+                // Use the substitute value if the value is bad.
+                if value.status().is_bad() && !v.substitute_value.is_empty() {
+                    DataValue {
+                        value: Some(v.substitute_value.clone()),
+                        status: Some(StatusCode::UncertainSubstituteValue),
+                        ..value
+                    }
+                } else {
+                    value
+                }
+            })
+            .collect()
+    }
+}
+
+fn bad_value(status: StatusCode) -> DataValue {
+    DataValue {
+        status: Some(status),
+        server_timestamp: Some(DateTime::now()),
+        ..Default::default()
+    }
+}
+
+/// Resolve the `opc.udp://host:port` address of a PubSub connection.
+async fn connection_address(
+    connection: &PubSubConnectionDataType,
+) -> Result<SocketAddr, StatusCode> {
+    let Some(address) = connection.address.inner_as::<NetworkAddressUrlDataType>() else {
+        return Err(StatusCode::BadConfigurationError);
+    };
+    let Some(host) = address.url.as_ref().strip_prefix("opc.udp://") else {
+        error!("Unsupported PubSub connection address {}", address.url);
+        return Err(StatusCode::BadConfigurationError);
+    };
+    let target = tokio::net::lookup_host(host.trim_end_matches('/'))
+        .await
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| {
+            error!(
+                "Failed to resolve PubSub connection address {}",
+                address.url
+            );
+            StatusCode::BadConfigurationError
+        })?;
+    Ok(target)
+}
+
+/// Transport for sending encoded PubSub network messages.
+#[async_trait]
+pub trait PubSubSink: Send + Sync {
+    /// Send an encoded network message. `queue_name` is the broker queue
+    /// configured on the writer group, if any.
+    async fn send(&self, queue_name: Option<&str>, payload: Vec<u8>) -> Result<(), StatusCode>;
+}
+
+/// PubSub sink sending each network message as a single UDP datagram.
+pub struct UdpSink {
+    socket: UdpSocket,
+    target: SocketAddr,
+}
+
+impl UdpSink {
+    /// Create a new UDP sink sending to `target`.
+    pub async fn new(target: SocketAddr) -> Result<Self, StatusCode> {
+        let bind_addr: SocketAddr = if target.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(bind_addr).await.map_err(|e| {
+            error!("Failed to bind UDP socket for PubSub: {e}");
+            StatusCode::BadCommunicationError
+        })?;
+        Ok(Self { socket, target })
+    }
+
+    /// Create a new UDP sink sending to the `opc.udp://host:port` address
+    /// configured on the given connection.
+    pub async fn from_connection(
+        connection: &PubSubConnectionDataType,
+    ) -> Result<Self, StatusCode> {
+        Self::new(connection_address(connection).await?).await
+    }
+}
+
+#[async_trait]
+impl PubSubSink for UdpSink {
+    async fn send(&self, _queue_name: Option<&str>, payload: Vec<u8>) -> Result<(), StatusCode> {
+        self.socket
+            .send_to(&payload, self.target)
+            .await
+            .map_err(|_| StatusCode::BadCommunicationError)?;
+        Ok(())
+    }
+}
+
+/// Transport for receiving encoded PubSub network messages.
+#[async_trait]
+pub trait PubSubSource: Send + Sync {
+    /// Receive the next encoded network message.
+    async fn recv(&self) -> Result<Vec<u8>, StatusCode>;
+}
+
+/// PubSub source receiving network messages as UDP datagrams.
+pub struct UdpSource {
+    socket: UdpSocket,
+}
+
+impl UdpSource {
+    /// Create a new UDP source listening on `addr`.
+    pub async fn bind(addr: SocketAddr) -> Result<Self, StatusCode> {
+        let socket = UdpSocket::bind(addr).await.map_err(|e| {
+            error!("Failed to bind UDP socket for PubSub on {addr}: {e}");
+            StatusCode::BadCommunicationError
+        })?;
+        Ok(Self { socket })
+    }
+
+    /// Create a new UDP source listening on the port of the `opc.udp://host:port`
+    /// address configured on the given connection.
+    pub async fn from_connection(
+        connection: &PubSubConnectionDataType,
+    ) -> Result<Self, StatusCode> {
+        let target = connection_address(connection).await?;
+        let addr: SocketAddr = if target.is_ipv4() {
+            ([0, 0, 0, 0], target.port()).into()
+        } else {
+            ([0u16; 8], target.port()).into()
+        };
+        Self::bind(addr).await
+    }
+
+    /// Get the local address the source is listening on.
+    pub fn local_addr(&self) -> Result<SocketAddr, StatusCode> {
+        self.socket
+            .local_addr()
+            .map_err(|_| StatusCode::BadCommunicationError)
+    }
+}
+
+#[async_trait]
+impl PubSubSource for UdpSource {
+    async fn recv(&self) -> Result<Vec<u8>, StatusCode> {
+        // UDP datagrams are at most 64KiB.
+        let mut buf = vec![0u8; 65536];
+        let len = self
+            .socket
+            .recv(&mut buf)
+            .await
+            .map_err(|_| StatusCode::BadCommunicationError)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+}
@@ -0,0 +1,647 @@
+use std::io::{Cursor, Read, Write};
+
+use opcua_types::{
+    read_u8, write_u8, BinaryDecodable, BinaryEncodable, Context, DataSetFieldContentMask,
+    DataValue, DateTime, EncodingResult, Error, FieldMetaData, Guid, StatusCode, UAString, Variant,
+};
+
+use super::{message::masked_value, DataSetMessage, DataSetMetaDataMessage};
+
+const UADP_VERSION: u8 = 1;
+
+// Flags in the first byte of a network message.
+const PUBLISHER_ID_ENABLED: u8 = 0x10;
+const GROUP_HEADER_ENABLED: u8 = 0x20;
+const PAYLOAD_HEADER_ENABLED: u8 = 0x40;
+const EXTENDED_FLAGS_1_ENABLED: u8 = 0x80;
+
+// Extended flags 1 of a network message.
+const PUBLISHER_ID_TYPE_MASK: u8 = 0x07;
+const DATA_SET_CLASS_ID_ENABLED: u8 = 0x08;
+const SECURITY_ENABLED: u8 = 0x10;
+const TIMESTAMP_ENABLED: u8 = 0x20;
+const PICOSECONDS_ENABLED: u8 = 0x40;
+const EXTENDED_FLAGS_2_ENABLED: u8 = 0x80;
+
+// Group flags.
+const WRITER_GROUP_ID_ENABLED: u8 = 0x01;
+const GROUP_VERSION_ENABLED: u8 = 0x02;
+const NETWORK_MESSAGE_NUMBER_ENABLED: u8 = 0x04;
+const SEQUENCE_NUMBER_ENABLED: u8 = 0x08;
+
+// Security flags.
+const SECURITY_SIGNED: u8 = 0x01;
+const SECURITY_ENCRYPTED: u8 = 0x02;
+const SECURITY_FOOTER_ENABLED: u8 = 0x04;
+
+// Data set flags 1.
+const DATA_SET_MESSAGE_VALID: u8 = 0x01;
+const FIELD_ENCODING_MASK: u8 = 0x06;
+const DATA_SET_SEQUENCE_NUMBER_ENABLED: u8 = 0x08;
+const STATUS_ENABLED: u8 = 0x10;
+const CONFIG_MAJOR_VERSION_ENABLED: u8 = 0x20;
+const CONFIG_MINOR_VERSION_ENABLED: u8 = 0x40;
+const DATA_SET_FLAGS_2_ENABLED: u8 = 0x80;
+
+// Data set flags 2.
+const DATA_SET_MESSAGE_TYPE_MASK: u8 = 0x0F;
+const DATA_SET_TIMESTAMP_ENABLED: u8 = 0x10;
+const DATA_SET_PICOSECONDS_ENABLED: u8 = 0x20;
+
+const MESSAGE_TYPE_KEY_FRAME: u8 = 0;
+const MESSAGE_TYPE_KEEP_ALIVE: u8 = 3;
+
+const FIELD_ENCODING_VARIANT: u8 = 0;
+const FIELD_ENCODING_RAW: u8 = 1;
+const FIELD_ENCODING_DATA_VALUE: u8 = 2;
+
+// Extended flags 2 of a network message.
+const NETWORK_MESSAGE_TYPE_MASK: u8 = 0x1C;
+const NETWORK_MESSAGE_TYPE_DISCOVERY_RESPONSE: u8 = 0x08;
+
+// Type of a discovery response message.
+const DISCOVERY_RESPONSE_DATA_SET_META_DATA: u8 = 2;
+
+fn publisher_id_type(publisher_id: &Variant) -> EncodingResult<Option<u8>> {
+    match publisher_id {
+        Variant::Empty => Ok(None),
+        Variant::Byte(_) => Ok(Some(0)),
+        Variant::UInt16(_) => Ok(Some(1)),
+        Variant::UInt32(_) => Ok(Some(2)),
+        Variant::UInt64(_) => Ok(Some(3)),
+        Variant::String(_) => Ok(Some(4)),
+        v => Err(Error::encoding(format!(
+            "Invalid publisher ID type: {:?}",
+            v.type_id()
+        ))),
+    }
+}
+
+fn encode_publisher_id(
+    publisher_id: &Variant,
+    stream: &mut Vec<u8>,
+    ctx: &Context<'_>,
+) -> EncodingResult<()> {
+    match publisher_id {
+        Variant::Byte(v) => v.encode(stream, ctx)?,
+        Variant::UInt16(v) => v.encode(stream, ctx)?,
+        Variant::UInt32(v) => v.encode(stream, ctx)?,
+        Variant::UInt64(v) => v.encode(stream, ctx)?,
+        Variant::String(v) => v.encode(stream, ctx)?,
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Security header of a UADP network message.
+///
+/// Only the header and footer are encoded, signing and encryption of messages is not
+/// implemented. Messages with the signed or encrypted flags set are rejected when encoding
+/// and decoding, this is where support for message security should be added.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UadpSecurityHeader {
+    /// Security flags. Only the `SecurityFooter` (`0x04`) and `ForceKeyReset` (`0x08`)
+    /// flags are currently supported.
+    pub security_flags: u8,
+    /// ID of the security token used to secure the message.
+    pub security_token_id: u32,
+    /// Nonce of the message.
+    pub message_nonce: Vec<u8>,
+    /// Security footer, included if the `SecurityFooter` flag is set.
+    pub security_footer: Vec<u8>,
+}
+
+impl UadpSecurityHeader {
+    fn check_supported(&self) -> EncodingResult<()> {
+        if self.security_flags & (SECURITY_SIGNED | SECURITY_ENCRYPTED) != 0 {
+            return Err(Error::encoding(
+                "Signed and encrypted UADP messages are not supported",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A network message using the UADP message mapping, the binary
+/// encoding for PubSub messages.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UadpNetworkMessage {
+    /// ID of the publisher. Must be `Byte`, `UInt16`, `UInt32`, `UInt64` or `String`,
+    /// or `Empty` to omit the publisher ID.
+    pub publisher_id: Variant,
+    /// Class of the data sets in the message.
+    pub data_set_class_id: Option<Guid>,
+    /// ID of the writer group that produced the message.
+    pub writer_group_id: Option<u16>,
+    /// Version of the writer group configuration.
+    pub group_version: Option<u32>,
+    /// Number of the network message, used when the data set messages of a writer group
+    /// are split over multiple network messages.
+    pub network_message_number: Option<u16>,
+    /// Sequence number of the network message.
+    pub sequence_number: Option<u16>,
+    /// Time the network message was created.
+    pub timestamp: Option<DateTime>,
+    /// Security header of the message.
+    pub security: Option<UadpSecurityHeader>,
+    /// Data set messages in the network message. At most 255 messages are allowed.
+    pub messages: Vec<DataSetMessage>,
+}
+
+impl UadpNetworkMessage {
+    /// Encode the network message.
+    ///
+    /// The field encoding of each data set message is taken from its field content mask.
+    /// If the mask is empty fields are encoded as variants, if it contains `RawData` they are
+    /// encoded as raw values, and otherwise as data values with the fields given by the mask.
+    pub fn encode(&self, ctx: &Context<'_>) -> EncodingResult<Vec<u8>> {
+        if self.messages.len() > u8::MAX as usize {
+            return Err(Error::encoding(format!(
+                "Too many data set messages in UADP network message: {}",
+                self.messages.len()
+            )));
+        }
+
+        let publisher_id_type = publisher_id_type(&self.publisher_id)?;
+
+        let mut group_flags = 0;
+        if self.writer_group_id.is_some() {
+            group_flags |= WRITER_GROUP_ID_ENABLED;
+        }
+        if self.group_version.is_some() {
+            group_flags |= GROUP_VERSION_ENABLED;
+        }
+        if self.network_message_number.is_some() {
+            group_flags |= NETWORK_MESSAGE_NUMBER_ENABLED;
+        }
+        if self.sequence_number.is_some() {
+            group_flags |= SEQUENCE_NUMBER_ENABLED;
+        }
+
+        let mut extended_flags_1 = publisher_id_type.unwrap_or_default();
+        if self.data_set_class_id.is_some() {
+            extended_flags_1 |= DATA_SET_CLASS_ID_ENABLED;
+        }
+        if self.security.is_some() {
+            extended_flags_1 |= SECURITY_ENABLED;
+        }
+        if self.timestamp.is_some() {
+            extended_flags_1 |= TIMESTAMP_ENABLED;
+        }
+
+        let mut flags = UADP_VERSION | PAYLOAD_HEADER_ENABLED;
+        if publisher_id_type.is_some() {
+            flags |= PUBLISHER_ID_ENABLED;
+        }
+        if group_flags != 0 {
+            flags |= GROUP_HEADER_ENABLED;
+        }
+        if extended_flags_1 != 0 {
+            flags |= EXTENDED_FLAGS_1_ENABLED;
+        }
+
+        let mut stream = Vec::new();
+        write_u8(&mut stream, flags)?;
+        if extended_flags_1 != 0 {
+            write_u8(&mut stream, extended_flags_1)?;
+        }
+        encode_publisher_id(&self.publisher_id, &mut stream, ctx)?;
+        if let Some(class_id) = &self.data_set_class_id {
+            class_id.encode(&mut stream, ctx)?;
+        }
+
+        if group_flags != 0 {
+            write_u8(&mut stream, group_flags)?;
+            if let Some(id) = self.writer_group_id {
+                id.encode(&mut stream, ctx)?;
+            }
+            if let Some(version) = self.group_version {
+                version.encode(&mut stream, ctx)?;
+            }
+            if let Some(number) = self.network_message_number {
+                number.encode(&mut stream, ctx)?;
+            }
+            if let Some(number) = self.sequence_number {
+                number.encode(&mut stream, ctx)?;
+            }
+        }
+
+        write_u8(&mut stream, self.messages.len() as u8)?;
+        for message in &self.messages {
+            message.data_set_writer_id.encode(&mut stream, ctx)?;
+        }
+
+        if let Some(timestamp) = &self.timestamp {
+            timestamp.encode(&mut stream, ctx)?;
+        }
+
+        if let Some(security) = &self.security {
+            security.check_supported()?;
+            let mut security_flags = security.security_flags & !SECURITY_FOOTER_ENABLED;
+            if !security.security_footer.is_empty() {
+                security_flags |= SECURITY_FOOTER_ENABLED;
+            }
+            write_u8(&mut stream, security_flags)?;
+            security.security_token_id.encode(&mut stream, ctx)?;
+            if security.message_nonce.len() > u8::MAX as usize {
+                return Err(Error::encoding("UADP message nonce is too long"));
+            }
+            write_u8(&mut stream, security.message_nonce.len() as u8)?;
+            stream
+                .write_all(&security.message_nonce)
+                .map_err(Error::encoding)?;
+            if !security.security_footer.is_empty() {
+                let Ok(len) = u16::try_from(security.security_footer.len()) else {
+                    return Err(Error::encoding("UADP security footer is too long"));
+                };
+                len.encode(&mut stream, ctx)?;
+            }
+        }
+
+        let messages = self
+            .messages
+            .iter()
+            .map(|m| m.encode_uadp(ctx))
+            .collect::<EncodingResult<Vec<_>>>()?;
+        if messages.len() > 1 {
+            for message in &messages {
+                let Ok(len) = u16::try_from(message.len()) else {
+                    return Err(Error::encoding("UADP data set message is too long"));
+                };
+                len.encode(&mut stream, ctx)?;
+            }
+        }
+        for message in messages {
+            stream.extend_from_slice(&message);
+        }
+
+        if let Some(security) = &self.security {
+            stream.extend_from_slice(&security.security_footer);
+        }
+
+        Ok(stream)
+    }
+
+    /// Decode a network message.
+    ///
+    /// `fields` is called with the publisher ID of the message and the data set writer ID of
+    /// each data set message, and should return the field metadata of the data set, if known.
+    /// The metadata is used to name the fields, and is required to decode fields using the raw
+    /// data encoding, since raw data set messages do not include the number of fields. Fields
+    /// of data sets without metadata are named by their index.
+    pub fn decode<'a>(
+        data: &[u8],
+        ctx: &Context<'_>,
+        fields: impl Fn(&Variant, u16) -> Option<&'a [FieldMetaData]>,
+    ) -> EncodingResult<Self> {
+        let mut stream = Cursor::new(data);
+        let flags = read_u8(&mut stream)?;
+        if flags & 0x0F != UADP_VERSION {
+            return Err(Error::decoding(format!(
+                "Unsupported UADP version {}",
+                flags & 0x0F
+            )));
+        }
+        let extended_flags_1 = if flags & EXTENDED_FLAGS_1_ENABLED != 0 {
+            read_u8(&mut stream)?
+        } else {
+            0
+        };
+        let extended_flags_2 = if extended_flags_1 & EXTENDED_FLAGS_2_ENABLED != 0 {
+            read_u8(&mut stream)?
+        } else {
+            0
+        };
+        // Chunked messages, promoted fields, and discovery requests are not supported.
+        // Discovery responses, such as data set metadata, are decoded without messages.
+        let is_discovery_response =
+            extended_flags_2 & NETWORK_MESSAGE_TYPE_MASK == NETWORK_MESSAGE_TYPE_DISCOVERY_RESPONSE;
+        if extended_flags_2 != 0 && !is_discovery_response {
+            return Err(Error::decoding(format!(
+                "Unsupported UADP extended flags 2: {extended_flags_2:#x}"
+            )));
+        }
+
+        let mut res = Self::default();
+        if flags & PUBLISHER_ID_ENABLED != 0 {
+            res.publisher_id = match extended_flags_1 & PUBLISHER_ID_TYPE_MASK {
+                0 => Variant::Byte(u8::decode(&mut stream, ctx)?),
+                1 => Variant::UInt16(u16::decode(&mut stream, ctx)?),
+                2 => Variant::UInt32(u32::decode(&mut stream, ctx)?),
+                3 => Variant::UInt64(u64::decode(&mut stream, ctx)?),
+                4 => Variant::String(UAString::decode(&mut stream, ctx)?),
+                t => {
+                    return Err(Error::decoding(format!(
+                        "Invalid UADP publisher ID type {t}"
+                    )))
+                }
+            };
+        }
+        if is_discovery_response {
+            return Ok(res);
+        }
+        if extended_flags_1 & DATA_SET_CLASS_ID_ENABLED != 0 {
+            res.data_set_class_id = Some(Guid::decode(&mut stream, ctx)?);
+        }
+
+        if flags & GROUP_HEADER_ENABLED != 0 {
+            let group_flags = read_u8(&mut stream)?;
+            if group_flags & WRITER_GROUP_ID_ENABLED != 0 {
+                res.writer_group_id = Some(u16::decode(&mut stream, ctx)?);
+            }
+            if group_flags & GROUP_VERSION_ENABLED != 0 {
+                res.group_version = Some(u32::decode(&mut stream, ctx)?);
+            }
+            if group_flags & NETWORK_MESSAGE_NUMBER_ENABLED != 0 {
+                res.network_message_number = Some(u16::decode(&mut stream, ctx)?);
+            }
+            if group_flags & SEQUENCE_NUMBER_ENABLED != 0 {
+                res.sequence_number = Some(u16::decode(&mut stream, ctx)?);
+            }
+        }
+
+        let writer_ids = if flags & PAYLOAD_HEADER_ENABLED != 0 {
+            let count = read_u8(&mut stream)?;
+            (0..count)
+                .map(|_| u16::decode(&mut stream, ctx))
+                .collect::<EncodingResult<Vec<_>>>()?
+        } else {
+            // Without a payload header there is a single message from an unknown writer.
+            vec![0]
+        };
+
+        if extended_flags_1 & TIMESTAMP_ENABLED != 0 {
+            res.timestamp = Some(DateTime::decode(&mut stream, ctx)?);
+        }
+        if extended_flags_1 & PICOSECONDS_ENABLED != 0 {
+            u16::decode(&mut stream, ctx)?;
+        }
+
+        let mut footer_len = 0;
+        if extended_flags_1 & SECURITY_ENABLED != 0 {
+            let security_flags = read_u8(&mut stream)?;
+            let security_token_id = u32::decode(&mut stream, ctx)?;
+            let nonce_len = read_u8(&mut stream)?;
+            let mut message_nonce = vec![0u8; nonce_len as usize];
+            stream
+                .read_exact(&mut message_nonce)
+                .map_err(Error::decoding)?;
+            if security_flags & SECURITY_FOOTER_ENABLED != 0 {
+                footer_len = u16::decode(&mut stream, ctx)? as usize;
+            }
+            let security = UadpSecurityHeader {
+                security_flags,
+                security_token_id,
+                message_nonce,
+                security_footer: Vec::new(),
+            };
+            security.check_supported().map_err(|_| {
+                Error::decoding("Signed and encrypted UADP messages are not supported")
+            })?;
+            res.security = Some(security);
+        }
+
+        let sizes = if writer_ids.len() > 1 {
+            writer_ids
+                .iter()
+                .map(|_| u16::decode(&mut stream, ctx).map(Some))
+                .collect::<EncodingResult<Vec<_>>>()?
+        } else {
+            vec![None]
+        };
+
+        let payload_end = data
+            .len()
+            .checked_sub(footer_len)
+            .ok_or_else(|| Error::decoding("UADP security footer is longer than the message"))?;
+        for (writer_id, size) in writer_ids.into_iter().zip(sizes) {
+            let start = stream.position() as usize;
+            let end = match size {
+                Some(size) => start + size as usize,
+                None => payload_end,
+            };
+            // A security footer overlapping the headers leaves the payload end before the start.
+            if start > end || end > payload_end {
+                return Err(Error::decoding(
+                    "UADP data set message exceeds message length",
+                ));
+            }
+            let mut message_stream = Cursor::new(&data[start..end]);
+            if let Some(message) = DataSetMessage::decode_uadp(
+                &mut message_stream,
+                ctx,
+                writer_id,
+                fields(&res.publisher_id, writer_id),
+            )? {
+                res.messages.push(message);
+            }
+            stream.set_position(end as u64);
+        }
+
+        if let Some(security) = &mut res.security {
+            security.security_footer = data[payload_end..].to_vec();
+        }
+
+        Ok(res)
+    }
+}
+
+impl DataSetMetaDataMessage {
+    /// Encode the metadata message as a UADP discovery response.
+    ///
+    /// `sequence_number` is the sequence number of the discovery response, incremented
+    /// for each discovery response sent by the publisher.
+    pub fn encode_uadp(&self, sequence_number: u16, ctx: &Context<'_>) -> EncodingResult<Vec<u8>> {
+        let publisher_id_type = publisher_id_type(&self.publisher_id)?;
+        let mut flags = UADP_VERSION | EXTENDED_FLAGS_1_ENABLED;
+        if publisher_id_type.is_some() {
+            flags |= PUBLISHER_ID_ENABLED;
+        }
+
+        let mut stream = Vec::new();
+        write_u8(&mut stream, flags)?;
+        write_u8(
+            &mut stream,
+            publisher_id_type.unwrap_or_default() | EXTENDED_FLAGS_2_ENABLED,
+        )?;
+        write_u8(&mut stream, NETWORK_MESSAGE_TYPE_DISCOVERY_RESPONSE)?;
+        encode_publisher_id(&self.publisher_id, &mut stream, ctx)?;
+
+        write_u8(&mut stream, DISCOVERY_RESPONSE_DATA_SET_META_DATA)?;
+        sequence_number.encode(&mut stream, ctx)?;
+        self.data_set_writer_id.encode(&mut stream, ctx)?;
+        self.meta_data.encode(&mut stream, ctx)?;
+        StatusCode::Good.encode(&mut stream, ctx)?;
+        Ok(stream)
+    }
+}
+
+impl DataSetMessage {
+    fn field_encoding(&self) -> u8 {
+        if self.field_content_mask.is_empty() {
+            FIELD_ENCODING_VARIANT
+        } else if self
+            .field_content_mask
+            .contains(DataSetFieldContentMask::RawData)
+        {
+            FIELD_ENCODING_RAW
+        } else {
+            FIELD_ENCODING_DATA_VALUE
+        }
+    }
+
+    fn encode_uadp(&self, ctx: &Context<'_>) -> EncodingResult<Vec<u8>> {
+        let encoding = self.field_encoding();
+        let mut stream = Vec::new();
+        write_u8(
+            &mut stream,
+            DATA_SET_MESSAGE_VALID
+                | (encoding << 1)
+                | DATA_SET_SEQUENCE_NUMBER_ENABLED
+                | DATA_SET_FLAGS_2_ENABLED,
+        )?;
+        write_u8(
+            &mut stream,
+            MESSAGE_TYPE_KEY_FRAME | DATA_SET_TIMESTAMP_ENABLED,
+        )?;
+        // The UADP sequence number is only 16 bits, and is expected to wrap around.
+        (self.sequence_number as u16).encode(&mut stream, ctx)?;
+        self.timestamp.encode(&mut stream, ctx)?;
+
+        let Ok(count) = u16::try_from(self.fields.len()) else {
+            return Err(Error::encoding("Too many fields in UADP data set message"));
+        };
+        // Raw data set messages have no field count, the subscriber takes it from the metadata.
+        if encoding != FIELD_ENCODING_RAW {
+            count.encode(&mut stream, ctx)?;
+        }
+        for (_, value) in &self.fields {
+            match encoding {
+                FIELD_ENCODING_VARIANT => value
+                    .value
+                    .clone()
+                    .unwrap_or_default()
+                    .encode(&mut stream, ctx)?,
+                FIELD_ENCODING_RAW => {
+                    // Raw fields are encoded as variants without the encoding mask.
+                    let Some(value) = value.value.as_ref().filter(|v| !v.is_empty()) else {
+                        return Err(Error::encoding(
+                            "Cannot encode empty value as raw UADP field",
+                        ));
+                    };
+                    let mut buf = Vec::with_capacity(value.byte_len(ctx));
+                    value.encode(&mut buf, ctx)?;
+                    stream.extend_from_slice(&buf[1..]);
+                }
+                _ => masked_value(value, self.field_content_mask).encode(&mut stream, ctx)?,
+            }
+        }
+
+        Ok(stream)
+    }
+
+    fn decode_uadp<S: Read + ?Sized>(
+        stream: &mut S,
+        ctx: &Context<'_>,
+        data_set_writer_id: u16,
+        meta_data: Option<&[FieldMetaData]>,
+    ) -> EncodingResult<Option<Self>> {
+        let flags_1 = read_u8(stream)?;
+        if flags_1 & DATA_SET_MESSAGE_VALID == 0 {
+            return Ok(None);
+        }
+        let flags_2 = if flags_1 & DATA_SET_FLAGS_2_ENABLED != 0 {
+            read_u8(stream)?
+        } else {
+            0
+        };
+
+        let mut res = DataSetMessage {
+            data_set_writer_id,
+            sequence_number: 0,
+            timestamp: DateTime::null(),
+            field_content_mask: DataSetFieldContentMask::empty(),
+            fields: Vec::new(),
+        };
+        if flags_1 & DATA_SET_SEQUENCE_NUMBER_ENABLED != 0 {
+            res.sequence_number = u16::decode(stream, ctx)? as u32;
+        }
+        if flags_2 & DATA_SET_TIMESTAMP_ENABLED != 0 {
+            res.timestamp = DateTime::decode(stream, ctx)?;
+        }
+        if flags_2 & DATA_SET_PICOSECONDS_ENABLED != 0 {
+            u16::decode(stream, ctx)?;
+        }
+        if flags_1 & STATUS_ENABLED != 0 {
+            u16::decode(stream, ctx)?;
+        }
+        if flags_1 & CONFIG_MAJOR_VERSION_ENABLED != 0 {
+            u32::decode(stream, ctx)?;
+        }
+        if flags_1 & CONFIG_MINOR_VERSION_ENABLED != 0 {
+            u32::decode(stream, ctx)?;
+        }
+
+        match flags_2 & DATA_SET_MESSAGE_TYPE_MASK {
+            MESSAGE_TYPE_KEY_FRAME => (),
+            MESSAGE_TYPE_KEEP_ALIVE => return Ok(Some(res)),
+            t => {
+                return Err(Error::decoding(format!(
+                    "Unsupported UADP data set message type {t}"
+                )))
+            }
+        }
+
+        let encoding = (flags_1 & FIELD_ENCODING_MASK) >> 1;
+        res.field_content_mask = match encoding {
+            FIELD_ENCODING_VARIANT => DataSetFieldContentMask::empty(),
+            FIELD_ENCODING_RAW => DataSetFieldContentMask::RawData,
+            FIELD_ENCODING_DATA_VALUE => {
+                DataSetFieldContentMask::StatusCode
+                    | DataSetFieldContentMask::SourceTimestamp
+                    | DataSetFieldContentMask::ServerTimestamp
+                    | DataSetFieldContentMask::SourcePicoSeconds
+                    | DataSetFieldContentMask::ServerPicoSeconds
+            }
+            e => return Err(Error::decoding(format!("Invalid UADP field encoding {e}"))),
+        };
+
+        let count = if encoding == FIELD_ENCODING_RAW {
+            let Some(meta_data) = meta_data else {
+                return Err(Error::decoding(
+                    "Cannot decode raw UADP data set message without data set metadata",
+                ));
+            };
+            meta_data.len()
+        } else {
+            u16::decode(stream, ctx)? as usize
+        };
+        for i in 0..count {
+            let meta = meta_data.and_then(|m| m.get(i));
+            let name = meta
+                .map(|m| m.name.as_ref().to_owned())
+                .unwrap_or_else(|| i.to_string());
+            let value = match encoding {
+                FIELD_ENCODING_VARIANT => DataValue::value_only(Variant::decode(stream, ctx)?),
+                FIELD_ENCODING_RAW => {
+                    let Some(meta) = meta.filter(|m| m.built_in_type != 0) else {
+                        return Err(Error::decoding(
+                            "Cannot decode raw UADP field without a built in type in the data set metadata",
+                        ));
+                    };
+                    // Add back the variant encoding mask, so that the field can be decoded as a variant.
+                    let mut mask = meta.built_in_type;
+                    if meta.value_rank >= 0 {
+                        mask |= 0x80;
+                    }
+                    let mut mask_stream = Cursor::new([mask]).chain(&mut *stream);
+                    DataValue::value_only(Variant::decode(&mut mask_stream, ctx)?)
+                }
+                _ => DataValue::decode(stream, ctx)?,
+            };
+            res.fields.push((name, value));
+        }
+
+        Ok(Some(res))
+    }
+}
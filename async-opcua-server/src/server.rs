@@ -25,10 +25,16 @@ use opcua_core::{config::Config, handle::AtomicHandle};
 use opcua_crypto::CertificateStore;
 
 use crate::{
-    diagnostics::ServerDiagnostics,
+    diagnostics::{ServerDiagnostics, ServiceFaultDiagnostics},
     node_manager::{DefaultTypeTreeGetter, ServerContext},
-    session::controller::{ControllerCommand, SessionStarter},
-    transport::tcp::{TcpConnector, TransportConfig},
+    session::{
+        continuation_points::DefaultContinuationPointStoreFactory,
+        controller::{ControllerCommand, SessionStarter},
+    },
+    transport::{
+        tcp::{TcpConnector, TransportConfig},
+        LogThrottle, SecureChannelLimiter,
+    },
     ServerStatusWrapper,
 };
 use opcua_types::{DateTime, LocalizedText, ServerState, UAString};
@@ -42,7 +48,6 @@ use super::{
     server_handle::ServerHandle,
     session::manager::SessionManager,
     subscriptions::SubscriptionCache,
-    ServerCapabilities,
 };
 
 struct ConnectionInfo {
@@ -100,8 +105,11 @@ impl Server {
         let send_buffer_size = config.limits.send_buffer_size;
         let receive_buffer_size = config.limits.receive_buffer_size;
 
-        let application_description = if config.create_sample_keypair {
-            Some(config.application_description())
+        let x509_data = if config.create_sample_keypair {
+            Some((
+                config.application_description(),
+                Some(config.sample_keypair_host_names()),
+            ))
         } else {
             None
         };
@@ -112,7 +120,7 @@ impl Server {
                 false,
                 config.certificate_path.as_deref(),
                 config.private_key_path.as_deref(),
-                application_description,
+                x509_data,
             );
 
         if server_certificate.is_none() || server_pkey.is_none() {
@@ -133,6 +141,8 @@ impl Server {
 
         let type_tree = Arc::new(RwLock::new(DefaultTypeTree::new()));
 
+        let transport_error_log_interval =
+            Duration::from_millis(config.tcp_config.transport_error_log_interval_ms);
         let info = ServerInfo {
             authenticator: builder
                 .authenticator
@@ -156,17 +166,27 @@ impl Server {
             subscription_id_handle: AtomicHandle::new(1),
             monitored_item_id_handle: AtomicHandle::new(1),
             secure_channel_id_handle: Arc::new(AtomicHandle::new(1)),
-            capabilities: ServerCapabilities::default(),
+            capabilities: builder.capabilities,
             service_level: service_level.clone(),
             port: AtomicU16::new(0),
             type_tree_getter: builder
                 .type_tree_getter
                 .unwrap_or_else(|| Arc::new(DefaultTypeTreeGetter)),
+            node_visibility_filter: builder.node_visibility_filter,
+            continuation_point_store: builder
+                .continuation_point_store
+                .unwrap_or_else(|| Arc::new(DefaultContinuationPointStoreFactory)),
             type_loaders: RwLock::new(builder.type_loaders),
-            diagnostics: ServerDiagnostics {
-                enabled: config.diagnostics,
-                ..Default::default()
-            },
+            diagnostics: ServerDiagnostics::new(config.diagnostics, config.diagnostics_groups),
+            service_fault_diagnostics: ServiceFaultDiagnostics::new(
+                &config.service_fault_diagnostics,
+            ),
+            sampler_runtime: builder.sampler_runtime,
+            secure_channel_limiter: SecureChannelLimiter::new(&config.tcp_config),
+            recoverable_transport_error_log: LogThrottle::new(transport_error_log_interval),
+            fatal_transport_error_log: LogThrottle::new(transport_error_log_interval),
+            #[cfg(feature = "pubsub")]
+            security_key_service: builder.security_key_service,
         };
 
         let certificate_store = Arc::new(RwLock::new(certificate_store));
@@ -178,6 +198,7 @@ impl Server {
         let status_wrapper = Arc::new(ServerStatusWrapper::new(
             builder.build_info,
             subscriptions.clone(),
+            &info.sampler_runtime(),
         ));
         let context = ServerContext {
             node_managers: node_managers_ref.clone(),
@@ -280,6 +301,8 @@ impl Server {
     /// This is useful for testing, as you can bind a `TcpListener` to port `0` auto-assign
     /// a port.
     pub async fn run_with(mut self, listener: TcpListener) -> Result<(), String> {
+        // Stop any shutdown countdown once the server stops, or if this future is dropped.
+        let _countdown_guard = self.status.countdown_guard();
         let context = ServerContext {
             node_managers: self.node_managers.as_weak(),
             subscriptions: self.subscriptions.clone(),
@@ -324,6 +347,10 @@ impl Server {
             Self::run_session_expiry(&self.session_manager, &self.session_notify);
         pin!(session_expiry_fut);
 
+        let certificate_expiry_fut =
+            Self::run_certificate_expiry_check(&self.info, &self.subscriptions);
+        pin!(certificate_expiry_fut);
+
         loop {
             let conn_fut = if self.connections.is_empty() {
                 if self.token.is_cancelled() {
@@ -347,6 +374,7 @@ impl Server {
                 _ = &mut subscription_fut => {}
                 _ = &mut discovery_fut => {}
                 _ = &mut session_expiry_fut => {}
+                _ = &mut certificate_expiry_fut => {}
                 rs = listener.accept() => {
                     match rs {
                         Ok((socket, addr)) => {
@@ -354,6 +382,7 @@ impl Server {
                             let conn = SessionStarter::new(
                                 TcpConnector::new(socket, TransportConfig {
                                     send_buffer_size: self.info.config.limits.send_buffer_size,
+                                    send_buffer_pool_size: self.info.config.limits.send_buffer_pool_size,
                                     max_message_size: self.info.config.limits.max_message_size,
                                     max_chunk_count: self.info.config.limits.max_chunk_count,
                                     receive_buffer_size: self.info.config.limits.receive_buffer_size,
@@ -446,6 +475,67 @@ impl Server {
         }
     }
 
+    async fn run_certificate_expiry_check(
+        info: &ServerInfo,
+        subscriptions: &SubscriptionCache,
+    ) -> Never {
+        let interval = info.config.certificate_expiry.check_interval_secs;
+        if interval == 0 || info.server_certificate.is_none() {
+            futures::future::pending().await
+        } else {
+            let mut tick = tokio::time::interval(Duration::from_secs(interval));
+            tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                tick.tick().await;
+                Self::check_certificate_expiry(info, subscriptions);
+            }
+        }
+    }
+
+    /// Check the expiry date of the application instance certificate, update the
+    /// diagnostics, and warn if the certificate has expired or is about to expire.
+    fn check_certificate_expiry(info: &ServerInfo, subscriptions: &SubscriptionCache) {
+        let Some(cert) = &info.server_certificate else {
+            return;
+        };
+        let not_after = match cert.not_after() {
+            Ok(not_after) => not_after,
+            Err(e) => {
+                error!("Cannot read the expiry date of the application instance certificate: {e}");
+                return;
+            }
+        };
+        let config = &info.config.certificate_expiry;
+        let remaining = not_after - DateTime::now().as_chrono();
+        let days = remaining.num_days();
+        info.diagnostics.set_certificate_days_until_expiry(days);
+
+        let expired = remaining < chrono::Duration::zero();
+        let message = if expired {
+            error!("The application instance certificate expired on {not_after}. Clients will reject connections using it.");
+            format!("The application instance certificate expired on {not_after}")
+        } else if days < config.warning_days as i64 {
+            warn!("The application instance certificate expires in {days} days, on {not_after}");
+            format!("The application instance certificate expires in {days} days")
+        } else {
+            return;
+        };
+
+        #[cfg(feature = "generated-address-space")]
+        if config.emit_events {
+            let event = crate::events::certificate_expiry_event(
+                cert,
+                &info.application_uri,
+                expired,
+                message,
+            );
+            subscriptions
+                .notify_events([(&*event, &opcua_types::ObjectId::Server.into())].into_iter());
+        }
+        #[cfg(not(feature = "generated-address-space"))]
+        let _ = (subscriptions, message);
+    }
+
     /// Log information about the endpoints on this server
     fn log_endpoint_info(&self) {
         info!("OPC UA Server: {}", self.info.application_name);
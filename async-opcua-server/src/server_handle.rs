@@ -55,6 +55,11 @@ impl ServerHandle {
         }
     }
 
+    /// Get a reference to the wrapper managing the `ServerStatus` variable.
+    pub fn status(&self) -> &Arc<ServerStatusWrapper> {
+        &self.status
+    }
+
     /// Get a reference to the ServerInfo, containing configuration and other shared server data.
     pub fn info(&self) -> &Arc<ServerInfo> {
         &self.info
@@ -79,6 +84,14 @@ impl ServerHandle {
         );
     }
 
+    /// Notify clients subscribed to the `NamespaceArray` variable that the set of
+    /// namespaces on the server has changed, see
+    /// [`ServerContext::notify_namespaces_changed`](crate::node_manager::ServerContext::notify_namespaces_changed).
+    pub fn notify_namespaces_changed(&self) {
+        self.node_managers
+            .notify_namespaces_changed(&self.subscriptions);
+    }
+
     /// Get a reference to the node managers on the server.
     pub fn node_managers(&self) -> &NodeManagers {
         &self.node_managers
@@ -110,6 +123,30 @@ impl ServerHandle {
         self.token.cancel();
     }
 
+    /// Shut down the server gracefully.
+    ///
+    /// This sets the server state to `Shutdown`, notifying any clients subscribed to
+    /// the server state, then waits up to `timeout` for pending notifications to be
+    /// published before closing all connections.
+    pub async fn shutdown_graceful(&self, timeout: Duration) {
+        info!("Shutting down server gracefully");
+        self.status.set_state(ServerState::Shutdown);
+
+        let deadline = Instant::now() + timeout;
+        let poll_interval =
+            Duration::from_millis(self.info.config.subscription_poll_interval_ms.max(1));
+        while self.subscriptions.has_pending_notifications() {
+            let now = Instant::now();
+            if now >= deadline {
+                info!("Timed out waiting for notifications to be published");
+                break;
+            }
+            tokio::time::sleep_until((now + poll_interval).min(deadline).into()).await;
+        }
+
+        self.token.cancel();
+    }
+
     /// Shorthand for getting the index of a namespace defined in the global server type tree.
     pub fn get_namespace_index(&self, namespace: &str) -> Option<u16> {
         self.type_tree.read().namespaces().get_index(namespace)
@@ -119,8 +156,7 @@ impl ServerHandle {
     /// update the `SecondsTillShutdown` variable on the server as needed.
     pub fn shutdown_after(&self, time: Duration, reason: impl Into<LocalizedText>) {
         let deadline = Instant::now() + time;
-        self.status
-            .schedule_shutdown(reason.into(), Instant::now() + time);
+        self.status.begin_shutdown_at(reason.into(), deadline);
         let token = self.token.clone();
         info!("Shutting down server in {time:?}");
         tokio::task::spawn(async move {
@@ -1,5 +1,5 @@
 use std::{
-    sync::{Arc, OnceLock},
+    sync::Arc,
     time::{Duration, Instant},
 };
 
@@ -8,9 +8,17 @@ use opcua_types::{
     AttributeId, BuildInfo, DataValue, DateTime, ExtensionObject, LocalizedText, MonitoringMode,
     NodeId, ServerState, ServerStatusDataType, VariableId,
 };
+use tokio::runtime::Handle;
+use tokio_util::sync::{CancellationToken, DropGuard};
 
 use crate::{node_manager::SyncSampler, SubscriptionCache};
 
+/// Whole seconds left until `deadline`, rounded up.
+fn seconds_until(deadline: Instant) -> u32 {
+    let left = deadline.saturating_duration_since(Instant::now());
+    left.as_secs_f64().ceil() as u32
+}
+
 // Note: some of these are unused if the generated namespace feature is disabled.
 
 /// Wrapper for managing the `ServerStatus` variable on the server.
@@ -19,7 +27,12 @@ pub struct ServerStatusWrapper {
     subscriptions: Arc<SubscriptionCache>,
     #[allow(unused)]
     sampler: SyncSampler,
-    shutdown: Arc<OnceLock<ShutdownTarget>>,
+    /// The planned shutdown, if any. Replaced if the shutdown is rescheduled.
+    shutdown: Arc<Mutex<Option<ShutdownTarget>>>,
+    /// Parent of the countdown tokens, cancelled when the server stops.
+    countdown_root: CancellationToken,
+    /// Token for the currently running shutdown countdown, if any.
+    countdown: Mutex<Option<CancellationToken>>,
 }
 
 struct ShutdownTarget {
@@ -31,9 +44,13 @@ struct ShutdownTarget {
 
 #[allow(unused)]
 impl ServerStatusWrapper {
-    pub(crate) fn new(build_info: BuildInfo, subscriptions: Arc<SubscriptionCache>) -> Self {
+    pub(crate) fn new(
+        build_info: BuildInfo,
+        subscriptions: Arc<SubscriptionCache>,
+        runtime: &Handle,
+    ) -> Self {
         let sampler = SyncSampler::new();
-        sampler.run(Duration::from_secs(1), subscriptions.clone());
+        sampler.run_on(Duration::from_secs(1), subscriptions.clone(), runtime);
 
         Self {
             status: Arc::new(Mutex::new(ServerStatusDataType {
@@ -46,7 +63,9 @@ impl ServerStatusWrapper {
             })),
             subscriptions,
             sampler,
-            shutdown: Arc::new(OnceLock::new()),
+            shutdown: Default::default(),
+            countdown_root: CancellationToken::new(),
+            countdown: Mutex::new(None),
         }
     }
 
@@ -83,6 +102,11 @@ impl ServerStatusWrapper {
                 move || {
                     let mut status = status.lock();
                     status.current_time = DateTime::now();
+                    status.seconds_till_shutdown = shutdown
+                        .lock()
+                        .as_ref()
+                        .map(|v| seconds_until(v.deadline))
+                        .unwrap_or_default();
                     Some(DataValue::new_now(ExtensionObject::from_message(
                         status.clone(),
                     )))
@@ -103,17 +127,10 @@ impl ServerStatusWrapper {
                 id.into(),
                 AttributeId::Value,
                 move || {
-                    if let Some(v) = shutdown.get() {
-                        let now = Instant::now();
-                        let left = if now < v.deadline {
-                            (v.deadline - now).as_secs()
-                        } else {
-                            0
-                        };
-                        Some(DataValue::new_now(left as u32))
-                    } else {
-                        None
-                    }
+                    shutdown
+                        .lock()
+                        .as_ref()
+                        .map(|v| DataValue::new_now(seconds_until(v.deadline)))
                 },
                 mode,
                 handle,
@@ -124,7 +141,8 @@ impl ServerStatusWrapper {
                 AttributeId::Value,
                 move || {
                     shutdown
-                        .get()
+                        .lock()
+                        .as_ref()
                         .map(|v| DataValue::new_at(v.reason.clone(), v.time))
                 },
                 mode,
@@ -179,13 +197,51 @@ impl ServerStatusWrapper {
     }
 
     pub(crate) fn schedule_shutdown(&self, reason: LocalizedText, deadline: Instant) {
-        let _ = self.shutdown.set(ShutdownTarget {
+        self.status.lock().shutdown_reason = reason.clone();
+        *self.shutdown.lock() = Some(ShutdownTarget {
             time: DateTime::now(),
             reason,
             deadline,
         });
     }
 
+    /// Begin a planned shutdown of the server. This sets `ShutdownReason` and
+    /// `SecondsTillShutdown`, which subscribers are notified of as they are sampled.
+    /// Once the countdown reaches zero, the server state is set to `Shutdown`.
+    ///
+    /// Calling this again replaces the planned shutdown.
+    ///
+    /// Note that this does not stop the server, use
+    /// [`ServerHandle::shutdown_after`](crate::ServerHandle::shutdown_after) for that.
+    pub fn begin_shutdown(self: &Arc<Self>, reason: LocalizedText, seconds: u32) {
+        self.begin_shutdown_at(reason, Instant::now() + Duration::from_secs(seconds as u64));
+    }
+
+    pub(crate) fn begin_shutdown_at(self: &Arc<Self>, reason: LocalizedText, deadline: Instant) {
+        self.schedule_shutdown(reason, deadline);
+
+        // Replace any countdown that is already running.
+        let token = self.countdown_root.child_token();
+        if let Some(old) = self.countdown.lock().replace(token.clone()) {
+            old.cancel();
+        }
+
+        let status = self.clone();
+        tokio::task::spawn(async move {
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline.into()) => {}
+                _ = token.cancelled() => return,
+            }
+            status.set_state(ServerState::Shutdown);
+        });
+    }
+
+    /// Get a guard that stops any shutdown countdown when dropped.
+    /// Held by the server while it is running.
+    pub(crate) fn countdown_guard(&self) -> DropGuard {
+        self.countdown_root.clone().drop_guard()
+    }
+
     /// Get a copy of the current build info.
     pub fn build_info(&self) -> BuildInfo {
         self.status.lock().build_info.clone()
@@ -203,24 +259,23 @@ impl ServerStatusWrapper {
 
     /// Get the current seconds till shutdown value.
     pub fn seconds_till_shutdown(&self) -> Option<u32> {
-        self.shutdown.get().map(|v| {
-            let now = Instant::now();
-            let left = if now < v.deadline {
-                (v.deadline - now).as_secs()
-            } else {
-                0
-            };
-            left as u32
-        })
+        self.shutdown
+            .lock()
+            .as_ref()
+            .map(|v| seconds_until(v.deadline))
     }
 
     /// Get the current shutdown reason.
     pub fn shutdown_reason(&self) -> Option<LocalizedText> {
-        self.shutdown.get().map(|v| v.reason.clone())
+        self.shutdown.lock().as_ref().map(|v| v.reason.clone())
     }
 
     /// Get the full status object as an extension object.
     pub fn full_status_obj(&self) -> ExtensionObject {
-        ExtensionObject::from_message(self.status.lock().clone())
+        let mut status = self.status.lock().clone();
+        if let Some(seconds) = self.seconds_till_shutdown() {
+            status.seconds_till_shutdown = seconds;
+        }
+        ExtensionObject::from_message(status)
     }
 }
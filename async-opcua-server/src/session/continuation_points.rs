@@ -1,39 +1,300 @@
 use std::any::Any;
 
+use hashbrown::HashMap;
+use opcua_types::{ByteString, NodeId, StatusCode};
+
+use crate::ServerInfo;
+
+enum Payload {
+    Value(Box<dyn Any + Send + Sync + 'static>),
+    Bytes(ByteString),
+}
+
 /// Representation of a dynamic continuation point.
 /// Each node manager may provide their own continuation point type,
 /// which is stored by the server. This wraps that value and provides interfaces
 /// to access it for a given node manager.
+///
+/// Continuation points created with [`ContinuationPoint::from_bytes`] can be
+/// serialized by a [`ContinuationPointStore`], for example to share them between
+/// servers behind a load balancer.
 pub struct ContinuationPoint {
-    payload: Box<dyn Any + Send + Sync + 'static>,
+    payload: Payload,
 }
 
 impl ContinuationPoint {
     /// Create a new continuation point with `item` as content.
     pub fn new<T: Send + Sync + 'static>(item: Box<T>) -> Self {
-        Self { payload: item }
+        Self {
+            payload: Payload::Value(item),
+        }
+    }
+
+    /// Create a new continuation point with an encoded value as content.
+    /// Use [`ContinuationPoint::as_bytes`] to retrieve it.
+    pub fn from_bytes(bytes: ByteString) -> Self {
+        Self {
+            payload: Payload::Bytes(bytes),
+        }
+    }
+
+    /// Retrieve the encoded value of the continuation point.
+    /// This will return `None` if the continuation point was not created
+    /// with [`ContinuationPoint::from_bytes`].
+    pub fn as_bytes(&self) -> Option<&ByteString> {
+        match &self.payload {
+            Payload::Bytes(b) => Some(b),
+            Payload::Value(_) => None,
+        }
     }
 
     /// Retrieve the value of the continuation point.
     /// This will return `None` if the stored value is not equal to the
     /// given type. Most node managers should report an error if this happens.
     pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
-        self.payload.downcast_ref()
+        match &self.payload {
+            Payload::Value(v) => v.downcast_ref(),
+            Payload::Bytes(_) => None,
+        }
     }
 
     /// Retrieve the value of the continuation point.
     /// This will return `None` if the stored value is not equal to the
     /// given type. Most node managers should report an error if this happens.
     pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
-        self.payload.downcast_mut()
+        match &mut self.payload {
+            Payload::Value(v) => v.downcast_mut(),
+            Payload::Bytes(_) => None,
+        }
     }
 
     /// Consume this continuation point and return a specific type.
     pub fn take<T: Send + Sync + 'static>(self) -> Option<Box<T>> {
-        self.payload.downcast().ok()
+        match self.payload {
+            Payload::Value(v) => v.downcast().ok(),
+            Payload::Bytes(_) => None,
+        }
     }
 }
 
 /// Continuation point implementation used when continuation is necessary, but
 /// the last called node manager is empty.
 pub(crate) struct EmptyContinuationPoint;
+
+/// The service a continuation point belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContinuationPointKind {
+    /// Continuation point for Browse and BrowseNext.
+    Browse,
+    /// Continuation point for HistoryRead.
+    History,
+    /// Continuation point for QueryFirst and QueryNext.
+    Query,
+}
+
+/// Storage for the continuation points of a single session.
+///
+/// The server creates one store per session using a [`ContinuationPointStoreFactory`],
+/// and uses it whenever a service call returns or consumes a continuation point.
+///
+/// A store keeping continuation points outside of the server process can serialize
+/// those where [`ContinuationPoint::as_bytes`] returns a value, and recreate them with
+/// [`ContinuationPoint::from_bytes`]. History continuation points are the ones created
+/// by the node manager. Browse continuation points are encoded by the server if the
+/// node manager continuation point is encoded, or if the browse continues in the next
+/// node manager. Query continuation points are never encoded, and must be kept in memory.
+pub trait ContinuationPointStore: Send + Sync {
+    /// Store a continuation point with the given ID.
+    ///
+    /// Return `BadNoContinuationPoints` if the session has no more continuation
+    /// points of this kind available. The continuation point is then discarded.
+    fn add(
+        &mut self,
+        kind: ContinuationPointKind,
+        id: ByteString,
+        point: ContinuationPoint,
+    ) -> Result<(), StatusCode>;
+
+    /// Remove and return the continuation point with the given ID.
+    ///
+    /// Return `None` if it does not exist, the service will then
+    /// report `BadContinuationPointInvalid`.
+    fn remove(&mut self, kind: ContinuationPointKind, id: &ByteString)
+        -> Option<ContinuationPoint>;
+
+    /// Get the number of stored continuation points of the given kind.
+    fn len(&self, kind: ContinuationPointKind) -> usize;
+
+    /// Return `true` if there are no stored continuation points of the given kind.
+    fn is_empty(&self, kind: ContinuationPointKind) -> bool {
+        self.len(kind) == 0
+    }
+
+    /// Remove all continuation points. Called when the session is closed.
+    fn clear(&mut self);
+}
+
+/// Factory for [`ContinuationPointStore`]s, called once for each new session.
+pub trait ContinuationPointStoreFactory: Send + Sync {
+    /// Create a continuation point store for the session with the given ID.
+    fn create(&self, info: &ServerInfo, session_id: &NodeId) -> Box<dyn ContinuationPointStore>;
+}
+
+/// Default continuation point store, keeping continuation points in memory,
+/// with an optional limit on the number of continuation points of each kind.
+#[derive(Default)]
+pub struct InMemoryContinuationPointStore {
+    max_browse: usize,
+    max_history: usize,
+    max_query: usize,
+    points: HashMap<(ContinuationPointKind, ByteString), ContinuationPoint>,
+    counts: HashMap<ContinuationPointKind, usize>,
+}
+
+impl InMemoryContinuationPointStore {
+    /// Create a new in-memory continuation point store with the given limits
+    /// on the number of continuation points of each kind. Use 0 for no limit.
+    pub fn new(max_browse: usize, max_history: usize, max_query: usize) -> Self {
+        Self {
+            max_browse,
+            max_history,
+            max_query,
+            ..Default::default()
+        }
+    }
+
+    fn max(&self, kind: ContinuationPointKind) -> usize {
+        match kind {
+            ContinuationPointKind::Browse => self.max_browse,
+            ContinuationPointKind::History => self.max_history,
+            ContinuationPointKind::Query => self.max_query,
+        }
+    }
+}
+
+impl ContinuationPointStore for InMemoryContinuationPointStore {
+    fn add(
+        &mut self,
+        kind: ContinuationPointKind,
+        id: ByteString,
+        point: ContinuationPoint,
+    ) -> Result<(), StatusCode> {
+        let max = self.max(kind);
+        let count = self.counts.entry(kind).or_default();
+        if max > 0 && *count >= max {
+            return Err(StatusCode::BadNoContinuationPoints);
+        }
+        if self.points.insert((kind, id), point).is_none() {
+            *count += 1;
+        }
+        Ok(())
+    }
+
+    fn remove(
+        &mut self,
+        kind: ContinuationPointKind,
+        id: &ByteString,
+    ) -> Option<ContinuationPoint> {
+        let point = self.points.remove(&(kind, id.clone()))?;
+        if let Some(count) = self.counts.get_mut(&kind) {
+            *count -= 1;
+        }
+        Some(point)
+    }
+
+    fn len(&self, kind: ContinuationPointKind) -> usize {
+        self.counts.get(&kind).copied().unwrap_or_default()
+    }
+
+    fn clear(&mut self) {
+        self.points.clear();
+        self.counts.clear();
+    }
+}
+
+/// Factory for [`InMemoryContinuationPointStore`], using the continuation
+/// point limits from the server configuration.
+pub(crate) struct DefaultContinuationPointStoreFactory;
+
+impl ContinuationPointStoreFactory for DefaultContinuationPointStoreFactory {
+    fn create(&self, info: &ServerInfo, _session_id: &NodeId) -> Box<dyn ContinuationPointStore> {
+        let limits = &info.config.limits;
+        Box::new(InMemoryContinuationPointStore::new(
+            limits.max_browse_continuation_points,
+            limits.max_history_continuation_points,
+            limits.max_query_continuation_points,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opcua_types::{ByteString, StatusCode};
+
+    use super::{
+        ContinuationPoint, ContinuationPointKind, ContinuationPointStore,
+        InMemoryContinuationPointStore,
+    };
+
+    fn point(value: u32) -> ContinuationPoint {
+        ContinuationPoint::new(Box::new(value))
+    }
+
+    #[test]
+    fn in_memory_store_limits() {
+        let mut store = InMemoryContinuationPointStore::new(2, 0, 1);
+        let id = |i: u8| ByteString::from(vec![i]);
+
+        store
+            .add(ContinuationPointKind::Browse, id(1), point(1))
+            .unwrap();
+        store
+            .add(ContinuationPointKind::Browse, id(2), point(2))
+            .unwrap();
+        assert_eq!(
+            store
+                .add(ContinuationPointKind::Browse, id(3), point(3))
+                .unwrap_err(),
+            StatusCode::BadNoContinuationPoints
+        );
+        // Limits are separate for each kind, and 0 means no limit.
+        for i in 0..10 {
+            store
+                .add(ContinuationPointKind::History, id(i), point(i.into()))
+                .unwrap();
+        }
+        store
+            .add(ContinuationPointKind::Query, id(1), point(1))
+            .unwrap();
+        assert_eq!(store.len(ContinuationPointKind::Browse), 2);
+        assert_eq!(store.len(ContinuationPointKind::History), 10);
+        assert_eq!(store.len(ContinuationPointKind::Query), 1);
+
+        // Points are only found for the kind they were added with.
+        assert!(store.remove(ContinuationPointKind::Query, &id(2)).is_none());
+        let p = store.remove(ContinuationPointKind::Browse, &id(2)).unwrap();
+        assert_eq!(p.get::<u32>(), Some(&2));
+        assert!(p.as_bytes().is_none());
+        assert!(store
+            .remove(ContinuationPointKind::Browse, &id(2))
+            .is_none());
+
+        // Removing a point frees up space for a new one.
+        store
+            .add(ContinuationPointKind::Browse, id(3), point(3))
+            .unwrap();
+        assert_eq!(store.len(ContinuationPointKind::Browse), 2);
+
+        store.clear();
+        assert!(store.is_empty(ContinuationPointKind::Browse));
+        assert!(store.is_empty(ContinuationPointKind::History));
+        assert!(store.is_empty(ContinuationPointKind::Query));
+    }
+
+    #[test]
+    fn encoded_continuation_point() {
+        let p = ContinuationPoint::from_bytes(ByteString::from(vec![1, 2, 3]));
+        assert_eq!(p.as_bytes(), Some(&ByteString::from(vec![1, 2, 3])));
+        assert!(p.get::<ByteString>().is_none());
+    }
+}
@@ -18,9 +18,9 @@ use opcua_core::{
 };
 use opcua_crypto::{CertificateStore, SecurityPolicy};
 use opcua_types::{
-    ChannelSecurityToken, DateTime, FindServersResponse, GetEndpointsResponse, MessageSecurityMode,
-    OpenSecureChannelRequest, OpenSecureChannelResponse, ResponseHeader, SecurityTokenRequestType,
-    ServiceFault, StatusCode,
+    ChannelSecurityToken, DateTime, DiagnosticBits, FindServersResponse, GetEndpointsResponse,
+    MessageSecurityMode, OpenSecureChannelRequest, OpenSecureChannelResponse, RequestHeader,
+    ResponseHeader, SecurityTokenRequestType, ServiceFault, StatusCode, UAString,
 };
 use tokio_util::sync::CancellationToken;
 use tracing_futures::Instrument;
@@ -48,7 +48,9 @@ pub(crate) struct Response {
 impl Response {
     pub(super) fn from_result(
         result: Result<impl Into<ResponseMessage>, StatusCode>,
+        info: &ServerInfo,
         request_handle: u32,
+        return_diagnostics: DiagnosticBits,
         request_id: u32,
     ) -> Self {
         match result {
@@ -57,7 +59,10 @@ impl Response {
                 request_id,
             },
             Err(e) => Self {
-                message: ServiceFault::new(request_handle, e).into(),
+                message: info
+                    .service_fault_diagnostics
+                    .service_fault(request_handle, return_diagnostics, e)
+                    .into(),
                 request_id,
             },
         }
@@ -81,6 +86,8 @@ pub(crate) struct SessionController {
     pending_messages: FuturesUnordered<Pin<Box<PendingMessageResponse>>>,
     info: Arc<ServerInfo>,
     deadline: Instant,
+    /// Deadline for the secure channel to have a session, if the idle timeout is enabled.
+    idle_deadline: Option<Instant>,
 }
 
 enum RequestProcessResult {
@@ -183,11 +190,33 @@ impl SessionController {
             message_handler: MessageHandler::new(info.clone(), node_managers, subscriptions),
             deadline: Instant::now()
                 + Duration::from_secs(info.config.tcp_config.hello_timeout as u64),
+            idle_deadline: None,
             info,
             pending_messages: FuturesUnordered::new(),
         }
     }
 
+    fn reset_idle_deadline(&mut self) {
+        let timeout = self.info.config.secure_channel_idle_timeout_ms;
+        if timeout > 0 {
+            self.idle_deadline = Some(Instant::now() + Duration::from_millis(timeout));
+        }
+    }
+
+    /// Check whether the channel has a session once the idle deadline is reached,
+    /// closing the channel if it does not.
+    fn check_idle_channel(&mut self) {
+        let has_session = trace_read_lock!(self.session_manager)
+            .has_session_on_channel(self.channel.secure_channel_id());
+        if has_session {
+            self.reset_idle_deadline();
+        } else {
+            warn!("Secure channel has no session after idle timeout, closing");
+            self.idle_deadline = None;
+            self.fatal_error(StatusCode::BadTimeout, "Secure channel idle timeout");
+        }
+    }
+
     async fn run(mut self, mut command: tokio::sync::mpsc::Receiver<ControllerCommand>) {
         loop {
             let resp_fut = if self.pending_messages.is_empty() {
@@ -195,12 +224,19 @@ impl SessionController {
             } else {
                 Either::Right(self.pending_messages.next())
             };
+            let idle_fut = match self.idle_deadline {
+                Some(d) => Either::Left(tokio::time::sleep_until(d.into())),
+                None => Either::Right(futures::future::pending::<()>()),
+            };
 
             tokio::select! {
                 _ = tokio::time::sleep_until(self.deadline.into()) => {
                     warn!("Connection timed out, closing");
                     self.fatal_error(StatusCode::BadTimeout, "Connection timeout");
                 }
+                _ = idle_fut => {
+                    self.check_idle_channel();
+                }
                 cmd = command.recv() => {
                     match cmd {
                         Some(ControllerCommand::Close) | None => {
@@ -238,7 +274,9 @@ impl SessionController {
                             }
                         }
                         TransportPollResult::RecoverableError(s, id, handle) => {
-                            warn!("Non-fatal transport error: {s}, with request id {id}, request handle {handle}");
+                            if let Some(suppressed) = self.info.recoverable_transport_error_log.check(self.transport.peer_addr) {
+                                warn!("Non-fatal transport error: {s}, with request id {id}, request handle {handle}{suppressed}");
+                            }
                             let msg = ServiceFault::new(handle, s).into();
                             if let Err(e) = self.transport.enqueue_message_for_send(&mut self.channel, msg, id) {
                                 error!("Failed to send response: {e}");
@@ -246,7 +284,9 @@ impl SessionController {
                             }
                         }
                         TransportPollResult::Error(s) => {
-                            error!("Fatal transport error: {s}");
+                            if let Some(suppressed) = self.info.fatal_transport_error_log.check(self.transport.peer_addr) {
+                                error!("Fatal transport error: {s}{suppressed}");
+                            }
                             self.fatal_error(s, "Transport error");
                         }
                         TransportPollResult::Closed => break,
@@ -257,8 +297,18 @@ impl SessionController {
         }
     }
 
+    /// Get the endpoint URL the client used to connect, preferring the URL from the HELLO
+    /// message, falling back to the URL given in the request.
+    fn client_endpoint_url<'a>(&'a self, request_url: &'a UAString) -> &'a str {
+        if self.transport.client_endpoint_url.is_null() {
+            request_url.as_ref()
+        } else {
+            self.transport.client_endpoint_url.as_ref()
+        }
+    }
+
     fn response_metrics(&self, msg: &Response) {
-        if self.info.diagnostics.enabled {
+        if self.info.diagnostics.requests_enabled() {
             let status = msg.message.response_header().service_result;
             if status.is_bad() {
                 self.info.diagnostics.inc_rejected_requests();
@@ -300,6 +350,9 @@ impl SessionController {
                 );
                 if res.is_ok() {
                     self.deadline = self.channel.token_renewal_deadline();
+                    if self.idle_deadline.is_none() {
+                        self.reset_idle_deadline();
+                    }
                 } else {
                     self.info.diagnostics.inc_rejected_requests();
                     self.info.diagnostics.inc_security_rejected_requests();
@@ -330,10 +383,17 @@ impl SessionController {
 
             RequestMessage::CreateSession(request) => {
                 let _h = span.enter();
+                let client_endpoint_url =
+                    self.client_endpoint_url(&request.endpoint_url).to_owned();
                 let mut mgr = trace_write_lock!(self.session_manager);
-                let res = mgr.create_session(&mut self.channel, &self.certificate_store, &request);
+                let res = mgr.create_session(
+                    &mut self.channel,
+                    &self.certificate_store,
+                    &request,
+                    &client_endpoint_url,
+                );
                 drop(mgr);
-                self.process_service_result(res, request.request_header.request_handle, id)
+                self.process_service_result(res, &request.request_header, id)
             }
 
             RequestMessage::ActivateSession(request) => {
@@ -346,7 +406,7 @@ impl SessionController {
                 .instrument(span.clone())
                 .await;
                 let _h = span.enter();
-                self.process_service_result(res, request.request_header.request_handle, id)
+                self.process_service_result(res, &request.request_header, id)
             }
 
             RequestMessage::CloseSession(request) => {
@@ -359,7 +419,11 @@ impl SessionController {
                 .instrument(span.clone())
                 .await;
                 let _h = span.enter();
-                self.process_service_result(res, request.request_header.request_handle, id)
+                if res.is_ok() {
+                    // Give the client the full idle timeout to create a new session.
+                    self.reset_idle_deadline();
+                }
+                self.process_service_result(res, &request.request_header, id)
             }
             RequestMessage::GetEndpoints(request) => {
                 // TODO some of the arguments in the request are ignored
@@ -370,19 +434,28 @@ impl SessionController {
                 let _h = span.enter();
                 let endpoints = self
                     .info
-                    .endpoints(&request.endpoint_url, &request.profile_uris);
+                    .endpoints(&request.endpoint_url, &request.profile_uris)
+                    .map(|e| {
+                        self.info.substitute_endpoint_hosts(
+                            e,
+                            self.client_endpoint_url(&request.endpoint_url),
+                        )
+                    });
                 self.process_service_result(
                     Ok(GetEndpointsResponse {
                         response_header: ResponseHeader::new_good(&request.request_header),
                         endpoints,
                     }),
-                    request.request_header.request_handle,
+                    &request.request_header,
                     id,
                 )
             }
             RequestMessage::FindServers(request) => {
                 let _h = span.enter();
-                let desc = self.info.config.application_description();
+                let desc = self.info.substitute_application_hosts(
+                    self.info.config.application_description(),
+                    self.client_endpoint_url(&request.endpoint_url),
+                );
                 let mut servers = vec![desc];
 
                 // TODO endpoint URL
@@ -404,7 +477,7 @@ impl SessionController {
                         response_header: ResponseHeader::new_good(&request.request_header),
                         servers,
                     }),
-                    request.request_header.request_handle,
+                    &request.request_header,
                     id,
                 )
             }
@@ -453,7 +526,6 @@ impl SessionController {
 
             message => {
                 let _h = span.enter();
-                let now = Instant::now();
                 let mgr = trace_read_lock!(self.session_manager);
                 let session = mgr.find_by_token(&message.request_header().authentication_token);
 
@@ -479,23 +551,45 @@ impl SessionController {
                 debug!("Received request on session {session_id}");
 
                 let deadline = {
-                    let timeout = message.request_header().timeout_hint;
-                    let max_timeout = self.info.config.max_timeout_ms;
-                    let timeout = if max_timeout == 0 {
-                        timeout
-                    } else {
-                        max_timeout.max(timeout)
+                    // The timeout hint of the client is always enforced, `max_timeout_ms`
+                    // only applies to requests without one.
+                    let timeout = match message.request_header().timeout_hint {
+                        0 => self.info.config.max_timeout_ms,
+                        hint => hint,
                     };
                     if timeout == 0 {
                         // Just set some huge value. A request taking a day can probably
                         // be safely canceled...
-                        now + Duration::from_secs(60 * 60 * 24)
+                        req.received_at + Duration::from_secs(60 * 60 * 24)
                     } else {
-                        now + Duration::from_millis(timeout.into())
+                        req.received_at + Duration::from_millis(timeout.into())
                     }
                 };
                 let request_handle = message.request_handle();
 
+                // If the request expired while it was queued, there is no point dispatching it.
+                if deadline <= Instant::now() {
+                    warn!("Request expired before it was dispatched");
+                    self.info.diagnostics.inc_pre_dispatch_timeout_count();
+                    self.info.diagnostics.inc_rejected_requests();
+                    let fault = self.info.service_fault_diagnostics.service_fault(
+                        request_handle,
+                        message.request_header().return_diagnostics,
+                        StatusCode::BadTimeout,
+                    );
+                    return match self.transport.enqueue_message_for_send(
+                        &mut self.channel,
+                        fault.into(),
+                        id,
+                    ) {
+                        Ok(_) => RequestProcessResult::Ok,
+                        Err(e) => {
+                            error!("Failed to send request response: {e}");
+                            RequestProcessResult::Close
+                        }
+                    };
+                }
+
                 match self
                     .message_handler
                     .handle_message(message, session_id, session, user_token, id)
@@ -560,7 +654,7 @@ impl SessionController {
     fn process_service_result(
         &mut self,
         res: Result<impl Into<ResponseMessage>, StatusCode>,
-        request_handle: u32,
+        request_header: &RequestHeader,
         request_id: u32,
     ) -> RequestProcessResult {
         let message = match res {
@@ -576,7 +670,14 @@ impl SessionController {
                     self.info.diagnostics.inc_security_rejected_requests();
                 }
 
-                ServiceFault::new(request_handle, e).into()
+                self.info
+                    .service_fault_diagnostics
+                    .service_fault(
+                        request_header.request_handle,
+                        request_header.return_diagnostics,
+                        e,
+                    )
+                    .into()
             }
         };
         if let Err(e) =
@@ -1,11 +1,12 @@
-use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use arc_swap::ArcSwap;
 use tracing::error;
 
-use super::continuation_points::ContinuationPoint;
+use super::continuation_points::{
+    ContinuationPoint, ContinuationPointKind, ContinuationPointStore,
+};
 use super::manager::next_session_id;
 use crate::authenticator::UserToken;
 use crate::identity_token::IdentityToken;
@@ -13,7 +14,8 @@ use crate::info::ServerInfo;
 use crate::node_manager::{BrowseContinuationPoint, QueryContinuationPoint};
 use opcua_crypto::X509;
 use opcua_types::{
-    ApplicationDescription, ByteString, MessageSecurityMode, NodeId, StatusCode, UAString,
+    ApplicationDescription, ByteString, MessageSecurityMode, NamespaceMap, NodeId, StatusCode,
+    UAString,
 };
 
 /// An instance of an OPC-UA session.
@@ -46,26 +48,18 @@ pub struct Session {
     max_response_message_size: u32,
     /// Endpoint url for this session
     endpoint_url: UAString,
-    /// Maximum number of continuation points for browse
-    max_browse_continuation_points: usize,
-    /// Maximum number of continuation points for history.
-    max_history_continuation_points: usize,
-    /// Maximum number of continuation points for query.
-    max_query_continuation_points: usize,
     /// Client application description
     application_description: ApplicationDescription,
     /// Message security mode. Set on the channel, but cached here.
     message_security_mode: MessageSecurityMode,
     /// Time of last service request.
     last_service_request: ArcSwap<Instant>,
-    /// Continuation points for browse.
-    browse_continuation_points: HashMap<ByteString, BrowseContinuationPoint>,
-    /// Continuation points for history.
-    history_continuation_points: HashMap<ByteString, ContinuationPoint>,
-    /// Continuation points for querying.
-    query_continuation_points: HashMap<ByteString, QueryContinuationPoint>,
+    /// Continuation points for browse, history and query.
+    continuation_points: Box<dyn ContinuationPointStore>,
     /// User token.
     user_token: Option<UserToken>,
+    /// Namespaces visible to the user of the session, set when it is activated.
+    namespaces: Option<NamespaceMap>,
     /// Whether the session has been closed.
     is_closed: bool,
 }
@@ -90,6 +84,7 @@ impl Session {
         message_security_mode: MessageSecurityMode,
     ) -> Self {
         let (session_id, session_id_numeric) = next_session_id();
+        let continuation_points = info.continuation_point_store.create(info, &session_id);
         Self {
             session_id,
             session_id_numeric,
@@ -110,13 +105,9 @@ impl Session {
             max_request_message_size,
             max_response_message_size,
             endpoint_url,
-            max_browse_continuation_points: info.config.limits.max_browse_continuation_points,
-            max_history_continuation_points: info.config.limits.max_history_continuation_points,
-            max_query_continuation_points: info.config.limits.max_query_continuation_points,
-            browse_continuation_points: Default::default(),
-            history_continuation_points: Default::default(),
-            query_continuation_points: Default::default(),
+            continuation_points,
             user_token: None,
+            namespaces: None,
             application_description,
             message_security_mode,
             is_closed: false,
@@ -179,8 +170,10 @@ impl Session {
         identity: IdentityToken,
         locale_ids: Option<Vec<UAString>>,
         user_token: UserToken,
+        namespaces: NamespaceMap,
     ) {
         self.user_token = Some(user_token);
+        self.namespaces = Some(namespaces);
         self.secure_channel_id = secure_channel_id;
         self.session_nonce = server_nonce;
         self.user_identity = identity;
@@ -189,6 +182,7 @@ impl Session {
 
     pub(crate) fn close(&mut self) {
         self.is_closed = true;
+        self.continuation_points.clear();
     }
 
     /// Get the session ID of this session, this is known to the client, and is what they
@@ -220,74 +214,78 @@ impl Session {
         self.user_token.is_some() && !self.is_closed
     }
 
+    /// Get the namespaces visible to the user of this session, if it has been activated.
+    pub(crate) fn namespaces(&self) -> Option<&NamespaceMap> {
+        self.namespaces.as_ref()
+    }
+
     /// Get the secure channel ID of this session.
     pub fn secure_channel_id(&self) -> u32 {
         self.secure_channel_id
     }
-
     pub(crate) fn add_browse_continuation_point(
         &mut self,
         cp: BrowseContinuationPoint,
-    ) -> Result<(), ()> {
-        if self.max_browse_continuation_points <= self.browse_continuation_points.len()
-            && self.max_browse_continuation_points > 0
-        {
-            Err(())
-        } else {
-            self.browse_continuation_points.insert(cp.id.clone(), cp);
-            Ok(())
-        }
+    ) -> Result<(), StatusCode> {
+        let id = cp.id.clone();
+        let point = match cp.encode() {
+            Some(bytes) => ContinuationPoint::from_bytes(bytes),
+            None => ContinuationPoint::new(Box::new(cp)),
+        };
+        self.continuation_points
+            .add(ContinuationPointKind::Browse, id, point)
     }
 
     pub(crate) fn remove_browse_continuation_point(
         &mut self,
         id: &ByteString,
     ) -> Option<BrowseContinuationPoint> {
-        self.browse_continuation_points.remove(id)
+        let point = self
+            .continuation_points
+            .remove(ContinuationPointKind::Browse, id)?;
+        match point.as_bytes() {
+            Some(bytes) => BrowseContinuationPoint::decode(bytes),
+            None => point.take().map(|p| *p),
+        }
     }
 
     pub(crate) fn add_history_continuation_point(
         &mut self,
         id: &ByteString,
         cp: ContinuationPoint,
-    ) -> Result<(), ()> {
-        if self.max_history_continuation_points <= self.history_continuation_points.len()
-            && self.max_history_continuation_points > 0
-        {
-            Err(())
-        } else {
-            self.history_continuation_points.insert(id.clone(), cp);
-            Ok(())
-        }
+    ) -> Result<(), StatusCode> {
+        self.continuation_points
+            .add(ContinuationPointKind::History, id.clone(), cp)
     }
 
     pub(crate) fn remove_history_continuation_point(
         &mut self,
         id: &ByteString,
     ) -> Option<ContinuationPoint> {
-        self.history_continuation_points.remove(id)
+        self.continuation_points
+            .remove(ContinuationPointKind::History, id)
     }
 
     pub(crate) fn add_query_continuation_point(
         &mut self,
         id: &ByteString,
         cp: QueryContinuationPoint,
-    ) -> Result<(), ()> {
-        if self.max_query_continuation_points <= self.query_continuation_points.len()
-            && self.max_query_continuation_points > 0
-        {
-            Err(())
-        } else {
-            self.query_continuation_points.insert(id.clone(), cp);
-            Ok(())
-        }
+    ) -> Result<(), StatusCode> {
+        self.continuation_points.add(
+            ContinuationPointKind::Query,
+            id.clone(),
+            ContinuationPoint::new(Box::new(cp)),
+        )
     }
 
     pub(crate) fn remove_query_continuation_point(
         &mut self,
         id: &ByteString,
     ) -> Option<QueryContinuationPoint> {
-        self.query_continuation_points.remove(id)
+        self.continuation_points
+            .remove(ContinuationPointKind::Query, id)
+            .and_then(|p| p.take())
+            .map(|p| *p)
     }
 
     /// Get the application description of the client that created this session.
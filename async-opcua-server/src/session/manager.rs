@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{
         atomic::{AtomicU32, Ordering},
         Arc,
@@ -11,13 +11,13 @@ use opcua_core::{comms::secure_channel::SecureChannel, trace_read_lock, trace_wr
 use opcua_crypto::{random, security_policy::SecurityPolicy, CertificateStore};
 use parking_lot::RwLock;
 use tokio::sync::Notify;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::{identity_token::IdentityToken, info::ServerInfo};
 use opcua_types::{
     ActivateSessionRequest, ActivateSessionResponse, CloseSessionRequest, CloseSessionResponse,
-    CreateSessionRequest, CreateSessionResponse, Error, NodeId, ResponseHeader, SignatureData,
-    StatusCode,
+    CreateSessionRequest, CreateSessionResponse, Error, NamespaceMap, NodeId, ResponseHeader,
+    SignatureData, StatusCode,
 };
 
 use super::{instance::Session, message_handler::MessageHandler};
@@ -51,6 +51,14 @@ impl SessionManager {
         Self::find_by_token_int(&self.sessions, authentication_token)
     }
 
+    /// Return `true` if any session is currently bound to the secure channel
+    /// with ID `secure_channel_id`.
+    pub(crate) fn has_session_on_channel(&self, secure_channel_id: u32) -> bool {
+        self.sessions
+            .values()
+            .any(|s| s.read().secure_channel_id() == secure_channel_id)
+    }
+
     fn find_by_token_int(
         sessions: &HashMap<NodeId, Arc<RwLock<Session>>>,
         authentication_token: &NodeId,
@@ -66,6 +74,7 @@ impl SessionManager {
         channel: &mut SecureChannel,
         certificate_store: &RwLock<CertificateStore>,
         request: &CreateSessionRequest,
+        client_endpoint_url: &str,
     ) -> Result<CreateSessionResponse, StatusCode> {
         if self.sessions.len() >= self.info.config.limits.max_sessions {
             return Err(StatusCode::BadTooManySessions);
@@ -130,7 +139,11 @@ impl SessionManager {
         let authentication_token = NodeId::new(0, random::byte_string(32));
         let server_nonce = security_policy.random_nonce();
         let server_certificate = self.info.server_certificate_as_byte_string();
-        let server_endpoints = Some(endpoints);
+        // Report the same endpoint URLs as GetEndpoints, so clients can compare the two.
+        let server_endpoints = Some(
+            self.info
+                .substitute_endpoint_hosts(endpoints, client_endpoint_url),
+        );
 
         let session = Session::create(
             &self.info,
@@ -206,6 +219,31 @@ impl SessionManager {
         }
     }
 
+    /// Check that the application URI the client sent in `CreateSession` matches
+    /// the application URI in its certificate.
+    fn verify_client_application_uri(
+        info: &ServerInfo,
+        session: &Session,
+    ) -> Result<(), StatusCode> {
+        let Some(client_certificate) = session.client_certificate() else {
+            return Ok(());
+        };
+        let application_uri = session.application_description().application_uri.as_ref();
+        if let Err(e) = client_certificate.is_application_uri_valid(application_uri) {
+            if info
+                .config
+                .certificate_validation
+                .warn_on_application_uri_mismatch
+            {
+                warn!("Client application URI {application_uri} does not match its certificate, activating session anyway");
+            } else {
+                error!("activate_session, client application URI {application_uri} does not match its certificate");
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) fn expire_session(&mut self, id: &NodeId) {
         let Some(session) = self.sessions.remove(id) else {
             return;
@@ -330,6 +368,7 @@ pub(crate) async fn activate_session(
                     &session,
                     &request.client_signature,
                 )?;
+                SessionManager::verify_client_application_uri(&mgr.info, &session)?;
             }
             (endpoint_url, session.session_nonce().clone())
         };
@@ -347,9 +386,35 @@ pub(crate) async fn activate_session(
         )
         .await?;
 
-    let (server_nonce, session_id) = {
-        let mut session = trace_write_lock!(session_lck);
+    let session_id = trace_read_lock!(session_lck).session_id_numeric();
+    let namespaces =
+        handler.get_namespaces_for_user(session_lck.clone(), session_id, user_token.clone());
+    // Several sessions may be active on the same channel, possibly with different users.
+    // Messages on the channel are encoded using a single namespace map, so the namespaces
+    // of all sessions on the channel must agree.
+    let channel_namespaces = {
+        let mgr = trace_read_lock!(mgr_lck);
+        let others: Vec<_> = mgr
+            .sessions
+            .values()
+            .filter(|s| !Arc::ptr_eq(s, &session_lck))
+            .filter_map(|s| {
+                let s = trace_read_lock!(s);
+                if s.is_activated() && s.secure_channel_id() == secure_channel_id {
+                    s.namespaces().cloned()
+                } else {
+                    None
+                }
+            })
+            .collect();
+        merge_namespaces(&namespaces, others.iter()).ok_or_else(|| {
+            error!("activate_session, namespaces for the user of session {} conflict with another session on secure channel {}", session_id, secure_channel_id);
+            StatusCode::BadInvalidState
+        })?
+    };
 
+    let server_nonce = {
+        let mut session = trace_write_lock!(session_lck);
         if !session.is_activated() && session.secure_channel_id() != secure_channel_id {
             error!("activate session, rejected secure channel id {} for inactive session does not match one used to create session, {}", secure_channel_id, session.secure_channel_id());
             return Err(StatusCode::BadSecureChannelIdInvalid);
@@ -368,18 +433,13 @@ pub(crate) async fn activate_session(
             server_nonce,
             IdentityToken::new(request.user_identity_token.clone()),
             request.locale_ids.clone(),
-            user_token.clone(),
+            user_token,
+            namespaces,
         );
-        (
-            session.session_nonce().clone(),
-            session.session_id_numeric(),
-        )
+        session.session_nonce().clone()
     };
 
-    let namespaces = handler.get_namespaces_for_user(session_lck.clone(), session_id, user_token);
-    {
-        channel.set_namespaces(namespaces);
-    }
+    channel.set_namespaces(channel_namespaces);
 
     // TODO: Audit
 
@@ -390,3 +450,60 @@ pub(crate) async fn activate_session(
         diagnostic_infos: None,
     })
 }
+
+/// Merge the namespaces of a session being activated with those of the other sessions
+/// on the same secure channel. Returns `None` if the maps assign different indices to
+/// the same namespace, or the same index to different namespaces.
+fn merge_namespaces<'a>(
+    namespaces: &NamespaceMap,
+    others: impl Iterator<Item = &'a NamespaceMap>,
+) -> Option<NamespaceMap> {
+    let mut merged = namespaces.known_namespaces().clone();
+    for other in others {
+        for (uri, idx) in other.known_namespaces() {
+            if *merged.entry(uri.clone()).or_insert(*idx) != *idx {
+                return None;
+            }
+        }
+    }
+    let indices: HashSet<_> = merged.values().collect();
+    (indices.len() == merged.len()).then(|| NamespaceMap::new_full(merged))
+}
+
+#[cfg(test)]
+mod tests {
+    use opcua_types::NamespaceMap;
+
+    use super::merge_namespaces;
+
+    fn map(namespaces: &[(&str, u16)]) -> NamespaceMap {
+        NamespaceMap::new_full(
+            namespaces
+                .iter()
+                .map(|(uri, idx)| (uri.to_string(), *idx))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn merge_session_namespaces() {
+        let own = map(&[("http://opcfoundation.org/UA/", 0), ("urn:a", 1)]);
+        let merged = merge_namespaces(&own, std::iter::empty()).unwrap();
+        assert_eq!(merged.known_namespaces(), own.known_namespaces());
+
+        // Namespaces visible to another user are added.
+        let other = map(&[("http://opcfoundation.org/UA/", 0), ("urn:b", 2)]);
+        let merged = merge_namespaces(&own, [&other].into_iter()).unwrap();
+        assert_eq!(merged.get_index("urn:a"), Some(1));
+        assert_eq!(merged.get_index("urn:b"), Some(2));
+        assert_eq!(merged.known_namespaces().len(), 3);
+
+        // A namespace with a different index conflicts.
+        let other = map(&[("urn:a", 2)]);
+        assert!(merge_namespaces(&own, [&other].into_iter()).is_none());
+
+        // So does a different namespace with the same index.
+        let other = map(&[("urn:b", 1)]);
+        assert!(merge_namespaces(&own, [&other].into_iter()).is_none());
+    }
+}
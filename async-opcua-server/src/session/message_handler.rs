@@ -14,8 +14,8 @@ use crate::{
     subscriptions::{PendingPublish, SubscriptionCache},
 };
 use opcua_types::{
-    NamespaceMap, PublishRequest, ResponseHeader, ServiceFault, SetTriggeringRequest,
-    SetTriggeringResponse, StatusCode,
+    DiagnosticBits, NamespaceMap, PublishRequest, ResponseHeader, ServiceFault,
+    SetTriggeringRequest, SetTriggeringResponse, StatusCode,
 };
 
 use super::{controller::Response, instance::Session};
@@ -87,7 +87,15 @@ pub(super) struct Request<T> {
 macro_rules! service_fault {
     ($req:ident, $status:expr) => {
         Response {
-            message: opcua_types::ServiceFault::new($req.request_handle, $status).into(),
+            message: $req
+                .info
+                .service_fault_diagnostics
+                .service_fault(
+                    $req.request_handle,
+                    $req.request.request_header.return_diagnostics,
+                    $status,
+                )
+                .into(),
             request_id: $req.request_id,
         }
     };
@@ -156,6 +164,7 @@ macro_rules! async_service_call {
 struct RequestData {
     request_id: u32,
     request_handle: u32,
+    return_diagnostics: DiagnosticBits,
     session: Arc<RwLock<Session>>,
     token: UserToken,
     session_id: u32,
@@ -190,6 +199,7 @@ impl MessageHandler {
         let data = RequestData {
             request_id,
             request_handle: message.request_handle(),
+            return_diagnostics: message.request_header().return_diagnostics,
             session,
             token,
             session_id,
@@ -243,7 +253,9 @@ impl MessageHandler {
             RequestMessage::Republish(request) => {
                 HandleMessageResult::SyncMessage(Response::from_result(
                     self.subscriptions.republish(data.session_id, &request),
+                    &self.info,
                     data.request_handle,
+                    data.return_diagnostics,
                     data.request_id,
                 ))
             }
@@ -256,7 +268,9 @@ impl MessageHandler {
                         &request,
                         &self.info,
                     ),
+                    &self.info,
                     data.request_handle,
+                    data.return_diagnostics,
                     data.request_id,
                 ))
             }
@@ -265,7 +279,9 @@ impl MessageHandler {
                 HandleMessageResult::SyncMessage(Response::from_result(
                     self.subscriptions
                         .modify_subscription(data.session_id, &request, &self.info),
+                    &self.info,
                     data.request_handle,
+                    data.return_diagnostics,
                     data.request_id,
                 ))
             }
@@ -274,7 +290,9 @@ impl MessageHandler {
                 HandleMessageResult::SyncMessage(Response::from_result(
                     self.subscriptions
                         .set_publishing_mode(data.session_id, &request),
+                    &self.info,
                     data.request_handle,
+                    data.return_diagnostics,
                     data.request_id,
                 ))
             }
@@ -431,7 +449,9 @@ impl MessageHandler {
 
         HandleMessageResult::SyncMessage(Response::from_result(
             result,
+            &self.info,
             data.request_handle,
+            data.return_diagnostics,
             data.request_id,
         ))
     }
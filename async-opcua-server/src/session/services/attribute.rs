@@ -1,17 +1,19 @@
+use hashbrown::HashMap;
 use opcua_core::trace_write_lock;
 use tracing::{debug_span, Instrument};
 
 use crate::{
     node_manager::{
-        consume_results, HistoryNode, HistoryReadDetails, HistoryUpdateDetails, HistoryUpdateNode,
-        NodeManagers, ReadNode, WriteNode,
+        consume_results, DynNodeManager, HistoryNode, HistoryReadDetails, HistoryUpdateDetails,
+        HistoryUpdateNode, NodeManagers, ReadNode, RequestContext, WriteNode,
     },
     session::{controller::Response, message_handler::Request},
 };
 use opcua_types::{
     ByteString, DeleteAtTimeDetails, ExtensionObject, HistoryReadRequest, HistoryReadResponse,
-    HistoryReadResult, HistoryUpdateRequest, HistoryUpdateResponse, NodeId, ObjectId, ReadRequest,
-    ReadResponse, ResponseHeader, StatusCode, TimestampsToReturn, WriteRequest, WriteResponse,
+    HistoryReadResult, HistoryUpdateRequest, HistoryUpdateResponse, NodeId, NumericRange, ObjectId,
+    ReadRequest, ReadResponse, ResponseHeader, StatusCode, TimestampsToReturn, WriteRequest,
+    WriteResponse,
 };
 pub(crate) async fn read(node_managers: NodeManagers, request: Request<ReadRequest>) -> Response {
     let mut context = request.context();
@@ -34,11 +36,13 @@ pub(crate) async fn read(node_managers: NodeManagers, request: Request<ReadReque
 
     for (idx, node_manager) in node_managers.into_iter().enumerate() {
         context.current_node_manager_index = idx;
+        // Hidden nodes are left as `BadNodeIdUnknown`.
         let mut batch: Vec<_> = results
             .iter_mut()
             .filter(|n| {
                 node_manager.owns_node(&n.node().node_id)
                     && n.status() == StatusCode::BadNodeIdUnknown
+                    && context.is_node_visible(&n.node().node_id)
             })
             .collect();
 
@@ -103,6 +107,13 @@ pub(crate) async fn write(node_managers: NodeManagers, request: Request<WriteReq
             continue;
         }
 
+        if node_manager.supports_write_transactions() {
+            batch = write_transactions(&context, &**node_manager, batch).await;
+            if batch.is_empty() {
+                continue;
+            }
+        }
+
         if let Err(e) = node_manager
             .write(&context, &mut batch)
             .instrument(debug_span!("Write", node_manager = %node_manager.name()))
@@ -128,6 +139,56 @@ pub(crate) async fn write(node_managers: NodeManagers, request: Request<WriteReq
     }
 }
 
+/// Run any groups of writes to the same node as write transactions,
+/// returning the writes that should be handled independently.
+async fn write_transactions<'a>(
+    context: &RequestContext,
+    node_manager: &DynNodeManager,
+    batch: Vec<&'a mut WriteNode>,
+) -> Vec<&'a mut WriteNode> {
+    let mut counts: HashMap<NodeId, usize> = HashMap::new();
+    for node in &batch {
+        if node.value().index_range == NumericRange::None {
+            *counts.entry(node.value().node_id.clone()).or_default() += 1;
+        }
+    }
+
+    let mut groups: HashMap<NodeId, Vec<&'a mut WriteNode>> = HashMap::new();
+    let mut remaining = Vec::new();
+    for node in batch {
+        if node.value().index_range == NumericRange::None
+            && counts.get(&node.value().node_id).is_some_and(|c| *c > 1)
+        {
+            groups
+                .entry(node.value().node_id.clone())
+                .or_default()
+                .push(node);
+        } else {
+            remaining.push(node);
+        }
+    }
+
+    for (node_id, nodes) in groups {
+        let writes: Vec<_> = nodes
+            .iter()
+            .map(|n| (n.value().attribute_id, n.value().value.clone()))
+            .collect();
+        let status = match node_manager
+            .write_transaction(context, &node_id, &writes)
+            .instrument(debug_span!("WriteTransaction", node_manager = %node_manager.name()))
+            .await
+        {
+            Ok(()) => StatusCode::Good,
+            Err(e) => e,
+        };
+        for node in nodes {
+            node.set_status(status);
+        }
+    }
+
+    remaining
+}
+
 pub(crate) async fn history_read(
     node_managers: NodeManagers,
     request: Request<HistoryReadRequest>,
@@ -24,7 +24,10 @@ pub(crate) async fn call(node_managers: NodeManagers, request: Request<CallReque
         let mut owned: Vec<_> = calls
             .iter_mut()
             .filter(|c| {
-                node_manager.owns_node(c.method_id()) && c.status() == StatusCode::BadMethodInvalid
+                // Methods defined on a type may be called on instances of the type, in which case
+                // the node manager owning the object should handle the call.
+                (node_manager.owns_node(c.method_id()) || node_manager.owns_node(c.object_id()))
+                    && c.status() == StatusCode::BadMethodInvalid
             })
             .collect();
 
@@ -3,14 +3,14 @@ use std::collections::HashMap;
 use crate::{
     node_manager::{MonitoredItemRef, NodeManagers, RequestContext},
     session::{controller::Response, message_handler::Request},
-    subscriptions::CreateMonitoredItem,
+    subscriptions::{CreateMonitoredItem, ModifyMonitoredItem},
 };
 use opcua_core::ResponseMessage;
 use opcua_types::{
     AttributeId, BrowsePath, CreateMonitoredItemsRequest, CreateMonitoredItemsResponse,
     DataChangeFilter, DeadbandType, DeleteMonitoredItemsRequest, DeleteMonitoredItemsResponse,
-    ModifyMonitoredItemsRequest, ModifyMonitoredItemsResponse, NodeId, Range, ReadRequest,
-    ReferenceTypeId, RelativePath, RelativePathElement, RequestHeader, ResponseHeader,
+    ExtensionObject, ModifyMonitoredItemsRequest, ModifyMonitoredItemsResponse, NodeId, Range,
+    ReadRequest, ReferenceTypeId, RelativePath, RelativePathElement, RequestHeader, ResponseHeader,
     SetMonitoringModeRequest, SetMonitoringModeResponse, StatusCode, TimestampsToReturn,
     TranslateBrowsePathsToNodeIdsRequest, Variant,
 };
@@ -138,6 +138,12 @@ async fn get_eu_range(
     res
 }
 
+fn has_percent_deadband(filter: &ExtensionObject) -> bool {
+    filter
+        .inner_as::<DataChangeFilter>()
+        .is_some_and(|f| f.deadband_type == DeadbandType::Percent as u32)
+}
+
 pub(crate) async fn create_monitored_items(
     node_managers: NodeManagers,
     request: Request<CreateMonitoredItemsRequest>,
@@ -166,20 +172,11 @@ pub(crate) async fn create_monitored_items(
     }
 
     // Try to get EURange for each item with a percent deadband filter.
-    let mut items_needing_deadband = Vec::new();
-    for item in &items_to_create {
-        let Some(filter) = item
-            .requested_parameters
-            .filter
-            .inner_as::<DataChangeFilter>()
-        else {
-            continue;
-        };
-
-        if filter.deadband_type == DeadbandType::Percent as u32 {
-            items_needing_deadband.push(&item.item_to_monitor.node_id);
-        }
-    }
+    let items_needing_deadband: Vec<_> = items_to_create
+        .iter()
+        .filter(|i| has_percent_deadband(&i.requested_parameters.filter))
+        .map(|i| &i.item_to_monitor.node_id)
+        .collect();
     let ranges = get_eu_range(&items_needing_deadband, &context, &node_managers).await;
 
     let mut items: Vec<_> = {
@@ -279,6 +276,33 @@ pub(crate) async fn modify_monitored_items(
         request.info.operational_limits.max_monitored_items_per_call
     );
 
+    // Items that did not use a percent deadband when they were created have not
+    // read their EURange, so get it for any item changed to use a percent deadband.
+    let items_needing_deadband: Vec<_> = items_to_modify
+        .iter()
+        .filter(|i| has_percent_deadband(&i.requested_parameters.filter))
+        .map(|i| i.monitored_item_id)
+        .collect();
+    let mut eu_ranges = hashbrown::HashMap::new();
+    if !items_needing_deadband.is_empty() {
+        let node_ids = request.subscriptions.get_monitored_item_node_ids(
+            request.session_id,
+            request.request.subscription_id,
+            &items_needing_deadband,
+        );
+        let ranges = get_eu_range(
+            &node_ids.iter().map(|(_, n)| n).collect::<Vec<_>>(),
+            &context,
+            &node_managers,
+        )
+        .await;
+        eu_ranges.extend(
+            node_ids
+                .into_iter()
+                .filter_map(|(id, node_id)| ranges.get(&node_id).map(|r| (id, *r))),
+        );
+    }
+
     // Call modify first, then only pass successful modify's to the node managers.
     let results = {
         let type_tree = context.get_type_tree_for_user();
@@ -288,7 +312,13 @@ pub(crate) async fn modify_monitored_items(
             request.request.subscription_id,
             &request.info,
             request.request.timestamps_to_return,
-            items_to_modify,
+            items_to_modify
+                .into_iter()
+                .map(|request| ModifyMonitoredItem {
+                    eu_range: eu_ranges.get(&request.monitored_item_id).copied(),
+                    request,
+                })
+                .collect(),
             type_tree.get(),
         ) {
             Ok(r) => r,
@@ -46,13 +46,21 @@ pub(crate) async fn browse(
             .min(request.request.requested_max_references_per_node as usize)
     };
 
-    let mut nodes: Vec<_> = nodes_to_browse
-        .into_iter()
-        .enumerate()
-        .map(|(idx, r)| BrowseNode::new(r, max_references_per_node, idx))
-        .collect();
-
-    let mut results: Vec<_> = (0..nodes.len()).map(|_| None).collect();
+    let mut results: Vec<_> = (0..nodes_to_browse.len()).map(|_| None).collect();
+    let mut nodes = Vec::with_capacity(nodes_to_browse.len());
+    for (idx, r) in nodes_to_browse.into_iter().enumerate() {
+        if context.is_node_visible(&r.node_id) {
+            let mut node = BrowseNode::new(r, max_references_per_node, idx);
+            node.set_visibility_context(&context);
+            nodes.push(node);
+        } else {
+            results[idx] = Some(BrowseResult {
+                status_code: StatusCode::BadNodeIdUnknown,
+                continuation_point: ByteString::null(),
+                references: None,
+            });
+        }
+    }
     let node_manager_count = node_managers.len();
 
     for (node_manager_index, node_manager) in node_managers.iter().enumerate() {
@@ -153,7 +161,8 @@ pub(crate) async fn browse(
     }
 
     // Cannot be None here, since we are guaranteed to always empty out nodes.
-    let results = results.into_iter().map(Option::unwrap).collect();
+    let mut results: Vec<_> = results.into_iter().map(Option::unwrap).collect();
+    remove_hidden_references(&context, &mut results);
 
     Response {
         message: BrowseResponse {
@@ -166,6 +175,20 @@ pub(crate) async fn browse(
     }
 }
 
+/// Remove references that are hidden from the current user. Hidden references
+/// are normally rejected as they are added to the result, this only catches
+/// references that node managers add without validating them.
+fn remove_hidden_references(context: &RequestContext, results: &mut [BrowseResult]) {
+    if context.info.node_visibility_filter.is_none() {
+        return;
+    }
+    for result in results {
+        if let Some(references) = &mut result.references {
+            references.retain(|r| context.is_reference_visible(&r.reference_type_id, &r.node_id));
+        }
+    }
+}
+
 pub(crate) async fn browse_next(
     node_managers: NodeManagers,
     request: Request<BrowseNextRequest>,
@@ -184,7 +207,9 @@ pub(crate) async fn browse_next(
         for (idx, point) in nodes_to_browse.into_iter().enumerate() {
             let point = session.remove_browse_continuation_point(&point);
             if let Some(point) = point {
-                nodes.push(BrowseNode::from_continuation_point(point, idx));
+                let mut node = BrowseNode::from_continuation_point(point, idx);
+                node.set_visibility_context(&context);
+                nodes.push(node);
             } else {
                 results[idx] = Some(BrowseResult {
                     status_code: StatusCode::BadContinuationPointInvalid,
@@ -196,7 +221,7 @@ pub(crate) async fn browse_next(
         nodes
     };
 
-    let results = if request.request.release_continuation_points {
+    let mut results: Vec<_> = if request.request.release_continuation_points {
         results
             .into_iter()
             .map(|r| {
@@ -324,6 +349,7 @@ pub(crate) async fn browse_next(
         // Cannot be None here, since we are guaranteed to always empty out nodes.
         results.into_iter().map(Option::unwrap).collect()
     };
+    remove_hidden_references(&context, &mut results);
 
     Response {
         message: BrowseNextResponse {
@@ -0,0 +1,211 @@
+use hashbrown::HashMap;
+use opcua_nodes::{Event, ParsedContentFilter, ParsedEventFilter, ParsedSimpleAttributeOperand};
+use opcua_types::Variant;
+
+/// Cached results for a clause, by the index of the event in the batch.
+type ByEvent<T> = HashMap<usize, T>;
+
+/// Cache of event filter evaluations, used when notifying monitored items of a batch of events.
+///
+/// Monitored items listening to the same event source often use identical
+/// where clauses and select clauses. Each distinct where clause is evaluated only
+/// once per event, and each distinct list of select clauses is only fetched once
+/// per event, the result is then shared between all monitored items using it.
+///
+/// Events are identified by their index in the batch, so the cache must not
+/// outlive the batch of events it is used for.
+///
+/// This is used by [SubscriptionCache::notify_events](super::SubscriptionCache::notify_events).
+#[derive(Default)]
+pub struct EventFilterCache {
+    where_clauses: Vec<(ParsedContentFilter, ByEvent<bool>)>,
+    select_clauses: Vec<(Vec<ParsedSimpleAttributeOperand>, ByEvent<Vec<Variant>>)>,
+}
+
+impl EventFilterCache {
+    /// Evaluate `filter` for `event`, the event at `event_index` in the batch,
+    /// returning the selected fields if the event passes the where clause.
+    pub fn evaluate(
+        &mut self,
+        filter: &ParsedEventFilter,
+        event_index: usize,
+        event: &dyn Event,
+    ) -> Option<Vec<Variant>> {
+        let key = event_index;
+
+        let where_clause = filter.content_filter();
+        let idx = match self
+            .where_clauses
+            .iter()
+            .position(|(f, _)| f == where_clause)
+        {
+            Some(idx) => idx,
+            None => {
+                self.where_clauses
+                    .push((where_clause.clone(), HashMap::new()));
+                self.where_clauses.len() - 1
+            }
+        };
+        let passed = *self.where_clauses[idx]
+            .1
+            .entry(key)
+            .or_insert_with(|| where_clause.evaluate(event));
+        if !passed {
+            return None;
+        }
+
+        let select_clauses = filter.select_clauses();
+        let idx = match self
+            .select_clauses
+            .iter()
+            .position(|(s, _)| s == select_clauses)
+        {
+            Some(idx) => idx,
+            None => {
+                self.select_clauses
+                    .push((select_clauses.to_vec(), HashMap::new()));
+                self.select_clauses.len() - 1
+            }
+        };
+        Some(
+            self.select_clauses[idx]
+                .1
+                .entry(key)
+                .or_insert_with(|| ParsedEventFilter::select_fields(select_clauses, event))
+                .clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use opcua_nodes::{BaseEventType, DefaultTypeTree, Event, EventField, ParsedEventFilter};
+    use opcua_types::{
+        AttributeId, ByteString, ContentFilter, ContentFilterElement, DateTime, EventFilter,
+        FilterOperator, NodeId, NumericRange, ObjectTypeId, Operand, QualifiedName,
+        SimpleAttributeOperand, Variant,
+    };
+
+    use super::EventFilterCache;
+
+    /// Event that counts the number of fields fetched from it.
+    struct CountingEvent {
+        base: BaseEventType,
+        fetched: AtomicUsize,
+    }
+
+    impl EventField for CountingEvent {
+        fn get_value(
+            &self,
+            attribute_id: AttributeId,
+            index_range: &NumericRange,
+            remaining_path: &[QualifiedName],
+        ) -> Variant {
+            self.base
+                .get_value(attribute_id, index_range, remaining_path)
+        }
+    }
+
+    impl Event for CountingEvent {
+        fn get_field(
+            &self,
+            type_definition_id: &NodeId,
+            attribute_id: AttributeId,
+            index_range: &NumericRange,
+            browse_path: &[QualifiedName],
+        ) -> Variant {
+            self.fetched.fetch_add(1, Ordering::Relaxed);
+            self.base
+                .get_field(type_definition_id, attribute_id, index_range, browse_path)
+        }
+
+        fn time(&self) -> &DateTime {
+            self.base.time()
+        }
+    }
+
+    fn event(severity: u16) -> CountingEvent {
+        let mut base =
+            BaseEventType::new_now(ObjectTypeId::BaseEventType, ByteString::null(), "message");
+        base.severity = severity;
+        CountingEvent {
+            base,
+            fetched: AtomicUsize::new(0),
+        }
+    }
+
+    fn filter(min_severity: u16, select: &[&str]) -> ParsedEventFilter {
+        let type_tree = DefaultTypeTree::new();
+        let (_, filter) = ParsedEventFilter::new(
+            EventFilter {
+                select_clauses: Some(
+                    select
+                        .iter()
+                        .map(|s| {
+                            SimpleAttributeOperand::new(
+                                ObjectTypeId::BaseEventType,
+                                s,
+                                AttributeId::Value,
+                                NumericRange::None,
+                            )
+                        })
+                        .collect(),
+                ),
+                where_clause: ContentFilter {
+                    elements: Some(vec![ContentFilterElement {
+                        filter_operator: FilterOperator::GreaterThanOrEqual,
+                        filter_operands: Some(vec![
+                            (&Operand::simple_attribute(
+                                ObjectTypeId::BaseEventType,
+                                "Severity",
+                                AttributeId::Value,
+                                NumericRange::None,
+                            ))
+                                .into(),
+                            (&Operand::literal(min_severity)).into(),
+                        ]),
+                    }]),
+                },
+            },
+            &type_tree,
+        );
+        filter.unwrap()
+    }
+
+    #[test]
+    fn shared_filter_evaluation() {
+        let filters: Vec<_> = (0..100)
+            .map(|i| {
+                if i % 2 == 0 {
+                    filter(500, &["Message", "Severity"])
+                } else {
+                    filter(500, &["Severity"])
+                }
+            })
+            .collect();
+        let high = event(800);
+        let low = event(100);
+
+        let mut cache = EventFilterCache::default();
+        for f in &filters {
+            for (idx, evt) in [&high, &low].into_iter().enumerate() {
+                let res = cache.evaluate(f, idx, evt);
+                assert_eq!(res, f.evaluate(evt, 0).and_then(|r| r.event_fields));
+            }
+        }
+
+        // The uncached evaluation fetches the where clause field, and the select
+        // clauses if the event passes, for each filter.
+        let uncached_high = 50 * (1 + 2) + 50 * (1 + 1);
+        let uncached_low = 100;
+        // The cache evaluates the where clause once per event, and each distinct
+        // list of select clauses once for the event that passed.
+        assert_eq!(
+            high.fetched.load(Ordering::Relaxed),
+            uncached_high + 1 + 2 + 1
+        );
+        assert_eq!(low.fetched.load(Ordering::Relaxed), uncached_low + 1);
+    }
+}
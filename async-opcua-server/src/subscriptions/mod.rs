@@ -1,3 +1,4 @@
+mod event_filter_cache;
 mod monitored_item;
 mod session_subscriptions;
 mod subscription;
@@ -5,7 +6,9 @@ mod subscription;
 use std::{sync::Arc, time::Instant};
 
 use chrono::Utc;
-use hashbrown::{Equivalent, HashMap};
+pub use event_filter_cache::EventFilterCache;
+use hashbrown::{Equivalent, HashMap, HashSet};
+pub(crate) use monitored_item::ModifyMonitoredItem;
 pub use monitored_item::{CreateMonitoredItem, MonitoredItem};
 use opcua_core::{trace_read_lock, trace_write_lock, ResponseMessage};
 use opcua_nodes::{Event, TypeTree};
@@ -19,11 +22,10 @@ use opcua_core::sync::{Mutex, RwLock};
 use opcua_types::{
     AttributeId, CreateSubscriptionRequest, CreateSubscriptionResponse, DataEncoding, DataValue,
     DateTimeUtc, MessageSecurityMode, ModifySubscriptionRequest, ModifySubscriptionResponse,
-    MonitoredItemCreateResult, MonitoredItemModifyRequest, MonitoringMode, NodeId,
-    NotificationMessage, NumericRange, ObjectId, PublishRequest, RepublishRequest,
-    RepublishResponse, ResponseHeader, SetPublishingModeRequest, SetPublishingModeResponse,
-    StatusCode, TimestampsToReturn, TransferResult, TransferSubscriptionsRequest,
-    TransferSubscriptionsResponse,
+    MonitoredItemCreateResult, MonitoringMode, NodeId, NotificationMessage, NumericRange, ObjectId,
+    PublishRequest, RepublishRequest, RepublishResponse, ResponseHeader, SetPublishingModeRequest,
+    SetPublishingModeResponse, StatusCode, SubscriptionDiagnosticsDataType, TimestampsToReturn,
+    TransferResult, TransferSubscriptionsRequest, TransferSubscriptionsResponse,
 };
 
 use super::{
@@ -58,6 +60,12 @@ struct MonitoredItemEntry {
     index_range: NumericRange,
 }
 
+#[cfg(feature = "generated-address-space")]
+struct RetainedCondition {
+    source: NodeId,
+    event: Box<dyn Event + Send + Sync>,
+}
+
 struct SubscriptionCacheInner {
     /// Map from session ID to subscription cache
     session_subscriptions: HashMap<u32, Arc<Mutex<SessionSubscriptions>>>,
@@ -65,6 +73,11 @@ struct SubscriptionCacheInner {
     subscription_to_session: HashMap<u32, u32>,
     /// Map from notifier node ID to monitored item handles.
     monitored_items: HashMap<MonitoredItemKey, HashMap<MonitoredItemHandle, MonitoredItemEntry>>,
+    /// Nodes registered as event sources.
+    event_sources: HashSet<NodeId>,
+    /// Map from condition ID to the last event of each retained condition.
+    #[cfg(feature = "generated-address-space")]
+    retained_conditions: HashMap<NodeId, RetainedCondition>,
 }
 
 /// Structure storing all subscriptions and monitored items on the server.
@@ -86,6 +99,9 @@ impl SubscriptionCache {
                 session_subscriptions: HashMap::new(),
                 subscription_to_session: HashMap::new(),
                 monitored_items: HashMap::new(),
+                event_sources: HashSet::new(),
+                #[cfg(feature = "generated-address-space")]
+                retained_conditions: HashMap::new(),
             }),
             limits,
         }
@@ -100,6 +116,15 @@ impl SubscriptionCache {
         inner.session_subscriptions.get(&session_id).cloned()
     }
 
+    /// Get the diagnostics of every subscription on the server.
+    pub fn subscription_diagnostics(&self) -> Vec<SubscriptionDiagnosticsDataType> {
+        let lck = trace_read_lock!(self.inner);
+        lck.session_subscriptions
+            .values()
+            .flat_map(|s| s.lock().subscription_diagnostics())
+            .collect()
+    }
+
     /// This is the periodic subscription tick where we check for
     /// triggered subscriptions.
     ///
@@ -109,6 +134,8 @@ impl SubscriptionCache {
         // be more efficient, and would be more responsive.
         let mut to_delete = Vec::new();
         let mut items_to_delete = Vec::new();
+        let mut dropped = 0;
+        let mut dropped_retained = 0;
         {
             let now = Utc::now();
             let now_instant = Instant::now();
@@ -119,11 +146,21 @@ impl SubscriptionCache {
                     sub_lck.session().clone(),
                     sub_lck.tick(&now, now_instant, TickReason::TickTimerFired),
                 ));
+                dropped += sub_lck.take_dropped_notifications();
+                dropped_retained += sub_lck.take_dropped_retained_messages();
                 if sub_lck.is_ready_to_delete() {
                     to_delete.push(*session_id);
                 }
             }
         }
+        context
+            .info
+            .diagnostics
+            .inc_dropped_notification_count(dropped);
+        context
+            .info
+            .diagnostics
+            .inc_dropped_retained_message_count(dropped_retained);
         if !to_delete.is_empty() {
             let mut lck = trace_write_lock!(self.inner);
             for id in to_delete {
@@ -195,6 +232,53 @@ impl SubscriptionCache {
         cache_lck.get_monitored_item_count(subscription_id)
     }
 
+    /// Get the IDs and client handles of the monitored items in a subscription.
+    ///
+    /// If `session_id` is given the subscription must belong to that session,
+    /// otherwise the subscription may belong to any session.
+    pub(crate) fn get_monitored_items(
+        &self,
+        session_id: Option<u32>,
+        subscription_id: u32,
+    ) -> Result<(Vec<u32>, Vec<u32>), StatusCode> {
+        let cache = {
+            let lck = trace_read_lock!(self.inner);
+            let session_id = match session_id {
+                Some(id) => id,
+                None => *lck
+                    .subscription_to_session
+                    .get(&subscription_id)
+                    .ok_or(StatusCode::BadSubscriptionIdInvalid)?,
+            };
+            lck.session_subscriptions
+                .get(&session_id)
+                .cloned()
+                .ok_or(StatusCode::BadSessionIdInvalid)?
+        };
+        let cache_lck = cache.lock();
+        let sub = cache_lck
+            .get(subscription_id)
+            .ok_or(StatusCode::BadSubscriptionIdInvalid)?;
+        Ok(sub.items().map(|i| (i.id(), i.client_handle())).unzip())
+    }
+
+    /// Get the node IDs of the given monitored items, skipping any items that do not exist.
+    pub(crate) fn get_monitored_item_node_ids(
+        &self,
+        session_id: u32,
+        subscription_id: u32,
+        monitored_item_ids: &[u32],
+    ) -> Vec<(u32, NodeId)> {
+        let Some(cache) = ({
+            let lck = trace_read_lock!(self.inner);
+            lck.session_subscriptions.get(&session_id).cloned()
+        }) else {
+            return Vec::new();
+        };
+        let cache_lck = cache.lock();
+        cache_lck.get_monitored_item_node_ids(subscription_id, monitored_item_ids)
+    }
+
     pub(crate) fn create_subscription(
         &self,
         session_id: u32,
@@ -392,12 +476,126 @@ impl SubscriptionCache {
         }
     }
 
+    /// Notify specific monitored items about data changes, for values that
+    /// may be different for each monitored item, for example because they depend on the user.
+    #[cfg(any(feature = "generated-address-space", feature = "remote-node-manager"))]
+    pub(crate) fn notify_data_change_for_items(
+        &self,
+        items: impl Iterator<Item = (MonitoredItemHandle, DataValue)>,
+    ) {
+        let lck = trace_read_lock!(self.inner);
+        let mut by_subscription: HashMap<u32, Vec<_>> = HashMap::new();
+        for (handle, dv) in items {
+            by_subscription
+                .entry(handle.subscription_id)
+                .or_default()
+                .push((handle, dv));
+        }
+
+        for (sub_id, items) in by_subscription {
+            let Some(session_id) = lck.subscription_to_session.get(&sub_id) else {
+                continue;
+            };
+            let Some(cache) = lck.session_subscriptions.get(session_id) else {
+                continue;
+            };
+            let mut cache_lck = cache.lock();
+            cache_lck.notify_data_changes(items);
+        }
+    }
+
+    /// Notify any monitored items on the value of `node_id` that the semantics of the
+    /// variable have changed, for example because its `EURange`, `EngineeringUnits` or
+    /// `EnumStrings` property was modified.
+    ///
+    /// The next value reported by each monitored item has the `SemanticsChanged`
+    /// info bit set in the status code. `eu_range` is the current `EURange` of the variable,
+    /// if it has one, which is used by percent deadband filters from now on.
+    pub fn notify_semantics_changed(&self, node_id: &NodeId, eu_range: Option<(f64, f64)>) {
+        let lck = trace_read_lock!(self.inner);
+        let key = MonitoredItemKeyRef {
+            id: node_id,
+            attribute_id: AttributeId::Value,
+        };
+        let Some(items) = lck.monitored_items.get(&key) else {
+            return;
+        };
+
+        let mut by_subscription: HashMap<u32, Vec<_>> = HashMap::new();
+        for handle in items.keys() {
+            by_subscription
+                .entry(handle.subscription_id)
+                .or_default()
+                .push(*handle);
+        }
+
+        for (sub_id, items) in by_subscription {
+            let Some(session_id) = lck.subscription_to_session.get(&sub_id) else {
+                continue;
+            };
+            let Some(cache) = lck.session_subscriptions.get(session_id) else {
+                continue;
+            };
+            let mut cache_lck = cache.lock();
+            cache_lck.notify_semantics_changed(items, eu_range);
+        }
+    }
+
+    /// Return `true` if any subscription on the server has data that has not yet
+    /// been published to the client.
+    pub fn has_pending_notifications(&self) -> bool {
+        let lck = trace_read_lock!(self.inner);
+        lck.session_subscriptions
+            .values()
+            .any(|s| s.lock().has_pending_data())
+    }
+
+    /// Register the node given by `node_id` as a source of events.
+    ///
+    /// This does not set the `EventNotifier` attribute of the node, use
+    /// [`InMemoryNodeManager::enable_events`](crate::node_manager::memory::InMemoryNodeManager::enable_events)
+    /// to do both.
+    pub fn register_event_source(&self, node_id: &NodeId) {
+        let mut lck = trace_write_lock!(self.inner);
+        lck.event_sources.insert(node_id.clone());
+    }
+
+    /// Return `true` if the node given by `node_id` is a source of events, meaning
+    /// that it is either the `Server` object, or has been registered with
+    /// [`SubscriptionCache::register_event_source`].
+    pub fn is_event_source(&self, node_id: &NodeId) -> bool {
+        if node_id == &ObjectId::Server {
+            return true;
+        }
+        let lck = trace_read_lock!(self.inner);
+        lck.event_sources.contains(node_id)
+    }
+
+    /// Return `true` if any enabled monitored item is subscribed to events emitted
+    /// from `node_id`. Since all events are also reported on the `Server` object, this
+    /// includes monitored items on the `Server` object.
+    ///
+    /// This can be used to avoid building events that no client would receive.
+    pub fn has_event_monitored_items(&self, node_id: &NodeId) -> bool {
+        let lck = trace_read_lock!(self.inner);
+        let server_id: NodeId = ObjectId::Server.into();
+        let has_items = |id| {
+            lck.monitored_items
+                .get(&MonitoredItemKeyRef {
+                    id,
+                    attribute_id: AttributeId::EventNotifier,
+                })
+                .is_some_and(|items| items.values().any(|i| i.enabled))
+        };
+        has_items(node_id) || has_items(&server_id)
+    }
+
     /// Notify listening clients to events. Without a custom node manager implementing
     /// event history, this is the only way to report events in the server.
     pub fn notify_events<'a>(&self, items: impl Iterator<Item = (&'a dyn Event, &'a NodeId)>) {
         let lck = trace_read_lock!(self.inner);
         let mut by_subscription = HashMap::<u32, Vec<_>>::new();
-        for (evt, notifier) in items {
+        for (evt_idx, (evt, notifier)) in items.enumerate() {
             let notifier_key = MonitoredItemKeyRef {
                 id: notifier,
                 attribute_id: AttributeId::EventNotifier,
@@ -410,7 +608,7 @@ impl SubscriptionCache {
                     by_subscription
                         .entry(handle.subscription_id)
                         .or_default()
-                        .push((*handle, evt));
+                        .push((*handle, evt_idx, evt));
                 }
             }
             // The server gets all notifications.
@@ -430,11 +628,14 @@ impl SubscriptionCache {
                     by_subscription
                         .entry(handle.subscription_id)
                         .or_default()
-                        .push((*handle, evt));
+                        .push((*handle, evt_idx, evt));
                 }
             }
         }
 
+        // Filters are often shared between monitored items on the same source,
+        // so cache filter evaluation for the events across all subscriptions.
+        let mut filter_cache = EventFilterCache::default();
         for (sub_id, items) in by_subscription {
             let Some(session_id) = lck.subscription_to_session.get(&sub_id) else {
                 continue;
@@ -443,10 +644,69 @@ impl SubscriptionCache {
                 continue;
             };
             let mut cache_lck = cache.lock();
-            cache_lck.notify_events(items);
+            cache_lck.notify_events(items, &mut filter_cache);
         }
     }
 
+    /// Notify listening clients of `event`, emitted by the condition `condition_id`
+    /// from the event source `source`.
+    ///
+    /// If `retain` is `true` the event is stored as the current state of the condition,
+    /// and sent again to clients calling `ConditionRefresh`. Otherwise any stored state
+    /// of the condition is removed.
+    ///
+    /// Conditions are only retained with the `generated-address-space` feature, which
+    /// provides the `ConditionRefresh` method.
+    pub fn notify_condition_event(
+        &self,
+        condition_id: &NodeId,
+        source: &NodeId,
+        event: Box<dyn Event + Send + Sync>,
+        retain: bool,
+    ) {
+        self.notify_events([(&*event as &dyn Event, source)].into_iter());
+        #[cfg(not(feature = "generated-address-space"))]
+        let _ = (condition_id, retain);
+        #[cfg(feature = "generated-address-space")]
+        let mut lck = trace_write_lock!(self.inner);
+        #[cfg(feature = "generated-address-space")]
+        if retain {
+            lck.retained_conditions.insert(
+                condition_id.clone(),
+                RetainedCondition {
+                    source: source.clone(),
+                    event,
+                },
+            );
+        } else {
+            lck.retained_conditions.remove(condition_id);
+        }
+    }
+
+    /// Send the current state of all retained conditions to the event monitored items
+    /// in the subscription `subscription_id`, bracketed by the `start` and `end` events.
+    #[cfg(feature = "generated-address-space")]
+    pub(crate) fn condition_refresh(
+        &self,
+        session_id: u32,
+        subscription_id: u32,
+        start: &dyn Event,
+        end: &dyn Event,
+    ) -> Result<(), StatusCode> {
+        let lck = trace_read_lock!(self.inner);
+        let cache = lck
+            .session_subscriptions
+            .get(&session_id)
+            .ok_or(StatusCode::BadSubscriptionIdInvalid)?;
+        let conditions: Vec<_> = lck
+            .retained_conditions
+            .values()
+            .map(|c| (&c.source, &*c.event as &dyn Event))
+            .collect();
+        let mut cache_lck = cache.lock();
+        cache_lck.condition_refresh(subscription_id, start, &conditions, end)
+    }
+
     pub(crate) fn create_monitored_items(
         &self,
         session_id: u32,
@@ -491,7 +751,7 @@ impl SubscriptionCache {
         subscription_id: u32,
         info: &ServerInfo,
         timestamps_to_return: TimestampsToReturn,
-        requests: Vec<MonitoredItemModifyRequest>,
+        requests: Vec<ModifyMonitoredItem>,
         type_tree: &dyn TypeTree,
     ) -> Result<Vec<MonitoredItemUpdateRef>, StatusCode> {
         let Some(cache) = ({
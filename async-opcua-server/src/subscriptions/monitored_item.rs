@@ -3,10 +3,10 @@ use std::collections::{BTreeSet, VecDeque};
 use opcua_nodes::{Event, ParsedEventFilter, TypeTree};
 use tracing::error;
 
-use super::MonitoredItemHandle;
+use super::{event_filter_cache::EventFilterCache, MonitoredItemHandle};
 use crate::{info::ServerInfo, node_manager::ParsedReadValueId};
 use opcua_types::{
-    match_extension_object_owned, DataChangeFilter, DataValue, DateTime, EventFieldList,
+    match_extension_object_owned, DataChangeFilter, DataValue, DateTime, Deadband, EventFieldList,
     EventFilter, EventFilterResult, ExtensionObject, MonitoredItemCreateRequest,
     MonitoredItemModifyRequest, MonitoredItemNotification, MonitoringMode, NumericRange,
     ParsedDataChangeFilter, StatusCode, TimestampsToReturn, Variant,
@@ -73,6 +73,15 @@ impl FilterType {
             }
         )
     }
+
+    /// Return `true` if this is a data change filter with a deadband. Deadbands
+    /// are only allowed on numeric values.
+    pub fn has_deadband(&self) -> bool {
+        matches!(
+            self,
+            FilterType::DataChangeFilter(f) if !matches!(f.deadband, Deadband::None)
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -92,6 +101,16 @@ pub struct CreateMonitoredItem {
     filter_res: Option<EventFilterResult>,
     timestamps_to_return: TimestampsToReturn,
     eu_range: Option<(f64, f64)>,
+    deadband_allowed: bool,
+}
+
+/// Container for a request to modify a single monitored item.
+pub(crate) struct ModifyMonitoredItem {
+    /// The modify request from the client.
+    pub request: MonitoredItemModifyRequest,
+    /// The EURange of the monitored node, if it was read for a percent deadband.
+    /// This replaces the EURange read when the item was created.
+    pub eu_range: Option<(f64, f64)>,
 }
 
 /// Takes the requested sampling interval value supplied by client and ensures it is within
@@ -101,8 +120,12 @@ fn sanitize_sampling_interval(info: &ServerInfo, requested_sampling_interval: f6
         // From spec "any negative number is interpreted as -1"
         // -1 means monitored item's sampling interval defaults to the subscription's publishing interval
         -1.0
-    } else if requested_sampling_interval == 0.0
-        || requested_sampling_interval < info.config.limits.subscriptions.min_sampling_interval_ms
+    } else if requested_sampling_interval == 0.0 {
+        // 0 means report on change. Values set directly by node managers are reported
+        // as they are written, values that are sampled are sampled as fast as possible.
+        0.0
+    } else if requested_sampling_interval
+        < info.config.limits.subscriptions.min_sampling_interval_ms
     {
         info.config.limits.subscriptions.min_sampling_interval_ms
     } else {
@@ -179,6 +202,7 @@ impl CreateMonitoredItem {
             timestamps_to_return,
             filter_res,
             eu_range,
+            deadband_allowed: true,
         }
     }
 
@@ -248,6 +272,23 @@ impl CreateMonitoredItem {
         }
     }
 
+    /// Revise the sampling interval to be at least `minimum_sampling_interval`,
+    /// typically the `MinimumSamplingInterval` attribute of the monitored variable.
+    /// A sampling interval of -1, meaning the publishing interval, is not changed.
+    pub fn clamp_sampling_interval(&mut self, minimum_sampling_interval: f64) {
+        if self.sampling_interval >= 0.0 && self.sampling_interval < minimum_sampling_interval {
+            self.sampling_interval = minimum_sampling_interval;
+        }
+    }
+
+    /// Set whether deadband filters may be used with this monitored item, typically
+    /// `false` if the monitored value is not numeric. This is checked when the
+    /// monitored item is modified later, so it should be set even if the
+    /// requested filter has no deadband.
+    pub fn set_deadband_allowed(&mut self, allowed: bool) {
+        self.deadband_allowed = allowed;
+    }
+
     /// Requested timestamps to return.
     pub fn timestamps_to_return(&self) -> TimestampsToReturn {
         self.timestamps_to_return
@@ -279,10 +320,13 @@ pub struct MonitoredItem {
     queue_size: usize,
     notification_queue: VecDeque<Notification>,
     queue_overflow: bool,
+    queue_overflow_count: u32,
     timestamps_to_return: TimestampsToReturn,
     last_data_value: Option<DataValue>,
     any_new_notification: bool,
     eu_range: Option<(f64, f64)>,
+    semantics_changed: bool,
+    deadband_allowed: bool,
 }
 
 impl MonitoredItem {
@@ -301,8 +345,11 @@ impl MonitoredItem {
             queue_size: request.queue_size,
             notification_queue: VecDeque::new(),
             queue_overflow: false,
+            queue_overflow_count: 0,
             any_new_notification: false,
             eu_range: request.eu_range,
+            semantics_changed: false,
+            deadband_allowed: request.deadband_allowed,
         };
         if let Some(val) = request.initial_value.as_ref() {
             v.notify_data_value(val.clone());
@@ -326,16 +373,23 @@ impl MonitoredItem {
         &mut self,
         info: &ServerInfo,
         timestamps_to_return: TimestampsToReturn,
-        request: &MonitoredItemModifyRequest,
+        modify: &ModifyMonitoredItem,
         type_tree: &dyn TypeTree,
     ) -> (Option<EventFilterResult>, StatusCode) {
+        let request = &modify.request;
         self.timestamps_to_return = timestamps_to_return;
+        if modify.eu_range.is_some() {
+            self.eu_range = modify.eu_range;
+        }
         let (filter_res, filter) = FilterType::from_filter(
             request.requested_parameters.filter.clone(),
             self.eu_range,
             type_tree,
         );
         self.filter = match filter {
+            Ok(f) if f.has_deadband() && !self.deadband_allowed => {
+                return (filter_res, StatusCode::BadFilterNotAllowed)
+            }
             Ok(f) => f,
             Err(e) => return (filter_res, e),
         };
@@ -364,6 +418,11 @@ impl MonitoredItem {
     }
 
     fn filter_by_sampling_interval(&self, old: &DataValue, new: &DataValue) -> bool {
+        if self.sampling_interval == 0.0 {
+            // Report every change.
+            return true;
+        }
+
         let (Some(old), Some(new)) = (&old.source_timestamp, &new.source_timestamp) else {
             // Always include measurements without source timestamp, we don't know enough about these,
             // assume the server implementation did filtering elsewhere.
@@ -411,8 +470,10 @@ impl MonitoredItem {
                 filter.is_changed(&value, last_dv)
                     && self.filter_by_sampling_interval(last_dv, &value)
             }
+            // Without a filter, the default trigger is StatusValue.
             (Some(last_dv), FilterType::None) => {
-                value.value != last_dv.value && self.filter_by_sampling_interval(last_dv, &value)
+                (value.status_changed_from(last_dv) || value.value_changed_from(last_dv))
+                    && self.filter_by_sampling_interval(last_dv, &value)
             }
             (None, _) => true,
             _ => false,
@@ -424,6 +485,10 @@ impl MonitoredItem {
 
         self.last_data_value = Some(value.clone());
 
+        if std::mem::take(&mut self.semantics_changed) {
+            value.status = Some(value.status().set_semantics_changed(true));
+        }
+
         match self.timestamps_to_return {
             TimestampsToReturn::Neither | TimestampsToReturn::Invalid => {
                 value.source_timestamp = None;
@@ -453,6 +518,22 @@ impl MonitoredItem {
         true
     }
 
+    /// Notify the monitored item that the semantics of the monitored value have changed,
+    /// for example because the `EURange` of the variable was modified. The next value
+    /// reported by the item has the `SemanticsChanged` info bit set.
+    ///
+    /// `eu_range` is the current EURange of the variable, if it has one, which replaces
+    /// the range used by a percent deadband filter.
+    pub(super) fn notify_semantics_changed(&mut self, eu_range: Option<(f64, f64)>) {
+        self.semantics_changed = true;
+        if let Some(eu_range) = eu_range {
+            self.eu_range = Some(eu_range);
+            if let FilterType::DataChangeFilter(filter) = &mut self.filter {
+                filter.set_eu_range(eu_range);
+            }
+        }
+    }
+
     pub(super) fn notify_event(&mut self, event: &dyn Event) -> bool {
         if self.monitoring_mode == MonitoringMode::Disabled {
             return false;
@@ -471,6 +552,32 @@ impl MonitoredItem {
         true
     }
 
+    pub(super) fn notify_event_cached(
+        &mut self,
+        event_index: usize,
+        event: &dyn Event,
+        filter_cache: &mut EventFilterCache,
+    ) -> bool {
+        if self.monitoring_mode == MonitoringMode::Disabled {
+            return false;
+        }
+
+        let FilterType::EventFilter(filter) = &self.filter else {
+            return false;
+        };
+
+        let Some(fields) = filter_cache.evaluate(filter, event_index, event) else {
+            return false;
+        };
+
+        self.enqueue_notification(EventFieldList {
+            client_handle: self.client_handle,
+            event_fields: Some(fields),
+        });
+
+        true
+    }
+
     fn enqueue_notification(&mut self, notification: impl Into<Notification>) {
         self.any_new_notification = true;
         let overflow = self.notification_queue.len() == self.queue_size;
@@ -488,6 +595,7 @@ impl MonitoredItem {
                 n.value.status = Some(n.value.status().set_overflow(true));
             }
             self.queue_overflow = true;
+            self.queue_overflow_count = self.queue_overflow_count.saturating_add(1);
         }
 
         self.notification_queue.push_back(notification);
@@ -599,6 +707,12 @@ impl MonitoredItem {
         self.monitoring_mode
     }
 
+    /// Number of notifications dropped from the queue of this monitored item
+    /// because it was full.
+    pub fn queue_overflow_count(&self) -> u32 {
+        self.queue_overflow_count
+    }
+
     /// Whether oldest or newest values are discarded when the queue
     /// overflows.
     pub fn discard_oldest(&self) -> bool {
@@ -617,9 +731,9 @@ pub(super) mod tests {
 
     use crate::{node_manager::ParsedReadValueId, subscriptions::monitored_item::Notification};
     use opcua_types::{
-        AttributeId, DataChangeFilter, DataChangeTrigger, DataValue, DateTime, Deadband,
-        DeadbandType, MonitoringMode, NodeId, ParsedDataChangeFilter, ReadValueId, StatusCode,
-        Variant,
+        AttributeId, ByteString, DataChangeFilter, DataChangeTrigger, DataValue, DateTime,
+        Deadband, DeadbandType, LocalizedText, MonitoringMode, NodeId, ParsedDataChangeFilter,
+        ReadValueId, StatusCode, Variant,
     };
 
     use super::{FilterType, MonitoredItem};
@@ -645,10 +759,13 @@ pub(super) mod tests {
             queue_size: 10,
             notification_queue: Default::default(),
             queue_overflow: false,
+            queue_overflow_count: 0,
             timestamps_to_return: opcua_types::TimestampsToReturn::Both,
             last_data_value: None,
             any_new_notification: false,
             eu_range: None,
+            semantics_changed: false,
+            deadband_allowed: true,
         };
 
         if let Some(val) = initial_value {
@@ -697,12 +814,16 @@ pub(super) mod tests {
 
         assert!(!filter.is_changed(&v1, &v2));
 
-        // Change v1 status
+        // A missing status is the same as Good
         v1.status = Some(StatusCode::Good);
+        assert!(!filter.is_changed(&v1, &v2));
+
+        // Change v1 status
+        v1.status = Some(StatusCode::BadUnexpectedError);
         assert!(filter.is_changed(&v1, &v2));
 
         // Change v2 status
-        v2.status = Some(StatusCode::Good);
+        v2.status = Some(StatusCode::BadUnexpectedError);
         assert!(!filter.is_changed(&v1, &v2));
 
         // Change value - but since trigger is status, this should not matter
@@ -727,6 +848,47 @@ pub(super) mod tests {
         assert!(filter.is_changed(&v1, &v2));
     }
 
+    #[test]
+    fn data_change_non_numeric() {
+        let filter = ParsedDataChangeFilter {
+            trigger: DataChangeTrigger::StatusValue,
+            deadband: Deadband::None,
+        };
+        assert!(!FilterType::DataChangeFilter(filter.clone()).has_deadband());
+
+        let changed = |v1: Variant, v2: Variant| {
+            filter.is_changed(&DataValue::value_only(v1), &DataValue::value_only(v2))
+        };
+        assert!(!changed("foo".into(), "foo".into()));
+        assert!(changed("foo".into(), "bar".into()));
+        assert!(!changed(
+            ByteString::from(vec![1u8, 2]).into(),
+            ByteString::from(vec![1u8, 2]).into()
+        ));
+        assert!(changed(
+            ByteString::from(vec![1u8, 2]).into(),
+            ByteString::from(vec![1u8, 2, 3]).into()
+        ));
+        assert!(!changed(
+            LocalizedText::new("en", "foo").into(),
+            LocalizedText::new("en", "foo").into()
+        ));
+        assert!(changed(
+            LocalizedText::new("en", "foo").into(),
+            LocalizedText::new("de", "foo").into()
+        ));
+        assert!(changed(
+            vec!["foo".to_owned(), "bar".to_owned()].into(),
+            vec!["foo".to_owned(), "baz".to_owned()].into()
+        ));
+
+        let filter = ParsedDataChangeFilter {
+            trigger: DataChangeTrigger::StatusValue,
+            deadband: Deadband::Absolute(1.0),
+        };
+        assert!(FilterType::DataChangeFilter(filter).has_deadband());
+    }
+
     #[test]
     fn data_change_deadband_abs() {
         let filter = DataChangeFilter {
@@ -827,6 +989,97 @@ pub(super) mod tests {
         assert_eq!(item.notification_queue.len(), 3);
     }
 
+    fn trigger_item(filter: FilterType, start: chrono::DateTime<Utc>) -> MonitoredItem {
+        new_monitored_item(
+            1,
+            ReadValueId {
+                node_id: NodeId::null(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            },
+            MonitoringMode::Reporting,
+            filter,
+            100.0,
+            true,
+            Some(DataValue::new_at(1.0, start.into())),
+        )
+    }
+
+    #[test]
+    fn monitored_item_trigger_timestamp_only() {
+        let start = Utc::now();
+        let later = start + Duration::try_milliseconds(200).unwrap();
+
+        for (trigger, expect_change) in [
+            (DataChangeTrigger::Status, false),
+            (DataChangeTrigger::StatusValue, false),
+            (DataChangeTrigger::StatusValueTimestamp, true),
+        ] {
+            let mut item = trigger_item(
+                FilterType::DataChangeFilter(ParsedDataChangeFilter {
+                    trigger,
+                    deadband: Deadband::None,
+                }),
+                start,
+            );
+            // Same value and status, only the source timestamp differs.
+            assert_eq!(
+                item.notify_data_value(DataValue::new_at(1.0, later.into())),
+                expect_change,
+                "Unexpected result for trigger {trigger:?}"
+            );
+            assert_eq!(
+                item.notification_queue.len(),
+                if expect_change { 2 } else { 1 }
+            );
+        }
+
+        // No filter behaves like StatusValue.
+        let mut item = trigger_item(FilterType::None, start);
+        assert!(!item.notify_data_value(DataValue::new_at(1.0, later.into())));
+
+        // A missing status is the same as Good.
+        let mut value = DataValue::new_at(1.0, later.into());
+        value.status = Some(StatusCode::Good);
+        assert!(!item.notify_data_value(value));
+    }
+
+    #[test]
+    fn monitored_item_trigger_status_only() {
+        let start = Utc::now();
+        let later = start + Duration::try_milliseconds(200).unwrap();
+        let bad_value = || {
+            let mut v = DataValue::new_at(1.0, start.into());
+            v.status = Some(StatusCode::BadCommunicationError);
+            v
+        };
+
+        for trigger in [
+            DataChangeTrigger::Status,
+            DataChangeTrigger::StatusValue,
+            DataChangeTrigger::StatusValueTimestamp,
+        ] {
+            let mut item = trigger_item(
+                FilterType::DataChangeFilter(ParsedDataChangeFilter {
+                    trigger,
+                    deadband: Deadband::None,
+                }),
+                start,
+            );
+            let mut value = bad_value();
+            value.source_timestamp = Some(later.into());
+            assert!(
+                item.notify_data_value(value),
+                "Unexpected result for trigger {trigger:?}"
+            );
+        }
+
+        let mut item = trigger_item(FilterType::None, start);
+        let mut value = bad_value();
+        value.source_timestamp = Some(later.into());
+        assert!(item.notify_data_value(value));
+    }
+
     #[test]
     fn monitored_item_overflow() {
         let start = Utc::now();
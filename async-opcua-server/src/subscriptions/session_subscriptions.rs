@@ -1,11 +1,13 @@
 use std::{
+    cmp::Reverse,
     collections::VecDeque,
     sync::Arc,
     time::{Duration, Instant},
 };
 
 use super::{
-    monitored_item::MonitoredItem,
+    event_filter_cache::EventFilterCache,
+    monitored_item::{ModifyMonitoredItem, MonitoredItem},
     subscription::{MonitoredItemHandle, Subscription, TickReason, TickResult},
     CreateMonitoredItem, NonAckedPublish, PendingPublish, PersistentSessionKey,
 };
@@ -22,10 +24,10 @@ use opcua_core::sync::RwLock;
 use opcua_types::{
     AttributeId, CreateSubscriptionRequest, CreateSubscriptionResponse, DataValue, DateTime,
     DateTimeUtc, ExtensionObject, ModifySubscriptionRequest, ModifySubscriptionResponse,
-    MonitoredItemCreateResult, MonitoredItemModifyRequest, MonitoredItemModifyResult,
-    MonitoringMode, NodeId, NotificationMessage, PublishRequest, PublishResponse, RepublishRequest,
-    RepublishResponse, ResponseHeader, ServiceFault, SetPublishingModeRequest,
-    SetPublishingModeResponse, StatusCode, TimestampsToReturn,
+    MonitoredItemCreateResult, MonitoredItemModifyResult, MonitoringMode, NodeId,
+    NotificationMessage, PublishRequest, PublishResponse, RepublishRequest, RepublishResponse,
+    ResponseHeader, ServiceFault, SetPublishingModeRequest, SetPublishingModeResponse, StatusCode,
+    SubscriptionDiagnosticsDataType, TimestampsToReturn,
 };
 
 /// Subscriptions belonging to a single session. Note that they are technically _owned_ by
@@ -41,6 +43,12 @@ pub struct SessionSubscriptions {
     retransmission_queue: VecDeque<NonAckedPublish>,
     /// Configured limits on subscriptions.
     limits: SubscriptionLimits,
+    /// Dropped notifications from deleted subscriptions that have not yet
+    /// been reported to the server diagnostics.
+    unreported_dropped_count: u64,
+    /// Messages dropped from the retransmission queue that have not yet
+    /// been reported to the server diagnostics.
+    unreported_dropped_retained_count: u64,
 
     /// Static reference to the session owning this, required to cleanly handle deletion.
     session: Arc<RwLock<Session>>,
@@ -58,6 +66,8 @@ impl SessionSubscriptions {
             publish_request_queue: VecDeque::new(),
             retransmission_queue: VecDeque::new(),
             limits,
+            unreported_dropped_count: 0,
+            unreported_dropped_retained_count: 0,
             session,
         }
     }
@@ -148,7 +158,7 @@ impl SessionSubscriptions {
         let subscription = Subscription::new(
             subscription_id,
             request.publishing_enabled,
-            Duration::from_millis(revised_publishing_interval as u64),
+            Duration::from_micros((revised_publishing_interval * 1000.0) as u64),
             revised_lifetime_count,
             revised_max_keep_alive_count,
             request.priority,
@@ -289,17 +299,18 @@ impl SessionSubscriptions {
         subscription_id: u32,
         info: &ServerInfo,
         timestamps_to_return: TimestampsToReturn,
-        requests: Vec<MonitoredItemModifyRequest>,
+        requests: Vec<ModifyMonitoredItem>,
         type_tree: &dyn TypeTree,
     ) -> Result<Vec<MonitoredItemUpdateRef>, StatusCode> {
         let Some(sub) = self.subscriptions.get_mut(&subscription_id) else {
             return Err(StatusCode::BadSubscriptionIdInvalid);
         };
         let mut results = Vec::with_capacity(requests.len());
-        for request in requests {
+        for modify in requests {
+            let request = &modify.request;
             if let Some(item) = sub.get_mut(&request.monitored_item_id) {
                 let (filter_result, status) =
-                    item.modify(info, timestamps_to_return, &request, type_tree);
+                    item.modify(info, timestamps_to_return, &modify, type_tree);
                 let filter_result = filter_result
                     .map(ExtensionObject::from_message)
                     .unwrap_or_else(ExtensionObject::null);
@@ -457,6 +468,7 @@ impl SessionSubscriptions {
                 result.push((StatusCode::BadSubscriptionIdInvalid, Vec::new()));
                 continue;
             };
+            self.unreported_dropped_count += sub.take_new_dropped_notifications();
 
             let items = sub
                 .drain()
@@ -489,10 +501,12 @@ impl SessionSubscriptions {
         requested_max_keep_alive_count: u32,
         requested_lifetime_count: u32,
     ) -> (f64, u32, u32) {
-        let revised_publishing_interval = f64::max(
-            requested_publishing_interval,
-            info.config.limits.subscriptions.min_publishing_interval_ms,
-        );
+        // Intervals outside the configured bounds, including 0 and NaN, are revised to
+        // the nearest bound.
+        let limits = &info.config.limits.subscriptions;
+        let revised_publishing_interval = requested_publishing_interval
+            .max(limits.min_publishing_interval_ms)
+            .min(limits.max_publishing_interval_ms);
         let revised_max_keep_alive_count = if requested_max_keep_alive_count
             > info.config.limits.subscriptions.max_keep_alive_count
         {
@@ -578,13 +592,14 @@ impl SessionSubscriptions {
         self.remove_expired_publish_requests(now_instant);
 
         let subscription_ids = {
-            // Sort subscriptions by priority
-            let mut subscription_priority: Vec<(u32, u8)> = self
+            // Subscriptions with data go first, so that publish requests are not spent on
+            // keep-alives while data is waiting. Within each group, higher priority goes first.
+            let mut subscription_priority: Vec<(u32, bool, u8)> = self
                 .subscriptions
                 .values()
-                .map(|v| (v.id(), v.priority()))
+                .map(|v| (v.id(), v.has_pending_data(), v.priority()))
                 .collect();
-            subscription_priority.sort_by(|s1, s2| s1.1.cmp(&s2.1));
+            subscription_priority.sort_by_key(|s| Reverse((s.1, s.2)));
             subscription_priority.into_iter().map(|s| s.0)
         };
 
@@ -628,6 +643,7 @@ impl SessionSubscriptions {
             }
 
             if subscription.ready_to_remove() {
+                self.unreported_dropped_count += subscription.take_new_dropped_notifications();
                 self.subscriptions.remove(&sub_id);
                 self.retransmission_queue
                     .retain(|f| f.subscription_id != sub_id);
@@ -642,13 +658,7 @@ impl SessionSubscriptions {
 
             let available_sequence_numbers = self.available_sequence_numbers(subscription_id);
 
-            if self.retransmission_queue.len() >= self.max_publish_requests() * 2 {
-                self.retransmission_queue.pop_front();
-            }
-            self.retransmission_queue.push_back(NonAckedPublish {
-                message: notification.clone(),
-                subscription_id,
-            });
+            self.retain_for_retransmission(subscription_id, &notification);
 
             let _ = publish_request.response.send(
                 PublishResponse {
@@ -672,6 +682,43 @@ impl SessionSubscriptions {
         to_delete
     }
 
+    /// Keep a sent notification message until it is acknowledged, so that
+    /// the client can request it again using `Republish`.
+    fn retain_for_retransmission(&mut self, subscription_id: u32, message: &NotificationMessage) {
+        // Keep-alive messages carry the next sequence number and are never acknowledged.
+        if message.notification_data.is_none() {
+            return;
+        }
+        let max_size = self.limits.max_retained_notifications_per_subscription;
+        if max_size == 0 {
+            return;
+        }
+        let mut num_retained = 0;
+        let mut oldest = None;
+        for (idx, m) in self.retransmission_queue.iter().enumerate() {
+            if m.subscription_id == subscription_id {
+                num_retained += 1;
+                oldest.get_or_insert(idx);
+            }
+        }
+        if num_retained >= max_size {
+            if let Some(idx) = oldest {
+                self.retransmission_queue.remove(idx);
+                self.unreported_dropped_retained_count += 1;
+            }
+        }
+        // The queue is also bounded for the whole session.
+        if self.retransmission_queue.len() >= self.max_publish_requests() * 2
+            && self.retransmission_queue.pop_front().is_some()
+        {
+            self.unreported_dropped_retained_count += 1;
+        }
+        self.retransmission_queue.push_back(NonAckedPublish {
+            message: message.clone(),
+            subscription_id,
+        });
+    }
+
     fn find_notification_message(
         &self,
         subscription_id: u32,
@@ -702,6 +749,9 @@ impl SessionSubscriptions {
         }
     }
 
+    /// Process the subscription acknowledgements in a publish request, removing
+    /// acknowledged messages from the retransmission queue. Returns one result per
+    /// acknowledgement, or `None` if the request contains no acknowledgements.
     fn process_subscription_acks(&mut self, request: &PublishRequest) -> Option<Vec<StatusCode>> {
         let acks = request.subscription_acknowledgements.as_ref()?;
         if acks.is_empty() {
@@ -713,12 +763,10 @@ impl SessionSubscriptions {
                 .map(|ack| {
                     if !self.subscriptions.contains_key(&ack.subscription_id) {
                         StatusCode::BadSubscriptionIdInvalid
-                    } else if let Some((idx, _)) =
-                        self.retransmission_queue.iter().enumerate().find(|(_, p)| {
-                            p.subscription_id == ack.subscription_id
-                                && p.message.sequence_number == ack.sequence_number
-                        })
-                    {
+                    } else if let Some(idx) = self.retransmission_queue.iter().position(|p| {
+                        p.subscription_id == ack.subscription_id
+                            && p.message.sequence_number == ack.sequence_number
+                    }) {
                         // This is potentially innefficient, but this is probably fine due to two factors:
                         //  - we need unordered removal, _and_ ordered removal, which means we need to deal
                         //    with this anyway.
@@ -762,23 +810,101 @@ impl SessionSubscriptions {
         }
     }
 
-    pub(super) fn notify_events(&mut self, events: Vec<(MonitoredItemHandle, &dyn Event)>) {
-        for (handle, event) in events {
+    pub(super) fn notify_semantics_changed(
+        &mut self,
+        handles: Vec<MonitoredItemHandle>,
+        eu_range: Option<(f64, f64)>,
+    ) {
+        for handle in handles {
+            let Some(sub) = self.subscriptions.get_mut(&handle.subscription_id) else {
+                continue;
+            };
+            sub.notify_semantics_changed(&handle.monitored_item_id, eu_range);
+        }
+    }
+
+    pub(super) fn notify_events(
+        &mut self,
+        events: Vec<(MonitoredItemHandle, usize, &dyn Event)>,
+        filter_cache: &mut EventFilterCache,
+    ) {
+        for (handle, event_index, event) in events {
             let Some(sub) = self.subscriptions.get_mut(&handle.subscription_id) else {
                 continue;
             };
-            sub.notify_event(&handle.monitored_item_id, event);
+            sub.notify_event_cached(&handle.monitored_item_id, event_index, event, filter_cache);
         }
     }
 
+    #[cfg(feature = "generated-address-space")]
+    pub(super) fn condition_refresh(
+        &mut self,
+        subscription_id: u32,
+        start: &dyn Event,
+        conditions: &[(&NodeId, &dyn Event)],
+        end: &dyn Event,
+    ) -> Result<(), StatusCode> {
+        let sub = self
+            .subscriptions
+            .get_mut(&subscription_id)
+            .ok_or(StatusCode::BadSubscriptionIdInvalid)?;
+        sub.condition_refresh(start, conditions, end);
+        Ok(())
+    }
+
     pub(super) fn user_token(&self) -> &PersistentSessionKey {
         &self.user_token
     }
 
+    pub(super) fn has_pending_data(&self) -> bool {
+        self.subscriptions.values().any(|s| s.has_pending_data())
+    }
+
     pub(super) fn get_monitored_item_count(&self, subscription_id: u32) -> Option<usize> {
         self.subscriptions.get(&subscription_id).map(|s| s.len())
     }
 
+    pub(super) fn get_monitored_item_node_ids(
+        &self,
+        subscription_id: u32,
+        monitored_item_ids: &[u32],
+    ) -> Vec<(u32, NodeId)> {
+        let Some(sub) = self.subscriptions.get(&subscription_id) else {
+            return Vec::new();
+        };
+        monitored_item_ids
+            .iter()
+            .filter_map(|id| {
+                sub.get(id)
+                    .map(|item| (*id, item.item_to_monitor().node_id.clone()))
+            })
+            .collect()
+    }
+
+    /// Get the diagnostics of each subscription in this session.
+    pub fn subscription_diagnostics(&self) -> Vec<SubscriptionDiagnosticsDataType> {
+        let session_id = self.session.read().session_id().clone();
+        self.subscriptions
+            .values()
+            .map(|s| s.diagnostics(&session_id))
+            .collect()
+    }
+
+    /// Get the number of notifications dropped by subscriptions in this session
+    /// since the last call to this method.
+    pub(super) fn take_dropped_notifications(&mut self) -> u64 {
+        let removed = std::mem::take(&mut self.unreported_dropped_count);
+        self.subscriptions
+            .values_mut()
+            .fold(removed, |acc, s| acc + s.take_new_dropped_notifications())
+    }
+
+    /// Get the number of notification messages dropped from the retransmission
+    /// queue since the last call to this method.
+    pub(super) fn take_dropped_retained_messages(&mut self) -> u64 {
+        std::mem::take(&mut self.unreported_dropped_retained_count)
+    }
+
     /// Get a reference to the session this subscription collection is owned by.
     pub fn session(&self) -> &Arc<RwLock<Session>> {
         &self.session
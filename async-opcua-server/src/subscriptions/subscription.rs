@@ -5,10 +5,18 @@ use std::{
 
 use opcua_core::handle::Handle;
 use opcua_nodes::Event;
-use opcua_types::{DataValue, DateTime, DateTimeUtc, NotificationMessage, StatusCode};
+#[cfg(feature = "generated-address-space")]
+use opcua_types::{AttributeId, ObjectId};
+use opcua_types::{
+    DataValue, DateTime, DateTimeUtc, MonitoringMode, NodeId, NotificationMessage, StatusCode,
+    SubscriptionDiagnosticsDataType,
+};
 use tracing::{debug, trace, warn};
 
-use super::monitored_item::{MonitoredItem, Notification};
+use super::{
+    event_filter_cache::EventFilterCache,
+    monitored_item::{MonitoredItem, Notification},
+};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 /// Current internal state of the subscription.
@@ -117,6 +125,9 @@ pub struct Subscription {
     /// A flag that tells the subscription to send the latest value of every monitored item on the
     /// next publish request.
     resend_data: bool,
+    /// Monitored items that should send their latest value on the next publish request,
+    /// like `resend_data`, but for individual items.
+    resend_items: HashSet<u32>,
     /// The next sequence number to be sent
     sequence_number: Handle,
     // The time that the subscription interval last fired
@@ -127,6 +138,12 @@ pub struct Subscription {
     max_queued_notifications: usize,
     /// Maximum number of notifications per publish.
     max_notifications_per_publish: usize,
+    /// Number of notifications dropped by monitored items that have since been removed.
+    removed_items_overflow_count: u32,
+    /// Number of notification messages discarded because too many were queued.
+    discarded_message_count: u32,
+    /// Number of dropped notifications already reported to the server diagnostics.
+    reported_dropped_count: u64,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -161,6 +178,7 @@ impl Subscription {
             keep_alive_counter,
             first_message_sent: false,
             resend_data: false,
+            resend_items: HashSet::new(),
             publishing_enabled,
             // Counters for new items
             sequence_number: Handle::new(1),
@@ -168,6 +186,9 @@ impl Subscription {
             notifications: VecDeque::new(),
             max_queued_notifications,
             max_notifications_per_publish: max_notifications_per_publish as usize,
+            removed_items_overflow_count: 0,
+            discarded_message_count: 0,
+            reported_dropped_count: 0,
         }
     }
 
@@ -201,6 +222,7 @@ impl Subscription {
     }
 
     pub(super) fn drain(&mut self) -> impl Iterator<Item = (u32, MonitoredItem)> + '_ {
+        self.removed_items_overflow_count = self.monitoring_queue_overflow_count();
         self.monitored_items.drain()
     }
 
@@ -210,8 +232,29 @@ impl Subscription {
         self.resend_data = true;
     }
 
+    /// Set `resend_data` for the monitored items with the given client handle only.
+    /// The next publish request will send the latest value of these items, whether
+    /// or not they have produced any new data.
+    ///
+    /// Returns `false` if no monitored item in this subscription has the given client handle.
+    pub fn set_resend_data_for_client_handle(&mut self, client_handle: u32) -> bool {
+        let mut found = false;
+        for (id, item) in &self.monitored_items {
+            if item.client_handle() == client_handle {
+                self.resend_items.insert(*id);
+                self.notified_monitored_items.insert(*id);
+                found = true;
+            }
+        }
+        found
+    }
+
     pub(super) fn remove(&mut self, id: &u32) -> Option<MonitoredItem> {
-        self.monitored_items.remove(id)
+        let item = self.monitored_items.remove(id)?;
+        self.removed_items_overflow_count = self
+            .removed_items_overflow_count
+            .saturating_add(item.queue_overflow_count());
+        Some(item)
     }
 
     pub(super) fn insert(&mut self, id: u32, item: MonitoredItem) {
@@ -228,6 +271,14 @@ impl Subscription {
         }
     }
 
+    /// Notify the given monitored item that the semantics of its value have changed,
+    /// passing the current EURange of the variable, if any.
+    pub fn notify_semantics_changed(&mut self, id: &u32, eu_range: Option<(f64, f64)>) {
+        if let Some(item) = self.monitored_items.get_mut(id) {
+            item.notify_semantics_changed(eu_range);
+        }
+    }
+
     /// Notify the given monitored item of a new event.
     pub fn notify_event(&mut self, id: &u32, event: &dyn Event) {
         if let Some(item) = self.monitored_items.get_mut(id) {
@@ -237,6 +288,51 @@ impl Subscription {
         }
     }
 
+    /// Send the current state of the given retained conditions to every event monitored
+    /// item in this subscription, preceded by `start` and followed by `end`.
+    ///
+    /// Items on the `Server` object receive all conditions, other items only
+    /// receive the conditions whose source is the monitored node.
+    #[cfg(feature = "generated-address-space")]
+    pub(super) fn condition_refresh(
+        &mut self,
+        start: &dyn Event,
+        conditions: &[(&NodeId, &dyn Event)],
+        end: &dyn Event,
+    ) {
+        let server_id: NodeId = ObjectId::Server.into();
+        let items: Vec<_> = self
+            .monitored_items
+            .iter()
+            .filter(|(_, item)| item.item_to_monitor().attribute_id == AttributeId::EventNotifier)
+            .map(|(id, item)| (*id, item.item_to_monitor().node_id.clone()))
+            .collect();
+
+        for (id, node_id) in items {
+            self.notify_event(&id, start);
+            for (source, event) in conditions {
+                if node_id == server_id || *source == &node_id {
+                    self.notify_event(&id, *event);
+                }
+            }
+            self.notify_event(&id, end);
+        }
+    }
+
+    pub(super) fn notify_event_cached(
+        &mut self,
+        id: &u32,
+        event_index: usize,
+        event: &dyn Event,
+        filter_cache: &mut EventFilterCache,
+    ) {
+        if let Some(item) = self.monitored_items.get_mut(id) {
+            if item.notify_event_cached(event_index, event, filter_cache) {
+                self.notified_monitored_items.insert(*id);
+            }
+        }
+    }
+
     /// Tests if the publishing interval has elapsed since the last time this function in which case
     /// it returns `true` and updates its internal state.
     fn test_and_set_publishing_interval_elapsed(&mut self, now: Instant) -> bool {
@@ -521,6 +617,7 @@ impl Subscription {
             UpdateStateAction::SubscriptionCreated => TickResult::None,
             UpdateStateAction::SubscriptionExpired => {
                 debug!("Subscription status change to closed / timeout");
+                self.removed_items_overflow_count = self.monitoring_queue_overflow_count();
                 self.monitored_items.clear();
                 let notification = NotificationMessage::status_change(
                     self.sequence_number.next(),
@@ -537,6 +634,7 @@ impl Subscription {
         if self.notifications.len() >= self.max_queued_notifications {
             warn!("Maximum number of queued notifications exceeded, dropping oldest. Subscription ID: {}", self.id);
             self.notifications.pop_front();
+            self.discarded_message_count = self.discarded_message_count.saturating_add(1);
         }
 
         // debug!("Enqueuing notification {:?}", notification);
@@ -551,6 +649,16 @@ impl Subscription {
         !self.notifications.is_empty()
     }
 
+    /// Whether this subscription has data to send, either as queued notification
+    /// messages or as pending notifications on its monitored items.
+    /// Keep-alive messages do not count as data.
+    pub(super) fn has_pending_data(&self) -> bool {
+        self.notifications
+            .iter()
+            .any(|n| n.notification_data.is_some())
+            || (self.publishing_enabled && self.notifications_available(self.resend_data))
+    }
+
     pub(super) fn ready_to_remove(&self) -> bool {
         self.state == SubscriptionState::Closed && self.notifications.is_empty()
     }
@@ -656,6 +764,7 @@ impl Subscription {
         let mut notifications = Vec::new();
         let mut messages = Vec::new();
         let mut triggers = Vec::new();
+        let resend_items = std::mem::take(&mut self.resend_items);
 
         // If resend data is true, we must visit ever monitored item
         if resend_data {
@@ -679,7 +788,7 @@ impl Subscription {
                 Self::tick_monitored_item(
                     monitored_item,
                     now,
-                    resend_data,
+                    resend_items.contains(&item_id),
                     self.max_notifications_per_publish,
                     &mut triggers,
                     &mut notifications,
@@ -731,6 +840,8 @@ impl Subscription {
         self.priority
     }
 
+    /// Set the publishing interval. The interval is compared against the time of the
+    /// last publish on each tick, so the new interval applies from the next tick.
     pub(super) fn set_publishing_interval(&mut self, publishing_interval: Duration) {
         self.publishing_interval = publishing_interval;
         self.reset_lifetime_counter();
@@ -756,6 +867,65 @@ impl Subscription {
         self.publishing_enabled = publishing_enabled;
     }
 
+    /// Number of notifications dropped because the queue of a monitored item in this
+    /// subscription was full, including monitored items that have since been deleted.
+    pub fn monitoring_queue_overflow_count(&self) -> u32 {
+        self.monitored_items
+            .values()
+            .fold(self.removed_items_overflow_count, |acc, item| {
+                acc.saturating_add(item.queue_overflow_count())
+            })
+    }
+
+    /// Number of notification messages discarded because the maximum number of
+    /// queued notifications was exceeded.
+    pub fn discarded_message_count(&self) -> u32 {
+        self.discarded_message_count
+    }
+
+    /// Total number of dropped notifications and discarded notification messages.
+    pub fn dropped_notification_count(&self) -> u64 {
+        self.monitoring_queue_overflow_count() as u64 + self.discarded_message_count as u64
+    }
+
+    /// Get the number of notifications dropped since the last call to this method.
+    pub(super) fn take_new_dropped_notifications(&mut self) -> u64 {
+        let total = self.dropped_notification_count();
+        let new = total.saturating_sub(self.reported_dropped_count);
+        self.reported_dropped_count = total;
+        new
+    }
+
+    /// Get the standard diagnostics structure for this subscription, owned by the
+    /// session with ID `session_id`.
+    pub fn diagnostics(&self, session_id: &NodeId) -> SubscriptionDiagnosticsDataType {
+        SubscriptionDiagnosticsDataType {
+            session_id: session_id.clone(),
+            subscription_id: self.id,
+            priority: self.priority,
+            publishing_interval: self.publishing_interval.as_secs_f64() * 1000.0,
+            max_keep_alive_count: self.max_keep_alive_counter,
+            max_lifetime_count: self.max_lifetime_counter,
+            max_notifications_per_publish: self
+                .max_notifications_per_publish
+                .try_into()
+                .unwrap_or(u32::MAX),
+            publishing_enabled: self.publishing_enabled,
+            current_keep_alive_count: self.keep_alive_counter,
+            current_lifetime_count: self.lifetime_counter,
+            discarded_message_count: self.discarded_message_count,
+            monitored_item_count: self.monitored_items.len() as u32,
+            disabled_monitored_item_count: self
+                .monitored_items
+                .values()
+                .filter(|i| i.monitoring_mode() == MonitoringMode::Disabled)
+                .count() as u32,
+            monitoring_queue_overflow_count: self.monitoring_queue_overflow_count(),
+            next_sequence_number: self.sequence_number.peek_next(),
+            ..Default::default()
+        }
+    }
+
     /// The publishing interval of this subscription.
     pub fn publishing_interval(&self) -> Duration {
         self.publishing_interval
@@ -1019,4 +1189,169 @@ mod tests {
             };
         }
     }
+
+    #[test]
+    fn modify_publishing_interval() {
+        let mut sub =
+            Subscription::new(1, true, Duration::from_millis(1000), 100, 20, 1, 100, 1000);
+        let start = Instant::now();
+        let start_dt = Utc::now();
+
+        sub.last_time_publishing_interval_elapsed = start;
+        sub.tick(&start_dt, start, TickReason::TickTimerFired, true);
+        assert_eq!(sub.state, SubscriptionState::Normal);
+        sub.insert(
+            1,
+            new_monitored_item(
+                1,
+                ReadValueId {
+                    node_id: NodeId::null(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                MonitoringMode::Reporting,
+                FilterType::None,
+                100.0,
+                false,
+                Some(DataValue::new_at(0, start_dt.into())),
+            ),
+        );
+
+        // Nothing is published before the original interval has elapsed.
+        let (time, time_inst) = offset(start_dt, start, 100);
+        sub.tick(&time, time_inst, TickReason::TickTimerFired, true);
+        assert!(sub.take_notification().is_none());
+
+        // A shorter interval takes effect on the next tick.
+        sub.set_publishing_interval(Duration::from_millis(100));
+        sub.set_max_keep_alive_counter(5);
+        sub.reset_keep_alive_counter();
+        assert_eq!(sub.keep_alive_counter, 5);
+        assert_eq!(sub.lifetime_counter, 100);
+        let (time, time_inst) = offset(start_dt, start, 200);
+        sub.tick(&time, time_inst, TickReason::TickTimerFired, true);
+        assert_eq!(
+            get_notifications(&sub.take_notification().unwrap()).len(),
+            1
+        );
+
+        // A longer interval delays the next publish until it has elapsed.
+        sub.set_publishing_interval(Duration::from_millis(500));
+        sub.notify_data_value(&1, DataValue::new_at(1, time.into()));
+        let (time, time_inst) = offset(start_dt, start, 600);
+        sub.tick(&time, time_inst, TickReason::TickTimerFired, true);
+        assert!(sub.take_notification().is_none());
+        let (time, time_inst) = offset(start_dt, start, 700);
+        sub.tick(&time, time_inst, TickReason::TickTimerFired, true);
+        assert_eq!(
+            get_notifications(&sub.take_notification().unwrap()).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn publishing_disabled() {
+        let mut sub = Subscription::new(1, false, Duration::from_millis(100), 100, 3, 1, 100, 1000);
+        let start = Instant::now();
+        let start_dt = Utc::now();
+
+        sub.last_time_publishing_interval_elapsed = start;
+        sub.tick(&start_dt, start, TickReason::TickTimerFired, true);
+        sub.insert(
+            1,
+            new_monitored_item(
+                1,
+                ReadValueId {
+                    node_id: NodeId::null(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                MonitoringMode::Reporting,
+                FilterType::None,
+                100.0,
+                false,
+                Some(DataValue::new_at(0, start_dt.into())),
+            ),
+        );
+
+        // While publishing is disabled, only keep-alives are sent.
+        let mut keep_alives = 0;
+        for i in 1..=10 {
+            let (time, time_inst) = offset(start_dt, start, i * 100);
+            sub.notify_data_value(&1, DataValue::new_at(i as i32, time.into()));
+            sub.tick(&time, time_inst, TickReason::TickTimerFired, true);
+            if let Some(notif) = sub.take_notification() {
+                assert!(notif.notification_data.is_none());
+                keep_alives += 1;
+            }
+        }
+        assert!(keep_alives >= 3);
+        assert_ne!(sub.state, SubscriptionState::Closed);
+
+        // Once it is enabled again, the queued values are published.
+        sub.set_publishing_enabled(true);
+        let (time, time_inst) = offset(start_dt, start, 1100);
+        sub.tick(&time, time_inst, TickReason::TickTimerFired, true);
+        let notif = sub.take_notification().unwrap();
+        let its = get_notifications(&notif);
+        assert!(!its.is_empty());
+        let Notification::MonitoredItemNotification(m) = its.last().unwrap() else {
+            panic!("Wrong notification type");
+        };
+        assert_eq!(m.value.value, Some(Variant::Int32(10)));
+    }
+
+    #[test]
+    fn dropped_notifications() {
+        let mut sub = Subscription::new(1, true, Duration::from_millis(100), 100, 20, 1, 2, 1);
+        let start = Instant::now();
+        let start_dt = Utc::now();
+
+        sub.last_time_publishing_interval_elapsed = start;
+        sub.tick(&start_dt, start, TickReason::TickTimerFired, true);
+
+        sub.insert(
+            1,
+            new_monitored_item(
+                1,
+                ReadValueId {
+                    node_id: NodeId::null(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                MonitoringMode::Reporting,
+                FilterType::None,
+                0.0,
+                true,
+                Some(DataValue::new_now(0)),
+            ),
+        );
+        // The queue holds 10 values, so 6 of these are dropped.
+        for i in 1..16 {
+            sub.notify_data_value(&1, DataValue::new_now(i));
+        }
+        assert_eq!(sub.monitoring_queue_overflow_count(), 6);
+        assert_eq!(sub.discarded_message_count(), 0);
+
+        // Each notification goes in its own message, but only 2 messages may be queued.
+        let (time, time_inst) = offset(start_dt, start, 100);
+        sub.tick(&time, time_inst, TickReason::TickTimerFired, true);
+        assert_eq!(sub.discarded_message_count(), 8);
+        assert_eq!(sub.dropped_notification_count(), 14);
+        assert_eq!(sub.take_new_dropped_notifications(), 14);
+        assert_eq!(sub.take_new_dropped_notifications(), 0);
+
+        let diagnostics = sub.diagnostics(&NodeId::new(1, 1));
+        assert_eq!(diagnostics.session_id, NodeId::new(1, 1));
+        assert_eq!(diagnostics.subscription_id, 1);
+        assert_eq!(diagnostics.publishing_interval, 100.0);
+        assert_eq!(diagnostics.monitored_item_count, 1);
+        assert_eq!(diagnostics.discarded_message_count, 8);
+        assert_eq!(diagnostics.monitoring_queue_overflow_count, 6);
+
+        // Counts are kept when the monitored item is removed.
+        sub.remove(&1).unwrap();
+        assert_eq!(sub.monitoring_queue_overflow_count(), 6);
+        assert_eq!(sub.take_new_dropped_notifications(), 0);
+    }
 }
@@ -0,0 +1,124 @@
+use std::{collections::HashMap, net::IpAddr, sync::Arc};
+
+use opcua_core::sync::Mutex;
+
+use crate::config::TcpConfig;
+
+type ChannelCounts = Arc<Mutex<HashMap<IpAddr, usize>>>;
+
+/// Tracks the number of open secure channels per remote IP address,
+/// refusing new channels once the configured limit is reached.
+pub(crate) struct SecureChannelLimiter {
+    max_per_ip: usize,
+    allowlist: Vec<IpAddr>,
+    counts: ChannelCounts,
+}
+
+impl SecureChannelLimiter {
+    pub(crate) fn new(config: &TcpConfig) -> Self {
+        Self {
+            max_per_ip: config.max_secure_channels_per_ip,
+            allowlist: config.secure_channel_limit_allowlist.clone(),
+            counts: Default::default(),
+        }
+    }
+
+    /// Register a new secure channel from `addr`. Returns `None` if the limit
+    /// for `addr` is reached, otherwise a guard which releases the channel
+    /// when dropped. Channels from unknown addresses are not limited.
+    pub(crate) fn acquire(&self, addr: Option<IpAddr>) -> Option<SecureChannelGuard> {
+        let Some(addr) = addr.filter(|a| self.max_per_ip > 0 && !self.allowlist.contains(a)) else {
+            return Some(SecureChannelGuard { inner: None });
+        };
+        let mut counts = self.counts.lock();
+        let count = counts.entry(addr).or_default();
+        if *count >= self.max_per_ip {
+            return None;
+        }
+        *count += 1;
+        Some(SecureChannelGuard {
+            inner: Some((addr, self.counts.clone())),
+        })
+    }
+
+    #[cfg(test)]
+    fn count(&self, addr: IpAddr) -> usize {
+        self.counts.lock().get(&addr).copied().unwrap_or_default()
+    }
+}
+
+/// Guard for a secure channel registered with a [SecureChannelLimiter].
+pub(crate) struct SecureChannelGuard {
+    inner: Option<(IpAddr, ChannelCounts)>,
+}
+
+impl Drop for SecureChannelGuard {
+    fn drop(&mut self) {
+        let Some((addr, counts)) = self.inner.take() else {
+            return;
+        };
+        let mut counts = counts.lock();
+        if let Some(count) = counts.get_mut(&addr) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&addr);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::SecureChannelLimiter;
+    use crate::config::TcpConfig;
+
+    fn config(max_per_ip: usize, allowlist: Vec<IpAddr>) -> TcpConfig {
+        TcpConfig {
+            hello_timeout: 5,
+            host: "127.0.0.1".to_owned(),
+            port: 4855,
+            max_secure_channels_per_ip: max_per_ip,
+            secure_channel_limit_allowlist: allowlist,
+            transport_error_log_interval_ms: 0,
+        }
+    }
+
+    #[test]
+    fn limit_per_ip() {
+        let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let limiter = SecureChannelLimiter::new(&config(2, Vec::new()));
+
+        let g1 = limiter.acquire(Some(a)).unwrap();
+        let g2 = limiter.acquire(Some(a)).unwrap();
+        assert!(limiter.acquire(Some(a)).is_none());
+        // Other addresses are counted separately.
+        let g3 = limiter.acquire(Some(b)).unwrap();
+        assert_eq!(limiter.count(a), 2);
+
+        // Closing a channel frees up a slot.
+        drop(g1);
+        assert_eq!(limiter.count(a), 1);
+        let g4 = limiter.acquire(Some(a)).unwrap();
+        assert!(limiter.acquire(Some(a)).is_none());
+
+        drop((g2, g3, g4));
+        assert_eq!(limiter.count(a), 0);
+        assert!(limiter.counts.lock().is_empty());
+    }
+
+    #[test]
+    fn limit_allowlist_and_unlimited() {
+        let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let limiter = SecureChannelLimiter::new(&config(1, vec![a]));
+        let guards: Vec<_> = (0..5).map(|_| limiter.acquire(Some(a)).unwrap()).collect();
+        assert_eq!(limiter.count(a), 0);
+        drop(guards);
+
+        let limiter = SecureChannelLimiter::new(&config(0, Vec::new()));
+        let _guards: Vec<_> = (0..5).map(|_| limiter.acquire(Some(a)).unwrap()).collect();
+        assert_eq!(limiter.count(a), 0);
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use opcua_core::sync::Mutex;
+
+struct ThrottleState {
+    last_logged: Instant,
+    suppressed: u64,
+}
+
+/// Rate limit for a log message that may be emitted very often, for example
+/// transport errors on a flapping connection. The first occurrence is always
+/// logged, after that at most one message is logged per interval, along with
+/// the number of messages suppressed since the last one.
+///
+/// Messages are throttled separately for each remote IP address, so a single
+/// misbehaving client does not hide errors from other clients. Connections
+/// without a known address share a throttle.
+pub(crate) struct LogThrottle {
+    interval: Duration,
+    state: Mutex<HashMap<Option<IpAddr>, ThrottleState>>,
+}
+
+impl LogThrottle {
+    /// Create a new throttle. An interval of zero disables throttling.
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            state: Default::default(),
+        }
+    }
+
+    /// Check whether the message for a connection from `peer` should be logged now.
+    /// Returns `None` if the message should be suppressed.
+    pub(crate) fn check(&self, peer: Option<IpAddr>) -> Option<Suppressed> {
+        self.check_at(peer, Instant::now())
+    }
+
+    fn check_at(&self, peer: Option<IpAddr>, now: Instant) -> Option<Suppressed> {
+        if self.interval.is_zero() {
+            return Some(Suppressed(0));
+        }
+        let mut state = self.state.lock();
+        if let Some(s) = state.get_mut(&peer) {
+            if now.saturating_duration_since(s.last_logged) < self.interval {
+                s.suppressed += 1;
+                return None;
+            }
+        }
+        let suppressed = state.remove(&peer).map(|s| s.suppressed).unwrap_or(0);
+        // Forget peers that have been quiet for a full interval, so the map
+        // does not grow with every address that ever had an error.
+        state.retain(|_, s| now.saturating_duration_since(s.last_logged) < self.interval);
+        state.insert(
+            peer,
+            ThrottleState {
+                last_logged: now,
+                suppressed: 0,
+            },
+        );
+        Some(Suppressed(suppressed))
+    }
+}
+
+/// Number of messages suppressed by a [LogThrottle] since the last logged message.
+/// Displays as a suffix for the log message, or nothing if no messages were suppressed.
+pub(crate) struct Suppressed(pub u64);
+
+impl fmt::Display for Suppressed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 > 0 {
+            write!(f, " ({} similar messages suppressed)", self.0)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        time::{Duration, Instant},
+    };
+
+    use super::LogThrottle;
+
+    #[test]
+    fn throttle_repeated_messages() {
+        let throttle = LogThrottle::new(Duration::from_secs(10));
+        let start = Instant::now();
+        let peer = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+
+        assert_eq!(throttle.check_at(peer, start).unwrap().0, 0);
+        for i in 1..5 {
+            assert!(throttle
+                .check_at(peer, start + Duration::from_secs(i))
+                .is_none());
+        }
+        let s = throttle
+            .check_at(peer, start + Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(s.0, 4);
+        assert_eq!(s.to_string(), " (4 similar messages suppressed)");
+
+        assert!(throttle
+            .check_at(peer, start + Duration::from_secs(15))
+            .is_none());
+        let s = throttle
+            .check_at(peer, start + Duration::from_secs(30))
+            .unwrap();
+        assert_eq!(s.0, 1);
+        let s = throttle
+            .check_at(peer, start + Duration::from_secs(45))
+            .unwrap();
+        assert_eq!(s.0, 0);
+        assert_eq!(s.to_string(), "");
+    }
+
+    #[test]
+    fn throttle_per_peer() {
+        let throttle = LogThrottle::new(Duration::from_secs(10));
+        let start = Instant::now();
+        let a = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        let b = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+
+        // Errors from one peer do not suppress errors from another.
+        assert!(throttle.check_at(a, start).is_some());
+        assert!(throttle.check_at(a, start).is_none());
+        assert!(throttle.check_at(b, start).is_some());
+        assert!(throttle.check_at(None, start).is_some());
+        assert!(throttle.check_at(b, start).is_none());
+        assert!(throttle.check_at(None, start).is_none());
+
+        // Quiet peers are forgotten once another message is logged.
+        let later = start + Duration::from_secs(20);
+        assert_eq!(throttle.check_at(a, later).unwrap().0, 1);
+        assert_eq!(throttle.state.lock().len(), 1);
+    }
+
+    #[test]
+    fn throttle_disabled() {
+        let throttle = LogThrottle::new(Duration::ZERO);
+        let now = Instant::now();
+        for _ in 0..5 {
+            assert_eq!(throttle.check_at(None, now).unwrap().0, 0);
+        }
+    }
+}
@@ -1,3 +1,7 @@
+mod channel_limits;
 mod connect;
+mod log_throttle;
 pub(crate) mod tcp;
+pub(crate) use channel_limits::{SecureChannelGuard, SecureChannelLimiter};
 pub(crate) use connect::Connector;
+pub(crate) use log_throttle::LogThrottle;
@@ -1,4 +1,6 @@
 use std::{
+    io::Cursor,
+    net::IpAddr,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -16,11 +18,14 @@ use opcua_core::{
     },
     RequestMessage, ResponseMessage,
 };
-use tracing::error;
+use tracing::{error, warn};
 use tracing_futures::Instrument;
 
 use crate::info::ServerInfo;
-use opcua_types::{DecodingOptions, Error, ResponseHeader, ServiceFault, StatusCode};
+use opcua_types::{
+    DecodingOptions, Error, ResponseHeader, ServiceFault, SimpleBinaryDecodable, StatusCode,
+    UAString,
+};
 
 use futures::StreamExt;
 use tokio::{
@@ -29,7 +34,7 @@ use tokio::{
 };
 use tokio_util::{codec::FramedRead, sync::CancellationToken};
 
-use super::connect::Connector;
+use super::{connect::Connector, SecureChannelGuard};
 
 /// Transport implementation for opc.tcp.
 pub(crate) struct TcpTransport {
@@ -38,10 +43,20 @@ pub(crate) struct TcpTransport {
     send_buffer: SendBuffer,
     state: TransportState,
     pending_chunks: Vec<MessageChunk>,
+    /// Total size in bytes of the chunks in `pending_chunks`.
+    pending_size: usize,
+    /// Time the first chunk of the pending message was received.
+    message_received_at: Instant,
     /// Client protocol version set during HELLO
     pub(crate) client_protocol_version: u32,
+    /// Endpoint URL sent by the client during HELLO
+    pub(crate) client_endpoint_url: UAString,
+    /// IP address of the client, if known.
+    pub(crate) peer_addr: Option<IpAddr>,
     /// Last decoded sequence number
     sequence_numbers: SequenceNumberHandle,
+    /// Registration of this channel with the per IP channel limit.
+    _channel_guard: SecureChannelGuard,
 }
 
 enum TransportState {
@@ -52,6 +67,7 @@ enum TransportState {
 #[derive(Debug, Clone)]
 pub(crate) struct TransportConfig {
     pub send_buffer_size: usize,
+    pub send_buffer_pool_size: usize,
     pub receive_buffer_size: usize,
     pub max_message_size: usize,
     pub max_chunk_count: usize,
@@ -63,6 +79,8 @@ pub(crate) struct Request {
     pub message: RequestMessage,
     pub chunk_info: ChunkInfo,
     pub request_id: u32,
+    /// Time the first chunk of the request was received.
+    pub received_at: Instant,
 }
 
 #[derive(Debug)]
@@ -89,6 +107,7 @@ fn min_zero_infinite(server: u32, client: u32) -> u32 {
 pub(crate) struct TcpConnector {
     read: FramedRead<ReadHalf<TcpStream>, TcpCodec>,
     write: WriteHalf<TcpStream>,
+    peer_addr: Option<IpAddr>,
     deadline: Instant,
     config: TransportConfig,
     decoding_options: DecodingOptions,
@@ -100,18 +119,23 @@ impl TcpConnector {
         config: TransportConfig,
         decoding_options: DecodingOptions,
     ) -> Self {
+        let peer_addr = stream.peer_addr().ok().map(|a| a.ip());
         let (read, write) = tokio::io::split(stream);
         let read = FramedRead::new(read, TcpCodec::new(decoding_options.clone()));
         TcpConnector {
             read,
             write,
+            peer_addr,
             deadline: Instant::now() + config.hello_timeout,
             config,
             decoding_options,
         }
     }
 
-    async fn connect_inner(&mut self, info: Arc<ServerInfo>) -> Result<SendBuffer, ErrorMessage> {
+    async fn connect_inner(
+        &mut self,
+        info: Arc<ServerInfo>,
+    ) -> Result<(SendBuffer, UAString), ErrorMessage> {
         let hello = match self.read.next().await {
             Some(Ok(Message::Hello(hello))) => Ok(hello),
             Some(Ok(bad_msg)) => Err(ErrorMessage::new(
@@ -137,6 +161,7 @@ impl TcpConnector {
             self.config.max_chunk_count,
             true,
         );
+        buffer.set_buffer_pool_size(self.config.send_buffer_pool_size);
 
         let endpoints = info.endpoints(&hello.endpoint_url, &None);
 
@@ -196,7 +221,7 @@ impl TcpConnector {
             )
         })?;
 
-        Ok(buffer)
+        Ok((buffer, hello.endpoint_url))
     }
 }
 
@@ -206,19 +231,39 @@ impl Connector for TcpConnector {
         info: Arc<ServerInfo>,
         token: CancellationToken,
     ) -> Result<TcpTransport, StatusCode> {
-        let err = tokio::select! {
-            _ = tokio::time::sleep_until(self.deadline.into()) => {
-                ErrorMessage::new(StatusCode::BadTimeout, "Timeout waiting for HELLO")
-            }
-            _ = token.cancelled() => {
-                ErrorMessage::new(StatusCode::BadServerHalted, "Server closed")
-            }
-            r = self.connect_inner(info).instrument(tracing::info_span!("OPC-UA TCP handshake")) => {
-                match r {
-                    Ok(r) => return Ok(TcpTransport::new(self.read, self.write, r)),
-                    Err(e) => e,
+        let err = if let Some(guard) = info.secure_channel_limiter.acquire(self.peer_addr) {
+            tokio::select! {
+                _ = tokio::time::sleep_until(self.deadline.into()) => {
+                    ErrorMessage::new(StatusCode::BadTimeout, "Timeout waiting for HELLO")
+                }
+                _ = token.cancelled() => {
+                    ErrorMessage::new(StatusCode::BadServerHalted, "Server closed")
+                }
+                r = self.connect_inner(info).instrument(tracing::info_span!("OPC-UA TCP handshake")) => {
+                    match r {
+                        Ok((buffer, endpoint_url)) => {
+                            return Ok(TcpTransport::new(
+                                self.read,
+                                self.write,
+                                buffer,
+                                endpoint_url,
+                                self.peer_addr,
+                                guard,
+                            ))
+                        }
+                        Err(e) => e,
+                    }
                 }
             }
+        } else {
+            warn!(
+                "Refusing connection from {:?}, too many open secure channels",
+                self.peer_addr
+            );
+            ErrorMessage::new(
+                StatusCode::BadMaxConnectionsReached,
+                "Too many open secure channels from this address",
+            )
         };
 
         // We want to send an error if connection failed for whatever reason, but
@@ -237,15 +282,23 @@ impl TcpTransport {
         read: FramedRead<ReadHalf<TcpStream>, TcpCodec>,
         write: WriteHalf<TcpStream>,
         send_buffer: SendBuffer,
+        client_endpoint_url: UAString,
+        peer_addr: Option<IpAddr>,
+        channel_guard: SecureChannelGuard,
     ) -> Self {
         Self {
             read,
             write,
             state: TransportState::Running,
             pending_chunks: Vec::new(),
+            pending_size: 0,
+            message_received_at: Instant::now(),
             sequence_numbers: SequenceNumberHandle::new(true),
             client_protocol_version: 0,
+            client_endpoint_url,
+            peer_addr,
             send_buffer,
+            _channel_guard: channel_guard,
         }
     }
 
@@ -301,6 +354,7 @@ impl TcpTransport {
         // If there's nothing in the send buffer, but there are chunks available,
         // write them to the send buffer before proceeding.
         if self.send_buffer.should_encode_chunks() {
+            self.send_buffer.wait_for_next_chunk().await;
             if let Err(e) = self.send_buffer.encode_next_chunk(channel) {
                 return TransportPollResult::Error(e);
             }
@@ -343,11 +397,11 @@ impl TcpTransport {
             Ok(message) => match self.process_message(message, channel) {
                 Ok(None) => TransportPollResult::IncomingChunk,
                 Ok(Some(message)) => {
-                    self.pending_chunks.clear();
+                    self.clear_pending_chunks();
                     TransportPollResult::IncomingMessage(message)
                 }
                 Err(e) => {
-                    self.pending_chunks.clear();
+                    self.clear_pending_chunks();
                     if let Some((id, handle)) = e.full_context() {
                         TransportPollResult::RecoverableError(e.status(), id, handle)
                     } else {
@@ -362,6 +416,24 @@ impl TcpTransport {
         }
     }
 
+    fn clear_pending_chunks(&mut self) {
+        self.pending_chunks.clear();
+        self.pending_size = 0;
+    }
+
+    /// Decode the request ID, error and reason from an abort chunk.
+    fn decode_abort(
+        chunk: &MessageChunk,
+        channel: &SecureChannel,
+    ) -> Result<(u32, StatusCode, UAString), Error> {
+        let chunk_info = chunk.chunk_info(channel)?;
+        let decoding_options = channel.decoding_options();
+        let mut stream = Cursor::new(&chunk.data[chunk_info.body_offset..]);
+        let status = StatusCode::decode(&mut stream, &decoding_options)?;
+        let reason = UAString::decode(&mut stream, &decoding_options)?;
+        Ok((chunk_info.sequence_header.request_id, status, reason))
+    }
+
     fn process_message(
         &mut self,
         message: Message,
@@ -372,17 +444,53 @@ impl TcpTransport {
                 let header = chunk.message_header(&channel.decoding_options())?;
 
                 if header.is_final == MessageIsFinalType::FinalError {
-                    self.pending_chunks.clear();
+                    // The client aborted the message, discard any chunks received so far.
+                    let chunk = channel.verify_and_remove_security(&chunk.data)?;
+                    let (request_id, status, reason) = Self::decode_abort(&chunk, channel)?;
+                    let discarded = self.pending_chunks.len();
+                    // The aborted chunks still consume sequence numbers.
+                    self.pending_chunks.push(chunk);
+                    self.sequence_numbers.set(Chunker::validate_chunks(
+                        self.sequence_numbers.clone(),
+                        channel,
+                        &self.pending_chunks,
+                    )?);
+                    self.clear_pending_chunks();
+                    warn!(
+                        "Client aborted message with request id {}, {} chunks discarded: {} ({})",
+                        request_id, discarded, reason, status
+                    );
                     Ok(None)
                 } else {
                     let chunk = channel.verify_and_remove_security(&chunk.data)?;
 
-                    if self.pending_chunks.len() == self.send_buffer.max_chunk_count {
-                        return Err(Error::decoding(format!(
-                            "Message has more than {} chunks, exceeding negotiated limits",
-                            self.send_buffer.max_chunk_count
-                        )));
+                    let max_chunk_count = self.send_buffer.max_chunk_count;
+                    if max_chunk_count > 0 && self.pending_chunks.len() >= max_chunk_count {
+                        return Err(Error::new(
+                            StatusCode::BadRequestTooLarge,
+                            format!(
+                                "Message has more than {} chunks, exceeding negotiated limits",
+                                max_chunk_count
+                            ),
+                        ));
+                    }
+                    let max_message_size = self.send_buffer.max_message_size;
+                    if max_message_size > 0 {
+                        let message_size = self.pending_size + chunk.data.len();
+                        if message_size > max_message_size {
+                            return Err(Error::new(
+                                StatusCode::BadRequestTooLarge,
+                                format!(
+                                    "Message is larger than {} bytes, exceeding negotiated limits",
+                                    max_message_size
+                                ),
+                            ));
+                        }
+                    }
+                    if self.pending_chunks.is_empty() {
+                        self.message_received_at = Instant::now();
                     }
+                    self.pending_size += chunk.data.len();
                     self.pending_chunks.push(chunk);
 
                     if header.is_final == MessageIsFinalType::Intermediate {
@@ -403,6 +511,7 @@ impl TcpTransport {
                         request_id: chunk_info.sequence_header.request_id,
                         chunk_info,
                         message: request,
+                        received_at: self.message_received_at,
                     }))
                 }
             }
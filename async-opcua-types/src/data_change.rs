@@ -90,17 +90,16 @@ impl ParsedDataChangeFilter {
     /// Check if this data change filter considers `v1` different from `v2`.
     pub fn is_changed(&self, v1: &DataValue, v2: &DataValue) -> bool {
         match self.trigger {
-            DataChangeTrigger::Status => v1.status != v2.status,
+            DataChangeTrigger::Status => v2.status_changed_from(v1),
             DataChangeTrigger::StatusValue => {
-                v1.status != v2.status
+                v2.status_changed_from(v1)
                     || self
                         .deadband
                         .is_changed_option(v1.value.as_ref(), v2.value.as_ref())
             }
             DataChangeTrigger::StatusValueTimestamp => {
-                v1.status != v2.status
-                    || v1.source_timestamp != v2.source_timestamp
-                    || v1.source_picoseconds != v2.source_picoseconds
+                v2.status_changed_from(v1)
+                    || v2.timestamp_changed_from(v1)
                     || self
                         .deadband
                         .is_changed_option(v1.value.as_ref(), v2.value.as_ref())
@@ -149,4 +148,15 @@ impl ParsedDataChangeFilter {
             deadband,
         })
     }
+
+    /// Update the EURange used by a percent deadband, for example after the `EURange`
+    /// property of the node was modified. Other deadbands, and invalid ranges, are ignored.
+    pub fn set_eu_range(&mut self, (low, high): (f64, f64)) {
+        if let Deadband::Percent(percent) = &mut self.deadband {
+            if low < high {
+                percent.low = low;
+                percent.range = high - low;
+            }
+        }
+    }
 }
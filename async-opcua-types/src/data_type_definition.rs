@@ -1,8 +1,10 @@
 //! Implementation of the [`DataTypeDefinition`] enum, and some utilities related to this.
 
+use std::fmt;
+
 use crate::match_extension_object_owned;
 
-use super::{EnumDefinition, ExtensionObject, StatusCode, StructureDefinition, Variant};
+use super::{EnumDefinition, EnumField, ExtensionObject, StatusCode, StructureDefinition, Variant};
 
 #[derive(Debug, Clone)]
 /// Type for an OPC UA data type definition.
@@ -49,3 +51,41 @@ impl From<DataTypeDefinition> for Variant {
         value.into_extension_object().into()
     }
 }
+
+impl EnumDefinition {
+    /// Get the field of this enumeration with the given value.
+    pub fn field(&self, value: i64) -> Option<&EnumField> {
+        self.fields.as_ref()?.iter().find(|f| f.value == value)
+    }
+
+    /// Pair the enumeration value `value` with the name of its field.
+    pub fn value<'a>(&'a self, value: i32) -> EnumValue<'a> {
+        EnumValue {
+            value,
+            name: self
+                .field(value.into())
+                .and_then(|f| f.name.value().as_deref()),
+        }
+    }
+}
+
+/// An enumeration value, with the name of its field in an [`EnumDefinition`], if known.
+///
+/// This displays as the name of the field, or as the raw integer if the value has
+/// no named field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnumValue<'a> {
+    /// The integer value, as it is encoded.
+    pub value: i32,
+    /// The name of the field with this value, if the definition has one.
+    pub name: Option<&'a str>,
+}
+
+impl fmt::Display for EnumValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
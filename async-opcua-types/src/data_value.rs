@@ -365,6 +365,15 @@ impl From<DateTime> for DataValue {
     }
 }
 
+impl From<DateTimeUtc> for DataValue {
+    /// Create a data value from a chrono date time. Like converting to a variant, this does
+    /// not check the range: times outside of the range OPC UA can represent (1601 to 9999)
+    /// are clamped to the minimum or maximum date time when the value is encoded.
+    fn from(v: DateTimeUtc) -> Self {
+        Self::from(Variant::from(v))
+    }
+}
+
 impl From<Guid> for DataValue {
     fn from(v: Guid) -> Self {
         Self::from(Variant::from(v))
@@ -633,6 +642,27 @@ impl DataValue {
         self.status().is_good()
     }
 
+    /// Returns `true` if the status of this data value differs from the status of `other`.
+    /// A missing status is treated as `Good`, so a data value without a status is not
+    /// considered changed from one with status `Good`.
+    pub fn status_changed_from(&self, other: &DataValue) -> bool {
+        self.status() != other.status()
+    }
+
+    /// Returns `true` if the value of this data value differs from the value of `other`.
+    /// A missing value is considered different from any present value.
+    pub fn value_changed_from(&self, other: &DataValue) -> bool {
+        self.value != other.value
+    }
+
+    /// Returns `true` if the source timestamp of this data value differs from the
+    /// source timestamp of `other`, including picoseconds. Missing picoseconds are
+    /// treated as `0`. The server timestamp is not considered.
+    pub fn timestamp_changed_from(&self, other: &DataValue) -> bool {
+        self.source_timestamp != other.source_timestamp
+            || self.source_picoseconds.unwrap_or(0) != other.source_picoseconds.unwrap_or(0)
+    }
+
     fn encoding_mask(&self) -> DataValueFlags {
         let mut encoding_mask = DataValueFlags::empty();
         if self.value.is_some() {
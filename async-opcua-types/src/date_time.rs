@@ -349,8 +349,29 @@ impl DateTime {
         self.date_time.to_rfc3339_opts(SecondsFormat::Millis, true)
     }
 
-    /// Parses an RFC 3339 and ISO 8601 date and time string such as 1996-12-19T16:39:57-08:00, then returns a new DateTime
-    pub fn parse_from_rfc3339(s: &str) -> Result<DateTime, chrono::ParseError> {
+    /// Returns an RFC 3339 and ISO 8601 date and time string in UTC, such as 1996-12-19T16:39:57.000Z,
+    /// keeping the full precision of the value.
+    ///
+    /// Milliseconds are always included, further fractional digits are included down to
+    /// the 100 nanosecond tick precision of OPC UA, if they are non-zero.
+    pub fn to_rfc3339_ticks(&self) -> String {
+        let ticks = self.date_time.nanosecond() / NANOS_PER_TICK as u32;
+        let mut fraction = format!("{ticks:07}");
+        while fraction.len() > 3 && fraction.ends_with('0') {
+            fraction.pop();
+        }
+        format!(
+            "{}.{}Z",
+            self.date_time.format("%Y-%m-%dT%H:%M:%S"),
+            fraction
+        )
+    }
+
+    /// Parses an RFC 3339 and ISO 8601 date and time string such as 1996-12-19T16:39:57-08:00, then returns a new DateTime.
+    ///
+    /// The time is converted to UTC, and truncated to the 100 nanosecond tick precision of OPC UA.
+    /// Times before the OPC UA epoch (1601) or after the end of 9999 are clipped to those limits.
+    pub fn from_rfc3339(s: &str) -> Result<DateTime, chrono::ParseError> {
         let date_time = chrono::DateTime::parse_from_rfc3339(s)?;
         // Internally, the min date is going to get clipped to the epoch.
         let mut date_time = date_time.with_timezone(&Utc);
@@ -362,7 +383,14 @@ impl DateTime {
             date_time = Self::endtimes_chrono();
         }
 
-        Ok(Self { date_time })
+        Ok(Self::from(date_time))
+    }
+
+    /// Parses an RFC 3339 and ISO 8601 date and time string such as 1996-12-19T16:39:57-08:00, then returns a new DateTime.
+    ///
+    /// This is the same as [DateTime::from_rfc3339].
+    pub fn parse_from_rfc3339(s: &str) -> Result<DateTime, chrono::ParseError> {
+        Self::from_rfc3339(s)
     }
 
     /// Returns the time in ticks, of 100 nanosecond intervals
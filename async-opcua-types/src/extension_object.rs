@@ -536,8 +536,17 @@ impl BinaryDecodable for ExtensionObject {
                 let size = i32::decode(stream, ctx)?;
                 if size <= 0 {
                     None
+                } else if let Some(body) = ctx.try_load_from_binary(&node_id, &mut stream) {
+                    Some(body?)
                 } else {
-                    Some(ctx.load_from_binary(&node_id, &mut stream)?)
+                    // The type ID may be a JSON encoding ID, in which case the
+                    // body is a JSON document wrapped in a ByteString.
+                    Some(Self::decode_json_body(
+                        &node_id,
+                        size as usize,
+                        stream,
+                        ctx,
+                    )?)
                 }
             }
             0x2 => {
@@ -579,6 +588,38 @@ impl BinaryDecodable for ExtensionObject {
 }
 
 impl ExtensionObject {
+    #[cfg(feature = "json")]
+    fn decode_json_body<S: Read + ?Sized>(
+        node_id: &NodeId,
+        size: usize,
+        stream: &mut S,
+        ctx: &crate::Context<'_>,
+    ) -> EncodingResult<Self> {
+        let max = ctx.options().max_byte_string_length;
+        if max > 0 && size > max {
+            return Err(Error::decoding(format!(
+                "Extension object body length {size} exceeds decoding limit {max}"
+            )));
+        }
+        let mut body = vec![0u8; size];
+        stream.read_exact(&mut body)?;
+        let mut cursor = std::io::Cursor::new(body);
+        let mut inner_stream = crate::json::JsonStreamReader::new(&mut cursor as &mut dyn Read);
+        ctx.load_from_json(node_id, &mut inner_stream)
+    }
+
+    #[cfg(not(feature = "json"))]
+    fn decode_json_body<S: Read + ?Sized>(
+        node_id: &NodeId,
+        _size: usize,
+        _stream: &mut S,
+        _ctx: &crate::Context<'_>,
+    ) -> EncodingResult<Self> {
+        Err(Error::decoding(format!(
+            "No type loader defined for {node_id}"
+        )))
+    }
+
     /// Create an extension object from a structure.
     pub fn new<T>(encodable: T) -> ExtensionObject
     where
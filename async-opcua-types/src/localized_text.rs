@@ -139,4 +139,45 @@ impl LocalizedText {
             text: UAString::null(),
         }
     }
+
+    /// Pick the best match for a list of requested locales from a list of translations
+    /// of the same text, following the locale matching rules in OPC UA Part 4, 5.4.
+    ///
+    /// For each requested locale, in order of preference, a translation with the exact
+    /// same locale is used, then a translation with the same language, so `en-US`
+    /// matches `en` or `en-GB`. If no requested locale matches, the `default` locale
+    /// is matched the same way. If that fails too, the first candidate is returned.
+    ///
+    /// Locales are compared case-insensitively. Returns `None` only if there are no candidates.
+    pub fn resolve<'a>(
+        candidates: &'a [LocalizedText],
+        requested: &[&str],
+        default: &str,
+    ) -> Option<&'a LocalizedText> {
+        requested
+            .iter()
+            .chain(std::iter::once(&default))
+            .filter(|l| !l.is_empty())
+            .find_map(|locale| Self::match_locale(candidates, locale))
+            .or_else(|| candidates.first())
+    }
+
+    fn match_locale<'a>(
+        candidates: &'a [LocalizedText],
+        locale: &str,
+    ) -> Option<&'a LocalizedText> {
+        fn language(locale: &str) -> &str {
+            locale.split(['-', '_']).next().unwrap_or(locale)
+        }
+
+        candidates
+            .iter()
+            .find(|c| c.locale.as_ref().eq_ignore_ascii_case(locale))
+            .or_else(|| {
+                let lang = language(locale);
+                candidates
+                    .iter()
+                    .find(|c| language(c.locale.as_ref()).eq_ignore_ascii_case(lang))
+            })
+    }
 }
@@ -1,8 +1,9 @@
 //! Utilities for working with namespaces.
 
 use hashbrown::HashMap;
+use thiserror::Error;
 
-use crate::{errors::OpcUaError, ExpandedNodeId, NodeId, Variant};
+use crate::{errors::OpcUaError, ExpandedNodeId, NodeId, QualifiedName, Variant};
 
 /// Utility for handling assignment of namespaces on server startup.
 #[derive(Debug, Default, Clone)]
@@ -80,6 +81,129 @@ impl NamespaceMap {
     ) -> Option<std::borrow::Cow<'b, NodeId>> {
         id.try_resolve(self)
     }
+
+    /// Create a remapper translating namespace indices in this namespace map
+    /// to namespace indices in `other`, by namespace URI.
+    ///
+    /// This is useful when exposing nodes from one server on another,
+    /// where the two servers have different namespace arrays.
+    pub fn remapper(&self, other: &NamespaceMap) -> NamespaceRemapper {
+        let mut forward = HashMap::new();
+        let mut inverse = HashMap::new();
+        for (uri, idx) in &self.known_namespaces {
+            if let Some(other_idx) = other.get_index(uri) {
+                forward.insert(*idx, other_idx);
+                inverse.insert(other_idx, *idx);
+            }
+        }
+        let uris = other
+            .known_namespaces
+            .iter()
+            .map(|(uri, idx)| (*idx, uri.clone()))
+            .collect();
+        let inverse_uris = self
+            .known_namespaces
+            .iter()
+            .map(|(uri, idx)| (*idx, uri.clone()))
+            .collect();
+
+        NamespaceRemapper {
+            forward,
+            inverse,
+            uris,
+            inverse_uris,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("Namespace with index {0} does not exist in the target namespace map")]
+/// Error returned when remapping a namespace index that does not exist in
+/// the target namespace map.
+pub struct UnknownNamespace(pub u16);
+
+/// Utility for translating namespace indices between two namespace maps,
+/// created using [`NamespaceMap::remapper`].
+///
+/// Namespaces are matched by URI, any namespace that is not present in both maps
+/// cannot be remapped. Use [`NamespaceRemapper::inverse`] to remap in the opposite direction.
+#[derive(Debug, Clone)]
+pub struct NamespaceRemapper {
+    forward: HashMap<u16, u16>,
+    inverse: HashMap<u16, u16>,
+    uris: HashMap<u16, String>,
+    inverse_uris: HashMap<u16, String>,
+}
+
+impl NamespaceRemapper {
+    /// Get a remapper translating namespace indices in the opposite direction.
+    pub fn inverse(&self) -> NamespaceRemapper {
+        NamespaceRemapper {
+            forward: self.inverse.clone(),
+            inverse: self.forward.clone(),
+            uris: self.inverse_uris.clone(),
+            inverse_uris: self.uris.clone(),
+        }
+    }
+
+    /// Remap a namespace index.
+    pub fn remap_index(&self, index: u16) -> Result<u16, UnknownNamespace> {
+        self.forward
+            .get(&index)
+            .copied()
+            .ok_or(UnknownNamespace(index))
+    }
+
+    /// Remap the namespace index of a node ID.
+    pub fn remap_node_id(&self, id: &NodeId) -> Result<NodeId, UnknownNamespace> {
+        Ok(NodeId {
+            namespace: self.remap_index(id.namespace)?,
+            identifier: id.identifier.clone(),
+        })
+    }
+
+    /// Remap the namespace index of a qualified name.
+    pub fn remap_qualified_name(
+        &self,
+        name: &QualifiedName,
+    ) -> Result<QualifiedName, UnknownNamespace> {
+        Ok(QualifiedName {
+            namespace_index: self.remap_index(name.namespace_index)?,
+            name: name.name.clone(),
+        })
+    }
+
+    /// Remap an expanded node ID. Expanded node IDs pointing to a different server,
+    /// or with a namespace URI, are returned unchanged.
+    pub fn remap_expanded_node_id(
+        &self,
+        id: &ExpandedNodeId,
+    ) -> Result<ExpandedNodeId, UnknownNamespace> {
+        if id.server_index != 0 || !id.namespace_uri.is_null() {
+            return Ok(id.clone());
+        }
+        Ok(ExpandedNodeId::new(self.remap_node_id(&id.node_id)?))
+    }
+
+    /// Remap an expanded node ID. If the namespace does not exist in the target
+    /// namespace map, this returns an expanded node ID with an absolute namespace URI
+    /// instead of failing.
+    pub fn remap_expanded_node_id_or_absolute(&self, id: &ExpandedNodeId) -> ExpandedNodeId {
+        match self.remap_expanded_node_id(id) {
+            Ok(id) => id,
+            Err(UnknownNamespace(idx)) => match self.inverse_uris.get(&idx) {
+                Some(uri) => ExpandedNodeId {
+                    node_id: NodeId {
+                        namespace: 0,
+                        identifier: id.node_id.identifier.clone(),
+                    },
+                    namespace_uri: uri.as_str().into(),
+                    server_index: 0,
+                },
+                None => id.clone(),
+            },
+        }
+    }
 }
 
 /// Utility handling namespaces when loading node sets.
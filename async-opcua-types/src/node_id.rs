@@ -507,9 +507,11 @@ impl FromStr for NodeId {
         //   b = OPAQUE (ByteString)
         //
         // If namespace == 0, the ns=0; will be omitted
+        //
+        // The value may contain any character, including `;`, `=` and line breaks.
 
         static RE: LazyLock<Regex> =
-            LazyLock::new(|| Regex::new(r"^(ns=(?P<ns>[0-9]+);)?(?P<t>[isgb]=.+)$").unwrap());
+            LazyLock::new(|| Regex::new(r"(?s)^(ns=(?P<ns>[0-9]+);)?(?P<t>[isgb]=.+)$").unwrap());
 
         let captures = RE.captures(s).ok_or(StatusCode::BadNodeIdInvalid)?;
 
@@ -6,6 +6,7 @@
 use std::{
     fmt::Display,
     io::{Read, Write},
+    str::FromStr,
     sync::LazyLock,
 };
 
@@ -15,7 +16,7 @@ use regex::Regex;
 use crate::{
     encoding::{BinaryDecodable, BinaryEncodable, EncodingResult},
     string::*,
-    NamespaceMap, UaNullable,
+    NamespaceMap, StatusCode, UaNullable,
 };
 
 #[allow(unused)]
@@ -226,6 +227,35 @@ impl Display for QualifiedName {
 static NUMERIC_QNAME_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r#"^(\d+):(.*)$"#).unwrap());
 
+impl FromStr for QualifiedName {
+    type Err = StatusCode;
+
+    /// Parse a QualifiedName from the form `ns:name`, for example `1:Temperature`.
+    /// The namespace prefix is optional, if it is omitted the namespace index is 0.
+    /// This is the inverse of the `Display` implementation.
+    ///
+    /// Fails with `BadBrowseNameInvalid` if the name is empty, or if the namespace
+    /// index is not a valid `u16`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (namespace_index, name) = match NUMERIC_QNAME_REGEX.captures(s) {
+            Some(caps) => {
+                let namespace_index = caps
+                    .get(1)
+                    .unwrap()
+                    .as_str()
+                    .parse::<u16>()
+                    .map_err(|_| StatusCode::BadBrowseNameInvalid)?;
+                (namespace_index, caps.get(2).unwrap().as_str())
+            }
+            None => (0, s),
+        };
+        if name.is_empty() {
+            return Err(StatusCode::BadBrowseNameInvalid);
+        }
+        Ok(QualifiedName::new(namespace_index, name))
+    }
+}
+
 impl QualifiedName {
     /// Create a new qualified name from namespace index and name.
     pub fn new<T>(namespace_index: u16, name: T) -> QualifiedName
@@ -0,0 +1,86 @@
+use crate::{
+    DataChangeFilter, DataChangeTrigger, DataValue, DateTime, ParsedDataChangeFilter, StatusCode,
+};
+
+#[test]
+fn status_changed_from() {
+    let good = DataValue::from((5i32.into(), StatusCode::Good));
+    let bad = DataValue::from((5i32.into(), StatusCode::BadUnexpectedError));
+    let no_status = DataValue::value_only(5i32);
+
+    assert!(!good.status_changed_from(&good));
+    assert!(bad.status_changed_from(&good));
+    assert!(good.status_changed_from(&bad));
+    // A missing status is treated as Good.
+    assert!(!no_status.status_changed_from(&good));
+    assert!(!good.status_changed_from(&no_status));
+    assert!(no_status.status_changed_from(&bad));
+    // Only the status is considered.
+    let other_value = DataValue::from((6i32.into(), StatusCode::Good));
+    assert!(!other_value.status_changed_from(&good));
+}
+
+#[test]
+fn value_changed_from() {
+    let time = DateTime::now();
+    let v1 = DataValue::new_at(5i32, time);
+    let v2 = DataValue::new_at_status(5i32, DateTime::null(), StatusCode::BadUnexpectedError);
+    assert!(!v1.value_changed_from(&v2));
+    assert!(DataValue::new_at(6i32, time).value_changed_from(&v1));
+    assert!(DataValue::new_at(5i64, time).value_changed_from(&v1));
+    assert!(DataValue::null().value_changed_from(&v1));
+    assert!(!DataValue::null().value_changed_from(&DataValue::null()));
+}
+
+#[test]
+fn timestamp_changed_from() {
+    let time = DateTime::now();
+    let v1 = DataValue::new_at(5i32, time);
+    let mut v2 = DataValue::new_at(6i32, time);
+    assert!(!v2.timestamp_changed_from(&v1));
+
+    // Missing picoseconds are treated as 0.
+    v2.source_picoseconds = None;
+    assert!(!v2.timestamp_changed_from(&v1));
+    v2.source_picoseconds = Some(10);
+    assert!(v2.timestamp_changed_from(&v1));
+
+    // The server timestamp is not considered.
+    let mut v3 = DataValue::new_at(5i32, time);
+    v3.server_timestamp = None;
+    assert!(!v3.timestamp_changed_from(&v1));
+
+    let v4 = DataValue::new_at(5i32, time + chrono::TimeDelta::milliseconds(1));
+    assert!(v4.timestamp_changed_from(&v1));
+    assert!(DataValue::value_only(5i32).timestamp_changed_from(&v1));
+}
+
+#[test]
+fn data_change_trigger_status() {
+    let filter = |trigger| {
+        ParsedDataChangeFilter::parse(
+            DataChangeFilter {
+                trigger,
+                deadband_type: 0,
+                deadband_value: 0.0,
+            },
+            None,
+        )
+        .unwrap()
+    };
+    let time = DateTime::now();
+    let v1 = DataValue::value_only(5i32);
+    let v2 = DataValue::new_at(6i32, time);
+    let v3 = DataValue::new_at_status(6i32, time, StatusCode::UncertainLastUsableValue);
+
+    let status = filter(DataChangeTrigger::Status);
+    assert!(!status.is_changed(&v1, &v2));
+    assert!(status.is_changed(&v2, &v3));
+
+    let status_value = filter(DataChangeTrigger::StatusValue);
+    assert!(status_value.is_changed(&v1, &v2));
+    assert!(!status_value.is_changed(&v2, &DataValue::new_at(6i32, DateTime::null())));
+
+    let status_value_timestamp = filter(DataChangeTrigger::StatusValueTimestamp);
+    assert!(status_value_timestamp.is_changed(&v2, &DataValue::new_at(6i32, DateTime::null())));
+}
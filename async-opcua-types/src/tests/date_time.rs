@@ -85,3 +85,57 @@ fn iso8601() {
     let dt = DateTime::parse_from_rfc3339(lt_min_date).unwrap();
     assert_eq!(epoch, dt.to_rfc3339());
 }
+
+#[test]
+fn rfc3339_fractional_seconds() {
+    // Full tick precision round trips
+    let s = "2024-05-06T07:08:09.1234567Z";
+    let dt = DateTime::from_rfc3339(s).unwrap();
+    assert_eq!(dt.to_rfc3339_ticks(), s);
+    // to_rfc3339 keeps millisecond precision
+    assert_eq!(dt.to_rfc3339(), "2024-05-06T07:08:09.123Z");
+
+    // Milliseconds are always included
+    let dt = DateTime::from_rfc3339("2024-05-06T07:08:09Z").unwrap();
+    assert_eq!(dt.to_rfc3339_ticks(), "2024-05-06T07:08:09.000Z");
+    let dt = DateTime::from_rfc3339("2024-05-06T07:08:09.5Z").unwrap();
+    assert_eq!(dt.to_rfc3339_ticks(), "2024-05-06T07:08:09.500Z");
+
+    // Trailing zeros beyond milliseconds are trimmed
+    let dt = DateTime::from_rfc3339("2024-05-06T07:08:09.123400Z").unwrap();
+    assert_eq!(dt.to_rfc3339_ticks(), "2024-05-06T07:08:09.1234Z");
+
+    // Precision beyond 100 nanoseconds is truncated
+    let dt = DateTime::from_rfc3339("2024-05-06T07:08:09.123456789Z").unwrap();
+    assert_eq!(dt.to_rfc3339_ticks(), "2024-05-06T07:08:09.1234567Z");
+    assert_eq!(
+        dt,
+        DateTime::from_rfc3339("2024-05-06T07:08:09.1234567Z").unwrap()
+    );
+    assert_eq!(
+        dt.ticks() % 10_000_000,
+        1_234_567,
+        "Ticks should contain the fractional part"
+    );
+
+    // Round trip through the string representation preserves the tick count.
+    let now = DateTime::now();
+    let now2 = DateTime::from_rfc3339(&now.to_rfc3339_ticks()).unwrap();
+    assert_eq!(now.ticks(), now2.ticks());
+}
+
+#[test]
+fn rfc3339_timezone_offset() {
+    // Offsets are converted to UTC
+    let dt = DateTime::from_rfc3339("2024-05-06T09:08:09.25+02:00").unwrap();
+    assert_eq!(dt.to_rfc3339(), "2024-05-06T07:08:09.250Z");
+    let dt2 = DateTime::from_rfc3339("2024-05-05T21:38:09.25-09:30").unwrap();
+    assert_eq!(dt, dt2);
+
+    // Offsets can move the date across the epoch
+    let dt = DateTime::from_rfc3339("1601-01-01T01:00:00+02:00").unwrap();
+    assert_eq!(dt, DateTime::epoch());
+    let dt = DateTime::from_rfc3339("1601-01-01T01:00:00-02:00").unwrap();
+    assert_eq!(dt.to_rfc3339(), "1601-01-01T03:00:00.000Z");
+    assert_eq!(dt.ticks(), 3 * 3600 * 10_000_000);
+}
@@ -693,3 +693,41 @@ fn test_xml_in_binary() {
     let decoded = ExtensionObject::decode(&mut stream, &ctx).unwrap();
     assert_eq!(decoded.inner_as::<EUInformation>().unwrap(), &rf);
 }
+
+#[test]
+fn type_registry_enumerates_types() {
+    use crate::{Argument, BinaryEncodable, DataTypeId, GeneratedTypeLoader};
+
+    let registry = GeneratedTypeLoader::type_registry();
+    let ids: Vec<_> = registry.binary_type_ids().collect();
+    assert!(ids.contains(&(DataTypeId::Argument as u32)));
+    assert!(ids.contains(&(ObjectId::Argument_Encoding_DefaultBinary as u32)));
+    #[cfg(feature = "json")]
+    assert!(registry
+        .json_type_ids()
+        .any(|id| id == ObjectId::Argument_Encoding_DefaultJson as u32));
+
+    let arg = Argument {
+        name: "Arg".into(),
+        data_type: DataTypeId::Int32.into(),
+        value_rank: -1,
+        array_dimensions: Some(vec![]),
+        description: "Some argument".into(),
+    };
+    let ctx_f = ContextOwned::default();
+    let ctx = ctx_f.context();
+    let mut buf = Vec::new();
+    arg.encode(&mut buf, &ctx).unwrap();
+    let decoded = registry
+        .decode_binary(
+            ObjectId::Argument_Encoding_DefaultBinary as u32,
+            &mut Cursor::new(buf),
+            &ctx,
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        decoded.as_dyn_any_ref().downcast_ref::<Argument>(),
+        Some(&arg)
+    );
+}
@@ -94,6 +94,13 @@ fn serialize_date_time() {
     assert_eq!(dt1, dt2);
 }
 
+#[test]
+fn serialize_date_time_millis() {
+    // The JSON encoding uses millisecond precision, even if the value has more.
+    let dt = DateTime::from_rfc3339("2024-05-06T07:08:09.1234567Z").unwrap();
+    assert_eq!(to_value(&dt).unwrap(), json!("2024-05-06T07:08:09.123Z"));
+}
+
 #[test]
 fn serialize_guid() {
     let g1 = Guid::new();
@@ -1008,3 +1015,52 @@ fn test_binary_in_json() {
         obj_3.inner_as().unwrap()
     );
 }
+
+#[test]
+fn test_json_in_binary() {
+    let rf = EUInformation {
+        namespace_uri: "https://my.namespace.uri".into(),
+        unit_id: 1,
+        display_name: LocalizedText::new("en", "MyUnit"),
+        description: LocalizedText::new("en", "MyDesc"),
+    };
+    let ctx_r = ContextOwned::default();
+    let ctx = ctx_r.context();
+
+    // Binary extension object with the JSON encoding ID, and the JSON body
+    // wrapped in a ByteString.
+    let body = to_string(&rf).unwrap();
+    let mut buf = Vec::new();
+    let mut cursor = Cursor::new(&mut buf);
+    BinaryEncodable::encode(
+        &NodeId::from(ObjectId::EUInformation_Encoding_DefaultJson),
+        &mut cursor,
+        &ctx,
+    )
+    .unwrap();
+    BinaryEncodable::encode(&1u8, &mut cursor, &ctx).unwrap();
+    BinaryEncodable::encode(&ByteString::from(body.as_bytes()), &mut cursor, &ctx).unwrap();
+
+    let mut cursor = Cursor::new(&buf);
+    let obj: ExtensionObject = crate::BinaryDecodable::decode(&mut cursor, &ctx).unwrap();
+    assert_eq!(&rf, obj.inner_as::<EUInformation>().unwrap());
+    // The whole body should be consumed.
+    assert_eq!(cursor.position() as usize, buf.len());
+
+    // Encoding the decoded object again uses the binary encoding, which decodes to the same value.
+    let mut bin = Vec::new();
+    BinaryEncodable::encode(&obj, &mut Cursor::new(&mut bin), &ctx).unwrap();
+    let obj2: ExtensionObject =
+        crate::BinaryDecodable::decode(&mut Cursor::new(&bin), &ctx).unwrap();
+    assert_eq!(&rf, obj2.inner_as::<EUInformation>().unwrap());
+
+    // An unknown encoding ID is still an error.
+    let mut buf = Vec::new();
+    let mut cursor = Cursor::new(&mut buf);
+    BinaryEncodable::encode(&NodeId::new(0, 123456u32), &mut cursor, &ctx).unwrap();
+    BinaryEncodable::encode(&1u8, &mut cursor, &ctx).unwrap();
+    BinaryEncodable::encode(&ByteString::from(body.as_bytes()), &mut cursor, &ctx).unwrap();
+    assert!(
+        <ExtensionObject as crate::BinaryDecodable>::decode(&mut Cursor::new(&buf), &ctx).is_err()
+    );
+}
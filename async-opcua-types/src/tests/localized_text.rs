@@ -0,0 +1,46 @@
+use crate::LocalizedText;
+
+fn candidates() -> Vec<LocalizedText> {
+    vec![
+        LocalizedText::new("de", "Hallo"),
+        LocalizedText::new("en", "Hello"),
+        LocalizedText::new("fr-FR", "Bonjour"),
+    ]
+}
+
+#[test]
+fn resolve_exact() {
+    let c = candidates();
+    let r = LocalizedText::resolve(&c, &["fr-FR", "en"], "de").unwrap();
+    assert_eq!(r.text.as_ref(), "Bonjour");
+    // Case-insensitive
+    let r = LocalizedText::resolve(&c, &["EN"], "de").unwrap();
+    assert_eq!(r.text.as_ref(), "Hello");
+}
+
+#[test]
+fn resolve_language_only() {
+    let c = candidates();
+    // en-US is requested, but only en is available
+    let r = LocalizedText::resolve(&c, &["en-US"], "de").unwrap();
+    assert_eq!(r.text.as_ref(), "Hello");
+    // fr is requested, fr-FR is available
+    let r = LocalizedText::resolve(&c, &["fr"], "de").unwrap();
+    assert_eq!(r.text.as_ref(), "Bonjour");
+    // Earlier requested locales take precedence, even for language-only matches.
+    let r = LocalizedText::resolve(&c, &["en-US", "de"], "de").unwrap();
+    assert_eq!(r.text.as_ref(), "Hello");
+}
+
+#[test]
+fn resolve_default() {
+    let c = candidates();
+    let r = LocalizedText::resolve(&c, &["es-ES", "it"], "de").unwrap();
+    assert_eq!(r.text.as_ref(), "Hallo");
+    let r = LocalizedText::resolve(&c, &[], "en-GB").unwrap();
+    assert_eq!(r.text.as_ref(), "Hello");
+    // Nothing matches, use the first candidate
+    let r = LocalizedText::resolve(&c, &["es"], "it").unwrap();
+    assert_eq!(r.text.as_ref(), "Hallo");
+    assert!(LocalizedText::resolve(&[], &["en"], "en").is_none());
+}
@@ -1,8 +1,12 @@
+mod data_value;
 mod date_time;
 mod encoding;
 #[cfg(feature = "json")]
 mod json;
+mod localized_text;
+mod namespaces;
 mod node_id;
+mod qualified_name;
 mod variant;
 #[cfg(feature = "xml")]
 mod xml;
@@ -0,0 +1,84 @@
+use crate::{ExpandedNodeId, NamespaceMap, NodeId, QualifiedName, UnknownNamespace};
+
+fn remote_and_local() -> (NamespaceMap, NamespaceMap) {
+    let mut remote = NamespaceMap::new();
+    remote.add_namespace("urn:a");
+    remote.add_namespace("urn:b");
+    remote.add_namespace("urn:remote-only");
+
+    let mut local = NamespaceMap::new();
+    local.add_namespace("urn:local-only");
+    local.add_namespace("urn:b");
+    local.add_namespace("urn:a");
+    (remote, local)
+}
+
+#[test]
+fn remap_node_ids() {
+    let (remote, local) = remote_and_local();
+    let remapper = remote.remapper(&local);
+
+    assert_eq!(remapper.remap_index(0), Ok(0));
+    assert_eq!(remapper.remap_index(1), Ok(3));
+    assert_eq!(remapper.remap_index(2), Ok(2));
+    assert_eq!(remapper.remap_index(3), Err(UnknownNamespace(3)));
+    assert_eq!(remapper.remap_index(4), Err(UnknownNamespace(4)));
+
+    assert_eq!(
+        remapper.remap_node_id(&NodeId::new(1, "foo")),
+        Ok(NodeId::new(3, "foo"))
+    );
+    assert_eq!(
+        remapper.remap_node_id(&NodeId::new(3, 15)),
+        Err(UnknownNamespace(3))
+    );
+    assert_eq!(
+        remapper.remap_qualified_name(&QualifiedName::new(2, "Name")),
+        Ok(QualifiedName::new(2, "Name"))
+    );
+    assert_eq!(
+        remapper.remap_qualified_name(&QualifiedName::new(3, "Name")),
+        Err(UnknownNamespace(3))
+    );
+}
+
+#[test]
+fn remap_inverse() {
+    let (remote, local) = remote_and_local();
+    let remapper = remote.remapper(&local).inverse();
+
+    assert_eq!(
+        remapper.remap_node_id(&NodeId::new(3, "foo")),
+        Ok(NodeId::new(1, "foo"))
+    );
+    assert_eq!(
+        remapper.remap_node_id(&NodeId::new(1, "foo")),
+        Err(UnknownNamespace(1))
+    );
+    assert_eq!(
+        remapper.remap_qualified_name(&QualifiedName::new(2, "Name")),
+        Ok(QualifiedName::new(2, "Name"))
+    );
+}
+
+#[test]
+fn remap_expanded_node_ids() {
+    let (remote, local) = remote_and_local();
+    let remapper = remote.remapper(&local);
+
+    assert_eq!(
+        remapper.remap_expanded_node_id(&NodeId::new(1, "foo").into()),
+        Ok(NodeId::new(3, "foo").into())
+    );
+    assert_eq!(
+        remapper.remap_expanded_node_id(&NodeId::new(3, "foo").into()),
+        Err(UnknownNamespace(3))
+    );
+    // Absolute node IDs are left alone
+    let absolute = ExpandedNodeId::new_with_namespace("urn:remote-only", "foo");
+    assert_eq!(remapper.remap_expanded_node_id(&absolute), Ok(absolute));
+
+    let id = remapper.remap_expanded_node_id_or_absolute(&NodeId::new(3, "foo").into());
+    assert_eq!(id.namespace_uri.as_ref(), "urn:remote-only");
+    assert_eq!(id.node_id, NodeId::new(0, "foo"));
+}
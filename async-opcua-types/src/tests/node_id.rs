@@ -87,6 +87,41 @@ fn parse_node_id_byte_string() {
     assert_eq!(format!("{}", node_id), "ns=1;b=M/RbKBsRVkePCePcx24oRA==");
 }
 
+#[test]
+fn node_id_round_trip() {
+    let guid = Guid::from_str("72962b91-fa75-4ae6-8d28-b404dc7daf63").unwrap();
+    [
+        NodeId::new(0, 0),
+        NodeId::new(0, u32::MAX),
+        NodeId::new(5, 123),
+        NodeId::new(u16::MAX, 1),
+        NodeId::new(0, "Hello World"),
+        NodeId::new(2, "with;semicolon"),
+        NodeId::new(2, "with=equals"),
+        NodeId::new(2, "ns=3;s=nested"),
+        NodeId::new(0, "ns=3;i=1"),
+        NodeId::new(2, "line\nbreak"),
+        NodeId::new(2, " padded "),
+        NodeId::new(2, "ünïcødé ✓"),
+        NodeId::new(0, guid.clone()),
+        NodeId::new(7, guid),
+        NodeId::new(0, ByteString::from(vec![0u8, 1, 2, 3])),
+        NodeId::new(3, ByteString::from(b";=ns=1;i=2".to_vec())),
+        NodeId::new(3, ByteString::from(vec![0xfbu8, 0xff, 0xfe])),
+    ]
+    .into_iter()
+    .for_each(|node_id| {
+        let s = node_id.to_string();
+        assert_eq!(node_id.namespace == 0, !s.starts_with("ns="), "{s}");
+        assert_eq!(NodeId::from_str(&s), Ok(node_id), "{s}");
+    });
+
+    // An explicit namespace 0 is accepted, but omitted when formatted.
+    let node_id = NodeId::from_str("ns=0;i=85").unwrap();
+    assert_eq!(node_id, NodeId::new(0, 85));
+    assert_eq!(node_id.to_string(), "i=85");
+}
+
 #[test]
 fn expanded_node_id() {
     // Parse invalid expanded node ids
@@ -0,0 +1,51 @@
+use std::str::FromStr;
+
+use crate::{QualifiedName, StatusCode};
+
+#[test]
+fn parse_qualified_name() {
+    assert_eq!(
+        QualifiedName::from_str("1:Temperature").unwrap(),
+        QualifiedName::new(1, "Temperature")
+    );
+    assert_eq!(
+        QualifiedName::from_str("Temperature").unwrap(),
+        QualifiedName::new(0, "Temperature")
+    );
+    assert_eq!(
+        "0:Temperature".parse::<QualifiedName>().unwrap(),
+        QualifiedName::new(0, "Temperature")
+    );
+    // Only the first colon separates the namespace
+    assert_eq!(
+        QualifiedName::from_str("2:A:B").unwrap(),
+        QualifiedName::new(2, "A:B")
+    );
+    // A non-numeric prefix is part of the name
+    assert_eq!(
+        QualifiedName::from_str("ns:Name").unwrap(),
+        QualifiedName::new(0, "ns:Name")
+    );
+}
+
+#[test]
+fn parse_qualified_name_invalid() {
+    for s in ["", "1:", "70000:Name"] {
+        assert_eq!(
+            QualifiedName::from_str(s),
+            Err(StatusCode::BadBrowseNameInvalid),
+            "{s}"
+        );
+    }
+}
+
+#[test]
+fn qualified_name_display_round_trip() {
+    for qn in [
+        QualifiedName::new(0, "Name"),
+        QualifiedName::new(3, "Name"),
+        QualifiedName::new(65535, "With space"),
+    ] {
+        assert_eq!(QualifiedName::from_str(&qn.to_string()).unwrap(), qn);
+    }
+}
@@ -4,8 +4,9 @@ use crate::{
     numeric_range::NumericRange,
     status_code::StatusCode,
     variant::{Variant, VariantTypeId},
-    ByteString, DataTypeId, DataValue, DateTime, DiagnosticInfo, ExpandedNodeId, Guid,
-    LocalizedText, NodeId, QualifiedName, TryFromVariant, UAString, VariantScalarTypeId,
+    ByteString, DataTypeId, DataValue, DateTime, DiagnosticInfo, EnumDefinition, EnumField,
+    ExpandedNodeId, FromVariants, Guid, LocalizedText, NodeId, QualifiedName, TryFromVariant,
+    UAString, VariantScalarTypeId,
 };
 
 #[test]
@@ -1652,4 +1653,106 @@ fn variant_bytestring_to_bytearray() {
     assert_eq!(v[3], Variant::Byte(0x4));
 }
 
+#[test]
+fn chrono_date_time() {
+    use chrono::TimeZone;
+
+    let now = chrono::Utc::now();
+    // OPC UA date times have a resolution of 100 nanoseconds.
+    let expected = DateTime::from(now).as_chrono();
+
+    let v = Variant::from(now);
+    assert!(matches!(v, Variant::DateTime(_)));
+    assert_eq!(
+        chrono::DateTime::<chrono::Utc>::try_from(v).unwrap(),
+        expected
+    );
+
+    let dv = DataValue::from(now);
+    assert_eq!(
+        chrono::DateTime::<chrono::Utc>::try_from(dv.value.unwrap()).unwrap(),
+        expected
+    );
+
+    // Times outside of the range OPC UA can represent are clamped when encoded,
+    // both for variants and data values.
+    let too_early = chrono::Utc.with_ymd_and_hms(1500, 1, 1, 0, 0, 0).unwrap();
+    let too_late = chrono::Utc.with_ymd_and_hms(10000, 1, 1, 0, 0, 0).unwrap();
+    for (time, ticks) in [(too_early, 0), (too_late, i64::MAX)] {
+        let Variant::DateTime(v) = Variant::from(time) else {
+            panic!("Expected a date time variant");
+        };
+        assert_eq!(v.checked_ticks(), ticks);
+        let Some(Variant::DateTime(v)) = DataValue::from(time).value else {
+            panic!("Expected a date time value");
+        };
+        assert_eq!(v.checked_ticks(), ticks);
+    }
+
+    // The OPC UA epoch is 1601-01-01, which is a valid chrono date time.
+    let epoch = chrono::DateTime::<chrono::Utc>::try_from(Variant::from(DateTime::null())).unwrap();
+    assert_eq!(epoch.to_rfc3339(), "1601-01-01T00:00:00+00:00");
+
+    // Wrong type
+    let err = chrono::DateTime::<chrono::Utc>::try_from(Variant::from(5i32)).unwrap_err();
+    assert_eq!(err.status(), StatusCode::BadTypeMismatch);
+}
+
+#[test]
+fn from_variants_tuple() {
+    let (a, b, c) = <(i32, UAString, f64)>::from_variants(vec![
+        Variant::Int32(5),
+        Variant::from("foo"),
+        Variant::Double(1.5),
+    ])
+    .unwrap();
+    assert_eq!(a, 5);
+    assert_eq!(b, UAString::from("foo"));
+    assert_eq!(c, 1.5);
+
+    // Wrong number of variants
+    let err = <(i32, i32)>::from_variants(vec![Variant::Int32(1)]).unwrap_err();
+    assert_eq!(err.status(), StatusCode::BadTypeMismatch);
+
+    // Wrong type
+    let err = <(i32,)>::from_variants(vec![Variant::from("foo")]).unwrap_err();
+    assert_eq!(err.status(), StatusCode::BadTypeMismatch);
+
+    <()>::from_variants(Vec::new()).unwrap();
+}
+
 // TODO arrays
+
+#[test]
+fn variant_enum() {
+    let definition = EnumDefinition {
+        fields: Some(vec![
+            EnumField {
+                value: 0,
+                name: "Off".into(),
+                ..Default::default()
+            },
+            EnumField {
+                value: 1,
+                name: "On".into(),
+                ..Default::default()
+            },
+        ]),
+    };
+
+    assert_eq!(Variant::Int32(1).as_enum_i32(), Some(1));
+    assert_eq!(Variant::UInt32(1).as_enum_i32(), None);
+
+    let value = Variant::Int32(1).as_enum(&definition).unwrap();
+    assert_eq!(value.value, 1);
+    assert_eq!(value.name, Some("On"));
+    assert_eq!(value.to_string(), "On");
+
+    // Unknown values are displayed as the raw integer.
+    let value = Variant::Int32(7).as_enum(&definition).unwrap();
+    assert_eq!(value.name, None);
+    assert_eq!(value.to_string(), "7");
+
+    assert!(Variant::from("On").as_enum(&definition).is_none());
+    assert_eq!(definition.field(0).unwrap().name.as_ref(), "Off");
+}
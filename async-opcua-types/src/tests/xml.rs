@@ -221,6 +221,17 @@ fn from_xml_date_time() {
     );
 }
 
+#[test]
+fn to_xml_date_time_millis() {
+    // The XML encoding uses millisecond precision, even if the value has more.
+    let ctx = ContextOwned::new_default(namespaces(), DecodingOptions::default());
+    let dt = DateTime::from_rfc3339("2024-05-06T07:08:09.1234567Z").unwrap();
+    assert_eq!(
+        encode_xml_ctx(&dt, &ctx.context()).unwrap(),
+        "2024-05-06T07:08:09.123Z"
+    );
+}
+
 #[test]
 fn from_xml_byte_string() {
     xml_round_trip(&ByteString::from_base64("aGVsbG8=").unwrap(), "aGVsbG8=");
@@ -94,6 +94,26 @@ impl TypeLoaderInstance {
         self.json_types.insert(encoding_type, fun);
     }
 
+    /// Iterate over the IDs of all types that can be decoded from binary.
+    /// This includes both data type IDs and binary encoding IDs.
+    pub fn binary_type_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.binary_types.keys().copied()
+    }
+
+    #[cfg(feature = "xml")]
+    /// Iterate over the IDs of all types that can be decoded from XML.
+    /// This includes both data type IDs and XML encoding IDs.
+    pub fn xml_type_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.xml_types.keys().copied()
+    }
+
+    #[cfg(feature = "json")]
+    /// Iterate over the IDs of all types that can be decoded from JSON.
+    /// This includes both data type IDs and JSON encoding IDs.
+    pub fn json_type_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.json_types.keys().copied()
+    }
+
     /// Decode the type with ID `ty` using binary encoding.
     pub fn decode_binary(
         &self,
@@ -210,6 +230,7 @@ where
 /// Owned variant of [Context], this is stored by clients and servers, which
 /// call the [ContextOwned::context] method to produce a [Context]
 /// for decoding/encoding.
+#[derive(Clone)]
 pub struct ContextOwned {
     namespaces: NamespaceMap,
     loaders: TypeLoaderCollection,
@@ -484,14 +505,27 @@ impl<'a> Context<'a> {
         node_id: &NodeId,
         stream: &mut dyn Read,
     ) -> crate::EncodingResult<crate::ExtensionObject> {
+        self.try_load_from_binary(node_id, stream)
+            .unwrap_or_else(|| {
+                Err(Error::decoding(format!(
+                    "No type loader defined for {node_id}"
+                )))
+            })
+    }
+
+    /// Try to load a type dynamically from OPC-UA binary, returning `None` if no
+    /// matching type loader was found. Nothing is read from the stream in that case.
+    pub(crate) fn try_load_from_binary(
+        &self,
+        node_id: &NodeId,
+        stream: &mut dyn Read,
+    ) -> Option<crate::EncodingResult<crate::ExtensionObject>> {
         for loader in self.loaders {
             if let Some(r) = loader.load_from_binary(node_id, stream, self) {
-                return Ok(crate::ExtensionObject { body: Some(r?) });
+                return Some(r.map(|r| crate::ExtensionObject { body: Some(r) }));
             }
         }
-        Err(Error::decoding(format!(
-            "No type loader defined for {node_id}"
-        )))
+        None
     }
 
     #[cfg(feature = "xml")]
@@ -96,6 +96,14 @@ impl TryFromVariant for DateTimeUtc {
     }
 }
 
+impl TryFrom<Variant> for DateTimeUtc {
+    type Error = Error;
+
+    fn try_from(value: Variant) -> Result<Self, Self::Error> {
+        Self::try_from_variant(value)
+    }
+}
+
 impl<T> TryFromVariant for T
 where
     T: DynEncodable,
@@ -193,3 +201,50 @@ where
             .map_err(|_| Error::new(StatusCode::BadTypeMismatch, "Array size mismatch"))
     }
 }
+
+/// Trait for types that can be created from a list of variants, such as
+/// the output arguments of a method call.
+///
+/// This is implemented for `Vec<Variant>`, and for tuples of up to 10 elements
+/// implementing [TryFromVariant]. For tuples, the number of variants must match
+/// the number of elements in the tuple.
+pub trait FromVariants: Sized {
+    /// Try to convert the given list of variants to this type.
+    fn from_variants(variants: Vec<Variant>) -> Result<Self, Error>;
+}
+
+impl FromVariants for Vec<Variant> {
+    fn from_variants(variants: Vec<Variant>) -> Result<Self, Error> {
+        Ok(variants)
+    }
+}
+
+macro_rules! impl_from_variants_tuple {
+    ($len:literal; $($t:ident),*) => {
+        impl<$($t: TryFromVariant),*> FromVariants for ($($t,)*) {
+            fn from_variants(variants: Vec<Variant>) -> Result<Self, Error> {
+                if variants.len() != $len {
+                    return Err(Error::new(
+                        StatusCode::BadTypeMismatch,
+                        format!("Expected {} variants, got {}", $len, variants.len()),
+                    ));
+                }
+                #[allow(unused_mut, unused_variables)]
+                let mut iter = variants.into_iter();
+                Ok(($($t::try_from_variant(iter.next().unwrap_or_default())?,)*))
+            }
+        }
+    };
+}
+
+impl_from_variants_tuple!(0;);
+impl_from_variants_tuple!(1; T1);
+impl_from_variants_tuple!(2; T1, T2);
+impl_from_variants_tuple!(3; T1, T2, T3);
+impl_from_variants_tuple!(4; T1, T2, T3, T4);
+impl_from_variants_tuple!(5; T1, T2, T3, T4, T5);
+impl_from_variants_tuple!(6; T1, T2, T3, T4, T5, T6);
+impl_from_variants_tuple!(7; T1, T2, T3, T4, T5, T6, T7);
+impl_from_variants_tuple!(8; T1, T2, T3, T4, T5, T6, T7, T8);
+impl_from_variants_tuple!(9; T1, T2, T3, T4, T5, T6, T7, T8, T9);
+impl_from_variants_tuple!(10; T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
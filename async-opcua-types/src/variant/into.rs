@@ -96,6 +96,9 @@ impl IntoVariant for Uuid {
 }
 
 impl IntoVariant for DateTimeUtc {
+    /// Convert a chrono date time to a variant. This does not check the range: times outside
+    /// of the range OPC UA can represent (1601 to 9999) are clamped to the minimum or maximum
+    /// date time when the variant is encoded.
     fn into_variant(self) -> Variant {
         Variant::DateTime(Box::new(self.into()))
     }
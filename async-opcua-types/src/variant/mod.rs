@@ -17,7 +17,7 @@ mod xml_element;
 
 pub use xml_element::XmlElement;
 
-pub use from::TryFromVariant;
+pub use from::{FromVariants, TryFromVariant};
 pub use into::IntoVariant;
 pub use type_id::*;
 
@@ -45,7 +45,8 @@ use crate::{
     qualified_name::QualifiedName,
     status_code::StatusCode,
     string::UAString,
-    write_i32, write_u8, DataTypeId, DataValue, DiagnosticInfo, DynEncodable, Error, UaNullable,
+    write_i32, write_u8, DataTypeId, DataValue, DiagnosticInfo, DynEncodable, EnumDefinition,
+    EnumValue, Error, UaNullable,
 };
 /// A `Variant` holds built-in OPC UA data types, including single and multi dimensional arrays,
 /// data values and extension objects.
@@ -1350,6 +1351,22 @@ impl Variant {
         }
     }
 
+    /// Get the value of an enumeration, which is always encoded as an `Int32`.
+    /// Returns None if the variant is not an `Int32`.
+    pub fn as_enum_i32(&self) -> Option<i32> {
+        match self {
+            Variant::Int32(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get the value of an enumeration paired with the name of its field in
+    /// `definition`, for example to display enumerations only known at runtime.
+    /// Returns None if the variant is not an `Int32`.
+    pub fn as_enum<'a>(&self, definition: &'a EnumDefinition) -> Option<EnumValue<'a>> {
+        self.as_enum_i32().map(|v| definition.value(v))
+    }
+
     /// Returns the scalar data type. Returns None if the variant is Empty.
     pub fn data_type(&self) -> Option<ExpandedNodeId> {
         match self {
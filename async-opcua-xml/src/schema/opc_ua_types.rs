@@ -114,6 +114,17 @@ pub enum Variant {
     ListOfStatusCode(Vec<StatusCode>),
 }
 
+impl Variant {
+    /// Load the value wrapped in a `Variant` element.
+    fn load_wrapped(node: &Node<'_, '_>) -> Result<Self, XmlError> {
+        let inner = node
+            .children()
+            .find(|n| !n.tag_name().name().is_empty())
+            .ok_or_else(|| XmlError::other(node, "Empty Variant, expected value"))?;
+        Variant::load(&inner)
+    }
+}
+
 impl<'input> XmlLoad<'input> for Variant {
     fn load(node: &Node<'_, 'input>) -> Result<Self, XmlError> {
         Ok(match node.tag_name().name() {
@@ -174,8 +185,13 @@ impl<'input> XmlLoad<'input> for Variant {
             "ListOfExtensionObject" => {
                 Variant::ListOfExtensionObject(children_with_name(node, "ExtensionObject")?)
             }
-            "Variant" => Variant::Variant(Box::new(XmlLoad::load(node)?)),
-            "ListOfVariant" => Variant::ListOfVariant(children_with_name(node, "Variant")?),
+            "Variant" => Variant::Variant(Box::new(Variant::load_wrapped(node)?)),
+            "ListOfVariant" => Variant::ListOfVariant(
+                node.children()
+                    .filter(|n| n.tag_name().name() == "Variant")
+                    .map(|n| Variant::load_wrapped(&n))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
             "StatusCode" => Variant::StatusCode(XmlLoad::load(node)?),
             "ListOfStatusCode" => {
                 Variant::ListOfStatusCode(children_with_name(node, "StatusCode")?)
@@ -1,7 +1,20 @@
-use super::utils::setup;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use super::utils::{client_user_token, setup, test_server, TestNodeManager, Tester};
 use opcua::{
     nodes::TypeTree,
-    server::address_space::{ObjectBuilder, ReferenceDirection, VariableBuilder},
+    server::{
+        address_space::{ObjectBuilder, ReferenceDirection, VariableBuilder},
+        node_manager::{NodeVisibilityFilter, RequestContext},
+        ContinuationPoint, ContinuationPointKind, ContinuationPointStore,
+        ContinuationPointStoreFactory, InMemoryContinuationPointStore, ServerInfo,
+    },
     types::{
         BrowseDescription, BrowseDirection, BrowsePath, BrowseResultMask, ByteString, DataTypeId,
         NodeClass, NodeClassMask, NodeId, ObjectId, ObjectTypeId, ReferenceTypeId, RelativePath,
@@ -9,8 +22,12 @@ use opcua::{
     },
 };
 use opcua_client::browser::BrowseFilter;
+use opcua_crypto::SecurityPolicy;
 use opcua_nodes::DefaultTypeTree;
-use opcua_types::{AttributeId, ReadValueId, TimestampsToReturn, VariableId, Variant};
+use opcua_types::{
+    AttributeId, Identifier, MessageSecurityMode, ReadValueId, TimestampsToReturn, VariableId,
+    Variant,
+};
 
 fn hierarchical_desc(node_id: NodeId) -> BrowseDescription {
     BrowseDescription {
@@ -40,7 +57,7 @@ async fn browse() {
     let refs = it.references.clone().unwrap_or_default();
     // Exact number may vary with new versions of the standard. This number may need to be changed
     // in the future. Keep the test as a sanity check.
-    assert_eq!(refs.len(), 24);
+    assert_eq!(refs.len(), 26);
 
     let server_cap_node = refs
         .iter()
@@ -303,6 +320,94 @@ async fn browse_continuation_point() {
     assert_eq!(1000, results.len());
 }
 
+#[tokio::test]
+async fn browse_continuation_point_concurrent_modification() {
+    let (tester, nm, session) = setup().await;
+    let root_id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        ObjectBuilder::new(&root_id, "TestObj1", "TestObj1")
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&ObjectTypeId::FolderType.into()),
+        Vec::new(),
+    );
+    let add_var = |i: usize| {
+        let id = nm.inner().next_node_id();
+        nm.inner().add_node(
+            nm.address_space(),
+            tester.handle.type_tree(),
+            VariableBuilder::new(&id, format!("Var{i}"), format!("Var{i}"))
+                .data_type(DataTypeId::Int32)
+                .build()
+                .into(),
+            &root_id,
+            &ReferenceTypeId::HasComponent.into(),
+            Some(&VariableTypeId::BaseDataVariableType.into()),
+            Vec::new(),
+        );
+        id
+    };
+    let mut ids: Vec<_> = (0..1000).map(add_var).collect();
+
+    // Browse everything at once to get the expected order.
+    let desc = hierarchical_desc(root_id.clone());
+    let r = session
+        .browse(std::slice::from_ref(&desc), 0, None)
+        .await
+        .unwrap();
+    let expected: Vec<_> = r[0]
+        .references
+        .clone()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.node_id.node_id)
+        .collect();
+    assert_eq!(1000, expected.len());
+
+    let r = session.browse(&[desc], 100, None).await.unwrap();
+    let mut results: Vec<_> = r[0]
+        .references
+        .clone()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.node_id.node_id)
+        .collect();
+    let mut cp = r[0].continuation_point.clone();
+    let mut i = 1000;
+    while !cp.is_null() {
+        // Add and remove references between each call to BrowseNext.
+        for _ in 0..10 {
+            ids.push(add_var(i));
+            i += 1;
+        }
+        let removed = ids.remove(0);
+        nm.address_space().write().delete_reference(
+            &root_id,
+            &removed,
+            ReferenceTypeId::HasComponent,
+        );
+
+        let r = session.browse_next(false, &[cp]).await.unwrap();
+        assert_eq!(StatusCode::Good, r[0].status_code);
+        results.extend(
+            r[0].references
+                .clone()
+                .into_iter()
+                .flatten()
+                .map(|r| r.node_id.node_id),
+        );
+        cp = r[0].continuation_point.clone();
+    }
+
+    // The paginated result is a snapshot of the references when browsing started,
+    // in the same order.
+    assert_eq!(expected, results);
+}
+
 #[tokio::test]
 async fn browse_release_continuation_point() {
     let (tester, nm, session) = setup().await;
@@ -349,6 +454,365 @@ async fn browse_release_continuation_point() {
     assert!(refs.is_empty());
 }
 
+/// Continuation point store that keeps track of the number of stored browse continuation points.
+struct CountingContinuationPointStore {
+    inner: InMemoryContinuationPointStore,
+    browse_count: Arc<AtomicUsize>,
+}
+
+impl ContinuationPointStore for CountingContinuationPointStore {
+    fn add(
+        &mut self,
+        kind: ContinuationPointKind,
+        id: ByteString,
+        point: ContinuationPoint,
+    ) -> Result<(), StatusCode> {
+        self.inner.add(kind, id, point)?;
+        self.browse_count.store(
+            self.inner.len(ContinuationPointKind::Browse),
+            Ordering::Relaxed,
+        );
+        Ok(())
+    }
+
+    fn remove(
+        &mut self,
+        kind: ContinuationPointKind,
+        id: &ByteString,
+    ) -> Option<ContinuationPoint> {
+        let point = self.inner.remove(kind, id);
+        self.browse_count.store(
+            self.inner.len(ContinuationPointKind::Browse),
+            Ordering::Relaxed,
+        );
+        point
+    }
+
+    fn len(&self, kind: ContinuationPointKind) -> usize {
+        self.inner.len(kind)
+    }
+
+    fn clear(&mut self) {
+        self.inner.clear();
+        self.browse_count.store(0, Ordering::Relaxed);
+    }
+}
+
+struct CountingContinuationPointStoreFactory {
+    browse_count: Arc<AtomicUsize>,
+}
+
+impl ContinuationPointStoreFactory for CountingContinuationPointStoreFactory {
+    fn create(&self, _info: &ServerInfo, _session_id: &NodeId) -> Box<dyn ContinuationPointStore> {
+        Box::new(CountingContinuationPointStore {
+            inner: InMemoryContinuationPointStore::new(1, 0, 0),
+            browse_count: self.browse_count.clone(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn browse_custom_continuation_point_store() {
+    let browse_count = Arc::new(AtomicUsize::new(0));
+    let server = test_server().with_continuation_point_store(Arc::new(
+        CountingContinuationPointStoreFactory {
+            browse_count: browse_count.clone(),
+        },
+    ));
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<TestNodeManager>()
+        .unwrap();
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let mut roots = Vec::new();
+    for i in 0..2 {
+        let root_id = nm.inner().next_node_id();
+        nm.inner().add_node(
+            nm.address_space(),
+            tester.handle.type_tree(),
+            ObjectBuilder::new(&root_id, format!("TestObj{i}"), format!("TestObj{i}"))
+                .build()
+                .into(),
+            &ObjectId::ObjectsFolder.into(),
+            &ReferenceTypeId::Organizes.into(),
+            Some(&ObjectTypeId::FolderType.into()),
+            Vec::new(),
+        );
+        for j in 0..10 {
+            let id = nm.inner().next_node_id();
+            nm.inner().add_node(
+                nm.address_space(),
+                tester.handle.type_tree(),
+                VariableBuilder::new(&id, format!("Var{j}"), format!("Var{j}"))
+                    .data_type(DataTypeId::Int32)
+                    .build()
+                    .into(),
+                &root_id,
+                &ReferenceTypeId::HasComponent.into(),
+                Some(&VariableTypeId::BaseDataVariableType.into()),
+                Vec::new(),
+            );
+        }
+        roots.push(hierarchical_desc(root_id));
+    }
+
+    // The store only has room for a single browse continuation point.
+    let r = session.browse(&roots, 5, None).await.unwrap();
+    assert_eq!(2, r.len());
+    assert_eq!(StatusCode::Good, r[0].status_code);
+    assert!(!r[0].continuation_point.is_null());
+    assert_eq!(StatusCode::BadNoContinuationPoints, r[1].status_code);
+    assert!(r[1].continuation_point.is_null());
+    assert_eq!(1, browse_count.load(Ordering::Relaxed));
+
+    // Releasing the continuation point removes it from the store.
+    let cp = r[0].continuation_point.clone();
+    let r = session
+        .browse_next(true, std::slice::from_ref(&cp))
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::Good, r[0].status_code);
+    assert_eq!(0, browse_count.load(Ordering::Relaxed));
+
+    // The released continuation point is no longer valid.
+    let r = session
+        .browse_next(false, std::slice::from_ref(&cp))
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::BadContinuationPointInvalid, r[0].status_code);
+}
+
+/// Hides nodes with a string identifier starting with "Hidden", and properties,
+/// from anonymous users.
+struct HideFromAnonymous;
+
+impl NodeVisibilityFilter for HideFromAnonymous {
+    fn is_visible(&self, context: &RequestContext, node_id: &NodeId) -> bool {
+        !context.token.is_anonymous()
+            || !matches!(&node_id.identifier, Identifier::String(s) if s.as_ref().starts_with("Hidden"))
+    }
+
+    fn is_reference_type_visible(
+        &self,
+        context: &RequestContext,
+        reference_type_id: &NodeId,
+    ) -> bool {
+        !context.token.is_anonymous() || reference_type_id != &ReferenceTypeId::HasProperty
+    }
+}
+
+#[tokio::test]
+async fn browse_node_visibility_filter() {
+    let server = test_server().with_node_visibility_filter(Arc::new(HideFromAnonymous));
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<TestNodeManager>()
+        .unwrap();
+
+    let root_id = nm.inner().next_node_id();
+    let ns = root_id.namespace;
+    let hidden_id = NodeId::new(ns, "HiddenVar");
+    let visible_id = NodeId::new(ns, "VisibleVar");
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        ObjectBuilder::new(&root_id, "TestObj", "TestObj")
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&ObjectTypeId::FolderType.into()),
+        Vec::new(),
+    );
+    for id in [&hidden_id, &visible_id] {
+        nm.inner().add_node(
+            nm.address_space(),
+            tester.handle.type_tree(),
+            VariableBuilder::new(id, "Var", "Var")
+                .data_type(DataTypeId::Int32)
+                .value(1)
+                .build()
+                .into(),
+            &root_id,
+            &ReferenceTypeId::HasComponent.into(),
+            Some(&VariableTypeId::BaseDataVariableType.into()),
+            Vec::new(),
+        );
+    }
+
+    let (anonymous, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    let (user, lp) = tester
+        .connect(
+            SecurityPolicy::Aes128Sha256RsaOaep,
+            MessageSecurityMode::SignAndEncrypt,
+            client_user_token(),
+        )
+        .await
+        .unwrap();
+    lp.spawn();
+    for session in [&anonymous, &user] {
+        tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+            .await
+            .unwrap();
+    }
+
+    let nodes_to_browse = [
+        hierarchical_desc(root_id.clone()),
+        hierarchical_desc(hidden_id.clone()),
+    ];
+    let nodes_to_read = [
+        ReadValueId::new_value(visible_id.clone()),
+        ReadValueId::new_value(hidden_id.clone()),
+    ];
+
+    // Hidden nodes appear not to exist to anonymous users.
+    let r = anonymous
+        .browse(&nodes_to_browse, 1000, None)
+        .await
+        .unwrap();
+    let refs = r[0].references.clone().unwrap_or_default();
+    assert_eq!(refs.len(), 1);
+    assert_eq!(refs[0].node_id.node_id, visible_id);
+    assert_eq!(r[1].status_code, StatusCode::BadNodeIdUnknown);
+
+    let r = anonymous
+        .read(&nodes_to_read, TimestampsToReturn::Neither, 0.0)
+        .await
+        .unwrap();
+    assert_eq!(r[0].value, Some(Variant::Int32(1)));
+    assert_eq!(r[1].status, Some(StatusCode::BadNodeIdUnknown));
+
+    // Other users see them as usual.
+    let r = user.browse(&nodes_to_browse, 1000, None).await.unwrap();
+    assert_eq!(r[0].references.as_ref().unwrap().len(), 2);
+    assert_eq!(r[1].status_code, StatusCode::Good);
+
+    let r = user
+        .read(&nodes_to_read, TimestampsToReturn::Neither, 0.0)
+        .await
+        .unwrap();
+    assert_eq!(r[0].value, Some(Variant::Int32(1)));
+    assert_eq!(r[1].value, Some(Variant::Int32(1)));
+}
+
+#[tokio::test]
+async fn browse_hidden_children() {
+    let server = test_server().with_node_visibility_filter(Arc::new(HideFromAnonymous));
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<TestNodeManager>()
+        .unwrap();
+
+    let root_id = nm.inner().next_node_id();
+    let ns = root_id.namespace;
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        ObjectBuilder::new(&root_id, "TestObj", "TestObj")
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&ObjectTypeId::FolderType.into()),
+        Vec::new(),
+    );
+    // Interleave hidden and visible children, and add a property, which is hidden
+    // because of its reference type.
+    let mut children = Vec::new();
+    for i in 0..3 {
+        children.push((
+            NodeId::new(ns, format!("Hidden{i}")),
+            ReferenceTypeId::HasComponent,
+        ));
+        children.push((
+            NodeId::new(ns, format!("Visible{i}")),
+            ReferenceTypeId::HasComponent,
+        ));
+    }
+    children.push((NodeId::new(ns, "Property"), ReferenceTypeId::HasProperty));
+    for (id, reference_type) in &children {
+        nm.inner().add_node(
+            nm.address_space(),
+            tester.handle.type_tree(),
+            VariableBuilder::new(id, "Var", "Var")
+                .data_type(DataTypeId::Int32)
+                .build()
+                .into(),
+            &root_id,
+            &(*reference_type).into(),
+            Some(&VariableTypeId::BaseDataVariableType.into()),
+            Vec::new(),
+        );
+    }
+
+    let (anonymous, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    let (user, lp) = tester
+        .connect(
+            SecurityPolicy::Aes128Sha256RsaOaep,
+            MessageSecurityMode::SignAndEncrypt,
+            client_user_token(),
+        )
+        .await
+        .unwrap();
+    lp.spawn();
+    for session in [&anonymous, &user] {
+        tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+            .await
+            .unwrap();
+    }
+
+    let browse_all = |session: Arc<opcua::client::Session>| {
+        let root_id = root_id.clone();
+        async move {
+            let r = session
+                .browse(&[hierarchical_desc(root_id)], 2, None)
+                .await
+                .unwrap();
+            let mut pages = vec![r[0].references.clone().unwrap_or_default()];
+            let mut cp = r[0].continuation_point.clone();
+            while !cp.is_null() {
+                let r = session.browse_next(false, &[cp]).await.unwrap();
+                assert_eq!(r[0].status_code, StatusCode::Good);
+                pages.push(r[0].references.clone().unwrap_or_default());
+                cp = r[0].continuation_point.clone();
+            }
+            pages
+        }
+    };
+
+    // The anonymous user only sees the visible children, and pages are filled
+    // with visible references only.
+    let pages = browse_all(anonymous.clone()).await;
+    let mut refs: Vec<_> = pages
+        .iter()
+        .flatten()
+        .map(|r| r.node_id.node_id.clone())
+        .collect();
+    refs.sort_by_key(|r| r.to_string());
+    let expected: Vec<_> = (0..3)
+        .map(|i| NodeId::new(ns, format!("Visible{i}")))
+        .collect();
+    assert_eq!(refs, expected);
+    assert_eq!(pages[0].len(), 2);
+
+    // The other user sees everything.
+    let pages = browse_all(user.clone()).await;
+    assert_eq!(pages.iter().flatten().count(), children.len());
+}
+
 #[tokio::test]
 async fn browse_limits() {
     let (tester, _nm, session) = setup().await;
@@ -384,6 +848,42 @@ async fn browse_limits() {
     assert_eq!(r, StatusCode::BadTooManyOperations);
 }
 
+#[tokio::test]
+async fn browse_chunked() {
+    let mut server = test_server();
+    server.limits_mut().operational.max_nodes_per_browse = 5;
+    let mut tester = Tester::new(server, false).await;
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    // Alternate between a node that exists and one that does not, so that we can check the order.
+    let ops: Vec<_> = (0..23)
+        .map(|i| {
+            if i % 2 == 0 {
+                hierarchical_desc(ObjectId::Server.into())
+            } else {
+                hierarchical_desc(NodeId::new(2, i))
+            }
+        })
+        .collect();
+
+    let r = session.browse(&ops, 1000, None).await.unwrap_err();
+    assert_eq!(r, StatusCode::BadTooManyOperations);
+
+    let r = session.browse_chunked(&ops, 1000, None).await.unwrap();
+    assert_eq!(r.len(), 23);
+    for (i, res) in r.iter().enumerate() {
+        if i % 2 == 0 {
+            assert!(!res.references.as_ref().unwrap().is_empty());
+        } else {
+            assert!(res.references.as_ref().is_none_or(|r| r.is_empty()));
+        }
+    }
+}
+
 #[tokio::test]
 async fn translate_browse_path() {
     let (tester, nm, session) = setup().await;
@@ -522,6 +1022,65 @@ async fn translate_browse_paths_limits() {
     assert_eq!(r, StatusCode::BadTooManyOperations);
 }
 
+#[tokio::test]
+async fn register_nodes_handle() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .data_type(DataTypeId::Int32)
+            .value(5)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let handle = session
+        .register_nodes_handle(std::slice::from_ref(&id))
+        .await
+        .unwrap();
+    assert_eq!(handle.node_ids().len(), 1);
+
+    // The registered ID can be used in place of the original.
+    let r = session
+        .read(
+            &[ReadValueId {
+                node_id: handle.get(0).unwrap().clone(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            }],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].value, Some(Variant::Int32(5)));
+
+    // Explicitly unregistering waits for the server.
+    handle.unregister().await.unwrap();
+    assert_eq!(nm.inner().unregistered_nodes(), vec![id.clone()]);
+
+    // Dropping the handle unregisters the nodes in the background.
+    let handle = session
+        .register_nodes_handle(std::slice::from_ref(&id))
+        .await
+        .unwrap();
+    drop(handle);
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while nm.inner().unregistered_nodes().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
 #[tokio::test]
 async fn translate_browse_paths_auto_impl() {
     let (_tester, _nm, session) = setup().await;
@@ -1,4 +1,5 @@
 use std::{
+    path::PathBuf,
     sync::{atomic::Ordering, Arc},
     time::Duration,
 };
@@ -9,29 +10,42 @@ use bytes::BytesMut;
 use log::debug;
 use opcua::{
     client::IdentityToken,
-    core::comms::tcp_codec::{Message, TcpCodec},
+    core::comms::{
+        chunker::Chunker,
+        message_chunk::{MessageChunk, MessageChunkType, MessageIsFinalType},
+        secure_channel::{Role, SecureChannel},
+        sequence_number::SequenceNumberHandle,
+        tcp_codec::{Message, TcpCodec},
+        tcp_types::{HelloMessage, MIN_CHUNK_SIZE},
+    },
     core::config::Config,
-    crypto::SecurityPolicy,
+    core::{sync::RwLock, RequestMessage, ResponseMessage},
+    crypto::{CertificateStore, SecurityPolicy},
     types::{
-        ApplicationType, DecodingOptions, MessageSecurityMode, NodeId, ReadValueId, StatusCode,
+        ActivateSessionRequest, AnonymousIdentityToken, ApplicationType, CloseSessionRequest,
+        CreateSessionRequest, DecodingOptions, ExtensionObject, GetEndpointsRequest,
+        MessageSecurityMode, NodeId, OpenSecureChannelRequest, ReadRequest, ReadValueId,
+        RequestHeader, SecurityTokenRequestType, SimpleBinaryEncodable, StatusCode,
         TimestampsToReturn, VariableId, Variant,
     },
 };
 use opcua_client::IssuedTokenWrapper;
 use opcua_server::{
     authenticator::{issued_token_security_policy, AuthManager, UserToken},
-    ServerEndpoint,
+    DiagnosticsConfig, EndpointHostSubstitution, ServerEndpoint, ServiceFaultDiagnostic,
+};
+use opcua_types::{
+    ByteString, DiagnosticBits, DiagnosticInfo, Error, UAString, UserTokenPolicy, UserTokenType,
 };
-use opcua_types::{ByteString, Error, UAString, UserTokenPolicy, UserTokenType};
 use tokio::{
-    io::AsyncReadExt,
+    io::{AsyncReadExt, AsyncWriteExt},
     net::{TcpListener, TcpStream},
 };
-use tokio_util::codec::Decoder;
+use tokio_util::{codec::Decoder, sync::CancellationToken};
 
 use crate::utils::{
-    client_user_token, client_x509_token, copy_shared_certs, default_server, test_server, Tester,
-    CLIENT_USERPASS_ID, TEST_COUNTER,
+    client_user_token, client_x509_token, copy_shared_certs, default_client, default_server,
+    test_server, Tester, CLIENT_USERPASS_ID, TEST_COUNTER,
 };
 
 #[tokio::test]
@@ -108,6 +122,131 @@ async fn get_endpoints() {
     assert_eq!(endpoints.len(), tester.handle.info().config.endpoints.len());
 }
 
+#[tokio::test]
+async fn get_endpoints_host_substitution() {
+    let tester = Tester::new(
+        test_server().endpoint_host_substitution(EndpointHostSubstitution::Static(
+            "public.example.com".to_owned(),
+        )),
+        false,
+    )
+    .await;
+    let endpoints = tester
+        .client
+        .get_server_endpoints_from_url(tester.endpoint())
+        .await
+        .unwrap();
+    assert_eq!(endpoints.len(), tester.handle.info().config.endpoints.len());
+    let expected = format!("opc.tcp://public.example.com:{}/", tester.addr.port());
+    for endpoint in &endpoints {
+        assert_eq!(endpoint.endpoint_url.as_ref(), expected);
+    }
+
+    let tester = Tester::new(
+        test_server().endpoint_host_substitution(EndpointHostSubstitution::List(vec![
+            "host1".to_owned(),
+            "host2".to_owned(),
+        ])),
+        false,
+    )
+    .await;
+    let endpoints = tester
+        .client
+        .get_server_endpoints_from_url(tester.endpoint())
+        .await
+        .unwrap();
+    assert_eq!(
+        endpoints.len(),
+        tester.handle.info().config.endpoints.len() * 2
+    );
+    assert!(endpoints
+        .iter()
+        .any(|e| e.endpoint_url.as_ref().starts_with("opc.tcp://host1:")));
+    assert!(endpoints
+        .iter()
+        .any(|e| e.endpoint_url.as_ref().starts_with("opc.tcp://host2:")));
+}
+
+#[tokio::test]
+async fn create_sample_keypair_host_names() {
+    let pki_dir = PathBuf::from("pki-server/sample-keypair");
+    let _ = std::fs::remove_dir_all(&pki_dir);
+
+    let build = || {
+        test_server()
+            .pki_dir(&pki_dir)
+            .host("127.0.0.1")
+            .discovery_urls(vec!["opc.tcp://127.0.0.1:4855/".to_owned()])
+            .certificate_host_names(vec!["opcua.example.com".to_owned(), "10.1.2.3".to_owned()])
+            .build()
+            .unwrap()
+    };
+
+    // The certificate is created on first start, with the configured host names.
+    let _ = build();
+    let cert_path = pki_dir.join("own/cert.der");
+    let cert = CertificateStore::read_cert(&cert_path).unwrap();
+    cert.is_application_uri_valid("urn:integration_server")
+        .unwrap();
+    cert.is_hostname_valid("127.0.0.1").unwrap();
+    cert.is_hostname_valid("opcua.example.com").unwrap();
+    cert.is_hostname_valid("10.1.2.3").unwrap();
+    assert!(cert.is_hostname_valid("other.example.com").is_err());
+
+    // On later starts, the existing certificate is used.
+    let _ = build();
+    let reloaded = CertificateStore::read_cert(&cert_path).unwrap();
+    assert_eq!(reloaded.as_byte_string(), cert.as_byte_string());
+
+    let _ = std::fs::remove_dir_all(&pki_dir);
+}
+
+#[tokio::test]
+async fn endpoint_host_substitution_echo_client_host() {
+    let tester = Tester::new(
+        test_server().endpoint_host_substitution(EndpointHostSubstitution::EchoClientHost),
+        false,
+    )
+    .await;
+    // Connect using the IP address, instead of the host name the server is configured with.
+    let url = format!("opc.tcp://{}/", tester.addr);
+    assert_ne!(url, tester.endpoint());
+
+    let endpoints = tester
+        .client
+        .get_server_endpoints_from_url(url.as_str())
+        .await
+        .unwrap();
+    assert_eq!(endpoints.len(), tester.handle.info().config.endpoints.len());
+    for endpoint in &endpoints {
+        assert!(
+            endpoint.endpoint_url.as_ref().starts_with(&url),
+            "{}",
+            endpoint.endpoint_url
+        );
+    }
+
+    // The server endpoints returned from CreateSession use the same host.
+    let (mut stream, mut channel) = raw_open_secure_channel_url(&tester, &url).await;
+    let mut request = create_session_request(&tester, "echo");
+    request.endpoint_url = url.as_str().into();
+    let ResponseMessage::CreateSession(response) =
+        raw_request(&mut stream, &mut channel, &mut 2, 2, request).await
+    else {
+        panic!("Expected create session response");
+    };
+    assert!(response.response_header.service_result.is_good());
+    let server_endpoints = response.server_endpoints.unwrap();
+    assert_eq!(server_endpoints.len(), endpoints.len());
+    for endpoint in &server_endpoints {
+        assert!(
+            endpoint.endpoint_url.as_ref().starts_with(&url),
+            "{}",
+            endpoint.endpoint_url
+        );
+    }
+}
+
 async fn conn_test(policy: SecurityPolicy, mode: MessageSecurityMode, token: IdentityToken) {
     let mut tester = Tester::new_default_server(false).await;
     let (session, handle) = tester.connect(policy, mode, token).await.unwrap();
@@ -254,6 +393,36 @@ async fn connect_basic128rsa_15_with_invalid_token() {
     assert_eq!(res, StatusCode::BadIdentityTokenRejected);
 }
 
+#[tokio::test]
+async fn connect_with_mismatched_application_uri() {
+    // The client certificate is issued for a different application URI.
+    let client = default_client(0, true).application_uri("urn:other_client");
+    let mut tester = Tester::new_custom_client(test_server(), client).await;
+    let (_, handle) = tester
+        .connect(
+            SecurityPolicy::Basic256Sha256,
+            MessageSecurityMode::SignAndEncrypt,
+            IdentityToken::Anonymous,
+        )
+        .await
+        .unwrap();
+    let res = handle.spawn().await.unwrap();
+    assert_eq!(res, StatusCode::BadCertificateUriInvalid);
+
+    // In warn-only mode, the session is activated anyway.
+    let client = default_client(0, true).application_uri("urn:other_client");
+    let server = test_server().warn_on_application_uri_mismatch(true);
+    let mut tester = Tester::new_custom_client(server, client).await;
+    tester
+        .connect_and_wait(
+            SecurityPolicy::Basic256Sha256,
+            MessageSecurityMode::SignAndEncrypt,
+            IdentityToken::Anonymous,
+        )
+        .await
+        .unwrap();
+}
+
 #[tokio::test]
 async fn find_servers() {
     let tester = Tester::new_default_server(true).await;
@@ -408,6 +577,65 @@ async fn recoverable_error_test_server() {
         .unwrap();
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn cancel_large_request() {
+    // Test that cancelling a large request while it is being sent aborts it,
+    // without breaking the connection.
+    let server = test_server()
+        .max_array_length(100_000)
+        .max_message_size(1024 * 1024 * 64)
+        .max_chunk_count(64);
+    let mut tester = Tester::new(server, false).await;
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let request = ReadRequest {
+        request_header: RequestHeader {
+            request_handle: session.channel().request_handle(),
+            ..Default::default()
+        },
+        max_age: 0.0,
+        timestamps_to_return: TimestampsToReturn::Both,
+        nodes_to_read: Some(
+            (0..100_000)
+                .map(|_| {
+                    ReadValueId::from(<VariableId as Into<NodeId>>::into(
+                        VariableId::Server_ServiceLevel,
+                    ))
+                })
+                .collect(),
+        ),
+    };
+
+    let token = CancellationToken::new();
+    let token_ref = token.clone();
+    tokio::task::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        token_ref.cancel();
+    });
+    let res = session
+        .channel()
+        .send_cancellable(request, Duration::from_secs(5), token)
+        .await
+        .unwrap_err();
+    assert_eq!(res, StatusCode::BadRequestCancelledByClient);
+
+    // The channel is still usable.
+    session
+        .read(
+            &[ReadValueId::from(<VariableId as Into<NodeId>>::into(
+                VariableId::Server_ServiceLevel,
+            ))],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+}
+
 struct IssuedTokenAuthenticator;
 
 #[async_trait]
@@ -486,3 +714,730 @@ async fn issued_token_test() {
         .await
         .unwrap();
 }
+
+/// Raw TCP stream, with a buffer for partially received messages.
+struct RawStream {
+    stream: TcpStream,
+    buf: BytesMut,
+}
+
+/// Connect to the server with a raw TCP stream, sending a HELLO message with the given limits.
+async fn raw_connect(
+    tester: &Tester,
+    max_message_size: usize,
+    max_chunk_count: usize,
+) -> RawStream {
+    raw_connect_url(
+        tester,
+        &tester.endpoint(),
+        max_message_size,
+        max_chunk_count,
+    )
+    .await
+}
+
+/// Connect to the server, sending `endpoint_url` in the `HELLO` message.
+async fn raw_connect_url(
+    tester: &Tester,
+    endpoint_url: &str,
+    max_message_size: usize,
+    max_chunk_count: usize,
+) -> RawStream {
+    let mut stream = RawStream {
+        stream: TcpStream::connect(tester.addr).await.unwrap(),
+        buf: BytesMut::with_capacity(1024),
+    };
+    let hello = HelloMessage::new(
+        endpoint_url,
+        MIN_CHUNK_SIZE,
+        MIN_CHUNK_SIZE,
+        max_message_size,
+        max_chunk_count,
+    );
+    let mut buf = Vec::new();
+    SimpleBinaryEncodable::encode(&hello, &mut buf).unwrap();
+    stream.stream.write_all(&buf).await.unwrap();
+
+    let msg = read_raw_message(&mut stream).await;
+    let Message::Acknowledge(_) = msg else {
+        panic!("Expected acknowledge, got {msg:?}");
+    };
+    stream
+}
+
+async fn read_raw_message(stream: &mut RawStream) -> Message {
+    let mut codec = TcpCodec::new(DecodingOptions::default());
+    loop {
+        if let Some(msg) = codec.decode(&mut stream.buf).unwrap() {
+            return msg;
+        }
+        let read = tokio::time::timeout(
+            Duration::from_secs(2),
+            stream.stream.read_buf(&mut stream.buf),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(read > 0, "Stream closed before a message was received");
+    }
+}
+
+/// Create an unsecured client secure channel for use with a raw TCP stream.
+fn raw_client_channel(tester: &Tester) -> SecureChannel {
+    let mut channel = SecureChannel::new(
+        Arc::new(RwLock::new(CertificateStore::new(&PathBuf::from(format!(
+            "./pki-client/{}",
+            tester.test_id
+        ))))),
+        Role::Client,
+        Default::default(),
+    );
+    channel.set_security_policy(SecurityPolicy::None);
+    channel.set_security_mode(MessageSecurityMode::None);
+    channel
+}
+
+/// Encode a message into chunks of the minimum chunk size, starting at `sequence_number`.
+fn encode_raw(
+    channel: &SecureChannel,
+    sequence_number: u32,
+    request_id: u32,
+    message: impl Into<RequestMessage>,
+) -> Vec<MessageChunk> {
+    let mut sequence_numbers = SequenceNumberHandle::new(true);
+    sequence_numbers.set(sequence_number);
+    Chunker::encode(
+        sequence_numbers,
+        request_id,
+        0,
+        MIN_CHUNK_SIZE,
+        channel,
+        &message.into(),
+    )
+    .unwrap()
+}
+
+fn open_secure_channel_request(size: usize) -> OpenSecureChannelRequest {
+    OpenSecureChannelRequest {
+        request_header: RequestHeader {
+            audit_entry_id: "a".repeat(size).into(),
+            ..Default::default()
+        },
+        client_protocol_version: 0,
+        request_type: SecurityTokenRequestType::Issue,
+        security_mode: MessageSecurityMode::None,
+        client_nonce: ByteString::null(),
+        requested_lifetime: 60_000,
+    }
+}
+
+/// Encode an OpenSecureChannel request padded to roughly `size` bytes,
+/// split into chunks of the minimum chunk size.
+fn large_open_secure_channel_chunks(tester: &Tester, size: usize) -> Vec<MessageChunk> {
+    let channel = raw_client_channel(tester);
+    encode_raw(&channel, 1, 1, open_secure_channel_request(size))
+}
+
+async fn expect_request_too_large(stream: &mut RawStream, chunks: &[MessageChunk]) {
+    for chunk in chunks {
+        // The server may close the connection before we are done sending.
+        if stream.stream.write_all(&chunk.data).await.is_err() {
+            break;
+        }
+    }
+    let msg = read_raw_message(stream).await;
+    let Message::Error(msg) = msg else {
+        panic!("Expected error, got {msg:?}");
+    };
+    assert_eq!(msg.error, StatusCode::BadRequestTooLarge);
+}
+
+#[tokio::test]
+async fn receive_max_chunk_count_exceeded() {
+    let tester = Tester::new_default_server(false).await;
+    let mut stream = raw_connect(&tester, 0, 2).await;
+    let chunks = large_open_secure_channel_chunks(&tester, 5 * MIN_CHUNK_SIZE);
+    assert!(chunks.len() > 2);
+    expect_request_too_large(&mut stream, &chunks).await;
+}
+
+#[tokio::test]
+async fn receive_max_message_size_exceeded() {
+    let tester = Tester::new_default_server(false).await;
+    let mut stream = raw_connect(&tester, 2 * MIN_CHUNK_SIZE, 0).await;
+    let chunks = large_open_secure_channel_chunks(&tester, 5 * MIN_CHUNK_SIZE);
+    expect_request_too_large(&mut stream, &chunks).await;
+}
+
+#[tokio::test]
+async fn max_secure_channels_per_ip() {
+    let tester = Tester::new(default_server().max_secure_channels_per_ip(2), false).await;
+    let first = raw_connect(&tester, 0, 0).await;
+    let _second = raw_connect(&tester, 0, 0).await;
+
+    // The third channel from the same address is refused, and the socket closed.
+    let mut stream = RawStream {
+        stream: TcpStream::connect(tester.addr).await.unwrap(),
+        buf: BytesMut::with_capacity(1024),
+    };
+    let msg = read_raw_message(&mut stream).await;
+    let Message::Error(msg) = msg else {
+        panic!("Expected error, got {msg:?}");
+    };
+    assert_eq!(msg.error, StatusCode::BadMaxConnectionsReached);
+    let read = tokio::time::timeout(
+        Duration::from_secs(2),
+        stream.stream.read_buf(&mut stream.buf),
+    )
+    .await
+    .unwrap();
+    assert!(read.is_err() || read.unwrap() == 0);
+
+    // Once a channel is closed, a new one can be opened.
+    drop(first);
+    let mut attempts = 0;
+    loop {
+        let mut stream = TcpStream::connect(tester.addr).await.unwrap();
+        let hello = HelloMessage::new(&tester.endpoint(), MIN_CHUNK_SIZE, MIN_CHUNK_SIZE, 0, 0);
+        let mut buf = Vec::new();
+        SimpleBinaryEncodable::encode(&hello, &mut buf).unwrap();
+        stream.write_all(&buf).await.unwrap();
+        let mut stream = RawStream {
+            stream,
+            buf: BytesMut::with_capacity(1024),
+        };
+        match read_raw_message(&mut stream).await {
+            Message::Acknowledge(_) => break,
+            Message::Error(e) if e.error == StatusCode::BadMaxConnectionsReached => {
+                // The server may not have noticed the closed channel yet.
+                attempts += 1;
+                assert!(attempts < 20, "Channel limit was not released");
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            msg => panic!("Expected acknowledge, got {msg:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn max_secure_channels_per_ip_allowlist() {
+    // The tester listens on the machine hostname, so allow any address it resolves to.
+    let allowlist = tokio::net::lookup_host(format!("{}:0", hostname()))
+        .await
+        .unwrap()
+        .map(|a| a.ip())
+        .collect();
+    let server = default_server()
+        .max_secure_channels_per_ip(1)
+        .secure_channel_limit_allowlist(allowlist);
+    let tester = Tester::new(server, false).await;
+    let mut streams = Vec::new();
+    for _ in 0..3 {
+        streams.push(raw_connect(&tester, 0, 0).await);
+    }
+}
+
+async fn send_raw_chunks(stream: &mut RawStream, chunks: &[MessageChunk]) {
+    for chunk in chunks {
+        stream.stream.write_all(&chunk.data).await.unwrap();
+    }
+}
+
+async fn read_raw_response(stream: &mut RawStream, channel: &mut SecureChannel) -> ResponseMessage {
+    let mut chunks = Vec::new();
+    loop {
+        let msg = read_raw_message(stream).await;
+        let Message::Chunk(chunk) = msg else {
+            panic!("Expected chunk, got {msg:?}");
+        };
+        let chunk = channel.verify_and_remove_security(&chunk.data).unwrap();
+        let is_final = chunk
+            .message_header(&channel.decoding_options())
+            .unwrap()
+            .is_final;
+        chunks.push(chunk);
+        if is_final != MessageIsFinalType::Intermediate {
+            break;
+        }
+    }
+    Chunker::decode(&chunks, channel, None).unwrap()
+}
+
+#[tokio::test]
+async fn receive_aborted_message() {
+    let tester = Tester::new_default_server(false).await;
+    let mut stream = raw_connect(&tester, 0, 0).await;
+    let mut channel = raw_client_channel(&tester);
+
+    let chunks = encode_raw(&channel, 1, 1, open_secure_channel_request(0));
+    let mut sequence_number = 1 + chunks.len() as u32;
+    send_raw_chunks(&mut stream, &chunks).await;
+    let ResponseMessage::OpenSecureChannel(response) =
+        read_raw_response(&mut stream, &mut channel).await
+    else {
+        panic!("Expected open secure channel response");
+    };
+    channel.set_security_token(response.security_token);
+
+    let get_endpoints = |request_handle: u32, size: usize| GetEndpointsRequest {
+        request_header: RequestHeader {
+            request_handle,
+            audit_entry_id: "a".repeat(size).into(),
+            ..Default::default()
+        },
+        endpoint_url: tester.endpoint().into(),
+        locale_ids: None,
+        profile_uris: None,
+    };
+
+    // Send the first chunk of a large message, then abort it.
+    let chunks = encode_raw(
+        &channel,
+        sequence_number,
+        2,
+        get_endpoints(2, 3 * MIN_CHUNK_SIZE),
+    );
+    assert!(chunks.len() > 1);
+    send_raw_chunks(&mut stream, &chunks[..1]).await;
+    sequence_number += 1;
+
+    let mut body = Vec::new();
+    SimpleBinaryEncodable::encode(&StatusCode::BadRequestCancelledByClient, &mut body).unwrap();
+    SimpleBinaryEncodable::encode(&UAString::from("Cancelled"), &mut body).unwrap();
+    let abort = MessageChunk::new(
+        sequence_number,
+        2,
+        MessageChunkType::Message,
+        MessageIsFinalType::FinalError,
+        &channel,
+        &body,
+    )
+    .unwrap();
+    send_raw_chunks(&mut stream, &[abort]).await;
+    sequence_number += 1;
+
+    // A new message on the same channel is handled as normal.
+    let chunks = encode_raw(&channel, sequence_number, 3, get_endpoints(3, 0));
+    send_raw_chunks(&mut stream, &chunks).await;
+    let ResponseMessage::GetEndpoints(response) =
+        read_raw_response(&mut stream, &mut channel).await
+    else {
+        panic!("Expected get endpoints response");
+    };
+    assert_eq!(response.response_header.request_handle, 3);
+    assert!(response.response_header.service_result.is_good());
+}
+
+/// Open an unsecured secure channel on a raw TCP stream.
+async fn raw_open_secure_channel(tester: &Tester) -> (RawStream, SecureChannel) {
+    raw_open_secure_channel_url(tester, &tester.endpoint()).await
+}
+
+/// Open an unsecured secure channel on a raw TCP stream, connecting with `endpoint_url`.
+async fn raw_open_secure_channel_url(
+    tester: &Tester,
+    endpoint_url: &str,
+) -> (RawStream, SecureChannel) {
+    let mut stream = raw_connect_url(tester, endpoint_url, 0, 0).await;
+    let mut channel = raw_client_channel(tester);
+    let chunks = encode_raw(&channel, 1, 1, open_secure_channel_request(0));
+    send_raw_chunks(&mut stream, &chunks).await;
+    let ResponseMessage::OpenSecureChannel(response) =
+        read_raw_response(&mut stream, &mut channel).await
+    else {
+        panic!("Expected open secure channel response");
+    };
+    channel.set_security_token(response.security_token);
+    (stream, channel)
+}
+
+#[tokio::test]
+async fn secure_channel_idle_timeout() {
+    let server = default_server().secure_channel_idle_timeout_ms(500);
+    let tester = Tester::new(server, false).await;
+    let (mut stream, _channel) = raw_open_secure_channel(&tester).await;
+
+    // The channel never creates a session, so it is closed after the idle timeout.
+    let msg = read_raw_message(&mut stream).await;
+    let Message::Error(msg) = msg else {
+        panic!("Expected error, got {msg:?}");
+    };
+    assert_eq!(msg.error, StatusCode::BadTimeout);
+}
+
+/// Send a request on a raw secure channel and wait for the response.
+async fn raw_request(
+    stream: &mut RawStream,
+    channel: &mut SecureChannel,
+    sequence_number: &mut u32,
+    request_id: u32,
+    message: impl Into<RequestMessage>,
+) -> ResponseMessage {
+    let chunks = encode_raw(channel, *sequence_number, request_id, message);
+    *sequence_number += chunks.len() as u32;
+    send_raw_chunks(stream, &chunks).await;
+    read_raw_response(stream, channel).await
+}
+
+fn create_session_request(tester: &Tester, name: &str) -> CreateSessionRequest {
+    CreateSessionRequest {
+        endpoint_url: tester.endpoint().into(),
+        session_name: name.into(),
+        requested_session_timeout: 60_000.0,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn secure_channel_idle_timeout_with_session() {
+    let server = default_server().secure_channel_idle_timeout_ms(500);
+    let tester = Tester::new(server, false).await;
+    let (mut stream, mut channel) = raw_open_secure_channel(&tester).await;
+    let mut sequence_number = 2;
+
+    let ResponseMessage::CreateSession(response) = raw_request(
+        &mut stream,
+        &mut channel,
+        &mut sequence_number,
+        2,
+        create_session_request(&tester, "idle"),
+    )
+    .await
+    else {
+        panic!("Expected create session response");
+    };
+    assert!(response.response_header.service_result.is_good());
+
+    // A channel with a session is kept open past the idle timeout.
+    tokio::time::sleep(Duration::from_millis(1200)).await;
+    let request = GetEndpointsRequest {
+        request_header: RequestHeader {
+            request_handle: 3,
+            ..Default::default()
+        },
+        endpoint_url: tester.endpoint().into(),
+        locale_ids: None,
+        profile_uris: None,
+    };
+    let ResponseMessage::GetEndpoints(response) =
+        raw_request(&mut stream, &mut channel, &mut sequence_number, 3, request).await
+    else {
+        panic!("Expected get endpoints response");
+    };
+    assert!(response.response_header.service_result.is_good());
+}
+
+/// Create and activate an anonymous session on a raw secure channel,
+/// returning its authentication token.
+async fn raw_create_session(
+    tester: &Tester,
+    stream: &mut RawStream,
+    channel: &mut SecureChannel,
+    sequence_number: &mut u32,
+    request_id: &mut u32,
+    name: &str,
+) -> NodeId {
+    let ResponseMessage::CreateSession(response) = raw_request(
+        stream,
+        channel,
+        sequence_number,
+        *request_id,
+        create_session_request(tester, name),
+    )
+    .await
+    else {
+        panic!("Expected create session response");
+    };
+    *request_id += 1;
+    let token = response.authentication_token;
+
+    let request = ActivateSessionRequest {
+        request_header: RequestHeader {
+            authentication_token: token.clone(),
+            ..Default::default()
+        },
+        user_identity_token: ExtensionObject::from_message(AnonymousIdentityToken {
+            policy_id: "anonymous".into(),
+        }),
+        ..Default::default()
+    };
+    let ResponseMessage::ActivateSession(response) =
+        raw_request(stream, channel, sequence_number, *request_id, request).await
+    else {
+        panic!("Expected activate session response");
+    };
+    *request_id += 1;
+    assert!(response.response_header.service_result.is_good());
+    token
+}
+
+#[tokio::test]
+async fn multiple_sessions_on_one_channel() {
+    let tester = Tester::new(test_server(), false).await;
+    let (mut stream, mut channel) = raw_open_secure_channel(&tester).await;
+    let mut sequence_number = 2;
+    let mut request_id = 2;
+
+    // Create and activate two sessions on the same channel.
+    let mut tokens = Vec::new();
+    for name in ["first", "second"] {
+        tokens.push(
+            raw_create_session(
+                &tester,
+                &mut stream,
+                &mut channel,
+                &mut sequence_number,
+                &mut request_id,
+                name,
+            )
+            .await,
+        );
+    }
+    assert_ne!(tokens[0], tokens[1]);
+
+    let read = |token: &NodeId, request_handle: u32| ReadRequest {
+        request_header: RequestHeader {
+            authentication_token: token.clone(),
+            request_handle,
+            ..Default::default()
+        },
+        max_age: 0.0,
+        timestamps_to_return: TimestampsToReturn::Both,
+        nodes_to_read: Some(vec![ReadValueId::from(<VariableId as Into<NodeId>>::into(
+            VariableId::Server_ServiceLevel,
+        ))]),
+    };
+
+    // Requests are routed to each session by their authentication token.
+    for (i, token) in tokens.iter().enumerate() {
+        let ResponseMessage::Read(response) = raw_request(
+            &mut stream,
+            &mut channel,
+            &mut sequence_number,
+            request_id,
+            read(token, i as u32 + 10),
+        )
+        .await
+        else {
+            panic!("Expected read response");
+        };
+        request_id += 1;
+        assert!(response.response_header.service_result.is_good());
+        assert_eq!(response.response_header.request_handle, i as u32 + 10);
+        let results = response.results.unwrap();
+        assert_eq!(results[0].value, Some(Variant::Byte(255)));
+    }
+
+    // Closing one session leaves the other usable.
+    let request = CloseSessionRequest {
+        request_header: RequestHeader {
+            authentication_token: tokens[0].clone(),
+            ..Default::default()
+        },
+        delete_subscriptions: true,
+    };
+    let ResponseMessage::CloseSession(response) = raw_request(
+        &mut stream,
+        &mut channel,
+        &mut sequence_number,
+        request_id,
+        request,
+    )
+    .await
+    else {
+        panic!("Expected close session response");
+    };
+    request_id += 1;
+    assert!(response.response_header.service_result.is_good());
+
+    let ResponseMessage::ServiceFault(fault) = raw_request(
+        &mut stream,
+        &mut channel,
+        &mut sequence_number,
+        request_id,
+        read(&tokens[0], 20),
+    )
+    .await
+    else {
+        panic!("Expected service fault");
+    };
+    request_id += 1;
+    assert_eq!(
+        fault.response_header.service_result,
+        StatusCode::BadSessionIdInvalid
+    );
+
+    let ResponseMessage::Read(response) = raw_request(
+        &mut stream,
+        &mut channel,
+        &mut sequence_number,
+        request_id,
+        read(&tokens[1], 21),
+    )
+    .await
+    else {
+        panic!("Expected read response");
+    };
+    assert!(response.response_header.service_result.is_good());
+}
+
+#[tokio::test]
+async fn service_fault_diagnostics() {
+    let server = default_server()
+        .service_fault_diagnostics(true)
+        .service_fault_diagnostic(
+            StatusCode::BadSessionIdInvalid,
+            ServiceFaultDiagnostic {
+                text: Some("The session does not exist, create a new one".to_owned()),
+                additional_info: Some("Sessions expire after 60 seconds".to_owned()),
+                ..Default::default()
+            },
+        );
+    let tester = Tester::new(server, false).await;
+    let (mut stream, mut channel) = raw_open_secure_channel(&tester).await;
+    let mut sequence_number = 2;
+
+    let activate =
+        |request_handle: u32, return_diagnostics: DiagnosticBits| ActivateSessionRequest {
+            request_header: RequestHeader {
+                authentication_token: NodeId::new(0, "unknown"),
+                request_handle,
+                return_diagnostics,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+    // Service level diagnostics are returned with the fault when requested.
+    let ResponseMessage::ServiceFault(fault) = raw_request(
+        &mut stream,
+        &mut channel,
+        &mut sequence_number,
+        2,
+        activate(
+            2,
+            DiagnosticBits::SERVICE_LEVEL_SYMBOLIC_ID
+                | DiagnosticBits::SERVICE_LEVEL_LOCALIZED_TEXT
+                | DiagnosticBits::SERVICE_LEVEL_ADDITIONAL_INFO,
+        ),
+    )
+    .await
+    else {
+        panic!("Expected service fault");
+    };
+    let header = fault.response_header;
+    assert_eq!(header.service_result, StatusCode::BadSessionIdInvalid);
+    let strings = header.string_table.unwrap();
+    let info = header.service_diagnostics;
+    assert_eq!(
+        strings[info.symbolic_id.unwrap() as usize].as_ref(),
+        "BadSessionIdInvalid"
+    );
+    assert_eq!(
+        strings[info.localized_text.unwrap() as usize].as_ref(),
+        "The session does not exist, create a new one"
+    );
+    assert_eq!(
+        info.additional_info.unwrap().as_ref(),
+        "Sessions expire after 60 seconds"
+    );
+
+    // Without the diagnostic bits, the fault is returned as normal.
+    let ResponseMessage::ServiceFault(fault) = raw_request(
+        &mut stream,
+        &mut channel,
+        &mut sequence_number,
+        3,
+        activate(3, DiagnosticBits::empty()),
+    )
+    .await
+    else {
+        panic!("Expected service fault");
+    };
+    assert_eq!(
+        fault.response_header.service_result,
+        StatusCode::BadSessionIdInvalid
+    );
+    assert!(fault.response_header.string_table.is_none());
+    assert_eq!(
+        fault.response_header.service_diagnostics,
+        DiagnosticInfo::default()
+    );
+}
+
+#[tokio::test]
+async fn request_expired_before_dispatch() {
+    // The timeout hint of the client is enforced even when it is shorter than
+    // the configured `max_timeout_ms`.
+    let server = test_server()
+        .diagnostics(DiagnosticsConfig::new(true))
+        .max_timeout_ms(60_000);
+    let tester = Tester::new(server, false).await;
+    let (mut stream, mut channel) = raw_open_secure_channel(&tester).await;
+    let mut sequence_number = 2;
+    let mut request_id = 2;
+    let token = raw_create_session(
+        &tester,
+        &mut stream,
+        &mut channel,
+        &mut sequence_number,
+        &mut request_id,
+        "expired",
+    )
+    .await;
+
+    let read = |request_handle: u32, timeout_hint: u32| ReadRequest {
+        request_header: RequestHeader {
+            authentication_token: token.clone(),
+            request_handle,
+            timeout_hint,
+            ..Default::default()
+        },
+        max_age: 0.0,
+        timestamps_to_return: TimestampsToReturn::Both,
+        nodes_to_read: Some(
+            (0..1000)
+                .map(|_| {
+                    ReadValueId::from(<VariableId as Into<NodeId>>::into(
+                        VariableId::Server_ServiceLevel,
+                    ))
+                })
+                .collect(),
+        ),
+    };
+
+    // Send a request with a short timeout in several chunks, delaying the last chunk
+    // until the timeout has passed.
+    let chunks = encode_raw(&channel, sequence_number, request_id, read(10, 10));
+    assert!(chunks.len() > 1);
+    sequence_number += chunks.len() as u32;
+    request_id += 1;
+    let (last, first) = chunks.split_last().unwrap();
+    send_raw_chunks(&mut stream, first).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    send_raw_chunks(&mut stream, std::slice::from_ref(last)).await;
+
+    let ResponseMessage::ServiceFault(fault) = read_raw_response(&mut stream, &mut channel).await
+    else {
+        panic!("Expected service fault");
+    };
+    assert_eq!(fault.response_header.request_handle, 10);
+    assert_eq!(fault.response_header.service_result, StatusCode::BadTimeout);
+    let diagnostics = &tester.handle.info().diagnostics;
+    assert_eq!(diagnostics.pre_dispatch_timeout_count(), 1);
+
+    // A request that arrives in time is dispatched as normal.
+    let ResponseMessage::Read(response) = raw_request(
+        &mut stream,
+        &mut channel,
+        &mut sequence_number,
+        request_id,
+        read(11, 10_000),
+    )
+    .await
+    else {
+        panic!("Expected read response");
+    };
+    assert!(response.response_header.service_result.is_good());
+    assert_eq!(diagnostics.pre_dispatch_timeout_count(), 1);
+}
@@ -5,12 +5,13 @@ use std::{
 
 use crate::utils::ChannelNotifications;
 
-use super::utils::setup;
+use super::utils::{client_user_token, setup};
 use opcua::{
     server::address_space::MethodBuilder,
     types::{
-        AttributeId, CallMethodRequest, DataTypeId, NodeId, ObjectId, StatusCode, Variant,
-        VariantTypeId,
+        Argument, AttributeId, BrowseDescription, BrowseDirection, BrowseResultMask,
+        CallMethodRequest, DataTypeId, MethodId, NodeClassMask, NodeId, ObjectId, ReferenceTypeId,
+        StatusCode, Variant, VariantTypeId,
     },
 };
 use opcua_types::{
@@ -128,6 +129,111 @@ async fn call_args() {
         .unwrap();
 
     assert_eq!(r.status_code, StatusCode::BadInvalidArgument);
+
+    // Call with typed outputs
+    let (v,): (i64,) = session
+        .call_typed(
+            ObjectId::ObjectsFolder,
+            id.clone(),
+            vec![Variant::Int64(3), Variant::Int64(2)],
+        )
+        .await
+        .unwrap();
+    assert_eq!(v, 5);
+
+    // Bad status is returned as an error
+    let e = session
+        .call_typed::<_, (i64,)>(
+            ObjectId::ObjectsFolder,
+            id.clone(),
+            vec![Variant::String("foo".into()), Variant::Int64(2)],
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(e, StatusCode::BadInvalidArgument);
+
+    // Wrong number of outputs
+    let e = session
+        .call_typed::<_, (i64, i64)>(
+            ObjectId::ObjectsFolder,
+            id.clone(),
+            vec![Variant::Int64(3), Variant::Int64(2)],
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(e, StatusCode::BadTypeMismatch);
+}
+
+#[tokio::test]
+async fn call_typed_arguments() {
+    let (_tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    let num = id.as_u32().unwrap();
+    {
+        let mut sp = nm.address_space().write();
+        MethodBuilder::new(&id, "MethodMul", "MethodMul")
+            .executable(true)
+            .user_executable(true)
+            .component_of(ObjectId::ObjectsFolder)
+            .input_argument("Lhs", DataTypeId::Int64, -1, "Left hand side")
+            .input_argument("Rhs", DataTypeId::Int64, -1, "Right hand side")
+            .output_argument("Result", DataTypeId::Int64, -1, "Product")
+            .insert(&mut *sp);
+    }
+
+    nm.inner().add_method_cb(id.clone(), |args| {
+        let (Some(Variant::Int64(lhs)), Some(Variant::Int64(rhs))) = (args.first(), args.get(1))
+        else {
+            return Err(StatusCode::BadInvalidArgument);
+        };
+        Ok(vec![Variant::Int64(lhs * rhs)])
+    });
+
+    let (v,): (i64,) = session
+        .call_typed(
+            ObjectId::ObjectsFolder,
+            id.clone(),
+            vec![Variant::Int64(3), Variant::Int64(2)],
+        )
+        .await
+        .unwrap();
+    assert_eq!(v, 6);
+
+    // The generated InputArguments property is used to validate the call.
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: ObjectId::ObjectsFolder.into(),
+            method_id: id.clone(),
+            input_arguments: Some(vec![
+                Variant::Int64(3),
+                Variant::Int64(2),
+                Variant::Int64(1),
+            ]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadTooManyArguments);
+
+    let r = session
+        .read(
+            &[
+                ReadValueId::new_value(NodeId::new(id.namespace, format!("{num}_InputArguments"))),
+                ReadValueId::new_value(NodeId::new(id.namespace, format!("{num}_OutputArguments"))),
+            ],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    let inputs: Vec<Argument> = r[0].value.clone().unwrap().try_cast_to().unwrap();
+    assert_eq!(inputs.len(), 2);
+    assert_eq!(inputs[0].name.as_ref(), "Lhs");
+    assert_eq!(inputs[1].data_type, DataTypeId::Int64);
+    assert_eq!(inputs[1].description.text.as_ref(), "Right hand side");
+    let outputs: Vec<Argument> = r[1].value.clone().unwrap().try_cast_to().unwrap();
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(outputs[0].name.as_ref(), "Result");
 }
 
 #[tokio::test]
@@ -304,3 +410,167 @@ async fn call_get_monitored_items() {
     assert_eq!(handles.len(), 1);
     assert_eq!(15, handles[0]);
 }
+
+#[tokio::test]
+async fn call_get_all_monitored_items() {
+    let (mut tester, _nm, session) = setup().await;
+
+    let (notifs, _data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: VariableId::Server_ServerStatus_State.into(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: opcua::types::MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    client_handle: 15,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+
+    // The vendor method is a component of the server object.
+    let r = session
+        .browse(
+            &[BrowseDescription {
+                node_id: ObjectId::Server.into(),
+                browse_direction: BrowseDirection::Forward,
+                reference_type_id: ReferenceTypeId::HasComponent.into(),
+                include_subtypes: true,
+                node_class_mask: NodeClassMask::METHOD.bits(),
+                result_mask: BrowseResultMask::All as u32,
+            }],
+            1000,
+            None,
+        )
+        .await
+        .unwrap();
+    let method_id = r[0]
+        .references
+        .as_ref()
+        .unwrap()
+        .iter()
+        .find(|r| r.browse_name.name.as_ref() == "GetAllMonitoredItems")
+        .unwrap()
+        .node_id
+        .node_id
+        .clone();
+
+    // The method has argument properties, like the standard methods.
+    let r = session
+        .browse(
+            &[BrowseDescription {
+                node_id: method_id.clone(),
+                browse_direction: BrowseDirection::Forward,
+                reference_type_id: ReferenceTypeId::HasProperty.into(),
+                include_subtypes: true,
+                node_class_mask: 0,
+                result_mask: BrowseResultMask::All as u32,
+            }],
+            1000,
+            None,
+        )
+        .await
+        .unwrap();
+    let props = r[0].references.as_ref().unwrap();
+    assert_eq!(
+        props
+            .iter()
+            .map(|r| r.browse_name.name.as_ref())
+            .collect::<Vec<_>>(),
+        vec!["InputArguments", "OutputArguments"]
+    );
+    let args = session
+        .read(
+            &props
+                .iter()
+                .map(|r| ReadValueId::new_value(r.node_id.node_id.clone()))
+                .collect::<Vec<_>>(),
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    let arg_names = |v: &opcua_types::DataValue| {
+        let Some(Variant::Array(a)) = &v.value else {
+            panic!("Expected array, got {v:?}");
+        };
+        a.values
+            .iter()
+            .map(|v| {
+                let Variant::ExtensionObject(o) = v else {
+                    panic!("Expected extension object");
+                };
+                let arg = o.inner_as::<Argument>().unwrap();
+                assert_eq!(arg.data_type, DataTypeId::UInt32);
+                arg.name.to_string()
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(arg_names(&args[0]), vec!["SubscriptionId"]);
+    assert_eq!(arg_names(&args[1]), vec!["ServerHandles", "ClientHandles"]);
+
+    let call = CallMethodRequest {
+        object_id: ObjectId::Server.into(),
+        method_id,
+        input_arguments: Some(vec![sub_id.into()]),
+    };
+
+    // The anonymous user is not an admin, and may not call the method,
+    // even on its own subscription.
+    let r = session.call_one(call.clone()).await.unwrap();
+    assert_eq!(r.status_code, StatusCode::BadUserAccessDenied);
+
+    // An admin user on a different session can see the subscription.
+    let (admin_session, lp) = tester
+        .connect(
+            opcua_crypto::SecurityPolicy::Aes128Sha256RsaOaep,
+            opcua_types::MessageSecurityMode::SignAndEncrypt,
+            client_user_token(),
+        )
+        .await
+        .unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), admin_session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let r = admin_session.call_one(call.clone()).await.unwrap();
+    assert_eq!(r.status_code, StatusCode::Good);
+    let outputs = r.output_arguments.unwrap();
+    let handles: Vec<u32> = outputs[1].clone().try_cast_to().unwrap();
+    assert_eq!(handles, vec![15]);
+
+    // The standard method is still restricted to the calling session.
+    let r = admin_session
+        .call_one(CallMethodRequest {
+            method_id: MethodId::Server_GetMonitoredItems.into(),
+            ..call.clone()
+        })
+        .await
+        .unwrap();
+    assert_ne!(r.status_code, StatusCode::Good);
+
+    let r = admin_session
+        .call_one(CallMethodRequest {
+            input_arguments: Some(vec![(sub_id + 1000).into()]),
+            ..call
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadSubscriptionIdInvalid);
+}
@@ -3,7 +3,10 @@ mod core_tests;
 mod custom_types;
 mod methods;
 mod node_management;
+mod pubsub;
 mod read;
+mod remote;
+mod simple;
 mod subscriptions;
 mod write;
 
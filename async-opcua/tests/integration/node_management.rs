@@ -1,12 +1,18 @@
-use super::utils::setup;
+use std::time::Duration;
+
+use super::utils::{setup, ChannelNotifications};
 use opcua::{
     server::address_space::{EventNotifier, NodeBase, NodeType, ObjectBuilder},
     types::{
-        AddNodeAttributes, AddNodesItem, AddReferencesItem, DeleteNodesItem, DeleteReferencesItem,
-        ExpandedNodeId, NodeClass, NodeId, ObjectAttributes, ObjectId, ObjectTypeId,
-        ReferenceTypeId, StatusCode,
+        AddNodeAttributes, AddNodesItem, AddReferencesItem, AttributeId, ContentFilter,
+        DeleteNodesItem, DeleteReferencesItem, EventFilter, ExpandedNodeId, ExtensionObject,
+        ModelChangeStructureDataType, ModelChangeStructureVerbMask, MonitoredItemCreateRequest,
+        MonitoringMode, MonitoringParameters, NodeClass, NodeId, NumericRange, ObjectAttributes,
+        ObjectId, ObjectTypeId, ReadValueId, ReferenceTypeId, SimpleAttributeOperand, StatusCode,
+        TimestampsToReturn, Variant,
     },
 };
+use tokio::time::timeout;
 
 #[tokio::test]
 async fn add_delete_node() {
@@ -212,3 +218,171 @@ async fn add_delete_reference_limits() {
         .unwrap_err();
     assert_eq!(e, StatusCode::BadTooManyOperations);
 }
+
+#[tokio::test]
+async fn model_change_events() {
+    let (tester, nm, session) = setup().await;
+
+    let id1 = nm.inner().next_node_id();
+    let id2 = nm.inner().next_node_id();
+    for id in [&id1, &id2] {
+        nm.inner().add_node(
+            nm.address_space(),
+            tester.handle.type_tree(),
+            ObjectBuilder::new(id, "TestObj", "TestObj").build().into(),
+            &ObjectId::ObjectsFolder.into(),
+            &ReferenceTypeId::Organizes.into(),
+            Some(&ObjectTypeId::FolderType.into()),
+            Vec::new(),
+        );
+    }
+
+    let (notifs, _, mut events) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: ObjectId::Server.into(),
+                    attribute_id: AttributeId::EventNotifier as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    queue_size: 10,
+                    filter: ExtensionObject::from_message(EventFilter {
+                        select_clauses: Some(vec![SimpleAttributeOperand::new(
+                            ObjectTypeId::GeneralModelChangeEventType,
+                            "Changes",
+                            AttributeId::Value,
+                            NumericRange::None,
+                        )]),
+                        where_clause: ContentFilter::default(),
+                    }),
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+
+    let mut next_changes = async || {
+        let (_, fields) = timeout(Duration::from_millis(500), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let Some(Variant::Array(changes)) = fields.unwrap().into_iter().next() else {
+            panic!("Expected an array of changes");
+        };
+        changes
+            .values
+            .into_iter()
+            .map(|v| match v {
+                Variant::ExtensionObject(o) => {
+                    let c = o.into_inner_as::<ModelChangeStructureDataType>().unwrap();
+                    (c.affected, c.affected_type, c.verb)
+                }
+                _ => panic!("Expected a ModelChangeStructureDataType"),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Adding several nodes emits a single event.
+    let r = session
+        .add_nodes(
+            &(0..2)
+                .map(|i| AddNodesItem {
+                    parent_node_id: ObjectId::ObjectsFolder.into(),
+                    reference_type_id: ReferenceTypeId::Organizes.into(),
+                    requested_new_node_id: ExpandedNodeId::null(),
+                    browse_name: format!("Added{i}").into(),
+                    node_class: NodeClass::Object,
+                    node_attributes: AddNodeAttributes::Object(ObjectAttributes {
+                        specified_attributes: 1 << 6,
+                        display_name: format!("Added{i}").into(),
+                        ..Default::default()
+                    })
+                    .as_extension_object(),
+                    type_definition: ExpandedNodeId::new(ObjectTypeId::FolderType),
+                })
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+    assert!(r.iter().all(|r| r.status_code == StatusCode::Good));
+    let added: Vec<_> = r.into_iter().map(|r| r.added_node_id).collect();
+    let added_verb = ModelChangeStructureVerbMask::NodeAdded as u8;
+    assert_eq!(
+        next_changes().await,
+        added
+            .iter()
+            .map(|id| (id.clone(), ObjectTypeId::FolderType.into(), added_verb))
+            .collect::<Vec<_>>()
+    );
+
+    // Both ends of a reference within the node manager are affected.
+    let r = session
+        .add_references(&[AddReferencesItem {
+            source_node_id: id1.clone(),
+            reference_type_id: ReferenceTypeId::HasCondition.into(),
+            is_forward: true,
+            target_server_uri: Default::default(),
+            target_node_id: id2.clone().into(),
+            target_node_class: NodeClass::Object,
+        }])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::Good);
+    let verb = ModelChangeStructureVerbMask::ReferenceAdded as u8;
+    assert_eq!(
+        next_changes().await,
+        vec![
+            (id1.clone(), ObjectTypeId::FolderType.into(), verb),
+            (id2.clone(), ObjectTypeId::FolderType.into(), verb),
+        ]
+    );
+
+    let r = session
+        .delete_references(&[DeleteReferencesItem {
+            source_node_id: id1.clone(),
+            reference_type_id: ReferenceTypeId::HasCondition.into(),
+            is_forward: true,
+            target_node_id: id2.clone().into(),
+            delete_bidirectional: true,
+        }])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::Good);
+    let verb = ModelChangeStructureVerbMask::ReferenceDeleted as u8;
+    assert_eq!(
+        next_changes().await,
+        vec![
+            (id1.clone(), ObjectTypeId::FolderType.into(), verb),
+            (id2.clone(), ObjectTypeId::FolderType.into(), verb),
+        ]
+    );
+
+    // The type of deleted nodes is still reported.
+    let r = session
+        .delete_nodes(&[DeleteNodesItem {
+            node_id: added[0].clone(),
+            delete_target_references: true,
+        }])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::Good);
+    assert_eq!(
+        next_changes().await,
+        vec![(
+            added[0].clone(),
+            ObjectTypeId::FolderType.into(),
+            ModelChangeStructureVerbMask::NodeDeleted as u8
+        )]
+    );
+}
@@ -0,0 +1,1260 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use opcua::{
+    client::IdentityToken,
+    crypto::SecurityPolicy,
+    server::{
+        address_space::VariableBuilder,
+        pubsub::{
+            data_set_meta_data, DataSetMessage, DataSetMetaDataMessage, MqttSink, MqttSource,
+            PubSubPublisher, PubSubSink, PubSubSource, PubSubSubscriber, QoS, SecurityKeyService,
+            UadpNetworkMessage, UadpSecurityHeader, UdpSink, UdpSource, PUBSUB_AES256_CTR,
+        },
+    },
+    types::{
+        BrokerDataSetReaderTransportDataType, BrokerDataSetWriterTransportDataType,
+        BrokerWriterGroupTransportDataType, ByteString, CallMethodRequest, ContextOwned,
+        DataSetFieldContentMask, DataSetMetaDataType, DataSetReaderDataType, DataSetWriterDataType,
+        DataTypeId, DataValue, DateTime, ExtensionObject, FieldMetaData, Guid, MessageSecurityMode,
+        MethodId, NetworkAddressUrlDataType, NodeId, ObjectId, PubSubConnectionDataType,
+        PublishedDataItemsDataType, PublishedDataSetDataType, PublishedVariableDataType,
+        ReaderGroupDataType, SecurityGroupDataType, StatusCode, UadpWriterGroupMessageDataType,
+        Variant, WriterGroupDataType,
+    },
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    time::timeout,
+};
+use tokio_util::sync::CancellationToken;
+
+use super::utils::{client_user_token, setup, test_server, Tester};
+
+struct ChannelSink(UnboundedSender<(Option<String>, Vec<u8>)>);
+
+#[async_trait]
+impl PubSubSink for ChannelSink {
+    async fn send(&self, queue_name: Option<&str>, payload: Vec<u8>) -> Result<(), StatusCode> {
+        self.0
+            .send((queue_name.map(|q| q.to_owned()), payload))
+            .map_err(|_| StatusCode::BadCommunicationError)
+    }
+}
+
+#[tokio::test]
+async fn publish_json() {
+    let (_tester, nm, _session) = setup().await;
+
+    let id1 = nm.inner().next_node_id();
+    let id2 = nm.inner().next_node_id();
+    {
+        let mut sp = nm.address_space().write();
+        VariableBuilder::new(&id1, "Temperature", "Temperature")
+            .value(21.5f64)
+            .data_type(DataTypeId::Double)
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+        VariableBuilder::new(&id2, "Count", "Count")
+            .value(5i32)
+            .data_type(DataTypeId::Int32)
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+    }
+
+    let data_set = PublishedDataSetDataType {
+        name: "Machine".into(),
+        data_set_meta_data: DataSetMetaDataType {
+            name: "Machine".into(),
+            fields: Some(vec![
+                FieldMetaData {
+                    name: "Temperature".into(),
+                    ..Default::default()
+                },
+                FieldMetaData {
+                    name: "Count".into(),
+                    ..Default::default()
+                },
+            ]),
+            ..Default::default()
+        },
+        data_set_source: ExtensionObject::from_message(PublishedDataItemsDataType {
+            published_data: Some(vec![
+                PublishedVariableDataType {
+                    published_variable: id1.clone(),
+                    attribute_id: 13,
+                    ..Default::default()
+                },
+                PublishedVariableDataType {
+                    published_variable: id2.clone(),
+                    attribute_id: 13,
+                    ..Default::default()
+                },
+            ]),
+        }),
+        ..Default::default()
+    };
+    let connection = PubSubConnectionDataType {
+        name: "Connection".into(),
+        enabled: true,
+        publisher_id: "Publisher".into(),
+        writer_groups: Some(vec![WriterGroupDataType {
+            name: "Group".into(),
+            enabled: true,
+            writer_group_id: 1,
+            publishing_interval: 50.0,
+            transport_settings: ExtensionObject::from_message(BrokerWriterGroupTransportDataType {
+                queue_name: "machines/data".into(),
+                ..Default::default()
+            }),
+            data_set_writers: Some(vec![
+                DataSetWriterDataType {
+                    name: "Writer".into(),
+                    enabled: true,
+                    data_set_writer_id: 3,
+                    data_set_name: "Machine".into(),
+                    ..Default::default()
+                },
+                DataSetWriterDataType {
+                    name: "StatusWriter".into(),
+                    enabled: true,
+                    data_set_writer_id: 4,
+                    data_set_name: "Machine".into(),
+                    data_set_field_content_mask: DataSetFieldContentMask::StatusCode,
+                    ..Default::default()
+                },
+            ]),
+            ..Default::default()
+        }]),
+        ..Default::default()
+    };
+
+    let (send, mut recv) = unbounded_channel();
+    let publisher = PubSubPublisher::new(
+        &connection,
+        &[data_set],
+        nm.address_space().clone(),
+        Arc::new(ChannelSink(send)),
+    )
+    .unwrap();
+    let token = CancellationToken::new();
+    let handle = tokio::spawn(publisher.run(token.clone()));
+
+    let mut last_sequence_number = 0;
+    for _ in 0..2 {
+        let (queue_name, payload) = timeout(Duration::from_millis(500), recv.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(queue_name.as_deref(), Some("machines/data"));
+        let message: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(message["MessageType"], "ua-data");
+        assert_eq!(message["PublisherId"], "Publisher");
+        assert_eq!(message["WriterGroupName"], "Group");
+
+        let messages = message["Messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        let writer = &messages[0];
+        assert_eq!(writer["DataSetWriterId"], 3);
+        let sequence_number = writer["SequenceNumber"].as_u64().unwrap();
+        assert!(sequence_number > last_sequence_number);
+        last_sequence_number = sequence_number;
+        // Without a field content mask, fields are encoded as variants.
+        assert_eq!(writer["Payload"]["Temperature"]["Body"], 21.5);
+        assert_eq!(writer["Payload"]["Count"]["Body"], 5);
+
+        // With a field content mask, fields are encoded as data values.
+        let writer = &messages[1];
+        assert_eq!(writer["DataSetWriterId"], 4);
+        assert_eq!(writer["Payload"]["Count"]["Value"]["Body"], 5);
+        assert!(writer["Payload"]["Count"].get("SourceTimestamp").is_none());
+    }
+
+    token.cancel();
+    timeout(Duration::from_millis(500), handle)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+/// Read a single MQTT control packet, returning the first header byte and the body.
+async fn read_mqtt_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let header = stream.read_u8().await.unwrap();
+    let mut len = 0usize;
+    for shift in (0..28).step_by(7) {
+        let b = stream.read_u8().await.unwrap();
+        len |= ((b & 0x7f) as usize) << shift;
+        if b & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body).await.unwrap();
+    (header, body)
+}
+
+/// Accept a single MQTT client on `listener`, and acknowledge its connection.
+async fn accept_mqtt_client(listener: &TcpListener) -> TcpStream {
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let (header, _) = read_mqtt_packet(&mut stream).await;
+    assert_eq!(header >> 4, 1, "Expected CONNECT");
+    stream.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap();
+    stream
+}
+
+fn mqtt_connection(port: u16) -> PubSubConnectionDataType {
+    PubSubConnectionDataType {
+        name: "Connection".into(),
+        enabled: true,
+        publisher_id: "Publisher".into(),
+        address: ExtensionObject::from_message(NetworkAddressUrlDataType {
+            url: format!("mqtt://127.0.0.1:{port}").into(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn publish_mqtt() {
+    let (_tester, nm, _session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    {
+        let mut sp = nm.address_space().write();
+        VariableBuilder::new(&id, "Temperature", "Temperature")
+            .value(21.5f64)
+            .data_type(DataTypeId::Double)
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+    }
+
+    let data_set = PublishedDataSetDataType {
+        name: "Machine".into(),
+        data_set_meta_data: data_set_meta_data(
+            "Machine",
+            [("Temperature", DataTypeId::Double.into(), -1)],
+        ),
+        data_set_source: ExtensionObject::from_message(PublishedDataItemsDataType {
+            published_data: Some(vec![PublishedVariableDataType {
+                published_variable: id.clone(),
+                attribute_id: 13,
+                ..Default::default()
+            }]),
+        }),
+        ..Default::default()
+    };
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let connection = PubSubConnectionDataType {
+        writer_groups: Some(vec![WriterGroupDataType {
+            name: "Group".into(),
+            enabled: true,
+            writer_group_id: 1,
+            publishing_interval: 50.0,
+            transport_settings: ExtensionObject::from_message(BrokerWriterGroupTransportDataType {
+                queue_name: "machines/data".into(),
+                ..Default::default()
+            }),
+            data_set_writers: Some(vec![DataSetWriterDataType {
+                name: "Writer".into(),
+                enabled: true,
+                data_set_writer_id: 3,
+                data_set_name: "Machine".into(),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }]),
+        ..mqtt_connection(listener.local_addr().unwrap().port())
+    };
+
+    let sink = MqttSink::from_connection(&connection)
+        .unwrap()
+        .with_qos(QoS::AtMostOnce);
+    let publisher = PubSubPublisher::new(
+        &connection,
+        &[data_set],
+        nm.address_space().clone(),
+        Arc::new(sink),
+    )
+    .unwrap();
+    let token = CancellationToken::new();
+    let handle = tokio::spawn(publisher.run(token.clone()));
+
+    let mut stream = timeout(Duration::from_secs(2), accept_mqtt_client(&listener))
+        .await
+        .unwrap();
+    let (header, body) = loop {
+        let (header, body) = timeout(Duration::from_secs(2), read_mqtt_packet(&mut stream))
+            .await
+            .unwrap();
+        if header >> 4 == 3 {
+            break (header, body);
+        }
+    };
+    // QoS 0, so there is no packet identifier after the topic.
+    assert_eq!(header & 0x06, 0);
+    let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+    assert_eq!(&body[2..2 + topic_len], b"machines/data");
+    let message: serde_json::Value = serde_json::from_slice(&body[2 + topic_len..]).unwrap();
+    assert_eq!(message["MessageType"], "ua-data");
+    assert_eq!(
+        message["Messages"][0]["Payload"]["Temperature"]["Body"],
+        21.5
+    );
+
+    token.cancel();
+    timeout(Duration::from_millis(500), handle)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn subscribe_mqtt() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let connection = PubSubConnectionDataType {
+        reader_groups: Some(vec![ReaderGroupDataType {
+            name: "Group".into(),
+            enabled: true,
+            data_set_readers: Some(vec![DataSetReaderDataType {
+                name: "Reader".into(),
+                enabled: true,
+                transport_settings: ExtensionObject::from_message(
+                    BrokerDataSetReaderTransportDataType {
+                        queue_name: "machines/data".into(),
+                        ..Default::default()
+                    },
+                ),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }]),
+        ..mqtt_connection(listener.local_addr().unwrap().port())
+    };
+    let source = MqttSource::from_connection(&connection).unwrap();
+
+    let mut stream = timeout(Duration::from_secs(2), accept_mqtt_client(&listener))
+        .await
+        .unwrap();
+    let (header, body) = timeout(Duration::from_secs(2), read_mqtt_packet(&mut stream))
+        .await
+        .unwrap();
+    assert_eq!(header >> 4, 8, "Expected SUBSCRIBE");
+    // Packet identifier, then the topic filter.
+    let topic_len = u16::from_be_bytes([body[2], body[3]]) as usize;
+    assert_eq!(&body[4..4 + topic_len], b"machines/data");
+    stream
+        .write_all(&[0x90, 0x03, body[0], body[1], 0x01])
+        .await
+        .unwrap();
+
+    let payload = br#"{"MessageType":"ua-data","Messages":[]}"#;
+    let mut packet = vec![0x30, (2 + 13 + payload.len()) as u8, 0x00, 13];
+    packet.extend_from_slice(b"machines/data");
+    packet.extend_from_slice(payload);
+    stream.write_all(&packet).await.unwrap();
+
+    let received = timeout(Duration::from_secs(2), source.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(received, payload);
+}
+
+#[tokio::test]
+async fn publish_meta_data() {
+    let (_tester, nm, _session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    {
+        let mut sp = nm.address_space().write();
+        VariableBuilder::new(&id, "Temperature", "Temperature")
+            .value(21.5f64)
+            .data_type(DataTypeId::Double)
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+    }
+
+    let meta_data = data_set_meta_data("Machine", [("Temperature", DataTypeId::Double.into(), -1)]);
+    let fields = meta_data.fields.as_deref().unwrap();
+    assert_eq!(fields[0].built_in_type, 11);
+    assert_eq!(fields[0].value_rank, -1);
+    assert!(meta_data.configuration_version.major_version > 0);
+
+    let data_set = PublishedDataSetDataType {
+        name: "Machine".into(),
+        data_set_meta_data: meta_data.clone(),
+        data_set_source: ExtensionObject::from_message(PublishedDataItemsDataType {
+            published_data: Some(vec![PublishedVariableDataType {
+                published_variable: id.clone(),
+                attribute_id: 13,
+                ..Default::default()
+            }]),
+        }),
+        ..Default::default()
+    };
+    let connection = PubSubConnectionDataType {
+        name: "Connection".into(),
+        enabled: true,
+        publisher_id: "Publisher".into(),
+        writer_groups: Some(vec![WriterGroupDataType {
+            name: "Group".into(),
+            enabled: true,
+            writer_group_id: 1,
+            publishing_interval: 50.0,
+            transport_settings: ExtensionObject::from_message(BrokerWriterGroupTransportDataType {
+                queue_name: "machines/data".into(),
+                ..Default::default()
+            }),
+            data_set_writers: Some(vec![DataSetWriterDataType {
+                name: "Writer".into(),
+                enabled: true,
+                data_set_writer_id: 3,
+                data_set_name: "Machine".into(),
+                transport_settings: ExtensionObject::from_message(
+                    BrokerDataSetWriterTransportDataType {
+                        meta_data_queue_name: "machines/metadata".into(),
+                        ..Default::default()
+                    },
+                ),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }]),
+        ..Default::default()
+    };
+
+    let (send, mut recv) = unbounded_channel();
+    let publisher = PubSubPublisher::new(
+        &connection,
+        &[data_set],
+        nm.address_space().clone(),
+        Arc::new(ChannelSink(send)),
+    )
+    .unwrap();
+    let token = CancellationToken::new();
+    let handle = tokio::spawn(publisher.run(token.clone()));
+
+    // Metadata is sent once before the first data message, since no update time is set.
+    let mut meta_data_messages = Vec::new();
+    let mut data_messages = 0;
+    while data_messages < 3 {
+        let (queue_name, payload) = timeout(Duration::from_millis(500), recv.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let message: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+        if queue_name.as_deref() == Some("machines/metadata") {
+            assert_eq!(data_messages, 0);
+            meta_data_messages.push(message);
+        } else {
+            assert_eq!(message["MessageType"], "ua-data");
+            data_messages += 1;
+        }
+    }
+    assert_eq!(meta_data_messages.len(), 1);
+    let message = &meta_data_messages[0];
+    assert_eq!(message["MessageType"], "ua-metadata");
+    assert_eq!(message["PublisherId"], "Publisher");
+    assert_eq!(message["DataSetWriterId"], 3);
+    assert_eq!(message["MetaData"]["Name"], "Machine");
+    assert_eq!(message["MetaData"]["Fields"][0]["Name"], "Temperature");
+    assert_eq!(message["MetaData"]["Fields"][0]["BuiltInType"], 11);
+
+    token.cancel();
+    timeout(Duration::from_millis(500), handle)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // UADP metadata is sent as a discovery response, which contains no data set messages.
+    let ctx = ContextOwned::default();
+    let encoded = DataSetMetaDataMessage {
+        publisher_id: Variant::UInt16(5),
+        data_set_writer_id: 3,
+        meta_data,
+    }
+    .encode_uadp(1, &ctx.context())
+    .unwrap();
+    let decoded = UadpNetworkMessage::decode(&encoded, &ctx.context(), |_, _| None).unwrap();
+    assert_eq!(decoded.publisher_id, Variant::UInt16(5));
+    assert!(decoded.messages.is_empty());
+}
+
+#[tokio::test]
+async fn publish_invalid_config() {
+    let (_tester, nm, _session) = setup().await;
+
+    let connection = PubSubConnectionDataType {
+        enabled: true,
+        writer_groups: Some(vec![WriterGroupDataType {
+            name: "Group".into(),
+            enabled: true,
+            publishing_interval: 100.0,
+            data_set_writers: Some(vec![DataSetWriterDataType {
+                name: "Writer".into(),
+                enabled: true,
+                data_set_name: "Missing".into(),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }]),
+        ..Default::default()
+    };
+    let (send, _recv) = unbounded_channel();
+    let res = PubSubPublisher::new(
+        &connection,
+        &[],
+        nm.address_space().clone(),
+        Arc::new(ChannelSink(send)),
+    );
+    assert_eq!(res.err(), Some(StatusCode::BadConfigurationError));
+}
+
+#[tokio::test]
+async fn publish_subscribe_udp() {
+    let (_tester, nm, _session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    {
+        let mut sp = nm.address_space().write();
+        VariableBuilder::new(&id, "Pressure", "Pressure")
+            .value(3.25f64)
+            .data_type(DataTypeId::Double)
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+    }
+
+    let meta_data = DataSetMetaDataType {
+        name: "Pump".into(),
+        fields: Some(vec![FieldMetaData {
+            name: "Pressure".into(),
+            built_in_type: 11,
+            ..Default::default()
+        }]),
+        ..Default::default()
+    };
+    let data_set = PublishedDataSetDataType {
+        name: "Pump".into(),
+        data_set_meta_data: meta_data.clone(),
+        data_set_source: ExtensionObject::from_message(PublishedDataItemsDataType {
+            published_data: Some(vec![PublishedVariableDataType {
+                published_variable: id.clone(),
+                attribute_id: 13,
+                ..Default::default()
+            }]),
+        }),
+        ..Default::default()
+    };
+
+    let source = UdpSource::bind(([127, 0, 0, 1], 0).into()).await.unwrap();
+    let address = source.local_addr().unwrap();
+    let connection = PubSubConnectionDataType {
+        name: "Connection".into(),
+        enabled: true,
+        publisher_id: Variant::from(12u16),
+        address: ExtensionObject::from_message(NetworkAddressUrlDataType {
+            url: format!("opc.udp://{address}").into(),
+            ..Default::default()
+        }),
+        writer_groups: Some(vec![WriterGroupDataType {
+            name: "Group".into(),
+            enabled: true,
+            publishing_interval: 50.0,
+            data_set_writers: Some(vec![DataSetWriterDataType {
+                name: "Writer".into(),
+                enabled: true,
+                data_set_writer_id: 7,
+                data_set_name: "Pump".into(),
+                data_set_field_content_mask: DataSetFieldContentMask::StatusCode,
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }]),
+        reader_groups: Some(vec![ReaderGroupDataType {
+            name: "Readers".into(),
+            enabled: true,
+            data_set_readers: Some(vec![
+                DataSetReaderDataType {
+                    name: "Reader".into(),
+                    enabled: true,
+                    publisher_id: Variant::from(12u16),
+                    data_set_writer_id: 7,
+                    data_set_meta_data: meta_data,
+                    data_set_field_content_mask: DataSetFieldContentMask::StatusCode,
+                    ..Default::default()
+                },
+                // Listens to a different writer, so it should never receive anything.
+                DataSetReaderDataType {
+                    name: "Other".into(),
+                    enabled: true,
+                    data_set_writer_id: 8,
+                    ..Default::default()
+                },
+            ]),
+            ..Default::default()
+        }]),
+        ..Default::default()
+    };
+
+    let sink = UdpSink::from_connection(&connection).await.unwrap();
+    let publisher = PubSubPublisher::new(
+        &connection,
+        &[data_set],
+        nm.address_space().clone(),
+        Arc::new(sink),
+    )
+    .unwrap();
+    let subscriber = PubSubSubscriber::new(&connection, Arc::new(source));
+
+    let token = CancellationToken::new();
+    let (send, mut recv) = unbounded_channel();
+    let pub_handle = tokio::spawn(publisher.run(token.clone()));
+    let sub_handle = tokio::spawn(subscriber.run(token.clone(), move |reader, message| {
+        let _ = send.send((reader.to_owned(), message));
+    }));
+
+    let (reader, message) = timeout(Duration::from_millis(500), recv.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reader, "Reader");
+    assert_eq!(message.data_set_writer_id, 7);
+    assert_eq!(message.fields.len(), 1);
+    let (name, value) = &message.fields[0];
+    assert_eq!(name, "Pressure");
+    assert_eq!(value.value, Some(Variant::from(3.25f64)));
+    assert_eq!(value.status(), StatusCode::Good);
+
+    token.cancel();
+    pub_handle.await.unwrap();
+    sub_handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn subscribe_raw_fields() {
+    struct NoSource;
+
+    #[async_trait]
+    impl PubSubSource for NoSource {
+        async fn recv(&self) -> Result<Vec<u8>, StatusCode> {
+            Err(StatusCode::BadNotSupported)
+        }
+    }
+
+    let connection = PubSubConnectionDataType {
+        enabled: true,
+        reader_groups: Some(vec![ReaderGroupDataType {
+            enabled: true,
+            data_set_readers: Some(vec![
+                // Reader for the same data set writer ID of another publisher.
+                DataSetReaderDataType {
+                    name: "OtherReader".into(),
+                    enabled: true,
+                    publisher_id: "Other".into(),
+                    data_set_field_content_mask: DataSetFieldContentMask::RawData,
+                    data_set_meta_data: DataSetMetaDataType {
+                        fields: Some(vec![FieldMetaData {
+                            name: "Flag".into(),
+                            built_in_type: 1,
+                            ..Default::default()
+                        }]),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                DataSetReaderDataType {
+                    name: "Reader".into(),
+                    enabled: true,
+                    publisher_id: "Publisher".into(),
+                    data_set_field_content_mask: DataSetFieldContentMask::RawData,
+                    data_set_meta_data: DataSetMetaDataType {
+                        fields: Some(vec![
+                            FieldMetaData {
+                                name: "Count".into(),
+                                built_in_type: 6,
+                                value_rank: -1,
+                                ..Default::default()
+                            },
+                            FieldMetaData {
+                                name: "Name".into(),
+                                built_in_type: 12,
+                                value_rank: -1,
+                                ..Default::default()
+                            },
+                        ]),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            ]),
+            ..Default::default()
+        }]),
+        ..Default::default()
+    };
+    let subscriber = PubSubSubscriber::new(&connection, Arc::new(NoSource));
+
+    // Fields are returned in the order of the metadata, and unknown fields are ignored.
+    let messages = subscriber
+        .decode(
+            br#"{
+                "MessageId": "1",
+                "MessageType": "ua-data",
+                "PublisherId": "Publisher",
+                "Messages": [{
+                    "DataSetWriterId": 1,
+                    "SequenceNumber": 5,
+                    "Payload": { "Name": "Pump", "Unknown": true, "Count": 3 }
+                }]
+            }"#,
+        )
+        .unwrap();
+    assert_eq!(messages.len(), 1);
+    let (reader, message) = &messages[0];
+    assert_eq!(reader, "Reader");
+    assert_eq!(message.sequence_number, 5);
+    assert_eq!(
+        message
+            .fields
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.value.clone().unwrap()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("Count", Variant::from(3i32)),
+            ("Name", Variant::from("Pump"))
+        ]
+    );
+
+    // Messages from other publishers are ignored.
+    let messages = subscriber
+        .decode(br#"{"MessageType": "ua-data", "PublisherId": "Other", "Messages": []}"#)
+        .unwrap();
+    assert!(messages.is_empty());
+
+    // Raw UADP fields are decoded using the metadata of the reader matching both the
+    // publisher ID and the data set writer ID.
+    let fields = vec![
+        ("Count".to_owned(), DataValue::value_only(3i32)),
+        ("Name".to_owned(), DataValue::value_only("Pump")),
+    ];
+    let payload = UadpNetworkMessage {
+        publisher_id: "Publisher".into(),
+        messages: vec![DataSetMessage {
+            data_set_writer_id: 1,
+            sequence_number: 6,
+            timestamp: DateTime::null(),
+            field_content_mask: DataSetFieldContentMask::RawData,
+            fields: fields.clone(),
+        }],
+        ..Default::default()
+    }
+    .encode(&ContextOwned::default().context())
+    .unwrap();
+    let messages = subscriber.decode(&payload).unwrap();
+    assert_eq!(messages.len(), 1);
+    let (reader, message) = &messages[0];
+    assert_eq!(reader, "Reader");
+    assert_eq!(message.sequence_number, 6);
+    assert_eq!(message.fields, fields);
+
+    assert_eq!(
+        subscriber.decode(b"not json").err(),
+        Some(StatusCode::BadDecodingError)
+    );
+}
+
+#[test]
+fn uadp_round_trip() {
+    let ctx_owned = ContextOwned::default();
+    let ctx = ctx_owned.context();
+    let timestamp = DateTime::now();
+    let meta_data = vec![
+        FieldMetaData {
+            name: "Count".into(),
+            built_in_type: 6,
+            value_rank: -1,
+            ..Default::default()
+        },
+        FieldMetaData {
+            name: "Values".into(),
+            built_in_type: 11,
+            value_rank: 1,
+            ..Default::default()
+        },
+    ];
+
+    let message = UadpNetworkMessage {
+        publisher_id: "Publisher".into(),
+        data_set_class_id: Some(Guid::new()),
+        writer_group_id: Some(2),
+        group_version: Some(15),
+        network_message_number: Some(1),
+        sequence_number: Some(100),
+        timestamp: Some(timestamp),
+        security: Some(UadpSecurityHeader {
+            // Security footer flag.
+            security_flags: 0x04,
+            security_token_id: 4,
+            message_nonce: vec![1, 2, 3, 4],
+            security_footer: vec![9, 9],
+        }),
+        messages: vec![
+            DataSetMessage {
+                data_set_writer_id: 1,
+                sequence_number: 10,
+                timestamp,
+                field_content_mask: DataSetFieldContentMask::empty(),
+                fields: vec![("0".to_owned(), DataValue::value_only(5i32))],
+            },
+            DataSetMessage {
+                data_set_writer_id: 2,
+                sequence_number: 11,
+                timestamp,
+                field_content_mask: DataSetFieldContentMask::StatusCode
+                    | DataSetFieldContentMask::SourceTimestamp
+                    | DataSetFieldContentMask::ServerTimestamp,
+                fields: vec![(
+                    "0".to_owned(),
+                    DataValue {
+                        value: Some("Pump".into()),
+                        status: Some(StatusCode::UncertainSubstituteValue),
+                        source_timestamp: Some(timestamp),
+                        ..Default::default()
+                    },
+                )],
+            },
+            DataSetMessage {
+                data_set_writer_id: 3,
+                sequence_number: 12,
+                timestamp,
+                field_content_mask: DataSetFieldContentMask::RawData,
+                fields: vec![
+                    ("Count".to_owned(), DataValue::value_only(7i32)),
+                    (
+                        "Values".to_owned(),
+                        DataValue::value_only(vec![1.5f64, 2.5f64]),
+                    ),
+                ],
+            },
+        ],
+    };
+
+    let encoded = message.encode(&ctx).unwrap();
+    let decoded = UadpNetworkMessage::decode(&encoded, &ctx, |publisher_id, id| {
+        (*publisher_id == Variant::from("Publisher") && id == 3).then_some(meta_data.as_slice())
+    })
+    .unwrap();
+    // The decoded field content mask reflects the field encoding, so compare the rest.
+    assert_eq!(decoded.messages.len(), 3);
+    for (decoded, original) in decoded.messages.iter().zip(&message.messages) {
+        assert_eq!(decoded.data_set_writer_id, original.data_set_writer_id);
+        assert_eq!(decoded.sequence_number, original.sequence_number);
+        assert_eq!(decoded.timestamp, original.timestamp);
+        assert_eq!(decoded.fields, original.fields);
+    }
+    assert_eq!(
+        decoded.messages[2].field_content_mask,
+        DataSetFieldContentMask::RawData
+    );
+    assert_eq!(
+        UadpNetworkMessage {
+            messages: message.messages.clone(),
+            ..decoded
+        },
+        message
+    );
+
+    // Raw fields cannot be decoded without metadata.
+    assert!(UadpNetworkMessage::decode(&encoded, &ctx, |_, _| None).is_err());
+
+    // Signed and encrypted messages are not supported.
+    let signed = UadpNetworkMessage {
+        security: Some(UadpSecurityHeader {
+            security_flags: 0x01,
+            ..Default::default()
+        }),
+        ..message
+    };
+    assert!(signed.encode(&ctx).is_err());
+}
+
+#[test]
+fn uadp_raw_fields_without_count() {
+    let ctx_owned = ContextOwned::default();
+    let ctx = ctx_owned.context();
+    let message = UadpNetworkMessage {
+        messages: vec![DataSetMessage {
+            data_set_writer_id: 1,
+            sequence_number: 1,
+            timestamp: DateTime::null(),
+            field_content_mask: DataSetFieldContentMask::RawData,
+            fields: vec![("Count".to_owned(), DataValue::value_only(7i32))],
+        }],
+        ..Default::default()
+    };
+    let encoded = message.encode(&ctx).unwrap();
+    // Network message header, payload header with one writer ID, data set message flags,
+    // sequence number and timestamp, followed directly by the raw field.
+    assert_eq!(encoded.len(), 4 + 12 + 4);
+    assert_eq!(encoded[16..], 7i32.to_le_bytes());
+
+    let meta_data = [FieldMetaData {
+        name: "Count".into(),
+        built_in_type: 6,
+        value_rank: -1,
+        ..Default::default()
+    }];
+    let decoded = UadpNetworkMessage::decode(&encoded, &ctx, |_, _| Some(&meta_data)).unwrap();
+    assert_eq!(decoded.messages[0].fields, message.messages[0].fields);
+}
+
+#[test]
+fn uadp_decode_invalid_footer_length() {
+    let ctx_owned = ContextOwned::default();
+    let ctx = ctx_owned.context();
+    let nonce = [0xab, 0xcd, 0xef, 0x12];
+    let message = UadpNetworkMessage {
+        publisher_id: "Publisher".into(),
+        security: Some(UadpSecurityHeader {
+            // Security footer flag.
+            security_flags: 0x04,
+            security_token_id: 4,
+            message_nonce: nonce.to_vec(),
+            security_footer: vec![9, 9],
+        }),
+        messages: vec![DataSetMessage {
+            data_set_writer_id: 1,
+            sequence_number: 10,
+            timestamp: DateTime::now(),
+            field_content_mask: DataSetFieldContentMask::empty(),
+            fields: vec![("0".to_owned(), DataValue::value_only(5i32))],
+        }],
+        ..Default::default()
+    };
+    let mut encoded = message.encode(&ctx).unwrap();
+    assert!(UadpNetworkMessage::decode(&encoded, &ctx, |_, _| None).is_ok());
+
+    // The footer length follows the message nonce. A footer that fits in the
+    // message, but overlaps the headers, must not be read as a payload.
+    let footer_len_pos = encoded
+        .windows(nonce.len())
+        .position(|w| w == nonce)
+        .unwrap()
+        + nonce.len();
+    let footer_len = (encoded.len() - 1) as u16;
+    encoded[footer_len_pos..footer_len_pos + 2].copy_from_slice(&footer_len.to_le_bytes());
+    let err = UadpNetworkMessage::decode(&encoded, &ctx, |_, _| None).unwrap_err();
+    assert_eq!(err.status(), StatusCode::BadDecodingError);
+
+    // A footer longer than the message.
+    encoded[footer_len_pos..footer_len_pos + 2].copy_from_slice(&u16::MAX.to_le_bytes());
+    let err = UadpNetworkMessage::decode(&encoded, &ctx, |_, _| None).unwrap_err();
+    assert_eq!(err.status(), StatusCode::BadDecodingError);
+}
+
+#[tokio::test]
+async fn publish_uadp() {
+    let (_tester, nm, _session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    {
+        let mut sp = nm.address_space().write();
+        VariableBuilder::new(&id, "Speed", "Speed")
+            .value(1200u32)
+            .data_type(DataTypeId::UInt32)
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+    }
+
+    let meta_data = DataSetMetaDataType {
+        name: "Motor".into(),
+        fields: Some(vec![FieldMetaData {
+            name: "Speed".into(),
+            built_in_type: 7,
+            value_rank: -1,
+            ..Default::default()
+        }]),
+        ..Default::default()
+    };
+    let data_set = PublishedDataSetDataType {
+        name: "Motor".into(),
+        data_set_meta_data: meta_data.clone(),
+        data_set_source: ExtensionObject::from_message(PublishedDataItemsDataType {
+            published_data: Some(vec![PublishedVariableDataType {
+                published_variable: id.clone(),
+                attribute_id: 13,
+                ..Default::default()
+            }]),
+        }),
+        ..Default::default()
+    };
+    let connection = PubSubConnectionDataType {
+        enabled: true,
+        publisher_id: Variant::from(42u64),
+        writer_groups: Some(vec![WriterGroupDataType {
+            name: "Group".into(),
+            enabled: true,
+            writer_group_id: 5,
+            publishing_interval: 50.0,
+            message_settings: ExtensionObject::from_message(
+                UadpWriterGroupMessageDataType::default(),
+            ),
+            data_set_writers: Some(vec![DataSetWriterDataType {
+                name: "Writer".into(),
+                enabled: true,
+                data_set_writer_id: 9,
+                data_set_name: "Motor".into(),
+                data_set_field_content_mask: DataSetFieldContentMask::RawData,
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }]),
+        reader_groups: Some(vec![ReaderGroupDataType {
+            enabled: true,
+            data_set_readers: Some(vec![DataSetReaderDataType {
+                name: "Reader".into(),
+                enabled: true,
+                publisher_id: Variant::from(42u64),
+                data_set_writer_id: 9,
+                data_set_meta_data: meta_data,
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }]),
+        ..Default::default()
+    };
+
+    let (send, mut recv) = unbounded_channel();
+    let publisher = PubSubPublisher::new(
+        &connection,
+        &[data_set],
+        nm.address_space().clone(),
+        Arc::new(ChannelSink(send)),
+    )
+    .unwrap();
+    let source = UdpSource::bind(([127, 0, 0, 1], 0).into()).await.unwrap();
+    let subscriber = PubSubSubscriber::new(&connection, Arc::new(source));
+    let token = CancellationToken::new();
+    let handle = tokio::spawn(publisher.run(token.clone()));
+
+    let (_, payload) = timeout(Duration::from_millis(500), recv.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    let fields = [FieldMetaData {
+        built_in_type: 7,
+        value_rank: -1,
+        ..Default::default()
+    }];
+    let message =
+        UadpNetworkMessage::decode(&payload, &ContextOwned::default().context(), |_, _| {
+            Some(&fields)
+        })
+        .unwrap();
+    assert_eq!(message.publisher_id, Variant::from(42u64));
+    assert_eq!(message.writer_group_id, Some(5));
+    assert_eq!(message.sequence_number, Some(1));
+
+    let messages = subscriber.decode(&payload).unwrap();
+    assert_eq!(messages.len(), 1);
+    let (reader, message) = &messages[0];
+    assert_eq!(reader, "Reader");
+    assert_eq!(message.data_set_writer_id, 9);
+    assert_eq!(message.fields[0].0, "Speed");
+    assert_eq!(message.fields[0].1.value, Some(Variant::from(1200u32)));
+
+    token.cancel();
+    timeout(Duration::from_millis(500), handle)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn security_key_service() {
+    let group = SecurityGroupDataType {
+        name: "Group".into(),
+        security_group_id: "Group".into(),
+        security_policy_uri: PUBSUB_AES256_CTR.into(),
+        key_lifetime: 300.0,
+        max_future_key_count: 2,
+        max_past_key_count: 1,
+        ..Default::default()
+    };
+    let sks = Arc::new(SecurityKeyService::new());
+    sks.add_security_group(NodeId::new(1, "Group"), group.clone())
+        .unwrap();
+    assert_eq!(
+        sks.add_security_group(
+            NodeId::new(1, "Other"),
+            SecurityGroupDataType {
+                security_group_id: "Other".into(),
+                security_policy_uri: "http://example.com/UnknownPolicy".into(),
+                key_lifetime: 300.0,
+                ..Default::default()
+            },
+        ),
+        Err(StatusCode::BadSecurityPolicyRejected)
+    );
+
+    let mut tester = Tester::new(test_server().with_security_key_service(sks.clone()), false).await;
+    let session = tester
+        .connect_and_wait(
+            SecurityPolicy::Basic256Sha256,
+            MessageSecurityMode::SignAndEncrypt,
+            client_user_token(),
+        )
+        .await
+        .unwrap();
+
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: ObjectId::PublishSubscribe.into(),
+            method_id: MethodId::PublishSubscribe_GetSecurityKeys.into(),
+            input_arguments: Some(vec!["Group".into(), 0u32.into(), 0u32.into()]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::Good);
+    let outputs = r.output_arguments.unwrap();
+    assert_eq!(outputs[0], Variant::from(PUBSUB_AES256_CTR));
+    assert_eq!(outputs[1], Variant::from(1u32));
+    // The current key and two future keys.
+    let Variant::Array(keys) = &outputs[2] else {
+        panic!("Expected array of keys, got {:?}", outputs[2]);
+    };
+    assert_eq!(keys.values.len(), 3);
+    for key in &keys.values {
+        let Variant::ByteString(key) = key else {
+            panic!("Expected key to be a byte string, got {key:?}");
+        };
+        assert_eq!(key.as_ref().len(), 68);
+    }
+    assert_eq!(outputs[4], Variant::from(300.0));
+
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: ObjectId::PublishSubscribe.into(),
+            method_id: MethodId::PublishSubscribe_GetSecurityGroup.into(),
+            input_arguments: Some(vec!["Group".into()]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::Good);
+    assert_eq!(
+        r.output_arguments,
+        Some(vec![Variant::from(NodeId::new(1, "Group"))])
+    );
+
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: ObjectId::PublishSubscribe.into(),
+            method_id: MethodId::PublishSubscribe_GetSecurityGroup.into(),
+            input_arguments: Some(vec!["Missing".into()]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadNotFound);
+
+    // After the key lifetime the next key is current, and the old key is kept as a past key.
+    // Replace the group first, so that the key lifetime starts now.
+    sks.add_security_group(NodeId::new(1, "Group"), group)
+        .unwrap();
+    let first = sks.get_security_keys("Group", 0, 1).unwrap();
+    tokio::time::sleep(Duration::from_millis(350)).await;
+    let keys = sks.get_security_keys("Group", 1, 0).unwrap();
+    assert_eq!(keys.first_token_id, 1);
+    assert_eq!(keys.keys.len(), 4);
+    assert_eq!(keys.keys[0], first.keys[0]);
+    let current = sks.get_security_keys("Group", 0, 1).unwrap();
+    assert_eq!(current.first_token_id, 2);
+    assert_eq!(current.keys, vec![keys.keys[1].clone()]);
+    assert_ne!(current.keys[0], ByteString::null());
+
+    // Keys are only sent over encrypted channels.
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: ObjectId::PublishSubscribe.into(),
+            method_id: MethodId::PublishSubscribe_GetSecurityKeys.into(),
+            input_arguments: Some(vec!["Group".into(), 0u32.into(), 0u32.into()]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadSecurityModeInsufficient);
+
+    // Anonymous users may not fetch keys, even over an encrypted channel.
+    let anonymous = tester
+        .connect_and_wait(
+            SecurityPolicy::Basic256Sha256,
+            MessageSecurityMode::SignAndEncrypt,
+            IdentityToken::Anonymous,
+        )
+        .await
+        .unwrap();
+    let r = anonymous
+        .call_one(CallMethodRequest {
+            object_id: ObjectId::PublishSubscribe.into(),
+            method_id: MethodId::PublishSubscribe_GetSecurityKeys.into(),
+            input_arguments: Some(vec!["Group".into(), 0u32.into(), 0u32.into()]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadUserAccessDenied);
+}
+
+#[test]
+fn security_key_service_key_lifetime() {
+    let group = SecurityGroupDataType {
+        name: "Group".into(),
+        security_group_id: "Group".into(),
+        security_policy_uri: PUBSUB_AES256_CTR.into(),
+        key_lifetime: 300.0,
+        max_future_key_count: 2,
+        max_past_key_count: 1,
+        ..Default::default()
+    };
+    let sks = SecurityKeyService::new();
+    for key_lifetime in [0.0, -1.0, f64::NAN, f64::INFINITY, f64::MAX, 1e-12] {
+        assert_eq!(
+            sks.add_security_group(
+                NodeId::new(1, "Group"),
+                SecurityGroupDataType {
+                    key_lifetime,
+                    ..group.clone()
+                },
+            ),
+            Err(StatusCode::BadConfigurationError),
+            "key lifetime {key_lifetime}"
+        );
+    }
+
+    // With a tiny key lifetime, a very large number of keys expire between requests.
+    // Only the keys that are kept should be created.
+    sks.add_security_group(
+        NodeId::new(1, "Group"),
+        SecurityGroupDataType {
+            key_lifetime: 0.0001,
+            ..group
+        },
+    )
+    .unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    let start = std::time::Instant::now();
+    let keys = sks.get_security_keys("Group", 0, 0).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    // At least 50ms / 100ns keys have expired since the group was added.
+    assert!(keys.first_token_id > 500_000);
+    // The current key and two future keys.
+    assert_eq!(keys.keys.len(), 3);
+    // Token 1 is long gone, so the keys start at the current key.
+    let from_first = sks.get_security_keys("Group", 1, 0).unwrap();
+    assert!(from_first.first_token_id >= keys.first_token_id);
+    assert_eq!(from_first.keys.len(), 3);
+}
@@ -1,20 +1,24 @@
 use std::{sync::atomic::Ordering, time::Duration};
 
-use crate::utils::{client_user_token, default_server, Tester};
+use crate::utils::{client_user_token, default_client, default_server, Tester};
 
 use super::utils::{array_value, read_value_id, read_value_ids, setup};
 use chrono::TimeDelta;
 use opcua::{
-    client::HistoryReadAction,
-    server::address_space::{
-        AccessLevel, DataTypeBuilder, EventNotifier, MethodBuilder, ObjectBuilder,
-        ObjectTypeBuilder, ReferenceTypeBuilder, VariableBuilder, VariableTypeBuilder, ViewBuilder,
+    client::{DataChangeCallback, HistoryReadAction, OperationLimits},
+    server::{
+        address_space::{
+            AccessLevel, DataTypeBuilder, EventNotifier, MethodBuilder, ObjectBuilder,
+            ObjectTypeBuilder, ReferenceTypeBuilder, VariableBuilder, VariableTypeBuilder,
+            ViewBuilder,
+        },
+        DiagnosticsConfig, Profile, ServerCapabilities,
     },
     types::{
-        AttributeId, DataTypeId, DataValue, DateTime, HistoryData, HistoryReadValueId, NodeClass,
-        NodeId, ObjectId, ObjectTypeId, QualifiedName, ReadRawModifiedDetails, ReadValueId,
-        ReferenceTypeId, StatusCode, TimestampsToReturn, VariableId, VariableTypeId, Variant,
-        WriteMask,
+        AttributeId, DataTypeDefinition, DataTypeId, DataValue, DateTime, HistoryData,
+        HistoryReadValueId, IdType, NodeClass, NodeId, ObjectId, ObjectTypeId, QualifiedName,
+        ReadRawModifiedDetails, ReadValueId, ReferenceTypeId, ServerState, StatusCode,
+        TimestampsToReturn, VariableId, VariableTypeId, Variant, WriteMask,
     },
 };
 use opcua_client::{services::Read, DefaultRetryPolicy, ExponentialBackoff};
@@ -40,6 +44,111 @@ async fn read() {
     assert_eq!(&Variant::Byte(123), r[0].value.as_ref().unwrap())
 }
 
+#[tokio::test]
+async fn read_server_object() {
+    let (tester, _nm, session) = setup().await;
+    tester.handle.set_service_level(123);
+
+    let server = session.server_object();
+    let status = server.server_status().await.unwrap();
+    assert_eq!(status.state, ServerState::Running);
+    assert_eq!(server.state().await.unwrap(), ServerState::Running);
+    assert_eq!(server.service_level().await.unwrap(), 123);
+
+    let time = server.current_time().await.unwrap();
+    assert!((DateTime::now() - time).num_seconds().abs() < 10);
+
+    let namespaces = server.namespace_array().await.unwrap();
+    assert!(namespaces.get_index("urn:rustopcuatestserver").is_some());
+
+    let sub_id = session
+        .create_subscription(
+            Duration::from_secs(1),
+            100,
+            20,
+            1000,
+            0,
+            true,
+            DataChangeCallback::new(|_, _| {}),
+        )
+        .await
+        .unwrap();
+    let (server_handles, client_handles) = server.get_monitored_items(sub_id).await.unwrap();
+    assert!(server_handles.is_empty());
+    assert!(client_handles.is_empty());
+}
+
+#[tokio::test]
+async fn read_namespace_metadata() {
+    let (_tester, _nm, session) = setup().await;
+
+    let metadata = session.namespace_metadata().await.unwrap();
+    let test_ns = metadata
+        .iter()
+        .find(|m| m.namespace_uri == "urn:rustopcuatestserver")
+        .unwrap();
+    assert_eq!(test_ns.namespace_version.as_deref(), Some("1.0.0"));
+    assert_eq!(
+        test_ns.namespace_publication_date,
+        Some(DateTime::ymd(2024, 1, 1))
+    );
+    assert_eq!(test_ns.is_namespace_subset, Some(false));
+
+    // The base namespace is always present, even though it does not populate everything.
+    let base_ns = metadata
+        .iter()
+        .find(|m| m.namespace_uri == "http://opcfoundation.org/UA/")
+        .unwrap();
+    assert_eq!(base_ns.static_node_id_types, Some(vec![IdType::Numeric]));
+}
+
+#[tokio::test]
+async fn read_data_type_definitions() {
+    let (_tester, _nm, session) = setup().await;
+
+    let DataTypeDefinition::Structure(def) = session
+        .read_data_type_definition(&DataTypeId::EUInformation.into())
+        .await
+        .unwrap()
+    else {
+        panic!("Expected structure definition");
+    };
+    let fields = def.fields.unwrap();
+    assert_eq!(fields.len(), 4);
+    assert_eq!(fields[0].name.as_ref(), "NamespaceUri");
+
+    let DataTypeDefinition::Enum(def) = session
+        .data_type_definition(&DataTypeId::NodeClass.into())
+        .await
+        .unwrap()
+    else {
+        panic!("Expected enum definition");
+    };
+    assert!(def
+        .fields
+        .unwrap()
+        .iter()
+        .any(|f| f.name.as_ref() == "Variable" && f.value == 2));
+
+    // Cached
+    assert!(matches!(
+        session
+            .data_type_definition(&DataTypeId::NodeClass.into())
+            .await
+            .unwrap(),
+        DataTypeDefinition::Enum(_)
+    ));
+
+    // Nodes that are not data types do not have a definition.
+    assert_eq!(
+        session
+            .data_type_definition(&ObjectId::Server.into())
+            .await
+            .unwrap_err(),
+        StatusCode::BadAttributeIdInvalid
+    );
+}
+
 #[tokio::test]
 async fn read_variable() {
     let (tester, nm, session) = setup().await;
@@ -683,6 +792,57 @@ async fn read_mixed() {
     assert_eq!(r[8].value, None);
 }
 
+#[tokio::test]
+async fn read_batched_by_node_manager() {
+    let (tester, nm, session) = setup().await;
+
+    let ids: Vec<_> = (0..500)
+        .map(|i| {
+            let id = nm.inner().next_node_id();
+            nm.inner().add_node(
+                nm.address_space(),
+                tester.handle.type_tree(),
+                VariableBuilder::new(&id, format!("Var{i}"), format!("Var{i}"))
+                    .value(i)
+                    .data_type(DataTypeId::Int32)
+                    .access_level(AccessLevel::CURRENT_READ)
+                    .user_access_level(AccessLevel::CURRENT_READ)
+                    .build()
+                    .into(),
+                &ObjectId::ObjectsFolder.into(),
+                &ReferenceTypeId::Organizes.into(),
+                Some(&VariableTypeId::BaseDataVariableType.into()),
+                Vec::new(),
+            );
+            id
+        })
+        .collect();
+
+    // Interleave reads from the test node manager with reads from the core node manager.
+    // Each node manager should still only receive a single batch.
+    let to_read: Vec<_> = ids
+        .iter()
+        .flat_map(|id| {
+            [
+                read_value_id(AttributeId::Value, id),
+                read_value_id(AttributeId::Value, VariableId::Server_ServiceLevel),
+            ]
+        })
+        .collect();
+
+    let before = nm.inner().read_values_batches();
+    let r = session
+        .read(&to_read, TimestampsToReturn::Both, 0.0)
+        .await
+        .unwrap();
+    assert_eq!(r.len(), 1000);
+    for (i, v) in r.chunks(2).enumerate() {
+        assert_eq!(v[0].value, Some(Variant::Int32(i as i32)));
+        assert_eq!(v[1].status, Some(StatusCode::Good));
+    }
+    assert_eq!(nm.inner().read_values_batches() - before, 1);
+}
+
 #[tokio::test]
 async fn read_limits() {
     let (tester, _nm, session) = setup().await;
@@ -744,6 +904,119 @@ async fn read_limits() {
         .unwrap();
 }
 
+#[tokio::test]
+async fn read_chunked() {
+    let mut server = default_server();
+    server.limits_mut().operational.max_nodes_per_read = 7;
+    let mut tester = Tester::new(server, false).await;
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    // Alternate between a node that exists and one that does not, so that we can check the order.
+    let ops: Vec<_> = (0..50)
+        .map(|i| {
+            if i % 2 == 0 {
+                read_value_id(AttributeId::Value, VariableId::Server_ServiceLevel)
+            } else {
+                read_value_id(AttributeId::Value, NodeId::new(2, i))
+            }
+        })
+        .collect();
+
+    let r = session
+        .read(&ops, TimestampsToReturn::Both, 0.0)
+        .await
+        .unwrap_err();
+    assert_eq!(r, StatusCode::BadTooManyOperations);
+
+    let r = session
+        .read_chunked(&ops, TimestampsToReturn::Both, 0.0)
+        .await
+        .unwrap();
+    assert_eq!(r.len(), 50);
+    for (i, v) in r.iter().enumerate() {
+        if i % 2 == 0 {
+            assert_eq!(v.status(), StatusCode::Good);
+            assert!(matches!(v.value, Some(Variant::Byte(_))));
+        } else {
+            assert_eq!(v.status(), StatusCode::BadNodeIdUnknown);
+        }
+    }
+
+    // Fewer nodes than the limit are read in a single request.
+    let r = session
+        .read_chunked(&ops[..3], TimestampsToReturn::Both, 0.0)
+        .await
+        .unwrap();
+    assert_eq!(r.len(), 3);
+}
+
+#[tokio::test]
+async fn read_operation_limits() {
+    let mut server = default_server();
+    let limits = &mut server.limits_mut().operational;
+    limits.max_nodes_per_read = 11;
+    limits.max_nodes_per_write = 12;
+    limits.max_nodes_per_browse = 13;
+    limits.max_monitored_items_per_call = 14;
+    let client = default_client(0, false).read_operation_limits_on_connect(true);
+    let mut tester = Tester::new_custom_client(server, client).await;
+    let (session, lp) = tester.connect_default().await.unwrap();
+
+    // Nothing is known before the session is activated.
+    assert_eq!(session.operation_limits(), OperationLimits::default());
+
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let limits = session.operation_limits();
+    assert_eq!(limits.max_nodes_per_read, 11);
+    assert_eq!(limits.max_nodes_per_write, 12);
+    assert_eq!(limits.max_nodes_per_browse, 13);
+    assert_eq!(limits.max_monitored_items_per_call, 14);
+    assert_eq!(
+        limits.max_nodes_per_method_call as usize,
+        tester
+            .handle
+            .info()
+            .config
+            .limits
+            .operational
+            .max_nodes_per_method_call
+    );
+}
+
+#[tokio::test]
+async fn read_operation_limits_lazily() {
+    let mut server = default_server();
+    server.limits_mut().operational.max_nodes_per_read = 11;
+    let client = default_client(0, false).read_operation_limits_on_connect(false);
+    let mut tester = Tester::new_custom_client(server, client).await;
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    // The limits are not read until a chunked service needs them.
+    assert_eq!(session.operation_limits(), OperationLimits::default());
+
+    let ops = vec![read_value_id(
+        AttributeId::Value,
+        VariableId::Server_ServiceLevel,
+    )];
+    session
+        .read_chunked(&ops, TimestampsToReturn::Both, 0.0)
+        .await
+        .unwrap();
+    assert_eq!(session.operation_limits().max_nodes_per_read, 11);
+}
+
 #[tokio::test]
 async fn history_read_raw() {
     let (tester, nm, session) = setup().await;
@@ -1196,3 +1469,145 @@ async fn test_diagnostics() {
     assert_eq!(diagnostics[2].value, Some(Variant::UInt32(1)));
     assert_eq!(diagnostics[3].value, Some(Variant::UInt32(0)));
 }
+
+#[tokio::test]
+async fn test_diagnostics_granularity() {
+    let server = default_server().diagnostics(DiagnosticsConfig::new(false).sessions(true));
+    let mut tester = Tester::new(server, false).await;
+    let (session, lp) = tester
+        .connect(
+            opcua_crypto::SecurityPolicy::Aes128Sha256RsaOaep,
+            opcua_types::MessageSecurityMode::SignAndEncrypt,
+            client_user_token(),
+        )
+        .await
+        .unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    // Make a request that should fail
+    session
+        .read(
+            &[read_value_id(AttributeId::DisplayName, ObjectId::Server)],
+            TimestampsToReturn::Both,
+            -15.0,
+        )
+        .await
+        .unwrap_err();
+
+    let diagnostics = session
+        .read(
+            &[ReadValueId::new_value(
+                VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_CurrentSessionCount
+                    .into(),
+            ), ReadValueId::new_value(
+                VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_RejectedRequestsCount
+                    .into(),
+            )],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(2, diagnostics.len());
+    // Session diagnostics are collected, request diagnostics are not.
+    assert_eq!(diagnostics[0].value, Some(Variant::UInt32(1)));
+    assert_eq!(diagnostics[1].value, None);
+}
+
+#[tokio::test]
+async fn test_diagnostics_snapshot_and_reset() {
+    let server = default_server().diagnostics_enabled(true);
+    let mut tester = Tester::new(server, false).await;
+    let (session, lp) = tester
+        .connect(
+            opcua_crypto::SecurityPolicy::Aes128Sha256RsaOaep,
+            opcua_types::MessageSecurityMode::SignAndEncrypt,
+            client_user_token(),
+        )
+        .await
+        .unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    // Make a request that should fail
+    session
+        .read(
+            &[read_value_id(AttributeId::DisplayName, ObjectId::Server)],
+            TimestampsToReturn::Both,
+            -15.0,
+        )
+        .await
+        .unwrap_err();
+
+    let summary = tester.handle.info().summary();
+    let snapshot = summary.snapshot_and_reset();
+    assert_eq!(snapshot.cumulated_session_count, 1);
+    assert_eq!(snapshot.current_session_count, 1);
+    assert_eq!(snapshot.rejected_requests_count, 1);
+
+    // The interval counters are reset, but current values are kept.
+    let snapshot = summary.snapshot_and_reset();
+    assert_eq!(snapshot.cumulated_session_count, 0);
+    assert_eq!(snapshot.current_session_count, 1);
+    assert_eq!(snapshot.rejected_requests_count, 0);
+
+    // The cumulative counters are unaffected.
+    let value = session
+        .read(
+            &[ReadValueId::new_value(
+                VariableId::Server_ServerDiagnostics_ServerDiagnosticsSummary_RejectedRequestsCount
+                    .into(),
+            )],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(value[0].value, Some(Variant::UInt32(1)));
+}
+
+#[tokio::test]
+async fn read_server_profiles() {
+    let server = default_server().capabilities(
+        ServerCapabilities::default()
+            .with_profile(Profile::StandardUA2017)
+            .with_profile("http://opcfoundation.org/UA-Profile/Server/DataAccess")
+            .with_profile("urn:custom:profile")
+            .with_profile(Profile::StandardUA2017),
+    );
+    let mut tester = Tester::new(server, false).await;
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let r = session
+        .read(
+            &[ReadValueId::new_value(
+                VariableId::Server_ServerCapabilities_ServerProfileArray.into(),
+            )],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        r[0].value,
+        Some(Variant::from(vec![
+            "http://opcfoundation.org/UA-Profile/Server/StandardUA2017".to_owned(),
+            "http://opcfoundation.org/UA-Profile/Server/DataAccess".to_owned(),
+            "urn:custom:profile".to_owned(),
+        ]))
+    );
+    assert_eq!(
+        tester.handle.info().capabilities.profiles[1],
+        Profile::DataAccess
+    );
+}
@@ -0,0 +1,562 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+
+use crate::utils::{default_server, ChannelNotifications, TestNodeManager, Tester};
+
+use super::utils::setup;
+use opcua::{
+    client::Session,
+    server::{
+        address_space::{AccessLevel, MethodBuilder, NodeType, ObjectBuilder, VariableBuilder},
+        authenticator::{AuthManager, UserToken},
+        node_manager::remote::RemoteNodeManagerBuilder,
+        ServerBuilder, ServerEndpoint,
+    },
+    types::{
+        AttributeId, BrowseDescription, BrowseDirection, BrowseResultMask, CallMethodRequest,
+        DataTypeId, DataValue, Error, MonitoredItemCreateRequest, MonitoringMode,
+        MonitoringParameters, NodeClass, NodeId, NumericRange, ObjectId, ObjectTypeId,
+        QualifiedName, ReadValueId, ReferenceTypeId, StatusCode, TimestampsToReturn,
+        UserTokenPolicy, VariableTypeId, Variant, WriteValue,
+    },
+};
+use tokio::time::timeout;
+
+const TEST_NAMESPACE: &str = "urn:rustopcuatestserver";
+
+/// Set up an upstream test server, and a gateway server proxying
+/// the test namespace from it.
+///
+/// The gateway also proxies a namespace that does not exist upstream, so that
+/// the test namespace gets a different index on the gateway than upstream.
+async fn setup_gateway() -> (Tester, Arc<TestNodeManager>, Tester, Arc<Session>, u16) {
+    setup_gateway_with(default_server()).await
+}
+
+async fn setup_gateway_with(
+    server: ServerBuilder,
+) -> (Tester, Arc<TestNodeManager>, Tester, Arc<Session>, u16) {
+    let (upstream, nm, upstream_session) = setup().await;
+
+    let mut gateway = Tester::new(
+        server.with_node_manager(RemoteNodeManagerBuilder::new(
+            upstream_session,
+            ["urn:gatewayonly", TEST_NAMESPACE],
+        )),
+        false,
+    )
+    .await;
+    let (session, lp) = gateway.connect_default().await.unwrap();
+    lp.spawn();
+    timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let ns = gateway
+        .handle
+        .type_tree()
+        .read()
+        .namespaces()
+        .get_index(TEST_NAMESPACE)
+        .unwrap();
+    let upstream_ns = upstream
+        .handle
+        .type_tree()
+        .read()
+        .namespaces()
+        .get_index(TEST_NAMESPACE)
+        .unwrap();
+    assert_ne!(ns, upstream_ns);
+
+    (upstream, nm, gateway, session, ns)
+}
+
+fn local_id(id: &NodeId, ns: u16) -> NodeId {
+    NodeId {
+        namespace: ns,
+        identifier: id.identifier.clone(),
+    }
+}
+
+fn add_variable(upstream: &Tester, nm: &TestNodeManager, name: &str, value: i32) -> NodeId {
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        upstream.handle.type_tree(),
+        VariableBuilder::new(&id, QualifiedName::new(id.namespace, name), name)
+            .value(value)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+            .user_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+    id
+}
+
+#[tokio::test]
+async fn remote_read_write() {
+    let (upstream, nm, _gateway, session, ns) = setup_gateway().await;
+    let id = add_variable(&upstream, &nm, "RemoteVar", 5);
+    let local = local_id(&id, ns);
+
+    let r = session
+        .read(
+            &[
+                ReadValueId::new_value(local.clone()),
+                ReadValueId::new(local.clone(), AttributeId::BrowseName),
+                ReadValueId::new(local.clone(), AttributeId::NodeId),
+            ],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].value, Some(Variant::Int32(5)));
+    let Some(Variant::QualifiedName(name)) = &r[1].value else {
+        panic!("Expected browse name");
+    };
+    assert_eq!(name.namespace_index, ns);
+    assert_eq!(name.name.as_ref(), "RemoteVar");
+    assert_eq!(r[2].value, Some(Variant::from(local.clone())));
+
+    let r = session
+        .write(&[WriteValue {
+            node_id: local.clone(),
+            attribute_id: AttributeId::Value as u32,
+            value: DataValue::new_now(7),
+            ..Default::default()
+        }])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::Good);
+
+    {
+        let sp = nm.address_space().read();
+        let NodeType::Variable(v) = sp.find(&id).unwrap() else {
+            panic!("Expected variable");
+        };
+        let value = v.value(
+            TimestampsToReturn::Neither,
+            &NumericRange::None,
+            &Default::default(),
+            0.0,
+        );
+        assert_eq!(value.value, Some(Variant::Int32(7)));
+    }
+
+    // Nodes that do not exist upstream are unknown on the gateway too.
+    let r = session
+        .read(
+            &[ReadValueId::new_value(NodeId::new(ns, "missing"))],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].status(), StatusCode::BadNodeIdUnknown);
+}
+
+#[tokio::test]
+async fn remote_browse() {
+    let (upstream, nm, _gateway, session, ns) = setup_gateway().await;
+    let folder_id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        upstream.handle.type_tree(),
+        ObjectBuilder::new(
+            &folder_id,
+            QualifiedName::new(folder_id.namespace, "RemoteFolder"),
+            "RemoteFolder",
+        )
+        .build()
+        .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&ObjectTypeId::FolderType.into()),
+        Vec::new(),
+    );
+    let mut children = Vec::new();
+    for i in 0..25 {
+        let id = nm.inner().next_node_id();
+        nm.inner().add_node(
+            nm.address_space(),
+            upstream.handle.type_tree(),
+            VariableBuilder::new(&id, format!("Var{i}"), format!("Var{i}"))
+                .data_type(DataTypeId::Int32)
+                .build()
+                .into(),
+            &folder_id,
+            &ReferenceTypeId::HasComponent.into(),
+            Some(&VariableTypeId::BaseDataVariableType.into()),
+            Vec::new(),
+        );
+        children.push(local_id(&id, ns));
+    }
+
+    // The upstream folder appears under the local objects folder.
+    let r = session
+        .browse(
+            &[BrowseDescription {
+                node_id: ObjectId::ObjectsFolder.into(),
+                browse_direction: BrowseDirection::Forward,
+                reference_type_id: ReferenceTypeId::Organizes.into(),
+                include_subtypes: true,
+                node_class_mask: 0,
+                result_mask: BrowseResultMask::All as u32,
+            }],
+            1000,
+            None,
+        )
+        .await
+        .unwrap();
+    let refs = r[0].references.clone().unwrap_or_default();
+    let folder = refs
+        .iter()
+        .find(|r| r.node_id.node_id == local_id(&folder_id, ns))
+        .unwrap();
+    assert_eq!(folder.node_class, NodeClass::Object);
+    assert_eq!(folder.browse_name.namespace_index, ns);
+    assert_eq!(folder.type_definition, ObjectTypeId::FolderType.into());
+
+    // Browse the folder in pages, using continuation points on both servers.
+    let r = session
+        .browse(
+            &[BrowseDescription {
+                node_id: local_id(&folder_id, ns),
+                browse_direction: BrowseDirection::Forward,
+                reference_type_id: ReferenceTypeId::HasComponent.into(),
+                include_subtypes: true,
+                node_class_mask: 0,
+                result_mask: BrowseResultMask::All as u32,
+            }],
+            10,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].status_code, StatusCode::Good);
+    let mut found: Vec<_> = r[0]
+        .references
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|r| r.node_id.node_id)
+        .collect();
+    assert_eq!(found.len(), 10);
+    let mut cp = r[0].continuation_point.clone();
+    while !cp.is_null() {
+        let r = session
+            .browse_next(false, std::slice::from_ref(&cp))
+            .await
+            .unwrap();
+        assert_eq!(r[0].status_code, StatusCode::Good);
+        found.extend(
+            r[0].references
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|r| r.node_id.node_id),
+        );
+        cp = r[0].continuation_point.clone();
+    }
+    found.sort_by_key(|n| n.to_string());
+    children.sort_by_key(|n| n.to_string());
+    assert_eq!(found, children);
+}
+
+#[tokio::test]
+async fn remote_call() {
+    let (_upstream, nm, _gateway, session, ns) = setup_gateway().await;
+
+    let id = nm.inner().next_node_id();
+    let input_id = nm.inner().next_node_id();
+    let output_id = nm.inner().next_node_id();
+    {
+        let mut sp = nm.address_space().write();
+        MethodBuilder::new(&id, "MethodAdd", "MethodAdd")
+            .executable(true)
+            .user_executable(true)
+            .component_of(ObjectId::ObjectsFolder)
+            .input_args(
+                &mut *sp,
+                &input_id,
+                &[
+                    ("Lhs", DataTypeId::Int64).into(),
+                    ("Rhs", DataTypeId::Int64).into(),
+                ],
+            )
+            .output_args(
+                &mut *sp,
+                &output_id,
+                &[("Result", DataTypeId::Int64).into()],
+            )
+            .insert(&mut *sp);
+    }
+    nm.inner().add_method_cb(id.clone(), |args| {
+        let (Some(Variant::Int64(lhs)), Some(Variant::Int64(rhs))) = (args.first(), args.get(1))
+        else {
+            return Err(StatusCode::BadInvalidArgument);
+        };
+        Ok(vec![Variant::Int64(lhs + rhs)])
+    });
+
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: ObjectId::ObjectsFolder.into(),
+            method_id: local_id(&id, ns),
+            input_arguments: Some(vec![Variant::Int64(3), Variant::Int64(2)]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::Good);
+    assert_eq!(r.output_arguments, Some(vec![Variant::Int64(5)]));
+}
+
+#[tokio::test]
+async fn remote_subscription() {
+    let (upstream, nm, _gateway, session, ns) = setup_gateway().await;
+    let id = add_variable(&upstream, &nm, "RemoteVar", -1);
+    let local = local_id(&id, ns);
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId::new_value(local.clone()),
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+
+    // Initial value
+    let (r, v) = timeout(Duration::from_millis(1000), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(r.node_id, local);
+    assert_eq!(v.value, Some(Variant::Int32(-1)));
+
+    // Change the value upstream, it should be published on the gateway.
+    nm.set_value(
+        upstream.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(1),
+    )
+    .unwrap();
+    let (r, v) = timeout(Duration::from_millis(1000), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(r.node_id, local);
+    assert_eq!(v.value, Some(Variant::Int32(1)));
+
+    session.delete_subscription(sub_id).await.unwrap();
+}
+
+#[tokio::test]
+async fn remote_subscription_shared_items() {
+    let (upstream, nm, _gateway, session, ns) = setup_gateway().await;
+    let id = add_variable(&upstream, &nm, "RemoteVar", -1);
+    let local = local_id(&id, ns);
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    // The first two items have the same parameters, the last has a different queue size.
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            [10, 10, 1]
+                .into_iter()
+                .enumerate()
+                .map(|(i, queue_size)| MonitoredItemCreateRequest {
+                    item_to_monitor: ReadValueId::new_value(local.clone()),
+                    monitoring_mode: MonitoringMode::Reporting,
+                    requested_parameters: MonitoringParameters {
+                        client_handle: i as u32,
+                        sampling_interval: 0.0,
+                        queue_size,
+                        discard_oldest: true,
+                        ..Default::default()
+                    },
+                })
+                .collect(),
+        )
+        .await
+        .unwrap();
+    for r in &res {
+        assert_eq!(r.result.status_code, StatusCode::Good);
+    }
+    let upstream_item_count = || {
+        upstream
+            .handle
+            .subscriptions()
+            .subscription_diagnostics()
+            .iter()
+            .map(|d| d.monitored_item_count)
+            .sum::<u32>()
+    };
+    assert_eq!(upstream_item_count(), 2);
+
+    // Initial values
+    for _ in 0..3 {
+        let (_, v) = timeout(Duration::from_millis(1000), data.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(v.value, Some(Variant::Int32(-1)));
+    }
+
+    // Each local item is notified once.
+    nm.set_value(
+        upstream.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(1),
+    )
+    .unwrap();
+    for _ in 0..3 {
+        let (_, v) = timeout(Duration::from_millis(1000), data.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(v.value, Some(Variant::Int32(1)));
+    }
+    assert!(data.try_recv().is_err());
+
+    // Deleting one of the shared items keeps the upstream item.
+    session
+        .delete_monitored_items(sub_id, &[res[0].result.monitored_item_id])
+        .await
+        .unwrap();
+    assert_eq!(upstream_item_count(), 2);
+    session
+        .delete_monitored_items(sub_id, &[res[2].result.monitored_item_id])
+        .await
+        .unwrap();
+    assert_eq!(upstream_item_count(), 1);
+
+    session.delete_subscription(sub_id).await.unwrap();
+}
+
+/// Authenticator for the gateway that only allows reading values.
+struct ReadOnlyAuthenticator;
+
+#[async_trait]
+impl AuthManager for ReadOnlyAuthenticator {
+    async fn authenticate_anonymous_token(&self, _endpoint: &ServerEndpoint) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn effective_user_access_level(
+        &self,
+        _token: &UserToken,
+        user_access_level: AccessLevel,
+        _node_id: &NodeId,
+    ) -> AccessLevel {
+        user_access_level & AccessLevel::CURRENT_READ
+    }
+
+    fn is_user_executable(&self, _token: &UserToken, _method_id: &NodeId) -> bool {
+        false
+    }
+
+    fn user_token_policies(&self, _endpoint: &ServerEndpoint) -> Vec<UserTokenPolicy> {
+        vec![UserTokenPolicy::anonymous()]
+    }
+}
+
+#[tokio::test]
+async fn remote_local_access() {
+    let (upstream, nm, _gateway, session, ns) =
+        setup_gateway_with(default_server().with_authenticator(Arc::new(ReadOnlyAuthenticator)))
+            .await;
+    let id = add_variable(&upstream, &nm, "RemoteVar", 5);
+    let local = local_id(&id, ns);
+
+    // The upstream server allows writing, but the local user may only read.
+    let r = session
+        .read(
+            &[
+                ReadValueId::new_value(local.clone()),
+                ReadValueId::new(local.clone(), AttributeId::UserAccessLevel),
+            ],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].value, Some(Variant::Int32(5)));
+    assert_eq!(
+        r[1].value,
+        Some(Variant::Byte(AccessLevel::CURRENT_READ.bits()))
+    );
+
+    let r = session
+        .write(&[WriteValue {
+            node_id: local.clone(),
+            attribute_id: AttributeId::Value as u32,
+            value: DataValue::new_now(7),
+            ..Default::default()
+        }])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::BadUserAccessDenied);
+
+    let method_id = nm.inner().next_node_id();
+    {
+        let mut sp = nm.address_space().write();
+        MethodBuilder::new(&method_id, "Method", "Method")
+            .executable(true)
+            .user_executable(true)
+            .component_of(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+    }
+    nm.inner()
+        .add_method_cb(method_id.clone(), |_| Ok(Vec::new()));
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: ObjectId::ObjectsFolder.into(),
+            method_id: local_id(&method_id, ns),
+            input_arguments: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadUserAccessDenied);
+    let r = session
+        .read(
+            &[ReadValueId::new(
+                local_id(&method_id, ns),
+                AttributeId::UserExecutable,
+            )],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].value, Some(Variant::Boolean(false)));
+}
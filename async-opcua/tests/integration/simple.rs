@@ -0,0 +1,230 @@
+use std::{
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::utils::{default_server, ChannelNotifications, Tester};
+use opcua::{
+    client::Session,
+    server::{
+        address_space::{AccessLevel, VariableBuilder},
+        diagnostics::NamespaceMetadata,
+        node_manager::{
+            memory::{simple_node_manager, SimpleNodeManager},
+            DebounceAck, DebouncedWriter,
+        },
+    },
+    types::{
+        AttributeId, DataTypeId, DataValue, MonitoredItemCreateRequest, MonitoringMode,
+        MonitoringParameters, NodeId, NumericRange, ObjectId, ReadValueId, StatusCode,
+        TimestampsToReturn, Variant, WriteValue,
+    },
+};
+use opcua_core::sync::Mutex;
+use tokio::time::timeout;
+
+const SIMPLE_NAMESPACE: &str = "urn:simpletest";
+
+/// Set up a test server with a simple node manager.
+async fn setup_simple() -> (Tester, Arc<SimpleNodeManager>, Arc<Session>, u16) {
+    let mut tester = Tester::new(
+        default_server().with_node_manager(simple_node_manager(
+            NamespaceMetadata {
+                namespace_uri: SIMPLE_NAMESPACE.to_owned(),
+                ..Default::default()
+            },
+            "simple",
+        )),
+        false,
+    )
+    .await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<SimpleNodeManager>()
+        .unwrap();
+    let ns = tester.handle.get_namespace_index(SIMPLE_NAMESPACE).unwrap();
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    (tester, nm, session, ns)
+}
+
+fn add_writable_variable(nm: &SimpleNodeManager, id: &NodeId) {
+    let mut sp = nm.address_space().write();
+    VariableBuilder::new(id, "Var", "Var")
+        .value(0i32)
+        .data_type(DataTypeId::Int32)
+        .access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+        .user_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+        .organized_by(ObjectId::ObjectsFolder)
+        .insert(&mut *sp);
+}
+
+fn write_value(id: &NodeId, value: i32) -> WriteValue {
+    WriteValue {
+        node_id: id.clone(),
+        attribute_id: AttributeId::Value as u32,
+        value: DataValue::new_now(value),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn simple_debounced_write() {
+    let (_tester, nm, session, ns) = setup_simple().await;
+    let id = NodeId::new(ns, "debounced");
+    let read_only = NodeId::new(ns, "read_only");
+    add_writable_variable(&nm, &id);
+    {
+        let mut sp = nm.address_space().write();
+        VariableBuilder::new(&read_only, "ReadOnly", "ReadOnly")
+            .value(0i32)
+            .data_type(DataTypeId::Int32)
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+    }
+
+    let flushed = Arc::new(Mutex::new(Vec::new()));
+    let flushed_ref = flushed.clone();
+    let writer = Arc::new(DebouncedWriter::new(
+        Duration::from_millis(50),
+        DebounceAck::OnFlush,
+        move |id, value, _| {
+            flushed_ref
+                .lock()
+                .push((id.clone(), value.value.unwrap_or_default()));
+            StatusCode::Good
+        },
+    ));
+    writer.run();
+    nm.inner().add_debounced_writer(id.clone(), writer.clone());
+    nm.inner()
+        .add_debounced_writer(read_only.clone(), writer.clone());
+
+    // Writes in a single request are coalesced, and acknowledged once flushed.
+    let r = session
+        .write(&[
+            write_value(&id, 1),
+            write_value(&id, 2),
+            write_value(&read_only, 3),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(
+        r,
+        vec![
+            StatusCode::Good,
+            StatusCode::Good,
+            StatusCode::BadUserAccessDenied
+        ]
+    );
+    assert_eq!(*flushed.lock(), vec![(id.clone(), Variant::Int32(2))]);
+}
+
+#[tokio::test]
+async fn simple_computed_variable() {
+    let (_tester, nm, session, ns) = setup_simple().await;
+    let id = NodeId::new(ns, "computed");
+    {
+        let mut sp = nm.address_space().write();
+        VariableBuilder::new(&id, "Computed", "Computed")
+            .value(vec![0i32; 3])
+            .data_type(DataTypeId::Int32)
+            .value_rank(1)
+            .access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+            .user_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+    }
+    // Each evaluation returns a new value, without a source timestamp.
+    let counter = Arc::new(AtomicI32::new(0));
+    let counter_ref = counter.clone();
+    nm.inner().add_computed_variable(id.clone(), move |_| {
+        let n = counter_ref.fetch_add(1, Ordering::Relaxed) + 1;
+        DataValue::value_only(vec![n, n * 10, n * 100])
+    });
+
+    // Reads evaluate the callback and apply the index range.
+    let read = |index_range: NumericRange| ReadValueId {
+        node_id: id.clone(),
+        attribute_id: AttributeId::Value as u32,
+        index_range,
+        ..Default::default()
+    };
+    let r = session
+        .read(
+            &[read(NumericRange::Range(1, 2))],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].value, Some(Variant::from(vec![10i32, 100])));
+    assert!(r[0].source_timestamp.is_some());
+    assert!(r[0].server_timestamp.is_some());
+    let r = session
+        .read(
+            &[read(NumericRange::None)],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].value, Some(Variant::from(vec![2i32, 20, 200])));
+    assert_eq!(r[0].source_timestamp, None);
+
+    // Writes are rejected, even though the variable is writable.
+    let r = session.write(&[write_value(&id, 5)]).await.unwrap();
+    assert_eq!(r, vec![StatusCode::BadNotWritable]);
+    assert_eq!(counter.load(Ordering::Relaxed), 2);
+
+    // Monitored items sample the computed value.
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: read(NumericRange::Index(0)),
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 50.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+
+    let mut last = 2;
+    for _ in 0..3 {
+        let (r, v) = timeout(Duration::from_secs(2), data.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(r.node_id, id);
+        let Some(Variant::Array(arr)) = v.value else {
+            panic!("Expected array value, got {v:?}");
+        };
+        let [Variant::Int32(n)] = arr.values[..] else {
+            panic!("Expected a single integer, got {arr:?}");
+        };
+        assert!(n > last, "Expected a new value, got {n} after {last}");
+        assert!(v.source_timestamp.is_some());
+        last = n;
+    }
+}
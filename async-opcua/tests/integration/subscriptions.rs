@@ -1,25 +1,41 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use crate::utils::{test_server, ChannelNotifications, TestNodeManager, Tester};
+use crate::utils::{client_user_token, test_server, ChannelNotifications, TestNodeManager, Tester};
 
 use super::utils::setup;
+use futures::StreamExt;
 use opcua::{
-    server::address_space::{AccessLevel, VariableBuilder},
+    server::{
+        address_space::{AccessLevel, AddressSpace, NodeType, ObjectBuilder, VariableBuilder},
+        events::Condition,
+        CertificateExpiryConfig,
+    },
     types::{
-        AttributeId, DataTypeId, DataValue, MonitoredItemCreateRequest, MonitoredItemModifyRequest,
+        AttributeId, BrowseDescription, BrowseDirection, BrowseResultMask, CallMethodRequest,
+        DataTypeId, DataValue, MethodId, MonitoredItemCreateRequest, MonitoredItemModifyRequest,
         MonitoringMode, MonitoringParameters, NodeId, ObjectId, ReadValueId, ReferenceTypeId,
         StatusCode, TimestampsToReturn, VariableTypeId, Variant,
     },
 };
 use opcua_client::{
     services::{
-        CreateMonitoredItems, CreateSubscription, Publish, Republish, TransferSubscriptions,
+        CreateMonitoredItems, CreateSubscription, ModifySubscription, Publish, Republish,
+        SetPublishingMode, TransferSubscriptions,
     },
-    IdentityToken, Subscription, UARequest,
+    IdentityToken, OnSubscriptionNotification, StreamCallback, Subscription, UARequest,
 };
+use opcua_core::sync::Mutex;
 use opcua_crypto::SecurityPolicy;
+use opcua_nodes::BaseEventType;
 use opcua_types::{
-    DataChangeFilter, DataChangeTrigger, DeadbandType, ExtensionObject, MessageSecurityMode, Range,
+    ByteString, ContentFilter, DataChangeFilter, DataChangeTrigger, DeadbandType, EventFilter,
+    ExtensionObject, Identifier, LocalizedText, MessageSecurityMode, NotificationMessage,
+    NumericRange, ObjectTypeId, Range, ServerState, SimpleAttributeOperand,
+    SubscriptionDiagnosticsDataType, VariableId, WriteValue,
 };
 use tokio::{sync::mpsc::UnboundedReceiver, time::timeout};
 
@@ -114,6 +130,208 @@ async fn simple_subscriptions() {
     session.delete_subscription(sub_id).await.unwrap();
 }
 
+#[tokio::test]
+async fn minimum_sampling_interval() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(-1)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .minimum_sampling_interval(500.0)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let (notifs, _data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let item = |sampling_interval: f64, client_handle: u32| MonitoredItemCreateRequest {
+        item_to_monitor: ReadValueId {
+            node_id: id.clone(),
+            attribute_id: AttributeId::Value as u32,
+            ..Default::default()
+        },
+        monitoring_mode: opcua::types::MonitoringMode::Reporting,
+        requested_parameters: MonitoringParameters {
+            sampling_interval,
+            client_handle,
+            queue_size: 10,
+            discard_oldest: true,
+            ..Default::default()
+        },
+    };
+
+    // Intervals faster than the minimum sampling interval of the node are revised.
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![item(100.0, 1), item(0.0, 2), item(1000.0, 3)],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.len(), 3);
+    for r in &res {
+        assert_eq!(r.result.status_code, StatusCode::Good);
+    }
+    assert_eq!(res[0].result.revised_sampling_interval, 500.0);
+    assert_eq!(res[1].result.revised_sampling_interval, 500.0);
+    assert_eq!(res[2].result.revised_sampling_interval, 1000.0);
+}
+
+#[tokio::test]
+async fn publishing_interval_bounds() {
+    let mut server = test_server();
+    let limits = &mut server.limits_mut().subscriptions;
+    limits.min_publishing_interval_ms = 200.0;
+    limits.max_publishing_interval_ms = 1000.0;
+    let mut tester = Tester::new(server, false).await;
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let opcua::types::Identifier::Numeric(session_id) = session.server_session_id().identifier
+    else {
+        panic!("Expected numeric session ID");
+    };
+    // Too small, too large, zero, and within bounds.
+    for (requested, revised) in [(50, 200), (5000, 1000), (0, 200), (500, 500)] {
+        let res = CreateSubscription::new(&session)
+            .publishing_interval(Duration::from_millis(requested))
+            .send(session.channel())
+            .await
+            .unwrap();
+        assert_eq!(res.revised_publishing_interval, revised as f64);
+
+        let sess_subs = tester
+            .handle
+            .subscriptions()
+            .get_session_subscriptions(session_id)
+            .unwrap();
+        let lck = sess_subs.lock();
+        let sub = lck.get(res.subscription_id).unwrap();
+        assert_eq!(sub.publishing_interval(), Duration::from_millis(revised));
+    }
+}
+
+#[tokio::test]
+async fn modify_subscription_interval() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(-1)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_secs(5), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: id.clone(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: opcua::types::MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+
+    // With the original interval, the initial value is not published for five seconds.
+    assert!(timeout(Duration::from_millis(300), data.recv())
+        .await
+        .is_err());
+
+    // Once the interval is shortened, the pending value is published within one cycle.
+    session
+        .modify_subscription(sub_id, Duration::from_millis(100), 100, 20, 1000, 0)
+        .await
+        .unwrap();
+    let (_, v) = timeout(Duration::from_millis(300), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(v.value, Some(Variant::Int32(-1)));
+
+    // Changes keep being published at the new interval.
+    for i in 1..4 {
+        nm.set_value(
+            tester.handle.subscriptions(),
+            &id,
+            None,
+            DataValue::new_now(i),
+        )
+        .unwrap();
+        let (_, v) = timeout(Duration::from_millis(300), data.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(v.value, Some(Variant::Int32(i)));
+    }
+
+    // The revised values are returned to the client.
+    let res = ModifySubscription::new(sub_id, &session)
+        .publishing_interval(Duration::from_millis(1))
+        .max_lifetime_count(1)
+        .max_keep_alive_count(0)
+        .send(session.channel())
+        .await
+        .unwrap();
+    let limits = &tester.handle.info().config.limits.subscriptions;
+    assert_eq!(
+        res.revised_publishing_interval,
+        limits.min_publishing_interval_ms
+    );
+    assert_eq!(
+        res.revised_max_keep_alive_count,
+        limits.default_keep_alive_count
+    );
+    assert_eq!(
+        res.revised_lifetime_count,
+        limits.default_keep_alive_count * 3
+    );
+}
+
 async fn recv_n<T>(recv: &mut UnboundedReceiver<T>, n: usize) -> Vec<T> {
     let mut res = Vec::with_capacity(n);
     for _ in 0..n {
@@ -278,7 +496,7 @@ async fn modify_subscription() {
         let item = sub.get(&monitored_item_id).unwrap();
         assert_eq!(id, item.item_to_monitor().node_id);
         assert_eq!(MonitoringMode::Reporting, item.monitoring_mode());
-        assert_eq!(100.0, item.sampling_interval());
+        assert_eq!(0.0, item.sampling_interval());
         assert_eq!(10, item.queue_size());
         assert!(item.discard_oldest());
     }
@@ -797,93 +1015,2450 @@ async fn test_data_change_filters() {
 }
 
 #[tokio::test]
-async fn test_manual_republish() {
+async fn data_change_filter_non_numeric() {
     let (tester, nm, session) = setup().await;
 
-    let id = nm.inner().next_node_id();
-    nm.inner().add_node(
-        nm.address_space(),
-        tester.handle.type_tree(),
-        VariableBuilder::new(&id, "TestVar1", "TestVar1")
-            .value(-1)
-            .data_type(DataTypeId::Int32)
-            .access_level(AccessLevel::CURRENT_READ)
-            .user_access_level(AccessLevel::CURRENT_READ)
-            .build()
-            .into(),
-        &ObjectId::ObjectsFolder.into(),
-        &ReferenceTypeId::Organizes.into(),
-        Some(&VariableTypeId::BaseDataVariableType.into()),
-        Vec::new(),
-    );
+    let initial_values: [(Variant, DataTypeId); 3] = [
+        ("foo".into(), DataTypeId::String),
+        (
+            ByteString::from(vec![1u8, 2]).into(),
+            DataTypeId::ByteString,
+        ),
+        (
+            LocalizedText::new("en", "foo").into(),
+            DataTypeId::LocalizedText,
+        ),
+    ];
+    let mut ids = Vec::new();
+    for (idx, (value, data_type)) in initial_values.into_iter().enumerate() {
+        let id = nm.inner().next_node_id();
+        let name = format!("TestVar{idx}");
+        nm.inner().add_node(
+            nm.address_space(),
+            tester.handle.type_tree(),
+            VariableBuilder::new(&id, &name, &name)
+                .value(value)
+                .data_type(data_type)
+                .access_level(AccessLevel::CURRENT_READ)
+                .user_access_level(AccessLevel::CURRENT_READ)
+                .build()
+                .into(),
+            &ObjectId::ObjectsFolder.into(),
+            &ReferenceTypeId::Organizes.into(),
+            Some(&VariableTypeId::BaseDataVariableType.into()),
+            Vec::new(),
+        );
+        ids.push(id);
+    }
 
-    // Create a subscription
-    let res = CreateSubscription::new(&session)
-        .publishing_interval(Duration::from_millis(100))
-        .max_lifetime_count(100)
-        .max_keep_alive_count(20)
-        .max_notifications_per_publish(1000)
-        .priority(0)
-        .publishing_enabled(true)
-        .send(session.channel())
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
         .await
         .unwrap();
-    let sub_id = res.subscription_id;
 
-    let res = CreateMonitoredItems::new(sub_id, &session)
-        .item(MonitoredItemCreateRequest {
-            item_to_monitor: ReadValueId {
-                node_id: id.clone(),
-                attribute_id: AttributeId::Value as u32,
-                ..Default::default()
-            },
-            monitoring_mode: opcua::types::MonitoringMode::Reporting,
-            requested_parameters: MonitoringParameters {
-                sampling_interval: 0.0,
-                queue_size: 10,
-                discard_oldest: true,
-                ..Default::default()
-            },
-        })
-        .timestamps_to_return(TimestampsToReturn::Both)
-        .send(session.channel())
+    let item = |id: &NodeId, deadband_type: DeadbandType| MonitoredItemCreateRequest {
+        item_to_monitor: ReadValueId {
+            node_id: id.clone(),
+            attribute_id: AttributeId::Value as u32,
+            ..Default::default()
+        },
+        monitoring_mode: opcua::types::MonitoringMode::Reporting,
+        requested_parameters: MonitoringParameters {
+            sampling_interval: 0.0,
+            queue_size: 10,
+            discard_oldest: true,
+            filter: ExtensionObject::from_message(DataChangeFilter {
+                trigger: DataChangeTrigger::StatusValue,
+                deadband_type: deadband_type as u32,
+                deadband_value: 1.0,
+            }),
+            ..Default::default()
+        },
+    };
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![
+                item(&ids[0], DeadbandType::None),
+                item(&ids[1], DeadbandType::None),
+                item(&ids[2], DeadbandType::None),
+                // Deadbands are not allowed on non-numeric values.
+                item(&ids[0], DeadbandType::Absolute),
+                item(&ids[1], DeadbandType::Absolute),
+            ],
+        )
         .await
         .unwrap();
+    assert_eq!(res.len(), 5);
+    for it in &res[..3] {
+        assert_eq!(it.result.status_code, StatusCode::Good);
+    }
+    for it in &res[3..] {
+        assert_eq!(it.result.status_code, StatusCode::BadFilterNotAllowed);
+    }
 
-    assert_eq!(res.results.len(), 1);
-    let it = &res.results[0];
-    assert_eq!(it.result.status_code, StatusCode::Good);
+    // Receive the initial values.
+    for _ in 0..3 {
+        timeout(Duration::from_millis(500), data.recv())
+            .await
+            .unwrap()
+            .unwrap();
+    }
 
-    // Send a publish request, this should return a notification.
-    let pubres = Publish::new(&session)
-        .timeout(Duration::from_millis(500))
-        .send(session.channel())
-        .await
+    let updates: [(usize, Variant); 6] = [
+        // Setting the same value does not trigger a notification.
+        (0, "foo".into()),
+        (0, "bar".into()),
+        (1, ByteString::from(vec![1u8, 2]).into()),
+        (1, ByteString::from(vec![1u8, 3]).into()),
+        (2, LocalizedText::new("en", "bar").into()),
+        // A change to only the locale is also a change.
+        (2, LocalizedText::new("de", "bar").into()),
+    ];
+    for (idx, value) in updates {
+        nm.set_value(
+            tester.handle.subscriptions(),
+            &ids[idx],
+            None,
+            DataValue::new_now(value),
+        )
         .unwrap();
+    }
 
-    assert_eq!(pubres.subscription_id, sub_id);
-    let sequence_number = pubres.notification_message.sequence_number;
-    let notifs = pubres.notification_message.into_notifications().unwrap().0;
-    assert_eq!(notifs.len(), 1);
-    let notif = &notifs[0];
-    let items = notif.monitored_items.as_ref().unwrap();
-    assert_eq!(items.len(), 1);
-    let value = items[0].value.value.as_ref().unwrap();
-    assert_eq!(value, &Variant::Int32(-1));
+    let expected: [(usize, Variant); 4] = [
+        (0, "bar".into()),
+        (1, ByteString::from(vec![1u8, 3]).into()),
+        (2, LocalizedText::new("en", "bar").into()),
+        (2, LocalizedText::new("de", "bar").into()),
+    ];
+    let mut received = Vec::new();
+    for _ in 0..expected.len() {
+        let (r, v) = timeout(Duration::from_millis(500), data.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        received.push((r.node_id, v.value.unwrap()));
+    }
+    for (idx, value) in expected {
+        assert!(received.contains(&(ids[idx].clone(), value)));
+    }
+    // No other notifications were sent.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(data.try_recv().is_err());
 
-    // Then, request a re-publish of the same notification.
-    let res = Republish::new(sub_id, sequence_number, &session)
-        .timeout(Duration::from_millis(500))
-        .send(session.channel())
+    // Deadbands cannot be added to an existing item either.
+    let modify = session
+        .modify_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            &[MonitoredItemModifyRequest {
+                monitored_item_id: res[0].result.monitored_item_id,
+                requested_parameters: item(&ids[0], DeadbandType::Absolute).requested_parameters,
+            }],
+        )
         .await
         .unwrap();
-    let notifs = res.notification_message.into_notifications().unwrap().0;
-    assert_eq!(notifs.len(), 1);
-    let notif = &notifs[0];
-    let items = notif.monitored_items.as_ref().unwrap();
-    assert_eq!(items.len(), 1);
-    let value = items[0].value.value.as_ref().unwrap();
-    assert_eq!(value, &Variant::Int32(-1));
+    assert_eq!(modify[0].status_code, StatusCode::BadFilterNotAllowed);
+
+    // The item is unchanged, and still reports changes.
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &ids[0],
+        None,
+        DataValue::new_now("baz"),
+    )
+    .unwrap();
+    let (r, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(r.node_id, ids[0]);
+    assert_eq!(v.value.unwrap(), Variant::from("baz"));
 }
 
-// TODO: Add more detailed high level tests on subscriptions.
+#[tokio::test]
+async fn percent_deadband_analog_item() {
+    let (tester, nm, session) = setup().await;
+
+    // Variables built with an EURange work with percent deadband without any extra setup.
+    let id = nm.inner().next_node_id();
+    {
+        let mut sp = nm.address_space().write();
+        VariableBuilder::new(&id, "AnalogVar", "AnalogVar")
+            .value(0.0f64)
+            .data_type(DataTypeId::Double)
+            .eu_range(0.0, 10.0)
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+    }
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    // Create the item without a filter, then switch to a percent deadband.
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId::new_value(id.clone()),
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    client_handle: 1,
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+    let monitored_item_id = res[0].result.monitored_item_id;
+
+    let (_, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(v.value.unwrap(), Variant::Double(0.0));
+
+    let res = session
+        .modify_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            &[MonitoredItemModifyRequest {
+                monitored_item_id,
+                requested_parameters: MonitoringParameters {
+                    client_handle: 1,
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    filter: ExtensionObject::from_message(DataChangeFilter {
+                        trigger: DataChangeTrigger::StatusValue,
+                        deadband_type: DeadbandType::Percent as u32,
+                        // 20% of the range from 0 to 10 is a change of 2.
+                        deadband_value: 20.0,
+                    }),
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].status_code, StatusCode::Good);
+
+    // Within the deadband.
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(1.0),
+    )
+    .unwrap();
+    // Outside the deadband.
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(3.0),
+    )
+    .unwrap();
+
+    let (r, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(r.node_id, id);
+    assert_eq!(v.value.unwrap(), Variant::Double(3.0));
+}
+
+#[tokio::test]
+async fn namespace_array_notification() {
+    let (_tester, nm, session) = setup().await;
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest::new(
+                ReadValueId::new_value(VariableId::Server_NamespaceArray.into()),
+                MonitoringMode::Reporting,
+                MonitoringParameters::default(),
+            )],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+
+    let (_, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    let namespaces: Vec<String> = v.value.unwrap().try_cast_to().unwrap();
+    assert!(!namespaces.contains(&"urn:late".to_owned()));
+
+    // Register a namespace after the server has started.
+    nm.inner()
+        .add_late_namespace(nm.address_space(), "urn:late");
+
+    let (_, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    let new_namespaces: Vec<String> = v.value.unwrap().try_cast_to().unwrap();
+    assert_eq!(new_namespaces.len(), namespaces.len() + 1);
+    assert_eq!(new_namespaces.last().unwrap(), "urn:late");
+}
+
+#[tokio::test]
+async fn resend_monitored_item_data() {
+    let (tester, nm, session) = setup().await;
+
+    let ids: Vec<_> = (0..2).map(|_| nm.inner().next_node_id()).collect();
+    for (idx, id) in ids.iter().enumerate() {
+        nm.inner().add_node(
+            nm.address_space(),
+            tester.handle.type_tree(),
+            VariableBuilder::new(id, format!("TestVar{idx}"), format!("TestVar{idx}"))
+                .value(idx as i32)
+                .data_type(DataTypeId::Int32)
+                .access_level(AccessLevel::CURRENT_READ)
+                .user_access_level(AccessLevel::CURRENT_READ)
+                .build()
+                .into(),
+            &ObjectId::ObjectsFolder.into(),
+            &ReferenceTypeId::Organizes.into(),
+            Some(&VariableTypeId::BaseDataVariableType.into()),
+            Vec::new(),
+        );
+    }
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            ids.iter()
+                .enumerate()
+                .map(|(idx, id)| MonitoredItemCreateRequest {
+                    item_to_monitor: ReadValueId::new_value(id.clone()),
+                    monitoring_mode: MonitoringMode::Reporting,
+                    requested_parameters: MonitoringParameters {
+                        sampling_interval: 0.0,
+                        queue_size: 10,
+                        discard_oldest: true,
+                        client_handle: idx as u32 + 1,
+                        ..Default::default()
+                    },
+                })
+                .collect(),
+        )
+        .await
+        .unwrap();
+    assert!(res.iter().all(|r| r.result.status_code == StatusCode::Good));
+
+    // Initial values for both items.
+    for _ in 0..2 {
+        timeout(Duration::from_millis(500), data.recv())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    // The vendor method is a component of the server object, in the server namespace.
+    let r = session
+        .browse(
+            &[BrowseDescription {
+                node_id: ObjectId::Server.into(),
+                browse_direction: BrowseDirection::Forward,
+                reference_type_id: ReferenceTypeId::HasComponent.into(),
+                include_subtypes: true,
+                node_class_mask: 0,
+                result_mask: BrowseResultMask::All as u32,
+            }],
+            1000,
+            None,
+        )
+        .await
+        .unwrap();
+    let method_id = r[0]
+        .references
+        .as_ref()
+        .unwrap()
+        .iter()
+        .find(|r| r.browse_name.name.as_ref() == "ResendMonitoredItemData")
+        .unwrap()
+        .node_id
+        .node_id
+        .clone();
+    assert_ne!(method_id.namespace, 0);
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: ObjectId::Server.into(),
+            method_id: method_id.clone(),
+            input_arguments: Some(vec![sub_id.into(), 2u32.into()]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::Good);
+
+    // Only the second item is resent.
+    let (r, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(r.node_id, ids[1]);
+    assert_eq!(v.value, Some(Variant::Int32(1)));
+    assert!(timeout(Duration::from_millis(300), data.recv())
+        .await
+        .is_err());
+
+    let r = session
+        .call_one(CallMethodRequest {
+            object_id: ObjectId::Server.into(),
+            method_id,
+            input_arguments: Some(vec![sub_id.into(), 5u32.into()]),
+        })
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadMonitoredItemIdInvalid);
+}
+
+#[tokio::test]
+async fn semantics_changed() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    let eu_range_id = match &id.identifier {
+        Identifier::Numeric(n) => NodeId::new(id.namespace, format!("{n}_EURange")),
+        _ => unreachable!(),
+    };
+    {
+        let mut sp = nm.address_space().write();
+        VariableBuilder::new(&id, "AnalogVar", "AnalogVar")
+            .value(5.0f64)
+            .data_type(DataTypeId::Double)
+            .eu_range(0.0, 10.0)
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+        let Some(NodeType::Variable(v)) = sp.find_mut(&eu_range_id) else {
+            panic!("Missing EURange property");
+        };
+        v.set_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE);
+        v.set_user_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE);
+    }
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest::new(
+                ReadValueId::new_value(id.clone()),
+                MonitoringMode::Reporting,
+                MonitoringParameters {
+                    client_handle: 1,
+                    filter: ExtensionObject::from_message(DataChangeFilter {
+                        trigger: DataChangeTrigger::StatusValue,
+                        deadband_type: DeadbandType::Percent as u32,
+                        // 10% of the range from 0 to 10 is a change of 1.
+                        deadband_value: 10.0,
+                    }),
+                    ..Default::default()
+                },
+            )],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+
+    let (_, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!v.status().semantics_changed());
+
+    // Changing the EURange changes the semantics of the value.
+    let r = session
+        .write(&[WriteValue {
+            node_id: eu_range_id,
+            attribute_id: AttributeId::Value as u32,
+            value: DataValue::new_now(ExtensionObject::from_message(Range {
+                low: 0.0,
+                high: 100.0,
+            })),
+            ..Default::default()
+        }])
+        .await
+        .unwrap();
+    assert_eq!(r[0], StatusCode::Good);
+
+    // The value is not reported again, the bit is set on the next value instead.
+    // The deadband is now 10% of the range from 0 to 100, so a change of 3 is ignored.
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(8.0f64),
+    )
+    .unwrap();
+    assert!(timeout(Duration::from_millis(300), data.recv())
+        .await
+        .is_err());
+
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(20.0f64),
+    )
+    .unwrap();
+    let (r, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(r.node_id, id);
+    assert_eq!(v.value, Some(Variant::Double(20.0)));
+    assert!(v.status().semantics_changed());
+
+    // The bit is only set once.
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(40.0f64),
+    )
+    .unwrap();
+    let (_, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(v.value, Some(Variant::Double(40.0)));
+    assert!(!v.status().semantics_changed());
+}
+
+#[tokio::test]
+async fn event_source() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    {
+        let mut sp = nm.address_space().write();
+        ObjectBuilder::new(&id, "EventSource", "EventSource")
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+    }
+
+    let (notifs, _, mut events) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let event_item = |node_id: NodeId, client_handle: u32| MonitoredItemCreateRequest {
+        item_to_monitor: ReadValueId {
+            node_id,
+            attribute_id: AttributeId::EventNotifier as u32,
+            ..Default::default()
+        },
+        monitoring_mode: MonitoringMode::Reporting,
+        requested_parameters: MonitoringParameters {
+            client_handle,
+            queue_size: 10,
+            filter: ExtensionObject::from_message(EventFilter {
+                select_clauses: Some(vec![SimpleAttributeOperand::new(
+                    ObjectTypeId::BaseEventType,
+                    "Message",
+                    AttributeId::Value,
+                    NumericRange::None,
+                )]),
+                where_clause: ContentFilter::default(),
+            }),
+            ..Default::default()
+        },
+    };
+
+    // Events are not enabled on the node yet.
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![event_item(id.clone(), 1)],
+        )
+        .await
+        .unwrap();
+    assert_ne!(res[0].result.status_code, StatusCode::Good);
+    let event = BaseEventType::new_now(
+        ObjectTypeId::BaseEventType,
+        ByteString::from(vec![1u8, 2, 3]),
+        "Hello",
+    )
+    .set_source_node(id.clone());
+    assert_eq!(
+        nm.emit_event(tester.handle.subscriptions(), &id, &event),
+        Err(StatusCode::BadNotSupported)
+    );
+
+    nm.enable_events(tester.handle.subscriptions(), &id)
+        .unwrap();
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![
+                event_item(id.clone(), 2),
+                event_item(ObjectId::Server.into(), 3),
+            ],
+        )
+        .await
+        .unwrap();
+    assert!(res.iter().all(|r| r.result.status_code == StatusCode::Good));
+
+    nm.emit_event(tester.handle.subscriptions(), &id, &event)
+        .unwrap();
+
+    // The event is reported on the source and on the server.
+    let mut sources = Vec::new();
+    for _ in 0..2 {
+        let (r, fields) = timeout(Duration::from_millis(500), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            fields,
+            Some(vec![Variant::from(LocalizedText::from("Hello"))])
+        );
+        sources.push(r.node_id);
+    }
+    assert!(sources.contains(&id));
+    assert!(sources.contains(&ObjectId::Server.into()));
+}
+
+#[tokio::test]
+async fn test_manual_republish() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(-1)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    // Create a subscription
+    let res = CreateSubscription::new(&session)
+        .publishing_interval(Duration::from_millis(100))
+        .max_lifetime_count(100)
+        .max_keep_alive_count(20)
+        .max_notifications_per_publish(1000)
+        .priority(0)
+        .publishing_enabled(true)
+        .send(session.channel())
+        .await
+        .unwrap();
+    let sub_id = res.subscription_id;
+
+    let res = CreateMonitoredItems::new(sub_id, &session)
+        .item(MonitoredItemCreateRequest {
+            item_to_monitor: ReadValueId {
+                node_id: id.clone(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            },
+            monitoring_mode: opcua::types::MonitoringMode::Reporting,
+            requested_parameters: MonitoringParameters {
+                sampling_interval: 0.0,
+                queue_size: 10,
+                discard_oldest: true,
+                ..Default::default()
+            },
+        })
+        .timestamps_to_return(TimestampsToReturn::Both)
+        .send(session.channel())
+        .await
+        .unwrap();
+
+    assert_eq!(res.results.len(), 1);
+    let it = &res.results[0];
+    assert_eq!(it.result.status_code, StatusCode::Good);
+
+    // Send a publish request, this should return a notification.
+    let pubres = Publish::new(&session)
+        .timeout(Duration::from_millis(500))
+        .send(session.channel())
+        .await
+        .unwrap();
+
+    assert_eq!(pubres.subscription_id, sub_id);
+    let sequence_number = pubres.notification_message.sequence_number;
+    let notifs = pubres.notification_message.into_notifications().unwrap().0;
+    assert_eq!(notifs.len(), 1);
+    let notif = &notifs[0];
+    let items = notif.monitored_items.as_ref().unwrap();
+    assert_eq!(items.len(), 1);
+    let value = items[0].value.value.as_ref().unwrap();
+    assert_eq!(value, &Variant::Int32(-1));
+
+    // Then, request a re-publish of the same notification.
+    let res = Republish::new(sub_id, sequence_number, &session)
+        .timeout(Duration::from_millis(500))
+        .send(session.channel())
+        .await
+        .unwrap();
+    let notifs = res.notification_message.into_notifications().unwrap().0;
+    assert_eq!(notifs.len(), 1);
+    let notif = &notifs[0];
+    let items = notif.monitored_items.as_ref().unwrap();
+    assert_eq!(items.len(), 1);
+    let value = items[0].value.value.as_ref().unwrap();
+    assert_eq!(value, &Variant::Int32(-1));
+}
+
+#[tokio::test]
+async fn republish_from_retransmission_queue() {
+    let mut server = test_server().diagnostics_enabled(true);
+    server
+        .limits_mut()
+        .subscriptions
+        .max_retained_notifications_per_subscription = 2;
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<TestNodeManager>()
+        .unwrap();
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(0)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let res = CreateSubscription::new(&session)
+        .publishing_interval(Duration::from_millis(100))
+        .max_lifetime_count(100)
+        .max_keep_alive_count(20)
+        .publishing_enabled(true)
+        .send(session.channel())
+        .await
+        .unwrap();
+    let sub_id = res.subscription_id;
+
+    let res = CreateMonitoredItems::new(sub_id, &session)
+        .item(MonitoredItemCreateRequest {
+            item_to_monitor: ReadValueId {
+                node_id: id.clone(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            },
+            monitoring_mode: opcua::types::MonitoringMode::Reporting,
+            requested_parameters: MonitoringParameters {
+                sampling_interval: 0.0,
+                queue_size: 10,
+                discard_oldest: true,
+                ..Default::default()
+            },
+        })
+        .timestamps_to_return(TimestampsToReturn::Both)
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert_eq!(res.results[0].result.status_code, StatusCode::Good);
+
+    fn value_of(msg: NotificationMessage) -> Variant {
+        let notifs = msg.into_notifications().unwrap().0;
+        let items = notifs[0].monitored_items.as_ref().unwrap();
+        items[0].value.value.clone().unwrap()
+    }
+
+    // Receive three notification messages without acknowledging any of them.
+    let mut sequence_numbers = Vec::new();
+    for i in 0..3 {
+        if i > 0 {
+            nm.set_value(
+                tester.handle.subscriptions(),
+                &id,
+                None,
+                DataValue::new_now(i),
+            )
+            .unwrap();
+        }
+        let res = Publish::new(&session)
+            .timeout(Duration::from_millis(1000))
+            .send(session.channel())
+            .await
+            .unwrap();
+        assert_eq!(res.subscription_id, sub_id);
+        let sequence_number = res.notification_message.sequence_number;
+        assert_eq!(value_of(res.notification_message), Variant::Int32(i));
+        sequence_numbers.push(sequence_number);
+    }
+
+    // Only the two most recent messages are retained.
+    let res = Republish::new(sub_id, sequence_numbers[0], &session)
+        .send(session.channel())
+        .await;
+    assert_eq!(res.unwrap_err(), StatusCode::BadMessageNotAvailable);
+    for (i, sequence_number) in sequence_numbers.iter().enumerate().skip(1) {
+        let res = Republish::new(sub_id, *sequence_number, &session)
+            .send(session.channel())
+            .await
+            .unwrap();
+        assert_eq!(res.notification_message.sequence_number, *sequence_number);
+        assert_eq!(value_of(res.notification_message), Variant::Int32(i as i32));
+    }
+
+    // The dropped message is counted on the next subscription tick.
+    let diagnostics = &tester.handle.info().diagnostics;
+    let start = Instant::now();
+    while diagnostics.dropped_retained_message_count() < 1 {
+        assert!(start.elapsed() < Duration::from_secs(2));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(diagnostics.dropped_retained_message_count(), 1);
+
+    // Acknowledging a message removes it from the retransmission queue.
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(3),
+    )
+    .unwrap();
+    let res = Publish::new(&session)
+        .ack(sub_id, sequence_numbers[1])
+        .timeout(Duration::from_millis(1000))
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert_eq!(res.results, Some(vec![StatusCode::Good]));
+    let last_sequence_number = res.notification_message.sequence_number;
+    assert_eq!(
+        res.available_sequence_numbers,
+        Some(vec![sequence_numbers[2]])
+    );
+
+    let res = Republish::new(sub_id, sequence_numbers[1], &session)
+        .send(session.channel())
+        .await;
+    assert_eq!(res.unwrap_err(), StatusCode::BadMessageNotAvailable);
+    let res = Republish::new(sub_id, last_sequence_number, &session)
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert_eq!(value_of(res.notification_message), Variant::Int32(3));
+}
+
+#[tokio::test]
+async fn publish_acknowledgement_results() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(0)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let res = CreateSubscription::new(&session)
+        .publishing_interval(Duration::from_millis(100))
+        .max_lifetime_count(100)
+        .max_keep_alive_count(20)
+        .publishing_enabled(true)
+        .send(session.channel())
+        .await
+        .unwrap();
+    let sub_id = res.subscription_id;
+
+    let res = CreateMonitoredItems::new(sub_id, &session)
+        .item(MonitoredItemCreateRequest {
+            item_to_monitor: ReadValueId {
+                node_id: id.clone(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            },
+            monitoring_mode: opcua::types::MonitoringMode::Reporting,
+            requested_parameters: MonitoringParameters {
+                sampling_interval: 0.0,
+                queue_size: 10,
+                discard_oldest: true,
+                ..Default::default()
+            },
+        })
+        .timestamps_to_return(TimestampsToReturn::Both)
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert_eq!(res.results[0].result.status_code, StatusCode::Good);
+
+    // Receive two notification messages without acknowledging them.
+    let mut sequence_numbers = Vec::new();
+    for i in 0..2 {
+        if i > 0 {
+            nm.set_value(
+                tester.handle.subscriptions(),
+                &id,
+                None,
+                DataValue::new_now(i),
+            )
+            .unwrap();
+        }
+        let res = Publish::new(&session)
+            .timeout(Duration::from_millis(1000))
+            .send(session.channel())
+            .await
+            .unwrap();
+        assert!(res.results.is_none());
+        sequence_numbers.push(res.notification_message.sequence_number);
+    }
+
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(2),
+    )
+    .unwrap();
+    let res = Publish::new(&session)
+        .ack(sub_id, sequence_numbers[1])
+        .ack(sub_id, sequence_numbers[1] + 100)
+        .ack(sub_id + 100, sequence_numbers[0])
+        .ack(sub_id, sequence_numbers[1])
+        .timeout(Duration::from_millis(1000))
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert_eq!(
+        res.results,
+        Some(vec![
+            StatusCode::Good,
+            StatusCode::BadSequenceNumberUnknown,
+            StatusCode::BadSubscriptionIdInvalid,
+            StatusCode::BadSequenceNumberUnknown,
+        ])
+    );
+    // Only the acknowledged message is removed from the retransmission queue.
+    assert_eq!(
+        res.available_sequence_numbers,
+        Some(vec![sequence_numbers[0]])
+    );
+    let res = Republish::new(sub_id, sequence_numbers[1], &session)
+        .send(session.channel())
+        .await;
+    assert_eq!(res.unwrap_err(), StatusCode::BadMessageNotAvailable);
+    let res = Republish::new(sub_id, sequence_numbers[0], &session)
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert_eq!(
+        res.notification_message.sequence_number,
+        sequence_numbers[0]
+    );
+}
+
+// TODO: Add more detailed high level tests on subscriptions.
+
+#[tokio::test]
+async fn publish_prioritizes_data() {
+    let mut server = test_server();
+    server
+        .limits_mut()
+        .subscriptions
+        .max_subscriptions_per_session = 100;
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<TestNodeManager>()
+        .unwrap();
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(-1)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    // Create 50 subscriptions, only one of which will have any data. The others
+    // have a mix of higher and lower priorities.
+    let mut sub_ids = Vec::new();
+    for i in 0..50 {
+        let res = CreateSubscription::new(&session)
+            .publishing_interval(Duration::from_millis(50))
+            .max_lifetime_count(100)
+            .max_keep_alive_count(1)
+            .max_notifications_per_publish(1000)
+            .priority(match i {
+                37 => 100,
+                i if i % 2 == 0 => 0,
+                _ => 200,
+            })
+            .publishing_enabled(true)
+            .send(session.channel())
+            .await
+            .unwrap();
+        sub_ids.push(res.subscription_id);
+    }
+    let data_sub_id = sub_ids[37];
+
+    let res = CreateMonitoredItems::new(data_sub_id, &session)
+        .item(MonitoredItemCreateRequest {
+            item_to_monitor: ReadValueId {
+                node_id: id.clone(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            },
+            monitoring_mode: MonitoringMode::Reporting,
+            requested_parameters: MonitoringParameters {
+                sampling_interval: 0.0,
+                queue_size: 10,
+                discard_oldest: true,
+                ..Default::default()
+            },
+        })
+        .timestamps_to_return(TimestampsToReturn::Both)
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert_eq!(res.results[0].result.status_code, StatusCode::Good);
+
+    // Let all subscriptions become late, so that they are all waiting for a publish request.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // The first publish request should go to the subscription with data, even though
+    // all the others are ready to send keep-alives, and some have higher priority.
+    let pubres = Publish::new(&session)
+        .timeout(Duration::from_millis(500))
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert_eq!(pubres.subscription_id, data_sub_id);
+    let notifs = pubres.notification_message.into_notifications().unwrap().0;
+    let items = notifs[0].monitored_items.as_ref().unwrap();
+    assert_eq!(items[0].value.value, Some(Variant::Int32(-1)));
+
+    // Change the value again while the other subscriptions are still waiting.
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(1),
+    )
+    .unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let pubres = Publish::new(&session)
+        .timeout(Duration::from_millis(500))
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert_eq!(pubres.subscription_id, data_sub_id);
+    let notifs = pubres.notification_message.into_notifications().unwrap().0;
+    let items = notifs[0].monitored_items.as_ref().unwrap();
+    assert_eq!(items[0].value.value, Some(Variant::Int32(1)));
+
+    // The remaining publish requests are spent on keep-alives, highest priority first.
+    for _ in 0..3 {
+        let pubres = Publish::new(&session)
+            .timeout(Duration::from_millis(500))
+            .send(session.channel())
+            .await
+            .unwrap();
+        let idx = sub_ids
+            .iter()
+            .position(|s| *s == pubres.subscription_id)
+            .unwrap();
+        assert_eq!(idx % 2, 1);
+        assert!(pubres.notification_message.notification_data.is_none());
+    }
+}
+
+#[tokio::test]
+async fn set_publishing_mode() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(-1)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let mut sub_ids = Vec::new();
+    for _ in 0..2 {
+        let res = CreateSubscription::new(&session)
+            .publishing_interval(Duration::from_millis(100))
+            .max_lifetime_count(100)
+            .max_keep_alive_count(1)
+            .publishing_enabled(true)
+            .send(session.channel())
+            .await
+            .unwrap();
+        sub_ids.push(res.subscription_id);
+    }
+    let data_sub_id = sub_ids[0];
+
+    let res = CreateMonitoredItems::new(data_sub_id, &session)
+        .item(MonitoredItemCreateRequest {
+            item_to_monitor: ReadValueId {
+                node_id: id.clone(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            },
+            monitoring_mode: MonitoringMode::Reporting,
+            requested_parameters: MonitoringParameters {
+                sampling_interval: 0.0,
+                queue_size: 10,
+                discard_oldest: true,
+                ..Default::default()
+            },
+        })
+        .timestamps_to_return(TimestampsToReturn::Both)
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert_eq!(res.results[0].result.status_code, StatusCode::Good);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let publish = || async {
+        Publish::new(&session)
+            .timeout(Duration::from_millis(500))
+            .send(session.channel())
+            .await
+            .unwrap()
+    };
+    let pubres = publish().await;
+    assert_eq!(pubres.subscription_id, data_sub_id);
+    assert!(pubres.notification_message.notification_data.is_some());
+
+    // Disable publishing on both subscriptions, results are returned per subscription.
+    let res = SetPublishingMode::new(false, &session)
+        .subscription_ids(vec![sub_ids[0], sub_ids[1], 12345])
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert_eq!(
+        res.results.unwrap(),
+        vec![
+            StatusCode::Good,
+            StatusCode::Good,
+            StatusCode::BadSubscriptionIdInvalid
+        ]
+    );
+
+    // Changes are not published, but both subscriptions keep sending keep-alives.
+    nm.set_value(
+        tester.handle.subscriptions(),
+        &id,
+        None,
+        DataValue::new_now(1),
+    )
+    .unwrap();
+    let mut keep_alive_subs = Vec::new();
+    for _ in 0..6 {
+        let pubres = publish().await;
+        assert!(pubres.notification_message.notification_data.is_none());
+        keep_alive_subs.push(pubres.subscription_id);
+    }
+    assert!(keep_alive_subs.contains(&sub_ids[0]));
+    assert!(keep_alive_subs.contains(&sub_ids[1]));
+
+    // Once publishing is enabled again, the suppressed change is published.
+    let res = SetPublishingMode::new(true, &session)
+        .subscription(data_sub_id)
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert_eq!(res.results.unwrap(), vec![StatusCode::Good]);
+    let mut value = None;
+    for _ in 0..6 {
+        let pubres = publish().await;
+        if let Some((notifs, _)) = pubres.notification_message.into_notifications() {
+            assert_eq!(pubres.subscription_id, data_sub_id);
+            let items = notifs[0].monitored_items.as_ref().unwrap();
+            value = items.last().unwrap().value.value.clone();
+            break;
+        }
+    }
+    assert_eq!(value, Some(Variant::Int32(1)));
+}
+
+#[tokio::test]
+async fn sampler_runtime() {
+    let sampler_rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+        .unwrap();
+    let server = test_server().sampler_runtime(sampler_rt.handle().clone());
+    let mut tester = Tester::new(server, false).await;
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    // Samplers for the built-in node managers should be running on the sampler runtime.
+    assert!(sampler_rt.metrics().num_alive_tasks() > 0);
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    // The service level is sampled, so changes must go through the sampler.
+    tester.handle.set_service_level(100);
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: VariableId::Server_ServiceLevel.into(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+
+    let (_, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(v.value, Some(Variant::Byte(100)));
+
+    tester.handle.set_service_level(50);
+    let (_, v) = timeout(Duration::from_millis(1000), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(v.value, Some(Variant::Byte(50)));
+
+    session.disconnect().await.unwrap();
+    drop(tester);
+    sampler_rt.shutdown_background();
+}
+
+#[tokio::test]
+async fn sampling_interval_zero_reports_every_change() {
+    let mut server = test_server();
+    server
+        .limits_mut()
+        .subscriptions
+        .max_monitored_item_queue_size = 1000;
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<TestNodeManager>()
+        .unwrap();
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(-1)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 0, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: id.clone(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 1000,
+                    discard_oldest: false,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+    assert_eq!(res[0].result.revised_sampling_interval, 0.0);
+
+    // Initial value
+    let (_, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(v.value, Some(Variant::Int32(-1)));
+
+    // Write values much faster than the minimum sampling interval.
+    for i in 0..1000 {
+        nm.set_value(
+            tester.handle.subscriptions(),
+            &id,
+            None,
+            DataValue::new_now(i),
+        )
+        .unwrap();
+    }
+
+    let values = timeout(Duration::from_millis(1000), recv_n(&mut data, 1000))
+        .await
+        .unwrap();
+    for (i, (_, v)) in values.into_iter().enumerate() {
+        assert_eq!(v.value, Some(Variant::Int32(i as i32)));
+    }
+}
+
+#[tokio::test]
+async fn shutdown_graceful_publishes_state() {
+    let (tester, _nm, session) = setup().await;
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: VariableId::Server_ServerStatus_State.into(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+
+    let (_, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(v.value, Some(Variant::Int32(ServerState::Running as i32)));
+
+    let start = Instant::now();
+    tester
+        .handle
+        .shutdown_graceful(Duration::from_secs(5))
+        .await;
+    // The notification was flushed, so we should not wait for the full timeout.
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert!(tester.handle.token().is_cancelled());
+
+    let (_, v) = timeout(Duration::from_millis(500), data.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(v.value, Some(Variant::Int32(ServerState::Shutdown as i32)));
+}
+
+#[tokio::test]
+async fn shutdown_countdown() {
+    let (tester, _nm, session) = setup().await;
+
+    let (notifs, mut data, _) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            [
+                VariableId::Server_ServerStatus_SecondsTillShutdown,
+                VariableId::Server_ServerStatus_ShutdownReason,
+                VariableId::Server_ServerStatus_State,
+            ]
+            .into_iter()
+            .enumerate()
+            .map(|(i, id)| MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: id.into(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    client_handle: i as u32,
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    ..Default::default()
+                },
+            })
+            .collect(),
+        )
+        .await
+        .unwrap();
+    for r in res {
+        assert_eq!(r.result.status_code, StatusCode::Good);
+    }
+
+    let status = tester.handle.status();
+    status.begin_shutdown("Upgrade".into(), 60);
+    loop {
+        let (r, v) = timeout(Duration::from_millis(3000), data.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        if r.node_id == VariableId::Server_ServerStatus_ShutdownReason
+            && v.value == Some(Variant::LocalizedText(Box::new("Upgrade".into())))
+        {
+            break;
+        }
+    }
+    // Beginning a new shutdown replaces the planned one.
+    status.begin_shutdown("Maintenance".into(), 2);
+
+    let mut seconds = Vec::new();
+    let mut reason = None;
+    let mut shut_down = false;
+    // The final countdown value may be published together with the state change,
+    // so wait for both.
+    while !shut_down || seconds.last() != Some(&0) {
+        let (r, v) = timeout(Duration::from_millis(3000), data.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        if r.node_id == VariableId::Server_ServerStatus_SecondsTillShutdown {
+            match v.value {
+                // Values of the replaced shutdown.
+                Some(Variant::UInt32(s)) if s > 2 && seconds.is_empty() => (),
+                Some(Variant::UInt32(s)) => seconds.push(s),
+                // The initial value is sent before the countdown started.
+                Some(Variant::Empty) if seconds.is_empty() => (),
+                r => panic!("Expected seconds till shutdown, got {r:?}"),
+            }
+        } else if r.node_id == VariableId::Server_ServerStatus_ShutdownReason {
+            reason = v.value;
+        } else if r.node_id == VariableId::Server_ServerStatus_State {
+            let Some(Variant::Int32(state)) = v.value else {
+                panic!("Expected server state, got {:?}", v.value);
+            };
+            // The initial value is sent before the countdown started.
+            if state == ServerState::Shutdown as i32 {
+                shut_down = true;
+            } else {
+                assert_eq!(state, ServerState::Running as i32);
+            }
+        }
+    }
+    assert_eq!(seconds, vec![2, 1, 0]);
+    assert_eq!(
+        reason,
+        Some(Variant::LocalizedText(Box::new("Maintenance".into())))
+    );
+}
+
+#[tokio::test]
+async fn condition_acknowledge() {
+    let (tester, nm, session) = setup().await;
+
+    let source_id = nm.inner().next_node_id();
+    let condition_id = nm.inner().next_node_id();
+    let condition = Condition::new(condition_id.clone(), source_id.clone(), "Source", "Alarm");
+    {
+        let mut sp = nm.address_space().write();
+        ObjectBuilder::new(&source_id, "Source", "Source")
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+        condition.insert(&mut *sp);
+    }
+    nm.enable_events(tester.handle.subscriptions(), &source_id)
+        .unwrap();
+
+    let ack_id = condition.acknowledge_method_id();
+    let type_ack_id: NodeId = MethodId::AcknowledgeableConditionType_Acknowledge.into();
+    let condition = Arc::new(Mutex::new(condition));
+    // The method can be called either on the condition node, or on the condition type.
+    for method_id in [ack_id.clone(), type_ack_id.clone()] {
+        let condition = condition.clone();
+        let subscriptions = tester.handle.subscriptions().clone();
+        let nm_ref = nm.clone();
+        let condition_id = condition_id.clone();
+        nm.inner().add_method_cb(method_id.clone(), move |args| {
+            let event = condition.lock().call(&condition_id, &method_id, args)?;
+            nm_ref.emit_condition_event(&subscriptions, event)?;
+            Ok(vec![])
+        });
+    }
+
+    let (notifs, _, mut events) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: source_id.clone(),
+                    attribute_id: AttributeId::EventNotifier as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    client_handle: 1,
+                    queue_size: 10,
+                    filter: ExtensionObject::from_message(EventFilter {
+                        select_clauses: Some(
+                            [
+                                "EventId",
+                                "ActiveState/Id",
+                                "AckedState/Id",
+                                "Retain",
+                                "Message",
+                                "Comment",
+                                "Comment/SourceTimestamp",
+                            ]
+                            .into_iter()
+                            .map(|p| {
+                                SimpleAttributeOperand::new(
+                                    ObjectTypeId::BaseEventType,
+                                    p,
+                                    AttributeId::Value,
+                                    NumericRange::None,
+                                )
+                            })
+                            .collect(),
+                        ),
+                        where_clause: ContentFilter::default(),
+                    }),
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+
+    let event = condition
+        .lock()
+        .set_active(true, 500, "Alarm active")
+        .unwrap();
+    nm.emit_event(tester.handle.subscriptions(), &source_id, &event)
+        .unwrap();
+
+    let (_, fields) = timeout(Duration::from_millis(500), events.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    let fields = fields.unwrap();
+    let Variant::ByteString(event_id) = &fields[0] else {
+        panic!("Expected event ID, got {:?}", fields[0]);
+    };
+    assert_eq!(event_id, event.event_id());
+    assert_eq!(fields[1], Variant::from(true));
+    assert_eq!(fields[2], Variant::from(false));
+    // Retained until the alarm is acknowledged and confirmed.
+    assert_eq!(fields[3], Variant::from(true));
+
+    let call = |method_id: &NodeId, event_id: ByteString| CallMethodRequest {
+        object_id: condition_id.clone(),
+        method_id: method_id.clone(),
+        input_arguments: Some(vec![
+            Variant::from(event_id),
+            Variant::from(LocalizedText::from("Handled")),
+        ]),
+    };
+
+    // Unknown event ID.
+    let r = session
+        .call_one(call(&ack_id, ByteString::from(vec![1u8, 2, 3])))
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadEventIdUnknown);
+
+    let r = session
+        .call_one(call(&type_ack_id, event_id.clone()))
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::Good);
+    assert!(condition.lock().is_acked());
+
+    let (_, fields) = timeout(Duration::from_millis(500), events.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    let fields = fields.unwrap();
+    assert_eq!(fields[1], Variant::from(true));
+    assert_eq!(fields[2], Variant::from(true));
+    // The comment is reported separately, the message is still the alarm text.
+    assert_eq!(
+        fields[4],
+        Variant::from(LocalizedText::from("Alarm active"))
+    );
+    assert_eq!(fields[5], Variant::from(LocalizedText::from("Handled")));
+    let Variant::DateTime(comment_time) = &fields[6] else {
+        panic!("Expected comment source timestamp, got {:?}", fields[6]);
+    };
+    assert!(!comment_time.is_null());
+    assert_eq!(condition.lock().comment(), &LocalizedText::from("Handled"));
+    let Variant::ByteString(event_id) = &fields[0] else {
+        panic!("Expected event ID, got {:?}", fields[0]);
+    };
+
+    // Already acknowledged.
+    let r = session
+        .call_one(call(&ack_id, event_id.clone()))
+        .await
+        .unwrap();
+    assert_eq!(r.status_code, StatusCode::BadConditionBranchAlreadyAcked);
+}
+
+#[tokio::test]
+async fn condition_refresh() {
+    let (tester, nm, session) = setup().await;
+
+    let source_id = nm.inner().next_node_id();
+    let condition_id = nm.inner().next_node_id();
+    let mut condition = Condition::new(condition_id.clone(), source_id.clone(), "Source", "Alarm");
+    {
+        let mut sp = nm.address_space().write();
+        ObjectBuilder::new(&source_id, "Source", "Source")
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut *sp);
+        condition.insert(&mut *sp);
+    }
+    nm.enable_events(tester.handle.subscriptions(), &source_id)
+        .unwrap();
+
+    // Emitted before any client subscribes, so it is only reported on refresh.
+    let event = condition.set_active(true, 500, "Alarm active").unwrap();
+    nm.emit_condition_event(tester.handle.subscriptions(), event)
+        .unwrap();
+
+    let (notifs, _, mut events) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: source_id.clone(),
+                    attribute_id: AttributeId::EventNotifier as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    client_handle: 1,
+                    queue_size: 10,
+                    filter: ExtensionObject::from_message(EventFilter {
+                        select_clauses: Some(
+                            ["EventType", "ActiveState/Id"]
+                                .into_iter()
+                                .map(|p| {
+                                    SimpleAttributeOperand::new(
+                                        ObjectTypeId::BaseEventType,
+                                        p,
+                                        AttributeId::Value,
+                                        NumericRange::None,
+                                    )
+                                })
+                                .collect(),
+                        ),
+                        where_clause: ContentFilter::default(),
+                    }),
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+
+    let refresh = |id: u32| CallMethodRequest {
+        object_id: ObjectTypeId::ConditionType.into(),
+        method_id: MethodId::ConditionType_ConditionRefresh.into(),
+        input_arguments: Some(vec![Variant::from(id)]),
+    };
+    let r = session.call_one(refresh(sub_id + 100)).await.unwrap();
+    assert_eq!(r.status_code, StatusCode::BadSubscriptionIdInvalid);
+
+    let r = session.call_one(refresh(sub_id)).await.unwrap();
+    assert_eq!(r.status_code, StatusCode::Good);
+
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        let (_, fields) = timeout(Duration::from_millis(500), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        received.push(fields.unwrap());
+    }
+    assert_eq!(
+        received[0][0],
+        Variant::from(NodeId::from(ObjectTypeId::RefreshStartEventType))
+    );
+    assert_eq!(
+        received[1][0],
+        Variant::from(NodeId::from(ObjectTypeId::AlarmConditionType))
+    );
+    assert_eq!(received[1][1], Variant::from(true));
+    assert_eq!(
+        received[2][0],
+        Variant::from(NodeId::from(ObjectTypeId::RefreshEndEventType))
+    );
+}
+
+#[tokio::test]
+async fn dropped_notification_diagnostics() {
+    let server = test_server().diagnostics_enabled(true);
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<TestNodeManager>()
+        .unwrap();
+    // Reading diagnostics requires an authenticated user.
+    let (session, lp) = tester
+        .connect(
+            SecurityPolicy::Aes128Sha256RsaOaep,
+            MessageSecurityMode::SignAndEncrypt,
+            client_user_token(),
+        )
+        .await
+        .unwrap();
+    lp.spawn();
+    timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(-1)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    // Use a long publishing interval, so that values pile up in the monitored item queue.
+    let res = CreateSubscription::new(&session)
+        .publishing_interval(Duration::from_secs(60))
+        .publishing_enabled(true)
+        .send(session.channel())
+        .await
+        .unwrap();
+    let sub_id = res.subscription_id;
+
+    let res = CreateMonitoredItems::new(sub_id, &session)
+        .item(MonitoredItemCreateRequest {
+            item_to_monitor: ReadValueId {
+                node_id: id.clone(),
+                attribute_id: AttributeId::Value as u32,
+                ..Default::default()
+            },
+            monitoring_mode: MonitoringMode::Reporting,
+            requested_parameters: MonitoringParameters {
+                sampling_interval: 0.0,
+                queue_size: 1,
+                discard_oldest: true,
+                ..Default::default()
+            },
+        })
+        .send(session.channel())
+        .await
+        .unwrap();
+    assert_eq!(res.results[0].result.status_code, StatusCode::Good);
+
+    // The queue already holds the initial value, so each of these overflows it.
+    for i in 0..5 {
+        nm.set_value(
+            tester.handle.subscriptions(),
+            &id,
+            None,
+            DataValue::new_now(i),
+        )
+        .unwrap();
+    }
+
+    let res = session
+        .read(
+            &[ReadValueId::new_value(
+                VariableId::Server_ServerDiagnostics_SubscriptionDiagnosticsArray.into(),
+            )],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+    let Some(Variant::Array(arr)) = &res[0].value else {
+        panic!("Expected array, got {:?}", res[0]);
+    };
+    assert_eq!(arr.values.len(), 1);
+    let Variant::ExtensionObject(obj) = &arr.values[0] else {
+        panic!("Expected extension object");
+    };
+    let diagnostics = obj.inner_as::<SubscriptionDiagnosticsDataType>().unwrap();
+    assert_eq!(diagnostics.subscription_id, sub_id);
+    assert_eq!(diagnostics.session_id, session.server_session_id());
+    assert_eq!(diagnostics.monitored_item_count, 1);
+    assert_eq!(diagnostics.monitoring_queue_overflow_count, 5);
+    assert_eq!(diagnostics.discarded_message_count, 0);
+
+    // The server-wide total is updated on the next subscription tick.
+    let diagnostics = &tester.handle.info().diagnostics;
+    let start = Instant::now();
+    while diagnostics.dropped_notification_count() < 5 {
+        assert!(start.elapsed() < Duration::from_secs(2));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(diagnostics.dropped_notification_count(), 5);
+}
+
+#[tokio::test]
+async fn subscription_streams() {
+    let (tester, nm, session) = setup().await;
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(-1)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let (callback, streams) = StreamCallback::new(2);
+    let mut data = streams.data_changes();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, callback)
+        .await
+        .unwrap();
+
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: id.clone(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 100,
+                    discard_oldest: true,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    let item_id = res[0].result.monitored_item_id;
+
+    let (r, v) = timeout(Duration::from_millis(500), data.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(r, item_id);
+    assert_eq!(v.value, Some(Variant::Int32(-1)));
+
+    // Produce values over several publishing intervals without consuming them.
+    // The buffer is much smaller than this, but nothing is dropped.
+    for i in 0..10 {
+        nm.set_value(
+            tester.handle.subscriptions(),
+            &id,
+            None,
+            DataValue::new_now(i),
+        )
+        .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    for i in 0..10 {
+        let (r, v) = timeout(Duration::from_millis(1000), data.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(r, item_id);
+        assert_eq!(v.value, Some(Variant::Int32(i)));
+    }
+
+    // The stream ends once the subscription is deleted.
+    session.delete_subscription(sub_id).await.unwrap();
+    assert!(timeout(Duration::from_millis(500), data.next())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+struct RecoveryNotifications {
+    values: tokio::sync::mpsc::UnboundedSender<i32>,
+    lost: tokio::sync::mpsc::UnboundedSender<u32>,
+}
+
+impl OnSubscriptionNotification for RecoveryNotifications {
+    fn on_data_value(&mut self, notification: DataValue, _item: &opcua::client::MonitoredItem) {
+        if let Some(Variant::Int32(v)) = notification.value {
+            let _ = self.values.send(v);
+        }
+    }
+
+    fn on_message_lost(&mut self, sequence_number: u32) {
+        let _ = self.lost.send(sequence_number);
+    }
+}
+
+async fn next_value(values: &mut UnboundedReceiver<i32>) -> i32 {
+    timeout(Duration::from_millis(1000), values.recv())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn republish_on_sequence_gap() {
+    let server = test_server();
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<TestNodeManager>()
+        .unwrap();
+    // Transfer requires an encrypted connection.
+    let mut sessions = Vec::new();
+    for _ in 0..2 {
+        let (session, lp) = tester
+            .connect(
+                SecurityPolicy::Aes256Sha256RsaPss,
+                MessageSecurityMode::SignAndEncrypt,
+                IdentityToken::Anonymous,
+            )
+            .await
+            .unwrap();
+        lp.spawn();
+        timeout(Duration::from_secs(2), session.wait_for_connection())
+            .await
+            .unwrap();
+        sessions.push(session);
+    }
+    // The first session is used normally, the second session is used to take
+    // notification messages away from the first.
+    let (session, other) = (&sessions[0], &sessions[1]);
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .value(-1)
+            .data_type(DataTypeId::Int32)
+            .access_level(AccessLevel::CURRENT_READ)
+            .user_access_level(AccessLevel::CURRENT_READ)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    let (values_tx, mut values) = tokio::sync::mpsc::unbounded_channel();
+    let (lost_tx, mut lost) = tokio::sync::mpsc::unbounded_channel();
+    let sub_id = session
+        .create_subscription(
+            Duration::from_millis(100),
+            100,
+            20,
+            1000,
+            0,
+            true,
+            RecoveryNotifications {
+                values: values_tx,
+                lost: lost_tx,
+            },
+        )
+        .await
+        .unwrap();
+    session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: id.clone(),
+                    attribute_id: AttributeId::Value as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    sampling_interval: 0.0,
+                    queue_size: 10,
+                    discard_oldest: true,
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(next_value(&mut values).await, -1);
+
+    let set_value = |v: i32| {
+        nm.set_value(
+            tester.handle.subscriptions(),
+            &id,
+            None,
+            DataValue::new_now(v),
+        )
+        .unwrap();
+    };
+    let transfer = |to: &Arc<opcua::client::Session>| {
+        let to = to.clone();
+        async move {
+            let r = TransferSubscriptions::new(&to)
+                .subscription(sub_id)
+                .send_initial_values(false)
+                .send(to.channel())
+                .await
+                .unwrap();
+            assert_eq!(r.results.unwrap()[0].status_code, StatusCode::Good);
+        }
+    };
+
+    // Move the subscription away and receive a message on the other session.
+    transfer(other).await;
+    set_value(1);
+    let r = Publish::new(other).send(other.channel()).await.unwrap();
+    assert_eq!(r.notification_message.sequence_number, 2);
+    transfer(session).await;
+    session.trigger_publish_now();
+
+    // The next message reveals the gap, and is held until the skipped message
+    // has been republished, so the values are delivered in order.
+    set_value(2);
+    assert_eq!(next_value(&mut values).await, 1);
+    assert_eq!(next_value(&mut values).await, 2);
+
+    // This time, the other session acknowledges the message, so it cannot be recovered.
+    transfer(other).await;
+    set_value(3);
+    let r = Publish::new(other).send(other.channel()).await.unwrap();
+    assert_eq!(r.notification_message.sequence_number, 4);
+    set_value(4);
+    let r = Publish::new(other)
+        .ack(sub_id, 4)
+        .send(other.channel())
+        .await
+        .unwrap();
+    assert_eq!(r.notification_message.sequence_number, 5);
+    transfer(session).await;
+    session.trigger_publish_now();
+
+    set_value(5);
+    assert_eq!(next_value(&mut values).await, 4);
+    assert_eq!(next_value(&mut values).await, 5);
+    let seq = timeout(Duration::from_millis(1000), lost.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(seq, 4);
+    assert!(values.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn replace_address_space() {
+    let (tester, nm, session) = setup().await;
+
+    let ns = nm.inner().next_node_id().namespace;
+    let folder_id = NodeId::new(ns, "Folder");
+    let keep_id = NodeId::new(ns, "Keep");
+    let gone_id = NodeId::new(ns, "Gone");
+    let build = |values: &[(&NodeId, i32)]| {
+        let mut address_space = AddressSpace::new();
+        address_space.add_namespace("urn:rustopcuatestserver", ns);
+        ObjectBuilder::new(&folder_id, "Folder", "Folder")
+            .organized_by(ObjectId::ObjectsFolder)
+            .insert(&mut address_space);
+        for (id, value) in values {
+            VariableBuilder::new(id, "Var", "Var")
+                .value(*value)
+                .data_type(DataTypeId::Int32)
+                .component_of(folder_id.clone())
+                .insert(&mut address_space);
+        }
+        address_space
+    };
+    nm.replace_address_space(
+        tester.handle.subscriptions(),
+        tester.handle.type_tree(),
+        build(&[(&keep_id, 1), (&gone_id, 2)]),
+    )
+    .unwrap();
+
+    // The namespaces of the new address space must match.
+    assert_eq!(
+        nm.replace_address_space(
+            tester.handle.subscriptions(),
+            tester.handle.type_tree(),
+            AddressSpace::new(),
+        ),
+        Err(StatusCode::BadInvalidArgument)
+    );
+
+    let (notifs, mut data, mut events) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![
+                keep_id.clone().into(),
+                gone_id.clone().into(),
+                MonitoredItemCreateRequest {
+                    item_to_monitor: ReadValueId {
+                        node_id: ObjectId::Server.into(),
+                        attribute_id: AttributeId::EventNotifier as u32,
+                        ..Default::default()
+                    },
+                    monitoring_mode: MonitoringMode::Reporting,
+                    requested_parameters: MonitoringParameters {
+                        queue_size: 10,
+                        filter: ExtensionObject::from_message(EventFilter {
+                            select_clauses: Some(vec![SimpleAttributeOperand::new(
+                                ObjectTypeId::BaseEventType,
+                                "EventType",
+                                AttributeId::Value,
+                                NumericRange::None,
+                            )]),
+                            where_clause: ContentFilter::default(),
+                        }),
+                        ..Default::default()
+                    },
+                },
+            ],
+        )
+        .await
+        .unwrap();
+    assert!(res.iter().all(|r| r.result.status_code == StatusCode::Good));
+    let mut initial = HashSet::new();
+    while !initial.contains(&keep_id) || !initial.contains(&gone_id) {
+        let (r, _) = timeout(Duration::from_millis(500), data.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        initial.insert(r.node_id);
+    }
+
+    // Get a continuation point into the current address space.
+    let r = session
+        .browse(
+            &[BrowseDescription {
+                node_id: folder_id.clone(),
+                browse_direction: BrowseDirection::Forward,
+                reference_type_id: ReferenceTypeId::HasComponent.into(),
+                include_subtypes: true,
+                node_class_mask: 0,
+                result_mask: BrowseResultMask::All as u32,
+            }],
+            1,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].references.as_ref().unwrap().len(), 1);
+    let cp = r[0].continuation_point.clone();
+    assert!(!cp.is_null());
+
+    nm.replace_address_space(
+        tester.handle.subscriptions(),
+        tester.handle.type_tree(),
+        build(&[(&keep_id, 10)]),
+    )
+    .unwrap();
+
+    // Monitored items report the new value, or that the node is gone.
+    let mut values = HashMap::new();
+    while !values.contains_key(&keep_id) || !values.contains_key(&gone_id) {
+        let (r, v) = timeout(Duration::from_millis(500), data.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        values.insert(r.node_id, v);
+    }
+    assert_eq!(values[&keep_id].value, Some(Variant::Int32(10)));
+    assert_eq!(values[&gone_id].status, Some(StatusCode::BadNodeIdUnknown));
+
+    let (_, fields) = timeout(Duration::from_millis(500), events.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        fields,
+        Some(vec![Variant::from(NodeId::from(
+            ObjectTypeId::BaseModelChangeEventType
+        ))])
+    );
+
+    let r = session
+        .read(
+            &[
+                ReadValueId::new_value(keep_id.clone()),
+                ReadValueId::new_value(gone_id.clone()),
+            ],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].value, Some(Variant::Int32(10)));
+    assert_eq!(r[1].status, Some(StatusCode::BadNodeIdUnknown));
+
+    // The continuation point was invalidated.
+    let r = session.browse_next(false, &[cp]).await.unwrap();
+    assert_eq!(r[0].status_code, StatusCode::BadContinuationPointInvalid);
+}
+
+#[tokio::test]
+async fn certificate_expiry_events() {
+    // The test certificate is valid for at most a year, so it is always within the window.
+    let server = test_server().certificate_expiry(CertificateExpiryConfig {
+        warning_days: 400,
+        check_interval_secs: 1,
+        emit_events: true,
+    });
+    let mut tester = Tester::new(server, false).await;
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let days = tester
+        .handle
+        .info()
+        .diagnostics
+        .certificate_days_until_expiry()
+        .unwrap();
+    assert!((0..=365).contains(&days));
+
+    let (notifs, _, mut events) = ChannelNotifications::new();
+    let sub_id = session
+        .create_subscription(Duration::from_millis(100), 100, 20, 1000, 0, true, notifs)
+        .await
+        .unwrap();
+    let res = session
+        .create_monitored_items(
+            sub_id,
+            TimestampsToReturn::Both,
+            vec![MonitoredItemCreateRequest {
+                item_to_monitor: ReadValueId {
+                    node_id: ObjectId::Server.into(),
+                    attribute_id: AttributeId::EventNotifier as u32,
+                    ..Default::default()
+                },
+                monitoring_mode: MonitoringMode::Reporting,
+                requested_parameters: MonitoringParameters {
+                    queue_size: 10,
+                    filter: ExtensionObject::from_message(EventFilter {
+                        select_clauses: Some(vec![
+                            SimpleAttributeOperand::new(
+                                ObjectTypeId::BaseEventType,
+                                "EventType",
+                                AttributeId::Value,
+                                NumericRange::None,
+                            ),
+                            SimpleAttributeOperand::new(
+                                ObjectTypeId::AuditSecurityEventType,
+                                "StatusCodeId",
+                                AttributeId::Value,
+                                NumericRange::None,
+                            ),
+                            SimpleAttributeOperand::new(
+                                ObjectTypeId::AuditCertificateEventType,
+                                "Certificate",
+                                AttributeId::Value,
+                                NumericRange::None,
+                            ),
+                        ]),
+                        where_clause: ContentFilter::default(),
+                    }),
+                    ..Default::default()
+                },
+            }],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res[0].result.status_code, StatusCode::Good);
+
+    // The certificate is checked again after one second, emitting an event.
+    let fields = loop {
+        let (_, fields) = timeout(Duration::from_secs(3), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let fields = fields.unwrap();
+        if fields[0] == Variant::NodeId(Box::new(ObjectTypeId::AuditCertificateEventType.into())) {
+            break fields;
+        }
+    };
+    assert_eq!(fields[1], Variant::StatusCode(StatusCode::Good));
+    let Variant::ByteString(cert) = &fields[2] else {
+        panic!("Expected the certificate");
+    };
+    assert_eq!(
+        Some(cert),
+        tester
+            .handle
+            .info()
+            .server_certificate
+            .as_ref()
+            .map(|c| c.as_byte_string())
+            .as_ref()
+    );
+}
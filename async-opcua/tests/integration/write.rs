@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use chrono::TimeDelta;
 use opcua::{
     client::{HistoryReadAction, HistoryUpdateAction, Session},
@@ -15,7 +17,7 @@ use opcua::{
 use opcua_types::NumericRange;
 // Write is not implemented in the core library itself, only in the test node manager,
 // we still test here to test write functionality in the address space.
-use super::utils::{array_value, read_value_id, setup};
+use super::utils::{array_value, read_value_id, setup, test_server, TestNodeManager, Tester};
 
 fn write_value(
     attribute_id: AttributeId,
@@ -537,6 +539,87 @@ async fn write_invalid() {
     assert_eq!(r[3], StatusCode::BadUserAccessDenied);
 }
 
+#[tokio::test]
+async fn effective_access_level() {
+    let (tester, nm, session) = setup().await;
+
+    let read = AccessLevel::CURRENT_READ;
+    let read_write = AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE;
+    let levels = [
+        (read, read_write),
+        (read_write, read),
+        (read_write, read_write),
+        (AccessLevel::CURRENT_WRITE, read_write),
+    ];
+    let mut ids = Vec::new();
+    for (idx, (access_level, user_access_level)) in levels.into_iter().enumerate() {
+        let id = nm.inner().next_node_id();
+        let name = format!("TestVar{idx}");
+        nm.inner().add_node(
+            nm.address_space(),
+            tester.handle.type_tree(),
+            VariableBuilder::new(&id, &name, &name)
+                .data_type(DataTypeId::String)
+                .value("value")
+                .access_level(access_level)
+                .user_access_level(user_access_level)
+                .build()
+                .into(),
+            &ObjectId::ObjectsFolder.into(),
+            &ReferenceTypeId::Organizes.into(),
+            Some(&VariableTypeId::BaseDataVariableType.into()),
+            Vec::new(),
+        );
+        ids.push(id);
+    }
+
+    // The user access level is limited by the access level of the node.
+    let r = session
+        .read(
+            &ids[..3]
+                .iter()
+                .map(|id| read_value_id(AttributeId::UserAccessLevel, id))
+                .collect::<Vec<_>>(),
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r.len(), 3);
+    for (r, level) in r.into_iter().zip([read, read, read_write]) {
+        assert_eq!(r.value, Some(Variant::Byte(level.bits())));
+    }
+
+    let r = session
+        .write(
+            &ids.iter()
+                .map(|id| write_value(AttributeId::Value, "foo", id))
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        r,
+        vec![
+            StatusCode::BadUserAccessDenied,
+            StatusCode::BadUserAccessDenied,
+            StatusCode::Good,
+            StatusCode::Good,
+        ]
+    );
+
+    // Without read access, the value cannot be read.
+    let r = session
+        .read(
+            &[read_value_id(AttributeId::Value, &ids[3])],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].status, Some(StatusCode::BadUserAccessDenied));
+}
+
 #[tokio::test]
 async fn write_limits() {
     let (tester, _nm, session) = setup().await;
@@ -570,6 +653,75 @@ async fn write_limits() {
     session.write(&ops).await.unwrap();
 }
 
+#[tokio::test]
+async fn write_chunked() {
+    let mut server = test_server();
+    server.limits_mut().operational.max_nodes_per_write = 7;
+    let mut tester = Tester::new(server, false).await;
+    let nm = tester
+        .handle
+        .node_managers()
+        .get_of_type::<TestNodeManager>()
+        .unwrap();
+    let (session, lp) = tester.connect_default().await.unwrap();
+    lp.spawn();
+    tokio::time::timeout(Duration::from_secs(2), session.wait_for_connection())
+        .await
+        .unwrap();
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        VariableBuilder::new(&id, "TestVar1", "TestVar1")
+            .data_type(DataTypeId::Int32)
+            .value(0)
+            .access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+            .user_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&VariableTypeId::BaseDataVariableType.into()),
+        Vec::new(),
+    );
+
+    // Alternate between a node that exists and one that does not, so that we can check the order.
+    let ops: Vec<_> = (0..50)
+        .map(|i| {
+            if i % 2 == 0 {
+                write_value(AttributeId::Value, i, &id)
+            } else {
+                write_value(AttributeId::Value, i, NodeId::new(2, "missing"))
+            }
+        })
+        .collect();
+
+    let r = session.write(&ops).await.unwrap_err();
+    assert_eq!(r, StatusCode::BadTooManyOperations);
+
+    let r = session.write_chunked(&ops).await.unwrap();
+    assert_eq!(r.len(), 50);
+    for (i, s) in r.iter().enumerate() {
+        if i % 2 == 0 {
+            assert_eq!(*s, StatusCode::Good);
+        } else {
+            assert_eq!(*s, StatusCode::BadNodeIdUnknown);
+        }
+    }
+
+    // The last write wins.
+    let r = session
+        .read(
+            &[read_value_id(AttributeId::Value, &id)],
+            TimestampsToReturn::Neither,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(r[0].value, Some(Variant::Int32(48)));
+}
+
 #[tokio::test]
 async fn write_bytestring_to_byte_array() {
     let (tester, nm, session) = setup().await;
@@ -877,3 +1029,62 @@ async fn history_update_fail() {
 
     assert_eq!(r[0].status_code, StatusCode::BadNodeIdUnknown);
 }
+
+#[tokio::test]
+async fn write_transaction() {
+    let (tester, nm, session) = setup().await;
+    nm.inner().set_write_transactions(true);
+
+    let id = nm.inner().next_node_id();
+    nm.inner().add_node(
+        nm.address_space(),
+        tester.handle.type_tree(),
+        ObjectBuilder::new(&id, "TestObj1", "TestObj1")
+            .description("Description")
+            .write_mask(WriteMask::DISPLAY_NAME | WriteMask::DESCRIPTION)
+            .build()
+            .into(),
+        &ObjectId::ObjectsFolder.into(),
+        &ReferenceTypeId::Organizes.into(),
+        Some(&ObjectTypeId::FolderType.into()),
+        Vec::new(),
+    );
+
+    write_then_read(
+        &session,
+        &[
+            write_value(AttributeId::DisplayName, LocalizedText::from("NewObj"), &id),
+            write_value(
+                AttributeId::Description,
+                LocalizedText::from("NewDescription"),
+                &id,
+            ),
+        ],
+    )
+    .await;
+
+    // The second write is invalid, so the entire transaction should fail.
+    let r = session
+        .write(&[
+            write_value(AttributeId::DisplayName, LocalizedText::from("Other"), &id),
+            write_value(AttributeId::Description, 15i32, &id),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(r.len(), 2);
+    assert!(r[0].is_bad());
+    assert_eq!(r[0], r[1]);
+
+    let r = session
+        .read(
+            &[read_value_id(AttributeId::DisplayName, &id)],
+            TimestampsToReturn::Both,
+            0.0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        r[0].value,
+        Some(Variant::from(LocalizedText::from("NewObj")))
+    );
+}
@@ -1,21 +1,22 @@
 use std::{
     collections::HashMap,
-    sync::atomic::{AtomicU32, Ordering},
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
 };
 
 use async_trait::async_trait;
 use opcua::{
     server::{
         address_space::{
-            new_node_from_attributes, AddressSpace, HasNodeId, NodeType, ReferenceDirection,
+            eu_range, new_node_from_attributes, semantics_changed_variable, AddressSpace,
+            HasNodeId, NodeType, ReferenceDirection,
         },
         node_manager::{
             get_node_metadata,
             memory::{InMemoryNodeManager, InMemoryNodeManagerBuilder, InMemoryNodeManagerImpl},
             AddNodeItem, AddReferenceItem, DeleteNodeItem, DeleteReferenceItem, HistoryNode,
             HistoryUpdateNode, MethodCall, MonitoredItemRef, MonitoredItemUpdateRef,
-            NodeManagerBuilder, NodeManagersRef, ParsedReadValueId, RequestContext, ServerContext,
-            WriteNode,
+            NodeManagerBuilder, NodeManagersRef, ParsedReadValueId, ParsedWriteValue,
+            RequestContext, ServerContext, WriteNode,
         },
         ContinuationPoint, CreateMonitoredItem,
     },
@@ -56,6 +57,9 @@ pub struct TestNodeManagerImpl {
     namespace_index: u16,
     node_managers: NodeManagersRef,
     issues: IssueEmulation,
+    write_transactions: AtomicBool,
+    context: ServerContext,
+    late_namespaces: RwLock<Vec<String>>,
 }
 
 #[derive(Default)]
@@ -68,6 +72,7 @@ pub struct IssueEmulation {
 pub struct CallInfo {
     pub value_monitored_items: Vec<NodeId>,
     pub read_values: Vec<NodeId>,
+    pub read_values_batches: usize,
     pub register_nodes: Vec<NodeId>,
     pub set_monitoring_mode: Vec<NodeId>,
     pub modify_monitored_items: Vec<NodeId>,
@@ -93,7 +98,7 @@ fn make_test_node_manager_impl(
     address_space: &mut AddressSpace,
 ) -> TestNodeManagerImpl {
     let idx = add_namespaces(&context, address_space, &["urn:rustopcuatestserver"])[0];
-    TestNodeManagerImpl::new(idx, context.node_managers.clone())
+    TestNodeManagerImpl::new(idx, context)
 }
 
 #[async_trait]
@@ -101,12 +106,23 @@ impl InMemoryNodeManagerImpl for TestNodeManagerImpl {
     async fn init(&self, _address_space: &mut AddressSpace, _context: ServerContext) {}
 
     fn namespaces(&self) -> Vec<NamespaceMetadata> {
-        vec![NamespaceMetadata {
+        let mut namespaces = vec![NamespaceMetadata {
             is_namespace_subset: Some(false),
             namespace_uri: "urn:rustopcuatestserver".to_owned(),
+            namespace_version: Some("1.0.0".to_owned()),
+            namespace_publication_date: Some(DateTime::ymd(2024, 1, 1)),
             namespace_index: self.namespace_index,
             ..Default::default()
-        }]
+        }];
+        let type_tree = self.context.type_tree.read();
+        namespaces.extend(self.late_namespaces.read().iter().filter_map(|uri| {
+            Some(NamespaceMetadata {
+                namespace_uri: uri.clone(),
+                namespace_index: type_tree.namespaces().get_index(uri)?,
+                ..Default::default()
+            })
+        }));
+        namespaces
     }
 
     fn name(&self) -> &str {
@@ -161,6 +177,7 @@ impl InMemoryNodeManagerImpl for TestNodeManagerImpl {
         }
         {
             let mut call_info = self.call_info.lock();
+            call_info.read_values_batches += 1;
             for node in nodes.iter() {
                 call_info.read_values.push(node.node_id.clone());
             }
@@ -375,6 +392,76 @@ impl InMemoryNodeManagerImpl for TestNodeManagerImpl {
                 )]
                 .into_iter(),
             );
+            if let Some(variable) =
+                semantics_changed_variable(&address_space, &write.value().node_id, &*type_tree)
+            {
+                context.subscriptions.notify_semantics_changed(
+                    &variable,
+                    eu_range(&address_space, &variable, &*type_tree),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn supports_write_transactions(&self) -> bool {
+        self.write_transactions.load(Ordering::Relaxed)
+    }
+
+    async fn write_transaction(
+        &self,
+        context: &RequestContext,
+        address_space: &RwLock<AddressSpace>,
+        node_id: &NodeId,
+        writes: &[(AttributeId, DataValue)],
+    ) -> Result<(), StatusCode> {
+        {
+            let mut call_info = self.call_info.lock();
+            for (attribute_id, _) in writes {
+                call_info.write.push((node_id.clone(), *attribute_id));
+            }
+        }
+        let mut address_space = trace_write_lock!(address_space);
+        let type_tree = trace_read_lock!(context.type_tree);
+
+        // Validate every write before applying any of them, keeping the old
+        // values so that we can roll back if applying fails.
+        let mut previous = Vec::with_capacity(writes.len());
+        for (attribute_id, value) in writes {
+            let parsed = ParsedWriteValue {
+                node_id: node_id.clone(),
+                attribute_id: *attribute_id,
+                index_range: opcua::types::NumericRange::None,
+                value: value.clone(),
+            };
+            let node = address_space.validate_node_write(context, &parsed, &*type_tree)?;
+            let old = node
+                .as_node()
+                .get_attribute(
+                    TimestampsToReturn::Neither,
+                    *attribute_id,
+                    &opcua::types::NumericRange::None,
+                    &DataEncoding::Binary,
+                )
+                .and_then(|v| v.value)
+                .unwrap_or(Variant::Empty);
+            previous.push((*attribute_id, old));
+        }
+
+        let node = address_space
+            .find_mut(node_id)
+            .ok_or(StatusCode::BadNodeIdUnknown)?;
+        for (idx, (attribute_id, value)) in writes.iter().enumerate() {
+            if let Err(e) = node
+                .as_mut_node()
+                .set_attribute(*attribute_id, value.value.clone().unwrap_or(Variant::Empty))
+            {
+                for (attribute_id, old) in previous.into_iter().take(idx) {
+                    let _ = node.as_mut_node().set_attribute(attribute_id, old);
+                }
+                return Err(e);
+            }
         }
 
         Ok(())
@@ -710,18 +797,38 @@ struct RawValue {
 
 impl TestNodeManagerImpl {
     #[allow(unused)]
-    pub fn new(namespace_index: u16, node_managers: NodeManagersRef) -> Self {
+    pub fn new(namespace_index: u16, context: ServerContext) -> Self {
         Self {
             history_data: Default::default(),
             call_info: Default::default(),
             method_cbs: Default::default(),
             node_id_generator: AtomicU32::new(1),
             namespace_index,
-            node_managers,
+            node_managers: context.node_managers.clone(),
             issues: Default::default(),
+            write_transactions: AtomicBool::new(false),
+            context,
+            late_namespaces: Default::default(),
         }
     }
 
+    /// Add a namespace after the server has started, as if registered by a plugin.
+    #[allow(unused)]
+    pub fn add_late_namespace(&self, address_space: &RwLock<AddressSpace>, namespace_uri: &str) {
+        self.late_namespaces.write().push(namespace_uri.to_owned());
+        add_namespaces(&self.context, &mut address_space.write(), &[namespace_uri]);
+    }
+
+    #[allow(unused)]
+    pub fn set_write_transactions(&self, enabled: bool) {
+        self.write_transactions.store(enabled, Ordering::Relaxed);
+    }
+
+    #[allow(unused)]
+    pub fn read_values_batches(&self) -> usize {
+        self.call_info.lock().read_values_batches
+    }
+
     pub fn issues(&self) -> &IssueEmulation {
         &self.issues
     }
@@ -929,6 +1036,11 @@ impl TestNodeManagerImpl {
         Ok(())
     }
 
+    #[allow(unused)]
+    pub fn unregistered_nodes(&self) -> Vec<NodeId> {
+        self.call_info.lock().unregister_nodes.clone()
+    }
+
     pub fn next_node_id(&self) -> NodeId {
         let val = self
             .node_id_generator
@@ -95,7 +95,8 @@ pub fn default_server() -> ServerBuilder {
                 CLIENT_USERPASS_ID,
                 &format!("{CLIENT_USERPASS_ID}_password"),
             )
-            .read_diagnostics(true),
+            .read_diagnostics(true)
+            .admin(true),
         )
         .add_user_token(
             CLIENT_X509_ID,
@@ -213,7 +214,7 @@ pub fn default_server() -> ServerBuilder {
 pub fn default_client(test_id: u16, quick_timeout: bool) -> ClientBuilder {
     let client = ClientBuilder::new()
         .application_name("integration_client")
-        .application_uri("x")
+        .application_uri(CLIENT_APPLICATION_URI)
         .pki_dir(format!("./pki-client/{test_id}"))
         .create_sample_keypair(true)
         .trust_server_certs(true)
@@ -236,9 +237,19 @@ pub fn test_server() -> ServerBuilder {
 
 static SHARED_CERT_LOCK: Mutex<()> = Mutex::new(());
 
+pub const CLIENT_APPLICATION_URI: &str = "urn:integration_client";
+
 pub fn copy_shared_certs(test_id: u16, desc: &ApplicationDescription) {
     let _lck = SHARED_CERT_LOCK.lock();
-    if !Path::new("certs").exists() {
+    // The client certificate must contain the application URI of the client.
+    let client_cert_valid = CertificateStore::read_cert(Path::new("certs/client/cert.der"))
+        .is_ok_and(|c| c.is_application_uri_valid(CLIENT_APPLICATION_URI).is_ok());
+    if !client_cert_valid {
+        let client_desc = ApplicationDescription {
+            application_uri: CLIENT_APPLICATION_URI.into(),
+            application_name: "integration_client".into(),
+            ..desc.clone()
+        };
         std::fs::create_dir_all("certs/server").unwrap();
         std::fs::create_dir_all("certs/client").unwrap();
         CertificateStore::create_certificate_and_key(
@@ -249,7 +260,7 @@ pub fn copy_shared_certs(test_id: u16, desc: &ApplicationDescription) {
         )
         .unwrap();
         CertificateStore::create_certificate_and_key(
-            &desc.clone().into(),
+            &client_desc.into(),
             true,
             Path::new("certs/client/cert.der"),
             Path::new("certs/client/private.pem"),
@@ -71,6 +71,14 @@ static TYPES: std::sync::LazyLock<opcua::types::TypeLoaderInstance> =
     });
 #[derive(Debug, Clone, Copy)]
 pub struct GeneratedTypeLoader;
+impl GeneratedTypeLoader {
+    /// Get the registry of all types handled by this type loader.
+    /// This can be used to enumerate the known types, and to decode them by
+    /// data type or encoding ID.
+    pub fn type_registry() -> &'static opcua::types::TypeLoaderInstance {
+        &TYPES
+    }
+}
 impl opcua::types::TypeLoader for GeneratedTypeLoader {
     fn load_from_binary(
         &self,
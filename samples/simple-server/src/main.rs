@@ -87,6 +87,7 @@ fn add_example_variables(
     let v3_node = NodeId::new(ns, "v3");
     let v4_node = NodeId::new(ns, "v4");
     let v5_node = NodeId::new(ns, "v5");
+    let v6_node = NodeId::new(ns, "v6");
 
     let address_space = manager.address_space();
 
@@ -111,6 +112,7 @@ fn add_example_variables(
                 Variable::new(&v3_node, "v3", "v3", UAString::from("")),
                 Variable::new(&v4_node, "v4", "v4", 0f64),
                 Variable::new(&v5_node, "v5", "v5", "Static Value"),
+                Variable::new(&v6_node, "v6", "v6", 0u32),
             ],
             &sample_folder_id,
         );
@@ -139,6 +141,14 @@ fn add_example_variables(
                     (2.0 * std::f64::consts::PI * moment).sin(),
                 ))
             });
+
+        // Computed variables are produced from the context of each read, here the ID of the
+        // session reading the value.
+        manager
+            .inner()
+            .add_computed_variable(v6_node.clone(), |context| {
+                DataValue::new_now(context.session_id)
+            });
     }
 
     // Alternatively, you can set the value in the node manager on a timer.